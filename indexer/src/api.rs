@@ -2,18 +2,21 @@
 //!
 //! Provides REST endpoints for querying indexed vault data.
 //! Serves vault-by-owner lookups so the dApp dashboard can
-//! auto-discover factory-deployed vaults.
+//! auto-discover factory-deployed vaults, and ingests the RPC proxy's
+//! off-chain interventions (`POST /events/proxy`) so they show up next
+//! to on-chain vault events.
 
 use crate::processor::EventProcessor;
-use crate::schema::EventType;
+use crate::schema::{self, EventType, IndexedEvent};
 
 use axum::{
     extract::{Path, State},
-    http::Method,
-    routing::get,
-    Json, Router,
+    http::{header, HeaderMap, Method, StatusCode},
+    routing::{get, post},
+    Extension, Json, Router,
 };
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 
@@ -38,12 +41,95 @@ pub struct VaultsResponse {
     pub count: usize,
 }
 
+#[derive(Serialize)]
+pub struct TargetResponse {
+    pub address: String,
+    pub blocked_executions: u64,
+    pub approved_executions: u64,
+}
+
 #[derive(Serialize)]
 pub struct HealthResponse {
     pub status: String,
     pub pending_events: usize,
 }
 
+// ── Request Types ───────────────────────────────────────────────
+
+/// An off-chain intervention reported by the RPC proxy.
+#[derive(Debug, Deserialize)]
+pub struct ProxyEvent {
+    /// Proxy-assigned unique ID. Synthetic tx hashes repeat for a
+    /// repeated block reason, so this is what deduplicates.
+    pub id: String,
+    /// `BlockedTransaction`, `SignatureBlocked` or `PaymasterSevered`.
+    pub event_type: EventType,
+    pub chain_id: u64,
+    /// Unix seconds.
+    pub at: i64,
+    /// Synthetic tx hash handed to the agent (blocks only).
+    #[serde(default)]
+    pub tx_hash: String,
+    /// Engine that blocked (blocks only).
+    #[serde(default)]
+    pub engine: String,
+    /// Numeric block code (blocks only).
+    #[serde(default)]
+    pub code: Option<u16>,
+    #[serde(default)]
+    pub reason: String,
+    /// Revert strikes that severed the Paymaster.
+    #[serde(default)]
+    pub strikes: Option<u64>,
+    /// Sender of the blocked transaction, when known.
+    #[serde(default)]
+    pub agent: String,
+    /// Counterparty of the blocked transaction, when known.
+    #[serde(default)]
+    pub target: String,
+}
+
+impl ProxyEvent {
+    /// Normalize into the universal schema. The proxy event ID stands in
+    /// for the tx hash; the synthetic hash goes to metadata.
+    pub fn into_indexed(self) -> IndexedEvent {
+        let block_timestamp = DateTime::from_timestamp(self.at, 0).unwrap_or_else(Utc::now);
+        IndexedEvent {
+            id: format!("proxy:{}", self.id),
+            chain_name: schema::chain_name(self.chain_id),
+            chain_id: self.chain_id,
+            tx_hash: format!("proxy:{}", self.id),
+            log_index: 0,
+            event_type: self.event_type,
+            vault_address: String::new(),
+            agent_address: self.agent.to_lowercase(),
+            target_address: self.target.to_lowercase(),
+            amount_raw: 0,
+            amount_usd: 0.0,
+            reason: self.reason,
+            block_number: 0,
+            block_timestamp,
+            indexed_at: Utc::now(),
+            metadata: serde_json::json!({
+                "source": "plimsoll-rpc",
+                "synthetic_tx_hash": self.tx_hash,
+                "engine": self.engine,
+                "code": self.code,
+                "strikes": self.strikes,
+            }),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct IngestResponse {
+    pub accepted: bool,
+}
+
+/// Bearer token required on `POST /events/proxy` (empty = disabled).
+#[derive(Clone)]
+pub struct IngestToken(pub String);
+
 // ── Handlers ────────────────────────────────────────────────────
 
 /// GET /vaults/:owner — returns all vaults owned by the given address.
@@ -68,6 +154,51 @@ async fn get_vaults_by_owner(
     })
 }
 
+/// GET /targets/:address — execution history for a target address.
+///
+/// Queried by the RPC proxy's reputation scorer: targets that vaults
+/// across the fleet have already blocked are suspect.
+async fn get_target_history(
+    Path(address): Path<String>,
+    State(processor): State<Arc<EventProcessor>>,
+) -> Json<TargetResponse> {
+    let (blocked_executions, approved_executions) =
+        processor.target_history(&address.to_lowercase());
+    Json(TargetResponse {
+        address,
+        blocked_executions,
+        approved_executions,
+    })
+}
+
+/// POST /events/proxy — ingest an off-chain intervention from the RPC
+/// proxy (`Authorization: Bearer <PLIMSOLL_INGEST_TOKEN>`).
+///
+/// Only proxy event types are accepted: the proxy cannot forge on-chain
+/// vault events. `accepted` is false for a duplicate.
+async fn ingest_proxy_event(
+    State(processor): State<Arc<EventProcessor>>,
+    Extension(IngestToken(token)): Extension<IngestToken>,
+    headers: HeaderMap,
+    Json(event): Json<ProxyEvent>,
+) -> Result<Json<IngestResponse>, StatusCode> {
+    if token.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if presented != Some(token.as_str()) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    if !event.event_type.is_proxy_event() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    let accepted = processor.process_event(event.into_indexed());
+    Ok(Json(IngestResponse { accepted }))
+}
+
 /// GET /health — health check endpoint.
 async fn health(
     State(processor): State<Arc<EventProcessor>>,
@@ -81,7 +212,7 @@ async fn health(
 // ── Router ──────────────────────────────────────────────────────
 
 /// Build the axum router with CORS enabled.
-pub fn build_router(processor: Arc<EventProcessor>) -> Router {
+pub fn build_router(processor: Arc<EventProcessor>, ingest_token: String) -> Router {
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods([Method::GET])
@@ -89,7 +220,10 @@ pub fn build_router(processor: Arc<EventProcessor>) -> Router {
 
     Router::new()
         .route("/vaults/{owner}", get(get_vaults_by_owner))
+        .route("/targets/{address}", get(get_target_history))
+        .route("/events/proxy", post(ingest_proxy_event))
         .route("/health", get(health))
+        .layer(Extension(IngestToken(ingest_token)))
         .layer(cors)
         .with_state(processor)
 }
@@ -132,4 +266,33 @@ mod tests {
         assert!(json.contains("\"vault_address\":\"0xVault\""));
         assert!(json.contains("\"count\":1"));
     }
+
+    #[test]
+    fn test_proxy_event_into_indexed() {
+        let event: ProxyEvent = serde_json::from_value(serde_json::json!({
+            "id": "65f0-7",
+            "event_type": "SignatureBlocked",
+            "chain_id": 8453,
+            "at": 1_700_000_000,
+            "tx_hash": "0xplimsoll01",
+            "engine": "raw_sign",
+            "code": 4101,
+            "reason": "GOD-TIER 1: raw signing blocked",
+            "agent": "0xA9E0000000000000000000000000000000002115",
+        }))
+        .unwrap();
+        let indexed = event.into_indexed();
+        assert_eq!(indexed.agent_address, "0xa9e0000000000000000000000000000000002115");
+        assert_eq!(indexed.target_address, "");
+        assert_eq!(indexed.event_type, EventType::SignatureBlocked);
+        assert_eq!(indexed.chain_name, "base");
+        assert_eq!(indexed.dedup_key(), "8453:proxy:65f0-7:0");
+        assert_eq!(indexed.block_timestamp.timestamp(), 1_700_000_000);
+        assert_eq!(indexed.metadata["synthetic_tx_hash"], "0xplimsoll01");
+        assert_eq!(indexed.metadata["engine"], "raw_sign");
+
+        let processor = EventProcessor::new("postgres://test".into());
+        assert!(processor.process_event(indexed.clone()));
+        assert!(!processor.process_event(indexed));
+    }
 }
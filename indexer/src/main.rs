@@ -21,6 +21,8 @@
 //!   │                                                          │
 //!   └──────────────────┬───────────────────────────────────────┘
 //!                      │ Normalized IndexedEvent
+//!                      │   ◄── POST /events/proxy: off-chain blocks and
+//!                      │       Paymaster severs from the RPC proxy
 //!   ┌──────────────────▼───────────────────────────────────────┐
 //!   │                   EVENT PROCESSOR                         │
 //!   │                                                          │
@@ -94,8 +96,9 @@ async fn main() {
 
     // Spawn the HTTP API server
     let api_proc = Arc::clone(&processor);
+    let ingest_token = config.ingest_token.clone();
     let api_handle = tokio::spawn(async move {
-        let router = api::build_router(api_proc);
+        let router = api::build_router(api_proc, ingest_token);
         let listener = tokio::net::TcpListener::bind("0.0.0.0:3001")
             .await
             .expect("Failed to bind API server on :3001");
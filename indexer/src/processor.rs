@@ -7,7 +7,7 @@
 use crate::schema::{EventType, IndexedEvent};
 
 use chrono::Utc;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
 use tracing::info;
 
@@ -19,6 +19,10 @@ pub struct EventProcessor {
     seen_events: Mutex<HashSet<String>>,
     /// Pending batch for bulk insert.
     pending_batch: Mutex<Vec<IndexedEvent>>,
+    /// Flushed executions per target: `(blocked, approved)`.
+    ///
+    /// In production, this is a GROUP BY over plimsoll_events.
+    persisted_targets: Mutex<HashMap<String, (u64, u64)>>,
     /// Statistics.
     stats: Mutex<ProcessorStats>,
 }
@@ -41,6 +45,7 @@ impl EventProcessor {
             database_url,
             seen_events: Mutex::new(HashSet::new()),
             pending_batch: Mutex::new(Vec::new()),
+            persisted_targets: Mutex::new(HashMap::new()),
             stats: Mutex::new(ProcessorStats::default()),
         }
     }
@@ -130,6 +135,15 @@ impl EventProcessor {
 
         info!("Flushing {} events to PostgreSQL", count);
 
+        {
+            let mut targets = self.persisted_targets.lock().unwrap();
+            for (target, (blocked, approved)) in Self::tally_targets(&batch) {
+                let entry = targets.entry(target).or_default();
+                entry.0 += blocked;
+                entry.1 += approved;
+            }
+        }
+
         {
             let mut stats = self.stats.lock().unwrap();
             stats.total_persisted += count as u64;
@@ -189,6 +203,49 @@ impl EventProcessor {
             .collect()
     }
 
+    /// `(blocked, approved)` executions per target in `events`.
+    fn tally_targets(events: &[IndexedEvent]) -> HashMap<String, (u64, u64)> {
+        let mut tally: HashMap<String, (u64, u64)> = HashMap::new();
+        for e in events {
+            let entry = match e.event_type {
+                EventType::ExecutionBlocked | EventType::ExecutionApproved => {
+                    tally.entry(e.target_address.to_lowercase()).or_default()
+                }
+                _ => continue,
+            };
+            if e.event_type == EventType::ExecutionBlocked {
+                entry.0 += 1;
+            } else {
+                entry.1 += 1;
+            }
+        }
+        tally
+    }
+
+    /// Count executions targeting `target`: persisted rows plus the
+    /// pending batch.
+    ///
+    /// Returns `(blocked, approved)`. In production, the persisted part
+    /// is a GROUP BY over plimsoll_events:
+    /// ```sql
+    /// SELECT event_type, COUNT(*) FROM plimsoll_events
+    /// WHERE target_address = $1 GROUP BY event_type
+    /// ```
+    pub fn target_history(&self, target: &str) -> (u64, u64) {
+        let target = target.to_lowercase();
+        let (blocked, approved) = self
+            .persisted_targets
+            .lock()
+            .unwrap()
+            .get(&target)
+            .copied()
+            .unwrap_or_default();
+        let batch = self.pending_batch.lock().unwrap();
+        let (pending_blocked, pending_approved) =
+            Self::tally_targets(&batch).remove(&target).unwrap_or_default();
+        (blocked + pending_blocked, approved + pending_approved)
+    }
+
     /// Register a newly created vault in the vault_registry.
     ///
     /// In production, this would INSERT into vault_registry.
@@ -396,6 +453,45 @@ mod tests {
         assert_eq!(batch[0].vault_address, "0xNewVault");
     }
 
+    #[test]
+    fn test_target_history_counts_blocked_and_approved() {
+        let processor = EventProcessor::new("postgres://test".into());
+        for (i, event_type) in [
+            EventType::ExecutionBlocked,
+            EventType::ExecutionBlocked,
+            EventType::ExecutionApproved,
+            EventType::Deposited,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut event = make_event("ethereum", 1, &format!("0xt{}", i), 0);
+            event.event_type = event_type;
+            event.target_address = "0xDrainer".into();
+            processor.process_event(event);
+        }
+
+        assert_eq!(processor.target_history("0xdrainer"), (2, 1));
+        assert_eq!(processor.target_history("0xother"), (0, 0));
+    }
+
+    #[test]
+    fn test_target_history_survives_flush() {
+        let processor = EventProcessor::new("postgres://test".into());
+        let mut event = make_event("ethereum", 1, "0xf1", 0);
+        event.event_type = EventType::ExecutionBlocked;
+        event.target_address = "0xDrainer".into();
+        processor.process_event(event);
+        processor.flush_batch();
+        assert_eq!(processor.pending_count(), 0);
+
+        let mut event = make_event("ethereum", 1, "0xf2", 0);
+        event.event_type = EventType::ExecutionApproved;
+        event.target_address = "0xdrainer".into();
+        processor.process_event(event);
+        assert_eq!(processor.target_history("0xdrainer"), (1, 1));
+    }
+
     #[test]
     fn test_concurrent_dedup() {
         // Verify the Mutex-based dedup handles concurrent access
@@ -41,6 +41,9 @@ pub struct IndexerConfig {
     pub batch_size: usize,
     /// Flush interval in milliseconds.
     pub flush_interval_ms: u64,
+    /// Bearer token the RPC proxy presents on `POST /events/proxy`.
+    /// Empty = proxy event ingest disabled.
+    pub ingest_token: String,
 }
 
 impl IndexerConfig {
@@ -53,6 +56,7 @@ impl IndexerConfig {
     ///   PLIMSOLL_CHAIN_ETHEREUM_HTTP=https://eth-mainnet.g.alchemy.com/v2/KEY
     ///   PLIMSOLL_CHAIN_ETHEREUM_CONTRACT=0x...
     ///   PLIMSOLL_CHAIN_ETHEREUM_ID=1
+    ///   PLIMSOLL_INGEST_TOKEN=<shared with the RPC proxy>
     pub fn from_env() -> Self {
         let database_url = env::var("DATABASE_URL")
             .unwrap_or_else(|_| "postgres://localhost/plimsoll_indexer".into());
@@ -106,6 +110,7 @@ impl IndexerConfig {
                 .unwrap_or_else(|_| "500".into())
                 .parse()
                 .unwrap_or(500),
+            ingest_token: env::var("PLIMSOLL_INGEST_TOKEN").unwrap_or_default(),
         }
     }
 }
@@ -122,6 +127,19 @@ fn default_chain_id(name: &str) -> u64 {
     }
 }
 
+/// Chain name for a numeric chain ID (inverse of `default_chain_id`).
+pub fn chain_name(chain_id: u64) -> String {
+    match chain_id {
+        1 => "ethereum".into(),
+        8453 => "base".into(),
+        42161 => "arbitrum".into(),
+        137 => "polygon".into(),
+        10 => "optimism".into(),
+        0 => "solana".into(),
+        other => other.to_string(),
+    }
+}
+
 fn default_ws_url(name: &str) -> String {
     match name {
         "solana" => "wss://api.mainnet-beta.solana.com".into(),
@@ -166,6 +184,21 @@ pub enum EventType {
     ProxyUpgradeBlocked,
     CosignRejected,
     VaultCreated,
+    // ── Off-chain interventions (streamed by the RPC proxy) ──
+    BlockedTransaction,
+    SignatureBlocked,
+    PaymasterSevered,
+}
+
+impl EventType {
+    /// Whether this event is reported by the RPC proxy rather than
+    /// indexed from a chain.
+    pub fn is_proxy_event(self) -> bool {
+        matches!(
+            self,
+            EventType::BlockedTransaction | EventType::SignatureBlocked | EventType::PaymasterSevered
+        )
+    }
 }
 
 /// Universal indexed event — normalized across all chains.
@@ -381,6 +414,14 @@ mod tests {
         assert_eq!(default_chain_id("solana"), 0);
     }
 
+    #[test]
+    fn test_chain_name_inverts_default_chain_id() {
+        for name in ["ethereum", "base", "arbitrum", "polygon", "optimism", "solana"] {
+            assert_eq!(chain_name(default_chain_id(name)), name);
+        }
+        assert_eq!(chain_name(31337), "31337");
+    }
+
     #[test]
     fn test_default_confirmations() {
         assert_eq!(default_confirmations("ethereum"), 12);
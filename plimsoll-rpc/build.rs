@@ -0,0 +1,14 @@
+//! Compiles `proto/plimsoll.proto` when the `grpc` feature is on.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/plimsoll.proto");
+    #[cfg(feature = "grpc")]
+    {
+        // A vendored protoc: no system install needed to build.
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+        tonic_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/plimsoll.proto"], &["proto"])?;
+    }
+    Ok(())
+}
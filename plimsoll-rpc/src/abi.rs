@@ -0,0 +1,203 @@
+//! Minimal ABI calldata reading for wrapper unwrapping (Safe, multicall).
+//!
+//! Offsets are relative to the argument area (calldata after the 4-byte
+//! selector). Every read is bounds-checked: attacker-controlled calldata
+//! must never panic the proxy.
+
+use alloy_primitives::U256;
+
+const WORD: usize = 32;
+
+/// Upper bound on any decoded length or offset, so a forged length word
+/// can't make us allocate or scan gigabytes.
+const MAX_DYNAMIC_LEN: usize = 1 << 20;
+
+/// The 4-byte selector of `data`, if present.
+pub fn selector(data: &[u8]) -> Option<[u8; 4]> {
+    data.get(..4)?.try_into().ok()
+}
+
+/// Word `index` of `args`.
+pub fn word(args: &[u8], index: usize) -> Result<&[u8], String> {
+    word_at(args, index * WORD)
+}
+
+fn word_at(args: &[u8], offset: usize) -> Result<&[u8], String> {
+    offset
+        .checked_add(WORD)
+        .and_then(|end| args.get(offset..end))
+        .ok_or_else(|| format!("calldata truncated at byte {offset}"))
+}
+
+/// Word `index` decoded as a uint256.
+pub fn uint(args: &[u8], index: usize) -> Result<U256, String> {
+    Ok(U256::from_be_slice(word(args, index)?))
+}
+
+/// Word `index` decoded as a `usize` (offsets, lengths, enums).
+pub fn usize_word(args: &[u8], index: usize) -> Result<usize, String> {
+    usize_at(args, index * WORD)
+}
+
+fn usize_at(args: &[u8], offset: usize) -> Result<usize, String> {
+    let v = U256::from_be_slice(word_at(args, offset)?);
+    usize::try_from(v)
+        .ok()
+        .filter(|v| *v <= MAX_DYNAMIC_LEN)
+        .ok_or_else(|| format!("implausible offset/length {v}"))
+}
+
+/// Word `index` decoded as an address (lowercase, `0x`-prefixed).
+pub fn address(args: &[u8], index: usize) -> Result<String, String> {
+    let w = word(args, index)?;
+    if w[..12].iter().any(|b| *b != 0) {
+        return Err("dirty high bytes in address word".into());
+    }
+    Ok(format!("0x{}", hex::encode(&w[12..])))
+}
+
+/// Dynamic `bytes` whose head is word `index`.
+pub fn bytes(args: &[u8], index: usize) -> Result<Vec<u8>, String> {
+    let offset = usize_word(args, index)?;
+    bytes_body(args.get(offset..).ok_or("dynamic bytes out of bounds")?)
+}
+
+/// `bytes` encoded in place: length word followed by the data.
+pub fn bytes_body(enc: &[u8]) -> Result<Vec<u8>, String> {
+    let len = usize_at(enc, 0)?;
+    enc.get(WORD..WORD + len)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| "dynamic bytes out of bounds".into())
+}
+
+/// Elements of a dynamic array of dynamic types (`bytes[]`, tuples with
+/// dynamic members) whose head is word `index`. Each returned slice starts
+/// at its element, so offsets inside the element resolve against it.
+pub fn dynamic_array(args: &[u8], index: usize) -> Result<Vec<&[u8]>, String> {
+    let offset = usize_word(args, index)?;
+    let array = args.get(offset..).ok_or("array out of bounds")?;
+    let len = usize_at(array, 0)?;
+    let elements = &array[WORD..];
+    (0..len)
+        .map(|i| {
+            let start = usize_word(elements, i)?;
+            elements.get(start..).ok_or_else(|| format!("array element {i} out of bounds"))
+        })
+        .collect()
+}
+
+/// Dynamic array of static words (`address[]`, `uint256[]`) whose head
+/// is word `index`.
+fn static_array(args: &[u8], index: usize) -> Result<&[u8], String> {
+    let offset = usize_word(args, index)?;
+    let array = args.get(offset..).ok_or("array out of bounds")?;
+    let len = usize_at(array, 0)?;
+    array
+        .get(WORD..WORD + len * WORD)
+        .ok_or_else(|| "array out of bounds".into())
+}
+
+/// Dynamic `address[]` whose head is word `index`.
+pub fn address_array(args: &[u8], index: usize) -> Result<Vec<String>, String> {
+    let elements = static_array(args, index)?;
+    (0..elements.len() / WORD).map(|i| address(elements, i)).collect()
+}
+
+/// Dynamic `uint256[]` whose head is word `index`.
+pub fn uint_array(args: &[u8], index: usize) -> Result<Vec<U256>, String> {
+    let elements = static_array(args, index)?;
+    (0..elements.len() / WORD).map(|i| uint(elements, i)).collect()
+}
+
+/// Encode a uint256 as an ABI word.
+pub fn encode_u256(v: U256) -> [u8; 32] {
+    v.to_be_bytes::<32>()
+}
+
+/// Encode an address as an ABI word.
+pub fn encode_address(address: &str) -> Result<[u8; 32], String> {
+    let raw = hex::decode(address.trim_start_matches("0x"))
+        .ok()
+        .filter(|b| b.len() == 20)
+        .ok_or_else(|| format!("invalid address '{address}'"))?;
+    let mut w = [0u8; 32];
+    w[12..].copy_from_slice(&raw);
+    Ok(w)
+}
+
+/// Selector of `Error(string)`, the standard Solidity revert payload.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Encode `Error(reason)` as a contract reverting with `reason` returns it.
+pub fn encode_revert(reason: &str) -> Vec<u8> {
+    let bytes = reason.as_bytes();
+    let mut out = ERROR_SELECTOR.to_vec();
+    out.extend_from_slice(&encode_u256(U256::from(WORD)));
+    out.extend_from_slice(&encode_u256(U256::from(bytes.len())));
+    out.extend_from_slice(bytes);
+    out.resize(out.len() + (WORD - bytes.len() % WORD) % WORD, 0);
+    out
+}
+
+/// Encode a uint as an ABI word (calldata-building helper).
+pub fn encode_uint(v: u64) -> [u8; 32] {
+    encode_u256(U256::from(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_are_bounds_checked() {
+        let mut args = Vec::new();
+        args.extend_from_slice(&encode_uint(0x20)); // offset
+        args.extend_from_slice(&encode_uint(2)); // len
+        args.extend_from_slice(&[0xab, 0xcd]);
+        args.resize(96, 0);
+        assert_eq!(bytes(&args, 0).unwrap(), vec![0xab, 0xcd]);
+        assert!(word(&args, 3).is_err());
+
+        let mut forged = encode_uint(0x20).to_vec();
+        forged.extend_from_slice(&U256::MAX.to_be_bytes::<32>());
+        assert!(bytes(&forged, 0).is_err());
+    }
+
+    #[test]
+    fn test_address_rejects_dirty_word() {
+        let mut w = [0u8; 32];
+        w[31] = 1;
+        assert_eq!(address(&w, 0).unwrap(), "0x0000000000000000000000000000000000000001");
+        w[0] = 1;
+        assert!(address(&w, 0).is_err());
+    }
+
+    #[test]
+    fn test_static_arrays() {
+        let a = "0x1111111111111111111111111111111111111111";
+        let mut args = encode_uint(0x20).to_vec();
+        args.extend_from_slice(&encode_uint(2));
+        args.extend_from_slice(&encode_address(a).unwrap());
+        args.extend_from_slice(&encode_uint(7));
+        assert_eq!(address_array(&args, 0).unwrap()[0], a);
+        assert_eq!(uint_array(&args, 0).unwrap()[1], U256::from(7));
+        args.truncate(args.len() - 1);
+        assert!(uint_array(&args, 0).is_err());
+        assert!(encode_address("0x1234").is_err());
+    }
+
+    #[test]
+    fn test_encode_revert() {
+        let encoded = encode_revert("Not enough Ether provided.");
+        assert_eq!(
+            hex::encode(encoded),
+            concat!(
+                "08c379a0",
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "000000000000000000000000000000000000000000000000000000000000001a",
+                "4e6f7420656e6f7567682045746865722070726f76696465642e000000000000",
+            )
+        );
+        assert_eq!(encode_revert("").len(), 4 + 2 * WORD);
+    }
+}
@@ -0,0 +1,360 @@
+//! Contract ABI registry.
+//!
+//! The selector directory only names the function; block reasons still
+//! read `approve(address,uint256)` with no idea *who* was approved for
+//! *how much*. With the target's ABI the calldata decodes into named
+//! parameters: `approve(spender=0x…, amount=1157…)`.
+//!
+//! ABIs come from, in order:
+//!
+//!   1. `abi_dir` — `<address>.json`, a plain ABI array or a Hardhat /
+//!      Foundry artifact with an `abi` field,
+//!   2. Sourcify (`abi_sourcify_url`), full or partial match,
+//!   3. Etherscan v2 (`etherscan_api_url` + `etherscan_api_key`),
+//!
+//! and are cached per address. An ABI only ever names calls to its own
+//! address: it never touches the global selector directory, so a hostile
+//! (even verified) ABI can't rename `transferOwnership` out of
+//! `blocked_functions`.
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use ethers::abi::{Abi, Function, Token};
+use ethers::types::I256;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Remote fetch timeout — this runs on the send path.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+lazy_static! {
+    static ref REGISTRY: RwLock<HashMap<String, Arc<Abi>>> = RwLock::new(HashMap::new());
+    /// Addresses no source had an ABI for, so we don't ask again.
+    static ref MISSES: RwLock<HashSet<String>> = RwLock::new(HashSet::new());
+}
+
+/// One decoded argument.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedParam {
+    /// Parameter name from the ABI; `arg{i}` when unnamed.
+    pub name: String,
+    pub kind: String,
+    pub value: String,
+}
+
+/// Calldata decoded against a contract ABI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCall {
+    /// The contract whose ABI matched.
+    pub contract: String,
+    pub function: String,
+    pub signature: String,
+    pub params: Vec<DecodedParam>,
+}
+
+impl DecodedCall {
+    /// Value of the parameter called `name`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.value.as_str())
+    }
+}
+
+impl fmt::Display for DecodedCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args: Vec<String> = self
+            .params
+            .iter()
+            .map(|p| format!("{}={}", p.name, p.value))
+            .collect();
+        write!(f, "{}({})", self.function, args.join(", "))
+    }
+}
+
+fn normalize(address: &str) -> String {
+    address.trim().to_lowercase()
+}
+
+/// Accept a plain ABI array or an artifact carrying one under `abi`.
+fn parse_abi(value: serde_json::Value) -> Result<Abi> {
+    let abi = match value {
+        serde_json::Value::Object(mut artifact) => artifact
+            .remove("abi")
+            .context("JSON object has no `abi` field")?,
+        other => other,
+    };
+    serde_json::from_value(abi).context("invalid ABI")
+}
+
+fn signature(function: &Function) -> String {
+    let inputs: Vec<String> = function.inputs.iter().map(|p| p.kind.to_string()).collect();
+    format!("{}({})", function.name, inputs.join(","))
+}
+
+fn insert(address: &str, abi: Abi) -> Arc<Abi> {
+    let abi = Arc::new(abi);
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.insert(normalize(address), Arc::clone(&abi));
+    }
+    abi
+}
+
+/// Rebuild the registry from `abi_dir`. Returns the number of ABIs loaded.
+/// Remotely fetched ABIs are dropped and re-fetched on demand.
+pub fn load(config: &Config) -> Result<usize> {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.clear();
+    }
+    if let Ok(mut misses) = MISSES.write() {
+        misses.clear();
+    }
+    if config.abi_dir.is_empty() {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+    let entries = std::fs::read_dir(&config.abi_dir)
+        .with_context(|| format!("reading ABI directory {}", config.abi_dir))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(address) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading ABI {}", path.display()))?;
+        let value = serde_json::from_str(&raw)
+            .with_context(|| format!("parsing ABI {}", path.display()))?;
+        let abi = parse_abi(value).with_context(|| format!("loading ABI {}", path.display()))?;
+        insert(address, abi);
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// The cached ABI for `address`, fetching from Sourcify / Etherscan on a
+/// miss when configured.
+pub async fn get(config: &Config, address: &str) -> Option<Arc<Abi>> {
+    let key = normalize(address);
+    if let Some(abi) = REGISTRY.read().ok()?.get(&key) {
+        return Some(Arc::clone(abi));
+    }
+    let remote_enabled =
+        !config.abi_sourcify_url.is_empty() || !config.etherscan_api_key.is_empty();
+    if !remote_enabled || MISSES.read().map(|m| m.contains(&key)).unwrap_or(false) {
+        return None;
+    }
+
+    let mut fetched = None;
+    if !config.abi_sourcify_url.is_empty() {
+        fetched = fetch_sourcify(config, &key).await;
+    }
+    if fetched.is_none() && !config.etherscan_api_key.is_empty() {
+        fetched = fetch_etherscan(config, &key).await;
+    }
+    match fetched {
+        Some(abi) => Some(insert(&key, abi)),
+        None => {
+            if let Ok(mut misses) = MISSES.write() {
+                misses.insert(key);
+            }
+            None
+        }
+    }
+}
+
+async fn fetch_json(request: reqwest::RequestBuilder) -> Option<serde_json::Value> {
+    request
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()
+}
+
+/// Sourcify v2: `GET {url}/v2/contract/{chainId}/{address}?fields=abi`.
+async fn fetch_sourcify(config: &Config, address: &str) -> Option<Abi> {
+    let url = format!(
+        "{}/v2/contract/{}/{}",
+        config.abi_sourcify_url.trim_end_matches('/'),
+        config.chain_id,
+        address
+    );
+    let body = fetch_json(reqwest::Client::new().get(url).query(&[("fields", "abi")])).await?;
+    parse_abi(body.get("abi")?.clone()).ok()
+}
+
+/// Etherscan v2: `module=contract&action=getabi`; the ABI arrives as a
+/// JSON string in `result`.
+async fn fetch_etherscan(config: &Config, address: &str) -> Option<Abi> {
+    let chain_id = config.chain_id.to_string();
+    let request = reqwest::Client::new().get(&config.etherscan_api_url).query(&[
+        ("chainid", chain_id.as_str()),
+        ("module", "contract"),
+        ("action", "getabi"),
+        ("address", address),
+        ("apikey", config.etherscan_api_key.as_str()),
+    ]);
+    let body = fetch_json(request).await?;
+    if body.get("status")?.as_str()? != "1" {
+        return None;
+    }
+    serde_json::from_str(body.get("result")?.as_str()?).ok()
+}
+
+fn format_token(token: &Token) -> String {
+    match token {
+        Token::Address(a) => format!("0x{}", hex::encode(a.as_bytes())),
+        Token::Uint(v) => v.to_string(),
+        Token::Int(v) => I256::from_raw(*v).to_string(),
+        Token::Bool(b) => b.to_string(),
+        Token::String(s) => format!("{s:?}"),
+        Token::Bytes(b) | Token::FixedBytes(b) => format!("0x{}", hex::encode(b)),
+        Token::Array(items) | Token::FixedArray(items) => {
+            let items: Vec<String> = items.iter().map(format_token).collect();
+            format!("[{}]", items.join(", "))
+        }
+        Token::Tuple(items) => {
+            let items: Vec<String> = items.iter().map(format_token).collect();
+            format!("({})", items.join(", "))
+        }
+    }
+}
+
+/// Decode `data` against `abi`. `None` if no function matches the
+/// selector or the arguments don't decode.
+pub fn decode(contract: &str, abi: &Abi, data: &[u8]) -> Option<DecodedCall> {
+    let selector: [u8; 4] = data.get(..4)?.try_into().ok()?;
+    let function = abi.functions().find(|f| f.short_signature() == selector)?;
+    let tokens = function.decode_input(&data[4..]).ok()?;
+    let params = function
+        .inputs
+        .iter()
+        .zip(&tokens)
+        .enumerate()
+        .map(|(i, (param, token))| DecodedParam {
+            name: if param.name.is_empty() { format!("arg{i}") } else { param.name.clone() },
+            kind: param.kind.to_string(),
+            value: format_token(token),
+        })
+        .collect();
+    Some(DecodedCall {
+        contract: normalize(contract),
+        function: function.name.clone(),
+        signature: signature(function),
+        params,
+    })
+}
+
+/// Decode a call to `to`. For proxies, pass the resolved `implementation`:
+/// its ABI is tried when the proxy's own ABI doesn't know the function.
+pub async fn decode_call(
+    config: &Config,
+    to: &str,
+    implementation: Option<&str>,
+    data: &[u8],
+) -> Option<DecodedCall> {
+    for contract in std::iter::once(to).chain(implementation) {
+        if let Some(abi) = get(config, contract).await {
+            if let Some(call) = decode(contract, &abi, data) {
+                return Some(call);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0x1111111111111111111111111111111111111111";
+
+    fn erc20_artifact() -> serde_json::Value {
+        serde_json::json!({
+            "contractName": "Token",
+            "abi": [{
+                "type": "function",
+                "name": "approve",
+                "stateMutability": "nonpayable",
+                "inputs": [
+                    { "name": "spender", "type": "address" },
+                    { "name": "amount", "type": "uint256" }
+                ],
+                "outputs": [{ "name": "", "type": "bool" }]
+            }]
+        })
+    }
+
+    fn approve_calldata() -> Vec<u8> {
+        let mut data = vec![0x09, 0x5e, 0xa7, 0xb3];
+        let mut spender = [0u8; 32];
+        spender[12..].copy_from_slice(&[0x66; 20]);
+        data.extend_from_slice(&spender);
+        data.extend_from_slice(&crate::abi::encode_uint(1000));
+        data
+    }
+
+    #[test]
+    fn test_decode_named_params() {
+        let abi = parse_abi(erc20_artifact()).unwrap();
+        let call = decode(TOKEN, &abi, &approve_calldata()).unwrap();
+        assert_eq!(call.signature, "approve(address,uint256)");
+        assert_eq!(call.param("spender"), Some("0x6666666666666666666666666666666666666666"));
+        assert_eq!(call.param("amount"), Some("1000"));
+        assert_eq!(
+            call.to_string(),
+            "approve(spender=0x6666666666666666666666666666666666666666, amount=1000)"
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_selector_and_truncated_args() {
+        let abi = parse_abi(erc20_artifact()).unwrap();
+        assert!(decode(TOKEN, &abi, &[0xde, 0xad, 0xbe, 0xef]).is_none());
+        assert!(decode(TOKEN, &abi, &approve_calldata()[..40]).is_none());
+    }
+
+    #[test]
+    fn test_load_abi_dir() {
+        let dir = std::env::temp_dir().join(format!("plimsoll-abis-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(format!("{TOKEN}.json")), erc20_artifact().to_string()).unwrap();
+        std::fs::write(dir.join("README.md"), "not an abi").unwrap();
+
+        let config = Config { abi_dir: dir.to_string_lossy().into_owned(), ..Config::default() };
+        assert_eq!(load(&config).unwrap(), 1);
+        assert!(REGISTRY.read().unwrap().contains_key(TOKEN));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_abi_stays_in_its_own_namespace() {
+        let abi = parse_abi(serde_json::json!([{
+            "type": "function",
+            "name": "sweepDust",
+            "stateMutability": "nonpayable",
+            "inputs": [{ "name": "to", "type": "address" }],
+            "outputs": []
+        }]))
+        .unwrap();
+        let selector = abi.functions().next().unwrap().short_signature();
+        let contract = "0x2222222222222222222222222222222222222222";
+        // Decodable against its own address, unknown to the global directory.
+        let abi = insert(contract, abi);
+        let data = [selector.as_slice(), &[0u8; 32]].concat();
+        assert_eq!(decode(contract, &abi, &data).unwrap().signature, "sweepDust(address)");
+        assert_eq!(crate::selectors::lookup(&selector), None);
+    }
+}
@@ -0,0 +1,736 @@
+//! Authenticated admin API for operational controls.
+//!
+//! All routes live under `/admin` and require
+//! `Authorization: Bearer <PLIMSOLL_ADMIN_TOKEN>`. With no token configured
+//! the whole surface returns 404.
+//!
+//! | Route                                | Action                                   |
+//! |--------------------------------------|------------------------------------------|
+//! | `GET  /admin/status`                 | Protective state summary                 |
+//! | `POST /admin/reload`                 | Hot-reload config (file + env)           |
+//! | `POST /admin/paymaster/reset`        | Clear the Paymaster sever + strike window |
+//! | `POST /admin/session-keys/revoke`    | `{"session_key": "0x..", "chain_id"?: 8453}` |
+//! | `POST /admin/session-keys/unrevoke`  | `{"session_key": "0x..", "chain_id"?: 8453}` |
+//! | `POST /admin/session-keys/scope`     | `{"session_key": "0x..", "targets"?, "selectors"?, "max_value"?, "expires_at"?}` |
+//! | `POST /admin/session-keys/unscope`   | `{"session_key": "0x..", "chain_id"?: 8453}` |
+//! | `POST /admin/session-keys/rotate`    | `{"old_session_key": "0x..", "new_session_key": "0x..", "scope"?, "onchain"?}` |
+//! | `POST /admin/blocked-txs/flush`      | Forget blocked txs (synthetic receipts)  |
+//! | `POST /admin/shadow-mode`            | `{"enabled": true}`                      |
+//! | `GET  /admin/shadow-verdicts`        | `?engine=` — would-block decisions in shadow |
+//! | `POST /admin/selectors/refresh`      | Reload selectors and the ABI registry    |
+//! | `POST /admin/counterparties/approve` | `{"agent": "0x..", "counterparty": "0x.."}` |
+//! | `GET  /admin/false-positives`        | False-positive reports                   |
+//! | `POST /admin/false-positives/review` | `{"id": "fp-..", "accept": true}`        |
+//! | `GET  /admin/approvals`              | Requests held for approval (`approve` mode) |
+//! | `POST /admin/approvals/review`       | `{"id": "apr-..", "accept": true}`       |
+//! | `GET  /admin/iocs/stix`              | Stored IOCs as a STIX 2.1 bundle         |
+//! | `GET  /admin/iocs/taxii/objects`     | Stored IOCs as a TAXII 2.1 envelope      |
+//! | `GET  /admin/fees`                   | Protocol fees owed, by chain and agent   |
+//! | `POST /admin/fees/settle`            | Settle owed fees now                     |
+//! | `GET  /admin/fees/report`            | `?period=day` — fee ledger totals        |
+//! | `GET  /admin/fees/export.csv`        | `?since=&until=` — fee ledger entries    |
+//! | `GET  /admin/audit/export`           | `?since=<seq>&limit=` — audit log lines (NDJSON) |
+//! | `GET  /admin/audit/verify`           | Check the audit log's chain and checkpoints |
+//!
+//! Every mutation is logged and, when a state store is configured,
+//! persisted immediately rather than at the next snapshot tick.
+//!
+//! Session key rotation is also a JSON-RPC method on the main endpoint,
+//! `plimsoll_rotateSessionKey` (params: `[<rotate body>]`), for agent
+//! tooling that only speaks JSON-RPC. It takes the admin token in the
+//! `plimsoll-admin-token` header and needs no agent key.
+
+use crate::abi_registry;
+use crate::audit;
+use crate::auth;
+use crate::chains;
+use crate::config::Config;
+use crate::counterparties;
+use crate::false_positives;
+use crate::fee;
+use crate::fee_ledger;
+use crate::identity;
+use crate::ioc_store;
+use crate::protection;
+use crate::reload;
+use crate::router::AppState;
+use crate::rpc;
+use crate::selectors;
+use crate::session_scopes::{self, OnChainRotation, SessionScope};
+use crate::shadow;
+use crate::state_store;
+use crate::types::{JsonRpcRequest, JsonRpcResponse};
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// JSON-RPC method rotating a session key (admin token required).
+pub const ROTATE_SESSION_KEY_METHOD: &str = "plimsoll_rotateSessionKey";
+
+/// v2.1: JSON-RPC form of `GET /admin/fees/report`. Every agent's fees:
+/// admin token only.
+pub const FEE_REPORT_METHOD: &str = "plimsoll_getFeeReport";
+
+/// `FEE_REPORT_METHOD` without a fee ledger (`fee_ledger_path`).
+const FEE_LEDGER_OFF_CODE: i64 = -32601;
+
+#[derive(Debug, Deserialize)]
+struct SessionKeyBody {
+    session_key: String,
+    /// Chain the key is revoked on (absent = the default chain).
+    #[serde(default)]
+    chain_id: Option<u64>,
+}
+
+impl SessionKeyBody {
+    /// The key as stored in the revocation cache of its chain.
+    fn scoped_key(&self, config: &Config) -> String {
+        let chain = self.chain_id.filter(|&id| id != config.default_chain_id());
+        chains::scope_key(chain, &self.session_key.to_lowercase())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionScopeBody {
+    #[serde(flatten)]
+    key: SessionKeyBody,
+    #[serde(flatten)]
+    scope: SessionScope,
+}
+
+#[derive(Debug, Deserialize)]
+struct RotateBody {
+    old_session_key: String,
+    new_session_key: String,
+    #[serde(default)]
+    chain_id: Option<u64>,
+    /// Scope of the new key (absent = the old key's).
+    #[serde(default)]
+    scope: Option<SessionScope>,
+    /// Build the vault transactions rotating the key on-chain.
+    #[serde(default)]
+    onchain: Option<OnChainRotation>,
+}
+
+impl RotateBody {
+    fn key(&self, session_key: &str) -> SessionKeyBody {
+        SessionKeyBody { session_key: session_key.to_string(), chain_id: self.chain_id }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CounterpartyBody {
+    agent: String,
+    counterparty: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewBody {
+    id: String,
+    accept: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    /// Unix time; only IOCs recorded at or after it.
+    #[serde(default)]
+    added_after: u64,
+    #[serde(default = "default_export_limit")]
+    limit: usize,
+    /// TAXII paging cursor from the previous envelope.
+    #[serde(default)]
+    next: Option<String>,
+}
+
+fn default_export_limit() -> usize {
+    ioc_store::MAX_EXPORT
+}
+
+#[derive(Debug, Deserialize)]
+struct FeeReportQuery {
+    #[serde(default = "default_fee_period")]
+    period: String,
+}
+
+fn default_fee_period() -> String {
+    "day".into()
+}
+
+#[derive(Debug, Deserialize)]
+struct FeeExportQuery {
+    /// Unix time; entries recorded at or after it.
+    #[serde(default)]
+    since: u64,
+    /// Unix time; entries recorded before it.
+    #[serde(default = "default_fee_until")]
+    until: u64,
+}
+
+fn default_fee_until() -> u64 {
+    u64::MAX
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditExportQuery {
+    #[serde(default)]
+    since: u64,
+    #[serde(default = "default_audit_limit")]
+    limit: usize,
+}
+
+fn default_audit_limit() -> usize {
+    audit::MAX_EXPORT
+}
+
+#[derive(Debug, Deserialize)]
+struct ShadowModeBody {
+    enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShadowVerdictsQuery {
+    #[serde(default)]
+    engine: Option<String>,
+}
+
+/// Admin routes, to be nested under `/admin`.
+pub fn routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/status", get(status))
+        .route("/reload", post(reload_config))
+        .route("/paymaster/reset", post(reset_paymaster))
+        .route("/session-keys/revoke", post(revoke_session_key))
+        .route("/session-keys/unrevoke", post(unrevoke_session_key))
+        .route("/session-keys/scope", post(scope_session_key))
+        .route("/session-keys/unscope", post(unscope_session_key))
+        .route("/session-keys/rotate", post(rotate_session_key))
+        .route("/blocked-txs/flush", post(flush_blocked_txs))
+        .route("/shadow-mode", post(set_shadow_mode))
+        .route("/shadow-verdicts", get(list_shadow_verdicts))
+        .route("/selectors/refresh", post(refresh_selectors))
+        .route("/counterparties/approve", post(approve_counterparty))
+        .route("/false-positives", get(list_false_positives))
+        .route("/false-positives/review", post(review_false_positive))
+        .route("/approvals", get(list_approvals))
+        .route("/approvals/review", post(review_approval))
+        .route("/iocs/stix", get(export_stix))
+        .route("/iocs/taxii/objects", get(export_taxii))
+        .route("/fees", get(list_fees))
+        .route("/fees/settle", post(settle_fees))
+        .route("/fees/report", get(fee_report))
+        .route("/fees/export.csv", get(export_fees))
+        .route("/audit/export", get(export_audit))
+        .route("/audit/verify", get(verify_audit))
+        .route_layer(middleware::from_fn_with_state(state, require_admin))
+}
+
+/// Reject requests without a valid admin bearer token.
+async fn require_admin(State(state): State<Arc<AppState>>, req: Request, next: Next) -> Response {
+    match authorize_admin(&state.config.current(), req.headers()) {
+        Ok(()) => next.run(req).await,
+        Err(status) => {
+            if status == StatusCode::UNAUTHORIZED {
+                warn!(path = %req.uri().path(), "ADMIN: rejected request with invalid token");
+            }
+            (status, Json(json!({ "error": "unauthorized" }))).into_response()
+        }
+    }
+}
+
+/// Check `Authorization: Bearer <admin_token>`. The admin API is disabled
+/// (404) when no token is configured.
+fn authorize_admin(config: &Config, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let presented = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    authorize_admin_token(config, presented)
+}
+
+fn authorize_admin_token(config: &Config, presented: &str) -> Result<(), StatusCode> {
+    if config.admin_token.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if auth::constant_time_eq(presented.as_bytes(), config.admin_token.as_bytes()) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Persist protective state right away after an admin mutation.
+fn persist(state: &AppState) {
+    if let Some(store) = &state.state_store {
+        state_store::flush(store);
+    }
+}
+
+/// GET /admin/status
+async fn status() -> Json<Value> {
+    Json(json!({
+        "paymaster_severed": rpc::is_paymaster_severed(),
+        "shadow_mode": rpc::is_shadow_mode(),
+        "revoked_session_keys": rpc::revoked_session_key_count(),
+        "scoped_session_keys": session_scopes::count(),
+        "blocked_txs": rpc::blocked_tx_count(),
+        "identity_public_key": identity::public_key(),
+    }))
+}
+
+/// POST /admin/reload
+async fn reload_config(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Value>) {
+    match reload::reload_and_log(&state.config, "admin_api") {
+        Ok(summary) => (
+            StatusCode::OK,
+            Json(json!({
+                "reloaded": true,
+                "applied": summary.applied,
+                "ignored": summary.ignored,
+            })),
+        ),
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": format!("{:#}", e) })),
+        ),
+    }
+}
+
+/// POST /admin/selectors/refresh
+async fn refresh_selectors(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Value>) {
+    let config = state.config.current();
+    match selectors::load(&config).and_then(|signatures| Ok((signatures, abi_registry::load(&config)?))) {
+        Ok((signatures, abis)) => {
+            info!(signatures, abis, "ADMIN: Function selector directory and ABI registry refreshed");
+            (StatusCode::OK, Json(json!({ "signatures": signatures, "abis": abis })))
+        }
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": format!("{:#}", e) })),
+        ),
+    }
+}
+
+/// POST /admin/counterparties/approve
+async fn approve_counterparty(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<CounterpartyBody>,
+) -> (StatusCode, Json<Value>) {
+    counterparties::approve(&body.agent, &body.counterparty);
+    persist(&state);
+    info!(agent = %body.agent, counterparty = %body.counterparty, "ADMIN: counterparty approved");
+    (
+        StatusCode::OK,
+        Json(json!({
+            "agent": body.agent.to_lowercase(),
+            "counterparty": body.counterparty.to_lowercase(),
+            "approved": true,
+        })),
+    )
+}
+
+/// GET /admin/false-positives
+async fn list_false_positives() -> Json<Value> {
+    Json(json!({ "reports": false_positives::snapshot() }))
+}
+
+/// POST /admin/false-positives/review
+async fn review_false_positive(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<ReviewBody>,
+) -> (StatusCode, Json<Value>) {
+    let Some(report) = false_positives::review(&body.id, body.accept) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "no such report" })));
+    };
+    persist(&state);
+    info!(id = %report.id, accept = body.accept, entries = ?report.entries, "ADMIN: false-positive report reviewed");
+    (StatusCode::OK, Json(json!(report)))
+}
+
+/// GET /admin/approvals
+async fn list_approvals() -> Json<Value> {
+    Json(json!({ "requests": protection::list() }))
+}
+
+/// POST /admin/approvals/review
+async fn review_approval(Json(body): Json<ReviewBody>) -> (StatusCode, Json<Value>) {
+    let Some(request) = protection::review(&body.id, body.accept) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "no such pending request" })));
+    };
+    warn!(id = %request.id, engine = %request.engine, accept = body.accept, "ADMIN: held request reviewed");
+    (StatusCode::OK, Json(json!(request)))
+}
+
+/// GET /admin/iocs/stix
+async fn export_stix(Query(query): Query<ExportQuery>) -> (StatusCode, Json<Value>) {
+    match ioc_store::load(query.added_after, 0, query.limit) {
+        Ok(page) => {
+            let iocs: Vec<_> = page.into_iter().map(|(_, ioc)| ioc).collect();
+            (StatusCode::OK, Json(ioc_store::bundle(&iocs)))
+        }
+        Err(e) => (StatusCode::NOT_FOUND, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// GET /admin/iocs/taxii/objects
+async fn export_taxii(Query(query): Query<ExportQuery>) -> Response {
+    let cursor = match query.next.as_deref().map(str::parse::<i64>) {
+        None => 0,
+        Some(Ok(cursor)) => cursor,
+        Some(Err(_)) => {
+            return (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid next cursor" }))).into_response()
+        }
+    };
+    match ioc_store::load(query.added_after, cursor, query.limit) {
+        Ok(page) => (
+            [(header::CONTENT_TYPE, ioc_store::TAXII_MEDIA_TYPE)],
+            Json(ioc_store::envelope(&page, query.limit)),
+        )
+            .into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// GET /admin/fees
+async fn list_fees(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let default_chain = state.config.current().default_chain_id();
+    let owed: Vec<Value> = fee::owed()
+        .into_iter()
+        .map(|(chain, agent, asset, amount)| {
+            json!({
+                "chain_id": chain.unwrap_or(default_chain),
+                "agent": agent,
+                "asset": asset,
+                "owed": amount.to_string(),
+            })
+        })
+        .collect();
+    Json(json!({ "fees": owed }))
+}
+
+/// POST /admin/fees/settle
+async fn settle_fees(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let config = state.config.current();
+    let settled: Vec<Value> = fee::settle(&config, state.state_store.as_ref())
+        .await
+        .into_iter()
+        .map(|(chain, agent, asset, amount, result)| {
+            let mut entry = json!({
+                "chain_id": chain.unwrap_or(config.default_chain_id()),
+                "agent": agent,
+                "asset": asset,
+                "fee": amount.to_string(),
+            });
+            match result {
+                Ok(tx_hash) => entry["tx_hash"] = json!(tx_hash),
+                Err(error) => entry["error"] = json!(error),
+            }
+            entry
+        })
+        .collect();
+    persist(&state);
+    info!(attempts = settled.len(), "ADMIN: protocol fees settled");
+    Json(json!({ "settled": settled }))
+}
+
+/// GET /admin/fees/report
+async fn fee_report(Query(query): Query<FeeReportQuery>) -> (StatusCode, Json<Value>) {
+    match fee_ledger::report_for(&query.period) {
+        Ok(report) => (StatusCode::OK, Json(report)),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// GET /admin/fees/export.csv
+async fn export_fees(Query(query): Query<FeeExportQuery>) -> Response {
+    match fee_ledger::load(query.since, query.until) {
+        Ok(entries) => ([(header::CONTENT_TYPE, "text/csv")], fee_ledger::csv(&entries)).into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// GET /admin/audit/export
+async fn export_audit(Query(query): Query<AuditExportQuery>) -> Response {
+    match audit::export(query.since, query.limit) {
+        Ok(lines) => {
+            let body = lines.into_iter().map(|l| l + "\n").collect::<String>();
+            ([(header::CONTENT_TYPE, "application/x-ndjson")], body).into_response()
+        }
+        Err(e) => (StatusCode::NOT_FOUND, Json(json!({ "error": e.to_string() }))).into_response(),
+    }
+}
+
+/// GET /admin/audit/verify
+async fn verify_audit(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Value>) {
+    match audit::verify(&state.config.current()) {
+        Ok(report) => (StatusCode::OK, Json(json!({ "valid": true, "report": report }))),
+        Err(e) => (StatusCode::OK, Json(json!({ "valid": false, "error": format!("{e:#}") }))),
+    }
+}
+
+/// POST /admin/paymaster/reset
+async fn reset_paymaster(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let was_severed = rpc::is_paymaster_severed();
+    rpc::reset_paymaster_sever();
+    persist(&state);
+    info!(was_severed, "ADMIN: Paymaster sever reset");
+    Json(json!({ "paymaster_severed": false, "was_severed": was_severed }))
+}
+
+/// POST /admin/session-keys/revoke
+async fn revoke_session_key(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<SessionKeyBody>,
+) -> Json<Value> {
+    rpc::revoke_session_key(&body.scoped_key(&state.config.current()));
+    persist(&state);
+    info!(session_key = %body.session_key, "ADMIN: session key revoked");
+    Json(json!({ "session_key": body.session_key.to_lowercase(), "revoked": true }))
+}
+
+/// POST /admin/session-keys/unrevoke
+async fn unrevoke_session_key(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<SessionKeyBody>,
+) -> (StatusCode, Json<Value>) {
+    if !rpc::unrevoke_session_key(&body.scoped_key(&state.config.current())) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "session key is not revoked" })),
+        );
+    }
+    persist(&state);
+    info!(session_key = %body.session_key, "ADMIN: session key un-revoked");
+    (
+        StatusCode::OK,
+        Json(json!({ "session_key": body.session_key.to_lowercase(), "revoked": false })),
+    )
+}
+
+/// POST /admin/session-keys/scope
+async fn scope_session_key(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<SessionScopeBody>,
+) -> (StatusCode, Json<Value>) {
+    if let Err(e) = body.scope.validate() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "error": e })));
+    }
+    if !session_scopes::register(&body.key.scoped_key(&state.config.current()), body.scope.clone()) {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": "session scope table is full" })),
+        );
+    }
+    persist(&state);
+    info!(session_key = %body.key.session_key, scope = ?body.scope, "ADMIN: session key scoped");
+    (
+        StatusCode::OK,
+        Json(json!({ "session_key": body.key.session_key.to_lowercase(), "scoped": true })),
+    )
+}
+
+/// POST /admin/session-keys/unscope
+async fn unscope_session_key(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<SessionKeyBody>,
+) -> (StatusCode, Json<Value>) {
+    if !session_scopes::remove(&body.scoped_key(&state.config.current())) {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "session key has no scope" })),
+        );
+    }
+    persist(&state);
+    info!(session_key = %body.session_key, "ADMIN: session key scope removed");
+    (
+        StatusCode::OK,
+        Json(json!({ "session_key": body.session_key.to_lowercase(), "scoped": false })),
+    )
+}
+
+/// POST /admin/session-keys/rotate
+async fn rotate_session_key(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<RotateBody>,
+) -> (StatusCode, Json<Value>) {
+    match rotate(&state, &body) {
+        Ok(result) => (StatusCode::OK, Json(result)),
+        Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "error": e }))),
+    }
+}
+
+/// `plimsoll_rotateSessionKey`: the rotate route over JSON-RPC,
+/// authenticated by `admin_token` instead of an agent key.
+pub fn rotate_session_key_rpc(state: &AppState, admin_token: Option<&str>, req: JsonRpcRequest) -> JsonRpcResponse {
+    if authorize_admin_token(&state.config.current(), admin_token.unwrap_or("")).is_err() {
+        warn!(method = ROTATE_SESSION_KEY_METHOD, "ADMIN: rejected request with invalid token");
+        return JsonRpcResponse::error(req.id, -32001, "Unauthorized: missing or invalid admin token".into());
+    }
+    let body = match req.params.get(0).cloned().map(serde_json::from_value::<RotateBody>) {
+        Some(Ok(body)) => body,
+        Some(Err(e)) => return JsonRpcResponse::error(req.id, -32602, format!("Invalid params: {e}")),
+        None => {
+            return JsonRpcResponse::error(
+                req.id,
+                -32602,
+                format!("Invalid params: {ROTATE_SESSION_KEY_METHOD} expects [{{old_session_key, new_session_key, ..}}]"),
+            )
+        }
+    };
+    match rotate(state, &body) {
+        Ok(result) => JsonRpcResponse::success(req.id, result),
+        Err(e) => JsonRpcResponse::error(req.id, -32602, e),
+    }
+}
+
+/// `plimsoll_getFeeReport(period)` over JSON-RPC, for the admin.
+pub fn fee_report_rpc(state: &AppState, admin_token: Option<&str>, req: JsonRpcRequest) -> JsonRpcResponse {
+    if authorize_admin_token(&state.config.current(), admin_token.unwrap_or("")).is_err() {
+        warn!(method = FEE_REPORT_METHOD, "ADMIN: rejected request with invalid token");
+        return JsonRpcResponse::error(req.id, -32001, "Unauthorized: missing or invalid admin token".into());
+    }
+    if !fee_ledger::is_open() {
+        return JsonRpcResponse::error(
+            req.id,
+            FEE_LEDGER_OFF_CODE,
+            format!("{FEE_REPORT_METHOD} is unavailable: no fee ledger (fee_ledger_path)"),
+        );
+    }
+    let period = req.params.get(0)
+        .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+        .unwrap_or_else(default_fee_period);
+    if fee_ledger::period_start(&period, 0).is_none() {
+        return JsonRpcResponse::error(
+            req.id,
+            -32602,
+            format!("Invalid params: {FEE_REPORT_METHOD}: invalid period '{period}' (day, week, month, all or seconds)"),
+        );
+    }
+    match fee_ledger::report_for(&period) {
+        Ok(report) => JsonRpcResponse::success(req.id, report),
+        Err(e) => JsonRpcResponse::error(req.id, -32603, format!("Internal error: {e}")),
+    }
+}
+
+/// Revoke the old key, carry its scope (or the given one) over to the new
+/// key, and build the on-chain rotation if asked.
+fn rotate(state: &AppState, body: &RotateBody) -> Result<Value, String> {
+    body.scope.as_ref().map_or(Ok(()), SessionScope::validate)?;
+    let transactions = match &body.onchain {
+        Some(rotation) => session_scopes::rotation_transactions(rotation, &body.old_session_key, &body.new_session_key)?,
+        None => Vec::new(),
+    };
+    let config = state.config.current();
+    let old_key = body.key(&body.old_session_key).scoped_key(&config);
+    let new_key = body.key(&body.new_session_key).scoped_key(&config);
+    if rpc::is_session_revoked(&new_key) {
+        return Err("new session key is revoked".into());
+    }
+    let scope = session_scopes::rotate(&old_key, &new_key, body.scope.clone())?;
+    rpc::revoke_session_key(&old_key);
+    persist(state);
+    info!(
+        old_session_key = %body.old_session_key,
+        new_session_key = %body.new_session_key,
+        "ADMIN: session key rotated"
+    );
+    Ok(json!({
+        "old_session_key": body.old_session_key.to_lowercase(),
+        "new_session_key": body.new_session_key.to_lowercase(),
+        "revoked": true,
+        "scope": scope,
+        "transactions": transactions,
+    }))
+}
+
+/// POST /admin/blocked-txs/flush
+async fn flush_blocked_txs(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let flushed = rpc::flush_blocked_txs();
+    persist(&state);
+    info!(flushed, "ADMIN: blocked-tx store flushed");
+    Json(json!({ "flushed": flushed }))
+}
+
+/// POST /admin/shadow-mode
+async fn set_shadow_mode(Json(body): Json<ShadowModeBody>) -> Json<Value> {
+    rpc::set_shadow_mode(body.enabled);
+    warn!(enabled = body.enabled, "ADMIN: shadow mode toggled");
+    Json(json!({ "shadow_mode": body.enabled }))
+}
+
+/// GET /admin/shadow-verdicts
+async fn list_shadow_verdicts(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ShadowVerdictsQuery>,
+) -> Json<Value> {
+    let config = state.config.current();
+    Json(json!({
+        "shadow_mode": rpc::is_shadow_mode(),
+        "shadow_engines": shadow::engines(&config).collect::<Vec<_>>(),
+        "verdicts": shadow::list(query.engine.as_deref()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers_with(auth: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            axum::http::header::AUTHORIZATION,
+            HeaderValue::from_str(auth).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_admin_disabled_without_token() {
+        let config = Config::default();
+        assert_eq!(
+            authorize_admin(&config, &headers_with("Bearer anything")),
+            Err(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn test_admin_token_checked() {
+        let config = Config { admin_token: "s3cret".into(), ..Config::default() };
+        assert!(authorize_admin(&config, &headers_with("Bearer s3cret")).is_ok());
+        assert_eq!(
+            authorize_admin(&config, &headers_with("Bearer wrong")),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            authorize_admin(&config, &HeaderMap::new()),
+            Err(StatusCode::UNAUTHORIZED)
+        );
+        assert!(authorize_admin_token(&config, "s3cret").is_ok());
+        assert_eq!(authorize_admin_token(&config, ""), Err(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn test_session_scope_body() {
+        let body: SessionScopeBody = serde_json::from_value(json!({
+            "session_key": "0x5E55000000000000000000000000000000000001",
+            "chain_id": 8453,
+            "selectors": ["0x38ed1739"],
+            "expires_at": 1_700_000_000u64,
+        }))
+        .unwrap();
+        assert_eq!(body.key.scoped_key(&Config::default()), "8453:0x5e55000000000000000000000000000000000001");
+        assert_eq!(body.scope.selectors, vec!["0x38ed1739".to_string()]);
+        assert_eq!(body.scope.expires_at, 1_700_000_000);
+        assert!(body.scope.targets.is_empty());
+    }
+}
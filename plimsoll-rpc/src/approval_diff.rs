@@ -0,0 +1,255 @@
+//! Approval-diff engine: allowance increases in the simulated execution.
+//!
+//! `approvals` judges the calldata the agent signs. A router, a Safe module
+//! or any contract further down the call chain can raise the agent's
+//! allowances where calldata screening never looks. This engine works from
+//! what the simulation actually did:
+//!
+//!   - the `Approval` / `ApprovalForAll` events the portfolio emitted
+//!     (`SimulationResult::approval_deltas`),
+//!   - allowance-mapping writes in the `prestateTracer` storage diff. A
+//!     written slot is attributed to `allowance[owner][spender]` by
+//!     recomputing Solidity's `keccak(spender . keccak(owner . base))` for
+//!     every portfolio owner, every address seen in the call trace, and
+//!     mapping bases 0–20 plus OpenZeppelin 5's namespaced ERC-20 storage.
+//!     Tokens that change allowances without an event are caught this way,
+//!     and an `approve` that lowers an allowance is recognised as such.
+//!
+//! With `block_approval_changes` on, any increase to a spender outside
+//! `approval_router_allowlist` blocks.
+
+use crate::abi;
+use crate::config::Config;
+use crate::portfolio;
+use crate::types::{CallFrame, SimulationResult};
+use alloy_primitives::{keccak256, U256};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Highest plain storage slot tried as an allowance-mapping base.
+const MAX_MAPPING_BASE: u64 = 20;
+
+/// OpenZeppelin 5 `ERC20Storage` location (ERC-7201,
+/// `openzeppelin.storage.ERC20`); `_allowances` is its second member.
+const OZ_ERC20_STORAGE: &str = "52c63247e1f47db19d5ce0460030c497f067ca4cebf71ba98eeadabe20bace00";
+
+/// An allowance (or operator approval) the simulation raised.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllowanceIncrease {
+    pub owner: String,
+    pub token: String,
+    pub spender: String,
+    /// Value before, when the storage write was seen.
+    pub before: Option<U256>,
+    /// Value after; `None` for an operator approval seen only as an event.
+    pub after: Option<U256>,
+}
+
+/// Storage slot of `mapping(address => mapping(address => _))[owner][spender]`
+/// declared at `base`.
+pub fn allowance_slot(owner: &str, spender: &str, base: U256) -> Option<U256> {
+    let owner = abi::encode_address(owner).ok()?;
+    let spender = abi::encode_address(spender).ok()?;
+    let inner = keccak256([owner, base.to_be_bytes::<32>()].concat());
+    Some(U256::from_be_bytes(keccak256([spender, inner.0].concat()).0))
+}
+
+fn mapping_bases() -> Vec<U256> {
+    let oz = U256::from_str_radix(OZ_ERC20_STORAGE, 16).unwrap_or_default() + U256::from(1);
+    (0..=MAX_MAPPING_BASE).map(U256::from).chain([oz]).collect()
+}
+
+/// Every address the trace touched: callers, callees, address-shaped
+/// calldata words and indexed event topics.
+fn addresses(frame: &CallFrame, out: &mut BTreeSet<String>) {
+    out.insert(frame.from.to_lowercase());
+    out.insert(frame.to.to_lowercase());
+    let input = hex::decode(frame.input.trim_start_matches("0x")).unwrap_or_default();
+    let words = input.get(4..).unwrap_or_default().chunks_exact(32);
+    let topics = frame.logs.iter().flat_map(|log| log.topics.iter()).filter_map(|t| {
+        hex::decode(t.trim_start_matches("0x")).ok()
+    });
+    for word in words.map(<[u8]>::to_vec).chain(topics) {
+        if word.len() == 32 && word[..12].iter().all(|b| *b == 0) && word[12..].iter().any(|b| *b != 0) {
+            out.insert(format!("0x{}", hex::encode(&word[12..])));
+        }
+    }
+    for call in &frame.calls {
+        addresses(call, out);
+    }
+}
+
+/// Allowance increases of the portfolio `accounts` (lowercase) in a
+/// finished simulation.
+pub fn increases(accounts: &[String], sim_result: &SimulationResult) -> Vec<AllowanceIncrease> {
+    let mut spenders = BTreeSet::new();
+    if let Some(trace) = &sim_result.call_trace {
+        addresses(trace, &mut spenders);
+    }
+    spenders.extend(sim_result.approval_deltas.iter().map(|d| d.spender.to_lowercase()));
+
+    let bases = mapping_bases();
+    let mut slots = HashMap::new();
+    for owner in accounts {
+        for spender in &spenders {
+            for base in &bases {
+                if let Some(slot) = allowance_slot(owner, spender, *base) {
+                    slots.insert(slot, (owner.clone(), spender.clone()));
+                }
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut written = HashSet::new();
+    for change in &sim_result.storage_changes {
+        let Some((owner, spender)) = slots.get(&change.slot) else {
+            continue;
+        };
+        written.insert((change.account.clone(), owner.clone(), spender.clone()));
+        if change.after > change.before {
+            found.push(AllowanceIncrease {
+                owner: owner.clone(),
+                token: change.account.clone(),
+                spender: spender.clone(),
+                before: Some(change.before),
+                after: Some(change.after),
+            });
+        }
+    }
+    // Events whose storage write was seen have been judged by value.
+    for delta in sim_result.approval_deltas.iter().filter(|d| d.approved) {
+        let key = (delta.token.to_lowercase(), delta.owner.to_lowercase(), delta.spender.to_lowercase());
+        if !written.contains(&key) {
+            found.push(AllowanceIncrease {
+                owner: key.1,
+                token: key.0,
+                spender: key.2,
+                before: None,
+                after: delta.amount,
+            });
+        }
+    }
+    found
+}
+
+/// Block a simulation that raises an allowance for a spender outside
+/// `approval_router_allowlist`.
+pub fn check(config: &Config, from: &str, sim_result: &SimulationResult) -> Result<(), String> {
+    if !config.block_approval_changes {
+        return Ok(());
+    }
+    let routers: Vec<String> = config
+        .approval_router_allowlist
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let accounts = portfolio::accounts(config, from);
+    match increases(&accounts, sim_result).into_iter().find(|i| !routers.contains(&i.spender)) {
+        Some(i) => {
+            let show = |v: Option<U256>| v.map_or("?".to_string(), |v| v.to_string());
+            let change = match (i.before, i.after) {
+                (None, None) => "operator approval".to_string(),
+                (before, after) => format!("{} → {}", show(before), show(after)),
+            };
+            Err(format!(
+                "PLIMSOLL APPROVAL DIFF: simulation raises {}'s allowance on {} for {} ({}) — \
+                 spender is not an allowlisted router",
+                i.owner, i.token, i.spender, change
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ApprovalDelta, StorageChange};
+
+    const AGENT: &str = "0xa9e0000000000000000000000000000000000001";
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const SPENDER: &str = "0x2222222222222222222222222222222222222222";
+    const ROUTER: &str = "0x3fc91a3afd70395cd496c647d5a6cc9d4b2b7fad";
+
+    fn sim(storage_changes: Vec<StorageChange>, approval_deltas: Vec<ApprovalDelta>) -> SimulationResult {
+        SimulationResult {
+            success: true,
+            gas_used: 50_000,
+            balance_before: 0,
+            balance_after: 0,
+            approval_changes: vec![],
+            loss_pct: 0.0,
+            error: None,
+            simulated_block: 1,
+            target_codehash: String::new(),
+            non_deterministic: false,
+            impl_slot_value: String::new(),
+            implementation_codehash: String::new(),
+            call_trace: Some(CallFrame {
+                call_type: "CALL".into(),
+                from: AGENT.into(),
+                to: TOKEN.into(),
+                // approve(SPENDER, _)
+                input: format!("0x095ea7b3{:0>64}{:0>64}", &SPENDER[2..], "ff"),
+                ..CallFrame::default()
+            }),
+            asset_deltas: vec![],
+            approval_deltas,
+            storage_changes,
+        }
+    }
+
+    fn write(spender: &str, base: u64, before: u64, after: u64) -> StorageChange {
+        StorageChange {
+            account: TOKEN.into(),
+            slot: allowance_slot(AGENT, spender, U256::from(base)).unwrap(),
+            before: U256::from(before),
+            after: U256::from(after),
+        }
+    }
+
+    fn event(spender: &str, amount: u64) -> ApprovalDelta {
+        ApprovalDelta {
+            owner: AGENT.into(),
+            token: TOKEN.into(),
+            spender: spender.into(),
+            amount: Some(U256::from(amount)),
+            approved: amount > 0,
+        }
+    }
+
+    #[test]
+    fn test_allowance_slot_matches_solidity_layout() {
+        let slot = allowance_slot(AGENT, SPENDER, U256::from(1)).unwrap();
+        assert_eq!(
+            format!("{slot:064x}"),
+            "d5231116cb2920669405f5e285f5d061d761785aba8f299c6c24b32a7cc2bd45"
+        );
+    }
+
+    #[test]
+    fn test_silent_storage_increase_blocked() {
+        // No event: the allowance write alone gives it away.
+        let err = check(&Config::default(), AGENT, &sim(vec![write(SPENDER, 2, 0, 255)], vec![])).unwrap_err();
+        assert!(err.contains("PLIMSOLL APPROVAL DIFF"));
+        assert!(err.contains("for 0x2222222222222222222222222222222222222222 (0 → 255)"));
+    }
+
+    #[test]
+    fn test_event_only_increase_blocked() {
+        let err = check(&Config::default(), AGENT, &sim(vec![], vec![event(SPENDER, 100)])).unwrap_err();
+        assert!(err.contains("(? → 100)"));
+    }
+
+    #[test]
+    fn test_decrease_router_and_disabled_allowed() {
+        // approve() that lowers the allowance: the storage diff overrides the event.
+        let lowered = sim(vec![write(SPENDER, 1, 500, 100)], vec![event(SPENDER, 100)]);
+        assert!(check(&Config::default(), AGENT, &lowered).is_ok());
+        let to_router = sim(vec![write(ROUTER, 1, 0, 100)], vec![event(ROUTER, 100)]);
+        assert!(check(&Config::default(), AGENT, &to_router).is_ok());
+        let off = Config { block_approval_changes: false, ..Config::default() };
+        assert!(check(&off, AGENT, &sim(vec![], vec![event(SPENDER, 100)])).is_ok());
+    }
+}
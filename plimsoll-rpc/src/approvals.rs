@@ -0,0 +1,175 @@
+//! Unlimited-approval interception on the send path.
+//!
+//! Three calls hand a third party control of the agent's assets in a single
+//! transaction, with no transfer for balance physics to see:
+//!
+//!   - ERC-20 `approve` / `increaseAllowance` for an unlimited amount
+//!     (MAX_UINT160 or above, as for Permit2),
+//!   - `setApprovalForAll(operator, true)` — every NFT (ERC-721) or every
+//!     id (ERC-1155) of the collection,
+//!   - ERC-4494 `permit(spender, tokenId, deadline, sig)` — an NFT
+//!     approval submitted from a signature.
+//!
+//! All three get the same severity: blocked unless the spender / operator
+//! is Permit2 (whose own grants are screened as signatures) or in
+//! `approval_operator_allowlist`. Revocations (`false`, zero) and bounded
+//! allowances pass. Off by default: DEX routers differ per chain, so the
+//! allowlist has to be written for the chains the agent uses.
+
+use crate::abi;
+use crate::config::Config;
+use crate::permit2;
+use std::fmt;
+
+/// `approve(address,uint256)`
+const APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+/// `increaseAllowance(address,uint256)`
+const INCREASE_ALLOWANCE: [u8; 4] = [0x39, 0x50, 0x93, 0x51];
+/// `setApprovalForAll(address,bool)`
+const SET_APPROVAL_FOR_ALL: [u8; 4] = [0xa2, 0x2c, 0xb4, 0x65];
+/// ERC-4494 `permit(address,uint256,uint256,bytes)`
+const NFT_PERMIT: [u8; 4] = [0x74, 0x5a, 0x41, 0xbc];
+
+/// What a call grants.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Grant {
+    /// ERC-20 allowance of MAX_UINT160 or more.
+    UnlimitedAllowance { spender: String },
+    /// `setApprovalForAll(operator, true)`.
+    OperatorForAll { operator: String },
+    /// ERC-4494 NFT permit.
+    NftPermit { spender: String, token_id: String },
+}
+
+impl Grant {
+    /// The address gaining control.
+    pub fn grantee(&self) -> &str {
+        match self {
+            Grant::UnlimitedAllowance { spender } => spender,
+            Grant::OperatorForAll { operator } => operator,
+            Grant::NftPermit { spender, .. } => spender,
+        }
+    }
+}
+
+impl fmt::Display for Grant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Grant::UnlimitedAllowance { spender } => write!(f, "approve({spender}, MAX_UINT)"),
+            Grant::OperatorForAll { operator } => write!(f, "setApprovalForAll({operator}, true)"),
+            Grant::NftPermit { spender, token_id } => {
+                write!(f, "ERC-4494 permit({spender}, tokenId {token_id})")
+            }
+        }
+    }
+}
+
+/// The unbounded grant `data` makes, if any.
+pub fn decode(data: &[u8]) -> Option<Grant> {
+    let selector = abi::selector(data)?;
+    let args = &data[4..];
+    match selector {
+        APPROVE | INCREASE_ALLOWANCE => {
+            let spender = abi::address(args, 0).ok()?;
+            let amount = abi::uint(args, 1).ok()?;
+            permit2::is_unlimited(amount).then_some(Grant::UnlimitedAllowance { spender })
+        }
+        SET_APPROVAL_FOR_ALL => {
+            let operator = abi::address(args, 0).ok()?;
+            let approved = !abi::uint(args, 1).ok()?.is_zero();
+            approved.then_some(Grant::OperatorForAll { operator })
+        }
+        NFT_PERMIT => Some(Grant::NftPermit {
+            spender: abi::address(args, 0).ok()?,
+            token_id: abi::uint(args, 1).ok()?.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Block an unbounded grant on `token` to a grantee other than Permit2
+/// and outside `approval_operator_allowlist`.
+pub fn check(config: &Config, token: &str, data: &[u8]) -> Result<(), String> {
+    if !config.block_unlimited_approvals {
+        return Ok(());
+    }
+    let Some(grant) = decode(data) else {
+        return Ok(());
+    };
+    let allowed = grant.grantee().eq_ignore_ascii_case(permit2::PERMIT2)
+        || config
+            .approval_operator_allowlist
+            .split(',')
+            .map(str::trim)
+            .any(|a| a.eq_ignore_ascii_case(grant.grantee()));
+    if allowed {
+        return Ok(());
+    }
+    Err(format!(
+        "PLIMSOLL APPROVAL: {} on {} hands {} unbounded control of the agent's assets",
+        grant, token, grant.grantee()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::{encode_address, encode_u256, encode_uint};
+    use alloy_primitives::U256;
+
+    const TOKEN: &str = "0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d";
+    const OPERATOR: &str = "0x6666666666666666666666666666666666666666";
+
+    fn call(selector: [u8; 4], second: [u8; 32]) -> Vec<u8> {
+        let mut data = selector.to_vec();
+        data.extend(encode_address(OPERATOR).unwrap());
+        data.extend(second);
+        data
+    }
+
+    #[test]
+    fn test_unbounded_grants_blocked() {
+        let config = Config { block_unlimited_approvals: true, ..Config::default() };
+        for data in [
+            call(APPROVE, encode_u256(U256::MAX)),
+            call(INCREASE_ALLOWANCE, encode_u256(U256::MAX)),
+            call(SET_APPROVAL_FOR_ALL, encode_uint(1)),
+            call(NFT_PERMIT, encode_uint(42)),
+        ] {
+            let err = check(&config, TOKEN, &data).unwrap_err();
+            assert!(err.starts_with("PLIMSOLL APPROVAL:"), "{err}");
+            assert!(err.contains(OPERATOR));
+        }
+        let err = check(&config, TOKEN, &call(SET_APPROVAL_FOR_ALL, encode_uint(1))).unwrap_err();
+        assert!(err.contains("setApprovalForAll(0x6666666666666666666666666666666666666666, true)"));
+    }
+
+    #[test]
+    fn test_bounded_grants_and_revocations_allowed() {
+        let config = Config { block_unlimited_approvals: true, ..Config::default() };
+        assert!(check(&config, TOKEN, &call(APPROVE, encode_uint(1_000_000))).is_ok());
+        assert!(check(&config, TOKEN, &call(SET_APPROVAL_FOR_ALL, encode_uint(0))).is_ok());
+        assert!(check(&config, TOKEN, &[0xa9, 0x05, 0x9c, 0xbb]).is_ok());
+    }
+
+    #[test]
+    fn test_allowlisted_operator_and_disabled() {
+        let data = call(SET_APPROVAL_FOR_ALL, encode_uint(1));
+        let config = Config {
+            block_unlimited_approvals: true,
+            approval_operator_allowlist: OPERATOR.to_uppercase().replace("0X", "0x"),
+            ..Config::default()
+        };
+        assert!(check(&config, TOKEN, &data).is_ok());
+        assert!(check(&Config::default(), TOKEN, &data).is_ok());
+    }
+
+    #[test]
+    fn test_permit2_allowed_without_allowlist() {
+        let config = Config { block_unlimited_approvals: true, ..Config::default() };
+        let mut data = APPROVE.to_vec();
+        data.extend(encode_address(permit2::PERMIT2).unwrap());
+        data.extend(encode_u256(U256::MAX));
+        assert!(check(&config, TOKEN, &data).is_ok());
+    }
+}
@@ -0,0 +1,443 @@
+//! Append-only, hash-chained audit log.
+//!
+//! With `audit_log_path` set, every decision is appended as one JSON line
+//! for incident forensics and compliance review:
+//!
+//! ```text
+//! {"seq":42,"at":1700000000,"kind":"decision","request_hash":"0x..","method":"eth_sendTransaction",
+//!  "engines":["engine0","simulation","physics"],"verdict":"blocked","engine":"physics",
+//!  "reason":"PLIMSOLL PHYSICS: ..","config_version":"0x..","prev":"0x..","hash":"0x.."}
+//! ```
+//!
+//! - `request_hash` is the request's method + params fingerprint (the one
+//!   `protection` matches resubmissions on); `config_version` a hash of
+//!   the live config.
+//! - `verdict` is `forwarded`, `blocked`, `would_block` (shadow mode) or
+//!   `dry_run`.
+//! - `hash` = `keccak256("<seq>:<at>:<prev>:" ‖ JSON of the kind-specific
+//!   fields)`, and `prev` is the previous entry's `hash`: editing, dropping
+//!   or reordering a line breaks the chain.
+//!
+//! Every `audit_checkpoint_secs` with new decisions, a `checkpoint` entry
+//! signs `AUDIT_SIGNING_DOMAIN ‖ prev` with the proxy identity (`identity`)
+//! and the file is synced — a truncated tail or a rewritten chain can't
+//! carry a valid signature. The chain resumes across restarts, so the log
+//! requires a persistent `proxy_identity_key_path`.
+//!
+//! The signer named in a checkpoint is only a hint: verification pins it
+//! to the proxy's identity or a key in `audit_trusted_signers` (rotated-out
+//! identities). A chain re-signed with any other key fails, however
+//! consistent it is.
+//!
+//! `GET /admin/audit/export` returns the lines, `GET /admin/audit/verify`
+//! checks the whole file.
+
+use crate::config::{self, Config};
+use crate::identity;
+use crate::reload::SharedConfigHandle;
+use alloy_primitives::keccak256;
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+
+/// Prefix of every signed checkpoint message.
+pub const AUDIT_SIGNING_DOMAIN: &[u8] = b"plimsoll-audit:v1:";
+
+/// `prev` of the first entry.
+const GENESIS: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Most lines returned per export.
+pub const MAX_EXPORT: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEvent {
+    Decision {
+        request_hash: String,
+        method: String,
+        engines: Vec<String>,
+        verdict: String,
+        engine: Option<String>,
+        reason: Option<String>,
+        config_version: String,
+    },
+    Checkpoint {
+        /// Entries in the log before this checkpoint.
+        records: u64,
+        signer: String,
+        signature: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    pub prev: String,
+    pub hash: String,
+}
+
+/// Result of `verify`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Verification {
+    pub records: u64,
+    pub checkpoints: u64,
+    pub head: String,
+    /// `seq` of the last checkpoint; decisions after it are not signed yet.
+    pub last_checkpoint: Option<u64>,
+}
+
+struct AuditLog {
+    file: File,
+    path: String,
+    /// `seq` of the next entry.
+    next_seq: u64,
+    head: String,
+    /// `next_seq` at the last checkpoint.
+    checkpointed: u64,
+}
+
+static LOG: OnceLock<Mutex<AuditLog>> = OnceLock::new();
+
+static CONFIG_VERSION: RwLock<String> = RwLock::new(String::new());
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn digest(seq: u64, at: u64, prev: &str, event: &AuditEvent) -> Result<String> {
+    let fields = serde_json::to_string(event)?;
+    Ok(format!("0x{}", hex::encode(keccak256(format!("{seq}:{at}:{prev}:{fields}")))))
+}
+
+/// Record the version of the config now live.
+pub fn set_config_version(config: &Config) {
+    let version = serde_json::to_vec(config)
+        .map(|json| format!("0x{}", hex::encode(&keccak256(json)[..16])))
+        .unwrap_or_default();
+    match CONFIG_VERSION.write() {
+        Ok(mut v) => *v = version,
+        Err(poisoned) => *poisoned.into_inner() = version,
+    }
+}
+
+fn config_version() -> String {
+    CONFIG_VERSION.read().map(|v| v.clone()).unwrap_or_default()
+}
+
+/// Open the log at `audit_log_path` (no-op when unset), resuming its chain.
+pub fn open(config: &Config) -> Result<()> {
+    if config.audit_log_path.is_empty() {
+        return Ok(());
+    }
+    let path = &config.audit_log_path;
+    let (next_seq, head) = match File::open(path) {
+        Ok(file) => {
+            let last = BufReader::new(file)
+                .lines()
+                .map_while(|l| l.ok())
+                .filter(|l| !l.trim().is_empty())
+                .last();
+            match last {
+                Some(line) => {
+                    let entry: AuditEntry = serde_json::from_str(&line)
+                        .with_context(|| format!("The last line of the audit log {path} is corrupt"))?;
+                    (entry.seq + 1, entry.hash)
+                }
+                None => (0, GENESIS.to_string()),
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (0, GENESIS.to_string()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read the audit log {path}")),
+    };
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open the audit log {path}"))?;
+    let log = AuditLog { file, path: path.clone(), next_seq, head, checkpointed: next_seq };
+    if LOG.set(Mutex::new(log)).is_err() {
+        anyhow::bail!("Audit log already open");
+    }
+    info!(path = %path, next_seq, "Audit log open");
+    Ok(())
+}
+
+fn append(log: &mut AuditLog, event: AuditEvent) -> Result<()> {
+    let at = now();
+    let hash = digest(log.next_seq, at, &log.head, &event)?;
+    let entry = AuditEntry { seq: log.next_seq, at, event, prev: log.head.clone(), hash };
+    writeln!(log.file, "{}", serde_json::to_string(&entry)?)?;
+    log.next_seq += 1;
+    log.head = entry.hash;
+    Ok(())
+}
+
+/// Record a decision. A no-op while no log is open.
+pub fn record_decision(
+    request_hash: String,
+    method: &str,
+    engines: Vec<String>,
+    verdict: &str,
+    engine: Option<String>,
+    reason: Option<String>,
+) {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let event = AuditEvent::Decision {
+        request_hash,
+        method: method.to_string(),
+        engines,
+        verdict: verdict.to_string(),
+        engine,
+        reason,
+        config_version: config_version(),
+    };
+    let mut log = log.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Err(e) = append(&mut log, event) {
+        error!(error = %e, "Failed to write the audit record");
+    }
+}
+
+/// Sign the chain head if decisions were added since the last checkpoint.
+/// Returns the checkpoint's `seq`.
+pub fn checkpoint() -> Option<u64> {
+    let mut log = LOG.get()?.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if log.next_seq == log.checkpointed {
+        return None;
+    }
+    let event = AuditEvent::Checkpoint {
+        records: log.next_seq,
+        signer: identity::public_key(),
+        signature: identity::sign(AUDIT_SIGNING_DOMAIN, log.head.as_bytes()),
+    };
+    let seq = log.next_seq;
+    if let Err(e) = append(&mut log, event) {
+        error!(error = %e, "Failed to write the audit checkpoint");
+        return None;
+    }
+    if let Err(e) = log.file.sync_data() {
+        error!(error = %e, "Failed to sync the audit log");
+    }
+    log.checkpointed = log.next_seq;
+    Some(seq)
+}
+
+/// Spawn the checkpoint task. Reads the live interval every round.
+pub fn spawn_checkpoint_task(config: SharedConfigHandle) {
+    if LOG.get().is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            let secs = config.current().audit_checkpoint_secs;
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+            if let Some(seq) = checkpoint() {
+                info!(seq, "Audit log checkpoint signed");
+            }
+        }
+    });
+}
+
+fn path() -> Option<String> {
+    LOG.get().map(|log| log.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).path.clone())
+}
+
+/// Lines of the open log from `since` (a `seq`), at most `limit`.
+pub fn export(since: u64, limit: usize) -> Result<Vec<String>> {
+    let path = path().context("No audit log configured")?;
+    let file = File::open(&path).with_context(|| format!("Failed to read the audit log {path}"))?;
+    let mut lines = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: AuditEntry = serde_json::from_str(&line).context("Corrupt audit log line")?;
+        if entry.seq >= since {
+            lines.push(line);
+            if lines.len() >= limit.min(MAX_EXPORT) {
+                break;
+            }
+        }
+    }
+    Ok(lines)
+}
+
+fn verify_signature(signer: &str, signature: &str, head: &str) -> bool {
+    let signer: Option<[u8; 32]> = hex::decode(signer).ok().and_then(|b| b.try_into().ok());
+    let signature: Option<[u8; 64]> = hex::decode(signature).ok().and_then(|b| b.try_into().ok());
+    let (Some(signer), Some(signature)) = (signer, signature) else {
+        return false;
+    };
+    let Ok(signer) = VerifyingKey::from_bytes(&signer) else {
+        return false;
+    };
+    let message = [AUDIT_SIGNING_DOMAIN, head.as_bytes()].concat();
+    signer.verify(&message, &Signature::from_bytes(&signature)).is_ok()
+}
+
+/// Keys checkpoints may be signed with: this proxy's identity and
+/// `audit_trusted_signers`, lowercase hex.
+pub fn pinned_signers(config: &Config) -> Vec<String> {
+    let mut signers = config::parse_list(&config.audit_trusted_signers);
+    signers.push(identity::public_key());
+    signers
+}
+
+/// Check the chain and every checkpoint signature of `lines`. Checkpoints
+/// must be signed by one of `pinned` (hex public keys).
+pub fn verify_lines<'a>(lines: impl IntoIterator<Item = &'a str>, pinned: &[String]) -> Result<Verification> {
+    let mut report = Verification { head: GENESIS.to_string(), ..Verification::default() };
+    for line in lines.into_iter().filter(|l| !l.trim().is_empty()) {
+        let entry: AuditEntry = serde_json::from_str(line)
+            .with_context(|| format!("entry {}: not an audit record", report.records))?;
+        if entry.seq != report.records {
+            anyhow::bail!("entry {}: out of sequence (seq {})", report.records, entry.seq);
+        }
+        if entry.prev != report.head {
+            anyhow::bail!("entry {}: does not chain to the previous entry", entry.seq);
+        }
+        if digest(entry.seq, entry.at, &entry.prev, &entry.event)? != entry.hash {
+            anyhow::bail!("entry {}: hash mismatch", entry.seq);
+        }
+        if let AuditEvent::Checkpoint { records, signer, signature } = &entry.event {
+            if !pinned.iter().any(|p| p.eq_ignore_ascii_case(signer)) {
+                anyhow::bail!("entry {}: checkpoint signed by {}, not a pinned key", entry.seq, signer);
+            }
+            if *records != entry.seq || !verify_signature(signer, signature, &entry.prev) {
+                anyhow::bail!("entry {}: invalid checkpoint", entry.seq);
+            }
+            report.checkpoints += 1;
+            report.last_checkpoint = Some(entry.seq);
+        }
+        report.records += 1;
+        report.head = entry.hash;
+    }
+    Ok(report)
+}
+
+/// Check the open log against the pinned signers.
+pub fn verify(config: &Config) -> Result<Verification> {
+    let path = path().context("No audit log configured")?;
+    let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read the audit log {path}"))?;
+    verify_lines(text.lines(), &pinned_signers(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(events: Vec<AuditEvent>) -> Vec<String> {
+        let mut head = GENESIS.to_string();
+        events
+            .into_iter()
+            .enumerate()
+            .map(|(seq, event)| {
+                let seq = seq as u64;
+                let hash = digest(seq, 1_700_000_000, &head, &event).unwrap();
+                let entry = AuditEntry { seq, at: 1_700_000_000, event, prev: head.clone(), hash: hash.clone() };
+                head = hash;
+                serde_json::to_string(&entry).unwrap()
+            })
+            .collect()
+    }
+
+    fn decision(verdict: &str) -> AuditEvent {
+        AuditEvent::Decision {
+            request_hash: "0x01".into(),
+            method: "eth_sendTransaction".into(),
+            engines: vec!["engine0".into(), "physics".into()],
+            verdict: verdict.into(),
+            engine: Some("physics".into()),
+            reason: Some("PLIMSOLL PHYSICS: test".into()),
+            config_version: "0xabc".into(),
+        }
+    }
+
+    #[test]
+    fn test_chain_verifies() {
+        let mut lines = chain(vec![decision("blocked"), decision("forwarded")]);
+        let pinned = pinned_signers(&Config::default());
+        let report = verify_lines(lines.iter().map(String::as_str), &pinned).unwrap();
+        assert_eq!(report.records, 2);
+        assert_eq!(report.last_checkpoint, None);
+
+        // A checkpoint over the head.
+        let head = report.head.clone();
+        let checkpoint = AuditEvent::Checkpoint {
+            records: 2,
+            signer: identity::public_key(),
+            signature: identity::sign(AUDIT_SIGNING_DOMAIN, head.as_bytes()),
+        };
+        let hash = digest(2, 1_700_000_001, &head, &checkpoint).unwrap();
+        let entry = AuditEntry { seq: 2, at: 1_700_000_001, event: checkpoint, prev: head, hash };
+        lines.push(serde_json::to_string(&entry).unwrap());
+        let report = verify_lines(lines.iter().map(String::as_str), &pinned).unwrap();
+        assert_eq!(report.checkpoints, 1);
+        assert_eq!(report.last_checkpoint, Some(2));
+        assert!(lines[0].contains(r#""kind":"decision""#));
+    }
+
+    #[test]
+    fn test_tampering_detected() {
+        let lines = chain(vec![decision("blocked"), decision("forwarded"), decision("forwarded")]);
+        let pinned = pinned_signers(&Config::default());
+
+        let edited = lines[0].replace("blocked", "forwarded");
+        let tampered = [edited.as_str(), lines[1].as_str(), lines[2].as_str()];
+        assert!(verify_lines(tampered, &pinned).unwrap_err().to_string().contains("hash mismatch"));
+
+        let dropped = [lines[0].as_str(), lines[2].as_str()];
+        assert!(verify_lines(dropped, &pinned).is_err());
+
+        // A checkpoint signed over something else.
+        let report = verify_lines(lines.iter().map(String::as_str), &pinned).unwrap();
+        let forged = AuditEvent::Checkpoint {
+            records: 3,
+            signer: identity::public_key(),
+            signature: identity::sign(AUDIT_SIGNING_DOMAIN, GENESIS.as_bytes()),
+        };
+        let hash = digest(3, 1, &report.head, &forged).unwrap();
+        let entry = AuditEntry { seq: 3, at: 1, event: forged, prev: report.head, hash };
+        let forged = serde_json::to_string(&entry).unwrap();
+        let all: Vec<&str> = lines.iter().map(String::as_str).chain([forged.as_str()]).collect();
+        assert!(verify_lines(all, &pinned).unwrap_err().to_string().contains("invalid checkpoint"));
+    }
+
+    #[test]
+    fn test_chain_resigned_with_another_key_rejected() {
+        let mut lines = chain(vec![decision("forwarded")]);
+        let head = verify_lines(lines.iter().map(String::as_str), &[]).unwrap().head;
+
+        // A well-formed checkpoint, but by a key the operator never pinned.
+        let intruder = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let message = [AUDIT_SIGNING_DOMAIN, head.as_bytes()].concat();
+        let checkpoint = AuditEvent::Checkpoint {
+            records: 1,
+            signer: hex::encode(intruder.verifying_key().to_bytes()),
+            signature: hex::encode(ed25519_dalek::Signer::sign(&intruder, &message).to_bytes()),
+        };
+        let hash = digest(1, 1, &head, &checkpoint).unwrap();
+        let entry = AuditEntry { seq: 1, at: 1, event: checkpoint, prev: head, hash };
+        lines.push(serde_json::to_string(&entry).unwrap());
+
+        let pinned = pinned_signers(&Config::default());
+        let err = verify_lines(lines.iter().map(String::as_str), &pinned).unwrap_err();
+        assert!(err.to_string().contains("not a pinned key"), "{err}");
+        // Pinning the key (a rotated-out identity) accepts it.
+        let rotated = Config {
+            audit_trusted_signers: hex::encode(intruder.verifying_key().to_bytes()),
+            ..Config::default()
+        };
+        assert!(verify_lines(lines.iter().map(String::as_str), &pinned_signers(&rotated)).is_ok());
+    }
+}
@@ -0,0 +1,333 @@
+//! Per-agent API key authentication with `from`-address binding.
+//!
+//! Several agents often share one proxy. Without authentication, a
+//! compromised (prompt-injected) agent process can submit transactions or
+//! request signatures on behalf of any other agent's wallet. With agents
+//! configured, every request must present an API key, and every send/sign
+//! request must act for an address bound to that key.
+//!
+//! The key is accepted from (first match wins):
+//!   - `plimsoll-api-key: <key>`
+//!   - `Authorization: Bearer <key>`
+//!   - the URL path: `POST /rpc/<key>` (for clients that can't set headers)
+//!
+//! Raw transactions are bound by their *recovered* signer, not by anything
+//! the client claims.
+//!
+//! Behind the mutual-TLS listener (`tls_client_ca_path`), the connection's
+//! client certificate identifies the agent too: an agent whose
+//! `client_cert` pins a SHA-256 fingerprint authenticates with that
+//! certificate alone, and can no longer authenticate with its API key
+//! without it. A key presented alongside a certificate must belong to the
+//! same agent.
+
+use crate::config::{AgentCredential, Config};
+use crate::eip7702;
+use crate::types::JsonRpcRequest;
+use axum::http::HeaderMap;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::rlp::Rlp;
+
+/// Header carrying the agent API key.
+pub const API_KEY_HEADER: &str = "plimsoll-api-key";
+
+/// Header carrying the admin token on admin JSON-RPC methods
+/// (`Authorization` already carries the agent key).
+pub const ADMIN_TOKEN_HEADER: &str = "plimsoll-admin-token";
+
+/// JSON-RPC error code for a send/sign on an address not bound to the key.
+pub const UNAUTHORIZED_SENDER_CODE: i64 = 4100;
+
+/// Why a request was rejected at the authentication layer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthError {
+    /// No API key presented.
+    MissingKey,
+    /// Key presented but not registered.
+    InvalidKey,
+    /// Client certificate pinned by no agent.
+    UnknownCertificate,
+    /// The agent pins a client certificate, and the connection lacks it.
+    CertificateRequired,
+    /// API key and client certificate belong to different agents.
+    CredentialMismatch,
+}
+
+/// Request extension set by the mutual-TLS listener: the SHA-256
+/// fingerprint of the connection's verified client certificate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientCert(pub String);
+
+/// What a caller presented: an API key, a client certificate, or both.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Credentials {
+    pub api_key: Option<String>,
+    /// Fingerprint of the client certificate (mutual TLS only).
+    pub client_cert: Option<String>,
+    /// `plimsoll-admin-token`, for admin JSON-RPC methods.
+    pub admin_token: Option<String>,
+}
+
+/// The credentials of a request: its API key (see [`presented_key`]) and
+/// client certificate, if any.
+pub fn credentials(headers: &HeaderMap, client_cert: Option<&ClientCert>) -> Credentials {
+    Credentials {
+        api_key: presented_key(headers),
+        client_cert: client_cert.map(|cert| cert.0.clone()),
+        admin_token: presented_admin_token(headers),
+    }
+}
+
+/// A `client_cert` pin or fingerprint in comparable form: lowercase hex,
+/// no colons.
+pub fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint.trim().replace(':', "").to_lowercase()
+}
+
+/// Pull the API key from the request headers.
+pub fn presented_key(headers: &HeaderMap) -> Option<String> {
+    if let Some(key) = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(key.trim().to_string());
+    }
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|k| k.trim().to_string())
+}
+
+/// Pull the admin token from the request headers.
+pub fn presented_admin_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|t| t.trim().to_string())
+}
+
+/// Resolve the calling agent.
+///
+/// `Ok(None)` means authentication is disabled (no agents configured).
+pub fn authenticate<'a>(
+    config: &'a Config,
+    credentials: &Credentials,
+) -> Result<Option<&'a AgentCredential>, AuthError> {
+    if config.agents.is_empty() {
+        return Ok(None);
+    }
+    let by_cert = match credentials.client_cert.as_deref() {
+        Some(fingerprint) => {
+            let fingerprint = normalize_fingerprint(fingerprint);
+            let agent = config
+                .agents
+                .iter()
+                .find(|a| !a.client_cert.is_empty() && normalize_fingerprint(&a.client_cert) == fingerprint)
+                .ok_or(AuthError::UnknownCertificate)?;
+            Some(agent)
+        }
+        None => None,
+    };
+    let by_key = match credentials.api_key.as_deref().filter(|k| !k.is_empty()) {
+        Some(key) => {
+            // Compare against every key so lookup time doesn't leak which prefix matched.
+            let mut found = None;
+            for agent in &config.agents {
+                if constant_time_eq(agent.api_key.as_bytes(), key.as_bytes()) {
+                    found = Some(agent);
+                }
+            }
+            Some(found.ok_or(AuthError::InvalidKey)?)
+        }
+        None => None,
+    };
+    match (by_cert, by_key) {
+        (Some(cert), Some(key)) if cert.name != key.name => Err(AuthError::CredentialMismatch),
+        (Some(agent), _) => Ok(Some(agent)),
+        (None, Some(agent)) if !agent.client_cert.is_empty() => Err(AuthError::CertificateRequired),
+        (None, Some(agent)) => Ok(Some(agent)),
+        (None, None) => Err(AuthError::MissingKey),
+    }
+}
+
+/// The address a send/sign request acts for, lowercased.
+///
+/// - `Ok(None)`: the method doesn't act for an address (reads).
+/// - `Err(_)`: a send/sign method whose signer can't be determined.
+pub fn request_signer(req: &JsonRpcRequest) -> Result<Option<String>, String> {
+    let params = req.params.as_array();
+    let param_str = |idx: usize| -> Result<String, String> {
+        params
+            .and_then(|p| p.get(idx))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_lowercase())
+            .ok_or_else(|| format!("{}: missing signer address in params[{}]", req.method, idx))
+    };
+
+    match req.method.as_str() {
+        "eth_sendTransaction" => params
+            .and_then(|p| p.first())
+            .and_then(|tx| tx.get("from"))
+            .and_then(|v| v.as_str())
+            .map(|s| Some(s.to_lowercase()))
+            .ok_or_else(|| "eth_sendTransaction: missing `from`".to_string()),
+        "eth_sendRawTransaction" => recover_raw_sender(&param_str(0)?).map(Some),
+        "eth_sign" | "eth_signTypedData" | "eth_signTypedData_v3" | "eth_signTypedData_v4" => {
+            param_str(0).map(Some)
+        }
+        "personal_sign" => param_str(1).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Recover the signer of an RLP-encoded signed transaction.
+fn recover_raw_sender(raw_hex: &str) -> Result<String, String> {
+    let bytes = hex::decode(raw_hex.trim_start_matches("0x"))
+        .map_err(|e| format!("eth_sendRawTransaction: invalid hex: {e}"))?;
+    // ethers predates EIP-7702 and cannot decode type-4 transactions.
+    if let Some(tx) = eip7702::decode_raw(&bytes)
+        .map_err(|e| format!("eth_sendRawTransaction: {e}"))?
+    {
+        return Ok(tx.sender);
+    }
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&bytes))
+        .map_err(|e| format!("eth_sendRawTransaction: undecodable tx: {e}"))?;
+    let sender = signature
+        .recover(tx.sighash())
+        .map_err(|e| format!("eth_sendRawTransaction: signer recovery failed: {e}"))?;
+    Ok(format!("{:#x}", sender))
+}
+
+/// Verify that a send/sign request acts for an address bound to `agent`.
+pub fn check_from_binding(agent: &AgentCredential, req: &JsonRpcRequest) -> Result<(), String> {
+    let Some(signer) = request_signer(req)? else {
+        return Ok(());
+    };
+    if agent.addresses.iter().any(|a| a.eq_ignore_ascii_case(&signer)) {
+        Ok(())
+    } else {
+        Err(format!(
+            "PLIMSOLL AGENT AUTH: agent '{}' is not authorized to act for {}",
+            agent.name, signer
+        ))
+    }
+}
+
+/// Compare secrets without leaking, through timing, how much of them matched.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "0x1111111111111111111111111111111111111111";
+    const BOB: &str = "0x2222222222222222222222222222222222222222";
+
+    fn config_with_agent() -> Config {
+        Config {
+            agents: vec![AgentCredential {
+                name: "alice".into(),
+                api_key: "pk_test_alice_0123456789".into(),
+                addresses: vec![ALICE.to_uppercase().replace("0X", "0x")],
+                chain_id: 0,
+                client_cert: String::new(),
+            }],
+            ..Config::default()
+        }
+    }
+
+    fn key(key: &str) -> Credentials {
+        Credentials { api_key: Some(key.into()), ..Credentials::default() }
+    }
+
+    fn req(method: &str, params: serde_json::Value) -> JsonRpcRequest {
+        JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            method: method.into(),
+            params,
+            id: serde_json::json!(1),
+            notification: false,
+        }
+    }
+
+    #[test]
+    fn test_auth_disabled_without_agents() {
+        assert_eq!(authenticate(&Config::default(), &Credentials::default()), Ok(None));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let config = config_with_agent();
+        assert_eq!(authenticate(&config, &Credentials::default()), Err(AuthError::MissingKey));
+        assert_eq!(authenticate(&config, &key("wrong")), Err(AuthError::InvalidKey));
+        let agent = authenticate(&config, &key("pk_test_alice_0123456789")).unwrap().unwrap();
+        assert_eq!(agent.name, "alice");
+    }
+
+    #[test]
+    fn test_authenticate_client_cert() {
+        const ALICE_CERT: &str = "AB:CD:00:11:22:33:44:55:66:77:88:99:AA:BB:CC:DD:EE:FF:00:11:22:33:44:55:66:77:88:99:AA:BB:CC:DD";
+        let mut config = config_with_agent();
+        config.agents[0].client_cert = ALICE_CERT.into();
+        config.agents.push(AgentCredential {
+            name: "bob".into(),
+            api_key: "pk_test_bob_0123456789ab".into(),
+            addresses: vec![BOB.into()],
+            chain_id: 0,
+            client_cert: String::new(),
+        });
+        let cert = |fingerprint: &str, api_key: Option<&str>| Credentials {
+            api_key: api_key.map(str::to_string),
+            client_cert: Some(fingerprint.into()),
+            admin_token: None,
+        };
+
+        let alice_fp = normalize_fingerprint(ALICE_CERT);
+        assert_eq!(authenticate(&config, &cert(&alice_fp, None)).unwrap().unwrap().name, "alice");
+        assert_eq!(
+            authenticate(&config, &cert(&alice_fp, Some("pk_test_alice_0123456789"))).unwrap().unwrap().name,
+            "alice"
+        );
+        assert_eq!(
+            authenticate(&config, &cert(&alice_fp, Some("pk_test_bob_0123456789ab"))),
+            Err(AuthError::CredentialMismatch)
+        );
+        assert_eq!(authenticate(&config, &cert(&"00".repeat(32), None)), Err(AuthError::UnknownCertificate));
+        // A pinned agent's key alone no longer suffices; an unpinned one's does.
+        assert_eq!(authenticate(&config, &key("pk_test_alice_0123456789")), Err(AuthError::CertificateRequired));
+        assert_eq!(authenticate(&config, &key("pk_test_bob_0123456789ab")).unwrap().unwrap().name, "bob");
+    }
+
+    #[test]
+    fn test_from_binding_send() {
+        let config = config_with_agent();
+        let agent = &config.agents[0];
+        let own = req("eth_sendTransaction", serde_json::json!([{ "from": ALICE, "to": BOB }]));
+        assert!(check_from_binding(agent, &own).is_ok());
+        let spoofed = req("eth_sendTransaction", serde_json::json!([{ "from": BOB, "to": ALICE }]));
+        assert!(check_from_binding(agent, &spoofed).is_err());
+    }
+
+    #[test]
+    fn test_from_binding_sign_param_positions() {
+        let config = config_with_agent();
+        let agent = &config.agents[0];
+        assert!(check_from_binding(agent, &req("personal_sign", serde_json::json!(["0xdead", ALICE]))).is_ok());
+        assert!(check_from_binding(agent, &req("eth_signTypedData_v4", serde_json::json!([BOB, "{}"]))).is_err());
+    }
+
+    #[test]
+    fn test_reads_not_bound_and_garbage_raw_rejected() {
+        let config = config_with_agent();
+        let agent = &config.agents[0];
+        assert!(check_from_binding(agent, &req("eth_getBalance", serde_json::json!([BOB, "latest"]))).is_ok());
+        assert!(check_from_binding(agent, &req("eth_sendRawTransaction", serde_json::json!(["0x1234"]))).is_err());
+    }
+}
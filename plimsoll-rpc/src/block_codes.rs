@@ -0,0 +1,151 @@
+//! Machine-readable block codes.
+//!
+//! Block reasons are prose for humans. Every block also carries a stable
+//! numeric code for the family of engine that fired, so agent frameworks
+//! branch on the code instead of parsing the reason:
+//!
+//! | Code | Category       | Engines                                                        |
+//! |------|----------------|----------------------------------------------------------------|
+//! | 4900 | UNKNOWN        | anything not listed below                                      |
+//! | 4901 | THREAT_FEED    | `engine0`, `sanctions`, `multicall`                            |
+//! | 4902 | PHYSICS        | `physics`, `simulation_error`, `velocity`, `drawdown`, `pvg`, `gas_fee`, `oracle` |
+//! | 4903 | EIP712         | `eip712_*`, `permit2_details`, `seaport`, `intent_price`, `forward_request`, `raw_sign` |
+//! | 4904 | TRACE          | `approval_diff`, `reentrancy`, `delegatecall`, `metamorphic`, `whitelist`, `non_determinism` |
+//! | 4905 | CONTRACT_RISK  | `honeypot`, `rugpull`, `verification`, `proxy`, `codehash_pin` |
+//! | 4906 | POLICY         | `function_policy`, `approval`, `target_allowlist`, `first_interaction`, `bridge`, `chain`, `method_policy`, `agent_binding` |
+//! | 4907 | SESSION        | `session_revoked`, `session_scope`                             |
+//! | 4908 | PHISHING       | `ens`, `address_poisoning`                                     |
+//! | 4909 | TX_ENVELOPE    | `json_pollution`, `eip7702`, `eip4844`, `l2`, `nonce_gap`, `replacement`, `paymaster` |
+//! | 4910 | SAFE           | `safe`                                                         |
+//! | 4911 | SANITIZER      | `sanitizer`                                                    |
+//!
+//! Codes are append-only: a code, once published, keeps its meaning. The
+//! code, category, engine and reason are the `data` of a block error and
+//! the `plimsoll` object of a synthetic receipt.
+
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCode {
+    pub code: u16,
+    pub category: &'static str,
+}
+
+pub const UNKNOWN: BlockCode = BlockCode { code: 4900, category: "UNKNOWN" };
+pub const THREAT_FEED: BlockCode = BlockCode { code: 4901, category: "THREAT_FEED" };
+pub const PHYSICS: BlockCode = BlockCode { code: 4902, category: "PHYSICS" };
+pub const EIP712: BlockCode = BlockCode { code: 4903, category: "EIP712" };
+pub const TRACE: BlockCode = BlockCode { code: 4904, category: "TRACE" };
+pub const CONTRACT_RISK: BlockCode = BlockCode { code: 4905, category: "CONTRACT_RISK" };
+pub const POLICY: BlockCode = BlockCode { code: 4906, category: "POLICY" };
+pub const SESSION: BlockCode = BlockCode { code: 4907, category: "SESSION" };
+pub const PHISHING: BlockCode = BlockCode { code: 4908, category: "PHISHING" };
+pub const TX_ENVELOPE: BlockCode = BlockCode { code: 4909, category: "TX_ENVELOPE" };
+pub const SAFE: BlockCode = BlockCode { code: 4910, category: "SAFE" };
+pub const SANITIZER: BlockCode = BlockCode { code: 4911, category: "SANITIZER" };
+
+/// Engine name → code. Engines with an `eip712_` prefix are matched in `of`.
+const ENGINES: &[(&str, BlockCode)] = &[
+    ("engine0", THREAT_FEED),
+    ("sanctions", THREAT_FEED),
+    ("multicall", THREAT_FEED),
+    ("physics", PHYSICS),
+    ("simulation_error", PHYSICS),
+    ("velocity", PHYSICS),
+    ("drawdown", PHYSICS),
+    ("pvg", PHYSICS),
+    ("gas_fee", PHYSICS),
+    ("oracle", PHYSICS),
+    ("permit2_details", EIP712),
+    ("seaport", EIP712),
+    ("intent_price", EIP712),
+    ("forward_request", EIP712),
+    ("raw_sign", EIP712),
+    ("approval_diff", TRACE),
+    ("reentrancy", TRACE),
+    ("delegatecall", TRACE),
+    ("metamorphic", TRACE),
+    ("whitelist", TRACE),
+    ("non_determinism", TRACE),
+    ("honeypot", CONTRACT_RISK),
+    ("rugpull", CONTRACT_RISK),
+    ("verification", CONTRACT_RISK),
+    ("proxy", CONTRACT_RISK),
+    ("codehash_pin", CONTRACT_RISK),
+    ("function_policy", POLICY),
+    ("approval", POLICY),
+    ("target_allowlist", POLICY),
+    ("first_interaction", POLICY),
+    ("bridge", POLICY),
+    ("chain", POLICY),
+    ("method_policy", POLICY),
+    ("agent_binding", POLICY),
+    ("session_revoked", SESSION),
+    ("session_scope", SESSION),
+    ("ens", PHISHING),
+    ("address_poisoning", PHISHING),
+    ("json_pollution", TX_ENVELOPE),
+    ("eip7702", TX_ENVELOPE),
+    ("eip4844", TX_ENVELOPE),
+    ("l2", TX_ENVELOPE),
+    ("nonce_gap", TX_ENVELOPE),
+    ("replacement", TX_ENVELOPE),
+    ("paymaster", TX_ENVELOPE),
+    ("safe", SAFE),
+    ("sanitizer", SANITIZER),
+];
+
+/// The code of blocks by `engine`.
+pub fn of(engine: &str) -> BlockCode {
+    if engine.starts_with("eip712_") {
+        return EIP712;
+    }
+    ENGINES
+        .iter()
+        .find(|(name, _)| *name == engine)
+        .map_or(UNKNOWN, |(_, code)| *code)
+}
+
+/// Structured description of a block by `engine` for `reason`.
+pub fn data(engine: &str, reason: &str) -> Value {
+    let code = of(engine);
+    json!({
+        "code": code.code,
+        "category": code.category,
+        "engine": engine,
+        "reason": reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_codes() {
+        assert_eq!(of("engine0").code, 4901);
+        assert_eq!(of("physics").code, 4902);
+        assert_eq!(of("eip712_permit").code, 4903);
+        assert_eq!(of("eip712_chain_id"), EIP712);
+        assert_eq!(of("not_an_engine"), UNKNOWN);
+
+        let data = data("sanctions", "PLIMSOLL SANCTIONS: listed");
+        assert_eq!(data["code"], 4901);
+        assert_eq!(data["category"], "THREAT_FEED");
+        assert_eq!(data["engine"], "sanctions");
+    }
+
+    #[test]
+    fn test_codes_unique_per_category() {
+        let all = [
+            UNKNOWN, THREAT_FEED, PHYSICS, EIP712, TRACE, CONTRACT_RISK, POLICY, SESSION, PHISHING,
+            TX_ENVELOPE, SAFE, SANITIZER,
+        ];
+        for (i, a) in all.iter().enumerate() {
+            for b in &all[i + 1..] {
+                assert_ne!(a.code, b.code);
+                assert_ne!(a.category, b.category);
+            }
+        }
+    }
+}
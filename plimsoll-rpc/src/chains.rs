@@ -0,0 +1,292 @@
+//! Multi-chain serving: one proxy instance, several chains.
+//!
+//! The default chain is `upstream_rpc_url` (chain `expected_chain_id`, else
+//! `chain_id`); `chains` adds more, each with its own upstream. A request
+//! is routed to:
+//!
+//!   1. the chain named in the `plimsoll-chain` header (decimal or `0x` hex),
+//!   2. else the authenticated agent's `chain_id`,
+//!   3. else the default chain.
+//!
+//! An agent bound to a chain may not name another one, and a chain the
+//! proxy doesn't serve is refused rather than sent to the default upstream.
+//!
+//! An additional chain runs against a config derived from the live one:
+//! its upstream, with `chain_id` and `expected_chain_id` pinned to it, so
+//! EIP-712 domains, EIP-7702 authorizations and transaction chain ids are
+//! validated against the chain the request is actually sent to. It has
+//! its own Engine 0 threat filter and its own slice of the protective
+//! state: blocked transactions, revoked session keys and pending nonces
+//! are keyed `"<chain_id>:<key>"`. Default-chain keys stay unprefixed, so
+//! state persisted by a single-chain deployment keeps applying. The rescue
+//! queue only follows the default chain.
+
+use crate::config::{AgentCredential, ChainUpstream, Config};
+use crate::threat_feed::{self, SharedThreatFilter};
+use anyhow::{Context, Result};
+use axum::http::HeaderMap;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Header selecting the chain a request is served on.
+pub const CHAIN_HEADER: &str = "plimsoll-chain";
+
+/// JSON-RPC error code for a chain the proxy doesn't serve (EIP-1193
+/// "Chain Disconnected").
+pub const UNSUPPORTED_CHAIN_CODE: i64 = 4901;
+
+/// Timeout of the startup `eth_chainId` probe of each upstream.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+tokio::task_local! {
+    /// Additional chain the current request is served on; unset or `None`
+    /// on the default chain.
+    static CURRENT: Option<u64>;
+}
+
+/// Engine 0 filters of the additional chains, created on first use.
+pub type ThreatFilters = Arc<Mutex<HashMap<u64, SharedThreatFilter>>>;
+
+fn parse_chain_id(raw: &str) -> Option<u64> {
+    let raw = raw.trim();
+    match raw.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => raw.parse().ok(),
+    }
+}
+
+/// The `plimsoll-chain` header, if the client sent one.
+pub fn requested(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CHAIN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string())
+}
+
+/// Pick the chain a request is served on.
+pub fn select(config: &Config, requested: Option<&str>, agent: Option<&AgentCredential>) -> Result<u64, String> {
+    let requested = match requested {
+        Some(raw) => Some(parse_chain_id(raw).ok_or_else(|| format!("invalid {CHAIN_HEADER} header '{raw}'"))?),
+        None => None,
+    };
+    let bound = agent.filter(|a| a.chain_id != 0);
+    let chain = match (requested, bound) {
+        (Some(id), Some(agent)) if id != agent.chain_id => {
+            return Err(format!("agent '{}' is bound to chain {}, not {}", agent.name, agent.chain_id, id));
+        }
+        (Some(id), _) => id,
+        (None, Some(agent)) => agent.chain_id,
+        (None, None) => config.default_chain_id(),
+    };
+    if !config.serves_chain(chain) {
+        return Err(format!("chain {chain} is not served by this proxy"));
+    }
+    Ok(chain)
+}
+
+/// The config requests on `chain` run against.
+pub fn config_for(config: &Arc<Config>, chain: u64) -> Arc<Config> {
+    let Some(upstream) = config.chains.iter().find(|c| c.chain_id == chain) else {
+        return Arc::clone(config);
+    };
+    let mut derived = Config::clone(config);
+    // `chain` becomes the default; the root's default takes its slot in
+    // `chains`, so the derived config serves the same set and validates.
+    let root_default = ChainUpstream {
+        chain_id: config.default_chain_id(),
+        upstream_rpc_url: config.upstream_rpc_url.clone(),
+        blob_upstream_rpc_url: config.blob_upstream_rpc_url.clone(),
+    };
+    derived.chains = config
+        .chains
+        .iter()
+        .map(|c| if c.chain_id == chain { root_default.clone() } else { c.clone() })
+        .collect();
+    derived.upstream_rpc_url = upstream.upstream_rpc_url.clone();
+    derived.blob_upstream_rpc_url = upstream.blob_upstream_rpc_url.clone();
+    derived.chain_id = chain;
+    derived.expected_chain_id = chain;
+    Arc::new(derived)
+}
+
+/// The Engine 0 filter of `chain`.
+pub fn threat_filter(config: &Config, default: &SharedThreatFilter, filters: &ThreatFilters, chain: u64) -> SharedThreatFilter {
+    if chain == config.default_chain_id() {
+        return Arc::clone(default);
+    }
+    match filters.lock() {
+        Ok(mut filters) => Arc::clone(filters.entry(chain).or_insert_with(threat_feed::new_shared_filter)),
+        // Never fall back to another chain's filter: start from an empty one.
+        Err(_) => threat_feed::new_shared_filter(),
+    }
+}
+
+/// Run `f` with `chain` as the current chain.
+pub async fn scope<F: Future>(config: &Config, chain: u64, f: F) -> F::Output {
+    let current = (chain != config.default_chain_id()).then_some(chain);
+    CURRENT.scope(current, f).await
+}
+
+/// The additional chain of the request being served, `None` on the
+/// default chain and outside requests.
+pub fn current() -> Option<u64> {
+    CURRENT.try_with(|c| *c).ok().flatten()
+}
+
+/// `key` in the protective state of `chain` (`None` = default chain).
+pub fn scope_key(chain: Option<u64>, key: &str) -> String {
+    match chain {
+        Some(id) => format!("{id}:{key}"),
+        None => key.to_string(),
+    }
+}
+
+/// Split a [`scope_key`] back into its chain and key.
+pub fn unscope_key(scoped: &str) -> (Option<u64>, &str) {
+    match scoped.split_once(':') {
+        Some((chain, key)) => match chain.parse() {
+            Ok(id) => (Some(id), key),
+            Err(_) => (None, scoped),
+        },
+        None => (None, scoped),
+    }
+}
+
+/// Reject a transaction signed or built for another chain than the one it
+/// is sent to. Only enforced when the chain is pinned (`expected_chain_id`).
+pub fn check_tx_chain_id(config: &Config, tx_chain_id: Option<u64>) -> Result<(), String> {
+    match tx_chain_id {
+        Some(id) if config.expected_chain_id != 0 && id != config.expected_chain_id => Err(format!(
+            "PLIMSOLL CHAIN: transaction is for chain {} but was sent to chain {}",
+            id, config.expected_chain_id
+        )),
+        _ => Ok(()),
+    }
+}
+
+async fn upstream_chain_id(rpc_url: &str) -> Result<u64> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_chainId",
+        "params": [],
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .context("Failed to call eth_chainId")?
+        .json()
+        .await
+        .context("Failed to parse eth_chainId response")?;
+    body["result"]
+        .as_str()
+        .and_then(parse_chain_id)
+        .context("eth_chainId returned no chain id")
+}
+
+/// Check every additional chain's upstream serves the chain it is
+/// configured for. An unreachable upstream only warns; a mismatch fails.
+pub async fn verify_upstreams(config: &Config) -> Result<()> {
+    for chain in &config.chains {
+        match upstream_chain_id(&chain.upstream_rpc_url).await {
+            Ok(id) if id != chain.chain_id => anyhow::bail!(
+                "chains: upstream for chain {} reports chain {}",
+                chain.chain_id,
+                id
+            ),
+            Ok(_) => tracing::info!(chain = chain.chain_id, "Serving additional chain"),
+            Err(e) => tracing::warn!(chain = chain.chain_id, error = %e, "Upstream chain id unverified"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ChainUpstream;
+
+    fn multi_chain() -> Config {
+        Config {
+            chains: vec![ChainUpstream {
+                chain_id: 8453,
+                upstream_rpc_url: "https://base.example".into(),
+                blob_upstream_rpc_url: String::new(),
+            }],
+            ..Config::default()
+        }
+    }
+
+    fn agent(chain_id: u64) -> AgentCredential {
+        AgentCredential {
+            name: "bot".into(),
+            api_key: "pk_test_0123456789abcdef".into(),
+            addresses: vec![],
+            chain_id,
+            client_cert: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let config = multi_chain();
+        assert_eq!(select(&config, None, None), Ok(1));
+        assert_eq!(select(&config, Some("8453"), None), Ok(8453));
+        assert_eq!(select(&config, Some("0x2105"), None), Ok(8453));
+        assert_eq!(select(&config, None, Some(&agent(8453))), Ok(8453));
+        assert_eq!(select(&config, Some("8453"), Some(&agent(0))), Ok(8453));
+        assert!(select(&config, Some("1"), Some(&agent(8453))).unwrap_err().contains("bound to chain 8453, not 1"));
+        assert!(select(&config, Some("10"), None).unwrap_err().contains("chain 10 is not served"));
+        assert!(select(&config, Some("base"), None).unwrap_err().contains("invalid plimsoll-chain header"));
+    }
+
+    #[test]
+    fn test_config_for_pins_chain() {
+        let config = Arc::new(multi_chain());
+        let base = config_for(&config, 8453);
+        assert_eq!(base.upstream_rpc_url, "https://base.example");
+        assert_eq!((base.chain_id, base.expected_chain_id), (8453, 8453));
+        assert!(base.validate().is_ok());
+        assert!(base.serves_chain(1) && base.serves_chain(8453));
+        assert!(Arc::ptr_eq(&config_for(&config, 1), &config));
+    }
+
+    #[test]
+    fn test_threat_filters_per_chain() {
+        let config = multi_chain();
+        let default = threat_feed::new_shared_filter();
+        let filters = ThreatFilters::default();
+        let base = threat_filter(&config, &default, &filters, 8453);
+        base.write().unwrap().add_address("0x6666666666666666666666666666666666666666");
+        assert!(Arc::ptr_eq(&base, &threat_filter(&config, &default, &filters, 8453)));
+        assert!(Arc::ptr_eq(&default, &threat_filter(&config, &default, &filters, 1)));
+        assert!(!default.read().unwrap().is_address_blacklisted("0x6666666666666666666666666666666666666666"));
+    }
+
+    #[tokio::test]
+    async fn test_state_keys_scoped_to_chain() {
+        let config = multi_chain();
+        assert_eq!(current(), None);
+        assert_eq!(scope(&config, 8453, async { current() }).await, Some(8453));
+        assert_eq!(scope(&config, 1, async { current() }).await, None);
+        assert_eq!(scope_key(Some(8453), "0xabc"), "8453:0xabc");
+        assert_eq!(scope_key(None, "0xabc"), "0xabc");
+        assert_eq!(unscope_key("8453:0xabc"), (Some(8453), "0xabc"));
+        assert_eq!(unscope_key("0xabc"), (None, "0xabc"));
+    }
+
+    #[test]
+    fn test_tx_chain_id_checked_when_pinned() {
+        assert!(check_tx_chain_id(&Config::default(), Some(10)).is_ok());
+        let base = Config { chain_id: 8453, expected_chain_id: 8453, ..Config::default() };
+        assert!(check_tx_chain_id(&base, Some(8453)).is_ok());
+        assert!(check_tx_chain_id(&base, None).is_ok());
+        let err = check_tx_chain_id(&base, Some(1)).unwrap_err();
+        assert!(err.contains("for chain 1 but was sent to chain 8453"));
+    }
+}
@@ -0,0 +1,289 @@
+//! Cluster-shared protective state over Redis.
+//!
+//! Replicas behind a load balancer each keep revocations, the Paymaster
+//! sever and blocked tx hashes in memory, so a revocation seen by one
+//! replica is unknown to the others. With `cluster_redis_url`, every
+//! change is written to Redis and fanned out over pub/sub:
+//!
+//!   - `<ns>:revoked` — session key → unix time a pending (mempool)
+//!     revocation was first seen, 0 once permanent,
+//!   - `<ns>:blocked` — synthetic tx hash → block reason,
+//!   - `<ns>:blocked_engines` — synthetic tx hash → engine that blocked it,
+//!   - `<ns>:paymaster_severed` — `1` while severed,
+//!   - `<ns>:events` — the change events.
+//!
+//! At startup a replica uploads the state it restored and merges the
+//! shared state; after losing its subscription it merges again, so no
+//! event is missed for good. Merging never un-revokes or un-severs —
+//! only events do. Keys are chain-scoped as stored.
+
+use crate::config::Config;
+use crate::rpc;
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, RedisResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// A change to the shared state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ClusterEvent {
+    /// A session key was revoked; `pending_since` is set while the
+    /// revocation is pessimistic (not confirmed on-chain).
+    Revoked { session_key: String, pending_since: Option<u64> },
+    Unrevoked { session_key: String },
+    PaymasterSevered { severed: bool },
+    Blocked {
+        tx_hash: String,
+        reason: String,
+        #[serde(default)]
+        engine: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    /// Replica that made the change.
+    origin: String,
+    event: ClusterEvent,
+}
+
+#[derive(Debug, Clone)]
+struct Keys {
+    revoked: String,
+    blocked: String,
+    blocked_engines: String,
+    severed: String,
+    channel: String,
+}
+
+impl Keys {
+    fn new(namespace: &str) -> Self {
+        Self {
+            revoked: format!("{namespace}:revoked"),
+            blocked: format!("{namespace}:blocked"),
+            blocked_engines: format!("{namespace}:blocked_engines"),
+            severed: format!("{namespace}:paymaster_severed"),
+            channel: format!("{namespace}:events"),
+        }
+    }
+}
+
+/// Local changes waiting to be shared. Unset when clustering is off.
+static OUTBOX: OnceLock<mpsc::UnboundedSender<ClusterEvent>> = OnceLock::new();
+
+static REPLICA_ID: OnceLock<String> = OnceLock::new();
+
+fn replica_id() -> &'static str {
+    REPLICA_ID.get_or_init(|| {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        format!("{}-{:x}", std::process::id(), nanos)
+    })
+}
+
+/// Share a local change with the other replicas. A no-op when clustering
+/// is off.
+pub fn publish(event: ClusterEvent) {
+    if let Some(outbox) = OUTBOX.get() {
+        let _ = outbox.send(event);
+    }
+}
+
+/// Write `event` to the shared state.
+async fn store(conn: &mut ConnectionManager, keys: &Keys, event: &ClusterEvent) -> RedisResult<()> {
+    match event {
+        ClusterEvent::Revoked { session_key, pending_since: None } => {
+            conn.hset(&keys.revoked, session_key, 0u64).await
+        }
+        ClusterEvent::Revoked { session_key, pending_since: Some(seen_at) } => {
+            conn.hset_nx(&keys.revoked, session_key, *seen_at).await
+        }
+        ClusterEvent::Unrevoked { session_key } => conn.hdel(&keys.revoked, session_key).await,
+        ClusterEvent::PaymasterSevered { severed } => {
+            conn.set(&keys.severed, if *severed { "1" } else { "0" }).await
+        }
+        ClusterEvent::Blocked { tx_hash, reason, engine } => {
+            let _: () = conn.hset(&keys.blocked_engines, tx_hash, engine).await?;
+            conn.hset(&keys.blocked, tx_hash, reason).await
+        }
+    }
+}
+
+/// Add this replica's state to the shared state.
+async fn upload(conn: &mut ConnectionManager, keys: &Keys) -> RedisResult<()> {
+    let snapshot = rpc::snapshot_state();
+    let pending: HashMap<String, u64> = snapshot.pending_revocations.into_iter().collect();
+    for key in snapshot.revoked_session_keys {
+        let pending_since = pending.get(&key).copied();
+        store(conn, keys, &ClusterEvent::Revoked { session_key: key, pending_since }).await?;
+    }
+    let engines: HashMap<String, String> = snapshot.blocked_tx_engines.into_iter().collect();
+    for (tx_hash, reason) in snapshot.blocked_txs {
+        if let Some(engine) = engines.get(&tx_hash) {
+            let _: () = conn.hset_nx(&keys.blocked_engines, &tx_hash, engine).await?;
+        }
+        let _: () = conn.hset_nx(&keys.blocked, tx_hash, reason).await?;
+    }
+    if snapshot.paymaster_severed {
+        store(conn, keys, &ClusterEvent::PaymasterSevered { severed: true }).await?;
+    }
+    Ok(())
+}
+
+/// Merge the shared state into this replica's.
+async fn merge(conn: &mut ConnectionManager, keys: &Keys) -> RedisResult<()> {
+    let revoked: HashMap<String, u64> = conn.hgetall(&keys.revoked).await?;
+    let blocked: HashMap<String, String> = conn.hgetall(&keys.blocked).await?;
+    let mut blocked_engines: HashMap<String, String> = conn.hgetall(&keys.blocked_engines).await?;
+    let severed: Option<String> = conn.get(&keys.severed).await?;
+    for (session_key, seen_at) in revoked {
+        let pending_since = (seen_at > 0).then_some(seen_at);
+        rpc::apply_cluster_event(ClusterEvent::Revoked { session_key, pending_since });
+    }
+    for (tx_hash, reason) in blocked {
+        let engine = blocked_engines.remove(&tx_hash).unwrap_or_default();
+        rpc::apply_cluster_event(ClusterEvent::Blocked { tx_hash, reason, engine });
+    }
+    if severed.as_deref() == Some("1") {
+        rpc::apply_cluster_event(ClusterEvent::PaymasterSevered { severed: true });
+    }
+    Ok(())
+}
+
+/// Store and publish local changes.
+async fn forward(mut outbox: mpsc::UnboundedReceiver<ClusterEvent>, mut conn: ConnectionManager, keys: Keys) {
+    while let Some(event) = outbox.recv().await {
+        if let Err(e) = store(&mut conn, &keys, &event).await {
+            warn!(error = %e, ?event, "Failed to write cluster state");
+        }
+        let envelope = Envelope { origin: replica_id().to_string(), event };
+        let payload = match serde_json::to_string(&envelope) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!(error = %e, "Failed to encode cluster event");
+                continue;
+            }
+        };
+        let published: RedisResult<()> = conn.publish(&keys.channel, payload).await;
+        if let Err(e) = published {
+            warn!(error = %e, "Failed to publish cluster event");
+        }
+    }
+}
+
+/// Apply the other replicas' changes, resubscribing (and merging) when
+/// the subscription drops.
+async fn subscribe(client: redis::Client, mut conn: ConnectionManager, keys: Keys) {
+    loop {
+        match client.get_async_pubsub().await {
+            Ok(mut pubsub) => match pubsub.subscribe(&keys.channel).await {
+                Ok(()) => {
+                    if let Err(e) = merge(&mut conn, &keys).await {
+                        warn!(error = %e, "Failed to merge cluster state");
+                    }
+                    let mut messages = pubsub.on_message();
+                    while let Some(message) = messages.next().await {
+                        let Ok(payload) = message.get_payload::<String>() else {
+                            continue;
+                        };
+                        match serde_json::from_str::<Envelope>(&payload) {
+                            Ok(envelope) if envelope.origin != replica_id() => {
+                                rpc::apply_cluster_event(envelope.event)
+                            }
+                            Ok(_) => {}
+                            Err(e) => warn!(error = %e, "Ignoring malformed cluster event"),
+                        }
+                    }
+                    warn!("Cluster subscription lost — resubscribing");
+                }
+                Err(e) => warn!(error = %e, "Failed to subscribe to cluster events"),
+            },
+            Err(e) => warn!(error = %e, "Failed to connect to the cluster Redis"),
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Join the cluster when `cluster_redis_url` is set: share this replica's
+/// state, merge the others', and keep both in step from then on. Call
+/// after the persisted state is restored.
+pub async fn start(config: &Config) -> Result<()> {
+    if config.cluster_redis_url.is_empty() {
+        return Ok(());
+    }
+    let client = redis::Client::open(config.cluster_redis_url.as_str()).context("Invalid cluster_redis_url")?;
+    let mut conn = ConnectionManager::new(client.clone())
+        .await
+        .context("Failed to connect to the cluster Redis")?;
+    let keys = Keys::new(&config.cluster_namespace);
+    upload(&mut conn, &keys).await.context("Failed to upload state to the cluster")?;
+    merge(&mut conn, &keys).await.context("Failed to merge the cluster state")?;
+
+    let (outbox, pending) = mpsc::unbounded_channel();
+    if OUTBOX.set(outbox).is_err() {
+        anyhow::bail!("Cluster state sharing already started");
+    }
+    tokio::spawn(forward(pending, conn.clone(), keys.clone()));
+    tokio::spawn(subscribe(client, conn, keys));
+    info!(
+        namespace = %config.cluster_namespace,
+        replica = replica_id(),
+        "Cluster state sharing enabled"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_wire_format() {
+        let envelope = Envelope {
+            origin: "r1".into(),
+            event: ClusterEvent::Revoked { session_key: "10:0xabc".into(), pending_since: Some(7) },
+        };
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "origin": "r1",
+                "event": { "event": "revoked", "session_key": "10:0xabc", "pending_since": 7 }
+            })
+        );
+        let back: Envelope = serde_json::from_value(json).unwrap();
+        assert_eq!(back.event, envelope.event);
+        let severed: ClusterEvent = serde_json::from_str(r#"{"event":"paymaster_severed","severed":true}"#).unwrap();
+        assert_eq!(severed, ClusterEvent::PaymasterSevered { severed: true });
+        // Events from replicas predating block codes carry no engine.
+        let blocked: ClusterEvent =
+            serde_json::from_str(r#"{"event":"blocked","tx_hash":"0xplimsoll01","reason":"ENGINE 0: blacklisted"}"#)
+                .unwrap();
+        assert_eq!(
+            blocked,
+            ClusterEvent::Blocked {
+                tx_hash: "0xplimsoll01".into(),
+                reason: "ENGINE 0: blacklisted".into(),
+                engine: String::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_remote_events_applied_without_downgrade() {
+        let key = "0xa9e00000000000000000000000000000000000e1";
+        rpc::apply_cluster_event(ClusterEvent::Revoked { session_key: key.into(), pending_since: None });
+        assert!(rpc::is_session_revoked(key));
+        // A pessimistic revocation never makes a permanent one expirable.
+        rpc::apply_cluster_event(ClusterEvent::Revoked { session_key: key.into(), pending_since: Some(1) });
+        assert!(!rpc::pending_revocations().iter().any(|(k, _)| k == key));
+        rpc::apply_cluster_event(ClusterEvent::Unrevoked { session_key: key.into() });
+        assert!(!rpc::is_session_revoked(key));
+    }
+}
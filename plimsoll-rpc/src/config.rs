@@ -1,8 +1,54 @@
 //! Configuration for the Plimsoll RPC Proxy.
+//!
+//! Configuration is layered, lowest to highest precedence:
+//!   1. Built-in defaults (`Config::default()`)
+//!   2. Optional config file (`--config plimsoll.toml` / `.yaml`)
+//!   3. `PLIMSOLL_*` environment variables
+//!
+//! Config files are validated strictly: unknown keys and malformed values
+//! are startup errors, never silent fallbacks to defaults.
 
+use crate::sanitizer::{PatternAction, SanitizerPattern};
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+/// Public demo endpoint used when no upstream is configured. Rate-limited
+/// and shared — never acceptable for production traffic.
+const DEMO_UPSTREAM_RPC: &str = "https://eth-mainnet.g.alchemy.com/v2/demo";
+
+/// Canonical Safe MultiSend / MultiSendCallOnly deployments (v1.3.0, v1.4.1).
+const DEFAULT_SAFE_DELEGATECALL_ALLOWLIST: &str = "\
+    0xa238cbeb142c10ef7ad8442c6d1f9e89e07e7761,0x40a2accbd92bca938b02010e17a5b8929b49130d,\
+    0x38869bf66a61cf6bdb996a6ae40d5853fd43b526,0x9641d764fc13c8b624c04430c7356c1c7c8102e2";
+
+/// Canonical UniswapX reactors (ExclusiveDutchOrderReactor,
+/// V2DutchOrderReactor, PriorityOrderReactor).
+const DEFAULT_UNISWAPX_REACTORS: &str = "\
+    0x6000da47483062a0d734ba3dc7576ce6a0b645c4,0x00000011f84b9aa48e5f8aa8b9897600006289be,\
+    0x000000001ec5656dcdb24d90dfa42742738de729";
+
+/// Mainnet WETH, USDC, USDT and DAI.
+const DEFAULT_KNOWN_TOKENS: &str = "\
+    0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2,0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48,\
+    0xdac17f958d2ee523a2206206994597c13d831ec7,0x6b175474e89094c44da98b954eedeac495271d0f";
+
+/// Mainnet Chainlink USD feeds for the default known tokens.
+const DEFAULT_CHAINLINK_FEEDS: &[(&str, &str)] = &[
+    // WETH → ETH / USD
+    ("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "0x5f4ec3df9cbd43714fe2740f5e3616155c5b8419"),
+    // USDC / USD
+    ("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "0x8fffffd4afb6115b954bd326cbe7b4ba576818f6"),
+    // USDT / USD
+    ("0xdac17f958d2ee523a2206206994597c13d831ec7", "0x3e7d1eab13ad0104d2750b8863b489d65364e32d"),
+    // DAI / USD
+    ("0x6b175474e89094c44da98b954eedeac495271d0f", "0xaed0c38402a5d19df6e4c03f4e2dced6e29c1ee9"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     /// Upstream Ethereum RPC URL (Alchemy, Infura, etc.)
     pub upstream_rpc_url: String,
@@ -152,139 +198,2045 @@ pub struct Config {
     /// Comma-separated domains governed by the HTTP proxy.
     /// Only these domains have their costs tracked.
     pub http_governed_domains: String,
+
+    // ── v2.1: Operational Hardening ─────────────────────────────────
+
+    /// Path to the SQLite database holding persistent proxy state
+    /// (revoked session keys, blocked tx hashes, revert strikes,
+    /// paymaster-severed flag). Empty = in-memory only (state lost on restart).
+    pub state_db_path: String,
+
+    /// Interval in seconds between snapshots of the protective state.
+    pub state_snapshot_interval_secs: u64,
+
+    /// OTLP/gRPC collector endpoint for span export (e.g. `http://otel:4317`).
+    /// Empty = tracing export disabled (stdout logs only).
+    pub otlp_endpoint: String,
+
+    /// `service.name` resource attribute reported to the collector.
+    pub otel_service_name: String,
+
+    /// Maximum threat feed age in seconds before `/readyz` reports not-ready.
+    /// 0 = feed freshness does not gate readiness.
+    pub threat_feed_max_age_secs: u64,
+
+    /// Bearer token for the `/admin/*` endpoints. Empty = admin API disabled.
+    pub admin_token: String,
+
+    /// Per-agent API keys and the addresses each agent may send/sign for.
+    /// Empty = agent authentication disabled (any caller may use the proxy).
+    pub agents: Vec<AgentCredential>,
+
+    /// Send requests per minute per agent (0 = unlimited).
+    pub rate_limit_send_per_min: u32,
+
+    /// Sign requests per minute per agent (0 = unlimited).
+    pub rate_limit_sign_per_min: u32,
+
+    /// Read requests per minute per agent (0 = unlimited).
+    pub rate_limit_read_per_min: u32,
+
+    /// Token bucket size (max burst). 0 = one minute's worth of requests.
+    pub rate_limit_burst: u32,
+
+    /// Comma-separated methods forwarded upstream (`*` suffix wildcard).
+    /// Empty = all methods allowed unless denied.
+    pub method_allowlist: String,
+
+    /// Comma-separated methods never forwarded (e.g. `eth_accounts,debug_*`).
+    /// Takes precedence over the allowlist.
+    pub method_denylist: String,
+
+    /// Comma-separated domains for which EIP-4361 (SIWE) `personal_sign`
+    /// login messages are allowed. Empty = all `personal_sign` blocked.
+    pub siwe_allowed_domains: String,
+
+    /// Maximum SIWE message validity (Expiration Time - now) in seconds.
+    /// 0 = no upper bound (an Expiration Time is still required).
+    pub siwe_max_validity_secs: u64,
+
+    /// EIP-712 domains trusted to request dangerous typed-data signatures
+    /// (e.g. a vetted DEX). Empty = GOD-TIER 1 blocks them all.
+    pub eip712_trusted_domains: Vec<TrustedTypedDataDomain>,
+
+    /// Permit2: maximum allowance per token (token address → max amount in
+    /// base units, decimal). Tokens not listed are uncapped.
+    pub permit2_token_caps: BTreeMap<String, String>,
+
+    /// Permit2: maximum per-token `expiration` horizon in seconds from now.
+    /// 0 = disabled.
+    pub permit2_max_expiration_secs: u64,
+
+    /// Seaport: minimum wei the offerer must receive when an order offers
+    /// NFTs (decimal string). "0" = only zero-consideration give-aways
+    /// are blocked.
+    pub seaport_min_nft_consideration_wei: String,
+
+    /// Seaport: comma-separated ERC20s valued 1:1 with ETH (e.g. WETH).
+    pub seaport_eth_equivalent_tokens: String,
+
+    /// Seaport: minimum share (%) of an order's ETH-valued consideration
+    /// that must go to the offerer; the rest is fees and royalties.
+    /// 0 = disabled.
+    pub seaport_min_offerer_share_pct: f64,
+
+    /// Seaport: minimum USD the offerer must receive per USD of native /
+    /// ERC-20 items offered, priced by the oracle. Unpriced orders are
+    /// blocked. 0 = disabled.
+    pub seaport_min_consideration_ratio: f64,
+
+    /// Seaport: minimum USD the offerer must receive when an order offers
+    /// NFTs for native / ERC-20 consideration, when it can be priced.
+    /// 0 = disabled.
+    pub seaport_min_nft_consideration_usd: f64,
+
+    /// CowSwap / UniswapX: maximum percentage an order's execution price
+    /// may fall below the reference price. 0 = no price check. Orders
+    /// still need an `eip712_trusted_domains` entry to be signed.
+    pub order_max_price_deviation_pct: f64,
+
+    /// UniswapX: comma-separated reactors an order's Permit2 `spender`
+    /// may be. Defaults to the canonical deployments.
+    pub uniswapx_reactors: String,
+
+    /// Static reference prices (token address → price). Checked before
+    /// the price oracle.
+    pub reference_prices: BTreeMap<String, ReferencePrice>,
+
+    /// Price oracle base URL, queried as `GET {url}/{token}`.
+    /// Empty = static `reference_prices` only.
+    pub price_oracle_url: String,
+
+    /// EIP-7702: comma-separated delegate implementations an agent may
+    /// delegate its EOA to. Empty = every delegation is blocked.
+    pub eip7702_allowed_delegates: String,
+
+    /// Gnosis Safe: comma-separated contracts a Safe may DELEGATECALL into
+    /// (MultiSend batches are unwrapped). Defaults to the canonical
+    /// MultiSend / MultiSendCallOnly deployments (v1.3.0, v1.4.1).
+    pub safe_delegatecall_allowlist: String,
+
+    /// Resolve upgradeable-proxy implementations (EIP-1967 / beacon /
+    /// EIP-1822) and run Engine 0 against the implementation too.
+    pub resolve_proxy_implementations: bool,
+
+    /// Local selector → signature JSON file merged over the built-in
+    /// 4byte seed. Empty = seed only.
+    pub selector_db_path: String,
+
+    /// 4byte-compatible signature lookup API queried on unknown selectors
+    /// (e.g. `https://www.4byte.directory/api/v1/signatures/`).
+    /// Empty = offline.
+    pub selector_lookup_url: String,
+
+    /// Comma-separated function names (`transferOwnership`) or full
+    /// signatures (`upgradeTo(address)`) that agents may never call.
+    pub blocked_functions: String,
+
+    /// Directory of contract ABIs named `<address>.json` (plain ABI array
+    /// or a Hardhat/Foundry artifact with an `abi` field). Empty = none.
+    pub abi_dir: String,
+
+    /// Sourcify server queried for verified ABIs on a cache miss
+    /// (e.g. `https://sourcify.dev/server`). Empty = disabled.
+    pub abi_sourcify_url: String,
+
+    /// Etherscan-compatible API (v2, multichain) for verified ABIs.
+    pub etherscan_api_url: String,
+
+    /// Etherscan API key. Empty = Etherscan fetch disabled.
+    pub etherscan_api_key: String,
+
+    /// Simulate a buy-then-sell round trip (`eth_simulateV1`) before
+    /// swaps into unknown tokens and block honeypots.
+    pub honeypot_detection: bool,
+
+    /// Maximum buy or sell tax (percent) before a token is treated as a
+    /// honeypot.
+    pub honeypot_max_tax_pct: f64,
+
+    /// Comma-separated well-known tokens exempt from the honeypot round
+    /// trip and rug-pull heuristics (stablecoins are mintable, pausable
+    /// and blacklisting by design).
+    pub known_tokens: String,
+
+    /// Score unknown contracts with static rug-pull heuristics (mint,
+    /// owner fees, blacklist, pause, unlocked liquidity). Off by default:
+    /// `uniswap_v2_factory` and `lp_quote_token` default to mainnet's.
+    pub rugpull_heuristics: bool,
+
+    /// Block at or above this rug-pull risk score (0–100).
+    /// 0 = score and log only.
+    pub rugpull_block_score: u32,
+
+    /// Uniswap V2 factory used to find a token's pool for the
+    /// liquidity-lock check. Empty = skip it.
+    pub uniswap_v2_factory: String,
+
+    /// Quote token of the pool checked for locked liquidity (WETH).
+    pub lp_quote_token: String,
+
+    /// Look up whether value-transfer targets are verified (Sourcify /
+    /// Etherscan) and when they were deployed.
+    pub contract_verification_check: bool,
+
+    /// Block value transfers to unverified contracts deployed less than
+    /// this many hours ago. 0 = report only.
+    pub unverified_min_age_hours: u64,
+
+    /// Compare recipients against each agent's past counterparties to
+    /// catch address-poisoning lookalikes.
+    pub address_poisoning_check: bool,
+
+    /// Leading and trailing hex characters a lookalike must share with a
+    /// known counterparty.
+    pub poisoning_match_chars: usize,
+
+    /// Block lookalike recipients (false = warn only).
+    pub block_address_poisoning: bool,
+
+    /// Hold the first transaction to a never-seen counterparty until the
+    /// operator approves it via the admin API.
+    pub first_interaction_requires_approval: bool,
+
+    /// Maximum value (wei, decimal) sent to a never-seen counterparty.
+    /// Empty = no cap.
+    pub first_interaction_max_value_wei: String,
+
+    /// Maximum USD value — native value plus ERC-20 tokens transferred —
+    /// sent to a never-seen counterparty. 0 = no cap.
+    pub first_interaction_max_value_usd: f64,
+
+    /// Score every send target's reputation (0–100) and log it with the
+    /// decision.
+    pub reputation_scoring: bool,
+
+    /// Fleet indexer base URL, queried for targets other vaults have
+    /// blocked. Empty = skip that signal.
+    pub indexer_url: String,
+
+    /// Check ENS names (in typed data and `plimsoll_resolveName`) for
+    /// homoglyphs, lookalikes, forward/reverse mismatches and fresh
+    /// registrations. Off by default; the registry lookups only run on
+    /// chains ENS is deployed on.
+    pub ens_spoof_detection: bool,
+
+    /// ENS registry contract.
+    pub ens_registry: String,
+
+    /// `.eth` base registrar, whose `NameRegistered` logs date a name.
+    /// Empty = skip the registration-age check.
+    pub ens_base_registrar: String,
+
+    /// Comma-separated names the agent deals with; lookalikes are blocked.
+    pub ens_known_names: String,
+
+    /// Reject `.eth` names registered less than this many days ago.
+    pub ens_min_name_age_days: u64,
+
+    /// Require the resolved address to have a primary (reverse) name.
+    pub ens_require_primary_name: bool,
+
+    /// Block swaps whose simulated execution price is more than this
+    /// percentage below the oracle price. 0 = off.
+    pub oracle_max_deviation_pct: f64,
+
+    /// Only swaps paying at least this much (USD) are checked.
+    pub oracle_min_trade_usd: f64,
+
+    /// Chainlink USD price feeds (token address → feed address), checked
+    /// before the reference prices.
+    pub chainlink_feeds: BTreeMap<String, String>,
+
+    /// Ignore Chainlink answers older than this many seconds. 0 = any age.
+    pub oracle_max_staleness_secs: u64,
+
+    /// Trace simulations (`debug_traceCall`) and block re-entrant calls
+    /// into the sender or a vault.
+    pub reentrancy_detection: bool,
+
+    /// Comma-separated agent vaults protected from re-entry (the sender
+    /// always is).
+    pub vault_addresses: String,
+
+    /// Comma-separated selectors a vault may legitimately be called back
+    /// with mid-transaction (NFT receiver hooks by default).
+    pub reentrancy_allowed_callbacks: String,
+
+    /// Trace simulations and flag DELEGATECALLs from the sender, a vault or
+    /// a module into code that is neither allowlisted nor pinned.
+    pub delegatecall_detection: bool,
+
+    /// Block unpinned delegatecalls. `false` only logs a warning.
+    pub block_unknown_delegatecall: bool,
+
+    /// Comma-separated libraries any watched account may delegatecall, on
+    /// top of `safe_delegatecall_allowlist` and pinned implementations.
+    pub delegatecall_allowlist: String,
+
+    /// Comma-separated modules approved on the agent's vaults, whose
+    /// delegatecalls are policed like the vault's own.
+    pub vault_modules: String,
+
+    /// Re-read the target's codehash, EIP-1967 slot and implementation
+    /// codehash just before forwarding and block if they changed since
+    /// simulation.
+    pub enforce_codehash_pin: bool,
+
+    /// Trace simulations and block SELFDESTRUCTs and targets whose code
+    /// was replaced (CREATE2 redeployment) within the lookback window.
+    pub metamorphic_detection: bool,
+
+    /// Blocks back to compare the target's code against. Needs an archive
+    /// upstream; 0 disables the redeploy check.
+    pub redeploy_lookback_blocks: u64,
+
+    /// Simulate a send after the sender's still-pending transactions with
+    /// lower nonces, instead of against latest state alone.
+    pub sequential_nonce_simulation: bool,
+
+    /// Forget a forwarded transaction this long after it was sent (it was
+    /// dropped or replaced out of band).
+    pub pending_tx_ttl_secs: u64,
+
+    /// Trace simulations with their events and compute loss over the
+    /// portfolio's USD value (native balance + priced ERC-20s moved)
+    /// rather than native balance alone.
+    pub portfolio_loss_accounting: bool,
+
+    /// Token whose oracle price is the native coin's (WETH on mainnet).
+    pub native_price_token: String,
+
+    /// Block unlimited ERC-20 approvals, `setApprovalForAll(_, true)` and
+    /// ERC-4494 NFT permits on the send path. Off by default: apart from
+    /// Permit2, the grantees an agent needs (routers, conduits) are per
+    /// chain and must be listed first.
+    pub block_unlimited_approvals: bool,
+
+    /// Comma-separated spenders / operators those grants may go to
+    /// besides Permit2 (e.g. a DEX router, a marketplace conduit).
+    pub approval_operator_allowlist: String,
+
+    /// Comma-separated spenders (DEX routers, Permit2) whose allowances
+    /// may be raised while `block_approval_changes` is on.
+    pub approval_router_allowlist: String,
+
+    /// Cap `maxFeePerGas` / `maxPriorityFeePerGas` / `gasPrice` relative to
+    /// the recent fee market (`eth_feeHistory`).
+    pub gas_fee_ceilings: bool,
+
+    /// Blocks of fee history the market is read from.
+    pub fee_history_blocks: u64,
+
+    /// Max fee ceiling as a multiple of the next block's base fee (plus
+    /// the priority fee ceiling).
+    pub max_base_fee_multiplier: f64,
+
+    /// Priority fee ceiling as a multiple of the recent median tip.
+    pub max_priority_fee_multiplier: f64,
+
+    /// Floor of the priority fee ceiling in gwei, for chains whose median
+    /// tip is near zero.
+    pub min_priority_fee_cap_gwei: f64,
+
+    /// Absolute max fee / gas price ceiling in gwei (0 = none). Applies
+    /// even when the fee history is unavailable.
+    pub max_fee_per_gas_gwei: f64,
+
+    /// What to do with a send whose nonce skips ahead of the sender's
+    /// next one: "block", "rewrite" (unsigned sends get the next nonce)
+    /// or "off".
+    pub nonce_gap_policy: String,
+
+    /// Nonces a send may run ahead of the expected one (0 = none).
+    pub max_nonce_gap: u64,
+
+    /// Only allow a pending nonce to be reused by a speed-up (same
+    /// contents) or a cancel, and hold speed-ups to the original's
+    /// simulated loss.
+    pub replacement_guard: bool,
+
+    /// Track forwarded `eth_sendTransaction`s until mined; resubmit stuck
+    /// ones with bumped fees and cancel ones whose target turns malicious.
+    pub tx_rescue: bool,
+
+    /// Seconds between rescue queue polls.
+    pub rescue_poll_secs: u64,
+
+    /// Blocks a transaction may wait unmined before it is bumped.
+    pub rescue_after_blocks: u64,
+
+    /// Fee bumps per transaction.
+    pub rescue_max_bumps: u32,
+
+    /// Fee increase per bump, in % (nodes require at least 10).
+    pub rescue_fee_bump_pct: f64,
+
+    /// Cancel a pending transaction whose target enters the threat feed.
+    pub rescue_cancel_unsafe: bool,
+
+    /// Endpoint EIP-4844 blob transactions are forwarded to, for
+    /// upstreams that reject sidecars (empty = `upstream_rpc_url`).
+    pub blob_upstream_rpc_url: String,
+
+    /// On OP Stack and Arbitrum (by `expected_chain_id`), charge the L1
+    /// data fee to the simulation before physics.
+    pub l1_fee_accounting: bool,
+
+    /// Chains served besides the default one (`chain_id` on
+    /// `upstream_rpc_url`), selected per request by the `plimsoll-chain`
+    /// header or the agent's `chain_id`.
+    pub chains: Vec<ChainUpstream>,
+
+    /// Rolling per-agent outflow caps (token address or `native` →
+    /// limit). Tokens not listed are uncapped.
+    pub token_velocity_limits: BTreeMap<String, VelocityLimit>,
+
+    /// Block sends once the agent's portfolio would be more than this %
+    /// below its high-water mark (0 = disabled).
+    pub max_drawdown_pct: f64,
+
+    /// Interval between portfolio value snapshots, in seconds.
+    pub drawdown_snapshot_secs: u64,
+
+    /// Comma-separated contracts a vault may call (empty = any). Filled
+    /// from the vaults' whitelist modules by `vault_policy_sync`.
+    pub vault_target_allowlist: String,
+
+    /// Read the velocity, whitelist and drawdown modules of every vault
+    /// in `vault_addresses` and enforce their limits in the proxy too.
+    pub vault_policy_sync: bool,
+
+    /// Interval between vault policy syncs, in seconds.
+    pub vault_policy_sync_secs: u64,
+
+    /// Comma-separated contracts sends may ultimately call, after vault,
+    /// Safe and multicall unwrapping, and typed data may name as its
+    /// verifying contract or spender (empty = any).
+    pub target_allowlist: String,
+
+    /// Fill an empty `target_allowlist` from the vaults' whitelist
+    /// modules (needs `vault_policy_sync`).
+    pub target_allowlist_from_vaults: bool,
+
+    /// Seconds after which a mempool revocation that hasn't landed
+    /// on-chain is lifted (0 = never).
+    pub pessimistic_revocation_ttl_secs: u64,
+
+    /// Interval between pessimistic revocation checks, in seconds.
+    pub revocation_reconcile_secs: u64,
+
+    /// Redis shared by the proxy replicas for revocations, the Paymaster
+    /// sever and blocked txs (empty = not clustered).
+    pub cluster_redis_url: String,
+
+    /// Prefix of the cluster's Redis keys and pub/sub channel.
+    pub cluster_namespace: String,
+
+    /// Comma-separated base URLs of peer proxies to gossip IOCs with
+    /// (empty = no gossip).
+    pub gossip_peers: String,
+
+    /// Shared bearer secret of the gossip fleet; also enables receiving.
+    pub gossip_secret: String,
+
+    /// Comma-separated engines whose gossiped IOCs blacklist the target.
+    pub gossip_engines: String,
+
+    /// Seconds a target blacklisted by peer gossip stays blocked (0 = gossip
+    /// is relayed but never applied).
+    pub gossip_ttl_secs: u64,
+
+    /// Threat feed polled for Engine 0 updates (empty = Cloud push only).
+    pub threat_feed_url: String,
+
+    /// Interval between threat feed polls, in seconds.
+    pub threat_feed_refresh_secs: u64,
+
+    /// File the Engine 0 filter is persisted to on refresh and loaded from
+    /// at startup (empty = no warm start).
+    pub threat_filter_cache_path: String,
+
+    /// Comma-separated addresses, selectors or calldata hashes that an
+    /// Engine 0 hit never blocks (feed false-positive override).
+    pub threat_allowlist: String,
+
+    /// Additional threat intelligence sources merged into Engine 0.
+    pub threat_sources: Vec<ThreatSource>,
+
+    /// OFAC SDN list (file or http(s) URL) screened by the sanctions engine
+    /// (empty = off). Required at startup once set.
+    pub sanctions_list_url: String,
+
+    /// Seconds between sanctions list reloads (minimum 60).
+    pub sanctions_refresh_secs: u64,
+
+    /// Append-only JSON-lines file receiving one audit record per sanctions
+    /// block (empty = tracing only).
+    pub sanctions_audit_path: String,
+
+    /// GOD-TIER 1: check EIP-712 domain names and linked hosts for
+    /// homoglyphs, lookalikes of protected names and feed-listed domains.
+    pub phishing_detection: bool,
+
+    /// Comma-separated protocol names whose lookalikes are blocked.
+    pub phishing_protected_names: String,
+
+    /// Phishing domain feed (file or http(s) URL; empty = homoglyph checks
+    /// only).
+    pub phishing_feed_url: String,
+
+    /// Seconds between phishing feed reloads.
+    pub phishing_feed_refresh_secs: u64,
+
+    /// Seconds the Engine 0 entries named by a false-positive report stay
+    /// allowlisted pending review (0 = record reports only).
+    pub false_positive_whitelist_secs: u64,
+
+    /// Upload false-positive reports to Plimsoll Cloud.
+    pub false_positive_upload: bool,
+
+    /// JSON-lines file IOCs are spooled to while the Cloud is unreachable
+    /// (empty = keep them in memory only).
+    pub ioc_spool_path: String,
+
+    /// Plimsoll Cloud IOC intake (empty = log IOCs locally only).
+    pub ioc_uplink_url: String,
+
+    /// Air-gapped mode: no IOC or false-positive report ever leaves the
+    /// proxy; IOCs are still recorded in the local log.
+    pub telemetry_offline: bool,
+
+    /// ed25519 key signing uplinked IOCs, created if missing (empty = an
+    /// ephemeral key per process).
+    pub proxy_identity_key_path: String,
+
+    /// SQLite database keeping every extracted IOC for STIX / TAXII export
+    /// (empty = off).
+    pub ioc_store_path: String,
+
+    /// Seconds a honeypot token or drainer-site spender stays on the local
+    /// Engine 0 blocklist (0 = off).
+    pub local_blocklist_ttl_secs: u64,
+
+    /// Control tokens the read-path sanitizer handles on top of its built-in
+    /// list, each stripped, replaced or blocking the whole response.
+    pub sanitizer_patterns: Vec<SanitizerPattern>,
+
+    /// Seconds between protocol fee settlements: each agent owing at least
+    /// `fee_settlement_min_wei` is sent a transfer to `fee_collector`
+    /// (0 = fees only accrue).
+    pub fee_settlement_interval_secs: u64,
+
+    /// Smallest owed fee worth a settlement transaction, wei (decimal).
+    pub fee_settlement_min_wei: String,
+
+    /// Smallest owed fee worth a settlement transaction in USD, for every
+    /// asset the oracle prices (0 = off). Unpriced ERC-20 fees settle at
+    /// any amount.
+    pub fee_settlement_min_usd: f64,
+
+    /// What the protocol fee is charged in: `native` (wei, on the tx value)
+    /// or `traded` (in kind, on each ERC-20 the simulation shows sent out).
+    pub fee_denomination: String,
+
+    /// Comma-separated ERC-20s a `traded` fee may be charged in (empty =
+    /// any); sends moving none of them pay in wei.
+    pub fee_tokens: String,
+
+    /// Comma-separated agent (`from`) addresses charged no protocol fee.
+    pub fee_exempt_addresses: String,
+
+    /// Value-tiered fee schedule replacing the flat `fee_bps` for sends
+    /// that can be priced (the native asset is priced as `native` in
+    /// `reference_prices`). Empty = `fee_bps` for everything.
+    pub fee_tiers: Vec<FeeTier>,
+
+    /// SQLite database recording every fee charged and settled, for
+    /// `plimsoll_getFeeReport` and the CSV export (empty = off).
+    pub fee_ledger_path: String,
+
+    /// Synthetic receipts look mined: intrinsic gasUsed and the upstream
+    /// block number when first polled. Off = zero gas at block 0.
+    pub synthetic_receipt_realistic: bool,
+
+    /// Seconds a blocked tx's receipt stays pending (null) before the
+    /// synthetic receipt appears (0 = at once).
+    pub synthetic_receipt_delay_secs: u64,
+
+    /// Attach a `plimsoll` object to every response: risk score, engines
+    /// run, simulated block and latency per stage.
+    pub response_metadata: bool,
+
+    /// Start in shadow (log-only) mode: every engine evaluates, would-block
+    /// decisions are recorded, all traffic is forwarded unchanged. Toggled
+    /// at runtime via `POST /admin/shadow-mode`.
+    pub shadow_mode: bool,
+
+    /// Comma-separated engines in shadow while the rest enforce
+    /// (e.g. `physics,velocity`). See `shadow::ENGINES`.
+    pub shadow_engines: String,
+
+    /// Protection mode of raw message signing (`eth_sign`, `personal_sign`):
+    /// `block`, `warn` (log and forward) or `approve` (hold for an operator).
+    pub raw_sign_mode: String,
+
+    /// Protection mode of dangerous EIP-712 primary types.
+    pub typed_data_mode: String,
+
+    /// Protection mode of physics violations.
+    pub physics_mode: String,
+
+    /// Protection mode of threat feed (Engine 0) hits.
+    pub threat_feed_mode: String,
+
+    /// Seconds a request held for approval stays queued, and an approval
+    /// stays redeemable.
+    pub approval_ttl_secs: u64,
+
+    /// Webhooks (generic JSON, Slack, Telegram) notified of blocks,
+    /// Paymaster severs and session revocations.
+    pub notify_targets: Vec<NotifyTarget>,
+
+    /// Append-only, hash-chained JSON-lines log of every decision (empty =
+    /// off). Restart required.
+    pub audit_log_path: String,
+
+    /// Seconds between signed audit log checkpoints.
+    pub audit_checkpoint_secs: u64,
+
+    /// Comma-separated ed25519 public keys (hex) accepted on audit
+    /// checkpoints besides the proxy identity, e.g. a rotated-out key.
+    pub audit_trusted_signers: String,
+
+    /// Stream blocks and Paymaster severs to the fleet indexer
+    /// (`POST {indexer_url}/events/proxy`) for the dashboard.
+    pub indexer_events: bool,
+
+    /// Bearer token the indexer requires on `/events/proxy`.
+    pub indexer_ingest_token: String,
+
+    /// Serve the MCP tools (`check_transaction`, `explain_block`,
+    /// `get_vault_limits`) on `POST /mcp`.
+    pub mcp_server: bool,
+
+    /// Port of the gRPC decisions API (0 = off). Needs a build with
+    /// `--features grpc`. Restart required.
+    pub grpc_port: u16,
+
+    /// Unix domain socket to serve on as well as TCP (empty = off), like
+    /// geth's `--ipcpath`. Restart required.
+    pub ipc_path: String,
+
+    /// PEM certificate chain for HTTPS on the main listener (empty = plain
+    /// HTTP). Set together with `tls_key_path`. Restart required.
+    pub tls_cert_path: String,
+
+    /// PEM private key matching `tls_cert_path`.
+    pub tls_key_path: String,
+
+    /// PEM bundle of the CA(s) issuing agents' client certificates (empty =
+    /// no client certificates). When set, every connection must present
+    /// one (mutual TLS); agents' `client_cert` maps it to an identity.
+    /// Needs `tls_cert_path`. Restart required.
+    pub tls_client_ca_path: String,
+
+    /// Comma-separated domains to obtain certificates for from Let's
+    /// Encrypt (empty = off). Needs a build with `--features acme`;
+    /// exclusive with `tls_cert_path`. Restart required.
+    pub tls_acme_domains: String,
+
+    /// Comma-separated ACME account contact emails.
+    pub tls_acme_contact: String,
+
+    /// Directory caching the ACME account and certificates.
+    pub tls_acme_cache_dir: String,
+
+    /// Order from the Let's Encrypt staging directory instead of production.
+    pub tls_acme_staging: bool,
+
+    /// On SIGTERM, how long in-flight requests may run before the proxy
+    /// flushes its state and exits anyway.
+    pub shutdown_grace_secs: u64,
+}
+
+/// A tier of the protocol fee schedule: sends worth at least `min_usd`
+/// pay `bps`.
+///
+/// ```toml
+/// [[fee_tiers]]
+/// min_usd = 0.0
+/// bps = 2
+///
+/// [[fee_tiers]]
+/// min_usd = 10000.0
+/// bps = 1
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub min_usd: f64,
+    pub bps: u16,
+}
+
+/// USD reference price of a token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferencePrice {
+    pub usd: f64,
+    pub decimals: u8,
+}
+
+/// An EIP-712 domain exempt from the `permit_decoder` block.
+///
+/// ```toml
+/// [[eip712_trusted_domains]]
+/// verifying_contract = "0x000000000022D473030F116dDEE9F6B43aC78BA3"
+/// name = "Permit2"
+/// chain_id = 1
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustedTypedDataDomain {
+    pub verifying_contract: String,
+    pub name: String,
+    pub chain_id: u64,
+}
+
+/// An agent's API key and the `from` addresses bound to it.
+///
+/// ```toml
+/// [[agents]]
+/// name = "treasury-bot"
+/// api_key = "pk_live_..."
+/// addresses = ["0xAbC...", "0xDeF..."]
+/// # mutual TLS: SHA-256 fingerprint of the agent's client certificate
+/// client_cert = "3F:A1:..."
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentCredential {
+    /// Human-readable agent name (logs, metrics).
+    pub name: String,
+    /// Secret presented by the agent on every request. May be empty when
+    /// `client_cert` is set.
+    #[serde(default)]
+    pub api_key: String,
+    /// Addresses this agent may use as `from` / signer.
+    pub addresses: Vec<String>,
+    /// Chain the agent is served on (0 = the default chain).
+    #[serde(default)]
+    pub chain_id: u64,
+    /// SHA-256 fingerprint of the agent's client certificate (mutual TLS,
+    /// hex, colons optional). Once set, the agent must connect with it.
+    #[serde(default)]
+    pub client_cert: String,
+}
+
+/// Outflow cap of one token, as `VelocityLimitModule` enforces on-chain.
+/// Amounts are base units, decimal.
+///
+/// ```toml
+/// [token_velocity_limits.native]
+/// max_per_window = "5000000000000000000"
+/// max_single_tx = "1000000000000000000"
+/// window_secs = 3600
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VelocityLimit {
+    pub max_per_window: String,
+    /// Empty = no single-transaction cap.
+    #[serde(default)]
+    pub max_single_tx: String,
+    #[serde(default = "default_velocity_window_secs")]
+    pub window_secs: u64,
+}
+
+fn default_velocity_window_secs() -> u64 {
+    3600
+}
+
+/// An additional chain served by the same proxy.
+///
+/// ```toml
+/// [[chains]]
+/// chain_id = 8453
+/// upstream_rpc_url = "https://mainnet.base.org"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainUpstream {
+    pub chain_id: u64,
+    pub upstream_rpc_url: String,
+    /// Blob-capable endpoint for this chain (empty = `upstream_rpc_url`).
+    #[serde(default)]
+    pub blob_upstream_rpc_url: String,
+}
+
+/// An additional threat intelligence source: a CSV export (Chainalysis,
+/// TRM, or a local list) read from a file or an http(s) URL.
+///
+/// ```toml
+/// [[threat_sources]]
+/// label = "chainalysis"
+/// location = "/etc/plimsoll/chainalysis-sanctioned.csv"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThreatSource {
+    /// Name recorded in block reasons and IOCs.
+    pub label: String,
+    pub location: String,
+}
+
+/// A destination for security event notifications (see `notify`).
+///
+/// ```toml
+/// [[notify_targets]]
+/// kind = "slack"
+/// url = "https://hooks.slack.com/services/T000/B000/XXXX"
+/// events = ["blocked", "paymaster_severed"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyTarget {
+    /// `json` (generic webhook), `slack` or `telegram`.
+    pub kind: String,
+    /// Webhook URL; for Telegram `https://api.telegram.org/bot<token>/sendMessage`.
+    pub url: String,
+    /// Telegram chat posted to.
+    #[serde(default)]
+    pub chat_id: String,
+    /// Events sent (`blocked`, `paymaster_severed`, `session_revoked`);
+    /// empty = all.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Message with `{placeholders}`; empty = the event's default.
+    #[serde(default)]
+    pub template: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            upstream_rpc_url: DEMO_UPSTREAM_RPC.into(),
+            host: "0.0.0.0".into(),
+            port: 8545,
+            fee_bps: 2,
+            fee_collector: "0x0000000000000000000000000000000000000000".into(),
+            max_loss_pct: 20.0,
+            block_approval_changes: true,
+            flashbots_enabled: false,
+            flashbots_relay_url: "https://relay.flashbots.net".into(),
+            fork_block: 0,
+            simulation_gas_ceiling: 5_000_000,
+            simulation_timeout_ms: 50,
+            max_bundle_deadline_secs: 24,
+            // v1.0.2: Zero-Day Patches
+            sanitize_read_responses: false,
+            detect_non_determinism: false,
+            expected_chain_id: 0,
+            max_userop_gas: 0,
+            revert_strike_max: 0,
+            revert_strike_window_secs: 300,
+            // v1.0.3: Bounty Patches
+            reject_duplicate_json_keys: false,
+            check_proxy_impl_slot: false,
+            chain_id: 1,
+            gas_anomaly_ratio: 0.0,
+            // v1.0.4: Kill-Shots
+            bundler_address: "".into(),
+            max_pre_verification_gas: 0,
+            bridge_refund_check: false,
+            bridge_contracts: "".into(),
+            max_permit_duration_secs: 0,
+            // v2.0: Multi-Chain
+            svm_enabled: false,
+            svm_whitelisted_accounts: "".into(),
+            utxo_enabled: false,
+            utxo_max_fee_usd: 50.0,
+            btc_price_usd: 60_000.0,
+            http_proxy_enabled: false,
+            http_proxy_port: 8080,
+            http_governed_domains: "".into(),
+            // v2.1: Operational Hardening
+            state_db_path: "".into(),
+            state_snapshot_interval_secs: 5,
+            otlp_endpoint: "".into(),
+            otel_service_name: "plimsoll-rpc".into(),
+            threat_feed_max_age_secs: 0,
+            admin_token: "".into(),
+            agents: Vec::new(),
+            rate_limit_send_per_min: 0,
+            rate_limit_sign_per_min: 0,
+            rate_limit_read_per_min: 0,
+            rate_limit_burst: 0,
+            method_allowlist: "".into(),
+            method_denylist: "".into(),
+            siwe_allowed_domains: "".into(),
+            siwe_max_validity_secs: 3600,
+            eip712_trusted_domains: Vec::new(),
+            permit2_token_caps: BTreeMap::new(),
+            permit2_max_expiration_secs: 0,
+            seaport_min_nft_consideration_wei: "0".into(),
+            seaport_eth_equivalent_tokens: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".into(),
+            seaport_min_offerer_share_pct: 50.0,
+            seaport_min_consideration_ratio: 0.5,
+            seaport_min_nft_consideration_usd: 1.0,
+            order_max_price_deviation_pct: 0.0,
+            uniswapx_reactors: DEFAULT_UNISWAPX_REACTORS.into(),
+            reference_prices: BTreeMap::new(),
+            price_oracle_url: "".into(),
+            eip7702_allowed_delegates: "".into(),
+            safe_delegatecall_allowlist: DEFAULT_SAFE_DELEGATECALL_ALLOWLIST.into(),
+            resolve_proxy_implementations: true,
+            selector_db_path: "".into(),
+            selector_lookup_url: "".into(),
+            blocked_functions: "".into(),
+            abi_dir: "".into(),
+            abi_sourcify_url: "".into(),
+            etherscan_api_url: "https://api.etherscan.io/v2/api".into(),
+            etherscan_api_key: "".into(),
+            honeypot_detection: true,
+            honeypot_max_tax_pct: 10.0,
+            known_tokens: DEFAULT_KNOWN_TOKENS.into(),
+            rugpull_heuristics: false,
+            rugpull_block_score: 70,
+            uniswap_v2_factory: "0x5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f".into(),
+            lp_quote_token: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".into(),
+            contract_verification_check: false,
+            unverified_min_age_hours: 48,
+            address_poisoning_check: true,
+            poisoning_match_chars: 4,
+            block_address_poisoning: true,
+            first_interaction_requires_approval: false,
+            first_interaction_max_value_wei: "".into(),
+            first_interaction_max_value_usd: 0.0,
+            reputation_scoring: true,
+            indexer_url: "".into(),
+            ens_spoof_detection: false,
+            ens_registry: "0x00000000000c2e074ec69a0dfb2997ba6c7d2e1e".into(),
+            ens_base_registrar: "0x57f1887a8bf19b14fc0df6fd9b2acc9af147ea85".into(),
+            ens_known_names: "".into(),
+            ens_min_name_age_days: 30,
+            ens_require_primary_name: false,
+            oracle_max_deviation_pct: 0.0,
+            oracle_min_trade_usd: 10_000.0,
+            chainlink_feeds: DEFAULT_CHAINLINK_FEEDS
+                .iter()
+                .map(|(token, feed)| (token.to_string(), feed.to_string()))
+                .collect(),
+            oracle_max_staleness_secs: 3600,
+            reentrancy_detection: true,
+            vault_addresses: "".into(),
+            reentrancy_allowed_callbacks: "0x150b7a02,0xf23a6e61,0xbc197c81".into(),
+            delegatecall_detection: true,
+            block_unknown_delegatecall: true,
+            delegatecall_allowlist: "".into(),
+            vault_modules: "".into(),
+            enforce_codehash_pin: true,
+            metamorphic_detection: true,
+            redeploy_lookback_blocks: 50_400,
+            sequential_nonce_simulation: true,
+            pending_tx_ttl_secs: 600,
+            portfolio_loss_accounting: true,
+            native_price_token: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".into(),
+            block_unlimited_approvals: false,
+            approval_operator_allowlist: "".into(),
+            // Permit2, Uniswap Universal Router (v3, v4), 1inch v6, 0x Exchange Proxy
+            approval_router_allowlist: "0x000000000022d473030f116ddee9f6b43ac78ba3,\
+                                        0x3fc91a3afd70395cd496c647d5a6cc9d4b2b7fad,\
+                                        0x66a9893cc07d91d95644aedd05d03f95e1dba8af,\
+                                        0x111111125421ca6dc452d289314280a0f8842a65,\
+                                        0xdef1c0ded9bec7f1a1670819833240f027b25eff"
+                .into(),
+            gas_fee_ceilings: true,
+            fee_history_blocks: 20,
+            max_base_fee_multiplier: 3.0,
+            max_priority_fee_multiplier: 5.0,
+            min_priority_fee_cap_gwei: 2.0,
+            max_fee_per_gas_gwei: 0.0,
+            nonce_gap_policy: "block".into(),
+            max_nonce_gap: 0,
+            replacement_guard: true,
+            tx_rescue: false,
+            rescue_poll_secs: 12,
+            rescue_after_blocks: 5,
+            rescue_max_bumps: 3,
+            rescue_fee_bump_pct: 15.0,
+            rescue_cancel_unsafe: true,
+            blob_upstream_rpc_url: "".into(),
+            l1_fee_accounting: true,
+            chains: Vec::new(),
+            token_velocity_limits: BTreeMap::new(),
+            max_drawdown_pct: 0.0,
+            drawdown_snapshot_secs: 60,
+            vault_target_allowlist: "".into(),
+            vault_policy_sync: false,
+            vault_policy_sync_secs: 300,
+            target_allowlist: "".into(),
+            target_allowlist_from_vaults: false,
+            pessimistic_revocation_ttl_secs: 1800,
+            revocation_reconcile_secs: 60,
+            cluster_redis_url: "".into(),
+            cluster_namespace: "plimsoll".into(),
+            gossip_peers: "".into(),
+            gossip_secret: "".into(),
+            gossip_engines: "metamorphic,reentrancy,delegatecall,approval_diff,eip712_permit,permit_decoder".into(),
+            gossip_ttl_secs: 3600,
+            threat_feed_url: "".into(),
+            threat_feed_refresh_secs: 300,
+            threat_filter_cache_path: "".into(),
+            threat_allowlist: "".into(),
+            threat_sources: Vec::new(),
+            sanctions_list_url: "".into(),
+            sanctions_refresh_secs: 86_400,
+            sanctions_audit_path: "".into(),
+            phishing_detection: true,
+            phishing_protected_names: "Uniswap,Permit2,OpenSea,Seaport,Aave,Compound,Curve,1inch,CoW Protocol,\
+                                       MetaMask,Lido,USD Coin,Tether,Blur,Safe"
+                .into(),
+            phishing_feed_url: "".into(),
+            phishing_feed_refresh_secs: 3600,
+            false_positive_whitelist_secs: 0,
+            false_positive_upload: false,
+            ioc_spool_path: "".into(),
+            ioc_uplink_url: "https://cloud.plimsoll.network/v1/ioc".into(),
+            telemetry_offline: false,
+            proxy_identity_key_path: "".into(),
+            ioc_store_path: "".into(),
+            local_blocklist_ttl_secs: 3600,
+            sanitizer_patterns: Vec::new(),
+            fee_settlement_interval_secs: 0,
+            fee_settlement_min_wei: "1000000000000000".into(),
+            fee_settlement_min_usd: 0.0,
+            fee_denomination: "native".into(),
+            fee_tokens: String::new(),
+            fee_exempt_addresses: String::new(),
+            fee_tiers: Vec::new(),
+            fee_ledger_path: "".into(),
+            synthetic_receipt_realistic: false,
+            synthetic_receipt_delay_secs: 0,
+            response_metadata: false,
+            shadow_mode: false,
+            shadow_engines: "".into(),
+            raw_sign_mode: "block".into(),
+            typed_data_mode: "block".into(),
+            physics_mode: "block".into(),
+            threat_feed_mode: "block".into(),
+            approval_ttl_secs: 3600,
+            notify_targets: Vec::new(),
+            audit_log_path: "".into(),
+            audit_checkpoint_secs: 300,
+            audit_trusted_signers: "".into(),
+            indexer_events: false,
+            indexer_ingest_token: "".into(),
+            mcp_server: false,
+            grpc_port: 0,
+            ipc_path: "".into(),
+            tls_cert_path: "".into(),
+            tls_key_path: "".into(),
+            tls_client_ca_path: "".into(),
+            tls_acme_domains: "".into(),
+            tls_acme_contact: "".into(),
+            tls_acme_cache_dir: "plimsoll_acme".into(),
+            tls_acme_staging: false,
+            shutdown_grace_secs: 30,
+        }
+    }
 }
 
 impl Config {
+    /// Built-in defaults overridden by `PLIMSOLL_*` environment variables.
     pub fn from_env() -> Result<Self> {
-        Ok(Config {
-            upstream_rpc_url: std::env::var("PLIMSOLL_UPSTREAM_RPC")
-                .unwrap_or_else(|_| "https://eth-mainnet.g.alchemy.com/v2/demo".into()),
-            host: std::env::var("PLIMSOLL_HOST").unwrap_or_else(|_| "0.0.0.0".into()),
-            port: std::env::var("PLIMSOLL_PORT")
-                .unwrap_or_else(|_| "8545".into())
-                .parse()
-                .context("Invalid PLIMSOLL_PORT")?,
-            fee_bps: std::env::var("PLIMSOLL_FEE_BPS")
-                .unwrap_or_else(|_| "2".into())
-                .parse()
-                .context("Invalid PLIMSOLL_FEE_BPS")?,
-            fee_collector: std::env::var("PLIMSOLL_FEE_COLLECTOR")
-                .unwrap_or_else(|_| "0x0000000000000000000000000000000000000000".into()),
-            max_loss_pct: std::env::var("PLIMSOLL_MAX_LOSS_PCT")
-                .unwrap_or_else(|_| "20.0".into())
-                .parse()
-                .context("Invalid PLIMSOLL_MAX_LOSS_PCT")?,
-            block_approval_changes: std::env::var("PLIMSOLL_BLOCK_APPROVALS")
-                .unwrap_or_else(|_| "true".into())
-                .parse()
-                .unwrap_or(true),
-            flashbots_enabled: std::env::var("PLIMSOLL_FLASHBOTS_ENABLED")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            flashbots_relay_url: std::env::var("PLIMSOLL_FLASHBOTS_RELAY")
-                .unwrap_or_else(|_| "https://relay.flashbots.net".into()),
-            fork_block: std::env::var("PLIMSOLL_FORK_BLOCK")
-                .unwrap_or_else(|_| "0".into())
-                .parse()
-                .unwrap_or(0),
-            simulation_gas_ceiling: std::env::var("PLIMSOLL_SIM_GAS_CEILING")
-                .unwrap_or_else(|_| "5000000".into())
-                .parse()
-                .unwrap_or(5_000_000),
-            simulation_timeout_ms: std::env::var("PLIMSOLL_SIM_TIMEOUT_MS")
-                .unwrap_or_else(|_| "50".into())
-                .parse()
-                .unwrap_or(50),
-            max_bundle_deadline_secs: std::env::var("PLIMSOLL_MAX_BUNDLE_DEADLINE")
-                .unwrap_or_else(|_| "24".into())
-                .parse()
-                .unwrap_or(24),
-            sanitize_read_responses: std::env::var("PLIMSOLL_SANITIZE_READS")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            detect_non_determinism: std::env::var("PLIMSOLL_DETECT_NONDET")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            expected_chain_id: std::env::var("PLIMSOLL_EXPECTED_CHAIN_ID")
-                .unwrap_or_else(|_| "0".into())
-                .parse()
-                .unwrap_or(0),
-            max_userop_gas: std::env::var("PLIMSOLL_MAX_USEROP_GAS")
-                .unwrap_or_else(|_| "0".into())
-                .parse()
-                .unwrap_or(0),
-            revert_strike_max: std::env::var("PLIMSOLL_REVERT_STRIKE_MAX")
-                .unwrap_or_else(|_| "0".into())
-                .parse()
-                .unwrap_or(0),
-            revert_strike_window_secs: std::env::var("PLIMSOLL_REVERT_STRIKE_WINDOW")
-                .unwrap_or_else(|_| "300".into())
-                .parse()
-                .unwrap_or(300),
-            reject_duplicate_json_keys: std::env::var("PLIMSOLL_REJECT_DUPLICATE_KEYS")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            check_proxy_impl_slot: std::env::var("PLIMSOLL_CHECK_PROXY_IMPL")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            chain_id: std::env::var("PLIMSOLL_CHAIN_ID")
-                .unwrap_or_else(|_| "1".into())
-                .parse()
-                .unwrap_or(1),
-            gas_anomaly_ratio: std::env::var("PLIMSOLL_GAS_ANOMALY_RATIO")
-                .unwrap_or_else(|_| "0.0".into())
-                .parse()
-                .unwrap_or(0.0),
-            bundler_address: std::env::var("PLIMSOLL_BUNDLER_ADDRESS")
-                .unwrap_or_else(|_| "".into()),
-            max_pre_verification_gas: std::env::var("PLIMSOLL_MAX_PVG")
-                .unwrap_or_else(|_| "0".into())
-                .parse()
-                .unwrap_or(0),
-            bridge_refund_check: std::env::var("PLIMSOLL_BRIDGE_REFUND_CHECK")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            bridge_contracts: std::env::var("PLIMSOLL_BRIDGE_CONTRACTS")
-                .unwrap_or_else(|_| "".into()),
-            max_permit_duration_secs: std::env::var("PLIMSOLL_MAX_PERMIT_DURATION")
-                .unwrap_or_else(|_| "0".into())
-                .parse()
-                .unwrap_or(0),
-            // v2.0: Multi-Chain
-            svm_enabled: std::env::var("PLIMSOLL_SVM_ENABLED")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            svm_whitelisted_accounts: std::env::var("PLIMSOLL_SVM_WHITELISTED_ACCOUNTS")
-                .unwrap_or_else(|_| "".into()),
-            utxo_enabled: std::env::var("PLIMSOLL_UTXO_ENABLED")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            utxo_max_fee_usd: std::env::var("PLIMSOLL_UTXO_MAX_FEE_USD")
-                .unwrap_or_else(|_| "50.0".into())
-                .parse()
-                .unwrap_or(50.0),
-            btc_price_usd: std::env::var("PLIMSOLL_BTC_PRICE_USD")
-                .unwrap_or_else(|_| "60000.0".into())
-                .parse()
-                .unwrap_or(60_000.0),
-            http_proxy_enabled: std::env::var("PLIMSOLL_HTTP_PROXY_ENABLED")
-                .unwrap_or_else(|_| "false".into())
-                .parse()
-                .unwrap_or(false),
-            http_proxy_port: std::env::var("PLIMSOLL_HTTP_PROXY_PORT")
-                .unwrap_or_else(|_| "8080".into())
-                .parse()
-                .unwrap_or(8080),
-            http_governed_domains: std::env::var("PLIMSOLL_HTTP_GOVERNED_DOMAINS")
-                .unwrap_or_else(|_| "".into()),
-        })
+        let mut cfg = Config::default();
+        cfg.apply_env()?;
+        Ok(cfg)
+    }
+
+    /// Load the effective configuration: defaults, then the optional config
+    /// file, then environment overrides. The result is validated.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut cfg = match path {
+            Some(p) => Self::from_file(p)?,
+            None => Config::default(),
+        };
+        cfg.apply_env()?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// Parse a TOML or YAML config file (selected by extension).
+    /// Keys omitted from the file keep their built-in defaults.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        match ext {
+            "toml" => toml::from_str(&raw)
+                .with_context(|| format!("Invalid TOML config {}", path.display())),
+            "yaml" | "yml" => serde_yaml::from_str(&raw)
+                .with_context(|| format!("Invalid YAML config {}", path.display())),
+            other => anyhow::bail!(
+                "Unsupported config file extension '.{}' (expected .toml, .yaml or .yml)",
+                other
+            ),
+        }
+    }
+
+    /// Apply `PLIMSOLL_*` environment overrides on top of the current values.
+    /// A set-but-unparseable variable is a startup error.
+    pub fn apply_env(&mut self) -> Result<()> {
+        env_string("PLIMSOLL_UPSTREAM_RPC", &mut self.upstream_rpc_url);
+        env_string("PLIMSOLL_HOST", &mut self.host);
+        env_parse("PLIMSOLL_PORT", &mut self.port)?;
+        env_parse("PLIMSOLL_FEE_BPS", &mut self.fee_bps)?;
+        env_string("PLIMSOLL_FEE_COLLECTOR", &mut self.fee_collector);
+        env_parse("PLIMSOLL_MAX_LOSS_PCT", &mut self.max_loss_pct)?;
+        env_parse("PLIMSOLL_BLOCK_APPROVALS", &mut self.block_approval_changes)?;
+        env_parse("PLIMSOLL_FLASHBOTS_ENABLED", &mut self.flashbots_enabled)?;
+        env_string("PLIMSOLL_FLASHBOTS_RELAY", &mut self.flashbots_relay_url);
+        env_parse("PLIMSOLL_FORK_BLOCK", &mut self.fork_block)?;
+        env_parse("PLIMSOLL_SIM_GAS_CEILING", &mut self.simulation_gas_ceiling)?;
+        env_parse("PLIMSOLL_SIM_TIMEOUT_MS", &mut self.simulation_timeout_ms)?;
+        env_parse("PLIMSOLL_MAX_BUNDLE_DEADLINE", &mut self.max_bundle_deadline_secs)?;
+        // v1.0.2: Zero-Day Patches
+        env_parse("PLIMSOLL_SANITIZE_READS", &mut self.sanitize_read_responses)?;
+        env_parse("PLIMSOLL_DETECT_NONDET", &mut self.detect_non_determinism)?;
+        env_parse("PLIMSOLL_EXPECTED_CHAIN_ID", &mut self.expected_chain_id)?;
+        env_parse("PLIMSOLL_MAX_USEROP_GAS", &mut self.max_userop_gas)?;
+        env_parse("PLIMSOLL_REVERT_STRIKE_MAX", &mut self.revert_strike_max)?;
+        env_parse("PLIMSOLL_REVERT_STRIKE_WINDOW", &mut self.revert_strike_window_secs)?;
+        // v1.0.3: Bounty Patches
+        env_parse("PLIMSOLL_REJECT_DUPLICATE_KEYS", &mut self.reject_duplicate_json_keys)?;
+        env_parse("PLIMSOLL_CHECK_PROXY_IMPL", &mut self.check_proxy_impl_slot)?;
+        env_parse("PLIMSOLL_CHAIN_ID", &mut self.chain_id)?;
+        env_parse("PLIMSOLL_GAS_ANOMALY_RATIO", &mut self.gas_anomaly_ratio)?;
+        // v1.0.4: Kill-Shots
+        env_string("PLIMSOLL_BUNDLER_ADDRESS", &mut self.bundler_address);
+        env_parse("PLIMSOLL_MAX_PVG", &mut self.max_pre_verification_gas)?;
+        env_parse("PLIMSOLL_BRIDGE_REFUND_CHECK", &mut self.bridge_refund_check)?;
+        env_string("PLIMSOLL_BRIDGE_CONTRACTS", &mut self.bridge_contracts);
+        env_parse("PLIMSOLL_MAX_PERMIT_DURATION", &mut self.max_permit_duration_secs)?;
+        // v2.0: Multi-Chain
+        env_parse("PLIMSOLL_SVM_ENABLED", &mut self.svm_enabled)?;
+        env_string("PLIMSOLL_SVM_WHITELISTED_ACCOUNTS", &mut self.svm_whitelisted_accounts);
+        env_parse("PLIMSOLL_UTXO_ENABLED", &mut self.utxo_enabled)?;
+        env_parse("PLIMSOLL_UTXO_MAX_FEE_USD", &mut self.utxo_max_fee_usd)?;
+        env_parse("PLIMSOLL_BTC_PRICE_USD", &mut self.btc_price_usd)?;
+        env_parse("PLIMSOLL_HTTP_PROXY_ENABLED", &mut self.http_proxy_enabled)?;
+        env_parse("PLIMSOLL_HTTP_PROXY_PORT", &mut self.http_proxy_port)?;
+        env_string("PLIMSOLL_HTTP_GOVERNED_DOMAINS", &mut self.http_governed_domains);
+        // v2.1: Operational Hardening
+        env_string("PLIMSOLL_STATE_DB", &mut self.state_db_path);
+        env_parse("PLIMSOLL_STATE_SNAPSHOT_INTERVAL", &mut self.state_snapshot_interval_secs)?;
+        env_string("PLIMSOLL_OTLP_ENDPOINT", &mut self.otlp_endpoint);
+        env_string("PLIMSOLL_OTEL_SERVICE_NAME", &mut self.otel_service_name);
+        env_parse("PLIMSOLL_THREAT_FEED_MAX_AGE", &mut self.threat_feed_max_age_secs)?;
+        env_string("PLIMSOLL_ADMIN_TOKEN", &mut self.admin_token);
+        env_json("PLIMSOLL_AGENT_KEYS", &mut self.agents)?;
+        env_parse("PLIMSOLL_RATE_LIMIT_SEND", &mut self.rate_limit_send_per_min)?;
+        env_parse("PLIMSOLL_RATE_LIMIT_SIGN", &mut self.rate_limit_sign_per_min)?;
+        env_parse("PLIMSOLL_RATE_LIMIT_READ", &mut self.rate_limit_read_per_min)?;
+        env_parse("PLIMSOLL_RATE_LIMIT_BURST", &mut self.rate_limit_burst)?;
+        env_string("PLIMSOLL_METHOD_ALLOWLIST", &mut self.method_allowlist);
+        env_string("PLIMSOLL_METHOD_DENYLIST", &mut self.method_denylist);
+        env_string("PLIMSOLL_SIWE_ALLOWED_DOMAINS", &mut self.siwe_allowed_domains);
+        env_parse("PLIMSOLL_SIWE_MAX_VALIDITY", &mut self.siwe_max_validity_secs)?;
+        env_json("PLIMSOLL_EIP712_TRUSTED_DOMAINS", &mut self.eip712_trusted_domains)?;
+        env_json("PLIMSOLL_PERMIT2_TOKEN_CAPS", &mut self.permit2_token_caps)?;
+        env_parse("PLIMSOLL_PERMIT2_MAX_EXPIRATION", &mut self.permit2_max_expiration_secs)?;
+        env_string("PLIMSOLL_SEAPORT_MIN_NFT_WEI", &mut self.seaport_min_nft_consideration_wei);
+        env_string("PLIMSOLL_SEAPORT_ETH_TOKENS", &mut self.seaport_eth_equivalent_tokens);
+        env_parse("PLIMSOLL_SEAPORT_MIN_OFFERER_SHARE_PCT", &mut self.seaport_min_offerer_share_pct)?;
+        env_parse("PLIMSOLL_SEAPORT_MIN_CONSIDERATION_RATIO", &mut self.seaport_min_consideration_ratio)?;
+        env_parse("PLIMSOLL_SEAPORT_MIN_NFT_USD", &mut self.seaport_min_nft_consideration_usd)?;
+        env_parse("PLIMSOLL_ORDER_MAX_PRICE_DEVIATION_PCT", &mut self.order_max_price_deviation_pct)?;
+        env_string("PLIMSOLL_UNISWAPX_REACTORS", &mut self.uniswapx_reactors);
+        env_json("PLIMSOLL_REFERENCE_PRICES", &mut self.reference_prices)?;
+        env_string("PLIMSOLL_PRICE_ORACLE_URL", &mut self.price_oracle_url);
+        env_string("PLIMSOLL_EIP7702_ALLOWED_DELEGATES", &mut self.eip7702_allowed_delegates);
+        env_string("PLIMSOLL_SAFE_DELEGATECALL_ALLOWLIST", &mut self.safe_delegatecall_allowlist);
+        env_parse("PLIMSOLL_RESOLVE_PROXIES", &mut self.resolve_proxy_implementations)?;
+        env_string("PLIMSOLL_SELECTOR_DB", &mut self.selector_db_path);
+        env_string("PLIMSOLL_SELECTOR_LOOKUP_URL", &mut self.selector_lookup_url);
+        env_string("PLIMSOLL_BLOCKED_FUNCTIONS", &mut self.blocked_functions);
+        env_string("PLIMSOLL_ABI_DIR", &mut self.abi_dir);
+        env_string("PLIMSOLL_ABI_SOURCIFY_URL", &mut self.abi_sourcify_url);
+        env_string("PLIMSOLL_ETHERSCAN_API_URL", &mut self.etherscan_api_url);
+        env_string("PLIMSOLL_ETHERSCAN_API_KEY", &mut self.etherscan_api_key);
+        env_parse("PLIMSOLL_HONEYPOT_DETECTION", &mut self.honeypot_detection)?;
+        env_parse("PLIMSOLL_HONEYPOT_MAX_TAX_PCT", &mut self.honeypot_max_tax_pct)?;
+        env_string("PLIMSOLL_KNOWN_TOKENS", &mut self.known_tokens);
+        env_parse("PLIMSOLL_RUGPULL_HEURISTICS", &mut self.rugpull_heuristics)?;
+        env_parse("PLIMSOLL_RUGPULL_BLOCK_SCORE", &mut self.rugpull_block_score)?;
+        env_string("PLIMSOLL_UNISWAP_V2_FACTORY", &mut self.uniswap_v2_factory);
+        env_string("PLIMSOLL_LP_QUOTE_TOKEN", &mut self.lp_quote_token);
+        env_parse("PLIMSOLL_CONTRACT_VERIFICATION", &mut self.contract_verification_check)?;
+        env_parse("PLIMSOLL_UNVERIFIED_MIN_AGE_HOURS", &mut self.unverified_min_age_hours)?;
+        env_parse("PLIMSOLL_ADDRESS_POISONING_CHECK", &mut self.address_poisoning_check)?;
+        env_parse("PLIMSOLL_POISONING_MATCH_CHARS", &mut self.poisoning_match_chars)?;
+        env_parse("PLIMSOLL_BLOCK_ADDRESS_POISONING", &mut self.block_address_poisoning)?;
+        env_parse(
+            "PLIMSOLL_FIRST_INTERACTION_REQUIRES_APPROVAL",
+            &mut self.first_interaction_requires_approval,
+        )?;
+        env_string("PLIMSOLL_FIRST_INTERACTION_MAX_VALUE_WEI", &mut self.first_interaction_max_value_wei);
+        env_parse("PLIMSOLL_FIRST_INTERACTION_MAX_VALUE_USD", &mut self.first_interaction_max_value_usd)?;
+        env_parse("PLIMSOLL_REPUTATION_SCORING", &mut self.reputation_scoring)?;
+        env_string("PLIMSOLL_INDEXER_URL", &mut self.indexer_url);
+        env_parse("PLIMSOLL_ENS_SPOOF_DETECTION", &mut self.ens_spoof_detection)?;
+        env_string("PLIMSOLL_ENS_REGISTRY", &mut self.ens_registry);
+        env_string("PLIMSOLL_ENS_BASE_REGISTRAR", &mut self.ens_base_registrar);
+        env_string("PLIMSOLL_ENS_KNOWN_NAMES", &mut self.ens_known_names);
+        env_parse("PLIMSOLL_ENS_MIN_NAME_AGE_DAYS", &mut self.ens_min_name_age_days)?;
+        env_parse("PLIMSOLL_ENS_REQUIRE_PRIMARY_NAME", &mut self.ens_require_primary_name)?;
+        env_parse("PLIMSOLL_ORACLE_MAX_DEVIATION_PCT", &mut self.oracle_max_deviation_pct)?;
+        env_parse("PLIMSOLL_ORACLE_MIN_TRADE_USD", &mut self.oracle_min_trade_usd)?;
+        env_json("PLIMSOLL_CHAINLINK_FEEDS", &mut self.chainlink_feeds)?;
+        env_parse("PLIMSOLL_ORACLE_MAX_STALENESS_SECS", &mut self.oracle_max_staleness_secs)?;
+        env_parse("PLIMSOLL_REENTRANCY_DETECTION", &mut self.reentrancy_detection)?;
+        env_string("PLIMSOLL_VAULT_ADDRESSES", &mut self.vault_addresses);
+        env_string("PLIMSOLL_REENTRANCY_ALLOWED_CALLBACKS", &mut self.reentrancy_allowed_callbacks);
+        env_parse("PLIMSOLL_DELEGATECALL_DETECTION", &mut self.delegatecall_detection)?;
+        env_parse("PLIMSOLL_BLOCK_UNKNOWN_DELEGATECALL", &mut self.block_unknown_delegatecall)?;
+        env_string("PLIMSOLL_DELEGATECALL_ALLOWLIST", &mut self.delegatecall_allowlist);
+        env_string("PLIMSOLL_VAULT_MODULES", &mut self.vault_modules);
+        env_parse("PLIMSOLL_ENFORCE_CODEHASH_PIN", &mut self.enforce_codehash_pin)?;
+        env_parse("PLIMSOLL_METAMORPHIC_DETECTION", &mut self.metamorphic_detection)?;
+        env_parse("PLIMSOLL_REDEPLOY_LOOKBACK_BLOCKS", &mut self.redeploy_lookback_blocks)?;
+        env_parse("PLIMSOLL_SEQUENTIAL_NONCE_SIMULATION", &mut self.sequential_nonce_simulation)?;
+        env_parse("PLIMSOLL_PENDING_TX_TTL_SECS", &mut self.pending_tx_ttl_secs)?;
+        env_parse("PLIMSOLL_PORTFOLIO_LOSS_ACCOUNTING", &mut self.portfolio_loss_accounting)?;
+        env_string("PLIMSOLL_NATIVE_PRICE_TOKEN", &mut self.native_price_token);
+        env_parse("PLIMSOLL_BLOCK_UNLIMITED_APPROVALS", &mut self.block_unlimited_approvals)?;
+        env_string("PLIMSOLL_APPROVAL_OPERATOR_ALLOWLIST", &mut self.approval_operator_allowlist);
+        env_string("PLIMSOLL_APPROVAL_ROUTER_ALLOWLIST", &mut self.approval_router_allowlist);
+        env_parse("PLIMSOLL_GAS_FEE_CEILINGS", &mut self.gas_fee_ceilings)?;
+        env_parse("PLIMSOLL_FEE_HISTORY_BLOCKS", &mut self.fee_history_blocks)?;
+        env_parse("PLIMSOLL_MAX_BASE_FEE_MULTIPLIER", &mut self.max_base_fee_multiplier)?;
+        env_parse("PLIMSOLL_MAX_PRIORITY_FEE_MULTIPLIER", &mut self.max_priority_fee_multiplier)?;
+        env_parse("PLIMSOLL_MIN_PRIORITY_FEE_CAP_GWEI", &mut self.min_priority_fee_cap_gwei)?;
+        env_parse("PLIMSOLL_MAX_FEE_PER_GAS_GWEI", &mut self.max_fee_per_gas_gwei)?;
+        env_string("PLIMSOLL_NONCE_GAP_POLICY", &mut self.nonce_gap_policy);
+        env_parse("PLIMSOLL_MAX_NONCE_GAP", &mut self.max_nonce_gap)?;
+        env_parse("PLIMSOLL_REPLACEMENT_GUARD", &mut self.replacement_guard)?;
+        env_parse("PLIMSOLL_TX_RESCUE", &mut self.tx_rescue)?;
+        env_parse("PLIMSOLL_RESCUE_POLL_SECS", &mut self.rescue_poll_secs)?;
+        env_parse("PLIMSOLL_RESCUE_AFTER_BLOCKS", &mut self.rescue_after_blocks)?;
+        env_parse("PLIMSOLL_RESCUE_MAX_BUMPS", &mut self.rescue_max_bumps)?;
+        env_parse("PLIMSOLL_RESCUE_FEE_BUMP_PCT", &mut self.rescue_fee_bump_pct)?;
+        env_parse("PLIMSOLL_RESCUE_CANCEL_UNSAFE", &mut self.rescue_cancel_unsafe)?;
+        env_string("PLIMSOLL_BLOB_UPSTREAM_RPC_URL", &mut self.blob_upstream_rpc_url);
+        env_parse("PLIMSOLL_L1_FEE_ACCOUNTING", &mut self.l1_fee_accounting)?;
+        env_json("PLIMSOLL_CHAINS", &mut self.chains)?;
+        env_json("PLIMSOLL_TOKEN_VELOCITY_LIMITS", &mut self.token_velocity_limits)?;
+        env_parse("PLIMSOLL_MAX_DRAWDOWN_PCT", &mut self.max_drawdown_pct)?;
+        env_parse("PLIMSOLL_DRAWDOWN_SNAPSHOT_SECS", &mut self.drawdown_snapshot_secs)?;
+        env_string("PLIMSOLL_VAULT_TARGET_ALLOWLIST", &mut self.vault_target_allowlist);
+        env_parse("PLIMSOLL_VAULT_POLICY_SYNC", &mut self.vault_policy_sync)?;
+        env_parse("PLIMSOLL_VAULT_POLICY_SYNC_SECS", &mut self.vault_policy_sync_secs)?;
+        env_string("PLIMSOLL_TARGET_ALLOWLIST", &mut self.target_allowlist);
+        env_parse("PLIMSOLL_TARGET_ALLOWLIST_FROM_VAULTS", &mut self.target_allowlist_from_vaults)?;
+        env_parse("PLIMSOLL_PESSIMISTIC_REVOCATION_TTL_SECS", &mut self.pessimistic_revocation_ttl_secs)?;
+        env_parse("PLIMSOLL_REVOCATION_RECONCILE_SECS", &mut self.revocation_reconcile_secs)?;
+        env_string("PLIMSOLL_CLUSTER_REDIS_URL", &mut self.cluster_redis_url);
+        env_string("PLIMSOLL_CLUSTER_NAMESPACE", &mut self.cluster_namespace);
+        env_string("PLIMSOLL_GOSSIP_PEERS", &mut self.gossip_peers);
+        env_string("PLIMSOLL_GOSSIP_SECRET", &mut self.gossip_secret);
+        env_string("PLIMSOLL_GOSSIP_ENGINES", &mut self.gossip_engines);
+        env_parse("PLIMSOLL_GOSSIP_TTL_SECS", &mut self.gossip_ttl_secs)?;
+        env_string("PLIMSOLL_THREAT_FEED_URL", &mut self.threat_feed_url);
+        env_parse("PLIMSOLL_THREAT_FEED_REFRESH_SECS", &mut self.threat_feed_refresh_secs)?;
+        env_string("PLIMSOLL_THREAT_FILTER_CACHE", &mut self.threat_filter_cache_path);
+        env_string("PLIMSOLL_THREAT_ALLOWLIST", &mut self.threat_allowlist);
+        env_json("PLIMSOLL_THREAT_SOURCES", &mut self.threat_sources)?;
+        env_string("PLIMSOLL_SANCTIONS_LIST_URL", &mut self.sanctions_list_url);
+        env_parse("PLIMSOLL_SANCTIONS_REFRESH_SECS", &mut self.sanctions_refresh_secs)?;
+        env_string("PLIMSOLL_SANCTIONS_AUDIT_PATH", &mut self.sanctions_audit_path);
+        env_parse("PLIMSOLL_PHISHING_DETECTION", &mut self.phishing_detection)?;
+        env_string("PLIMSOLL_PHISHING_PROTECTED_NAMES", &mut self.phishing_protected_names);
+        env_string("PLIMSOLL_PHISHING_FEED_URL", &mut self.phishing_feed_url);
+        env_parse("PLIMSOLL_PHISHING_FEED_REFRESH_SECS", &mut self.phishing_feed_refresh_secs)?;
+        env_parse("PLIMSOLL_FALSE_POSITIVE_WHITELIST_SECS", &mut self.false_positive_whitelist_secs)?;
+        env_parse("PLIMSOLL_FALSE_POSITIVE_UPLOAD", &mut self.false_positive_upload)?;
+        env_string("PLIMSOLL_IOC_SPOOL_PATH", &mut self.ioc_spool_path);
+        env_string("PLIMSOLL_IOC_UPLINK_URL", &mut self.ioc_uplink_url);
+        env_parse("PLIMSOLL_TELEMETRY_OFFLINE", &mut self.telemetry_offline)?;
+        env_string("PLIMSOLL_PROXY_IDENTITY_KEY", &mut self.proxy_identity_key_path);
+        env_string("PLIMSOLL_IOC_STORE_PATH", &mut self.ioc_store_path);
+        env_parse("PLIMSOLL_LOCAL_BLOCKLIST_TTL_SECS", &mut self.local_blocklist_ttl_secs)?;
+        env_json("PLIMSOLL_SANITIZER_PATTERNS", &mut self.sanitizer_patterns)?;
+        env_parse("PLIMSOLL_FEE_SETTLEMENT_INTERVAL_SECS", &mut self.fee_settlement_interval_secs)?;
+        env_string("PLIMSOLL_FEE_SETTLEMENT_MIN_WEI", &mut self.fee_settlement_min_wei);
+        env_parse("PLIMSOLL_FEE_SETTLEMENT_MIN_USD", &mut self.fee_settlement_min_usd)?;
+        env_string("PLIMSOLL_FEE_DENOMINATION", &mut self.fee_denomination);
+        env_string("PLIMSOLL_FEE_TOKENS", &mut self.fee_tokens);
+        env_string("PLIMSOLL_FEE_EXEMPT_ADDRESSES", &mut self.fee_exempt_addresses);
+        env_json("PLIMSOLL_FEE_TIERS", &mut self.fee_tiers)?;
+        env_string("PLIMSOLL_FEE_LEDGER_PATH", &mut self.fee_ledger_path);
+        env_parse("PLIMSOLL_SYNTHETIC_RECEIPT_REALISTIC", &mut self.synthetic_receipt_realistic)?;
+        env_parse("PLIMSOLL_SYNTHETIC_RECEIPT_DELAY_SECS", &mut self.synthetic_receipt_delay_secs)?;
+        env_parse("PLIMSOLL_RESPONSE_METADATA", &mut self.response_metadata)?;
+        env_parse("PLIMSOLL_SHADOW_MODE", &mut self.shadow_mode)?;
+        env_string("PLIMSOLL_SHADOW_ENGINES", &mut self.shadow_engines);
+        env_string("PLIMSOLL_RAW_SIGN_MODE", &mut self.raw_sign_mode);
+        env_string("PLIMSOLL_TYPED_DATA_MODE", &mut self.typed_data_mode);
+        env_string("PLIMSOLL_PHYSICS_MODE", &mut self.physics_mode);
+        env_string("PLIMSOLL_THREAT_FEED_MODE", &mut self.threat_feed_mode);
+        env_parse("PLIMSOLL_APPROVAL_TTL_SECS", &mut self.approval_ttl_secs)?;
+        env_json("PLIMSOLL_NOTIFY_TARGETS", &mut self.notify_targets)?;
+        env_string("PLIMSOLL_AUDIT_LOG_PATH", &mut self.audit_log_path);
+        env_parse("PLIMSOLL_AUDIT_CHECKPOINT_SECS", &mut self.audit_checkpoint_secs)?;
+        env_string("PLIMSOLL_AUDIT_TRUSTED_SIGNERS", &mut self.audit_trusted_signers);
+        env_parse("PLIMSOLL_INDEXER_EVENTS", &mut self.indexer_events)?;
+        env_string("PLIMSOLL_INDEXER_INGEST_TOKEN", &mut self.indexer_ingest_token);
+        env_parse("PLIMSOLL_MCP_SERVER", &mut self.mcp_server)?;
+        env_parse("PLIMSOLL_GRPC_PORT", &mut self.grpc_port)?;
+        env_string("PLIMSOLL_IPC_PATH", &mut self.ipc_path);
+        env_string("PLIMSOLL_TLS_CERT_PATH", &mut self.tls_cert_path);
+        env_string("PLIMSOLL_TLS_KEY_PATH", &mut self.tls_key_path);
+        env_string("PLIMSOLL_TLS_CLIENT_CA_PATH", &mut self.tls_client_ca_path);
+        env_string("PLIMSOLL_TLS_ACME_DOMAINS", &mut self.tls_acme_domains);
+        env_string("PLIMSOLL_TLS_ACME_CONTACT", &mut self.tls_acme_contact);
+        env_string("PLIMSOLL_TLS_ACME_CACHE_DIR", &mut self.tls_acme_cache_dir);
+        env_parse("PLIMSOLL_TLS_ACME_STAGING", &mut self.tls_acme_staging)?;
+        env_parse("PLIMSOLL_SHUTDOWN_GRACE_SECS", &mut self.shutdown_grace_secs)?;
+        Ok(())
+    }
+
+    /// Reject configurations that are malformed or internally inconsistent.
+    pub fn validate(&self) -> Result<()> {
+        let upstream = self.upstream_rpc_url.to_lowercase();
+        if !(upstream.starts_with("http://") || upstream.starts_with("https://")) {
+            anyhow::bail!("upstream_rpc_url must be an http(s) URL, got '{}'", self.upstream_rpc_url);
+        }
+        if self.fee_bps > 10_000 {
+            anyhow::bail!("fee_bps must be <= 10000, got {}", self.fee_bps);
+        }
+        if !is_hex_address(&self.fee_collector) {
+            anyhow::bail!("fee_collector is not a valid address: '{}'", self.fee_collector);
+        }
+        if !(0.0..=100.0).contains(&self.max_loss_pct) {
+            anyhow::bail!("max_loss_pct must be within 0..=100, got {}", self.max_loss_pct);
+        }
+        if self.simulation_timeout_ms == 0 {
+            anyhow::bail!("simulation_timeout_ms must be > 0");
+        }
+        if self.gas_anomaly_ratio < 0.0 {
+            anyhow::bail!("gas_anomaly_ratio must be >= 0, got {}", self.gas_anomaly_ratio);
+        }
+        if !self.bundler_address.is_empty() && !is_hex_address(&self.bundler_address) {
+            anyhow::bail!("bundler_address is not a valid address: '{}'", self.bundler_address);
+        }
+        let mut seen_keys = std::collections::HashSet::new();
+        let mut seen_certs = std::collections::HashSet::new();
+        for agent in &self.agents {
+            if agent.api_key.is_empty() && agent.client_cert.is_empty() {
+                anyhow::bail!("agent '{}': needs an api_key or a client_cert", agent.name);
+            }
+            if !agent.api_key.is_empty() && agent.api_key.len() < 16 {
+                anyhow::bail!("agent '{}': api_key must be at least 16 characters", agent.name);
+            }
+            if !agent.api_key.is_empty() && !seen_keys.insert(agent.api_key.as_str()) {
+                anyhow::bail!("agent '{}': api_key is shared with another agent", agent.name);
+            }
+            if !agent.client_cert.is_empty() {
+                let fingerprint = crate::auth::normalize_fingerprint(&agent.client_cert);
+                if fingerprint.len() != 64 || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+                    anyhow::bail!("agent '{}': client_cert is not a SHA-256 fingerprint", agent.name);
+                }
+                if !seen_certs.insert(fingerprint) {
+                    anyhow::bail!("agent '{}': client_cert is shared with another agent", agent.name);
+                }
+            }
+            if agent.addresses.is_empty() {
+                anyhow::bail!("agent '{}': no addresses bound", agent.name);
+            }
+            if let Some(bad) = agent.addresses.iter().find(|a| !is_hex_address(a)) {
+                anyhow::bail!("agent '{}': invalid address '{}'", agent.name, bad);
+            }
+            if agent.chain_id != 0 && !self.serves_chain(agent.chain_id) {
+                anyhow::bail!("agent '{}': chain {} is not served", agent.name, agent.chain_id);
+            }
+        }
+        let mut seen_chains = std::collections::HashSet::from([self.default_chain_id()]);
+        for chain in &self.chains {
+            if chain.chain_id == 0 {
+                anyhow::bail!("chains: chain_id must be > 0");
+            }
+            if !seen_chains.insert(chain.chain_id) {
+                anyhow::bail!("chains: chain {} is configured twice (or is the default chain)", chain.chain_id);
+            }
+            for url in [&chain.upstream_rpc_url, &chain.blob_upstream_rpc_url] {
+                if !url.is_empty() && !url.starts_with("https://") && !url.starts_with("http://") {
+                    anyhow::bail!("chains: chain {}: '{}' is not an http(s) URL", chain.chain_id, url);
+                }
+            }
+            if chain.upstream_rpc_url.is_empty() {
+                anyhow::bail!("chains: chain {} has no upstream_rpc_url", chain.chain_id);
+            }
+        }
+        for domain in &self.eip712_trusted_domains {
+            if !is_hex_address(&domain.verifying_contract) {
+                anyhow::bail!(
+                    "eip712_trusted_domains: invalid verifying_contract '{}'",
+                    domain.verifying_contract
+                );
+            }
+            if domain.chain_id == 0 {
+                anyhow::bail!("eip712_trusted_domains: chain_id 0 would trust every chain");
+            }
+        }
+        for (token, cap) in &self.permit2_token_caps {
+            if !is_hex_address(token) {
+                anyhow::bail!("permit2_token_caps: invalid token address '{}'", token);
+            }
+            if cap.is_empty() || !cap.chars().all(|c| c.is_ascii_digit()) {
+                anyhow::bail!("permit2_token_caps: cap for {} must be a decimal integer", token);
+            }
+        }
+        let is_decimal = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        for (token, limit) in &self.token_velocity_limits {
+            if token != "native" && !is_hex_address(token) {
+                anyhow::bail!("token_velocity_limits: '{}' is neither a token address nor 'native'", token);
+            }
+            if !is_decimal(&limit.max_per_window)
+                || !(limit.max_single_tx.is_empty() || is_decimal(&limit.max_single_tx))
+            {
+                anyhow::bail!("token_velocity_limits: caps for {} must be decimal integers", token);
+            }
+            if limit.window_secs == 0 {
+                anyhow::bail!("token_velocity_limits: window_secs for {} must be > 0", token);
+            }
+        }
+        if self.max_drawdown_pct.is_nan() || !(0.0..=100.0).contains(&self.max_drawdown_pct) {
+            anyhow::bail!("max_drawdown_pct must be within 0..=100, got {}", self.max_drawdown_pct);
+        }
+        if self.max_drawdown_pct > 0.0 && self.drawdown_snapshot_secs == 0 {
+            anyhow::bail!("drawdown_snapshot_secs must be > 0");
+        }
+        for vault in self.vault_addresses.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            if !is_hex_address(vault) {
+                anyhow::bail!("vault_addresses: invalid address '{}'", vault);
+            }
+        }
+        if self.vault_policy_sync && self.vault_addresses.trim().is_empty() {
+            anyhow::bail!("vault_policy_sync requires vault_addresses");
+        }
+        if self.target_allowlist_from_vaults && !self.vault_policy_sync {
+            anyhow::bail!("target_allowlist_from_vaults requires vault_policy_sync");
+        }
+        if self.vault_policy_sync && self.vault_policy_sync_secs == 0 {
+            anyhow::bail!("vault_policy_sync_secs must be > 0");
+        }
+        if self.revocation_reconcile_secs == 0 {
+            anyhow::bail!("revocation_reconcile_secs must be > 0");
+        }
+        if !self.cluster_redis_url.is_empty() {
+            if !self.cluster_redis_url.starts_with("redis://") && !self.cluster_redis_url.starts_with("rediss://") {
+                anyhow::bail!("cluster_redis_url must be a redis:// or rediss:// URL");
+            }
+            if self.cluster_namespace.trim().is_empty() {
+                anyhow::bail!("cluster_namespace must not be empty");
+            }
+        }
+        for peer in self.gossip_peers.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            if !(peer.starts_with("http://") || peer.starts_with("https://")) {
+                anyhow::bail!("gossip_peers: '{}' must be an http(s) URL", peer);
+            }
+        }
+        if !self.gossip_peers.trim().is_empty() && self.gossip_secret.is_empty() {
+            anyhow::bail!("gossip_peers requires gossip_secret");
+        }
+        if !self.threat_feed_url.is_empty()
+            && !self.threat_feed_url.starts_with("https://")
+            && !self.threat_feed_url.starts_with("http://")
+        {
+            anyhow::bail!("threat_feed_url must be an http(s) URL");
+        }
+        if self.threat_feed_refresh_secs == 0 {
+            anyhow::bail!("threat_feed_refresh_secs must be > 0");
+        }
+        for entry in self.threat_allowlist.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let hex_len = entry.strip_prefix("0x").map(|h| (h.len(), h.chars().all(|c| c.is_ascii_hexdigit())));
+            let valid = is_hex_address(entry)
+                || hex_len == Some((8, true))
+                || (entry.len() == 16 && entry.chars().all(|c| c.is_ascii_hexdigit()));
+            if !valid {
+                anyhow::bail!("threat_allowlist: '{}' is not an address, selector or calldata hash", entry);
+            }
+        }
+        let mut seen_sources = std::collections::HashSet::new();
+        for source in &self.threat_sources {
+            if source.label.trim().is_empty() || source.label == crate::threat_feed::CLOUD_SOURCE {
+                anyhow::bail!("threat_sources: invalid label '{}'", source.label);
+            }
+            if !seen_sources.insert(source.label.as_str()) {
+                anyhow::bail!("threat_sources: '{}' is configured twice", source.label);
+            }
+            if source.location.trim().is_empty() {
+                anyhow::bail!("threat_sources: '{}' has no location", source.label);
+            }
+        }
+        if !self.sanctions_list_url.is_empty() && self.sanctions_refresh_secs < 60 {
+            anyhow::bail!("sanctions_refresh_secs must be at least 60");
+        }
+        if !self.phishing_feed_url.is_empty() && self.phishing_feed_refresh_secs == 0 {
+            anyhow::bail!("phishing_feed_refresh_secs must be > 0");
+        }
+        if !self.ioc_uplink_url.is_empty()
+            && !self.ioc_uplink_url.starts_with("https://")
+            && !self.ioc_uplink_url.starts_with("http://")
+        {
+            anyhow::bail!("ioc_uplink_url must be an http(s) URL, got '{}'", self.ioc_uplink_url);
+        }
+        for p in &self.sanitizer_patterns {
+            if p.pattern.trim().is_empty() {
+                anyhow::bail!("sanitizer_patterns: empty pattern");
+            }
+            if p.replacement.is_some() && p.action != PatternAction::Replace {
+                anyhow::bail!("sanitizer_patterns: '{}' has a replacement but action is not 'replace'", p.pattern);
+            }
+            // Replacements are spliced into serialized JSON bodies too.
+            if p.replacement.as_deref().is_some_and(|r| r.contains(|c: char| c == '"' || c == '\\' || c.is_control())) {
+                anyhow::bail!("sanitizer_patterns: replacement for '{}' contains a quote, backslash or control character", p.pattern);
+            }
+        }
+        crate::sanitizer::Detector::new(&self.sanitizer_patterns).map_err(anyhow::Error::msg)?;
+
+        let min_wei = &self.fee_settlement_min_wei;
+        if !min_wei.chars().all(|c| c.is_ascii_digit()) || min_wei.parse::<u128>().is_err() {
+            anyhow::bail!("fee_settlement_min_wei must be a decimal integer");
+        }
+        if self.fee_settlement_min_usd.is_nan() || self.fee_settlement_min_usd < 0.0 {
+            anyhow::bail!("fee_settlement_min_usd must be >= 0, got {}", self.fee_settlement_min_usd);
+        }
+        if self.fee_settlement_interval_secs > 0 && self.fee_collector == "0x0000000000000000000000000000000000000000" {
+            anyhow::bail!("fee_settlement_interval_secs needs a fee_collector — settling to the zero address burns the fees");
+        }
+        if !matches!(self.fee_denomination.as_str(), "native" | "traded") {
+            anyhow::bail!("fee_denomination must be 'native' or 'traded', got '{}'", self.fee_denomination);
+        }
+        for (name, list) in [("fee_tokens", &self.fee_tokens), ("fee_exempt_addresses", &self.fee_exempt_addresses)] {
+            for addr in list.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+                if !is_hex_address(addr) {
+                    anyhow::bail!("{}: invalid address '{}'", name, addr);
+                }
+            }
+        }
+        for tier in &self.fee_tiers {
+            if !tier.min_usd.is_finite() || tier.min_usd < 0.0 {
+                anyhow::bail!("fee_tiers: min_usd must be a non-negative number, got {}", tier.min_usd);
+            }
+            if tier.bps > 10_000 {
+                anyhow::bail!("fee_tiers: bps must be <= 10000, got {}", tier.bps);
+            }
+        }
+        if self.portfolio_loss_accounting && !is_hex_address(&self.native_price_token) {
+            anyhow::bail!("native_price_token: invalid address '{}'", self.native_price_token);
+        }
+        for (name, list) in [
+            ("delegatecall_allowlist", &self.delegatecall_allowlist),
+            ("vault_modules", &self.vault_modules),
+            ("vault_target_allowlist", &self.vault_target_allowlist),
+            ("target_allowlist", &self.target_allowlist),
+            ("approval_operator_allowlist", &self.approval_operator_allowlist),
+            ("approval_router_allowlist", &self.approval_router_allowlist),
+            ("uniswapx_reactors", &self.uniswapx_reactors),
+        ] {
+            for addr in list.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+                if !is_hex_address(addr) {
+                    anyhow::bail!("{}: invalid address '{}'", name, addr);
+                }
+            }
+        }
+        for selector in self.reentrancy_allowed_callbacks.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            if selector.len() != 10 || !selector.starts_with("0x") || hex::decode(&selector[2..]).is_err() {
+                anyhow::bail!("reentrancy_allowed_callbacks: invalid selector '{}'", selector);
+            }
+        }
+        for (token, feed) in &self.chainlink_feeds {
+            if !is_hex_address(token) || !is_hex_address(feed) {
+                anyhow::bail!("chainlink_feeds: invalid entry '{}' → '{}'", token, feed);
+            }
+        }
+        if !(0.0..=100.0).contains(&self.oracle_max_deviation_pct) {
+            anyhow::bail!(
+                "oracle_max_deviation_pct must be within 0..=100, got {}",
+                self.oracle_max_deviation_pct
+            );
+        }
+        if !(0.0..=100.0).contains(&self.order_max_price_deviation_pct) {
+            anyhow::bail!(
+                "order_max_price_deviation_pct must be within 0..=100, got {}",
+                self.order_max_price_deviation_pct
+            );
+        }
+        let min_wei = &self.seaport_min_nft_consideration_wei;
+        if min_wei.is_empty() || !min_wei.chars().all(|c| c.is_ascii_digit()) {
+            anyhow::bail!("seaport_min_nft_consideration_wei must be a decimal integer");
+        }
+        if !(0.0..=100.0).contains(&self.seaport_min_offerer_share_pct) {
+            anyhow::bail!(
+                "seaport_min_offerer_share_pct must be within 0..=100, got {}",
+                self.seaport_min_offerer_share_pct
+            );
+        }
+        for (name, value) in [
+            ("seaport_min_consideration_ratio", self.seaport_min_consideration_ratio),
+            ("seaport_min_nft_consideration_usd", self.seaport_min_nft_consideration_usd),
+        ] {
+            if value.is_nan() || value < 0.0 {
+                anyhow::bail!("{} must be >= 0, got {}", name, value);
+            }
+        }
+        if let Some(bad) = self
+            .eip7702_allowed_delegates
+            .split(',')
+            .map(str::trim)
+            .find(|a| !a.is_empty() && !is_hex_address(a))
+        {
+            anyhow::bail!("eip7702_allowed_delegates: invalid address '{}'", bad);
+        }
+        if let Some(bad) = self
+            .safe_delegatecall_allowlist
+            .split(',')
+            .map(str::trim)
+            .find(|a| !a.is_empty() && !is_hex_address(a))
+        {
+            anyhow::bail!("safe_delegatecall_allowlist: invalid address '{}'", bad);
+        }
+        if !(0.0..=100.0).contains(&self.honeypot_max_tax_pct) {
+            anyhow::bail!(
+                "honeypot_max_tax_pct must be within 0..=100, got {}",
+                self.honeypot_max_tax_pct
+            );
+        }
+        if let Some(bad) = self
+            .known_tokens
+            .split(',')
+            .map(str::trim)
+            .find(|a| !a.is_empty() && !is_hex_address(a))
+        {
+            anyhow::bail!("known_tokens: invalid address '{}'", bad);
+        }
+        if self.rugpull_block_score > 100 {
+            anyhow::bail!("rugpull_block_score must be <= 100, got {}", self.rugpull_block_score);
+        }
+        for (name, value) in [
+            ("uniswap_v2_factory", &self.uniswap_v2_factory),
+            ("lp_quote_token", &self.lp_quote_token),
+            ("ens_base_registrar", &self.ens_base_registrar),
+        ] {
+            if !value.is_empty() && !is_hex_address(value) {
+                anyhow::bail!("{}: invalid address '{}'", name, value);
+            }
+        }
+        if self.ens_spoof_detection && !is_hex_address(&self.ens_registry) {
+            anyhow::bail!("ens_registry: invalid address '{}'", self.ens_registry);
+        }
+        if self.contract_verification_check
+            && self.abi_sourcify_url.is_empty()
+            && self.etherscan_api_key.is_empty()
+        {
+            anyhow::bail!(
+                "contract_verification_check needs abi_sourcify_url or etherscan_api_key"
+            );
+        }
+        let cap = &self.first_interaction_max_value_wei;
+        if !cap.is_empty() && !cap.chars().all(|c| c.is_ascii_digit()) {
+            anyhow::bail!("first_interaction_max_value_wei must be a decimal integer");
+        }
+        if self.first_interaction_max_value_usd.is_nan() || self.first_interaction_max_value_usd < 0.0 {
+            anyhow::bail!("first_interaction_max_value_usd must be >= 0");
+        }
+        if !self.indexer_url.is_empty()
+            && !self.indexer_url.starts_with("https://")
+            && !self.indexer_url.starts_with("http://")
+        {
+            anyhow::bail!("indexer_url must be an http(s) URL");
+        }
+        if !self.blob_upstream_rpc_url.is_empty()
+            && !self.blob_upstream_rpc_url.starts_with("https://")
+            && !self.blob_upstream_rpc_url.starts_with("http://")
+        {
+            anyhow::bail!("blob_upstream_rpc_url must be an http(s) URL");
+        }
+        if !(1..=1024).contains(&self.fee_history_blocks) {
+            anyhow::bail!("fee_history_blocks must be within 1..=1024, got {}", self.fee_history_blocks);
+        }
+        for (name, multiplier) in [
+            ("max_base_fee_multiplier", self.max_base_fee_multiplier),
+            ("max_priority_fee_multiplier", self.max_priority_fee_multiplier),
+        ] {
+            if multiplier.is_nan() || multiplier < 1.0 {
+                anyhow::bail!("{} must be >= 1, got {}", name, multiplier);
+            }
+        }
+        if [self.min_priority_fee_cap_gwei, self.max_fee_per_gas_gwei].iter().any(|g| g.is_nan() || *g < 0.0) {
+            anyhow::bail!("min_priority_fee_cap_gwei and max_fee_per_gas_gwei must be >= 0");
+        }
+        if self.tx_rescue && (self.rescue_fee_bump_pct.is_nan() || self.rescue_fee_bump_pct < 10.0) {
+            anyhow::bail!(
+                "rescue_fee_bump_pct must be >= 10 (the replacement minimum), got {}",
+                self.rescue_fee_bump_pct
+            );
+        }
+        if !["block", "rewrite", "off"].contains(&self.nonce_gap_policy.as_str()) {
+            anyhow::bail!(
+                "nonce_gap_policy must be block, rewrite or off, got '{}'",
+                self.nonce_gap_policy
+            );
+        }
+        if !(1..=19).contains(&self.poisoning_match_chars) {
+            anyhow::bail!(
+                "poisoning_match_chars must be within 1..=19, got {}",
+                self.poisoning_match_chars
+            );
+        }
+        if self.expected_chain_id != 0 && self.expected_chain_id != self.chain_id {
+            anyhow::bail!(
+                "expected_chain_id ({}) does not match chain_id ({})",
+                self.expected_chain_id,
+                self.chain_id
+            );
+        }
+        if self.synthetic_receipt_delay_secs > 600 {
+            anyhow::bail!(
+                "synthetic_receipt_delay_secs must be at most 600, got {}",
+                self.synthetic_receipt_delay_secs
+            );
+        }
+        if let Some(engine) = crate::shadow::engines(self).find(|e| !crate::shadow::ENGINES.contains(e)) {
+            anyhow::bail!("shadow_engines: '{engine}' cannot be shadowed per engine");
+        }
+        for (name, mode) in [
+            ("raw_sign_mode", &self.raw_sign_mode),
+            ("typed_data_mode", &self.typed_data_mode),
+            ("physics_mode", &self.physics_mode),
+            ("threat_feed_mode", &self.threat_feed_mode),
+        ] {
+            if !crate::protection::MODES.contains(&mode.as_str()) {
+                anyhow::bail!("{name} must be block, warn or approve, got '{mode}'");
+            }
+        }
+        if self.approval_ttl_secs == 0 {
+            anyhow::bail!("approval_ttl_secs must be greater than 0");
+        }
+        for target in &self.notify_targets {
+            if !crate::notify::KINDS.contains(&target.kind.as_str()) {
+                anyhow::bail!("notify_targets: kind must be json, slack or telegram, got '{}'", target.kind);
+            }
+            if !(target.url.starts_with("http://") || target.url.starts_with("https://")) {
+                anyhow::bail!("notify_targets: a {} target's url is not http(s)", target.kind);
+            }
+            if target.kind == "telegram" && target.chat_id.is_empty() {
+                anyhow::bail!("notify_targets: a telegram target needs a chat_id");
+            }
+            if let Some(event) = target.events.iter().find(|e| !crate::notify::EVENTS.contains(&e.as_str())) {
+                anyhow::bail!("notify_targets: unknown event '{event}'");
+            }
+        }
+        if self.audit_checkpoint_secs == 0 {
+            anyhow::bail!("audit_checkpoint_secs must be greater than 0");
+        }
+        if !self.audit_log_path.is_empty() && self.proxy_identity_key_path.is_empty() {
+            anyhow::bail!("audit_log_path requires proxy_identity_key_path: checkpoints must verify across restarts");
+        }
+        if let Some(bad) = split_list(&self.audit_trusted_signers)
+            .find(|key| key.len() != 64 || !key.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            anyhow::bail!("audit_trusted_signers: invalid ed25519 public key '{}'", bad);
+        }
+        if self.indexer_events && (self.indexer_url.is_empty() || self.indexer_ingest_token.is_empty()) {
+            anyhow::bail!("indexer_events requires indexer_url and indexer_ingest_token");
+        }
+        if self.grpc_port != 0 && !cfg!(feature = "grpc") {
+            anyhow::bail!("grpc_port requires a build with --features grpc");
+        }
+        if self.grpc_port != 0 && self.grpc_port == self.port {
+            anyhow::bail!("grpc_port must differ from port");
+        }
+        if !self.ipc_path.is_empty() && !cfg!(unix) {
+            anyhow::bail!("ipc_path needs Unix domain sockets, which this platform lacks");
+        }
+        if self.tls_cert_path.is_empty() != self.tls_key_path.is_empty() {
+            anyhow::bail!("tls_cert_path and tls_key_path must be set together");
+        }
+        if !self.tls_acme_domains.trim().is_empty() {
+            if !cfg!(feature = "acme") {
+                anyhow::bail!("tls_acme_domains requires a build with --features acme");
+            }
+            if !self.tls_cert_path.is_empty() {
+                anyhow::bail!("tls_acme_domains and tls_cert_path are exclusive");
+            }
+            if self.tls_acme_cache_dir.is_empty() {
+                anyhow::bail!("tls_acme_domains requires tls_acme_cache_dir");
+            }
+        }
+        if !self.tls_client_ca_path.is_empty() && self.tls_cert_path.is_empty() {
+            anyhow::bail!("tls_client_ca_path requires tls_cert_path and tls_key_path");
+        }
+        Ok(())
+    }
+
+    /// Chain of `upstream_rpc_url`: `expected_chain_id`, else `chain_id`.
+    pub fn default_chain_id(&self) -> u64 {
+        if self.expected_chain_id != 0 {
+            self.expected_chain_id
+        } else {
+            self.chain_id
+        }
+    }
+
+    /// Whether requests can be routed to `chain_id`.
+    pub fn serves_chain(&self, chain_id: u64) -> bool {
+        chain_id == self.default_chain_id() || self.chains.iter().any(|c| c.chain_id == chain_id)
+    }
+
+    /// Whether `token` is on the `known_tokens` list.
+    pub fn is_known_token(&self, token: &str) -> bool {
+        self.known_tokens
+            .split(',')
+            .map(str::trim)
+            .any(|t| !t.is_empty() && t.eq_ignore_ascii_case(token))
+    }
+
+    /// Settings that are valid but unsafe for production.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.upstream_rpc_url == DEMO_UPSTREAM_RPC {
+            warnings.push("upstream_rpc_url is the shared Alchemy demo endpoint".to_string());
+        }
+        if self.fee_bps > 0 && self.fee_collector == "0x0000000000000000000000000000000000000000" {
+            warnings.push("fee_collector is the zero address — fees would be burned".to_string());
+        }
+        if !self.block_approval_changes {
+            warnings.push("block_approval_changes is disabled".to_string());
+        }
+        warnings
+    }
+
+    /// Log the effective configuration at startup. Secrets embedded in
+    /// URLs (API keys in the path or query) are redacted.
+    pub fn log_summary(&self) {
+        tracing::info!(
+            upstream = %redact_url(&self.upstream_rpc_url),
+            chain_id = self.chain_id,
+            expected_chain_id = self.expected_chain_id,
+            fee_bps = self.fee_bps,
+            max_loss_pct = self.max_loss_pct,
+            block_approval_changes = self.block_approval_changes,
+            flashbots = self.flashbots_enabled,
+            sanitize_reads = self.sanitize_read_responses,
+            detect_non_determinism = self.detect_non_determinism,
+            revert_strike_max = self.revert_strike_max,
+            max_permit_duration_secs = self.max_permit_duration_secs,
+            state_db = %self.state_db_path,
+            agents = self.agents.len(),
+            notify_targets = self.notify_targets.len(),
+            indexer_events = self.indexer_events,
+            "Effective configuration"
+        );
+        for warning in self.warnings() {
+            tracing::warn!("CONFIG: {}", warning);
+        }
+    }
+}
+
+/// Override a string field from an environment variable, if set.
+fn env_string(var: &str, target: &mut String) {
+    if let Ok(v) = std::env::var(var) {
+        *target = v;
+    }
+}
+
+/// Override a parsed field from an environment variable, if set.
+fn env_parse<T>(var: &str, target: &mut T) -> Result<()>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    if let Ok(v) = std::env::var(var) {
+        *target = v.trim().parse().with_context(|| format!("Invalid {var}: '{v}'"))?;
+    }
+    Ok(())
+}
+
+/// Override a structured field from a JSON-encoded environment variable.
+fn env_json<T: serde::de::DeserializeOwned>(var: &str, target: &mut T) -> Result<()> {
+    if let Ok(v) = std::env::var(var) {
+        *target = serde_json::from_str(&v).with_context(|| format!("Invalid {var}"))?;
+    }
+    Ok(())
+}
+
+/// `0x` + 40 hex characters.
+pub fn is_hex_address(s: &str) -> bool {
+    s.len() == 42 && s.starts_with("0x") && s[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Entries of a comma-separated list setting: trimmed, empties dropped.
+pub fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|entry| !entry.is_empty())
+}
+
+/// Entries of a comma-separated address or selector list, lowercase.
+pub fn parse_list(list: &str) -> Vec<String> {
+    split_list(list).map(str::to_lowercase).collect()
+}
+
+/// Keep only scheme + host of a URL so API keys never reach the logs.
+fn redact_url(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split(['/', '?']).next().unwrap_or("");
+            if host.len() < rest.len() {
+                format!("{scheme}://{host}/<redacted>")
+            } else {
+                format!("{scheme}://{host}")
+            }
+        }
+        None => "<redacted>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_validates() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_toml_partial_keeps_defaults() {
+        let cfg: Config = toml::from_str(
+            r#"
+            upstream_rpc_url = "https://rpc.example.com/v2/KEY"
+            max_loss_pct = 5.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cfg.upstream_rpc_url, "https://rpc.example.com/v2/KEY");
+        assert_eq!(cfg.max_loss_pct, 5.0);
+        assert_eq!(cfg.fee_bps, 2);
+        assert!(cfg.block_approval_changes);
+    }
+
+    #[test]
+    fn test_unknown_key_rejected() {
+        let result: Result<Config, _> = toml::from_str("max_los_pct = 5.0");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_yaml_parses() {
+        let cfg: Config = serde_yaml::from_str("chain_id: 10\nexpected_chain_id: 10\n").unwrap();
+        assert_eq!(cfg.chain_id, 10);
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_values() {
+        let cfg = Config { max_loss_pct: 150.0, ..Config::default() };
+        assert!(cfg.validate().is_err());
+
+        let cfg = Config { fee_collector: "0xnotanaddress".into(), ..Config::default() };
+        assert!(cfg.validate().is_err());
+
+        let cfg = Config { expected_chain_id: 10, ..Config::default() };
+        assert!(cfg.validate().is_err());
+
+        let mut cfg = Config { tls_cert_path: "/etc/plimsoll/tls/fullchain.pem".into(), ..Config::default() };
+        assert!(cfg.validate().is_err());
+        cfg.tls_key_path = "/etc/plimsoll/tls/privkey.pem".into();
+        assert!(cfg.validate().is_ok());
+        cfg.tls_acme_domains = "rpc.example.com".into();
+        assert!(cfg.validate().is_err());
+
+        let cfg = Config { tls_client_ca_path: "/etc/plimsoll/tls/agents-ca.pem".into(), ..Config::default() };
+        assert!(cfg.validate().is_err());
+
+        let mut cfg = Config { audit_log_path: "/var/lib/plimsoll/audit.jsonl".into(), ..Config::default() };
+        assert!(cfg.validate().is_err());
+        cfg.proxy_identity_key_path = "/var/lib/plimsoll/identity.key".into();
+        assert!(cfg.validate().is_ok());
+    }
+
+    #[test]
+    fn test_agents_from_toml() {
+        let cfg: Config = toml::from_str(
+            r#"
+            [[agents]]
+            name = "treasury-bot"
+            api_key = "pk_test_0123456789abcdef"
+            addresses = ["0x1111111111111111111111111111111111111111"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cfg.agents.len(), 1);
+        assert!(cfg.validate().is_ok());
+
+        let mut bad = cfg.clone();
+        bad.agents[0].addresses = vec!["0xnope".into()];
+        assert!(bad.validate().is_err());
+
+        let mut pinned = cfg.clone();
+        pinned.agents[0].api_key = String::new();
+        assert!(pinned.validate().is_err());
+        pinned.agents[0].client_cert = "ab".repeat(32);
+        assert!(pinned.validate().is_ok());
+        pinned.agents[0].client_cert = "ab:cd".into();
+        assert!(pinned.validate().is_err());
+    }
+
+    #[test]
+    fn test_chains_from_toml() {
+        let cfg: Config = toml::from_str(
+            r#"
+            [[agents]]
+            name = "base-bot"
+            api_key = "pk_test_0123456789abcdef"
+            addresses = ["0x1111111111111111111111111111111111111111"]
+            chain_id = 8453
+
+            [[chains]]
+            chain_id = 8453
+            upstream_rpc_url = "https://mainnet.base.org"
+            "#,
+        )
+        .unwrap();
+        assert!(cfg.validate().is_ok());
+        assert!(cfg.serves_chain(1) && cfg.serves_chain(8453) && !cfg.serves_chain(10));
+
+        let mut bad = cfg.clone();
+        bad.agents[0].chain_id = 10;
+        assert!(bad.validate().unwrap_err().to_string().contains("chain 10 is not served"));
+        let mut bad = cfg.clone();
+        bad.chains[0].chain_id = 1;
+        bad.agents[0].chain_id = 1;
+        assert!(bad.validate().unwrap_err().to_string().contains("configured twice"));
+        let mut bad = cfg;
+        bad.chains[0].upstream_rpc_url = "ws://base".into();
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_demo_upstream_warns() {
+        assert!(Config::default()
+            .warnings()
+            .iter()
+            .any(|w| w.contains("demo")));
+    }
+
+    #[test]
+    fn test_redact_url() {
+        assert_eq!(
+            redact_url("https://eth-mainnet.g.alchemy.com/v2/SECRET"),
+            "https://eth-mainnet.g.alchemy.com/<redacted>"
+        );
+        assert_eq!(redact_url("http://localhost:8545"), "http://localhost:8545");
     }
 }
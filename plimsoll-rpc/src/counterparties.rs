@@ -0,0 +1,298 @@
+//! Per-agent counterparty history.
+//!
+//! Every forwarded transaction records its counterparties — the `to`
+//! address and ERC-20 `transfer` / `transferFrom` recipients — against
+//! the sending agent. The history is persisted with the rest of the
+//! protective state and feeds two checks:
+//!
+//!   - address poisoning: a new recipient that *looks like* a known one
+//!     (see `poisoning`),
+//!   - first interaction: the first transaction to a never-seen
+//!     counterparty can be value-capped — native value in wei, native
+//!     value plus tokens transferred in USD — or held for operator
+//!     approval (`POST /admin/counterparties/approve`).
+//!
+//! Each agent keeps its `MAX_COUNTERPARTIES` most recently used
+//! counterparties; a new one evicts the least recently used.
+
+use crate::abi;
+use crate::config::Config;
+use crate::intents;
+use crate::oracle;
+use alloy_primitives::U256;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// `transfer(address,uint256)`
+const TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// `transferFrom(address,address,uint256)`
+const TRANSFER_FROM: [u8; 4] = [0x23, 0xb8, 0x72, 0xdd];
+
+/// Counterparties remembered per agent.
+const MAX_COUNTERPARTIES: usize = 1000;
+
+/// Recency clock of the counterparty histories.
+static TICK: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    /// agent (lowercase) → known counterparty (lowercase) → last use.
+    static ref COUNTERPARTIES: Mutex<HashMap<String, HashMap<String, u64>>> = Mutex::new(HashMap::new());
+}
+
+/// Counterparties of a transaction: `to`, plus the ERC-20 recipient if
+/// `data` is a token transfer.
+pub fn recipients(to: &str, data: &[u8]) -> Vec<String> {
+    let mut out = vec![to.to_lowercase()];
+    let args = data.get(4..).unwrap_or_default();
+    let token_recipient = match abi::selector(data) {
+        Some(TRANSFER) => abi::address(args, 0).ok(),
+        Some(TRANSFER_FROM) => abi::address(args, 1).ok(),
+        _ => None,
+    };
+    out.extend(token_recipient);
+    out
+}
+
+/// Amount of the ERC-20 `transfer` / `transferFrom` in `data`.
+fn token_amount(data: &[u8]) -> Option<U256> {
+    let args = data.get(4..)?;
+    match abi::selector(data)? {
+        TRANSFER => abi::uint(args, 1).ok(),
+        TRANSFER_FROM => abi::uint(args, 2).ok(),
+        _ => None,
+    }
+}
+
+/// Add `counterparty` to `agent`'s history, or mark it most recently
+/// used. A full history evicts its least recently used counterparty.
+pub fn approve(agent: &str, counterparty: &str) {
+    let Ok(mut store) = COUNTERPARTIES.lock() else {
+        return;
+    };
+    let known = store.entry(agent.to_lowercase()).or_default();
+    known.insert(counterparty.to_lowercase(), TICK.fetch_add(1, Ordering::Relaxed));
+    if known.len() > MAX_COUNTERPARTIES {
+        if let Some(oldest) = known.iter().min_by_key(|(_, used)| **used).map(|(c, _)| c.clone()) {
+            known.remove(&oldest);
+        }
+    }
+}
+
+/// Remember the counterparties of a forwarded transaction.
+pub fn record(agent: &str, to: &str, data: &[u8]) {
+    for counterparty in recipients(to, data) {
+        approve(agent, &counterparty);
+    }
+}
+
+/// `agent`'s known counterparties.
+pub fn known(agent: &str) -> HashSet<String> {
+    COUNTERPARTIES
+        .lock()
+        .ok()
+        .and_then(|store| store.get(&agent.to_lowercase()).map(|known| known.keys().cloned().collect()))
+        .unwrap_or_default()
+}
+
+/// Number of agents that have `counterparty` in their history.
+pub fn familiar_agents(counterparty: &str) -> usize {
+    let counterparty = counterparty.to_lowercase();
+    COUNTERPARTIES
+        .lock()
+        .map(|store| store.values().filter(|known| known.contains_key(&counterparty)).count())
+        .unwrap_or(0)
+}
+
+/// Counterparties of this transaction `agent` has never dealt with.
+pub fn first_interactions(agent: &str, to: &str, data: &[u8]) -> Vec<String> {
+    let known = known(agent);
+    recipients(to, data)
+        .into_iter()
+        .filter(|c| !known.contains(c))
+        .collect()
+}
+
+/// USD value of a transaction: its native value plus the tokens it
+/// transfers. `None` when either is unpriced.
+async fn value_usd(config: &Config, to: &str, value: u128, data: &[u8]) -> Option<f64> {
+    let mut usd = 0.0;
+    if value > 0 {
+        let native = oracle::price(config, &config.native_price_token).await?;
+        usd += intents::usd_value(U256::from(value), &native);
+    }
+    if let Some(amount) = token_amount(data).filter(|amount| !amount.is_zero()) {
+        usd += intents::usd_value(amount, &oracle::price(config, to).await?);
+    }
+    Some(usd)
+}
+
+/// Apply the first-interaction policy: hold new counterparties for
+/// approval, or cap the value sent to them. Under the USD cap an
+/// unpriced transfer to a new counterparty is blocked.
+pub async fn check_first_interaction(
+    config: &Config,
+    agent: &str,
+    to: &str,
+    value: u128,
+    data: &[u8],
+) -> Result<(), String> {
+    let new = first_interactions(agent, to, data);
+    if new.is_empty() {
+        return Ok(());
+    }
+    if config.first_interaction_requires_approval {
+        return Err(format!(
+            "PLIMSOLL FIRST INTERACTION: {} never transacted with {} — operator approval \
+             required (POST /admin/counterparties/approve)",
+            agent,
+            new.join(", ")
+        ));
+    }
+    if !config.first_interaction_max_value_wei.is_empty() {
+        let cap = U256::from_str(&config.first_interaction_max_value_wei).unwrap_or(U256::ZERO);
+        if U256::from(value) > cap {
+            return Err(format!(
+                "PLIMSOLL FIRST INTERACTION: {} wei to never-seen counterparty {} exceeds the \
+                 first-interaction cap of {} wei",
+                value,
+                new.join(", "),
+                cap
+            ));
+        }
+    }
+    if config.first_interaction_max_value_usd > 0.0 && (value > 0 || token_amount(data).is_some()) {
+        let cap = config.first_interaction_max_value_usd;
+        match value_usd(config, to, value, data).await {
+            Some(usd) if usd <= cap => {}
+            Some(usd) => {
+                return Err(format!(
+                    "PLIMSOLL FIRST INTERACTION: ${:.2} to never-seen counterparty {} exceeds the \
+                     first-interaction cap of ${:.2}",
+                    usd,
+                    new.join(", "),
+                    cap
+                ));
+            }
+            None => {
+                return Err(format!(
+                    "PLIMSOLL FIRST INTERACTION: unpriced transfer to never-seen counterparty {} \
+                     under the first-interaction cap of ${:.2}",
+                    new.join(", "),
+                    cap
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// All `(agent, counterparty)` pairs, least recently used first, for
+/// persistence.
+pub fn snapshot() -> Vec<(String, String)> {
+    let Ok(store) = COUNTERPARTIES.lock() else {
+        return Vec::new();
+    };
+    let mut pairs: Vec<(u64, String, String)> = store
+        .iter()
+        .flat_map(|(agent, known)| known.iter().map(move |(k, used)| (*used, agent.clone(), k.clone())))
+        .collect();
+    pairs.sort_unstable_by_key(|(used, _, _)| *used);
+    pairs.into_iter().map(|(_, agent, k)| (agent, k)).collect()
+}
+
+/// Merge persisted `(agent, counterparty)` pairs, least recently used
+/// first, into the live history.
+pub fn restore(pairs: Vec<(String, String)>) {
+    for (agent, counterparty) in pairs {
+        approve(&agent, &counterparty);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ReferencePrice;
+
+    const FRIEND: &str = "0x1111111111111111111111111111111111111111";
+    const STRANGER: &str = "0x2222222222222222222222222222222222222222";
+
+    #[test]
+    fn test_token_transfer_recipient_is_counterparty() {
+        let token = "0x3333333333333333333333333333333333333333";
+        let mut data = TRANSFER.to_vec();
+        data.extend_from_slice(&abi::encode_address(STRANGER).unwrap());
+        data.extend_from_slice(&abi::encode_uint(1));
+        assert_eq!(recipients(token, &data), vec![token.to_string(), STRANGER.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_first_interaction_policy() {
+        let agent = "0xa9e0000000000000000000000000000000000010";
+        let mut config = Config::default();
+        approve(agent, FRIEND);
+        assert!(check_first_interaction(&config, agent, STRANGER, 1_000, &[]).await.is_ok());
+
+        config.first_interaction_max_value_wei = "100".into();
+        assert!(check_first_interaction(&config, agent, STRANGER, 100, &[]).await.is_ok());
+        assert!(check_first_interaction(&config, agent, STRANGER, 101, &[]).await.is_err());
+        assert!(check_first_interaction(&config, agent, FRIEND, 101, &[]).await.is_ok());
+
+        config.first_interaction_requires_approval = true;
+        assert!(check_first_interaction(&config, agent, STRANGER, 0, &[]).await.unwrap_err().contains("approval"));
+        approve(agent, STRANGER);
+        assert!(check_first_interaction(&config, agent, STRANGER, 0, &[]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_usd_cap_values_token_transfers() {
+        let agent = "0xa9e0000000000000000000000000000000000012";
+        let token = "0x3333333333333333333333333333333333333334";
+        let transfer = |amount: u64| {
+            let mut data = TRANSFER.to_vec();
+            data.extend_from_slice(&abi::encode_address(STRANGER).unwrap());
+            data.extend_from_slice(&abi::encode_uint(amount));
+            data
+        };
+        approve(agent, token);
+        let mut config = Config { first_interaction_max_value_usd: 100.0, ..Config::default() };
+        // An unpriced token transfer can't be shown to be under the cap.
+        let err = check_first_interaction(&config, agent, token, 0, &transfer(1)).await.unwrap_err();
+        assert!(err.contains("unpriced"), "{err}");
+
+        config.reference_prices.insert(token.into(), ReferencePrice { usd: 2.0, decimals: 0 });
+        assert!(check_first_interaction(&config, agent, token, 0, &transfer(50)).await.is_ok());
+        let err = check_first_interaction(&config, agent, token, 0, &transfer(51)).await.unwrap_err();
+        assert!(err.contains("$102.00"), "{err}");
+
+        approve(agent, STRANGER);
+        let data = transfer(51);
+        assert!(check_first_interaction(&config, agent, token, 0, &data).await.is_ok());
+    }
+
+    #[test]
+    fn test_full_history_evicts_least_recently_used() {
+        let agent = "0xa9e0000000000000000000000000000000000013";
+        let counterparty = |i: usize| format!("0x{i:040x}");
+        for i in 0..MAX_COUNTERPARTIES {
+            approve(agent, &counterparty(i));
+        }
+        // Touch the oldest so the second oldest is the one evicted.
+        record(agent, &counterparty(0), &[]);
+        approve(agent, STRANGER);
+        let known = known(agent);
+        assert_eq!(known.len(), MAX_COUNTERPARTIES);
+        assert!(known.contains(STRANGER));
+        assert!(known.contains(&counterparty(0)));
+        assert!(!known.contains(&counterparty(1)));
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let agent = "0xa9e0000000000000000000000000000000000011";
+        restore(vec![(agent.to_uppercase(), FRIEND.into())]);
+        assert!(snapshot().contains(&(agent.to_string(), FRIEND.to_string())));
+    }
+}
@@ -0,0 +1,305 @@
+//! Cuckoo filter backing Engine 0.
+//!
+//! Unlike a Bloom filter, a cuckoo filter can delete: a delisted address
+//! or a false positive reported upstream is removed by the next feed
+//! delta instead of staying blocked until a full rebuild. Each entry is
+//! stored as a 16-bit fingerprint in one of two candidate buckets of 4
+//! slots; lookups check both (false-positive rate ≈ 8 / 65 536 at full
+//! load, lower below it). Buckets are sized for about 50% load.
+//!
+//! An entry that can't be placed — both buckets full and no eviction
+//! path within `MAX_KICKS` — is kept verbatim in an exact-match overflow
+//! set instead of being dropped, so insertion never fails.
+//!
+//! The exact entries are kept alongside the table. Inserting an entry
+//! already present does nothing, so one removal delists it; removing an
+//! entry that was never inserted does nothing, so a colliding entry's
+//! fingerprint is never taken. Two distinct entries sharing a fingerprint
+//! and bucket each hold a slot, so removing one leaves the other listed.
+//! Hashing is FNV-1a, stable across builds, so a serialized filter stays
+//! valid.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Fingerprint slots per bucket.
+const BUCKET_SIZE: usize = 4;
+
+/// Evictions tried before an entry goes to the overflow set.
+const MAX_KICKS: usize = 500;
+
+/// Smallest table, in buckets.
+const MIN_BUCKETS: usize = 1024;
+
+/// Empty slot marker; fingerprints are never 0.
+const EMPTY: u16 = 0;
+
+/// Fingerprint width; serialized so a filter of another layout is
+/// rejected instead of misread.
+const FINGERPRINT_BITS: u8 = 16;
+
+fn fnv(data: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in data {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CuckooFilter {
+    buckets: Vec<[u16; BUCKET_SIZE]>,
+    /// Entries the table couldn't place.
+    overflow: HashSet<String>,
+    /// Entries in the table (not counting `overflow`).
+    stored: usize,
+    /// Every entry, exactly: what `insert` dedupes and `remove` checks
+    /// against.
+    #[serde(default)]
+    members: HashSet<String>,
+    /// `FINGERPRINT_BITS` of the build that wrote the filter.
+    #[serde(default)]
+    fingerprint_bits: u8,
+}
+
+impl Default for CuckooFilter {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl CuckooFilter {
+    /// A filter sized for about `items` entries.
+    pub fn with_capacity(items: usize) -> Self {
+        let buckets = (items.div_ceil(BUCKET_SIZE) * 2).next_power_of_two().max(MIN_BUCKETS);
+        Self {
+            buckets: vec![[EMPTY; BUCKET_SIZE]; buckets],
+            overflow: HashSet::new(),
+            stored: 0,
+            members: HashSet::new(),
+            fingerprint_bits: FINGERPRINT_BITS,
+        }
+    }
+
+    /// Check the layout of a deserialized filter: this build's
+    /// fingerprints, a power-of-two table (indexes are masked), an entry
+    /// count matching the occupied slots and the exact entries to match.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.fingerprint_bits != FINGERPRINT_BITS {
+            return Err(format!("{}-bit fingerprints, expected {FINGERPRINT_BITS}", self.fingerprint_bits));
+        }
+        let buckets = self.buckets.len();
+        if buckets < MIN_BUCKETS || !buckets.is_power_of_two() {
+            return Err(format!("{buckets} buckets, expected a power of two from {MIN_BUCKETS}"));
+        }
+        let occupied = self.buckets.iter().flatten().filter(|slot| **slot != EMPTY).count();
+        if occupied != self.stored {
+            return Err(format!("{} entries but {occupied} occupied slots", self.stored));
+        }
+        if self.members.len() != self.len() {
+            return Err(format!("{} entries but {} exact entries", self.len(), self.members.len()));
+        }
+        Ok(())
+    }
+
+    fn mask(&self) -> usize {
+        self.buckets.len() - 1
+    }
+
+    /// Fingerprint and primary bucket of `item`.
+    fn locate(&self, item: &str) -> (u16, usize) {
+        let hash = fnv(item.as_bytes());
+        let fingerprint = ((hash >> 48) as u16).max(1);
+        (fingerprint, hash as usize & self.mask())
+    }
+
+    fn alt_index(&self, index: usize, fingerprint: u16) -> usize {
+        (index ^ fnv(&fingerprint.to_le_bytes()) as usize) & self.mask()
+    }
+
+    fn bucket_has(&self, index: usize, fingerprint: u16) -> bool {
+        self.buckets[index].contains(&fingerprint)
+    }
+
+    fn put(&mut self, index: usize, fingerprint: u16) -> bool {
+        match self.buckets[index].iter_mut().find(|slot| **slot == EMPTY) {
+            Some(slot) => {
+                *slot = fingerprint;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether `item` is (probably) in the filter.
+    pub fn contains(&self, item: &str) -> bool {
+        if self.overflow.contains(item) {
+            return true;
+        }
+        let (fingerprint, i1) = self.locate(item);
+        self.bucket_has(i1, fingerprint) || self.bucket_has(self.alt_index(i1, fingerprint), fingerprint)
+    }
+
+    /// Add `item`; nothing happens if it is already in. A matching
+    /// fingerprint may belong to a colliding entry, so it is stored again
+    /// rather than taken as `item`.
+    pub fn insert(&mut self, item: &str) {
+        if !self.members.insert(item.to_string()) {
+            return;
+        }
+        let (fingerprint, i1) = self.locate(item);
+        let i2 = self.alt_index(i1, fingerprint);
+        if self.put(i1, fingerprint) || self.put(i2, fingerprint) {
+            self.stored += 1;
+            return;
+        }
+
+        // Evict along a path, remembering it to undo on failure.
+        let mut path: Vec<(usize, usize)> = Vec::new();
+        let mut carried = fingerprint;
+        let mut index = if fingerprint & 1 == 0 { i1 } else { i2 };
+        for kick in 0..MAX_KICKS {
+            let slot = (kick + fingerprint as usize) % BUCKET_SIZE;
+            std::mem::swap(&mut carried, &mut self.buckets[index][slot]);
+            path.push((index, slot));
+            index = self.alt_index(index, carried);
+            if self.put(index, carried) {
+                self.stored += 1;
+                return;
+            }
+        }
+        for (index, slot) in path.into_iter().rev() {
+            std::mem::swap(&mut carried, &mut self.buckets[index][slot]);
+        }
+        self.overflow.insert(item.to_string());
+    }
+
+    /// Remove `item`. `false`, touching nothing, if it wasn't inserted.
+    pub fn remove(&mut self, item: &str) -> bool {
+        if !self.members.remove(item) {
+            return false;
+        }
+        if self.overflow.remove(item) {
+            return true;
+        }
+        let (fingerprint, i1) = self.locate(item);
+        for index in [i1, self.alt_index(i1, fingerprint)] {
+            if let Some(slot) = self.buckets[index].iter_mut().find(|slot| **slot == fingerprint) {
+                *slot = EMPTY;
+                self.stored -= 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.stored + self.overflow.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(n: usize) -> String {
+        format!("0x{n:040x}")
+    }
+
+    #[test]
+    fn test_insert_contains_remove() {
+        let mut filter = CuckooFilter::default();
+        assert!(filter.is_empty());
+        filter.insert("0xdrainer");
+        assert!(filter.contains("0xdrainer"));
+        assert_eq!(filter.len(), 1);
+        assert!(filter.remove("0xdrainer"));
+        assert!(!filter.contains("0xdrainer"));
+        assert!(!filter.remove("0xdrainer"));
+        assert!(filter.is_empty());
+
+        // Inserted twice, still one entry: one removal delists it.
+        filter.insert("0xdrainer");
+        filter.insert("0xdrainer");
+        assert_eq!(filter.len(), 1);
+        assert!(filter.remove("0xdrainer"));
+        assert!(!filter.contains("0xdrainer"));
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_removing_one_colliding_entry_keeps_the_other() {
+        let mut filter = CuckooFilter::with_capacity(0);
+        let (a, b) = (address(37210), address(47520));
+        // Same fingerprint, same buckets.
+        assert_eq!(filter.locate(&a), filter.locate(&b));
+        filter.insert(&a);
+        // Removing the never-inserted twin leaves `a`'s fingerprint alone.
+        assert!(filter.contains(&b));
+        assert!(!filter.remove(&b));
+        assert!(filter.contains(&a));
+        filter.insert(&b);
+        assert_eq!(filter.len(), 2);
+        assert!(filter.remove(&a));
+        assert!(filter.contains(&b));
+        assert!(filter.remove(&b));
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_layouts() {
+        let mut filter = CuckooFilter::default();
+        filter.insert("0xdrainer");
+        assert!(filter.validate().is_ok());
+
+        let mut empty = filter.clone();
+        empty.buckets.clear();
+        assert!(empty.validate().is_err());
+        let mut odd = filter.clone();
+        odd.buckets.push([EMPTY; BUCKET_SIZE]);
+        assert!(odd.validate().is_err());
+        let mut narrow = filter.clone();
+        narrow.fingerprint_bits = 8;
+        assert!(narrow.validate().is_err());
+        let mut miscounted = filter.clone();
+        miscounted.stored = 0;
+        assert!(miscounted.validate().is_err());
+        // A cache written without the exact entries.
+        let mut inexact = filter;
+        inexact.members.clear();
+        assert!(inexact.validate().is_err());
+    }
+
+    #[test]
+    fn test_overfull_table_spills_to_overflow() {
+        // Far past the smallest table's 4 096 slots.
+        let mut filter = CuckooFilter::with_capacity(0);
+        let n = 6_000;
+        for i in 0..n {
+            filter.insert(&address(i));
+        }
+        assert!(!filter.overflow.is_empty());
+        // No entry is ever lost.
+        assert!((0..n).all(|i| filter.contains(&address(i))));
+        for i in 0..n {
+            filter.remove(&address(i));
+        }
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_false_positive_rate_is_low() {
+        let mut filter = CuckooFilter::with_capacity(10_000);
+        for i in 0..10_000 {
+            filter.insert(&address(i));
+        }
+        let false_positives = (10_000..110_000).filter(|&i| filter.contains(&address(i))).count();
+        assert!(false_positives < 100, "{false_positives} false positives in 100 000");
+    }
+}
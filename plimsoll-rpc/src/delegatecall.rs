@@ -0,0 +1,170 @@
+//! DELEGATECALL-to-unknown-code detection.
+//!
+//! `DELEGATECALL` runs foreign code against the caller's storage and
+//! balance. A vault that delegatecalls anything other than its own pinned
+//! implementation or a vetted library can have its owner, modules or
+//! guard rewritten — module-swap and storage-hijack attacks that move no
+//! funds in the simulated transaction, so balance physics sees nothing.
+//!
+//! Every `DELEGATECALL` / `CALLCODE` frame of the simulation trace that
+//! executes in the sender, a vault (`vault_addresses`) or an approved
+//! module (`vault_modules`) must target:
+//!
+//!   - `delegatecall_allowlist` or `safe_delegatecall_allowlist`, or
+//!   - the account's pinned implementation: its EIP-1967 / beacon /
+//!     EIP-1822 implementation or Safe singleton.
+//!
+//! `block_unknown_delegatecall` chooses block vs. warn.
+
+use crate::config::{parse_list, Config};
+use crate::proxy;
+use crate::types::CallFrame;
+use std::collections::HashMap;
+
+/// A delegatecall out of a watched account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delegation {
+    /// The account whose storage the foreign code runs against.
+    pub context: String,
+    /// The code address.
+    pub code: String,
+    /// Selector of the delegated call (`0x…`, empty if none).
+    pub selector: String,
+}
+
+/// Accounts whose delegatecalls are policed: the sender, vaults and modules.
+pub fn watched(config: &Config, from: &str) -> Vec<String> {
+    let mut accounts = parse_list(&config.vault_addresses);
+    accounts.extend(parse_list(&config.vault_modules));
+    accounts.push(from.to_lowercase());
+    accounts
+}
+
+/// Delegatecalls in `trace` out of a `watched` account into code not on
+/// `allowed`.
+pub fn find(trace: &CallFrame, watched: &[String], allowed: &[String]) -> Vec<Delegation> {
+    fn walk(frame: &CallFrame, watched: &[String], allowed: &[String], out: &mut Vec<Delegation>) {
+        if matches!(frame.call_type.as_str(), "DELEGATECALL" | "CALLCODE") {
+            let context = frame.from.to_lowercase();
+            let code = frame.to.to_lowercase();
+            if watched.contains(&context) && !allowed.contains(&code) {
+                out.push(Delegation {
+                    context,
+                    code,
+                    selector: frame.input.get(..10).unwrap_or("").to_lowercase(),
+                });
+            }
+        }
+        for call in &frame.calls {
+            walk(call, watched, allowed, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(trace, watched, allowed, &mut out);
+    out
+}
+
+/// Code `account` is expected to delegate to: its proxy implementation
+/// or Safe singleton.
+async fn pinned_code(config: &Config, account: &str) -> Vec<String> {
+    let rpc_url = &config.upstream_rpc_url;
+    let mut pinned = Vec::new();
+    match proxy::resolve(rpc_url, account).await {
+        Ok(Some(resolution)) => pinned.push(resolution.implementation),
+        Ok(None) => {}
+        Err(e) => tracing::debug!(error = %e, account, "Proxy resolution failed"),
+    }
+    match proxy::safe_singleton(rpc_url, account).await {
+        Ok(Some(singleton)) => pinned.push(singleton),
+        Ok(None) => {}
+        Err(e) => tracing::debug!(error = %e, account, "Safe singleton lookup failed"),
+    }
+    pinned
+}
+
+/// Flag delegatecalls from the sender, a vault or a module into code that
+/// is neither allowlisted nor the account's pinned implementation.
+pub async fn check(config: &Config, from: &str, trace: &CallFrame) -> Result<(), String> {
+    let mut allowed = parse_list(&config.delegatecall_allowlist);
+    allowed.extend(parse_list(&config.safe_delegatecall_allowlist));
+    let candidates = find(trace, &watched(config, from), &allowed);
+
+    let mut pinned: HashMap<String, Vec<String>> = HashMap::new();
+    for d in candidates {
+        if !pinned.contains_key(&d.context) {
+            pinned.insert(d.context.clone(), pinned_code(config, &d.context).await);
+        }
+        if !pinned[&d.context].contains(&d.code) {
+            return Err(format!(
+                "PLIMSOLL DELEGATECALL: {} delegatecalls unpinned code {} ({}) during simulation — \
+                 foreign code would run against its storage",
+                d.context,
+                d.code,
+                if d.selector.is_empty() { "no calldata" } else { d.selector.as_str() }
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: &str = "0xa9e0000000000000000000000000000000000001";
+    const VAULT: &str = "0x1111111111111111111111111111111111111111";
+    const MODULE: &str = "0x2222222222222222222222222222222222222222";
+    const LIBRARY: &str = "0x3333333333333333333333333333333333333333";
+    const UNKNOWN: &str = "0x6666666666666666666666666666666666666666";
+
+    fn frame(call_type: &str, from: &str, to: &str, calls: Vec<CallFrame>) -> CallFrame {
+        CallFrame {
+            call_type: call_type.into(),
+            from: from.into(),
+            to: to.into(),
+            input: "0x12345678".into(),
+            error: None,
+            calls,
+            ..CallFrame::default()
+        }
+    }
+
+    #[test]
+    fn test_find_reports_watched_contexts_only() {
+        let trace = frame(
+            "CALL",
+            AGENT,
+            VAULT,
+            vec![
+                frame("DELEGATECALL", VAULT, LIBRARY, vec![]),
+                frame("CALL", VAULT, MODULE, vec![frame("DELEGATECALL", MODULE, UNKNOWN, vec![])]),
+                frame("CALL", VAULT, UNKNOWN, vec![frame("DELEGATECALL", UNKNOWN, LIBRARY, vec![])]),
+            ],
+        );
+        let watched = vec![VAULT.to_string(), MODULE.to_string()];
+
+        let found = find(&trace, &watched, &[LIBRARY.to_string()]);
+        assert_eq!(
+            found,
+            vec![Delegation { context: MODULE.into(), code: UNKNOWN.into(), selector: "0x12345678".into() }]
+        );
+        assert_eq!(find(&trace, &watched, &[]).len(), 2);
+    }
+
+    #[test]
+    fn test_watched_includes_sender_vaults_and_modules() {
+        let config = Config {
+            vault_addresses: VAULT.into(),
+            vault_modules: format!(" {MODULE} ,"),
+            ..Config::default()
+        };
+        assert_eq!(watched(&config, AGENT), vec![VAULT, MODULE, AGENT]);
+    }
+
+    #[tokio::test]
+    async fn test_allowlisted_delegatecall_passes_without_lookups() {
+        let config = Config { delegatecall_allowlist: LIBRARY.into(), ..Config::default() };
+        let trace = frame("CALL", AGENT, AGENT, vec![frame("DELEGATECALL", AGENT, LIBRARY, vec![])]);
+        assert!(check(&config, AGENT, &trace).await.is_ok());
+    }
+}
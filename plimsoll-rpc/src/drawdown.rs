@@ -0,0 +1,200 @@
+//! Proxy-side drawdown tracking.
+//!
+//! `DrawdownGuardModule.sol` reverts a transaction that would take the
+//! vault below its floor — after the agent has paid for the gas, and only
+//! against the initial deposit. Here each agent's portfolio (the sender
+//! plus `vault_addresses`) is valued in USD on a fixed interval, the
+//! high-water mark is kept, and a send is blocked when the drawdown from
+//! that peak, after the USD loss the send itself simulates, would exceed
+//! `max_drawdown_pct`.
+//!
+//! The portfolio value is the native balance plus every `known_tokens`
+//! balance, priced through the oracle; without a native price no snapshot
+//! is taken and nothing is blocked. A sender is tracked from its first
+//! send. Snapshots follow the default chain only.
+
+use crate::config::Config;
+use crate::intents;
+use crate::oracle;
+use crate::portfolio;
+use crate::reload::SharedConfigHandle;
+use crate::simulator;
+use alloy_primitives::U256;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Senders whose portfolios are tracked.
+const MAX_TRACKED: usize = 256;
+
+/// Peak and latest portfolio value, in USD.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Watermark {
+    pub peak: f64,
+    pub latest: f64,
+}
+
+impl Watermark {
+    /// Drawdown from the peak after losing `loss_usd` of the latest
+    /// value, in %.
+    pub fn drawdown_pct(&self, loss_usd: f64) -> f64 {
+        if self.peak <= 0.0 {
+            return 0.0;
+        }
+        let after = (self.latest - loss_usd.max(0.0)).max(0.0);
+        ((self.peak - after) / self.peak * 100.0).max(0.0)
+    }
+}
+
+lazy_static! {
+    /// Sender (lowercase) → watermark; `None` until the first snapshot.
+    static ref WATERMARKS: Mutex<HashMap<String, Option<Watermark>>> = Mutex::new(HashMap::new());
+}
+
+/// Start tracking `from`. `true` if it wasn't tracked yet.
+pub fn watch(from: &str) -> bool {
+    let Ok(mut marks) = WATERMARKS.lock() else {
+        return false;
+    };
+    let key = from.to_lowercase();
+    if marks.contains_key(&key) || marks.len() >= MAX_TRACKED {
+        return false;
+    }
+    marks.insert(key, None);
+    true
+}
+
+/// Record a portfolio valuation of `from`.
+pub fn observe(from: &str, value: f64) {
+    if let Ok(mut marks) = WATERMARKS.lock() {
+        let mark = marks.entry(from.to_lowercase()).or_default().get_or_insert_with(Watermark::default);
+        mark.latest = value;
+        mark.peak = mark.peak.max(value);
+    }
+}
+
+fn watermark(from: &str) -> Option<Watermark> {
+    WATERMARKS.lock().ok()?.get(&from.to_lowercase()).copied().flatten()
+}
+
+/// USD value of the portfolio of `from`. `Ok(None)` when the native asset
+/// is unpriced.
+pub async fn portfolio_value(config: &Config, from: &str) -> Result<Option<f64>> {
+    let Some(native) = oracle::price(config, &config.native_price_token).await else {
+        return Ok(None);
+    };
+    let accounts = portfolio::accounts(config, from);
+    let mut balance = U256::ZERO;
+    for account in &accounts {
+        balance = balance.saturating_add(simulator::fetch_balance(&config.upstream_rpc_url, account).await?);
+    }
+    let mut value = intents::usd_value(balance, &native);
+
+    for token in config.known_tokens.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let Some(price) = oracle::price(config, token).await else {
+            continue;
+        };
+        let mut held = U256::ZERO;
+        for account in &accounts {
+            held = held.saturating_add(portfolio::balance_of(&config.upstream_rpc_url, token, account).await?);
+        }
+        value += intents::usd_value(held, &price);
+    }
+    Ok(Some(value))
+}
+
+/// Value the portfolio of `from` and record it.
+pub async fn snapshot(config: &Config, from: &str) {
+    match portfolio_value(config, from).await {
+        Ok(Some(value)) => observe(from, value),
+        Ok(None) => warn!("Native asset unpriced — drawdown snapshot skipped"),
+        Err(e) => warn!(error = %e, from, "Drawdown snapshot failed"),
+    }
+}
+
+/// Block a send whose simulated loss (`loss_usd`, in USD) would leave
+/// `from` more than `max_drawdown_pct` below its peak.
+pub fn check(config: &Config, from: &str, loss_usd: f64) -> Result<(), String> {
+    if config.max_drawdown_pct <= 0.0 {
+        return Ok(());
+    }
+    let Some(mark) = watermark(from) else {
+        return Ok(());
+    };
+    let drawdown = mark.drawdown_pct(loss_usd);
+    if drawdown > config.max_drawdown_pct {
+        return Err(format!(
+            "PLIMSOLL DRAWDOWN: portfolio would be {:.2}% below its peak of ${:.2} \
+             (now ${:.2}, send loses ${:.2}) — max drawdown {}%",
+            drawdown, mark.peak, mark.latest, loss_usd, config.max_drawdown_pct
+        ));
+    }
+    Ok(())
+}
+
+/// Spawn the background task snapshotting every tracked portfolio. Reads
+/// the live config every round, so the limit can be toggled by reload.
+pub fn spawn_snapshot_task(config: SharedConfigHandle) {
+    tokio::spawn(async move {
+        loop {
+            let cfg = config.current();
+            tokio::time::sleep(Duration::from_secs(cfg.drawdown_snapshot_secs.max(1))).await;
+            if cfg.max_drawdown_pct <= 0.0 {
+                continue;
+            }
+            let senders: Vec<String> = match WATERMARKS.lock() {
+                Ok(marks) => marks.keys().cloned().collect(),
+                Err(_) => continue,
+            };
+            for from in &senders {
+                snapshot(&cfg, from).await;
+            }
+            info!(portfolios = senders.len(), "Drawdown snapshots taken");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drawdown_from_peak() {
+        let mark = Watermark { peak: 10_000.0, latest: 9_500.0 };
+        assert!((mark.drawdown_pct(0.0) - 5.0).abs() < 1e-9);
+        // Losing $950 of 9 500 leaves 8 550: 14.5% below the peak.
+        assert!((mark.drawdown_pct(950.0) - 14.5).abs() < 1e-9);
+        assert!((mark.drawdown_pct(20_000.0) - 100.0).abs() < 1e-9);
+        assert_eq!(Watermark { peak: 100.0, latest: 120.0 }.drawdown_pct(0.0), 0.0);
+        assert_eq!(Watermark::default().drawdown_pct(50.0), 0.0);
+    }
+
+    #[test]
+    fn test_peak_is_a_high_water_mark() {
+        let agent = "0xa9e00000000000000000000000000000000000c1";
+        assert!(watch(agent));
+        assert!(!watch(&agent.to_uppercase().replace("0X", "0x")));
+        assert_eq!(watermark(agent), None);
+        observe(agent, 1_000.0);
+        observe(agent, 1_200.0);
+        observe(agent, 900.0);
+        assert_eq!(watermark(agent), Some(Watermark { peak: 1_200.0, latest: 900.0 }));
+    }
+
+    #[test]
+    fn test_send_blocked_past_max_drawdown() {
+        let agent = "0xa9e00000000000000000000000000000000000c2";
+        let config = Config { max_drawdown_pct: 20.0, ..Config::default() };
+        // Untracked: nothing to compare against.
+        assert!(check(&config, agent, 9_000.0).is_ok());
+        observe(agent, 10_000.0);
+        observe(agent, 9_000.0);
+        assert!(check(&config, agent, 450.0).is_ok());
+        let err = check(&config, agent, 1_350.0).unwrap_err();
+        assert!(err.contains("23.50% below its peak of $10000.00"));
+        assert!(check(&Config::default(), agent, 1_350.0).is_ok());
+    }
+}
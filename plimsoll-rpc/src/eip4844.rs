@@ -0,0 +1,256 @@
+//! EIP-4844 blob transactions.
+//!
+//! A type-3 raw transaction is submitted in its network form,
+//! `0x03 || rlp([tx, blobs, commitments, proofs])` (EIP-7594 adds a wrapper
+//! version after `tx`), so the generic request parser saw neither its
+//! target, value nor calldata and the send was simulated as a no-op.
+//!
+//! The envelope is decoded here: sender recovered, call fields extracted
+//! for the usual engines, and the sidecar checked against the versioned
+//! hashes it must carry. Blob gas is burned on top of the execution fee and
+//! never shows up in a simulated balance, so the worst-case blob fee
+//! (`blobs × GAS_PER_BLOB × max_fee_per_blob_gas`) is charged to the
+//! simulation before physics runs.
+
+use crate::types;
+use ethers::types::{Address, Signature, H256, U256};
+use ethers::utils::keccak256;
+use ethers::utils::rlp::{DecoderError, Rlp, RlpStream};
+
+/// EIP-2718 transaction type of a blob transaction.
+pub const BLOB_TX_TYPE: u8 = 0x03;
+
+/// Blob gas consumed per blob.
+pub const GAS_PER_BLOB: u128 = 1 << 17;
+
+/// Version byte of a KZG versioned hash.
+const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+/// Number of RLP fields in a signed blob transaction.
+const BLOB_TX_FIELDS: usize = 14;
+
+/// A decoded type-3 transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobTx {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+    pub gas_limit: u64,
+    pub to: String,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub max_fee_per_blob_gas: U256,
+    pub blob_versioned_hashes: Vec<H256>,
+    /// Blobs in the sidecar; `None` for the canonical form without one.
+    pub sidecar_blobs: Option<usize>,
+    /// Recovered transaction sender.
+    pub sender: String,
+}
+
+impl BlobTx {
+    /// Most the transaction can burn on blob gas, in wei.
+    pub fn max_blob_fee(&self) -> u128 {
+        let per_gas = u128::try_from(self.max_fee_per_blob_gas).unwrap_or(u128::MAX);
+        (self.blob_versioned_hashes.len() as u128)
+            .saturating_mul(GAS_PER_BLOB)
+            .saturating_mul(per_gas)
+    }
+}
+
+fn decode_body(rlp: &Rlp) -> Result<BlobTx, DecoderError> {
+    if rlp.item_count()? != BLOB_TX_FIELDS {
+        return Err(DecoderError::RlpIncorrectListLen);
+    }
+    let to: Address = rlp.val_at(5)?;
+
+    // Sender: sign over `0x03 || rlp(fields[0..11])`.
+    let mut unsigned = RlpStream::new_list(11);
+    for i in 0..11 {
+        unsigned.append_raw(rlp.at(i)?.as_raw(), 1);
+    }
+    let mut payload = vec![BLOB_TX_TYPE];
+    payload.extend_from_slice(&unsigned.out());
+    let y_parity: u64 = rlp.val_at(11)?;
+    if y_parity > 1 {
+        return Err(DecoderError::Custom("invalid transaction signature"));
+    }
+    let signature = Signature { r: rlp.val_at(12)?, s: rlp.val_at(13)?, v: y_parity + 27 };
+    let sender = signature
+        .recover(H256::from(keccak256(payload)))
+        .map_err(|_| DecoderError::Custom("invalid transaction signature"))?;
+
+    Ok(BlobTx {
+        chain_id: rlp.val_at(0)?,
+        nonce: rlp.val_at(1)?,
+        max_priority_fee_per_gas: rlp.val_at(2)?,
+        max_fee_per_gas: rlp.val_at(3)?,
+        gas_limit: rlp.val_at(4)?,
+        to: format!("{:#x}", to),
+        value: rlp.val_at(6)?,
+        data: rlp.val_at(7)?,
+        max_fee_per_blob_gas: rlp.val_at(9)?,
+        blob_versioned_hashes: rlp.list_at(10)?,
+        sidecar_blobs: None,
+        sender: format!("{:#x}", sender),
+    })
+}
+
+/// Decode a raw signed transaction. `Ok(None)` if it is not type-3.
+pub fn decode_raw(raw: &[u8]) -> Result<Option<BlobTx>, String> {
+    let Some((&BLOB_TX_TYPE, body)) = raw.split_first() else {
+        return Ok(None);
+    };
+    let rlp = Rlp::new(body);
+    let decode = || -> Result<BlobTx, DecoderError> {
+        let first = rlp.at(0)?;
+        if !first.is_list() {
+            return decode_body(&rlp);
+        }
+        // Network form: [tx, (wrapper_version,) blobs, commitments, proofs].
+        let mut tx = decode_body(&first)?;
+        let blobs_at = if rlp.item_count()? == 5 { 2 } else { 1 };
+        let blobs = rlp.at(blobs_at)?.item_count()?;
+        let commitments = rlp.at(blobs_at + 1)?.item_count()?;
+        if commitments != blobs {
+            return Err(DecoderError::Custom("sidecar commitment count does not match its blobs"));
+        }
+        tx.sidecar_blobs = Some(blobs);
+        Ok(tx)
+    };
+    let tx = decode().map_err(|e| format!("undecodable blob transaction: {e}"))?;
+    if tx.blob_versioned_hashes.is_empty() {
+        return Err("blob transaction without blob hashes".into());
+    }
+    if let Some(bad) = tx.blob_versioned_hashes.iter().find(|h| h[0] != VERSIONED_HASH_VERSION_KZG) {
+        return Err(format!("blob versioned hash {bad:#x} is not a KZG commitment hash"));
+    }
+    match tx.sidecar_blobs {
+        None => return Err("blob transaction without its sidecar cannot be broadcast".into()),
+        Some(n) if n != tx.blob_versioned_hashes.len() => {
+            return Err(format!(
+                "sidecar carries {n} blobs for {} versioned hashes",
+                tx.blob_versioned_hashes.len()
+            ));
+        }
+        Some(_) => {}
+    }
+    Ok(Some(tx))
+}
+
+/// Decode a hex-encoded raw transaction (`eth_sendRawTransaction` param).
+pub fn decode_raw_hex(raw_hex: &str) -> Result<Option<BlobTx>, String> {
+    types::decode_raw_tx_hex(raw_hex, decode_raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SimulationResult;
+    use ethers::signers::{LocalWallet, Signer};
+    use std::str::FromStr;
+
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const TARGET: &str = "0x1111111111111111111111111111111111111111";
+
+    fn versioned_hash(byte: u8) -> H256 {
+        let mut hash = [byte; 32];
+        hash[0] = VERSIONED_HASH_VERSION_KZG;
+        H256::from(hash)
+    }
+
+    fn body(hashes: &[H256]) -> Vec<u8> {
+        let wallet = LocalWallet::from_str(KEY).unwrap();
+        let fields = |stream: &mut RlpStream| {
+            stream.append(&1u64);
+            stream.append(&3u64);
+            stream.append(&U256::from(2_000_000_000u64));
+            stream.append(&U256::from(30_000_000_000u64));
+            stream.append(&100_000u64);
+            stream.append(&Address::from_str(TARGET).unwrap());
+            stream.append(&U256::from(5u64));
+            stream.append(&vec![0xa9u8, 0x05, 0x9c, 0xbb]);
+            stream.begin_list(0);
+            stream.append(&U256::from(10u64));
+            stream.append_list::<H256, H256>(hashes);
+        };
+        let mut unsigned = RlpStream::new_list(11);
+        fields(&mut unsigned);
+        let mut payload = vec![BLOB_TX_TYPE];
+        payload.extend_from_slice(&unsigned.out());
+        let sig = wallet.sign_hash(H256::from(keccak256(&payload))).unwrap();
+
+        let mut signed = RlpStream::new_list(BLOB_TX_FIELDS);
+        fields(&mut signed);
+        signed.append(&(sig.v - 27));
+        signed.append(&sig.r);
+        signed.append(&sig.s);
+        signed.out().to_vec()
+    }
+
+    fn network(hashes: &[H256], blobs: usize) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(4);
+        stream.append_raw(&body(hashes), 1);
+        for _ in 0..3 {
+            stream.begin_list(blobs);
+            for _ in 0..blobs {
+                stream.append(&vec![0u8; 48]);
+            }
+        }
+        let mut raw = vec![BLOB_TX_TYPE];
+        raw.extend_from_slice(&stream.out());
+        raw
+    }
+
+    #[test]
+    fn test_network_form_decoded() {
+        let tx = decode_raw(&network(&[versioned_hash(7)], 1)).unwrap().unwrap();
+        let wallet = LocalWallet::from_str(KEY).unwrap();
+        assert_eq!(tx.sender, format!("{:#x}", wallet.address()));
+        assert_eq!(tx.to, TARGET);
+        assert_eq!(tx.nonce, 3);
+        assert_eq!(tx.value, U256::from(5u64));
+        assert_eq!(tx.data, vec![0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(tx.sidecar_blobs, Some(1));
+        assert_eq!(tx.max_blob_fee(), GAS_PER_BLOB * 10);
+    }
+
+    #[test]
+    fn test_malformed_blob_txs_rejected() {
+        assert!(decode_raw(&[0x02, 0xc0]).unwrap().is_none());
+        // Canonical form: no sidecar to broadcast.
+        let mut canonical = vec![BLOB_TX_TYPE];
+        canonical.extend_from_slice(&body(&[versioned_hash(7)]));
+        assert!(decode_raw(&canonical).unwrap_err().contains("without its sidecar"));
+        assert!(decode_raw(&network(&[versioned_hash(7)], 2)).unwrap_err().contains("2 blobs for 1"));
+        assert!(decode_raw(&network(&[], 0)).unwrap_err().contains("without blob hashes"));
+        let mut bad = versioned_hash(7);
+        bad.0[0] = 0x02;
+        assert!(decode_raw(&network(&[bad], 1)).unwrap_err().contains("not a KZG"));
+    }
+
+    #[test]
+    fn test_blob_fee_charged_to_simulation() {
+        let mut sim = SimulationResult {
+            success: true,
+            gas_used: 21_000,
+            balance_before: 1_000_000,
+            balance_after: 1_000_000,
+            approval_changes: vec![],
+            loss_pct: 0.0,
+            error: None,
+            simulated_block: 1,
+            target_codehash: String::new(),
+            non_deterministic: false,
+            impl_slot_value: String::new(),
+            implementation_codehash: String::new(),
+            call_trace: None,
+            asset_deltas: vec![],
+            approval_deltas: vec![],
+            storage_changes: vec![],
+        };
+        sim.charge(250_000);
+        assert_eq!(sim.balance_after, 750_000);
+        assert_eq!(sim.loss_pct, 25.0);
+    }
+}
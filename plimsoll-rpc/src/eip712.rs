@@ -0,0 +1,531 @@
+//! EIP-712 typed-data helpers shared by the GOD-TIER 1 signing defenses.
+//!
+//! ## Trusted domains
+//!
+//! Blocking every dangerous primary type (Permit, Order, ...) also blocks
+//! the vetted protocols an agent is *supposed* to use. Operators can list
+//! trusted `(verifyingContract, name, chainId)` domains; typed data whose
+//! domain matches one exactly skips the `permit_decoder` block. Chain-ID
+//! replay and permit-deadline checks still apply.
+//!
+//! ## Type graph analysis
+//!
+//! Matching on `primaryType` alone is bypassable: an attacker can rename the
+//! primary type or nest a `PermitDetails` under an innocuous `Login` wrapper.
+//! [`TypeGraph`] resolves the full EIP-712 type graph reachable from the
+//! primary type, computes real `encodeType` / `typeHash` / `hashStruct`
+//! values, and [`find_dangerous_structs`] flags any struct in that graph
+//! that is a known token-moving struct — by name *or* by field shape.
+
+use crate::config::{Config, TrustedTypedDataDomain};
+use alloy_primitives::{keccak256, B256, U256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+/// Known token-moving structs: (name, canonical field list).
+/// A struct matches if it has this name, or exactly these fields under any name.
+const DANGEROUS_STRUCTS: &[(&str, &str)] = &[
+    ("Permit", "address owner,address spender,uint256 value,uint256 nonce,uint256 deadline"),
+    ("Permit", "address holder,address spender,uint256 nonce,uint256 expiry,bool allowed"),
+    ("Permit", "address spender,uint256 tokenId,uint256 nonce,uint256 deadline"),
+    ("PermitDetails", "address token,uint160 amount,uint48 expiration,uint48 nonce"),
+    ("PermitSingle", "PermitDetails details,address spender,uint256 sigDeadline"),
+    ("PermitBatch", "PermitDetails[] details,address spender,uint256 sigDeadline"),
+    ("TokenPermissions", "address token,uint256 amount"),
+    ("PermitTransferFrom", "TokenPermissions permitted,address spender,uint256 nonce,uint256 deadline"),
+    ("PermitBatchTransferFrom", "TokenPermissions[] permitted,address spender,uint256 nonce,uint256 deadline"),
+    ("OfferItem", "uint8 itemType,address token,uint256 identifierOrCriteria,uint256 startAmount,uint256 endAmount"),
+    ("OrderComponents", ""),
+    ("Order", ""),
+    ("MetaTransaction", ""),
+    ("ForwardRequest", ""),
+    ("Delegation", ""),
+    ("TransferWithAuthorization", "address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce"),
+    ("ReceiveWithAuthorization", "address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce"),
+];
+
+/// Field names that, combined, make any struct approval-shaped.
+const GRANTEE_FIELDS: &[&str] = &["spender", "operator", "taker", "delegate"];
+const AMOUNT_FIELDS: &[&str] = &["value", "amount", "startAmount", "allowed", "tokenId", "approved"];
+
+/// Parse an EIP-712 `chainId` (JSON number, hex string, or decimal string).
+pub fn parse_chain_id(value: &serde_json::Value) -> Option<u64> {
+    if let Some(n) = value.as_u64() {
+        return Some(n);
+    }
+    let s = value.as_str()?;
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Return the trusted domain entry matching `typed_data.domain`, if any.
+/// All three of verifyingContract, name, and chainId must match.
+pub fn trusted_domain<'a>(
+    config: &'a Config,
+    typed_data: &serde_json::Value,
+) -> Option<&'a TrustedTypedDataDomain> {
+    let domain = typed_data.get("domain")?;
+    let contract = domain.get("verifyingContract")?.as_str()?;
+    let name = domain.get("name")?.as_str()?;
+    let chain_id = parse_chain_id(domain.get("chainId")?)?;
+
+    config.eip712_trusted_domains.iter().find(|t| {
+        t.verifying_contract.eq_ignore_ascii_case(contract)
+            && t.name == name
+            && t.chain_id == chain_id
+    })
+}
+
+/// One `(type, name)` member of an EIP-712 struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub ty: String,
+    pub name: String,
+}
+
+/// The `types` section of an EIP-712 payload, validated.
+#[derive(Debug, Clone)]
+pub struct TypeGraph {
+    structs: BTreeMap<String, Vec<Member>>,
+}
+
+fn is_atomic(ty: &str) -> bool {
+    if matches!(ty, "address" | "bool" | "bytes" | "string") {
+        return true;
+    }
+    let bits = |prefix: &str, max: u32, step: u32| {
+        ty.strip_prefix(prefix)
+            .and_then(|n| n.parse::<u32>().ok())
+            .is_some_and(|n| n > 0 && n <= max && n % step == 0)
+    };
+    bits("uint", 256, 8) || bits("int", 256, 8) || bits("bytes", 32, 1)
+}
+
+/// Strip array suffixes: `Foo[2][]` → `Foo`.
+fn base_type(ty: &str) -> &str {
+    ty.split('[').next().unwrap_or(ty)
+}
+
+impl TypeGraph {
+    /// Parse and validate the `types` object. Every referenced struct type
+    /// must be defined and every field type must be atomic or a struct.
+    pub fn parse(types: &serde_json::Value) -> Result<Self, String> {
+        let obj = types.as_object().ok_or("`types` must be an object")?;
+        let mut structs = BTreeMap::new();
+        for (name, fields) in obj {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("invalid struct name '{name}'"));
+            }
+            let fields = fields.as_array().ok_or_else(|| format!("type '{name}' is not an array"))?;
+            let mut members = Vec::with_capacity(fields.len());
+            for f in fields {
+                let ty = f.get("type").and_then(|v| v.as_str()).ok_or_else(|| format!("'{name}': field without type"))?;
+                let field_name = f.get("name").and_then(|v| v.as_str()).ok_or_else(|| format!("'{name}': field without name"))?;
+                members.push(Member { ty: ty.to_string(), name: field_name.to_string() });
+            }
+            structs.insert(name.clone(), members);
+        }
+
+        for (name, members) in &structs {
+            for m in members {
+                let base = base_type(&m.ty);
+                if !is_atomic(base) && !structs.contains_key(base) {
+                    return Err(format!("'{name}.{}' references undefined type '{}'", m.name, m.ty));
+                }
+            }
+        }
+        Ok(Self { structs })
+    }
+
+    pub fn members(&self, name: &str) -> Option<&[Member]> {
+        self.structs.get(name).map(Vec::as_slice)
+    }
+
+    /// All struct types reachable from `primary`, including itself.
+    pub fn reachable(&self, primary: &str) -> BTreeSet<String> {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![primary.to_string()];
+        while let Some(name) = stack.pop() {
+            if !self.structs.contains_key(&name) || !seen.insert(name.clone()) {
+                continue;
+            }
+            for m in &self.structs[&name] {
+                stack.push(base_type(&m.ty).to_string());
+            }
+        }
+        seen
+    }
+
+    fn encode_single(&self, name: &str) -> String {
+        let fields = self.structs[name]
+            .iter()
+            .map(|m| format!("{} {}", m.ty, m.name))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{name}({fields})")
+    }
+
+    /// EIP-712 `encodeType`: primary first, then dependencies sorted by name.
+    pub fn encode_type(&self, primary: &str) -> Result<String, String> {
+        if !self.structs.contains_key(primary) {
+            return Err(format!("undefined type '{primary}'"));
+        }
+        let mut out = self.encode_single(primary);
+        for dep in self.reachable(primary) {
+            if dep != primary {
+                out.push_str(&self.encode_single(&dep));
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn type_hash(&self, primary: &str) -> Result<B256, String> {
+        Ok(keccak256(self.encode_type(primary)?.as_bytes()))
+    }
+
+    /// EIP-712 `hashStruct(s) = keccak256(typeHash ‖ encodeData(s))`.
+    pub fn hash_struct(&self, primary: &str, data: &serde_json::Value) -> Result<B256, String> {
+        let mut buf = self.type_hash(primary)?.to_vec();
+        let obj = data.as_object().ok_or_else(|| format!("'{primary}' value is not an object"))?;
+        for m in &self.structs[primary] {
+            let value = obj.get(&m.name).unwrap_or(&serde_json::Value::Null);
+            buf.extend_from_slice(self.encode_value(&m.ty, value)?.as_slice());
+        }
+        Ok(keccak256(&buf))
+    }
+
+    fn encode_value(&self, ty: &str, value: &serde_json::Value) -> Result<B256, String> {
+        if let Some(inner) = ty.strip_suffix(']') {
+            let elem_ty = &inner[..inner.rfind('[').ok_or("malformed array type")?];
+            let items = value.as_array().ok_or_else(|| format!("expected array for '{ty}'"))?;
+            let mut buf = Vec::with_capacity(items.len() * 32);
+            for item in items {
+                buf.extend_from_slice(self.encode_value(elem_ty, item)?.as_slice());
+            }
+            return Ok(keccak256(&buf));
+        }
+        if self.structs.contains_key(ty) {
+            return self.hash_struct(ty, value);
+        }
+        match ty {
+            "string" => Ok(keccak256(value.as_str().unwrap_or("").as_bytes())),
+            "bytes" => Ok(keccak256(decode_hex_value(value)?)),
+            "bool" => {
+                let b = value.as_bool()
+                    .or_else(|| value.as_str().map(|s| s == "true"))
+                    .ok_or("expected bool")?;
+                Ok(B256::from(U256::from(b as u8).to_be_bytes::<32>()))
+            }
+            "address" => {
+                let raw = decode_hex_value(value)?;
+                if raw.len() != 20 {
+                    return Err(format!("invalid address {value}"));
+                }
+                let mut word = [0u8; 32];
+                word[12..].copy_from_slice(&raw);
+                Ok(B256::from(word))
+            }
+            t if t.starts_with("bytes") => {
+                let raw = decode_hex_value(value)?;
+                if raw.len() > 32 {
+                    return Err(format!("value too long for {t}"));
+                }
+                let mut word = [0u8; 32];
+                word[..raw.len()].copy_from_slice(&raw);
+                Ok(B256::from(word))
+            }
+            t if t.starts_with("uint") || t.starts_with("int") => {
+                Ok(B256::from(parse_integer(value)?.to_be_bytes::<32>()))
+            }
+            other => Err(format!("unsupported type '{other}'")),
+        }
+    }
+}
+
+fn decode_hex_value(value: &serde_json::Value) -> Result<Vec<u8>, String> {
+    let s = value.as_str().ok_or_else(|| format!("expected hex string, got {value}"))?;
+    hex::decode(s.trim_start_matches("0x")).map_err(|_| format!("invalid hex '{s}'"))
+}
+
+/// Parse a JSON integer (number, decimal string, hex string, negative)
+/// into its 256-bit two's-complement word.
+pub fn parse_integer(value: &serde_json::Value) -> Result<U256, String> {
+    if let Some(n) = value.as_u64() {
+        return Ok(U256::from(n));
+    }
+    if let Some(n) = value.as_i64() {
+        return Ok(U256::ZERO.wrapping_sub(U256::from(n.unsigned_abs())));
+    }
+    let s = match value {
+        serde_json::Value::String(s) => s.as_str(),
+        // Large JSON numbers that don't fit in u64/i64.
+        serde_json::Value::Number(n) => return U256::from_str(&n.to_string()).map_err(|e| e.to_string()),
+        _ => return Err(format!("expected integer, got {value}")),
+    };
+    match s.strip_prefix('-') {
+        Some(abs) => U256::from_str(abs)
+            .map(|v| U256::ZERO.wrapping_sub(v))
+            .map_err(|e| format!("invalid integer '{s}': {e}")),
+        None => U256::from_str(s).map_err(|e| format!("invalid integer '{s}': {e}")),
+    }
+}
+
+/// Full EIP-712 signing digest:
+/// `keccak256(0x1901 ‖ hashStruct(EIP712Domain) ‖ hashStruct(message))`.
+pub fn signing_hash(typed_data: &serde_json::Value) -> Result<B256, String> {
+    let graph = TypeGraph::parse(typed_data.get("types").ok_or("missing `types`")?)?;
+    let primary = typed_data.get("primaryType").and_then(|v| v.as_str()).ok_or("missing `primaryType`")?;
+    let domain = typed_data.get("domain").ok_or("missing `domain`")?;
+    let message = typed_data.get("message").ok_or("missing `message`")?;
+
+    let mut buf = vec![0x19, 0x01];
+    buf.extend_from_slice(graph.hash_struct("EIP712Domain", domain)?.as_slice());
+    buf.extend_from_slice(graph.hash_struct(primary, message)?.as_slice());
+    Ok(keccak256(&buf))
+}
+
+/// Describe every dangerous struct reachable from the primary type.
+///
+/// - `Ok(vec![])` — the type graph is well-formed and contains nothing
+///   token-moving.
+/// - `Ok(findings)` — one entry per dangerous struct found.
+/// - `Err(reason)` — the type graph is malformed (fail closed).
+///
+/// Payloads without a `types` object (legacy `eth_signTypedData` v1
+/// arrays) return `Ok(vec![])`; they are handled by the primary-type check.
+pub fn find_dangerous_structs(typed_data: &serde_json::Value) -> Result<Vec<String>, String> {
+    let Some(types) = typed_data.get("types") else {
+        return Ok(Vec::new());
+    };
+    let graph = TypeGraph::parse(types)?;
+    let primary = typed_data
+        .get("primaryType")
+        .and_then(|v| v.as_str())
+        .ok_or("missing `primaryType`")?;
+    if graph.members(primary).is_none() {
+        return Err(format!("primaryType '{primary}' is not defined in `types`"));
+    }
+
+    let mut findings = Vec::new();
+    for name in graph.reachable(primary) {
+        let members = graph.members(&name).unwrap_or(&[]);
+        let fields = members
+            .iter()
+            .map(|m| format!("{} {}", m.ty, m.name))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        if let Some((known, _)) = DANGEROUS_STRUCTS.iter().find(|(n, _)| n.eq_ignore_ascii_case(&name)) {
+            findings.push(format!("struct '{name}' is a known {known} type"));
+        } else if let Some((known, _)) = DANGEROUS_STRUCTS
+            .iter()
+            .find(|(_, shape)| !shape.is_empty() && *shape == fields)
+        {
+            findings.push(format!("struct '{name}' has the exact shape of {known} (renamed)"));
+        } else if members.iter().any(|m| GRANTEE_FIELDS.contains(&m.name.as_str()))
+            && members.iter().any(|m| AMOUNT_FIELDS.contains(&m.name.as_str()))
+        {
+            findings.push(format!("struct '{name}' is approval-shaped ({fields})"));
+        }
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            eip712_trusted_domains: vec![TrustedTypedDataDomain {
+                verifying_contract: "0x000000000022D473030F116dDEE9F6B43aC78BA3".into(),
+                name: "Permit2".into(),
+                chain_id: 1,
+            }],
+            ..Config::default()
+        }
+    }
+
+    fn typed(contract: &str, name: &str, chain: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "primaryType": "PermitSingle",
+            "domain": { "name": name, "chainId": chain, "verifyingContract": contract },
+            "message": {}
+        })
+    }
+
+    #[test]
+    fn test_parse_chain_id_formats() {
+        assert_eq!(parse_chain_id(&serde_json::json!(137)), Some(137));
+        assert_eq!(parse_chain_id(&serde_json::json!("0x89")), Some(137));
+        assert_eq!(parse_chain_id(&serde_json::json!("137")), Some(137));
+        assert_eq!(parse_chain_id(&serde_json::json!("nope")), None);
+    }
+
+    #[test]
+    fn test_trusted_domain_exact_match() {
+        let config = config();
+        let contract = "0x000000000022d473030f116ddee9f6b43ac78ba3";
+        assert!(trusted_domain(&config, &typed(contract, "Permit2", serde_json::json!("0x1"))).is_some());
+        // Same contract, different chain or name → untrusted.
+        assert!(trusted_domain(&config, &typed(contract, "Permit2", serde_json::json!(10))).is_none());
+        assert!(trusted_domain(&config, &typed(contract, "Permit3", serde_json::json!(1))).is_none());
+    }
+
+    #[test]
+    fn test_missing_domain_fields_untrusted() {
+        let config = config();
+        let data = serde_json::json!({ "primaryType": "PermitSingle", "domain": { "name": "Permit2" } });
+        assert!(trusted_domain(&config, &data).is_none());
+    }
+
+    fn mail_example() -> serde_json::Value {
+        serde_json::json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallet", "type": "address" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person" },
+                    { "name": "contents", "type": "string" }
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+                "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+                "contents": "Hello, Bob!"
+            }
+        })
+    }
+
+    #[test]
+    fn test_eip712_spec_vector() {
+        let data = mail_example();
+        let graph = TypeGraph::parse(&data["types"]).unwrap();
+        assert_eq!(
+            graph.encode_type("Mail").unwrap(),
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        );
+        assert_eq!(
+            format!("{:x}", graph.hash_struct("EIP712Domain", &data["domain"]).unwrap()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+        assert_eq!(
+            format!("{:x}", signing_hash(&data).unwrap()),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+        assert!(find_dangerous_structs(&data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_nested_permit_under_innocuous_wrapper() {
+        let data = serde_json::json!({
+            "types": {
+                "EIP712Domain": [{ "name": "name", "type": "string" }],
+                "Login": [
+                    { "name": "greeting", "type": "string" },
+                    { "name": "details", "type": "PermitDetails" }
+                ],
+                "PermitDetails": [
+                    { "name": "token", "type": "address" },
+                    { "name": "amount", "type": "uint160" },
+                    { "name": "expiration", "type": "uint48" },
+                    { "name": "nonce", "type": "uint48" }
+                ]
+            },
+            "primaryType": "Login",
+            "domain": { "name": "Totally Safe" },
+            "message": {}
+        });
+        let findings = find_dangerous_structs(&data).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].contains("PermitDetails"));
+    }
+
+    #[test]
+    fn test_renamed_permit_detected_by_shape() {
+        let data = serde_json::json!({
+            "types": {
+                "EIP712Domain": [],
+                "Hello": [
+                    { "name": "owner", "type": "address" },
+                    { "name": "spender", "type": "address" },
+                    { "name": "value", "type": "uint256" },
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "deadline", "type": "uint256" }
+                ]
+            },
+            "primaryType": "Hello",
+            "domain": {},
+            "message": {}
+        });
+        let findings = find_dangerous_structs(&data).unwrap();
+        assert!(findings[0].contains("renamed"));
+    }
+
+    #[test]
+    fn test_erc4494_nft_permit_detected() {
+        let data = serde_json::json!({
+            "types": {
+                "EIP712Domain": [],
+                "Claim": [
+                    { "name": "spender", "type": "address" },
+                    { "name": "tokenId", "type": "uint256" },
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "deadline", "type": "uint256" }
+                ],
+                "Badge": [
+                    { "name": "operator", "type": "address" },
+                    { "name": "approved", "type": "bool" }
+                ],
+                "Mint": [
+                    { "name": "claim", "type": "Claim" },
+                    { "name": "badge", "type": "Badge" }
+                ]
+            },
+            "primaryType": "Mint",
+            "domain": {},
+            "message": {}
+        });
+        let findings = find_dangerous_structs(&data).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert!(findings.iter().any(|f| f.contains("'Claim' has the exact shape of Permit")));
+        assert!(findings.iter().any(|f| f.contains("'Badge' is approval-shaped")));
+    }
+
+    #[test]
+    fn test_malformed_graph_fails_closed() {
+        let undefined = serde_json::json!({
+            "types": { "Login": [{ "name": "x", "type": "Mystery" }] },
+            "primaryType": "Login"
+        });
+        assert!(find_dangerous_structs(&undefined).is_err());
+
+        let missing_primary = serde_json::json!({ "types": {}, "primaryType": "Permit" });
+        assert!(find_dangerous_structs(&missing_primary).is_err());
+    }
+
+    #[test]
+    fn test_negative_int_encoding() {
+        assert_eq!(parse_integer(&serde_json::json!(-1)).unwrap(), U256::MAX);
+        assert_eq!(parse_integer(&serde_json::json!("-1")).unwrap(), U256::MAX);
+        assert_eq!(parse_integer(&serde_json::json!("0x10")).unwrap(), U256::from(16));
+    }
+}
@@ -0,0 +1,425 @@
+//! EIP-7702 delegation analysis.
+//!
+//! A type-4 (SetCode) transaction carries an authorization list: each
+//! entry lets an EOA ("authority") install `0xef0100 || delegate` as its
+//! code, turning the account into a proxy for the delegate implementation
+//! until the next SetCode. A prompt-injected agent that signs one
+//! delegation to a drainer hands over everything the wallet holds, forever.
+//!
+//! Every authorization is decoded, the authority recovered, and the
+//! delegate must be:
+//!
+//!   - on the operator allowlist (`eip7702_allowed_delegates`),
+//!   - absent from the Engine 0 threat filter, and
+//!   - deployed code (not empty, not itself a delegation designator).
+//!
+//! Authorizations with `chain_id = 0` (replayable on every chain) or for
+//! another chain are rejected outright. Delegating to the zero address
+//! clears a delegation and is always allowed.
+//!
+//! The delegate's code is returned so the simulator can execute the
+//! transaction against the authority as it will look *after* the SetCode.
+//!
+//! The same delegate rules apply to signed `Delegation` typed data
+//! (delegation-framework grants to a `delegate` address).
+
+use crate::config::Config;
+use crate::simulator;
+use crate::threat_feed::SharedThreatFilter;
+use crate::types;
+use ethers::types::{Address, Signature, H256, U256};
+use ethers::utils::keccak256;
+use ethers::utils::rlp::{DecoderError, Rlp, RlpStream};
+
+/// EIP-2718 transaction type of a SetCode transaction.
+pub const SET_CODE_TX_TYPE: u8 = 0x04;
+
+/// Prefix of the authorization signing payload.
+const AUTHORIZATION_MAGIC: u8 = 0x05;
+
+/// Code prefix of a delegated EOA: `0xef0100 || address`.
+pub const DELEGATION_DESIGNATOR: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// Number of RLP fields in a signed SetCode transaction.
+const SET_CODE_TX_FIELDS: usize = 13;
+
+/// One entry of the authorization list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Authorization {
+    pub chain_id: U256,
+    /// The delegate implementation (lowercase hex).
+    pub address: String,
+    pub nonce: u64,
+    /// Recovered signer of the authorization, if the signature is valid.
+    pub authority: Option<String>,
+}
+
+impl Authorization {
+    /// Delegating to the zero address clears the authority's code.
+    pub fn is_revocation(&self) -> bool {
+        self.address == format!("{:#x}", Address::zero())
+    }
+}
+
+/// A decoded type-4 transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetCodeTx {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub to: String,
+    pub value: U256,
+    pub data: Vec<u8>,
+    pub authorizations: Vec<Authorization>,
+    /// Recovered transaction sender.
+    pub sender: String,
+}
+
+/// Signing hash of an authorization tuple:
+/// `keccak256(0x05 || rlp([chain_id, address, nonce]))`.
+pub fn authorization_hash(chain_id: U256, address: Address, nonce: u64) -> H256 {
+    let mut stream = RlpStream::new_list(3);
+    stream.append(&chain_id);
+    stream.append(&address);
+    stream.append(&nonce);
+    let mut payload = vec![AUTHORIZATION_MAGIC];
+    payload.extend_from_slice(&stream.out());
+    H256::from(keccak256(payload))
+}
+
+fn recover(hash: H256, y_parity: u64, r: U256, s: U256) -> Option<String> {
+    if y_parity > 1 {
+        return None;
+    }
+    let signature = Signature { r, s, v: y_parity + 27 };
+    signature.recover(hash).ok().map(|a| format!("{:#x}", a))
+}
+
+fn decode_authorization(item: &Rlp) -> Result<Authorization, DecoderError> {
+    let chain_id: U256 = item.val_at(0)?;
+    let address: Address = item.val_at(1)?;
+    let nonce: u64 = item.val_at(2)?;
+    let y_parity: u64 = item.val_at(3)?;
+    let r: U256 = item.val_at(4)?;
+    let s: U256 = item.val_at(5)?;
+    Ok(Authorization {
+        chain_id,
+        address: format!("{:#x}", address),
+        nonce,
+        authority: recover(authorization_hash(chain_id, address, nonce), y_parity, r, s),
+    })
+}
+
+/// Decode a raw signed transaction. `Ok(None)` if it is not type-4.
+pub fn decode_raw(raw: &[u8]) -> Result<Option<SetCodeTx>, String> {
+    let Some((&SET_CODE_TX_TYPE, body)) = raw.split_first() else {
+        return Ok(None);
+    };
+    let rlp = Rlp::new(body);
+    let decode = || -> Result<SetCodeTx, DecoderError> {
+        if rlp.item_count()? != SET_CODE_TX_FIELDS {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let to: Address = rlp.val_at(5)?;
+        let authorizations = rlp
+            .at(9)?
+            .iter()
+            .map(|item| decode_authorization(&item))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Sender: sign over `0x04 || rlp(fields[0..10])`.
+        let mut unsigned = RlpStream::new_list(10);
+        for i in 0..10 {
+            unsigned.append_raw(rlp.at(i)?.as_raw(), 1);
+        }
+        let mut payload = vec![SET_CODE_TX_TYPE];
+        payload.extend_from_slice(&unsigned.out());
+        let sender = recover(
+            H256::from(keccak256(payload)),
+            rlp.val_at(10)?,
+            rlp.val_at(11)?,
+            rlp.val_at(12)?,
+        )
+        .ok_or(DecoderError::Custom("invalid transaction signature"))?;
+
+        Ok(SetCodeTx {
+            chain_id: rlp.val_at(0)?,
+            nonce: rlp.val_at(1)?,
+            to: format!("{:#x}", to),
+            value: rlp.val_at(6)?,
+            data: rlp.val_at(7)?,
+            authorizations,
+            sender,
+        })
+    };
+    let tx = decode().map_err(|e| format!("undecodable SetCode transaction: {e}"))?;
+    if tx.authorizations.is_empty() {
+        // EIP-7702: an empty authorization list makes the tx invalid.
+        return Err("SetCode transaction with an empty authorization list".into());
+    }
+    Ok(Some(tx))
+}
+
+/// Decode a hex-encoded raw transaction (`eth_sendRawTransaction` param).
+pub fn decode_raw_hex(raw_hex: &str) -> Result<Option<SetCodeTx>, String> {
+    types::decode_raw_tx_hex(raw_hex, decode_raw)
+}
+
+fn expected_chain_id(config: &Config) -> u64 {
+    if config.expected_chain_id != 0 {
+        config.expected_chain_id
+    } else {
+        config.chain_id
+    }
+}
+
+/// Verify a delegate implementation and return its runtime code.
+pub async fn check_delegate(
+    config: &Config,
+    threat_filter: &SharedThreatFilter,
+    delegate: &str,
+) -> Result<Vec<u8>, String> {
+    let blacklisted = threat_filter
+        .read()
+        .map(|f| f.is_address_blacklisted(delegate))
+        .unwrap_or(false);
+    if blacklisted {
+        return Err(format!(
+            "PLIMSOLL EIP-7702: delegate {delegate} is on the global threat blacklist"
+        ));
+    }
+
+    let allowed = config
+        .eip7702_allowed_delegates
+        .split(',')
+        .map(str::trim)
+        .any(|a| !a.is_empty() && a.eq_ignore_ascii_case(delegate));
+    if !allowed {
+        return Err(format!(
+            "PLIMSOLL EIP-7702: delegate {delegate} is not a verified implementation \
+             (not in eip7702_allowed_delegates)"
+        ));
+    }
+
+    let code = simulator::fetch_code(&config.upstream_rpc_url, delegate)
+        .await
+        .map_err(|e| format!("PLIMSOLL EIP-7702: could not fetch code of delegate {delegate}: {e}"))?;
+    if code.is_empty() {
+        return Err(format!("PLIMSOLL EIP-7702: delegate {delegate} has no deployed code"));
+    }
+    if code.starts_with(&DELEGATION_DESIGNATOR) {
+        return Err(format!(
+            "PLIMSOLL EIP-7702: delegate {delegate} is itself a delegated EOA (chained delegation)"
+        ));
+    }
+    Ok(code)
+}
+
+/// Check every authorization of a SetCode transaction.
+///
+/// On success returns the code the transaction's `to` will execute, if
+/// `to` is one of the delegating authorities.
+pub async fn check_set_code_tx(
+    config: &Config,
+    threat_filter: &SharedThreatFilter,
+    tx: &SetCodeTx,
+) -> Result<Option<Vec<u8>>, String> {
+    let chain_id = expected_chain_id(config);
+    if tx.chain_id != chain_id {
+        return Err(format!(
+            "PLIMSOLL EIP-7702: transaction chain ID {} != expected {}",
+            tx.chain_id, chain_id
+        ));
+    }
+
+    let mut to_code = None;
+    for auth in &tx.authorizations {
+        if auth.chain_id.is_zero() {
+            return Err(format!(
+                "PLIMSOLL EIP-7702: authorization to {} has chain_id 0 — replayable on every chain",
+                auth.address
+            ));
+        }
+        if auth.chain_id != U256::from(chain_id) {
+            return Err(format!(
+                "PLIMSOLL EIP-7702: authorization chain ID {} != expected {}",
+                auth.chain_id, chain_id
+            ));
+        }
+        let authority = auth.authority.as_deref().ok_or_else(|| {
+            format!("PLIMSOLL EIP-7702: authorization to {} has an invalid signature", auth.address)
+        })?;
+        if auth.is_revocation() {
+            continue;
+        }
+        let code = check_delegate(config, threat_filter, &auth.address).await?;
+        // The last valid authorization for an authority wins on-chain.
+        if authority.eq_ignore_ascii_case(&tx.to) {
+            to_code = Some(code);
+        }
+    }
+    Ok(to_code)
+}
+
+/// Check a signed `Delegation` (delegation framework) typed-data payload:
+/// the signer must be the delegator and the delegate must pass
+/// [`check_delegate`]. Returns a summary on success.
+pub async fn check_typed_delegation(
+    config: &Config,
+    threat_filter: &SharedThreatFilter,
+    typed_data: &serde_json::Value,
+    signer: &str,
+) -> Result<String, String> {
+    let msg = typed_data.get("message").ok_or("PLIMSOLL EIP-7702: Delegation without message")?;
+    let field = |name: &str| {
+        msg.get(name)
+            .and_then(|v| v.as_str())
+            .map(str::to_lowercase)
+            .ok_or_else(|| format!("PLIMSOLL EIP-7702: Delegation missing `{name}`"))
+    };
+    let delegate = field("delegate")?;
+    let delegator = field("delegator")?;
+    if !delegator.eq_ignore_ascii_case(signer) {
+        return Err(format!(
+            "PLIMSOLL EIP-7702: Delegation delegator {delegator} is not the signer {signer}"
+        ));
+    }
+    check_delegate(config, threat_filter, &delegate).await?;
+    Ok(format!("Delegation from {delegator} to verified delegate {delegate}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threat_feed::new_shared_filter;
+    use ethers::signers::{LocalWallet, Signer};
+    use std::str::FromStr;
+
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const DELEGATE: &str = "0x5555555555555555555555555555555555555555";
+
+    fn wallet() -> LocalWallet {
+        LocalWallet::from_str(KEY).unwrap()
+    }
+
+    fn signed_auth(chain_id: u64, delegate: &str) -> Vec<u8> {
+        let address = Address::from_str(delegate).unwrap();
+        let hash = authorization_hash(U256::from(chain_id), address, 0);
+        let sig = wallet().sign_hash(hash).unwrap();
+        let mut s = RlpStream::new_list(6);
+        s.append(&U256::from(chain_id));
+        s.append(&address);
+        s.append(&0u64);
+        s.append(&(sig.v - 27));
+        s.append(&sig.r);
+        s.append(&sig.s);
+        s.out().to_vec()
+    }
+
+    fn raw_tx(chain_id: u64, auths: &[Vec<u8>]) -> Vec<u8> {
+        let to = wallet().address();
+        let mut s = RlpStream::new_list(10);
+        s.append(&chain_id);
+        s.append(&0u64);
+        s.append(&1u64);
+        s.append(&2u64);
+        s.append(&21_000u64);
+        s.append(&to);
+        s.append(&U256::zero());
+        s.append(&Vec::<u8>::new());
+        s.begin_list(0);
+        s.begin_list(auths.len());
+        for a in auths {
+            s.append_raw(a, 1);
+        }
+        let unsigned = s.out();
+        let mut payload = vec![SET_CODE_TX_TYPE];
+        payload.extend_from_slice(&unsigned);
+        let sig = wallet().sign_hash(H256::from(keccak256(&payload))).unwrap();
+
+        let fields = Rlp::new(&unsigned);
+        let mut signed = RlpStream::new_list(SET_CODE_TX_FIELDS);
+        for i in 0..10 {
+            signed.append_raw(fields.at(i).unwrap().as_raw(), 1);
+        }
+        signed.append(&(sig.v - 27));
+        signed.append(&sig.r);
+        signed.append(&sig.s);
+        let mut raw = vec![SET_CODE_TX_TYPE];
+        raw.extend_from_slice(&signed.out());
+        raw
+    }
+
+    #[test]
+    fn test_decode_recovers_authority_and_sender() {
+        let me = format!("{:#x}", wallet().address());
+        let tx = decode_raw(&raw_tx(1, &[signed_auth(1, DELEGATE)])).unwrap().unwrap();
+        assert_eq!(tx.sender, me);
+        assert_eq!(tx.to, me);
+        assert_eq!(tx.authorizations.len(), 1);
+        assert_eq!(tx.authorizations[0].address, DELEGATE);
+        assert_eq!(tx.authorizations[0].authority.as_deref(), Some(me.as_str()));
+    }
+
+    #[test]
+    fn test_non_type4_ignored_and_empty_list_rejected() {
+        assert_eq!(decode_raw(&[0x02, 0xc0]).unwrap(), None);
+        assert!(decode_raw(&raw_tx(1, &[])).is_err());
+        assert!(decode_raw(&[SET_CODE_TX_TYPE, 0xc0]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_chain_and_wrong_chain_blocked() {
+        let filter = new_shared_filter();
+        let config = Config::default();
+        let tx = decode_raw(&raw_tx(1, &[signed_auth(0, DELEGATE)])).unwrap().unwrap();
+        let err = check_set_code_tx(&config, &filter, &tx).await.unwrap_err();
+        assert!(err.contains("chain_id 0"));
+        let tx = decode_raw(&raw_tx(1, &[signed_auth(10, DELEGATE)])).unwrap().unwrap();
+        assert!(check_set_code_tx(&config, &filter, &tx).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unverified_and_blacklisted_delegates_blocked() {
+        let filter = new_shared_filter();
+        let mut config = Config::default();
+        let tx = decode_raw(&raw_tx(1, &[signed_auth(1, DELEGATE)])).unwrap().unwrap();
+        let err = check_set_code_tx(&config, &filter, &tx).await.unwrap_err();
+        assert!(err.contains("not a verified implementation"));
+
+        config.eip7702_allowed_delegates = DELEGATE.into();
+        filter.write().unwrap().add_address(DELEGATE);
+        let err = check_set_code_tx(&config, &filter, &tx).await.unwrap_err();
+        assert!(err.contains("blacklist"));
+    }
+
+    #[tokio::test]
+    async fn test_revocation_allowed() {
+        let zero = format!("{:#x}", Address::zero());
+        let tx = decode_raw(&raw_tx(1, &[signed_auth(1, &zero)])).unwrap().unwrap();
+        assert!(tx.authorizations[0].is_revocation());
+        let result = check_set_code_tx(&Config::default(), &new_shared_filter(), &tx).await;
+        assert_eq!(result, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_typed_delegation_requires_signer_as_delegator() {
+        let data = serde_json::json!({
+            "primaryType": "Delegation",
+            "message": {
+                "delegate": DELEGATE,
+                "delegator": "0x2222222222222222222222222222222222222222",
+                "authority": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "caveats": [], "salt": "0"
+            }
+        });
+        let err = check_typed_delegation(
+            &Config::default(),
+            &new_shared_filter(),
+            &data,
+            "0x1111111111111111111111111111111111111111",
+        )
+        .await
+        .unwrap_err();
+        assert!(err.contains("not the signer"));
+    }
+}
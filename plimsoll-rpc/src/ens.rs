@@ -0,0 +1,375 @@
+//! ENS resolution with spoof detection.
+//!
+//! An agent told to "pay vitalik.eth" trusts whatever the name resolves
+//! to. Attackers register names that *read* the same — Cyrillic `а` for
+//! Latin `a`, `1` for `l`, zero-width joiners — or a name that lapsed and
+//! was re-registered yesterday, and the payment goes to them.
+//!
+//! A name is accepted only if:
+//!
+//!   1. it has no invisible or homoglyph characters, and does not imitate
+//!      an operator-listed (`ens_known_names`) or previously verified name,
+//!   2. it forward-resolves through the ENS registry, and the address's
+//!      primary (reverse) name, if set, is the same name,
+//!   3. its `.eth` registration is older than `ens_min_name_age_days`.
+//!
+//! Names are checked when an agent resolves one through the proxy
+//! (`plimsoll_resolveName`) and when they appear in EIP-712 messages.
+//! The registry lookups (2 and 3) only run on chains where the ENS
+//! registry is deployed (`ENS_CHAINS`); elsewhere names get check 1 only.
+//! The registration age is read from the `NameRegistered` logs of the
+//! last `ens_min_name_age_days` worth of blocks, never from genesis.
+
+use crate::abi;
+use crate::config::Config;
+use crate::homoglyphs::{confusable, INVISIBLE};
+use crate::simulator;
+use alloy_primitives::keccak256;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `resolver(bytes32)` on the ENS registry.
+const RESOLVER: [u8; 4] = [0x01, 0x78, 0xb8, 0xbf];
+/// `addr(bytes32)`
+const ADDR: [u8; 4] = [0x3b, 0x3b, 0x57, 0xde];
+/// `name(bytes32)`
+const NAME: [u8; 4] = [0x69, 0x1f, 0x34, 0x31];
+/// `NameRegistered(uint256,address,uint256)` on the .eth base registrar.
+const NAME_REGISTERED_TOPIC: &str =
+    "0xb3d987963d01b2f68493b4bdb130988f157ea43070d4ad840fee0466ed9370d9";
+
+/// Chains the ENS registry and `.eth` registrar are deployed on, at the
+/// same addresses: mainnet, Sepolia, Holesky.
+pub const ENS_CHAINS: &[u64] = &[1, 11_155_111, 17_000];
+
+/// Blocks per day on the ENS chains (12 s slots).
+const BLOCKS_PER_DAY: u64 = 7_200;
+
+lazy_static! {
+    /// Names that passed verification — new names are compared against them.
+    static ref VERIFIED_NAMES: RwLock<HashSet<String>> = RwLock::new(HashSet::new());
+}
+
+/// A verified resolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    pub name: String,
+    pub address: String,
+    /// The address's primary (reverse) name.
+    pub primary_name: Option<String>,
+    /// Seconds since the `.eth` name was (last) registered. `None` when
+    /// not registered within the last `ens_min_name_age_days`.
+    pub age_secs: Option<u64>,
+}
+
+/// EIP-137 namehash.
+pub fn namehash(name: &str) -> [u8; 32] {
+    let mut node = [0u8; 32];
+    if name.is_empty() {
+        return node;
+    }
+    for label in name.rsplit('.') {
+        let mut buf = node.to_vec();
+        buf.extend_from_slice(keccak256(label.as_bytes()).as_slice());
+        node = keccak256(&buf).0;
+    }
+    node
+}
+
+/// Collapse characters and digraphs that read alike, so lookalikes of
+/// the same name share a skeleton.
+pub fn skeleton(name: &str) -> String {
+    let mapped: String = name
+        .to_lowercase()
+        .chars()
+        .filter(|c| !INVISIBLE.contains(c))
+        .map(|c| match confusable(c).unwrap_or(c) {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '5' => 's',
+            other => other,
+        })
+        .collect();
+    mapped.replace("rn", "m").replace("vv", "w").replace("cl", "d")
+}
+
+/// Whether the ENS registry is deployed on the chain `config` serves.
+pub fn deployed(config: &Config) -> bool {
+    ENS_CHAINS.contains(&config.default_chain_id())
+}
+
+/// Whether `s` looks like an ENS name.
+pub fn is_ens_name(s: &str) -> bool {
+    s.len() > 4
+        && s.to_lowercase().ends_with(".eth")
+        && !s.contains(|c: char| c.is_whitespace() || matches!(c, '/' | ':' | '@'))
+}
+
+/// ENS names among the string values of an EIP-712 `message`.
+pub fn names_in(typed_data: &serde_json::Value) -> Vec<String> {
+    fn walk(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(s) if is_ens_name(s.trim()) => out.push(s.trim().to_string()),
+            serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, out)),
+            serde_json::Value::Object(map) => map.values().for_each(|v| walk(v, out)),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    if let Some(message) = typed_data.get("message") {
+        walk(message, &mut out);
+    }
+    out
+}
+
+/// Reject invisible characters, homoglyphs and lookalikes of known names.
+pub fn check_name(config: &Config, name: &str) -> Result<(), String> {
+    if let Some(c) = name.chars().find(|c| INVISIBLE.contains(c)) {
+        return Err(format!(
+            "PLIMSOLL ENS SPOOF: {:?} contains invisible character U+{:04X}",
+            name, c as u32
+        ));
+    }
+    if let Some((c, latin)) = name.chars().find_map(|c| confusable(c).map(|l| (c, l))) {
+        return Err(format!(
+            "PLIMSOLL ENS SPOOF: {:?} contains '{}' (U+{:04X}) imitating Latin '{}'",
+            name, c, c as u32, latin
+        ));
+    }
+
+    let lower = name.to_lowercase();
+    let target = skeleton(&lower);
+    let verified = VERIFIED_NAMES.read().map(|v| v.clone()).unwrap_or_default();
+    let known = config
+        .ens_known_names
+        .split(',')
+        .map(|n| n.trim().to_lowercase())
+        .filter(|n| !n.is_empty())
+        .chain(verified);
+    for known in known {
+        if known != lower && skeleton(&known) == target {
+            return Err(format!(
+                "PLIMSOLL ENS SPOOF: {:?} is a lookalike of known name {:?}",
+                name, known
+            ));
+        }
+    }
+    Ok(())
+}
+
+async fn rpc(rpc_url: &str, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .with_context(|| format!("Failed to call {method}"))?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {method} response"))?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("{method} failed: {err}");
+    }
+    Ok(body["result"].clone())
+}
+
+/// Call `selector(node)` on `to`.
+async fn call_node(rpc_url: &str, to: &str, selector: [u8; 4], node: [u8; 32]) -> Result<Vec<u8>> {
+    let mut data = selector.to_vec();
+    data.extend_from_slice(&node);
+    simulator::eth_call(rpc_url, to, &data, "latest").await
+}
+
+fn non_zero(address: String) -> Option<String> {
+    address.trim_start_matches("0x").chars().any(|c| c != '0').then_some(address)
+}
+
+/// The resolver contract for `node`, if one is set.
+async fn resolver(config: &Config, node: [u8; 32]) -> Result<Option<String>> {
+    let ret = call_node(&config.upstream_rpc_url, &config.ens_registry, RESOLVER, node).await?;
+    Ok(non_zero(abi::address(&ret, 0).map_err(anyhow::Error::msg)?))
+}
+
+/// Forward-resolve `name` to an address.
+pub async fn resolve(config: &Config, name: &str) -> Result<Option<String>> {
+    let node = namehash(&name.to_lowercase());
+    let Some(resolver) = resolver(config, node).await? else {
+        return Ok(None);
+    };
+    let ret = call_node(&config.upstream_rpc_url, &resolver, ADDR, node).await?;
+    Ok(non_zero(abi::address(&ret, 0).map_err(anyhow::Error::msg)?))
+}
+
+/// The primary name `address` has claimed via `<addr>.addr.reverse`.
+async fn primary_name(config: &Config, address: &str) -> Result<Option<String>> {
+    let node = namehash(&format!("{}.addr.reverse", address.trim_start_matches("0x").to_lowercase()));
+    let Some(resolver) = resolver(config, node).await? else {
+        return Ok(None);
+    };
+    let ret = call_node(&config.upstream_rpc_url, &resolver, NAME, node).await?;
+    let name = abi::bytes(&ret, 0).map_err(anyhow::Error::msg)?;
+    let name = String::from_utf8(name).context("primary name is not UTF-8")?;
+    Ok((!name.is_empty()).then_some(name))
+}
+
+/// When the `.eth` second-level name above `name` was last registered,
+/// if that was within the last `ens_min_name_age_days`.
+async fn registered_at(config: &Config, name: &str) -> Result<Option<u64>> {
+    let labels: Vec<&str> = name.rsplit('.').collect();
+    if labels.len() < 2
+        || labels[0] != "eth"
+        || config.ens_base_registrar.is_empty()
+        || config.ens_min_name_age_days == 0
+    {
+        return Ok(None);
+    }
+    let labelhash = format!("0x{}", hex::encode(keccak256(labels[1].as_bytes())));
+    let latest = rpc(&config.upstream_rpc_url, "eth_blockNumber", serde_json::json!([])).await?;
+    let latest = latest.as_str().context("eth_blockNumber returned no number")?;
+    let latest = u64::from_str_radix(latest.trim_start_matches("0x"), 16)?;
+    let from = latest.saturating_sub(config.ens_min_name_age_days.saturating_mul(BLOCKS_PER_DAY));
+    let logs = rpc(
+        &config.upstream_rpc_url,
+        "eth_getLogs",
+        serde_json::json!([{
+            "address": config.ens_base_registrar,
+            "fromBlock": format!("0x{from:x}"),
+            "toBlock": format!("0x{latest:x}"),
+            "topics": [NAME_REGISTERED_TOPIC, labelhash],
+        }]),
+    )
+    .await?;
+    let Some(block) = logs
+        .as_array()
+        .and_then(|logs| logs.last())
+        .and_then(|log| log["blockNumber"].as_str())
+    else {
+        return Ok(None);
+    };
+    let header = rpc(&config.upstream_rpc_url, "eth_getBlockByNumber", serde_json::json!([block, false])).await?;
+    let ts = header["timestamp"].as_str().context("block has no timestamp")?;
+    Ok(Some(u64::from_str_radix(ts.trim_start_matches("0x"), 16)?))
+}
+
+/// Resolve `name` and run every spoof check. `Err` carries the block
+/// reason; lookup failures fail closed. Only for chains where ENS is
+/// `deployed`.
+pub async fn verify(config: &Config, name: &str) -> Result<Resolution, String> {
+    check_name(config, name)?;
+    let lower = name.to_lowercase();
+    let lookup_failed = |e: anyhow::Error| format!("PLIMSOLL ENS: lookup of {:?} failed: {}", name, e);
+
+    let address = resolve(config, &lower)
+        .await
+        .map_err(lookup_failed)?
+        .ok_or_else(|| format!("PLIMSOLL ENS: {:?} does not resolve to an address", name))?;
+
+    let primary = primary_name(config, &address).await.map_err(lookup_failed)?;
+    match &primary {
+        Some(p) if p.to_lowercase() != lower => {
+            return Err(format!(
+                "PLIMSOLL ENS SPOOF: {:?} resolves to {} whose primary name is {:?}",
+                name, address, p
+            ));
+        }
+        None if config.ens_require_primary_name => {
+            return Err(format!(
+                "PLIMSOLL ENS: {:?} resolves to {} which has no primary name",
+                name, address
+            ));
+        }
+        _ => {}
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let age_secs = registered_at(config, &lower)
+        .await
+        .map_err(lookup_failed)?
+        .map(|ts| now.saturating_sub(ts));
+    let min_age = config.ens_min_name_age_days.saturating_mul(24 * 3600);
+    if let Some(age) = age_secs.filter(|&age| age < min_age) {
+        return Err(format!(
+            "PLIMSOLL ENS SPOOF: {:?} was registered {:.1} days ago (minimum {})",
+            name,
+            age as f64 / 86400.0,
+            config.ens_min_name_age_days
+        ));
+    }
+
+    if let Ok(mut verified) = VERIFIED_NAMES.write() {
+        verified.insert(lower.clone());
+    }
+    Ok(Resolution { name: lower, address, primary_name: primary, age_secs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namehash() {
+        assert_eq!(namehash(""), [0u8; 32]);
+        assert_eq!(
+            hex::encode(namehash("eth")),
+            "93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+        );
+        assert_eq!(
+            hex::encode(namehash("vitalik.eth")),
+            "ee6c4522aab0003e8d14cd40a6af439055fd2577951148c14b6cea9a53475835"
+        );
+    }
+
+    #[test]
+    fn test_deployed_only_on_ens_chains() {
+        assert!(deployed(&Config::default()));
+        assert!(deployed(&Config { chain_id: 11_155_111, ..Config::default() }));
+        assert!(!deployed(&Config { chain_id: 8453, ..Config::default() }));
+        assert!(!deployed(&Config { chain_id: 1, expected_chain_id: 42161, ..Config::default() }));
+    }
+
+    #[test]
+    fn test_homoglyph_and_invisible_names_rejected() {
+        let config = Config::default();
+        // Cyrillic 'а'
+        let err = check_name(&config, "vit\u{0430}lik.eth").unwrap_err();
+        assert!(err.contains("U+0430"));
+        assert!(check_name(&config, "vita\u{200d}lik.eth").unwrap_err().contains("invisible"));
+        assert!(check_name(&config, "vitalik.eth").is_ok());
+    }
+
+    #[test]
+    fn test_ascii_lookalike_of_known_name_rejected() {
+        let config = Config { ens_known_names: "vitalik.eth, uniswap.eth".into(), ..Config::default() };
+        assert!(check_name(&config, "vita1ik.eth").unwrap_err().contains("\"vitalik.eth\""));
+        assert!(check_name(&config, "unlswap.eth").is_err());
+        assert!(check_name(&config, "VITALIK.eth").is_ok());
+        assert!(check_name(&config, "nick.eth").is_ok());
+    }
+
+    #[test]
+    fn test_names_in_typed_data_message() {
+        let typed = serde_json::json!({
+            "domain": { "name": "ens.eth" },
+            "message": {
+                "to": "vitalik.eth",
+                "memo": "see https://example.eth/x",
+                "nested": [{ "payee": " nick.eth " }]
+            }
+        });
+        let mut names = names_in(&typed);
+        names.sort();
+        assert_eq!(names, vec!["nick.eth", "vitalik.eth"]);
+    }
+}
@@ -0,0 +1,123 @@
+//! ERC-3009 `transferWithAuthorization` decoding.
+//!
+//! USDC (and every FiatToken v2 deployment, EURC, many bridged stables)
+//! accepts a signed `TransferWithAuthorization` / `ReceiveWithAuthorization`
+//! that moves tokens straight out of the signer's balance — no approval,
+//! no Permit2, no on-chain transaction from the agent. One signature is a
+//! completed transfer to whoever submits it.
+//!
+//! The payload is decoded into a synthetic `transferFrom` so it is
+//! reported the same way as permits.
+
+use crate::eip712::parse_integer;
+use alloy_primitives::U256;
+
+/// ERC-3009 primary types that move tokens.
+pub const TRANSFER_AUTHORIZATION_TYPES: &[&str] =
+    &["TransferWithAuthorization", "ReceiveWithAuthorization"];
+
+/// A decoded ERC-3009 authorization.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferAuthorization {
+    pub primary_type: String,
+    /// The token contract (`domain.verifyingContract`).
+    pub token: String,
+    pub from: String,
+    /// The recipient of the tokens.
+    pub to: String,
+    pub value: U256,
+    pub valid_after: U256,
+    pub valid_before: U256,
+}
+
+impl std::fmt::Display for TransferAuthorization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ERC20.transferFrom({}, {}, {}) on token {} via {} (valid {}..{})",
+            self.from,
+            self.to,
+            self.value,
+            self.token,
+            self.primary_type,
+            self.valid_after,
+            self.valid_before
+        )
+    }
+}
+
+/// Decode an ERC-3009 authorization. `Ok(None)` if the primary type is
+/// not one.
+pub fn decode(typed_data: &serde_json::Value) -> Result<Option<TransferAuthorization>, String> {
+    let primary = typed_data.get("primaryType").and_then(|v| v.as_str()).unwrap_or("");
+    let Some(primary_type) = TRANSFER_AUTHORIZATION_TYPES
+        .iter()
+        .find(|t| t.eq_ignore_ascii_case(primary))
+    else {
+        return Ok(None);
+    };
+    let msg = typed_data.get("message").ok_or("authorization missing `message`")?;
+    let address = |name: &str| {
+        msg.get(name)
+            .and_then(|v| v.as_str())
+            .map(str::to_lowercase)
+            .ok_or_else(|| format!("authorization missing `{name}`"))
+    };
+    let integer = |name: &str| {
+        msg.get(name)
+            .ok_or_else(|| format!("authorization missing `{name}`"))
+            .and_then(parse_integer)
+    };
+    Ok(Some(TransferAuthorization {
+        primary_type: primary_type.to_string(),
+        token: typed_data
+            .get("domain")
+            .and_then(|d| d.get("verifyingContract"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_lowercase(),
+        from: address("from")?,
+        to: address("to")?,
+        value: integer("value")?,
+        valid_after: integer("validAfter")?,
+        valid_before: integer("validBefore")?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    #[test]
+    fn test_decode_transfer_with_authorization() {
+        let data = serde_json::json!({
+            "primaryType": "TransferWithAuthorization",
+            "domain": { "name": "USD Coin", "version": "2", "chainId": 1, "verifyingContract": USDC },
+            "message": {
+                "from": "0x1111111111111111111111111111111111111111",
+                "to": "0x6666666666666666666666666666666666666666",
+                "value": "5000000000",
+                "validAfter": 0,
+                "validBefore": "1700003600",
+                "nonce": "0x0000000000000000000000000000000000000000000000000000000000000001"
+            }
+        });
+        let auth = decode(&data).unwrap().unwrap();
+        assert_eq!(auth.to, "0x6666666666666666666666666666666666666666");
+        assert_eq!(auth.value, U256::from(5_000_000_000u64));
+        assert_eq!(auth.token, USDC);
+        assert!(auth.to_string().starts_with("ERC20.transferFrom(0x1111"));
+    }
+
+    #[test]
+    fn test_other_types_ignored_and_missing_fields_rejected() {
+        assert_eq!(decode(&serde_json::json!({ "primaryType": "Mail" })).unwrap(), None);
+        let data = serde_json::json!({
+            "primaryType": "ReceiveWithAuthorization",
+            "message": { "from": "0x1111111111111111111111111111111111111111" }
+        });
+        assert!(decode(&data).is_err());
+    }
+}
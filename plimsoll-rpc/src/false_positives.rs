@@ -0,0 +1,252 @@
+//! False-positive reports.
+//!
+//! Without a way to dispute a block, an agent's operator can only turn a
+//! feed entry off wholesale (`threat_allowlist`). An agent calls
+//!
+//! ```text
+//! plimsoll_reportFalsePositive(tx_hash_or_address, note)
+//! ```
+//!
+//! with the synthetic hash of a blocked transaction or a blocked address.
+//! The report is kept with the protective state, listed and reviewed
+//! through `/admin/false-positives`, and uploaded to Plimsoll Cloud when
+//! `false_positive_upload` is set (never in `telemetry_offline` mode).
+//!
+//! With `false_positive_whitelist_secs` > 0 the Engine 0 entries the
+//! report names — the address, or the address / selector / calldata hash
+//! in the blocked tx's Engine 0 reason — are allowlisted until the report
+//! is reviewed or the window passes. Blocks by other engines are recorded
+//! only: their verdicts are the operator's policy, not feed data. Off by
+//! default, since a prompt-injected agent could "report" its way past a
+//! feed hit.
+
+use crate::config::{is_hex_address, Config};
+use crate::rpc;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Reports kept; the oldest reviewed ones go first.
+const MAX_REPORTS: usize = 1000;
+
+/// Longest note kept, in characters.
+const MAX_NOTE_CHARS: usize = 1000;
+
+/// Plimsoll Cloud false-positive intake.
+const CLOUD_URL: &str = "https://cloud.plimsoll.network/v1/false-positive";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FalsePositiveReport {
+    pub id: String,
+    /// Blocked tx hash or address, lowercase.
+    pub subject: String,
+    pub note: String,
+    /// Block reason of a reported tx.
+    pub block_reason: Option<String>,
+    /// Engine 0 entries the report disputes.
+    pub entries: Vec<String>,
+    pub reported_at: u64,
+    /// Unix time the entries stop being allowlisted while pending; `None`
+    /// = not allowlisted.
+    pub whitelisted_until: Option<u64>,
+    pub status: ReportStatus,
+}
+
+impl FalsePositiveReport {
+    /// Whether the entries are allowlisted at `now`.
+    fn whitelisted(&self, now: u64) -> bool {
+        match self.status {
+            ReportStatus::Accepted => true,
+            ReportStatus::Rejected => false,
+            ReportStatus::Pending => self.whitelisted_until.is_some_and(|until| now < until),
+        }
+    }
+}
+
+lazy_static! {
+    static ref REPORTS: Mutex<VecDeque<FalsePositiveReport>> = Mutex::new(VecDeque::new());
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Engine 0 entries named in a block reason.
+pub fn engine0_entries(reason: &str) -> Vec<String> {
+    if !reason.starts_with("ENGINE 0:") {
+        return Vec::new();
+    }
+    let mut entries: Vec<String> = reason
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|t| {
+            let selector = t.len() == 10 && t.starts_with("0x") && t[2..].chars().all(|c| c.is_ascii_hexdigit());
+            let calldata_hash = t.len() == 16 && t.chars().all(|c| c.is_ascii_hexdigit());
+            is_hex_address(t) || selector || calldata_hash
+        })
+        .collect();
+    entries.dedup();
+    entries
+}
+
+fn insert(report: FalsePositiveReport) {
+    let Ok(mut reports) = REPORTS.lock() else {
+        return;
+    };
+    if reports.len() >= MAX_REPORTS {
+        let oldest = reports
+            .iter()
+            .position(|r| r.status != ReportStatus::Pending)
+            .unwrap_or(0);
+        reports.remove(oldest);
+    }
+    reports.push_back(report);
+}
+
+/// Record a report of `subject`. Errors are for the agent.
+pub fn report(config: &Config, subject: &str, note: &str) -> Result<FalsePositiveReport, String> {
+    let subject = subject.trim().to_lowercase();
+    let (block_reason, entries) = if is_hex_address(&subject) {
+        (None, vec![subject.clone()])
+    } else if subject.starts_with("0x") {
+        let reason = rpc::blocked_reason(&subject)
+            .ok_or_else(|| format!("{subject} is not a transaction blocked by this proxy"))?;
+        let entries = engine0_entries(&reason);
+        (Some(reason), entries)
+    } else {
+        return Err("expected a blocked tx hash or an address".into());
+    };
+    let reported_at = now();
+    let whitelisted_until = (config.false_positive_whitelist_secs > 0 && !entries.is_empty())
+        .then(|| reported_at + config.false_positive_whitelist_secs);
+    let report = FalsePositiveReport {
+        id: format!("fp-{:x}-{}", reported_at, NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+        subject,
+        note: note.chars().take(MAX_NOTE_CHARS).collect(),
+        block_reason,
+        entries,
+        reported_at,
+        whitelisted_until,
+        status: ReportStatus::Pending,
+    };
+    warn!(
+        id = %report.id,
+        subject = %report.subject,
+        entries = ?report.entries,
+        whitelisted_until = ?report.whitelisted_until,
+        note = %report.note,
+        "False positive reported"
+    );
+    insert(report.clone());
+    if config.false_positive_upload && !config.telemetry_offline {
+        upload(report.clone());
+    }
+    Ok(report)
+}
+
+/// Send a report to Plimsoll Cloud. Fire-and-forget.
+fn upload(report: FalsePositiveReport) {
+    tokio::spawn(async move {
+        let sent = reqwest::Client::new()
+            .post(CLOUD_URL)
+            .json(&report)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await;
+        match sent {
+            Ok(resp) if resp.status().is_success() => info!(id = %report.id, "False-positive report uploaded"),
+            Ok(resp) => warn!(id = %report.id, status = resp.status().as_u16(), "False-positive upload rejected"),
+            Err(e) => warn!(id = %report.id, error = %e, "False-positive upload failed (non-blocking)"),
+        }
+    });
+}
+
+/// Accept (keep allowlisted) or reject (lift) a report. `None` if no
+/// report has that id.
+pub fn review(id: &str, accept: bool) -> Option<FalsePositiveReport> {
+    let mut reports = REPORTS.lock().ok()?;
+    let report = reports.iter_mut().find(|r| r.id == id)?;
+    report.status = if accept { ReportStatus::Accepted } else { ReportStatus::Rejected };
+    Some(report.clone())
+}
+
+/// Engine 0 entries allowlisted by reports right now.
+pub fn whitelisted() -> Vec<String> {
+    let now = now();
+    REPORTS
+        .lock()
+        .map(|reports| {
+            reports
+                .iter()
+                .filter(|r| r.whitelisted(now))
+                .flat_map(|r| r.entries.iter().cloned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// All reports, oldest first.
+pub fn snapshot() -> Vec<FalsePositiveReport> {
+    REPORTS.lock().map(|r| r.iter().cloned().collect()).unwrap_or_default()
+}
+
+/// Restore persisted reports (startup).
+pub fn restore(reports: Vec<FalsePositiveReport>) {
+    for report in reports {
+        let known = REPORTS.lock().map(|r| r.iter().any(|k| k.id == report.id)).unwrap_or(true);
+        if !known {
+            insert(report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine0_entries_from_reasons() {
+        assert_eq!(
+            engine0_entries("ENGINE 0: Address 0x6666666666666666666666666666666666666666 is listed by trm"),
+            vec!["0x6666666666666666666666666666666666666666"]
+        );
+        assert_eq!(engine0_entries("ENGINE 0: Selector 0xdeadbeef is blacklisted"), vec!["0xdeadbeef"]);
+        assert_eq!(
+            engine0_entries("ENGINE 0: Calldata hash 0123456789abcdef matches known exploit payload"),
+            vec!["0123456789abcdef"]
+        );
+        // Other engines' verdicts aren't feed entries.
+        assert!(engine0_entries("PLIMSOLL VELOCITY: 0x6666666666666666666666666666666666666666").is_empty());
+    }
+
+    #[test]
+    fn test_report_whitelists_until_reviewed() {
+        let address = "0xfa15e00000000000000000000000000000000001";
+        let off = report(&Config::default(), address, "ours").unwrap();
+        assert_eq!(off.whitelisted_until, None);
+        assert!(!whitelisted().contains(&address.to_string()));
+
+        let config = Config { false_positive_whitelist_secs: 3600, ..Config::default() };
+        let on = report(&config, &address.to_uppercase().replace("0X", "0x"), "our treasury").unwrap();
+        assert!(whitelisted().contains(&address.to_string()));
+        assert_eq!(review(&on.id, false).unwrap().status, ReportStatus::Rejected);
+        assert!(!whitelisted().contains(&address.to_string()));
+
+        assert!(report(&config, "0xplimsoll-unknown", "").is_err());
+        assert!(report(&config, "not a subject", "").is_err());
+    }
+}
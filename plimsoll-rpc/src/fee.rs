@@ -2,8 +2,83 @@
 //!
 //! Every successful transaction routed through Plimsoll is charged
 //! a 1-2 basis point fee. This is the revenue model for the protocol.
+//!
+//! With `fee_denomination = "traded"` the fee is charged in kind instead:
+//! a share of each ERC-20 the simulation shows leaving the portfolio —
+//! the stablecoin paid, the token sold — limited to `fee_tokens` when set.
+//! A send moving no such token pays in wei as before. Agent vaults often
+//! hold no spare ETH for a separate wei fee.
+//!
+//! Agents in `fee_exempt_addresses` pay nothing. With `fee_tiers` the rate
+//! follows the send's USD value (its value and ERC-20 outflows, priced
+//! like the oracle check); an unpriced send pays `fee_bps`.
+//!
+//! The proxy holds no keys, so it cannot pay a fee out of the user's tx.
+//! Fees accrue instead in a per-chain, per-agent, per-asset ledger,
+//! persisted with the protective state, and every
+//! `fee_settlement_interval_secs` each agent is sent one transaction per
+//! chain and asset it owes — a transfer to `fee_collector`, or an ERC-20
+//! `transfer` to it — signed by that chain's upstream node
+//! (`eth_signTransaction`), like the agent's own transactions. Native fees wait
+//! until they reach `fee_settlement_min_wei`, and any fee the oracle
+//! prices until it is worth `fee_settlement_min_usd`. An agent whose key the node
+//! doesn't hold, or whose chain is no longer served, keeps owing;
+//! `GET /admin/fees` lists the ledger.
+//!
+//! Settlement is idempotent. The signed transaction, with its nonce and
+//! hash, is persisted before it is broadcast, and the ledger after each
+//! settlement. When the broadcast's outcome is unknown (a timeout, a
+//! restart) the next round re-broadcasts the same signed transaction
+//! rather than a new one: its nonce can only be mined once, so a fee is
+//! never paid twice. It is dropped — the fee still owed — once the node
+//! rejects it and the nonce went to another transaction.
+
+use crate::chains;
+use crate::config::Config;
+use crate::fee_ledger;
+use crate::intents;
+use crate::oracle;
+use crate::reload::SharedConfigHandle;
+use crate::state_store::{self, SharedStateStore};
+use alloy_primitives::{keccak256, U256};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Ledger asset of fees charged in wei.
+pub const NATIVE: &str = "native";
+
+/// `transfer(address,uint256)`
+const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
 
-use tracing::info;
+lazy_static! {
+    /// (agent, asset) → fee owed; the agent is scoped to the chain it was
+    /// charged on (`chains::scope_key`), the asset is `native` or an ERC-20
+    /// (lowercase).
+    static ref LEDGER: Mutex<HashMap<(String, String), U256>> = Mutex::new(HashMap::new());
+
+    /// (agent, asset) → settlement signed but not known to be accepted,
+    /// keyed like `LEDGER`.
+    static ref PENDING: Mutex<HashMap<(String, String), PendingSettlement>> = Mutex::new(HashMap::new());
+}
+
+/// A signed settlement transaction whose broadcast may not have reached
+/// the node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingSettlement {
+    /// Chain-scoped agent (`chains::scope_key`).
+    pub agent: String,
+    pub asset: String,
+    /// Fee it pays, decimal.
+    pub amount: String,
+    pub nonce: u64,
+    pub tx_hash: String,
+    pub raw_tx: String,
+}
 
 /// Calculate the fee amount for a given transaction value.
 /// Fee is in basis points (1 bps = 0.01%).
@@ -15,40 +90,451 @@ pub fn calculate_fee(value_wei: u128, fee_bps: u16) -> u128 {
     value_wei * (fee_bps as u128) / 10000
 }
 
-/// Build a fee transfer transaction to be bundled with the user's tx.
-///
-/// In production, this creates an additional tx in the Flashbots bundle
-/// that sends the fee from the user's tx output to the fee collector.
-///
-/// # TODO (Production)
-/// Integrate with Flashbots bundle builder to atomically collect fees.
+/// Fee on a token amount, in that token.
+pub fn calculate_token_fee(amount: U256, fee_bps: u16) -> U256 {
+    amount.saturating_mul(U256::from(fee_bps)) / U256::from(10_000u64)
+}
+
+/// Basis points `from` pays on a send worth `value_usd`: none when exempt,
+/// else the highest `fee_tiers` tier the value reaches, else `fee_bps`.
+pub fn fee_bps(config: &Config, from: &str, value_usd: Option<f64>) -> u16 {
+    let exempt = config
+        .fee_exempt_addresses
+        .split(',')
+        .any(|a| a.trim().eq_ignore_ascii_case(from));
+    if exempt {
+        return 0;
+    }
+    value_usd
+        .and_then(|usd| {
+            config
+                .fee_tiers
+                .iter()
+                .filter(|t| t.min_usd <= usd)
+                .max_by(|a, b| a.min_usd.total_cmp(&b.min_usd))
+        })
+        .map_or(config.fee_bps, |tier| tier.bps)
+}
+
+/// USD value of a send for the tier schedule: its value in wei and the
+/// ERC-20s it moves out. `None` with no tiers or no priced leg.
+pub async fn value_usd(config: &Config, value_wei: u128, outflows: &[(String, U256)]) -> Option<f64> {
+    if config.fee_tiers.is_empty() {
+        return None;
+    }
+    let mut legs: Vec<(&str, U256)> = outflows
+        .iter()
+        .filter(|(token, _)| token.starts_with("0x"))
+        .map(|(token, amount)| (token.as_str(), *amount))
+        .collect();
+    if value_wei > 0 {
+        legs.push((NATIVE, U256::from(value_wei)));
+    }
+    let mut total = None;
+    for (asset, amount) in legs {
+        if let Some(price) = oracle::price(config, asset).await {
+            *total.get_or_insert(0.0) += intents::usd_value(amount, &price);
+        }
+    }
+    total
+}
+
+/// What `from` is charged for a forwarded send, per asset: in kind on the
+/// ERC-20s it moves out (`fee_denomination = "traded"`), else on its value
+/// in wei; at the `fee_bps` rate for a send worth `value_usd`.
+pub fn charges(
+    config: &Config,
+    from: &str,
+    value_wei: u128,
+    outflows: &[(String, U256)],
+    value_usd: Option<f64>,
+) -> Vec<(String, U256)> {
+    let bps = fee_bps(config, from, value_usd);
+    if bps == 0 {
+        return Vec::new();
+    }
+    if config.fee_denomination == "traded" {
+        let allowed: Vec<String> = config
+            .fee_tokens
+            .split(',')
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        let in_kind: Vec<(String, U256)> = outflows
+            .iter()
+            .filter(|(token, _)| token.starts_with("0x") && (allowed.is_empty() || allowed.contains(token)))
+            .map(|(token, sent)| (token.clone(), calculate_token_fee(*sent, bps)))
+            .filter(|(_, fee)| !fee.is_zero())
+            .collect();
+        if !in_kind.is_empty() {
+            return in_kind;
+        }
+    }
+    let fee = calculate_fee(value_wei, bps);
+    if fee == 0 {
+        return Vec::new();
+    }
+    vec![(NATIVE.to_string(), U256::from(fee))]
+}
+
+/// Build the transaction paying `fee_amount` of `asset` from `from` to the
+/// fee collector.
 pub fn build_fee_tx(
+    from: &str,
     fee_collector: &str,
-    fee_amount: u128,
-    _chain_id: u64,
+    asset: &str,
+    fee_amount: U256,
+    chain_id: u64,
 ) -> Option<serde_json::Value> {
-    if fee_amount == 0 {
+    if fee_amount.is_zero() {
         return None;
     }
 
     info!(
+        from = from,
         fee_collector = fee_collector,
-        fee_amount = fee_amount,
+        asset = asset,
+        fee_amount = %fee_amount,
         "Building fee collection tx"
     );
 
-    // Stub: return the fee tx parameters
-    Some(serde_json::json!({
-        "to": fee_collector,
-        "value": format!("0x{:x}", fee_amount),
-        "gas": "0x5208",  // 21000
-        "type": "fee_collection"
-    }))
+    let mut tx = if asset == NATIVE {
+        serde_json::json!({
+            "from": from,
+            "to": fee_collector,
+            "value": format!("0x{:x}", fee_amount),
+            "gas": "0x5208",  // 21000
+        })
+    } else {
+        let collector = hex::decode(fee_collector.trim_start_matches("0x")).ok()?;
+        let mut data = TRANSFER_SELECTOR.to_vec();
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(&collector);
+        data.extend_from_slice(&fee_amount.to_be_bytes::<32>());
+        serde_json::json!({
+            "from": from,
+            "to": asset,
+            "value": "0x0",
+            "data": format!("0x{}", hex::encode(data)),
+        })
+    };
+    if chain_id != 0 {
+        tx["chainId"] = serde_json::json!(format!("0x{:x}", chain_id));
+    }
+    Some(tx)
+}
+
+/// Charge `agent` a fee of `fee_amount` in `asset` on `chain` (`None` =
+/// default chain).
+pub fn accrue(chain: Option<u64>, agent: &str, asset: &str, fee_amount: U256) {
+    if fee_amount.is_zero() {
+        return;
+    }
+    if let Ok(mut ledger) = LEDGER.lock() {
+        let key = (chains::scope_key(chain, &agent.to_lowercase()), asset.to_lowercase());
+        let owed = ledger.entry(key).or_insert(U256::ZERO);
+        *owed = owed.saturating_add(fee_amount);
+    }
+}
+
+/// Fees owed as `(chain, agent, asset, amount)`, by chain, agent, then
+/// asset. `None` is the default chain.
+pub fn owed() -> Vec<(Option<u64>, String, String, U256)> {
+    let mut owed: Vec<(Option<u64>, String, String, U256)> = LEDGER
+        .lock()
+        .map(|ledger| {
+            ledger
+                .iter()
+                .map(|((key, asset), amount)| {
+                    let (chain, agent) = chains::unscope_key(key);
+                    (chain, agent.to_string(), asset.clone(), *amount)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    owed.sort();
+    owed
+}
+
+/// Deduct a settled amount (fees may have accrued meanwhile).
+fn deduct(chain: Option<u64>, agent: &str, asset: &str, amount: U256) {
+    if let Ok(mut ledger) = LEDGER.lock() {
+        let key = (chains::scope_key(chain, agent), asset.to_string());
+        if let Some(owed) = ledger.get_mut(&key) {
+            *owed = owed.saturating_sub(amount);
+            if owed.is_zero() {
+                ledger.remove(&key);
+            }
+        }
+    }
+}
+
+/// The ledger for persistence, amounts as decimal strings. Agents stay
+/// chain-scoped, so default-chain rows keep their single-chain form.
+pub fn snapshot() -> Vec<(String, String, String)> {
+    owed()
+        .into_iter()
+        .map(|(chain, agent, asset, amount)| (chains::scope_key(chain, &agent), asset, amount.to_string()))
+        .collect()
+}
+
+/// Restore a persisted ledger (startup). Keeps the larger of the live and
+/// stored amount, so a restore never forgives a fee.
+pub fn restore(entries: Vec<(String, String, String)>) {
+    let Ok(mut ledger) = LEDGER.lock() else {
+        return;
+    };
+    for (agent, asset, amount) in entries {
+        let Ok(amount) = amount.parse::<U256>() else {
+            warn!(agent = %agent, asset = %asset, "Ignoring unreadable stored fee");
+            continue;
+        };
+        let owed = ledger.entry((agent.to_lowercase(), asset.to_lowercase())).or_insert(U256::ZERO);
+        *owed = (*owed).max(amount);
+    }
+}
+
+/// Settlements awaiting their outcome, for persistence.
+pub fn pending_snapshot() -> Vec<PendingSettlement> {
+    let mut pending: Vec<PendingSettlement> =
+        PENDING.lock().map(|pending| pending.values().cloned().collect()).unwrap_or_default();
+    pending.sort_by(|a, b| (&a.agent, &a.asset).cmp(&(&b.agent, &b.asset)));
+    pending
+}
+
+/// Restore persisted pending settlements (startup). One already tracked
+/// in this process is kept.
+pub fn restore_pending(entries: Vec<PendingSettlement>) {
+    if let Ok(mut pending) = PENDING.lock() {
+        for entry in entries {
+            pending.entry((entry.agent.clone(), entry.asset.clone())).or_insert(entry);
+        }
+    }
+}
+
+/// One settlement attempt: chain, agent, asset, amount, and the tx hash
+/// or why it failed.
+pub type Settlement = (Option<u64>, String, String, U256, Result<String, String>);
+
+/// Why an upstream call failed.
+#[derive(Debug, PartialEq)]
+enum Failure {
+    /// The node answered with an error.
+    Rejected(String),
+    /// No answer: the request may or may not have been applied.
+    Unknown(String),
+}
+
+impl Failure {
+    fn message(self) -> String {
+        match self {
+            Failure::Rejected(e) | Failure::Unknown(e) => e,
+        }
+    }
+}
+
+/// JSON-RPC `method` on `upstream`.
+async fn call(client: &reqwest::Client, upstream: &str, method: &str, params: Value) -> Result<Value, Failure> {
+    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let resp = client
+        .post(upstream)
+        .json(&body)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| Failure::Unknown(e.to_string()))?;
+    let v = resp.json::<Value>().await.map_err(|e| Failure::Unknown(e.to_string()))?;
+    match v.get("error") {
+        Some(error) if !error.is_null() => Err(Failure::Rejected(error.to_string())),
+        _ => Ok(v["result"].clone()),
+    }
+}
+
+/// `u64` from a `0x` quantity.
+fn quantity(v: &Value) -> Option<u64> {
+    u64::from_str_radix(v.as_str()?.strip_prefix("0x")?, 16).ok()
+}
+
+/// Have `agent`'s node sign the transaction paying `amount` of `asset`, at
+/// the agent's next nonce.
+async fn sign(
+    client: &reqwest::Client,
+    upstream: &str,
+    config: &Config,
+    agent: &str,
+    asset: &str,
+    amount: U256,
+    chain_id: u64,
+) -> Result<(u64, String, String), String> {
+    let mut tx = build_fee_tx(agent, &config.fee_collector, asset, amount, chain_id).ok_or("nothing to settle")?;
+    let nonce = call(client, upstream, "eth_getTransactionCount", json!([agent, "pending"])).await.map_err(Failure::message)?;
+    let nonce = quantity(&nonce).ok_or("unreadable nonce")?;
+    tx["nonce"] = json!(format!("0x{:x}", nonce));
+    if tx.get("gas").is_none() {
+        tx["gas"] = call(client, upstream, "eth_estimateGas", json!([tx])).await.map_err(Failure::message)?;
+    }
+    tx["gasPrice"] = call(client, upstream, "eth_gasPrice", json!([])).await.map_err(Failure::message)?;
+    let signed = call(client, upstream, "eth_signTransaction", json!([tx])).await.map_err(Failure::message)?;
+    // geth answers `{raw, tx}`, others the raw transaction itself.
+    let raw = signed.as_str().or_else(|| signed["raw"].as_str()).ok_or("no signed transaction in response")?;
+    let bytes = hex::decode(raw.trim_start_matches("0x")).map_err(|e| e.to_string())?;
+    Ok((nonce, format!("{}", keccak256(&bytes)), raw.to_string()))
+}
+
+/// Whether a rejected broadcast means the node already has the
+/// transaction.
+fn already_known(error: &str) -> bool {
+    let error = error.to_lowercase();
+    error.contains("already known") || error.contains("known transaction") || error.contains("already imported")
+}
+
+/// Broadcast `pending`: its hash once the node has it. A rejection for a
+/// used nonce is accepted when the transaction itself was mined.
+async fn broadcast(client: &reqwest::Client, upstream: &str, pending: &PendingSettlement) -> Result<String, Failure> {
+    match call(client, upstream, "eth_sendRawTransaction", json!([pending.raw_tx])).await {
+        Ok(_) => Ok(pending.tx_hash.clone()),
+        Err(Failure::Rejected(e)) if already_known(&e) => Ok(pending.tx_hash.clone()),
+        Err(Failure::Rejected(e)) => {
+            let receipt = call(client, upstream, "eth_getTransactionReceipt", json!([pending.tx_hash])).await;
+            match receipt {
+                Ok(receipt) if receipt["status"] == "0x1" => Ok(pending.tx_hash.clone()),
+                Ok(_) => Err(Failure::Rejected(e)),
+                Err(lookup) => Err(Failure::Unknown(lookup.message())),
+            }
+        }
+        Err(unknown) => Err(unknown),
+    }
+}
+
+/// Persist the ledger and pending settlements right away.
+fn persist(store: Option<&SharedStateStore>) {
+    if let Some(store) = store {
+        state_store::flush(store);
+    }
+}
+
+/// Upstream and transaction chain id fees owed on `chain` settle through.
+/// `None` when the chain is no longer served.
+fn settlement_route(config: &Config, chain: Option<u64>) -> Option<(&str, u64)> {
+    match chain {
+        None => Some((config.upstream_rpc_url.as_str(), config.expected_chain_id)),
+        Some(id) => config.chains.iter().find(|c| c.chain_id == id).map(|c| (c.upstream_rpc_url.as_str(), id)),
+    }
+}
+
+/// Whether `amount` of `asset` is worth a settlement transaction: native
+/// fees from `fee_settlement_min_wei`, priced ones from
+/// `fee_settlement_min_usd`.
+async fn worth_settling(config: &Config, asset: &str, amount: U256) -> bool {
+    let min_wei: U256 = config.fee_settlement_min_wei.parse().unwrap_or(U256::MAX);
+    if asset == NATIVE && amount < min_wei {
+        return false;
+    }
+    if config.fee_settlement_min_usd > 0.0 {
+        if let Some(price) = oracle::price(config, asset).await {
+            return intents::usd_value(amount, &price) >= config.fee_settlement_min_usd;
+        }
+    }
+    true
+}
+
+/// Send each fee owed that is worth it (see [`worth_settling`]) its
+/// settlement transaction, through the upstream of the chain it was
+/// charged on, persisting to `store` as it goes. A settlement whose
+/// outcome is still unknown is re-broadcast first.
+pub async fn settle(config: &Config, store: Option<&SharedStateStore>) -> Vec<Settlement> {
+    let client = reqwest::Client::new();
+    let mut settled = Vec::new();
+    for (chain, agent, asset, owed) in owed() {
+        let Some((upstream, chain_id)) = settlement_route(config, chain) else {
+            warn!(chain, agent = %agent, asset = %asset, fee = %owed, "Protocol fee owed on a chain no longer served — still owed");
+            continue;
+        };
+        // Priced with the settling chain's config, not the default's.
+        let chain_config = chains::config_for(&Arc::new(config.clone()), chain_id);
+        let key = (chains::scope_key(chain, &agent), asset.clone());
+        let tracked = PENDING.lock().ok().and_then(|pending| pending.get(&key).cloned());
+        let pending = match tracked {
+            Some(pending) => pending,
+            None => {
+                if !worth_settling(&chain_config, &asset, owed).await {
+                    continue;
+                }
+                match sign(&client, upstream, config, &agent, &asset, owed, chain_id).await {
+                    Ok((nonce, tx_hash, raw_tx)) => {
+                        let pending = PendingSettlement {
+                            agent: key.0.clone(),
+                            asset: asset.clone(),
+                            amount: owed.to_string(),
+                            nonce,
+                            tx_hash,
+                            raw_tx,
+                        };
+                        if let Ok(mut tracked) = PENDING.lock() {
+                            tracked.insert(key.clone(), pending.clone());
+                        }
+                        persist(store);
+                        pending
+                    }
+                    Err(e) => {
+                        warn!(chain, agent = %agent, asset = %asset, fee = %owed, error = %e, "Protocol fee settlement failed — still owed");
+                        settled.push((chain, agent, asset, owed, Err(e)));
+                        continue;
+                    }
+                }
+            }
+        };
+        let amount: U256 = pending.amount.parse().unwrap_or(U256::ZERO);
+        let result = match broadcast(&client, upstream, &pending).await {
+            Ok(hash) => {
+                if let Ok(mut tracked) = PENDING.lock() {
+                    tracked.remove(&key);
+                }
+                deduct(chain, &agent, &asset, amount);
+                persist(store);
+                fee_ledger::record(&chain_config, fee_ledger::SETTLED, chain_id, &agent, &asset, amount, &hash).await;
+                info!(chain, agent = %agent, asset = %asset, fee = %amount, nonce = pending.nonce, tx_hash = %hash, "Protocol fee settled");
+                Ok(hash)
+            }
+            Err(Failure::Rejected(e)) => {
+                if let Ok(mut tracked) = PENDING.lock() {
+                    tracked.remove(&key);
+                }
+                persist(store);
+                warn!(chain, agent = %agent, asset = %asset, fee = %amount, nonce = pending.nonce, error = %e, "Protocol fee settlement rejected — still owed");
+                Err(e)
+            }
+            Err(Failure::Unknown(e)) => {
+                warn!(chain, agent = %agent, asset = %asset, fee = %amount, nonce = pending.nonce, error = %e, "Protocol fee settlement outcome unknown — will re-broadcast the same transaction");
+                Err(e)
+            }
+        };
+        settled.push((chain, agent, asset, amount, result));
+    }
+    settled
+}
+
+/// Settle owed fees every `fee_settlement_interval_secs` (0 = never),
+/// persisting to `store`.
+pub fn spawn_settlement_task(config: SharedConfigHandle, store: Option<SharedStateStore>) {
+    tokio::spawn(async move {
+        loop {
+            let interval = config.current().fee_settlement_interval_secs;
+            tokio::time::sleep(Duration::from_secs(if interval == 0 { 60 } else { interval })).await;
+            let cfg = config.current();
+            if cfg.fee_settlement_interval_secs > 0 {
+                settle(&cfg, store.as_ref()).await;
+            }
+        }
+    });
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::FeeTier;
+
+    const AGENT: &str = "0xa9e0000000000000000000000000000000000abc";
 
     #[test]
     fn test_fee_calculation() {
@@ -69,10 +555,134 @@ mod tests {
 
     #[test]
     fn test_build_fee_tx() {
-        let tx = build_fee_tx("0xFEE", 1000, 1);
+        let tx = build_fee_tx("0xAGENT", "0xFEE", NATIVE, U256::from(1000), 1);
         assert!(tx.is_some());
+        let tx = tx.unwrap();
+        assert_eq!(tx["from"], "0xAGENT");
+        assert_eq!(tx["value"], "0x3e8");
+        assert_eq!(tx["chainId"], "0x1");
 
-        let tx = build_fee_tx("0xFEE", 0, 1);
+        let tx = build_fee_tx("0xAGENT", "0xFEE", NATIVE, U256::ZERO, 1);
         assert!(tx.is_none());
+
+        // ERC-20: transfer(collector, amount) on the token.
+        let usdc = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+        let collector = "0x00000000000000000000000000000000000fee00";
+        let tx = build_fee_tx("0xAGENT", collector, usdc, U256::from(500), 8453).unwrap();
+        assert_eq!(tx["to"], usdc);
+        assert_eq!(
+            tx["data"],
+            format!("0xa9059cbb{:0>64}{:064x}", collector.trim_start_matches("0x"), 500)
+        );
+    }
+
+    #[test]
+    fn test_traded_denomination_charges_in_kind() {
+        let usdc = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913".to_string();
+        let outflows = vec![
+            ("native".to_string(), U256::from(10_000u64)),
+            (usdc.clone(), U256::from(1_000_000_000u64)), // 1000 USDC
+        ];
+        let mut config = Config::default();
+        assert!(charges(&config, AGENT, 0, &outflows, None).is_empty());
+        assert_eq!(charges(&config, AGENT, 1_000_000, &outflows, None), vec![(NATIVE.to_string(), U256::from(200))]);
+
+        config.fee_denomination = "traded".into();
+        assert_eq!(charges(&config, AGENT, 1_000_000, &outflows, None), vec![(usdc.clone(), U256::from(200_000))]);
+        // Not an accepted fee token: back to wei.
+        config.fee_tokens = "0x0000000000000000000000000000000000000001".into();
+        assert_eq!(charges(&config, AGENT, 1_000_000, &outflows, None), vec![(NATIVE.to_string(), U256::from(200))]);
+    }
+
+    #[test]
+    fn test_fee_schedule() {
+        let mut config = Config {
+            fee_tiers: vec![
+                FeeTier { min_usd: 10_000.0, bps: 1 },
+                FeeTier { min_usd: 0.0, bps: 2 },
+                FeeTier { min_usd: 1_000_000.0, bps: 0 },
+            ],
+            ..Config::default()
+        };
+        assert_eq!(fee_bps(&config, AGENT, Some(500.0)), 2);
+        assert_eq!(fee_bps(&config, AGENT, Some(10_000.0)), 1);
+        assert_eq!(fee_bps(&config, AGENT, Some(5_000_000.0)), 0);
+        // Unpriced: the flat rate.
+        config.fee_bps = 3;
+        assert_eq!(fee_bps(&config, AGENT, None), 3);
+
+        config.fee_exempt_addresses = "0x0000000000000000000000000000000000000001, 0xA9E0000000000000000000000000000000000ABC".into();
+        assert_eq!(fee_bps(&config, AGENT, Some(500.0)), 0);
+        assert!(charges(&config, AGENT, 1_000_000, &[], Some(500.0)).is_empty());
+    }
+
+    #[test]
+    fn test_ledger_accrues_and_restores() {
+        let agent = "0xFEE0000000000000000000000000000000000001";
+        let key = agent.to_lowercase();
+        accrue(None, agent, NATIVE, U256::from(200));
+        accrue(None, agent, NATIVE, U256::from(300));
+        accrue(None, agent, "0xToken", U256::from(7));
+        accrue(Some(8453), agent, NATIVE, U256::from(40));
+        let owed_on = |chain: Option<u64>, asset: &str| {
+            owed().into_iter().find(|(c, a, t, _)| *c == chain && *a == key && t == asset).map(|(_, _, _, w)| w)
+        };
+        let owed_by = |asset: &str| owed_on(None, asset);
+        assert_eq!(owed_by(NATIVE), Some(U256::from(500)));
+        assert_eq!(owed_by("0xtoken"), Some(U256::from(7)));
+        // Each chain owes separately.
+        assert_eq!(owed_on(Some(8453), NATIVE), Some(U256::from(40)));
+        assert!(snapshot().contains(&(format!("8453:{key}"), NATIVE.into(), "40".into())));
+
+        // A restore never lowers what is owed.
+        restore(vec![(key.clone(), NATIVE.into(), "100".into())]);
+        assert_eq!(owed_by(NATIVE), Some(U256::from(500)));
+        restore(vec![(key.clone(), NATIVE.into(), "900".into())]);
+        assert_eq!(owed_by(NATIVE), Some(U256::from(900)));
+
+        deduct(None, &key, NATIVE, U256::from(900));
+        assert_eq!(owed_by(NATIVE), None);
+        assert_eq!(owed_on(Some(8453), NATIVE), Some(U256::from(40)));
+    }
+
+    #[test]
+    fn test_pending_settlements_restore() {
+        let pending = PendingSettlement {
+            agent: "8453:0xfee0000000000000000000000000000000000002".into(),
+            asset: NATIVE.into(),
+            amount: "500".into(),
+            nonce: 7,
+            tx_hash: "0xabc".into(),
+            raw_tx: "0x02f8".into(),
+        };
+        restore_pending(vec![pending.clone()]);
+        // The tracked settlement wins over a stored one.
+        restore_pending(vec![PendingSettlement { nonce: 8, ..pending.clone() }]);
+        assert!(pending_snapshot().contains(&pending));
+        PENDING.lock().unwrap().remove(&(pending.agent.clone(), pending.asset.clone()));
+    }
+
+    #[test]
+    fn test_rebroadcast_of_known_tx_settles() {
+        assert!(already_known(r#"{"code":-32000,"message":"already known"}"#));
+        assert!(already_known("Known transaction: 0xabc"));
+        assert!(!already_known(r#"{"code":-32000,"message":"nonce too low"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_settlement_minimums() {
+        let usdc = "0x833589fcd6edb6e08f4c7c32d4f71b54bda02913";
+        let mut config = Config::default();
+        config.reference_prices.insert(usdc.into(), crate::config::ReferencePrice { usd: 1.0, decimals: 6 });
+        // No USD minimum: any token amount, native from the wei minimum.
+        assert!(worth_settling(&config, usdc, U256::from(1)).await);
+        assert!(!worth_settling(&config, NATIVE, U256::from(1)).await);
+        assert!(worth_settling(&config, NATIVE, U256::from(1_000_000_000_000_000u64)).await);
+
+        config.fee_settlement_min_usd = 5.0;
+        assert!(!worth_settling(&config, usdc, U256::from(4_990_000)).await); // $4.99
+        assert!(worth_settling(&config, usdc, U256::from(5_000_000)).await);
+        // Unpriced tokens aren't held back.
+        assert!(worth_settling(&config, "0x0000000000000000000000000000000000000001", U256::from(1)).await);
     }
 }
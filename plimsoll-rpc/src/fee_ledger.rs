@@ -0,0 +1,357 @@
+//! Fee accounting ledger.
+//!
+//! With `fee_ledger_path` set, every protocol fee the proxy charges and
+//! every settlement it sends is kept in a SQLite database — chain, agent,
+//! asset, amount, USD value at the time and tx hash — so operators
+//! reconcile protocol revenue from records instead of logs:
+//!
+//! | Where                                        | Output                                 |
+//! |----------------------------------------------|----------------------------------------|
+//! | `plimsoll_getFeeReport(period)` (admin)      | Totals per chain and asset, per agent  |
+//! | `GET /admin/fees/report?period=`             | Same report                            |
+//! | `GET /admin/fees/export.csv?since=&until=`   | Every entry, CSV                       |
+//!
+//! The report covers every agent, so the RPC method takes the admin token
+//! like `plimsoll_rotateSessionKey`. `period` is `day`, `week`, `month`,
+//! `all` or a number of seconds back from now. USD values use the oracle
+//! prices of the entry's chain (`oracle::price`); an unpriced entry has
+//! none and is left out of the USD totals. Entries recorded before the
+//! chain was kept have chain id 0.
+
+use crate::config::Config;
+use crate::intents;
+use crate::oracle;
+use alloy_primitives::U256;
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Entry kind of a fee accrued on a forwarded transaction.
+pub const CHARGED: &str = "charged";
+
+/// Entry kind of a settlement transaction.
+pub const SETTLED: &str = "settled";
+
+/// CSV header of `csv`.
+const CSV_HEADER: &str = "recorded_at,kind,chain_id,agent,asset,amount,usd,tx_hash";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS fee_entries (
+        seq         INTEGER PRIMARY KEY AUTOINCREMENT,
+        recorded_at INTEGER NOT NULL,
+        kind        TEXT NOT NULL,
+        chain_id    INTEGER NOT NULL DEFAULT 0,
+        agent       TEXT NOT NULL,
+        asset       TEXT NOT NULL,
+        amount      TEXT NOT NULL,
+        usd         REAL,
+        tx_hash     TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS fee_entries_recorded_at ON fee_entries (recorded_at);
+";
+
+static LEDGER: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeeEntry {
+    pub recorded_at: u64,
+    /// `charged` or `settled`.
+    pub kind: String,
+    /// Chain the fee was charged or settled on.
+    pub chain_id: u64,
+    pub agent: String,
+    /// `native` or an ERC-20.
+    pub asset: String,
+    /// Decimal, in the asset's smallest unit.
+    pub amount: String,
+    pub usd: Option<f64>,
+    pub tx_hash: String,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn init(conn: Connection) -> Result<Connection> {
+    conn.execute_batch(SCHEMA).context("Failed to initialize the fee ledger")?;
+    // Ledgers created before entries kept their chain.
+    let has_chain = conn
+        .prepare("SELECT 1 FROM pragma_table_info('fee_entries') WHERE name = 'chain_id'")?
+        .exists([])?;
+    if !has_chain {
+        conn.execute_batch("ALTER TABLE fee_entries ADD COLUMN chain_id INTEGER NOT NULL DEFAULT 0")
+            .context("Failed to add the chain to the fee ledger")?;
+    }
+    Ok(conn)
+}
+
+/// Whether the ledger is open.
+pub fn is_open() -> bool {
+    LEDGER.get().is_some()
+}
+
+/// Open the ledger at `fee_ledger_path` (no-op when unset).
+pub fn open(config: &Config) -> Result<()> {
+    if config.fee_ledger_path.is_empty() {
+        return Ok(());
+    }
+    let conn = Connection::open(&config.fee_ledger_path)
+        .with_context(|| format!("Failed to open fee ledger {}", config.fee_ledger_path))?;
+    if LEDGER.set(Mutex::new(init(conn)?)).is_err() {
+        anyhow::bail!("Fee ledger already open");
+    }
+    info!("Fee ledger at {}", config.fee_ledger_path);
+    Ok(())
+}
+
+fn insert(conn: &Connection, entry: &FeeEntry) -> Result<()> {
+    conn.execute(
+        "INSERT INTO fee_entries (recorded_at, kind, chain_id, agent, asset, amount, usd, tx_hash) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        rusqlite::params![
+            entry.recorded_at as i64,
+            entry.kind,
+            entry.chain_id as i64,
+            entry.agent,
+            entry.asset,
+            entry.amount,
+            entry.usd,
+            entry.tx_hash
+        ],
+    )?;
+    Ok(())
+}
+
+/// Record a fee `kind` of `amount` `asset` for `agent` on `chain_id`,
+/// priced now with `config` — the config serving that chain. A no-op when
+/// the ledger is off.
+pub async fn record(config: &Config, kind: &str, chain_id: u64, agent: &str, asset: &str, amount: U256, tx_hash: &str) {
+    let Some(ledger) = LEDGER.get() else {
+        return;
+    };
+    let usd = oracle::price(config, asset)
+        .await
+        .map(|price| intents::usd_value(amount, &price));
+    let entry = FeeEntry {
+        recorded_at: now(),
+        kind: kind.to_string(),
+        chain_id,
+        agent: agent.to_lowercase(),
+        asset: asset.to_lowercase(),
+        amount: amount.to_string(),
+        usd,
+        tx_hash: tx_hash.to_string(),
+    };
+    let inserted = match ledger.lock() {
+        Ok(conn) => insert(&conn, &entry),
+        Err(_) => Err(anyhow::anyhow!("Fee ledger lock poisoned")),
+    };
+    if let Err(e) = inserted {
+        warn!(error = %e, "Failed to record fee");
+    }
+}
+
+/// Entries recorded in `[since, until)`, oldest first.
+fn query(conn: &Connection, since: u64, until: u64) -> Result<Vec<FeeEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT recorded_at, kind, chain_id, agent, asset, amount, usd, tx_hash FROM fee_entries \
+         WHERE recorded_at >= ?1 AND recorded_at < ?2 ORDER BY seq",
+    )?;
+    let rows = stmt.query_map(
+        rusqlite::params![since as i64, i64::try_from(until).unwrap_or(i64::MAX)],
+        |row| {
+            Ok(FeeEntry {
+                recorded_at: row.get::<_, i64>(0)?.max(0) as u64,
+                kind: row.get(1)?,
+                chain_id: row.get::<_, i64>(2)?.max(0) as u64,
+                agent: row.get(3)?,
+                asset: row.get(4)?,
+                amount: row.get(5)?,
+                usd: row.get(6)?,
+                tx_hash: row.get(7)?,
+            })
+        },
+    )?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Stored entries, as in `query`.
+pub fn load(since: u64, until: u64) -> Result<Vec<FeeEntry>> {
+    let ledger = LEDGER.get().context("Fee ledger is not configured (fee_ledger_path)")?;
+    let conn = ledger.lock().map_err(|_| anyhow::anyhow!("Fee ledger lock poisoned"))?;
+    query(&conn, since, until)
+}
+
+/// Start of `period` ending at `now`; `None` if `period` is not one.
+pub fn period_start(period: &str, now: u64) -> Option<u64> {
+    let secs = match period {
+        "day" => 86_400,
+        "week" => 7 * 86_400,
+        "month" => 30 * 86_400,
+        "all" => return Some(0),
+        secs => secs.parse().ok()?,
+    };
+    Some(now.saturating_sub(secs))
+}
+
+/// The report for `period`: entries from the ledger, totalled.
+pub fn report_for(period: &str) -> Result<Value> {
+    let until = now();
+    let since = period_start(period, until)
+        .with_context(|| format!("invalid period '{period}' (day, week, month, all or seconds)"))?;
+    Ok(report(&load(since, until.saturating_add(1))?, since, until))
+}
+
+/// Totals of `entries` per kind, chain and asset, and per agent. Amounts
+/// of the same asset on different chains are never added up.
+pub fn report(entries: &[FeeEntry], since: u64, until: u64) -> Value {
+    let mut by_asset: BTreeMap<(&str, u64, &str), (U256, f64, usize)> = BTreeMap::new();
+    let mut by_agent: BTreeMap<&str, (f64, f64)> = BTreeMap::new();
+    let mut total_usd = (0.0, 0.0);
+    for entry in entries {
+        let amount: U256 = entry.amount.parse().unwrap_or_default();
+        let usd = entry.usd.unwrap_or_default();
+        let totals = by_asset.entry((entry.kind.as_str(), entry.chain_id, entry.asset.as_str())).or_default();
+        totals.0 = totals.0.saturating_add(amount);
+        totals.1 += usd;
+        totals.2 += 1;
+        let agent = by_agent.entry(entry.agent.as_str()).or_default();
+        if entry.kind == CHARGED {
+            agent.0 += usd;
+            total_usd.0 += usd;
+        } else {
+            agent.1 += usd;
+            total_usd.1 += usd;
+        }
+    }
+    let assets: Vec<Value> = by_asset
+        .into_iter()
+        .map(|((kind, chain_id, asset), (amount, usd, count))| {
+            json!({
+                "kind": kind,
+                "chain_id": chain_id,
+                "asset": asset,
+                "amount": amount.to_string(),
+                "usd": usd,
+                "count": count,
+            })
+        })
+        .collect();
+    let agents: Vec<Value> = by_agent
+        .into_iter()
+        .map(|(agent, (charged, settled))| json!({ "agent": agent, "charged_usd": charged, "settled_usd": settled }))
+        .collect();
+    json!({
+        "since": since,
+        "until": until,
+        "entries": entries.len(),
+        "charged_usd": total_usd.0,
+        "settled_usd": total_usd.1,
+        "assets": assets,
+        "agents": agents,
+    })
+}
+
+/// `entries` as CSV, with a header row.
+pub fn csv(entries: &[FeeEntry]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for e in entries {
+        let usd = e.usd.map(|usd| usd.to_string()).unwrap_or_default();
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            e.recorded_at, e.kind, e.chain_id, e.agent, e.asset, e.amount, usd, e.tx_hash
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(at: u64, kind: &str, agent: &str, asset: &str, amount: u64, usd: Option<f64>) -> FeeEntry {
+        FeeEntry {
+            recorded_at: at,
+            kind: kind.into(),
+            chain_id: 1,
+            agent: agent.into(),
+            asset: asset.into(),
+            amount: amount.to_string(),
+            usd,
+            tx_hash: format!("0x{at:064x}"),
+        }
+    }
+
+    #[test]
+    fn test_ledger_query_and_report() {
+        let conn = init(Connection::open_in_memory().unwrap()).unwrap();
+        for e in [
+            entry(1_000, CHARGED, "0xa", "native", 200, Some(0.6)),
+            entry(2_000, CHARGED, "0xa", "native", 300, Some(0.9)),
+            entry(2_500, CHARGED, "0xb", "0xusdc", 50, None),
+            entry(3_000, SETTLED, "0xa", "native", 500, Some(1.5)),
+            FeeEntry { chain_id: 8453, ..entry(3_500, CHARGED, "0xa", "native", 7, None) },
+        ] {
+            insert(&conn, &e).unwrap();
+        }
+        let all = query(&conn, 0, u64::MAX).unwrap();
+        assert_eq!(all.len(), 5);
+        assert_eq!(all[2].usd, None);
+        assert_eq!(all[4].chain_id, 8453);
+        assert_eq!(query(&conn, 2_000, 3_000).unwrap().len(), 2);
+
+        let report = report(&all, 0, 3_500);
+        assert_eq!(report["entries"], 5);
+        assert!((report["charged_usd"].as_f64().unwrap() - 1.5).abs() < 1e-9);
+        assert!((report["settled_usd"].as_f64().unwrap() - 1.5).abs() < 1e-9);
+        let native = report["assets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|a| a["kind"] == CHARGED && a["chain_id"] == 1 && a["asset"] == "native")
+            .unwrap();
+        // Base's native fee is totalled on its own.
+        assert_eq!(native["amount"], "500");
+        assert_eq!(native["count"], 2);
+        assert_eq!(report["assets"].as_array().unwrap().len(), 4);
+        assert_eq!(report["agents"].as_array().unwrap().len(), 2);
+
+        let csv = csv(&all[..1]);
+        assert_eq!(
+            csv,
+            format!("{CSV_HEADER}\n1000,charged,1,0xa,native,200,0.6,0x{:064x}\n", 1_000)
+        );
+    }
+
+    #[test]
+    fn test_ledger_without_chains_is_migrated() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE fee_entries (seq INTEGER PRIMARY KEY AUTOINCREMENT, recorded_at INTEGER NOT NULL, \
+             kind TEXT NOT NULL, agent TEXT NOT NULL, asset TEXT NOT NULL, amount TEXT NOT NULL, usd REAL, \
+             tx_hash TEXT NOT NULL);
+             INSERT INTO fee_entries (recorded_at, kind, agent, asset, amount, usd, tx_hash) \
+             VALUES (1, 'charged', '0xa', 'native', '5', NULL, '0x1');",
+        )
+        .unwrap();
+        let conn = init(conn).unwrap();
+        let conn = init(conn).unwrap();
+        insert(&conn, &entry(2, CHARGED, "0xa", "native", 5, None)).unwrap();
+        let chains: Vec<u64> = query(&conn, 0, u64::MAX).unwrap().iter().map(|e| e.chain_id).collect();
+        assert_eq!(chains, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_period_start() {
+        assert_eq!(period_start("day", 100_000), Some(13_600));
+        assert_eq!(period_start("all", 100_000), Some(0));
+        assert_eq!(period_start("3600", 100_000), Some(96_400));
+        assert_eq!(period_start("fortnight", 100_000), None);
+    }
+}
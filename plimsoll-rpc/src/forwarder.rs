@@ -0,0 +1,109 @@
+//! ERC-2771 meta-transaction decoding.
+//!
+//! A signed `ForwardRequest` (OpenZeppelin MinimalForwarder /
+//! ERC2771Forwarder) or Biconomy `MetaTransaction` is a transaction in
+//! disguise: a relayer submits it and the target sees the signer as
+//! `msg.sender`. GOD-TIER 1 used to block the wrapper by name; instead the
+//! wrapped call is decoded and vetted like a direct send.
+//!
+//!   - `ForwardRequest(address from,address to,uint256 value,uint256 gas,
+//!     uint256 nonce,[uint48 deadline,]bytes data)`
+//!   - `MetaTransaction(uint256 nonce,address from,bytes functionSignature)`
+//!     — the target is the domain's `verifyingContract`.
+
+use crate::eip712::parse_integer;
+use crate::types::InnerCall;
+
+fn str_field(v: &serde_json::Value, name: &str) -> Result<String, String> {
+    v.get(name)
+        .and_then(|x| x.as_str())
+        .map(str::to_lowercase)
+        .ok_or_else(|| format!("meta-transaction missing `{name}`"))
+}
+
+fn bytes_field(v: &serde_json::Value, name: &str) -> Result<Vec<u8>, String> {
+    let s = str_field(v, name)?;
+    hex::decode(s.trim_start_matches("0x")).map_err(|e| format!("invalid `{name}` bytes: {e}"))
+}
+
+/// Decode the call wrapped by a meta-transaction. `Ok(None)` if the
+/// payload is not one.
+pub fn decode(typed_data: &serde_json::Value) -> Result<Option<InnerCall>, String> {
+    let primary = typed_data.get("primaryType").and_then(|v| v.as_str()).unwrap_or("");
+    let Some(msg) = typed_data.get("message") else {
+        return Ok(None);
+    };
+
+    match primary {
+        "ForwardRequest" => {
+            let value = msg.get("value").map(parse_integer).transpose()?.unwrap_or_default();
+            Ok(Some(InnerCall {
+                from: str_field(msg, "from")?,
+                to: str_field(msg, "to")?,
+                value: u128::try_from(value).map_err(|_| "`value` exceeds u128")?,
+                data: bytes_field(msg, "data")?,
+            }))
+        }
+        "MetaTransaction" => {
+            let to = typed_data
+                .get("domain")
+                .map(|d| str_field(d, "verifyingContract"))
+                .transpose()?
+                .ok_or("MetaTransaction without a domain")?;
+            Ok(Some(InnerCall {
+                from: str_field(msg, "from")?,
+                to,
+                value: 0,
+                data: bytes_field(msg, "functionSignature")?,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_forward_request() {
+        let data = serde_json::json!({
+            "primaryType": "ForwardRequest",
+            "message": {
+                "from": "0x1111111111111111111111111111111111111111",
+                "to": "0xA0b86991c6218b36c1d19d4a2e9eb0ce3606eB48",
+                "value": "0", "gas": "100000", "nonce": "3",
+                "data": "0x095ea7b3"
+            }
+        });
+        let call = decode(&data).unwrap().unwrap();
+        assert_eq!(call.to, "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        assert_eq!(call.data, vec![0x09, 0x5e, 0xa7, 0xb3]);
+    }
+
+    #[test]
+    fn test_decode_biconomy_meta_transaction() {
+        let data = serde_json::json!({
+            "primaryType": "MetaTransaction",
+            "domain": { "verifyingContract": "0x2222222222222222222222222222222222222222" },
+            "message": {
+                "nonce": 1,
+                "from": "0x1111111111111111111111111111111111111111",
+                "functionSignature": "0xa9059cbb"
+            }
+        });
+        let call = decode(&data).unwrap().unwrap();
+        assert_eq!(call.to, "0x2222222222222222222222222222222222222222");
+        assert_eq!(call.value, 0);
+    }
+
+    #[test]
+    fn test_malformed_and_unrelated() {
+        assert_eq!(decode(&serde_json::json!({ "primaryType": "Mail", "message": {} })).unwrap(), None);
+        let bad = serde_json::json!({
+            "primaryType": "ForwardRequest",
+            "message": { "from": "0x1111111111111111111111111111111111111111", "to": "0x2222222222222222222222222222222222222222", "data": "0xzz" }
+        });
+        assert!(decode(&bad).is_err());
+    }
+}
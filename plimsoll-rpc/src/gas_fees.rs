@@ -0,0 +1,280 @@
+//! Gas price and priority fee sanity ceilings.
+//!
+//! Balance physics measures the value a transaction moves, not what it
+//! pays for gas: an agent talked into a 1000 gwei tip loses its fee budget
+//! to the block builder while every simulation reads "no loss".
+//!
+//! The fee fields of an `eth_sendTransaction` are compared to the chain's
+//! recent market from `eth_feeHistory` over `fee_history_blocks`:
+//!
+//!   - priority fee ≤ max(median tip × `max_priority_fee_multiplier`,
+//!     `min_priority_fee_cap_gwei`),
+//!   - max fee (or legacy `gasPrice`) ≤ next base fee ×
+//!     `max_base_fee_multiplier` + the priority cap.
+//!
+//! A legacy `gasPrice` pays everything above the base fee as tip, so that
+//! excess is held to the priority cap too. `max_fee_per_gas_gwei` is an
+//! absolute ceiling that also applies when the fee history is unavailable.
+//!
+//! `plimsoll_suggestFees` serves fees from the same market read, so agent
+//! frameworks need not trust an external fee API: the base fee forecast
+//! covers `BASE_FEE_HEADROOM_BLOCKS` of maximal EIP-1559 increases, the tip
+//! is the recent median, and both are clamped to the ceilings above so a
+//! suggestion always passes them.
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+
+const GWEI: f64 = 1e9;
+
+/// Full blocks of base fee growth (12.5% each) a suggested max fee absorbs.
+const BASE_FEE_HEADROOM_BLOCKS: i32 = 6;
+
+/// Fee fields of a transaction request, in wei.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TxFees {
+    /// `maxFeePerGas`, or `gasPrice` for a legacy transaction.
+    pub max_fee: Option<u128>,
+    /// `maxPriorityFeePerGas`; `None` for a legacy transaction.
+    pub priority_fee: Option<u128>,
+}
+
+/// The recent fee market, in wei.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeReference {
+    /// Base fee of the next block.
+    pub base_fee: u128,
+    /// Median priority fee paid over the sampled blocks.
+    pub priority_fee: u128,
+}
+
+fn quantity(value: &serde_json::Value) -> Option<u128> {
+    match value {
+        serde_json::Value::String(s) => u128::from_str_radix(s.trim_start_matches("0x"), 16).ok(),
+        serde_json::Value::Number(n) => n.as_u64().map(u128::from),
+        _ => None,
+    }
+}
+
+/// Fee fields of `tx` (an `eth_sendTransaction` object).
+pub fn parse(tx: &serde_json::Value) -> TxFees {
+    let priority_fee = tx.get("maxPriorityFeePerGas").and_then(quantity);
+    let max_fee = tx
+        .get("maxFeePerGas")
+        .and_then(quantity)
+        .or_else(|| tx.get("gasPrice").and_then(quantity));
+    TxFees { max_fee, priority_fee }
+}
+
+/// Next base fee and median tip from an `eth_feeHistory` result queried
+/// with reward percentile 50.
+fn parse_fee_history(history: &serde_json::Value) -> Result<FeeReference> {
+    let base_fee = history["baseFeePerGas"]
+        .as_array()
+        .and_then(|fees| fees.last())
+        .and_then(quantity)
+        .context("eth_feeHistory returned no baseFeePerGas")?;
+    let mut tips: Vec<u128> = history["reward"]
+        .as_array()
+        .map(|rows| rows.iter().filter_map(|row| row.get(0).and_then(quantity)).collect())
+        .unwrap_or_default();
+    tips.sort_unstable();
+    let priority_fee = tips.get(tips.len() / 2).copied().unwrap_or(0);
+    Ok(FeeReference { base_fee, priority_fee })
+}
+
+/// The fee market over the last `blocks` blocks.
+pub async fn reference(rpc_url: &str, blocks: u64) -> Result<FeeReference> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_feeHistory",
+        "params": [format!("0x{blocks:x}"), "latest", [50]],
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to call eth_feeHistory")?
+        .json()
+        .await
+        .context("Failed to parse eth_feeHistory response")?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("eth_feeHistory failed: {err}");
+    }
+    parse_fee_history(&body["result"])
+}
+
+/// An EIP-1559 fee recommendation, in wei.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeSuggestion {
+    pub base_fee: u128,
+    pub base_fee_forecast: u128,
+    pub max_priority_fee: u128,
+    pub max_fee: u128,
+    pub priority_fee_ceiling: u128,
+    pub max_fee_ceiling: u128,
+}
+
+impl FeeSuggestion {
+    /// JSON-RPC shape: hex quantities, like `eth_feeHistory`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "baseFeePerGas": format!("0x{:x}", self.base_fee),
+            "baseFeeForecast": format!("0x{:x}", self.base_fee_forecast),
+            "maxPriorityFeePerGas": format!("0x{:x}", self.max_priority_fee),
+            "maxFeePerGas": format!("0x{:x}", self.max_fee),
+            "ceilings": {
+                "maxPriorityFeePerGas": format!("0x{:x}", self.priority_fee_ceiling),
+                "maxFeePerGas": format!("0x{:x}", self.max_fee_ceiling),
+            },
+        })
+    }
+}
+
+/// Fees for the market `reference` that stay within the ceilings.
+pub fn suggest(config: &Config, reference: &FeeReference) -> FeeSuggestion {
+    let (priority_cap, mut max_fee_cap) = ceilings(config, reference);
+    if config.max_fee_per_gas_gwei > 0.0 {
+        max_fee_cap = max_fee_cap.min((config.max_fee_per_gas_gwei * GWEI) as u128);
+    }
+    let base_fee_forecast = (reference.base_fee as f64 * 1.125f64.powi(BASE_FEE_HEADROOM_BLOCKS)) as u128;
+    let max_priority_fee = reference.priority_fee.min(priority_cap).min(max_fee_cap);
+    FeeSuggestion {
+        base_fee: reference.base_fee,
+        base_fee_forecast,
+        max_priority_fee,
+        max_fee: (base_fee_forecast + max_priority_fee).min(max_fee_cap),
+        priority_fee_ceiling: priority_cap,
+        max_fee_ceiling: max_fee_cap,
+    }
+}
+
+/// Priority fee and max fee ceilings for the market `reference`.
+pub fn ceilings(config: &Config, reference: &FeeReference) -> (u128, u128) {
+    let priority_cap = (reference.priority_fee as f64 * config.max_priority_fee_multiplier)
+        .max(config.min_priority_fee_cap_gwei * GWEI) as u128;
+    let max_fee_cap = (reference.base_fee as f64 * config.max_base_fee_multiplier) as u128 + priority_cap;
+    (priority_cap, max_fee_cap)
+}
+
+/// Block fees above the ceilings. `reference` is `None` when the fee
+/// history could not be read; only the absolute ceiling applies then.
+pub fn check(config: &Config, fees: &TxFees, reference: Option<&FeeReference>) -> Result<(), String> {
+    let gwei = |wei: u128| wei as f64 / GWEI;
+    if let Some(max_fee) = fees.max_fee {
+        let absolute = (config.max_fee_per_gas_gwei * GWEI) as u128;
+        if config.max_fee_per_gas_gwei > 0.0 && max_fee > absolute {
+            return Err(format!(
+                "PLIMSOLL GAS FEE: max fee {:.2} gwei exceeds the {} gwei ceiling",
+                gwei(max_fee),
+                config.max_fee_per_gas_gwei
+            ));
+        }
+    }
+    let Some(reference) = reference else {
+        return Ok(());
+    };
+    let (priority_cap, max_fee_cap) = ceilings(config, reference);
+    let tip = fees
+        .priority_fee
+        .or_else(|| fees.max_fee.map(|price| price.saturating_sub(reference.base_fee)));
+    if let Some(tip) = tip.filter(|tip| *tip > priority_cap) {
+        return Err(format!(
+            "PLIMSOLL GAS FEE: priority fee {:.2} gwei exceeds the {:.2} gwei ceiling \
+             (recent median tip {:.2} gwei) — gas is a loss balance physics does not count",
+            gwei(tip),
+            gwei(priority_cap),
+            gwei(reference.priority_fee)
+        ));
+    }
+    if let Some(max_fee) = fees.max_fee.filter(|fee| *fee > max_fee_cap) {
+        return Err(format!(
+            "PLIMSOLL GAS FEE: max fee {:.2} gwei exceeds the {:.2} gwei ceiling \
+             (next base fee {:.2} gwei)",
+            gwei(max_fee),
+            gwei(max_fee_cap),
+            gwei(reference.base_fee)
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE_GWEI: u128 = 1_000_000_000;
+
+    /// 20 gwei next base fee, 1 gwei median tip.
+    fn market() -> FeeReference {
+        FeeReference { base_fee: 20 * ONE_GWEI, priority_fee: ONE_GWEI }
+    }
+
+    #[test]
+    fn test_fee_history_parsed() {
+        let history = serde_json::json!({
+            "baseFeePerGas": ["0x3b9aca00", "0x4a817c800"],
+            "reward": [["0x3b9aca00"], ["0x77359400"], ["0x5f5e100"]]
+        });
+        let reference = parse_fee_history(&history).unwrap();
+        assert_eq!(reference, FeeReference { base_fee: 20 * ONE_GWEI, priority_fee: ONE_GWEI });
+        assert!(parse_fee_history(&serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_tx_fees_parsed() {
+        let tx = serde_json::json!({ "maxFeePerGas": "0x4a817c800", "maxPriorityFeePerGas": "0x3b9aca00" });
+        assert_eq!(parse(&tx), TxFees { max_fee: Some(20 * ONE_GWEI), priority_fee: Some(ONE_GWEI) });
+        let legacy = serde_json::json!({ "gasPrice": "0x4a817c800" });
+        assert_eq!(parse(&legacy), TxFees { max_fee: Some(20 * ONE_GWEI), priority_fee: None });
+    }
+
+    #[test]
+    fn test_inflated_tip_blocked() {
+        let fees = TxFees { max_fee: Some(1_100 * ONE_GWEI), priority_fee: Some(1_000 * ONE_GWEI) };
+        let err = check(&Config::default(), &fees, Some(&market())).unwrap_err();
+        assert!(err.contains("priority fee 1000.00 gwei"));
+        // A legacy gasPrice far above the base fee is the same tip.
+        let legacy = TxFees { max_fee: Some(1_000 * ONE_GWEI), priority_fee: None };
+        assert!(check(&Config::default(), &legacy, Some(&market())).is_err());
+    }
+
+    #[test]
+    fn test_inflated_max_fee_blocked() {
+        let fees = TxFees { max_fee: Some(500 * ONE_GWEI), priority_fee: Some(ONE_GWEI) };
+        let err = check(&Config::default(), &fees, Some(&market())).unwrap_err();
+        assert!(err.contains("max fee 500.00 gwei"));
+    }
+
+    #[test]
+    fn test_market_fees_allowed() {
+        let fees = TxFees { max_fee: Some(42 * ONE_GWEI), priority_fee: Some(2 * ONE_GWEI) };
+        assert!(check(&Config::default(), &fees, Some(&market())).is_ok());
+        // Without a fee history only the absolute ceiling applies.
+        let config = Config { max_fee_per_gas_gwei: 300.0, ..Config::default() };
+        let high = TxFees { max_fee: Some(500 * ONE_GWEI), priority_fee: None };
+        assert!(check(&Config::default(), &high, None).is_ok());
+        assert!(check(&config, &high, None).unwrap_err().contains("300 gwei ceiling"));
+    }
+
+    #[test]
+    fn test_suggestion_passes_ceilings() {
+        let suggestion = suggest(&Config::default(), &market());
+        assert_eq!(suggestion.max_priority_fee, ONE_GWEI);
+        // 20 gwei × 1.125^6 ≈ 40.5 gwei of base fee headroom.
+        assert_eq!(suggestion.base_fee_forecast / ONE_GWEI, 40);
+        assert_eq!(suggestion.max_fee, suggestion.base_fee_forecast + ONE_GWEI);
+        let fees = TxFees { max_fee: Some(suggestion.max_fee), priority_fee: Some(suggestion.max_priority_fee) };
+        assert!(check(&Config::default(), &fees, Some(&market())).is_ok());
+
+        // A tight operator ceiling clamps the suggestion instead.
+        let tight = Config { max_base_fee_multiplier: 1.0, max_fee_per_gas_gwei: 22.0, ..Config::default() };
+        let suggestion = suggest(&tight, &market());
+        assert_eq!(suggestion.max_fee, 22 * ONE_GWEI);
+        let fees = TxFees { max_fee: Some(suggestion.max_fee), priority_fee: Some(suggestion.max_priority_fee) };
+        assert!(check(&tight, &fees, Some(&market())).is_ok());
+        assert_eq!(suggestion.to_json()["maxFeePerGas"], "0x51f4d5c00");
+    }
+}
@@ -0,0 +1,253 @@
+//! Peer-to-peer IOC gossip between proxies.
+//!
+//! An IOC uplinked to the Cloud only reaches the rest of the fleet after
+//! the consensus round trip. With `gossip_peers`, every IOC this proxy
+//! extracts is also pushed straight to its peers:
+//!
+//! ```text
+//! POST <peer>/gossip/ioc
+//! Authorization: Bearer <PLIMSOLL_GOSSIP_SECRET>
+//! {"id": "…", "hops": 3, "ioc": { …IOCReport… }}
+//! ```
+//!
+//! A peer blocks the IOC's target in Engine 0 (on the IOC's chain, for
+//! `gossip_ttl_secs`) when the blocking engine is one of `gossip_engines`
+//! — engines whose verdict is about the target rather than the agent's
+//! own limits — and the target isn't immune (see `AntiGriefing`). It then relays the
+//! IOC to its own peers until `hops` runs out; IOCs already seen are
+//! dropped, so meshes and rings both converge. Gossip is fleet-internal:
+//! the shared secret is the only trust boundary, and the Cloud uplink is
+//! unaffected.
+
+use crate::auth;
+use crate::config::{is_hex_address, split_list, Config};
+use crate::reload::SharedConfigHandle;
+use crate::router::AppState;
+use crate::telemetry::IOCReport;
+use crate::threat_feed::{self, AntiGriefing};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Hops an IOC travels from the proxy that extracted it.
+const GOSSIP_HOPS: u8 = 3;
+
+/// IOC ids remembered for deduplication.
+const MAX_SEEN: usize = 4096;
+
+/// An IOC in flight between peers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GossipMessage {
+    pub id: String,
+    /// Relays left, including the receiving peer's.
+    pub hops: u8,
+    pub ioc: IOCReport,
+}
+
+lazy_static! {
+    /// Recently seen IOC ids, oldest first.
+    static ref SEEN: Mutex<(HashSet<String>, VecDeque<String>)> =
+        Mutex::new((HashSet::new(), VecDeque::new()));
+}
+
+/// Live config, set by `start`. Gossip is off until then.
+static CONFIG: OnceLock<SharedConfigHandle> = OnceLock::new();
+
+/// Enable gossip with the live config (peers can be changed by reload).
+pub fn start(config: SharedConfigHandle) {
+    let _ = CONFIG.set(config);
+}
+
+/// Deduplication id of an IOC.
+fn ioc_id(ioc: &IOCReport) -> String {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for part in [&ioc.agent_id, &ioc.target_address, &ioc.calldata_hash, &ioc.block_engine] {
+        for b in part.bytes().chain([0]) {
+            h ^= b as u64;
+            h = h.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}{:x}", h, ioc.timestamp)
+}
+
+/// Remember `id`. `false` if it was seen before.
+fn mark_seen(id: &str) -> bool {
+    let Ok(mut seen) = SEEN.lock() else {
+        return false;
+    };
+    let (set, order) = &mut *seen;
+    if !set.insert(id.to_string()) {
+        return false;
+    }
+    order.push_back(id.to_string());
+    if order.len() > MAX_SEEN {
+        if let Some(oldest) = order.pop_front() {
+            set.remove(&oldest);
+        }
+    }
+    true
+}
+
+/// Push `message` to every peer. Fire-and-forget.
+fn fan_out(config: &Config, message: GossipMessage) {
+    let peers: Vec<String> = split_list(&config.gossip_peers).map(|p| p.trim_end_matches('/').to_string()).collect();
+    if peers.is_empty() || message.hops == 0 {
+        return;
+    }
+    let secret = config.gossip_secret.clone();
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        for peer in peers {
+            let sent = client
+                .post(format!("{peer}/gossip/ioc"))
+                .bearer_auth(&secret)
+                .json(&message)
+                .timeout(Duration::from_secs(2))
+                .send()
+                .await;
+            match sent {
+                Ok(resp) if resp.status().is_success() => {}
+                Ok(resp) => warn!(peer = %peer, status = resp.status().as_u16(), "IOC gossip rejected by peer"),
+                Err(e) => warn!(peer = %peer, error = %e, "IOC gossip failed (non-blocking)"),
+            }
+        }
+    });
+}
+
+/// Share a locally extracted IOC with the peers.
+pub fn share(ioc: &IOCReport) {
+    let Some(handle) = CONFIG.get() else {
+        return;
+    };
+    let config = handle.current();
+    if config.gossip_peers.is_empty() {
+        return;
+    }
+    let id = ioc_id(ioc);
+    mark_seen(&id);
+    fan_out(&config, GossipMessage { id, hops: GOSSIP_HOPS, ioc: ioc.clone() });
+}
+
+/// Whether a gossiped IOC's target should be blacklisted here.
+fn actionable(config: &Config, ioc: &IOCReport) -> bool {
+    is_hex_address(&ioc.target_address)
+        && split_list(&config.gossip_engines).any(|e| e.eq_ignore_ascii_case(&ioc.block_engine))
+        && AntiGriefing::validate_blacklist_entry(&ioc.target_address).0
+}
+
+/// Apply an IOC received from a peer and relay it. `false` if it was
+/// already seen.
+fn receive(config: &Config, message: GossipMessage) -> bool {
+    if !mark_seen(&message.id) {
+        return false;
+    }
+    let ioc = &message.ioc;
+    if actionable(config, ioc) {
+        threat_feed::block_from_gossip(config, ioc.chain_id, &ioc.target_address, &ioc.block_engine);
+        info!(
+            target = %ioc.target_address,
+            engine = %ioc.block_engine,
+            chain_id = ioc.chain_id,
+            ttl_secs = config.gossip_ttl_secs,
+            "Engine 0: target blacklisted from peer gossip"
+        );
+    }
+    let hops = message.hops.saturating_sub(1);
+    fan_out(config, GossipMessage { hops, ..message });
+    true
+}
+
+/// Gossip routes, to be nested under `/gossip`.
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/ioc", post(receive_ioc))
+}
+
+/// POST /gossip/ioc
+async fn receive_ioc(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(message): Json<GossipMessage>,
+) -> Response {
+    let config = state.config.current();
+    if let Err(status) = authorize_peer(&config, &headers) {
+        if status == StatusCode::UNAUTHORIZED {
+            warn!("GOSSIP: rejected IOC with invalid secret");
+        }
+        return (status, Json(json!({ "error": "unauthorized" }))).into_response();
+    }
+    let fresh = receive(&config, message);
+    Json(json!({ "accepted": fresh })).into_response()
+}
+
+/// Check `Authorization: Bearer <gossip_secret>`. Gossip is disabled (404)
+/// when no secret is configured.
+fn authorize_peer(config: &Config, headers: &HeaderMap) -> Result<(), StatusCode> {
+    if config.gossip_secret.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let presented = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if auth::constant_time_eq(presented.as_bytes(), config.gossip_secret.as_bytes()) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry;
+
+    const DRAINER: &str = "0x6666666666666666666666666666666666666666";
+
+    fn ioc(engine: &str, target: &str) -> IOCReport {
+        telemetry::extract_ioc("0xagent", target, &[0xde, 0xad, 0xbe, 0xef], engine, "blocked", None, 1)
+    }
+
+    #[test]
+    fn test_only_target_engines_are_actionable() {
+        let config = Config::default();
+        assert!(actionable(&config, &ioc("metamorphic", DRAINER)));
+        // The agent's own limits say nothing about the target.
+        assert!(!actionable(&config, &ioc("velocity", DRAINER)));
+        // Immune protocols are never blacklisted.
+        assert!(!actionable(&config, &ioc("metamorphic", "0x7a250d5630b4cf539739df2c5dacb4c659f2488d")));
+        assert!(!actionable(&config, &ioc("metamorphic", "not-an-address")));
+    }
+
+    #[test]
+    fn test_seen_ids_are_dropped() {
+        let reported = ioc("reentrancy", DRAINER);
+        let id = ioc_id(&reported);
+        assert_eq!(ioc_id(&reported), id);
+        assert!(mark_seen(&id));
+        assert!(!mark_seen(&id));
+        assert_ne!(ioc_id(&IOCReport { block_engine: "delegatecall".into(), ..reported }), id);
+    }
+
+    #[test]
+    fn test_peer_secret_checked() {
+        let mut headers = HeaderMap::new();
+        headers.insert(axum::http::header::AUTHORIZATION, "Bearer fleet".parse().unwrap());
+        assert_eq!(authorize_peer(&Config::default(), &headers), Err(StatusCode::NOT_FOUND));
+        let config = Config { gossip_secret: "fleet".into(), ..Config::default() };
+        assert!(authorize_peer(&config, &headers).is_ok());
+        let config = Config { gossip_secret: "other".into(), ..Config::default() };
+        assert_eq!(authorize_peer(&config, &headers), Err(StatusCode::UNAUTHORIZED));
+    }
+}
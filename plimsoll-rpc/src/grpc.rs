@@ -0,0 +1,317 @@
+//! gRPC decisions API (`--features grpc`).
+//!
+//! The proxy-native methods, typed, for agent infrastructure in Go or
+//! Python that would rather generate a client from `proto/plimsoll.proto`
+//! than hand-roll JSON-RPC extensions:
+//!
+//! | RPC           | Mirrors                                     |
+//! |---------------|---------------------------------------------|
+//! | `Simulate`    | `plimsoll_simulate`                         |
+//! | `RiskScore`   | `plimsoll_dryRun`                           |
+//! | `ListBlocked` | the blocked-tx store (synthetic receipts)   |
+//! | `Status`      | `/readyz`, shadow mode, Paymaster sever     |
+//!
+//! `Simulate` and `RiskScore` run through the RPC pipeline itself, so
+//! authentication, `from` binding, rate limits, chain selection and the
+//! audit log apply unchanged; the other two authenticate and rate limit
+//! the same way. Metadata carries what headers carry over HTTP
+//! (`plimsoll-api-key` or `authorization`, `plimsoll-chain`).
+//!
+//! Served on `host:grpc_port` (0 = off, restart required).
+
+use crate::auth;
+use crate::block_codes;
+use crate::chains;
+use crate::config::Config;
+use crate::health;
+use crate::rate_limit;
+use crate::router::{self, AppState};
+use crate::rpc;
+use crate::shutdown;
+use crate::types::JsonRpcRequest;
+use axum::http::{HeaderMap, StatusCode};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+#[allow(clippy::all)]
+pub mod pb {
+    tonic::include_proto!("plimsoll.v1");
+}
+
+use pb::decisions_server::{Decisions, DecisionsServer};
+
+/// `ListBlocked` entries when the request sets no limit, and at most.
+const DEFAULT_LIST_LIMIT: usize = 100;
+const MAX_LIST_LIMIT: usize = 1000;
+
+fn text(value: &Value, key: &str) -> String {
+    value.get(key).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+fn verdict(engine: &str, reason: &str) -> pb::Verdict {
+    let code = block_codes::of(engine);
+    pb::Verdict {
+        code: code.code.into(),
+        category: code.category.to_string(),
+        engine: engine.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// A simulation as `plimsoll_simulate` / `plimsoll_dryRun` report it.
+fn simulation(value: &Value) -> pb::Simulation {
+    let asset_deltas = value["assetDeltas"]
+        .as_array()
+        .map(|deltas| {
+            deltas
+                .iter()
+                .map(|d| pb::AssetDelta {
+                    standard: text(d, "standard"),
+                    token: text(d, "token"),
+                    token_id: text(d, "tokenId"),
+                    sent: text(d, "sent"),
+                    received: text(d, "received"),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    pb::Simulation {
+        success: value["success"].as_bool().unwrap_or(false),
+        gas_used: value["gasUsed"].as_u64().unwrap_or(0),
+        balance_before: text(value, "balanceBefore"),
+        balance_after: text(value, "balanceAfter"),
+        loss_pct: value["lossPct"].as_f64().unwrap_or(0.0),
+        error: text(value, "error"),
+        asset_deltas,
+    }
+}
+
+/// The `eth_sendTransaction` object of `tx`; empty fields are left out.
+fn transaction_json(tx: Option<&pb::Transaction>) -> Value {
+    let Some(tx) = tx else {
+        return json!({});
+    };
+    let fields = [("from", &tx.from), ("to", &tx.to), ("value", &tx.value), ("data", &tx.data), ("gas", &tx.gas)];
+    Value::Object(
+        fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.to_string(), json!(value)))
+            .collect(),
+    )
+}
+
+/// The gRPC status of an RPC pipeline error.
+fn rpc_status(status: StatusCode, error: &Value) -> Status {
+    let message = text(error, "message");
+    match (status, error["code"].as_i64().unwrap_or(0)) {
+        (StatusCode::UNAUTHORIZED, _) => Status::unauthenticated(message),
+        (StatusCode::TOO_MANY_REQUESTS, _) => Status::resource_exhausted(message),
+        (_, auth::UNAUTHORIZED_SENDER_CODE) => Status::permission_denied(message),
+        (_, -32602 | chains::UNSUPPORTED_CHAIN_CODE) => Status::invalid_argument(message),
+        _ => Status::unknown(message),
+    }
+}
+
+/// Authenticate and rate limit a call answered outside the RPC pipeline;
+/// the chain it selects. The status is boxed (it is large) and unboxed by
+/// the service methods.
+fn authorize(config: &Config, headers: &HeaderMap, method: &str) -> Result<u64, Box<Status>> {
+    let agent = auth::authenticate(config, &auth::credentials(headers, None))
+        .map_err(|_| Status::unauthenticated("Unauthorized: missing or invalid API key"))?;
+    let agent_name = agent.map_or(rate_limit::ANONYMOUS_AGENT, |a| a.name.as_str());
+    rate_limit::check(config, agent_name, method).map_err(Status::resource_exhausted)?;
+    Ok(chains::select(config, chains::requested(headers).as_deref(), agent).map_err(Status::invalid_argument)?)
+}
+
+pub struct DecisionsService {
+    state: Arc<AppState>,
+}
+
+impl DecisionsService {
+    /// Run `method` through the RPC pipeline; its result.
+    async fn call(&self, headers: HeaderMap, method: &str, params: Value) -> Result<Value, Status> {
+        let req = JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            method: method.to_string(),
+            params,
+            id: json!(1),
+            notification: false,
+        };
+        let credentials = auth::credentials(&headers, None);
+        let (status, _, axum::Json(response)) =
+            router::serve_rpc(&self.state, credentials, chains::requested(&headers), req).await;
+        if let Some(error) = response.get("error").filter(|e| !e.is_null()) {
+            return Err(rpc_status(status, error));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+}
+
+#[tonic::async_trait]
+impl Decisions for DecisionsService {
+    async fn simulate(
+        &self,
+        request: Request<pb::SimulateRequest>,
+    ) -> Result<Response<pb::SimulateResponse>, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let req = request.into_inner();
+        let mut params = vec![transaction_json(req.transaction.as_ref())];
+        if !req.state_override_json.is_empty() {
+            let overrides: Value = serde_json::from_str(&req.state_override_json)
+                .map_err(|e| Status::invalid_argument(format!("state_override_json: {e}")))?;
+            params.push(overrides);
+        }
+        let result = self.call(headers, "plimsoll_simulate", Value::Array(params)).await?;
+        let decision = &result["verdict"];
+        let allowed = decision["allowed"].as_bool().unwrap_or(false);
+        Ok(Response::new(pb::SimulateResponse {
+            simulation: Some(simulation(&result)),
+            allowed,
+            verdict: (!allowed).then(|| verdict(&text(decision, "engine"), &text(decision, "reason"))),
+        }))
+    }
+
+    async fn risk_score(
+        &self,
+        request: Request<pb::RiskScoreRequest>,
+    ) -> Result<Response<pb::RiskScoreResponse>, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let req = request.into_inner();
+        let method = if req.method.is_empty() { "eth_sendTransaction".to_string() } else { req.method };
+        let params = if req.params_json.is_empty() {
+            json!([transaction_json(req.transaction.as_ref())])
+        } else {
+            serde_json::from_str::<Value>(&req.params_json)
+                .ok()
+                .filter(Value::is_array)
+                .ok_or_else(|| Status::invalid_argument("params_json must be a JSON array"))?
+        };
+        let result = self.call(headers, "plimsoll_dryRun", json!([{ "method": method, "params": params }])).await?;
+        let decision = &result["verdict"];
+        let reputation = &result["reputation"];
+        Ok(Response::new(pb::RiskScoreResponse {
+            would_block: result["wouldBlock"].as_bool().unwrap_or(false),
+            verdict: decision
+                .is_object()
+                .then(|| verdict(&text(decision, "engine"), &text(decision, "reason"))),
+            risk_score: result["riskScore"].as_u64().unwrap_or(0) as u32,
+            reputation: reputation.is_object().then(|| pb::Reputation {
+                target: text(reputation, "target"),
+                score: reputation["score"].as_u64().unwrap_or(0) as u32,
+            }),
+            simulation: result["simulation"].is_object().then(|| simulation(&result["simulation"])),
+        }))
+    }
+
+    async fn list_blocked(
+        &self,
+        request: Request<pb::ListBlockedRequest>,
+    ) -> Result<Response<pb::ListBlockedResponse>, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let config = self.state.config.current();
+        let chain = authorize(&config, &headers, "plimsoll_listBlocked").map_err(|e| *e)?;
+        let limit = match request.get_ref().limit as usize {
+            0 => DEFAULT_LIST_LIMIT,
+            n => n.min(MAX_LIST_LIMIT),
+        };
+        let blocked = chains::scope(&config, chain, async { rpc::blocked_txs(limit) }).await;
+        Ok(Response::new(pb::ListBlockedResponse {
+            blocked: blocked
+                .into_iter()
+                .map(|(tx_hash, b)| pb::BlockedTransaction {
+                    verdict: Some(verdict(&b.engine, &b.reason)),
+                    tx_hash,
+                    from: b.from,
+                    to: b.to,
+                    blocked_at: b.blocked_at,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn status(&self, request: Request<pb::StatusRequest>) -> Result<Response<pb::StatusResponse>, Status> {
+        let headers = request.metadata().clone().into_headers();
+        let config = self.state.config.current();
+        let chain = authorize(&config, &headers, "plimsoll_status").map_err(|e| *e)?;
+        let report = health::build_report(&config, &self.state.threat_filter).await;
+        Ok(Response::new(pb::StatusResponse {
+            version: report.version.to_string(),
+            ready: report.ready,
+            shadow_mode: rpc::is_shadow_mode(),
+            paymaster_severed: report.paymaster_severed,
+            blocked_count: rpc::blocked_tx_count() as u64,
+            chain_id: chain,
+        }))
+    }
+}
+
+/// Serve the decisions API on `host:grpc_port` (0 = off).
+pub fn spawn_server(state: Arc<AppState>) {
+    let config = state.config.current();
+    if config.grpc_port == 0 {
+        return;
+    }
+    let addr: SocketAddr = match format!("{}:{}", config.host, config.grpc_port).parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            warn!(host = %config.host, error = %e, "gRPC decisions API not started: host is not an IP address");
+            return;
+        }
+    };
+    shutdown::track(async move {
+        info!(%addr, "gRPC decisions API listening");
+        let service = DecisionsServer::new(DecisionsService { state });
+        let server = tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_shutdown(addr, shutdown::signaled());
+        if let Err(e) = server.await {
+            warn!(error = %e, "gRPC decisions API stopped");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_json() {
+        let tx = pb::Transaction {
+            from: "0xA9E0000000000000000000000000000000002115".into(),
+            to: "0x1111111111111111111111111111111111111111".into(),
+            value: "0xde0b6b3a7640000".into(),
+            ..pb::Transaction::default()
+        };
+        let json = transaction_json(Some(&tx));
+        assert_eq!(json["value"], "0xde0b6b3a7640000");
+        assert!(json.get("data").is_none());
+        assert_eq!(transaction_json(None), json!({}));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let sim = simulation(&json!({
+            "success": true,
+            "gasUsed": 21000,
+            "balanceBefore": "10",
+            "balanceAfter": "4",
+            "lossPct": 60.0,
+            "error": null,
+            "assetDeltas": [{ "standard": "erc20", "token": "0xt", "tokenId": null, "sent": "5", "received": "0" }],
+        }));
+        assert_eq!((sim.gas_used, sim.loss_pct, sim.error.as_str()), (21000, 60.0, ""));
+        assert_eq!(sim.asset_deltas[0].sent, "5");
+
+        let physics = verdict("physics", "PLIMSOLL PHYSICS: loss 60%");
+        assert_eq!((physics.code, physics.category.as_str()), (4902, "PHYSICS"));
+
+        let limited = rpc_status(StatusCode::TOO_MANY_REQUESTS, &json!({ "code": -32005, "message": "slow down" }));
+        assert_eq!(limited.code(), tonic::Code::ResourceExhausted);
+        let invalid = rpc_status(StatusCode::OK, &json!({ "code": -32602, "message": "Invalid params" }));
+        assert_eq!(invalid.code(), tonic::Code::InvalidArgument);
+    }
+}
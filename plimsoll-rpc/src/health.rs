@@ -0,0 +1,153 @@
+//! Health and readiness probes for orchestrators (Kubernetes, Nomad, ECS).
+//!
+//! - `GET /healthz` — liveness. 200 as long as the process is serving HTTP.
+//!   The body carries the full component report for humans.
+//! - `GET /readyz`  — readiness. 503 when the proxy cannot protect traffic:
+//!   upstream RPC unreachable, (when required) the threat feed is stale, or
+//!   the proxy is draining for shutdown.
+//!
+//! A severed Paymaster is reported but deliberately does NOT fail readiness:
+//! draining a severed replica would route the agent to a healthy replica and
+//! silently bypass the sever.
+
+use crate::config::Config;
+use crate::rpc;
+use crate::threat_feed::SharedThreatFilter;
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Timeout for the upstream reachability probe.
+const UPSTREAM_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Status of a single component.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentStatus {
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Full health report returned by `/healthz` and `/readyz`.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub ready: bool,
+    pub version: &'static str,
+    pub upstream: ComponentStatus,
+    pub threat_feed: ComponentStatus,
+    pub mempool_watcher: ComponentStatus,
+    pub paymaster_severed: bool,
+}
+
+/// Probe the upstream RPC with `eth_chainId`.
+pub async fn check_upstream(config: &Config) -> ComponentStatus {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_chainId",
+        "params": [],
+        "id": 1
+    });
+    let start = Instant::now();
+    let result = reqwest::Client::new()
+        .post(&config.upstream_rpc_url)
+        .json(&payload)
+        .timeout(UPSTREAM_PROBE_TIMEOUT)
+        .send()
+        .await;
+
+    match result {
+        Ok(resp) if resp.status().is_success() => ComponentStatus {
+            ok: true,
+            detail: format!("reachable ({}ms)", start.elapsed().as_millis()),
+        },
+        Ok(resp) => ComponentStatus {
+            ok: false,
+            detail: format!("upstream returned HTTP {}", resp.status().as_u16()),
+        },
+        Err(e) => ComponentStatus {
+            ok: false,
+            detail: format!("unreachable: {e}"),
+        },
+    }
+}
+
+/// Evaluate threat feed freshness.
+///
+/// `max_age_secs == 0` disables the staleness requirement (the feed is
+/// always reported, but never fails readiness).
+pub fn check_threat_feed(filter: &SharedThreatFilter, max_age_secs: u64, now: u64) -> ComponentStatus {
+    let (version, entries, last_updated) = match filter.read() {
+        Ok(f) => (f.version, f.len(), f.last_updated),
+        Err(_) => {
+            return ComponentStatus {
+                ok: false,
+                detail: "threat filter lock poisoned".into(),
+            }
+        }
+    };
+
+    if last_updated == 0 {
+        return ComponentStatus {
+            ok: max_age_secs == 0,
+            detail: "no feed loaded yet (awaiting Cloud push)".into(),
+        };
+    }
+
+    let age = now.saturating_sub(last_updated);
+    ComponentStatus {
+        ok: max_age_secs == 0 || age <= max_age_secs,
+        detail: format!("v{} with {} entries, updated {}s ago", version, entries, age),
+    }
+}
+
+/// Build the full report. `ready` is the conjunction of the components
+/// that gate readiness (upstream + threat feed).
+pub async fn build_report(config: &Config, filter: &SharedThreatFilter) -> HealthReport {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let upstream = check_upstream(config).await;
+    let threat_feed = check_threat_feed(filter, config.threat_feed_max_age_secs, now);
+    let watcher = rpc::mempool_watcher_status();
+    let mempool_watcher = ComponentStatus {
+        ok: true,
+        detail: watcher.to_string(),
+    };
+
+    HealthReport {
+        ready: upstream.ok && threat_feed.ok,
+        version: env!("CARGO_PKG_VERSION"),
+        upstream,
+        threat_feed,
+        mempool_watcher,
+        paymaster_severed: rpc::is_paymaster_severed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threat_feed::new_shared_filter;
+
+    #[test]
+    fn test_empty_feed_ok_when_not_required() {
+        let filter = new_shared_filter();
+        let status = check_threat_feed(&filter, 0, 1_000);
+        assert!(status.ok);
+        assert!(status.detail.contains("no feed loaded"));
+    }
+
+    #[test]
+    fn test_empty_feed_not_ready_when_required() {
+        let filter = new_shared_filter();
+        assert!(!check_threat_feed(&filter, 600, 1_000).ok);
+    }
+
+    #[test]
+    fn test_stale_feed_not_ready() {
+        let filter = new_shared_filter();
+        filter.write().unwrap().last_updated = 1_000;
+        assert!(check_threat_feed(&filter, 600, 1_500).ok);
+        assert!(!check_threat_feed(&filter, 600, 2_000).ok);
+    }
+}
@@ -0,0 +1,41 @@
+//! Characters that hide or disguise text.
+//!
+//! Shared by every check that compares what a human (or a model) *reads*
+//! against what the bytes say: ENS names, EIP-712 domains and the
+//! read-path sanitizer.
+
+/// Characters that render as nothing.
+pub const INVISIBLE: &[char] = &[
+    '\u{00ad}', '\u{034f}', '\u{180e}', '\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}', '\u{2061}',
+    '\u{2062}', '\u{2063}', '\u{2064}', '\u{feff}',
+];
+
+/// Zero-width non-joiner and joiner: invisible, but they shape emoji
+/// sequences and Arabic-script and Indic text.
+pub const JOINERS: &[char] = &['\u{200c}', '\u{200d}'];
+
+/// Bidirectional embeddings, overrides and isolates: they reorder how the
+/// text around them is displayed.
+pub const BIDI_CONTROLS: &[char] = &[
+    '\u{061c}', '\u{200e}', '\u{200f}', '\u{202a}', '\u{202b}', '\u{202c}', '\u{202d}', '\u{202e}',
+    '\u{2066}', '\u{2067}', '\u{2068}', '\u{2069}',
+];
+
+/// Non-Latin letters that render like a Latin one: Cyrillic, Greek,
+/// then Latin extensions and letterlike symbols.
+const CONFUSABLES: &[(char, char)] = &[
+    ('а', 'a'), ('е', 'e'), ('о', 'o'), ('р', 'p'), ('с', 'c'), ('у', 'y'), ('х', 'x'),
+    ('і', 'i'), ('ј', 'j'), ('ѕ', 's'), ('һ', 'h'), ('ԁ', 'd'), ('ԛ', 'q'), ('ԝ', 'w'),
+    ('α', 'a'), ('ο', 'o'), ('ν', 'v'), ('ι', 'i'), ('κ', 'k'), ('ρ', 'p'), ('τ', 't'),
+    ('υ', 'u'), ('χ', 'x'), ('ı', 'i'), ('ɡ', 'g'), ('ℓ', 'l'), ('ⅼ', 'l'),
+];
+
+/// The Latin letter `c` imitates, if it is a homoglyph.
+pub fn confusable(c: char) -> Option<char> {
+    CONFUSABLES.iter().find(|(k, _)| *k == c).map(|(_, latin)| *latin)
+}
+
+/// Whether `c` is invisible or a bidi control.
+pub fn is_hidden(c: char) -> bool {
+    INVISIBLE.contains(&c) || BIDI_CONTROLS.contains(&c)
+}
@@ -0,0 +1,389 @@
+//! Honeypot token detection.
+//!
+//! A honeypot lets you buy and never sell: the token's `transfer` reverts
+//! (or takes a 99% "tax") on the way back to the pool. Single-transaction
+//! simulation of the buy looks perfect — the agent receives tokens and
+//! loses nothing but the ETH it meant to spend.
+//!
+//! Before a Uniswap-V2-style swap into an unknown token, the upstream
+//! node simulates the round trip with `eth_simulateV1` (state carries
+//! across calls, signatures aren't checked):
+//!
+//!   1. quote, then execute the agent's buy, measuring tokens received,
+//!   2. approve the router and sell everything back along the reversed
+//!      path, measuring what comes out against a fresh quote.
+//!
+//! A reverting sell, a buy that delivers nothing, or a buy/sell tax above
+//! `honeypot_max_tax_pct` blocks the swap.
+
+use crate::abi;
+use crate::config::Config;
+use alloy_primitives::U256;
+use anyhow::{Context, Result};
+
+/// `swapExactETHForTokens(uint256,address[],address,uint256)`
+const SWAP_EXACT_ETH_FOR_TOKENS: [u8; 4] = [0x7f, 0xf3, 0x6a, 0xb5];
+/// `swapExactETHForTokensSupportingFeeOnTransferTokens(uint256,address[],address,uint256)`
+const SWAP_EXACT_ETH_FOR_TOKENS_FOT: [u8; 4] = [0xb6, 0xf9, 0xde, 0x95];
+/// `swapExactTokensForTokens(uint256,uint256,address[],address,uint256)`
+const SWAP_EXACT_TOKENS_FOR_TOKENS: [u8; 4] = [0x38, 0xed, 0x17, 0x39];
+/// `swapExactTokensForTokensSupportingFeeOnTransferTokens(uint256,uint256,address[],address,uint256)`
+const SWAP_EXACT_TOKENS_FOR_TOKENS_FOT: [u8; 4] = [0x5c, 0x11, 0xd7, 0x95];
+/// `getAmountsOut(uint256,address[])`
+const GET_AMOUNTS_OUT: [u8; 4] = [0xd0, 0x6c, 0xa6, 0x1f];
+/// `balanceOf(address)`
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+/// `approve(address,uint256)`
+const APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+
+/// A decoded swap into a token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Swap {
+    pub router: String,
+    pub amount_in: U256,
+    pub path: Vec<String>,
+    /// Who receives the bought tokens (and would have to sell them).
+    pub recipient: String,
+}
+
+impl Swap {
+    /// The token being bought.
+    pub fn token(&self) -> &str {
+        self.path.last().map(String::as_str).unwrap_or_default()
+    }
+}
+
+/// Outcome of the round-trip simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundTrip {
+    pub token: String,
+    pub buy_tax_pct: f64,
+    pub sell_tax_pct: f64,
+    /// Revert reason of the sell, if it reverted.
+    pub sell_error: Option<String>,
+}
+
+/// Decode a Uniswap-V2-router swap. `Ok(None)` if `data` isn't one.
+pub fn decode_swap(router: &str, value: u128, data: &[u8]) -> Result<Option<Swap>, String> {
+    let Some(selector) = abi::selector(data) else {
+        return Ok(None);
+    };
+    let args = &data[4..];
+    let (amount_in, path, recipient) = match selector {
+        SWAP_EXACT_ETH_FOR_TOKENS | SWAP_EXACT_ETH_FOR_TOKENS_FOT => {
+            (U256::from(value), abi::address_array(args, 1)?, abi::address(args, 2)?)
+        }
+        SWAP_EXACT_TOKENS_FOR_TOKENS | SWAP_EXACT_TOKENS_FOR_TOKENS_FOT => {
+            (abi::uint(args, 0)?, abi::address_array(args, 2)?, abi::address(args, 3)?)
+        }
+        _ => return Ok(None),
+    };
+    if path.len() < 2 {
+        return Err("swap path shorter than two tokens".into());
+    }
+    Ok(Some(Swap { router: router.to_lowercase(), amount_in, path, recipient }))
+}
+
+fn encode_path(out: &mut Vec<u8>, path: &[String]) -> Result<(), String> {
+    out.extend_from_slice(&abi::encode_u256(U256::from(path.len())));
+    for token in path {
+        out.extend_from_slice(&abi::encode_address(token)?);
+    }
+    Ok(())
+}
+
+fn get_amounts_out(amount_in: U256, path: &[String]) -> Result<Vec<u8>, String> {
+    let mut out = GET_AMOUNTS_OUT.to_vec();
+    out.extend_from_slice(&abi::encode_u256(amount_in));
+    out.extend_from_slice(&abi::encode_u256(U256::from(0x40)));
+    encode_path(&mut out, path)?;
+    Ok(out)
+}
+
+fn balance_of(holder: &str) -> Result<Vec<u8>, String> {
+    let mut out = BALANCE_OF.to_vec();
+    out.extend_from_slice(&abi::encode_address(holder)?);
+    Ok(out)
+}
+
+fn approve(spender: &str, amount: U256) -> Result<Vec<u8>, String> {
+    let mut out = APPROVE.to_vec();
+    out.extend_from_slice(&abi::encode_address(spender)?);
+    out.extend_from_slice(&abi::encode_u256(amount));
+    Ok(out)
+}
+
+/// `swapExactTokensForTokensSupportingFeeOnTransferTokens(amount, 0, path, to, MAX)`
+fn sell(amount: U256, path: &[String], to: &str) -> Result<Vec<u8>, String> {
+    let mut out = SWAP_EXACT_TOKENS_FOR_TOKENS_FOT.to_vec();
+    out.extend_from_slice(&abi::encode_u256(amount));
+    out.extend_from_slice(&abi::encode_u256(U256::ZERO));
+    out.extend_from_slice(&abi::encode_u256(U256::from(0xa0)));
+    out.extend_from_slice(&abi::encode_address(to)?);
+    out.extend_from_slice(&abi::encode_u256(U256::MAX));
+    encode_path(&mut out, path)?;
+    Ok(out)
+}
+
+fn call(from: &str, to: &str, value: U256, data: &[u8]) -> serde_json::Value {
+    serde_json::json!({
+        "from": from,
+        "to": to,
+        "value": format!("0x{value:x}"),
+        "data": format!("0x{}", hex::encode(data)),
+    })
+}
+
+/// Result of one simulated call.
+struct CallResult {
+    return_data: Vec<u8>,
+    error: Option<String>,
+}
+
+/// Run `calls` in one simulated block; state carries across them.
+async fn simulate(rpc_url: &str, calls: Vec<serde_json::Value>) -> Result<Vec<CallResult>> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_simulateV1",
+        "params": [{ "blockStateCalls": [{ "calls": calls }], "validation": false }, "latest"],
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to call eth_simulateV1")?
+        .json()
+        .await
+        .context("Failed to parse eth_simulateV1 response")?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("eth_simulateV1 unavailable: {err}");
+    }
+    let results = body["result"][0]["calls"]
+        .as_array()
+        .context("eth_simulateV1 returned no call results")?;
+    results
+        .iter()
+        .map(|r| {
+            let return_data = hex::decode(r["returnData"].as_str().unwrap_or("0x").trim_start_matches("0x"))
+                .context("eth_simulateV1 returned invalid returnData")?;
+            let error = (r["status"].as_str() != Some("0x1")).then(|| {
+                r["error"]["message"].as_str().unwrap_or("reverted").to_string()
+            });
+            Ok(CallResult { return_data, error })
+        })
+        .collect()
+}
+
+fn returned_uint(result: &CallResult) -> Result<U256> {
+    if let Some(err) = &result.error {
+        anyhow::bail!("call reverted: {err}");
+    }
+    abi::uint(&result.return_data, 0).map_err(anyhow::Error::msg)
+}
+
+fn last_amount(result: &CallResult) -> Result<U256> {
+    if let Some(err) = &result.error {
+        anyhow::bail!("getAmountsOut reverted: {err}");
+    }
+    abi::uint_array(&result.return_data, 0)
+        .map_err(anyhow::Error::msg)?
+        .last()
+        .copied()
+        .context("getAmountsOut returned no amounts")
+}
+
+/// Percentage of `expected` that didn't arrive.
+fn tax_pct(received: U256, expected: U256) -> f64 {
+    if expected.is_zero() || received >= expected {
+        return 0.0;
+    }
+    let lossy = |v: U256| v.to_string().parse::<f64>().unwrap_or(f64::MAX);
+    lossy(expected - received) / lossy(expected) * 100.0
+}
+
+/// Simulate `data` and measure how many of the bought token reach the
+/// recipient. `Ok(None)` if the swap reverts.
+pub async fn simulate_received(
+    config: &Config,
+    from: &str,
+    value: U256,
+    data: &[u8],
+    swap: &Swap,
+) -> Result<Option<U256>> {
+    let token = swap.token();
+    let holder = &swap.recipient;
+    let err = anyhow::Error::msg;
+    let results = simulate(
+        &config.upstream_rpc_url,
+        vec![
+            call(holder, token, U256::ZERO, &balance_of(holder).map_err(err)?),
+            call(from, &swap.router, value, data),
+            call(holder, token, U256::ZERO, &balance_of(holder).map_err(err)?),
+        ],
+    )
+    .await?;
+    let [before, swapped, after] = results.as_slice() else {
+        anyhow::bail!("eth_simulateV1 returned {} results for 3 calls", results.len());
+    };
+    if swapped.error.is_some() {
+        return Ok(None);
+    }
+    Ok(Some(returned_uint(after)?.saturating_sub(returned_uint(before)?)))
+}
+
+/// Simulate buying and immediately selling the token `swap` buys.
+/// `Ok(None)` if the buy itself fails (the regular simulation reports it).
+pub async fn round_trip(
+    config: &Config,
+    from: &str,
+    value: U256,
+    data: &[u8],
+    swap: &Swap,
+) -> Result<Option<RoundTrip>> {
+    let token = swap.token();
+    let holder = &swap.recipient;
+    let buy = call(from, &swap.router, value, data);
+    let err = anyhow::Error::msg;
+
+    // ── Leg 1: buy, measure what actually arrives ───────────────
+    let leg1 = simulate(
+        &config.upstream_rpc_url,
+        vec![
+            call(holder, &swap.router, U256::ZERO, &get_amounts_out(swap.amount_in, &swap.path).map_err(err)?),
+            call(holder, token, U256::ZERO, &balance_of(holder).map_err(err)?),
+            buy.clone(),
+            call(holder, token, U256::ZERO, &balance_of(holder).map_err(err)?),
+        ],
+    )
+    .await?;
+    let [quote, before, bought, after] = leg1.as_slice() else {
+        anyhow::bail!("eth_simulateV1 returned {} results for 4 calls", leg1.len());
+    };
+    if bought.error.is_some() {
+        return Ok(None);
+    }
+    let received = returned_uint(after)?.saturating_sub(returned_uint(before)?);
+    let buy_tax_pct = tax_pct(received, last_amount(quote)?);
+    if received.is_zero() {
+        return Ok(Some(RoundTrip {
+            token: token.to_string(),
+            buy_tax_pct: 100.0,
+            sell_tax_pct: 0.0,
+            sell_error: None,
+        }));
+    }
+
+    // ── Leg 2: sell everything back along the reversed path ─────
+    let reverse: Vec<String> = swap.path.iter().rev().cloned().collect();
+    let proceeds = &reverse[reverse.len() - 1];
+    let leg2 = simulate(
+        &config.upstream_rpc_url,
+        vec![
+            buy,
+            call(holder, token, U256::ZERO, &approve(&swap.router, received).map_err(err)?),
+            call(holder, &swap.router, U256::ZERO, &get_amounts_out(received, &reverse).map_err(err)?),
+            call(holder, proceeds, U256::ZERO, &balance_of(holder).map_err(err)?),
+            call(holder, &swap.router, U256::ZERO, &sell(received, &reverse, holder).map_err(err)?),
+            call(holder, proceeds, U256::ZERO, &balance_of(holder).map_err(err)?),
+        ],
+    )
+    .await?;
+    let [_, approved, quote, before, sold, after] = leg2.as_slice() else {
+        anyhow::bail!("eth_simulateV1 returned {} results for 6 calls", leg2.len());
+    };
+    let sell_error = approved.error.clone().or_else(|| sold.error.clone());
+    let sell_tax_pct = if sell_error.is_some() {
+        100.0
+    } else {
+        let out = returned_uint(after)?.saturating_sub(returned_uint(before)?);
+        tax_pct(out, last_amount(quote)?)
+    };
+    Ok(Some(RoundTrip { token: token.to_string(), buy_tax_pct, sell_tax_pct, sell_error }))
+}
+
+/// Block a honeypot round trip.
+pub fn check(config: &Config, trip: &RoundTrip) -> Result<(), String> {
+    if let Some(err) = &trip.sell_error {
+        return Err(format!(
+            "PLIMSOLL HONEYPOT: token {} cannot be sold back — the sell reverts ({})",
+            trip.token, err
+        ));
+    }
+    if trip.buy_tax_pct >= 100.0 {
+        return Err(format!(
+            "PLIMSOLL HONEYPOT: buying token {} delivers no tokens",
+            trip.token
+        ));
+    }
+    let worst = trip.buy_tax_pct.max(trip.sell_tax_pct);
+    if worst > config.honeypot_max_tax_pct {
+        return Err(format!(
+            "PLIMSOLL HONEYPOT: token {} taxes {:.1}% on buy and {:.1}% on sell (max {:.1}%)",
+            trip.token, trip.buy_tax_pct, trip.sell_tax_pct, config.honeypot_max_tax_pct
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTER: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const TOKEN: &str = "0x1111111111111111111111111111111111111111";
+    const AGENT: &str = "0x2222222222222222222222222222222222222222";
+
+    fn swap_eth_for_tokens() -> Vec<u8> {
+        let mut data = SWAP_EXACT_ETH_FOR_TOKENS.to_vec();
+        data.extend_from_slice(&abi::encode_u256(U256::ZERO));
+        data.extend_from_slice(&abi::encode_u256(U256::from(0x80)));
+        data.extend_from_slice(&abi::encode_address(AGENT).unwrap());
+        data.extend_from_slice(&abi::encode_u256(U256::MAX));
+        encode_path(&mut data, &[WETH.to_string(), TOKEN.to_string()]).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_decode_swap_eth_for_tokens() {
+        let swap = decode_swap(ROUTER, 1000, &swap_eth_for_tokens()).unwrap().unwrap();
+        assert_eq!(swap.amount_in, U256::from(1000));
+        assert_eq!(swap.token(), TOKEN);
+        assert_eq!(swap.recipient, AGENT);
+        assert_eq!(decode_swap(ROUTER, 0, &[0xa9, 0x05, 0x9c, 0xbb]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_sell_calldata_roundtrips_through_decoder() {
+        let path = vec![TOKEN.to_string(), WETH.to_string()];
+        let data = sell(U256::from(5), &path, AGENT).unwrap();
+        let swap = decode_swap(ROUTER, 0, &data).unwrap().unwrap();
+        assert_eq!(swap.amount_in, U256::from(5));
+        assert_eq!(swap.path, path);
+    }
+
+    #[test]
+    fn test_check_taxes_and_reverts() {
+        let config = Config::default();
+        let trip = |buy, sell, err: Option<&str>| RoundTrip {
+            token: TOKEN.into(),
+            buy_tax_pct: buy,
+            sell_tax_pct: sell,
+            sell_error: err.map(str::to_string),
+        };
+        assert!(check(&config, &trip(3.0, 3.0, None)).is_ok());
+        assert!(check(&config, &trip(0.0, 50.0, None)).unwrap_err().contains("50.0%"));
+        assert!(check(&config, &trip(0.0, 100.0, Some("TRANSFER_FAILED"))).unwrap_err().contains("reverts"));
+        assert!(check(&config, &trip(100.0, 0.0, None)).is_err());
+    }
+
+    #[test]
+    fn test_tax_pct() {
+        assert_eq!(tax_pct(U256::from(90), U256::from(100)), 10.0);
+        assert_eq!(tax_pct(U256::from(120), U256::from(100)), 0.0);
+        assert_eq!(tax_pct(U256::ZERO, U256::ZERO), 0.0);
+        assert!(Config::default().is_known_token(WETH));
+    }
+}
@@ -0,0 +1,139 @@
+//! Proxy identity key for signed IOC uplinks.
+//!
+//! Each proxy holds an ed25519 key (`proxy_identity_key_path`, created on
+//! first start). Its public key is logged at startup and shown in
+//! `/admin/status`; the operator registers it with Plimsoll Cloud. Every
+//! uplinked IOC travels as
+//!
+//! ```text
+//! {"payload": "<IOC JSON>", "reporter": "<public key hex>", "signature": "<hex>"}
+//! ```
+//!
+//! where `signature` is over `IOC_SIGNING_DOMAIN ‖ payload`. The payload
+//! is kept as the exact signed string, so the Cloud — and anyone it
+//! redistributes the IOC to — can verify it and attribute and weight
+//! intel by reporter.
+//!
+//! Without a key path the key is generated per process and can't be
+//! registered; submissions are then signed but unattributable. The same
+//! key signs the audit log's checkpoints (`audit`), which is why the log
+//! requires a key path.
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+/// Prefix of every signed IOC message.
+pub const IOC_SIGNING_DOMAIN: &[u8] = b"plimsoll-ioc:v1:";
+
+static IDENTITY: OnceLock<SigningKey> = OnceLock::new();
+
+/// An IOC signed by this proxy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedPayload {
+    pub payload: String,
+    pub reporter: String,
+    pub signature: String,
+}
+
+fn generate() -> SigningKey {
+    SigningKey::from_bytes(&rand::random::<[u8; 32]>())
+}
+
+/// Read the key at `path`, creating it (owner-only) if missing.
+pub fn load_or_create(path: &str) -> Result<SigningKey> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => {
+            let seed: [u8; 32] = hex::decode(text.trim())
+                .ok()
+                .and_then(|b| b.try_into().ok())
+                .with_context(|| format!("{path} is not a hex ed25519 seed"))?;
+            Ok(SigningKey::from_bytes(&seed))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key = generate();
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let mut file = options
+                .open(path)
+                .with_context(|| format!("Failed to create proxy identity key {path}"))?;
+            std::io::Write::write_all(&mut file, hex::encode(key.to_bytes()).as_bytes())?;
+            info!("Proxy identity key created at {path}");
+            Ok(key)
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to read proxy identity key {path}")),
+    }
+}
+
+/// Load the identity at startup. Returns the public key.
+pub fn init(config: &Config) -> Result<String> {
+    let key = if config.proxy_identity_key_path.is_empty() {
+        warn!("No proxy_identity_key_path — using an ephemeral identity that can't be registered");
+        generate()
+    } else {
+        load_or_create(&config.proxy_identity_key_path)?
+    };
+    if IDENTITY.set(key).is_err() {
+        anyhow::bail!("Proxy identity already initialized");
+    }
+    Ok(public_key())
+}
+
+fn key() -> &'static SigningKey {
+    IDENTITY.get_or_init(generate)
+}
+
+/// This proxy's public key, hex.
+pub fn public_key() -> String {
+    hex::encode(key().verifying_key().to_bytes())
+}
+
+/// Sign `domain ‖ message` with this proxy's key. Returns the signature, hex.
+pub fn sign(domain: &[u8], message: &[u8]) -> String {
+    hex::encode(key().sign(&[domain, message].concat()).to_bytes())
+}
+
+/// Sign `payload` as an IOC of this proxy.
+pub fn sign_ioc(payload: String) -> SignedPayload {
+    SignedPayload {
+        signature: sign(IOC_SIGNING_DOMAIN, payload.as_bytes()),
+        reporter: public_key(),
+        payload,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    #[test]
+    fn test_signed_ioc_verifies() {
+        let signed = sign_ioc(r#"{"target_address":"0xdrainer"}"#.into());
+        let reporter: [u8; 32] = hex::decode(&signed.reporter).unwrap().try_into().unwrap();
+        let signature: [u8; 64] = hex::decode(&signed.signature).unwrap().try_into().unwrap();
+        let reporter = VerifyingKey::from_bytes(&reporter).unwrap();
+        let message = [IOC_SIGNING_DOMAIN, signed.payload.as_bytes()].concat();
+        assert!(reporter.verify(&message, &Signature::from_bytes(&signature)).is_ok());
+        // A tampered payload doesn't.
+        let tampered = [IOC_SIGNING_DOMAIN, br#"{"target_address":"0xvictim"}"#.as_slice()].concat();
+        assert!(reporter.verify(&tampered, &Signature::from_bytes(&signature)).is_err());
+    }
+
+    #[test]
+    fn test_key_persists() {
+        let path = std::env::temp_dir().join(format!("plimsoll-identity-{}.key", std::process::id()));
+        let path = path.to_str().unwrap();
+        let created = load_or_create(path).unwrap();
+        let loaded = load_or_create(path).unwrap();
+        assert_eq!(created.to_bytes(), loaded.to_bytes());
+        std::fs::write(path, "not hex").unwrap();
+        assert!(load_or_create(path).is_err());
+        let _ = std::fs::remove_file(path);
+    }
+}
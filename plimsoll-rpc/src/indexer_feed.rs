@@ -0,0 +1,172 @@
+//! Off-chain interventions streamed to the fleet indexer.
+//!
+//! A block in the proxy never reaches the chain, so the dashboard — fed by
+//! the indexer's chain listeners — would not know about it. With
+//! `indexer_events` on, the security events below are posted to
+//! `POST {indexer_url}/events/proxy` (bearer `indexer_ingest_token`) and
+//! land in the indexer's `EventProcessor` next to on-chain vault events:
+//!
+//! | Security event                               | Indexer event type   |
+//! |----------------------------------------------|----------------------|
+//! | `blocked` by a signing engine (raw, EIP-712) | `SignatureBlocked`   |
+//! | `blocked` by any other engine                | `BlockedTransaction` |
+//! | `paymaster_severed`                          | `PaymasterSevered`   |
+//!
+//! A block is reported on the chain it was served on, with the send's
+//! agent and target when the request was one.
+//!
+//! Events ride the notifier queue (see `notify`): delivery is best effort
+//! and never delays a request.
+
+use crate::block_codes;
+use crate::config::Config;
+use crate::notify::SecurityEvent;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// Engines that judge signature requests rather than transactions.
+const SIGNATURE_ENGINES: &[&str] = &[
+    "raw_sign",
+    "eip712_chain_id",
+    "eip712_deadline",
+    "eip712_phishing",
+    "eip712_permit",
+    "ens",
+    "permit2_details",
+    "seaport",
+];
+
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(1);
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Unique id of the next event: synthetic tx hashes repeat for a repeated
+/// reason, so the indexer deduplicates on this instead. A random instance
+/// id keeps replicas started in the same second apart.
+fn next_id() -> String {
+    static INSTANCE: OnceLock<u64> = OnceLock::new();
+    let instance = *INSTANCE.get_or_init(rand::random);
+    format!("{:016x}-{}", instance, NEXT_SEQ.fetch_add(1, Ordering::Relaxed))
+}
+
+/// The indexer event type for `event`, if it is streamed.
+fn event_type(event: &SecurityEvent) -> Option<&'static str> {
+    match event {
+        SecurityEvent::Blocked { engine, .. } if SIGNATURE_ENGINES.contains(&engine.as_str()) => {
+            Some("SignatureBlocked")
+        }
+        SecurityEvent::Blocked { .. } => Some("BlockedTransaction"),
+        SecurityEvent::PaymasterSevered { .. } => Some("PaymasterSevered"),
+        SecurityEvent::SessionRevoked { .. } => None,
+    }
+}
+
+/// The `/events/proxy` body for `event`.
+fn body(config: &Config, event: &SecurityEvent, id: String) -> Option<Value> {
+    let mut body = json!({
+        "id": id,
+        "event_type": event_type(event)?,
+        "chain_id": config.default_chain_id(),
+        "at": now(),
+    });
+    match event {
+        SecurityEvent::Blocked { tx_hash, engine, reason, chain, agent, target } => {
+            body["chain_id"] = json!(chain.unwrap_or(config.default_chain_id()));
+            body["agent"] = json!(agent);
+            body["target"] = json!(target);
+            body["tx_hash"] = json!(tx_hash);
+            body["engine"] = json!(engine);
+            body["code"] = json!(block_codes::of(engine).code);
+            body["reason"] = json!(reason);
+        }
+        SecurityEvent::PaymasterSevered { strikes } => {
+            body["reason"] = json!(format!("Paymaster severed after {strikes} reverts"));
+            body["strikes"] = json!(strikes);
+        }
+        SecurityEvent::SessionRevoked { .. } => {}
+    }
+    Some(body)
+}
+
+/// Post `event` to the indexer when `indexer_events` is on.
+pub async fn forward(client: &reqwest::Client, config: &Config, event: &SecurityEvent) {
+    if !config.indexer_events {
+        return;
+    }
+    let Some(body) = body(config, event, next_id()) else {
+        return;
+    };
+    let url = format!("{}/events/proxy", config.indexer_url.trim_end_matches('/'));
+    let sent = client
+        .post(&url)
+        .bearer_auth(&config.indexer_ingest_token)
+        .json(&body)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    match sent {
+        Ok(_) => debug!(event = event.name(), "Security event streamed to the indexer"),
+        Err(e) => warn!(event = event.name(), error = %e, "Streaming security event to the indexer failed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_types() {
+        let blocked = |engine: &str| SecurityEvent::Blocked {
+            tx_hash: "0xabc".into(),
+            engine: engine.into(),
+            reason: "PLIMSOLL TEST".into(),
+            chain: None,
+            agent: String::new(),
+            target: String::new(),
+        };
+        assert_eq!(event_type(&blocked("raw_sign")), Some("SignatureBlocked"));
+        assert_eq!(event_type(&blocked("eip712_permit")), Some("SignatureBlocked"));
+        assert_eq!(event_type(&blocked("physics")), Some("BlockedTransaction"));
+        assert_eq!(event_type(&SecurityEvent::PaymasterSevered { strikes: 3 }), Some("PaymasterSevered"));
+        let revoked = SecurityEvent::SessionRevoked { session_key: "0xkey".into(), pending: false };
+        assert_eq!(event_type(&revoked), None);
+    }
+
+    #[test]
+    fn test_body() {
+        let config = Config::default();
+        let event = SecurityEvent::Blocked {
+            tx_hash: "0xabc".into(),
+            engine: "physics".into(),
+            reason: "PLIMSOLL PHYSICS: loss 40%".into(),
+            chain: None,
+            agent: "0xagent".into(),
+            target: "0xtarget".into(),
+        };
+        let body = body(&config, &event, "1-1".into()).unwrap();
+        assert_eq!(body["id"], "1-1");
+        assert_eq!(body["event_type"], "BlockedTransaction");
+        assert_eq!(body["chain_id"], config.default_chain_id());
+        assert_eq!(body["code"], 4902);
+        assert_eq!(body["agent"], "0xagent");
+        assert_eq!(body["target"], "0xtarget");
+        assert_ne!(next_id(), next_id());
+
+        // A block on another chain is reported on that chain.
+        let event = SecurityEvent::Blocked {
+            tx_hash: "8453:0xabc".into(),
+            engine: "physics".into(),
+            reason: "PLIMSOLL PHYSICS: loss 40%".into(),
+            chain: Some(8453),
+            agent: "0xagent".into(),
+            target: "0xtarget".into(),
+        };
+        assert_eq!(super::body(&config, &event, "1-2".into()).unwrap()["chain_id"], 8453);
+    }
+}
@@ -1,20 +1,28 @@
 //! Patch 2 (v1.0.2): Schrödinger's State — Opcode-Level Non-Determinism Detector.
 //!
 //! Contracts that use environmental opcodes (BLOCKHASH, COINBASE, TIMESTAMP,
-//! NUMBER, PREVRANDAO, GASLIMIT, BASEFEE, GAS) in conditional branches (JUMPI)
+//! NUMBER, PREVRANDAO, GASLIMIT, BASEFEE) in conditional branches (JUMPI)
 //! produce different execution paths at simulation time vs on-chain execution.
 //!
 //! This inspector implements a taint-tracking system that mirrors the EVM stack:
 //! 1. When an ENV opcode pushes a value onto the stack, mark it as "tainted"
 //! 2. When DUP/SWAP operations move values, propagate taint accordingly
 //! 3. When arithmetic ops combine values, taint the result if any input is tainted
-//! 4. When JUMPI reads its condition (stack[1]), check if it's tainted
-//!    → if so, flag `non_deterministic_jumpi = true`
+//! 4. When JUMPI reads its condition (the second stack item, below the
+//!    destination), check if it's tainted → if so, flag
+//!    `non_deterministic_jumpi = true`
 //!
-//! This is designed for revm v17's Inspector trait using the builder pattern:
-//! `Evm::builder().with_external_context(&mut inspector)
+//! Every other opcode pops its inputs and pushes clean outputs (per revm's
+//! opcode table). Each call frame has its own taint stack. Taint is tracked
+//! on the stack only: a value stored to memory or storage and loaded back
+//! comes back clean.
+//!
+//! Hooked into revm v17 with the builder pattern:
+//! `Evm::builder().with_external_context(NonDeterminismInspector::new())
 //!     .append_handler_register(inspector_handle_register)`
 
+use revm::interpreter::{opcode, Interpreter, OpCode};
+use revm::{Database, EvmContext, Inspector};
 use tracing::warn;
 
 /// Environmental opcodes that produce values dependent on execution context.
@@ -27,7 +35,6 @@ const ENV_OPCODES: &[u8] = &[
     0x44, // PREVRANDAO (was DIFFICULTY pre-merge)
     0x45, // GASLIMIT
     0x48, // BASEFEE
-    0x5a, // GAS
 ];
 
 /// Arithmetic/logic opcodes that propagate taint (if any input is tainted,
@@ -66,6 +73,12 @@ pub struct NonDeterminismInspector {
     /// Taint stack mirroring the EVM stack. `true` = value came from ENV opcode.
     taint_stack: Vec<bool>,
 
+    /// Taint stacks of the calling frames, innermost last.
+    parent_stacks: Vec<Vec<bool>>,
+
+    /// Call depth `taint_stack` belongs to.
+    depth: usize,
+
     /// Whether a non-deterministic JUMPI was detected.
     pub non_deterministic_jumpi: bool,
 
@@ -84,136 +97,86 @@ impl NonDeterminismInspector {
     /// Reset the inspector for a new transaction.
     pub fn reset(&mut self) {
         self.taint_stack.clear();
+        self.parent_stacks.clear();
+        self.depth = 0;
         self.non_deterministic_jumpi = false;
         self.detection_details.clear();
         self.jumpi_pc = None;
     }
 
-    /// Process a single opcode step. Called by the revm Inspector trait.
-    ///
-    /// In production, this hooks into `Inspector::step()` which receives
-    /// the current opcode and stack state from the EVM interpreter.
-    pub fn process_opcode(&mut self, opcode: u8, stack_len: usize) {
-        // Sync taint stack size with actual EVM stack
-        while self.taint_stack.len() < stack_len {
-            self.taint_stack.push(false);
-        }
-        while self.taint_stack.len() > stack_len {
-            self.taint_stack.pop();
+    /// Switch to the taint stack of the frame at call `depth`: a deeper
+    /// frame starts clean, returning discards the callee's stack.
+    fn enter_depth(&mut self, depth: usize) {
+        while self.depth < depth {
+            self.parent_stacks.push(std::mem::take(&mut self.taint_stack));
+            self.depth += 1;
         }
-
-        // ── ENV opcodes: mark TOS as tainted ─────────────────────
-        if ENV_OPCODES.contains(&opcode) {
-            // ENV opcodes push one value onto the stack
-            self.taint_stack.push(true);
-            let opname = opcode_name(opcode);
-            self.detection_details.push(format!(
-                "ENV opcode {} (0x{:02x}) pushed tainted value",
-                opname, opcode
-            ));
-            return;
+        while self.depth > depth {
+            self.taint_stack = self.parent_stacks.pop().unwrap_or_default();
+            self.depth -= 1;
         }
+    }
 
-        // ── JUMPI (0x57): check if condition is tainted ──────────
-        if opcode == 0x57 {
-            // JUMPI pops 2: stack[-2] = dest, stack[-1] = condition
-            // The condition (stack[-1] = top of stack) determines the branch
-            if self.taint_stack.len() >= 2 {
-                let condition_tainted = self.taint_stack[self.taint_stack.len() - 1];
-                if condition_tainted {
+    /// Process a single opcode step, before it executes. `stack_len` is the
+    /// EVM stack height at that point.
+    pub fn process_opcode(&mut self, op: u8, stack_len: usize) {
+        // Sync taint stack size with actual EVM stack; anything the model
+        // missed is treated as clean.
+        self.taint_stack.resize(stack_len, false);
+        let len = stack_len;
+
+        match op {
+            // ── JUMPI: pops the destination, then the condition ──
+            opcode::JUMPI => {
+                if len >= 2 && self.taint_stack[len - 2] {
                     self.non_deterministic_jumpi = true;
-                    self.jumpi_pc = Some(stack_len); // approximate PC
+                    self.detection_details
+                        .push("JUMPI condition derived from an environmental opcode".to_string());
                     warn!(
                         "PATCH 2 (SCHRÖDINGER'S STATE): Non-deterministic JUMPI detected — \
                          branch condition depends on environmental opcode"
                     );
                 }
+                self.taint_stack.truncate(len.saturating_sub(2));
             }
-            // JUMPI pops 2 values
-            if self.taint_stack.len() >= 2 {
-                self.taint_stack.pop();
-                self.taint_stack.pop();
-            }
-            return;
-        }
 
-        // ── DUP1-DUP16 (0x80-0x8f): duplicate and propagate taint ─
-        if opcode >= 0x80 && opcode <= 0x8f {
-            let n = (opcode - 0x80 + 1) as usize;
-            if self.taint_stack.len() >= n {
-                let taint = self.taint_stack[self.taint_stack.len() - n];
+            // ── DUP1-DUP16: duplicate and propagate taint ────────
+            opcode::DUP1..=opcode::DUP16 => {
+                let n = (op - opcode::DUP1 + 1) as usize;
+                let taint = len >= n && self.taint_stack[len - n];
                 self.taint_stack.push(taint);
-            } else {
-                self.taint_stack.push(false);
             }
-            return;
-        }
-
-        // ── SWAP1-SWAP16 (0x90-0x9f): swap and propagate taint ──
-        if opcode >= 0x90 && opcode <= 0x9f {
-            let n = (opcode - 0x90 + 1) as usize;
-            let len = self.taint_stack.len();
-            if len > n {
-                self.taint_stack.swap(len - 1, len - 1 - n);
-            }
-            return;
-        }
 
-        // ── Arithmetic/logic: taint output if any input tainted ──
-        if TAINT_PROPAGATION_OPCODES.contains(&opcode) {
-            let (inputs, outputs) = opcode_io(opcode);
-            let mut any_tainted = false;
-
-            // Check if any input is tainted
-            for i in 0..inputs.min(self.taint_stack.len()) {
-                if self.taint_stack[self.taint_stack.len() - 1 - i] {
-                    any_tainted = true;
-                    break;
+            // ── SWAP1-SWAP16: swap and propagate taint ───────────
+            opcode::SWAP1..=opcode::SWAP16 => {
+                let n = (op - opcode::SWAP1 + 1) as usize;
+                if len > n {
+                    self.taint_stack.swap(len - 1, len - 1 - n);
                 }
             }
 
-            // Pop inputs
-            for _ in 0..inputs.min(self.taint_stack.len()) {
-                self.taint_stack.pop();
-            }
-
-            // Push outputs (tainted if any input was tainted)
-            for _ in 0..outputs {
-                self.taint_stack.push(any_tainted);
+            // ── Everything else: pop inputs, push outputs ────────
+            // ENV opcodes taint their output, arithmetic/logic passes
+            // taint through, anything else (loads, calls, …) is clean.
+            _ => {
+                let (inputs, outputs) = OpCode::new(op)
+                    .map_or((0, 0), |o| (o.inputs() as usize, o.outputs() as usize));
+                let inputs = inputs.min(len);
+                let tainted_input = self.taint_stack[len - inputs..].iter().any(|&t| t);
+                self.taint_stack.truncate(len - inputs);
+
+                let env = ENV_OPCODES.contains(&op);
+                if env {
+                    self.detection_details.push(format!(
+                        "ENV opcode {} (0x{:02x}) pushed tainted value",
+                        opcode_name(op),
+                        op
+                    ));
+                }
+                let tainted = env || (tainted_input && TAINT_PROPAGATION_OPCODES.contains(&op));
+                self.taint_stack.extend(std::iter::repeat_n(tainted, outputs));
             }
-            return;
-        }
-
-        // ── POP (0x50): remove top of stack ──────────────────────
-        if opcode == 0x50 {
-            self.taint_stack.pop();
-            return;
-        }
-
-        // ── PUSH1-PUSH32 (0x60-0x7f): push clean value ──────────
-        if opcode >= 0x60 && opcode <= 0x7f {
-            self.taint_stack.push(false);
-            return;
-        }
-
-        // ── JUMP (0x56): pop destination ─────────────────────────
-        if opcode == 0x56 {
-            self.taint_stack.pop();
-            return;
         }
-
-        // ── Memory/storage reads push clean values ───────────────
-        // MLOAD(0x51), SLOAD(0x54), CALLDATALOAD(0x35)
-        if opcode == 0x51 || opcode == 0x54 || opcode == 0x35 {
-            // These pop 1 (address/offset) and push 1 (value)
-            self.taint_stack.pop();
-            self.taint_stack.push(false);
-            return;
-        }
-
-        // ── Default: for unhandled opcodes, be conservative ──────
-        // Most other opcodes either don't affect the stack or
-        // have specific stack effects we handle above.
     }
 
     /// Check if the inspector detected any non-determinism.
@@ -222,6 +185,17 @@ impl NonDeterminismInspector {
     }
 }
 
+impl<DB: Database> Inspector<DB> for NonDeterminismInspector {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.enter_depth(context.journaled_state.depth() as usize);
+        let flagged = self.non_deterministic_jumpi;
+        self.process_opcode(interp.current_opcode(), interp.stack.len());
+        if !flagged && self.non_deterministic_jumpi {
+            self.jumpi_pc = Some(interp.program_counter());
+        }
+    }
+}
+
 /// Get a human-readable name for an opcode.
 fn opcode_name(opcode: u8) -> &'static str {
     match opcode {
@@ -232,24 +206,10 @@ fn opcode_name(opcode: u8) -> &'static str {
         0x44 => "PREVRANDAO",
         0x45 => "GASLIMIT",
         0x48 => "BASEFEE",
-        0x5a => "GAS",
         _ => "UNKNOWN",
     }
 }
 
-/// Get the number of (inputs, outputs) for an opcode.
-fn opcode_io(opcode: u8) -> (usize, usize) {
-    match opcode {
-        // Unary: 1 in, 1 out
-        0x15 | 0x19 => (1, 1), // ISZERO, NOT
-        // Binary: 2 in, 1 out
-        0x01..=0x07 | 0x0a | 0x0b | 0x10..=0x14 | 0x16..=0x18 | 0x1a..=0x1d => (2, 1),
-        // Ternary: 3 in, 1 out
-        0x08 | 0x09 => (3, 1), // ADDMOD, MULMOD
-        _ => (0, 0),
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -259,16 +219,16 @@ mod tests {
         assert!(ENV_OPCODES.contains(&0x42)); // TIMESTAMP
         assert!(ENV_OPCODES.contains(&0x43)); // NUMBER
         assert!(ENV_OPCODES.contains(&0x44)); // PREVRANDAO
-        assert!(ENV_OPCODES.contains(&0x5a)); // GAS
+        assert!(!ENV_OPCODES.contains(&0x5a)); // GAS varies with gas limit, not block
         assert!(!ENV_OPCODES.contains(&0x01)); // ADD is not ENV
     }
 
     #[test]
     fn test_clean_jumpi_no_flag() {
         let mut inspector = NonDeterminismInspector::new();
-        // PUSH1 (clean value)
+        // PUSH1 (clean condition)
         inspector.process_opcode(0x60, 0);
-        // PUSH1 (clean value)
+        // PUSH1 (clean destination)
         inspector.process_opcode(0x60, 1);
         // JUMPI — both values clean
         inspector.process_opcode(0x57, 2);
@@ -278,15 +238,26 @@ mod tests {
     #[test]
     fn test_tainted_jumpi_flags() {
         let mut inspector = NonDeterminismInspector::new();
-        // PUSH1 (clean destination)
-        inspector.process_opcode(0x60, 0);
         // TIMESTAMP (tainted condition)
-        inspector.process_opcode(0x42, 1);
+        inspector.process_opcode(0x42, 0);
+        // PUSH1 (clean destination)
+        inspector.process_opcode(0x60, 1);
         // JUMPI — condition is tainted!
         inspector.process_opcode(0x57, 2);
         assert!(inspector.is_non_deterministic());
     }
 
+    #[test]
+    fn test_tainted_destination_alone_does_not_flag() {
+        let mut inspector = NonDeterminismInspector::new();
+        // PUSH1 (clean condition)
+        inspector.process_opcode(0x60, 0);
+        // TIMESTAMP in the destination slot
+        inspector.process_opcode(0x42, 1);
+        inspector.process_opcode(0x57, 2);
+        assert!(!inspector.is_non_deterministic());
+    }
+
     #[test]
     fn test_taint_propagation_through_arithmetic() {
         let mut inspector = NonDeterminismInspector::new();
@@ -294,17 +265,27 @@ mod tests {
         inspector.process_opcode(0x60, 0);
         // NUMBER (tainted)
         inspector.process_opcode(0x43, 1);
-        // ADD: clean + tainted = tainted
-        inspector.process_opcode(0x01, 2);
+        // GT: clean vs tainted = tainted
+        inspector.process_opcode(0x11, 2);
         // PUSH1 (clean destination)
         inspector.process_opcode(0x60, 1);
-        // SWAP1 — swap dest and tainted result
-        inspector.process_opcode(0x90, 2);
-        // JUMPI — condition is the tainted ADD result
+        // JUMPI — condition is the tainted comparison
         inspector.process_opcode(0x57, 2);
         assert!(inspector.is_non_deterministic());
     }
 
+    #[test]
+    fn test_loads_and_blockhash_stack_effects() {
+        let mut inspector = NonDeterminismInspector::new();
+        // TIMESTAMP, SLOAD: storage read at a tainted key is a clean value
+        inspector.process_opcode(0x42, 0);
+        inspector.process_opcode(0x54, 1);
+        assert_eq!(inspector.taint_stack, vec![false]);
+        // BLOCKHASH pops its block number and pushes a tainted hash
+        inspector.process_opcode(0x40, 1);
+        assert_eq!(inspector.taint_stack, vec![true]);
+    }
+
     #[test]
     fn test_dup_propagates_taint() {
         let mut inspector = NonDeterminismInspector::new();
@@ -318,11 +299,23 @@ mod tests {
         assert!(inspector.taint_stack[1]);
     }
 
+    #[test]
+    fn test_call_frames_have_separate_stacks() {
+        let mut inspector = NonDeterminismInspector::new();
+        inspector.enter_depth(1);
+        inspector.process_opcode(0x42, 0); // TIMESTAMP in the caller
+        inspector.enter_depth(2);
+        assert!(inspector.taint_stack.is_empty());
+        inspector.process_opcode(0x60, 0); // callee: clean condition
+        inspector.enter_depth(1);
+        assert_eq!(inspector.taint_stack, vec![true]);
+    }
+
     #[test]
     fn test_reset_clears_state() {
         let mut inspector = NonDeterminismInspector::new();
-        inspector.process_opcode(0x60, 0); // PUSH (clean destination)
-        inspector.process_opcode(0x42, 1); // TIMESTAMP (tainted condition)
+        inspector.process_opcode(0x42, 0); // TIMESTAMP (tainted condition)
+        inspector.process_opcode(0x60, 1); // PUSH (clean destination)
         inspector.process_opcode(0x57, 2); // JUMPI — condition is tainted!
         assert!(inspector.is_non_deterministic());
 
@@ -337,6 +330,6 @@ mod tests {
         assert_eq!(opcode_name(0x42), "TIMESTAMP");
         assert_eq!(opcode_name(0x43), "NUMBER");
         assert_eq!(opcode_name(0x44), "PREVRANDAO");
-        assert_eq!(opcode_name(0x5a), "GAS");
+        assert_eq!(opcode_name(0x48), "BASEFEE");
     }
 }
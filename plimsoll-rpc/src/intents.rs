@@ -0,0 +1,369 @@
+//! Intent order (CowSwap / UniswapX) price sanity check.
+//!
+//! A signed order from a trusted protocol domain is still a blank cheque
+//! if it sells at a dump price. When `order_max_price_deviation_pct` is
+//! set, orders are decoded:
+//!
+//!   - CowSwap `Order`: `sellToken/sellAmount → buyToken/buyAmount`
+//!   - UniswapX `PermitWitnessTransferFrom` with a Dutch-order witness:
+//!     `input → outputs[]` (worst-case `endAmount`s)
+//!
+//! Both legs are valued with a reference price, and the order is blocked
+//! unless the signer receives the proceeds and the implied execution
+//! price is within the configured deviation from market. Unknown prices
+//! fail closed. A UniswapX order's Permit2 grant must also go to one of
+//! `uniswapx_reactors` (the reactor the witness names) for no more than
+//! the order's input.
+//!
+//! The check only ever adds a block: an order that passes still goes
+//! through the trusted-domain and type-graph checks like any other
+//! typed data.
+//!
+//! Reference prices come from the operator's static table first, then the
+//! optional HTTP price oracle (`GET {price_oracle_url}/{token}` returning
+//! `{"usd": 1.0, "decimals": 6}`), cached for [`PRICE_CACHE_TTL`].
+
+use crate::config::{self, Config, ReferencePrice};
+use crate::eip712::parse_integer;
+use alloy_primitives::U256;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long an oracle price is reused.
+const PRICE_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Oracle request timeout — this runs on the signing hot path.
+const PRICE_ORACLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+lazy_static! {
+    static ref PRICE_CACHE: Mutex<HashMap<String, (ReferencePrice, Instant)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// One leg of a swap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leg {
+    pub token: String,
+    pub amount: U256,
+}
+
+/// A decoded intent order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntentOrder {
+    pub protocol: &'static str,
+    pub sell: Leg,
+    /// Proceeds the signer is guaranteed (worst case).
+    pub buy: Vec<Leg>,
+    /// Recipients of the proceeds.
+    pub receivers: Vec<String>,
+    /// UniswapX: the Permit2 transfer the signature grants.
+    pub permit: Option<PermitGrant>,
+}
+
+/// The Permit2 `PermitWitnessTransferFrom` grant behind a UniswapX order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermitGrant {
+    pub spender: String,
+    pub permitted: Leg,
+    /// The witness's `info.reactor`, if present.
+    pub reactor: Option<String>,
+}
+
+fn str_field(v: &serde_json::Value, name: &str) -> Result<String, String> {
+    v.get(name)
+        .and_then(|x| x.as_str())
+        .map(|s| s.to_lowercase())
+        .ok_or_else(|| format!("order missing `{name}`"))
+}
+
+fn int_field(v: &serde_json::Value, name: &str) -> Result<U256, String> {
+    v.get(name)
+        .ok_or_else(|| format!("order missing `{name}`"))
+        .and_then(parse_integer)
+}
+
+/// Decode a CowSwap or UniswapX order. `Ok(None)` if the payload is
+/// neither.
+pub fn decode(typed_data: &serde_json::Value, signer: &str) -> Result<Option<IntentOrder>, String> {
+    let primary = typed_data.get("primaryType").and_then(|v| v.as_str()).unwrap_or("");
+    let Some(msg) = typed_data.get("message") else {
+        return Ok(None);
+    };
+
+    if primary == "Order" && msg.get("sellToken").is_some() {
+        // CowSwap: receiver 0x0 means "the owner".
+        let receiver = str_field(msg, "receiver").unwrap_or_else(|_| ZERO_ADDRESS.into());
+        let receiver = if receiver == ZERO_ADDRESS { signer.to_lowercase() } else { receiver };
+        return Ok(Some(IntentOrder {
+            protocol: "CowSwap",
+            sell: Leg {
+                token: str_field(msg, "sellToken")?,
+                amount: int_field(msg, "sellAmount")?.saturating_add(
+                    msg.get("feeAmount").map(parse_integer).transpose()?.unwrap_or_default(),
+                ),
+            },
+            buy: vec![Leg {
+                token: str_field(msg, "buyToken")?,
+                amount: int_field(msg, "buyAmount")?,
+            }],
+            receivers: vec![receiver],
+            permit: None,
+        }));
+    }
+
+    if primary == "PermitWitnessTransferFrom" {
+        let Some(witness) = msg.get("witness") else {
+            return Ok(None);
+        };
+        let (Some(input), Some(outputs)) = (
+            witness.get("input").or_else(|| witness.get("baseInput")),
+            witness
+                .get("outputs")
+                .or_else(|| witness.get("baseOutputs"))
+                .and_then(|o| o.as_array()),
+        ) else {
+            return Ok(None);
+        };
+        let sell = Leg {
+            token: str_field(input, "token")?,
+            // Worst case for the swapper: the larger input amount.
+            amount: int_field(input, "startAmount")?.max(int_field(input, "endAmount")?),
+        };
+        let mut buy = Vec::new();
+        let mut receivers = Vec::new();
+        for out in outputs {
+            buy.push(Leg {
+                token: str_field(out, "token")?,
+                // Worst case: the smaller (decayed) output amount.
+                amount: int_field(out, "startAmount")?.min(int_field(out, "endAmount")?),
+            });
+            receivers.push(str_field(out, "recipient")?);
+        }
+        let permitted = msg.get("permitted").ok_or("order missing `permitted`")?;
+        let permit = PermitGrant {
+            spender: str_field(msg, "spender")?,
+            permitted: Leg { token: str_field(permitted, "token")?, amount: int_field(permitted, "amount")? },
+            reactor: witness.get("info").and_then(|info| str_field(info, "reactor").ok()),
+        };
+        return Ok(Some(IntentOrder { protocol: "UniswapX", sell, buy, receivers, permit: Some(permit) }));
+    }
+
+    Ok(None)
+}
+
+/// Look up a reference price: static table, then cache, then oracle.
+pub async fn reference_price(config: &Config, token: &str) -> Option<ReferencePrice> {
+    let token = token.to_lowercase();
+    if let Some(p) = config
+        .reference_prices
+        .iter()
+        .find(|(t, _)| t.eq_ignore_ascii_case(&token))
+        .map(|(_, p)| p.clone())
+    {
+        return Some(p);
+    }
+    if config.price_oracle_url.is_empty() {
+        return None;
+    }
+    if let Ok(cache) = PRICE_CACHE.lock() {
+        if let Some((p, at)) = cache.get(&token) {
+            if at.elapsed() < PRICE_CACHE_TTL {
+                return Some(p.clone());
+            }
+        }
+    }
+
+    let url = format!("{}/{}", config.price_oracle_url.trim_end_matches('/'), token);
+    let price: ReferencePrice = reqwest::Client::new()
+        .get(&url)
+        .timeout(PRICE_ORACLE_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    if let Ok(mut cache) = PRICE_CACHE.lock() {
+        cache.insert(token, (price.clone(), Instant::now()));
+    }
+    Some(price)
+}
+
+/// USD value of `amount` base units.
+pub fn usd_value(amount: U256, price: &ReferencePrice) -> f64 {
+    // f64 precision is ample for a percentage comparison.
+    let units: f64 = amount.to_string().parse().unwrap_or(f64::INFINITY);
+    units / 10f64.powi(price.decimals as i32) * price.usd
+}
+
+/// The order's Permit2 grant must go to a known reactor — the one the
+/// witness names — for the order's own input and no more.
+fn check_permit(config: &Config, order: &IntentOrder, permit: &PermitGrant) -> Result<(), String> {
+    if !config::parse_list(&config.uniswapx_reactors).contains(&permit.spender) {
+        return Err(format!(
+            "PLIMSOLL INTENT ({}): Permit2 spender {} is not a known reactor",
+            order.protocol, permit.spender
+        ));
+    }
+    if permit.reactor.as_ref().is_some_and(|reactor| *reactor != permit.spender) {
+        return Err(format!(
+            "PLIMSOLL INTENT ({}): Permit2 spender {} is not the order's reactor {}",
+            order.protocol,
+            permit.spender,
+            permit.reactor.as_deref().unwrap_or_default()
+        ));
+    }
+    if permit.permitted.token != order.sell.token || permit.permitted.amount > order.sell.amount {
+        return Err(format!(
+            "PLIMSOLL INTENT ({}): Permit2 grants {} of {} but the order sells at most {} of {}",
+            order.protocol, permit.permitted.amount, permit.permitted.token, order.sell.amount, order.sell.token
+        ));
+    }
+    Ok(())
+}
+
+/// Pass the order only if the signer receives the proceeds, a UniswapX
+/// permit matches the order, and the execution price is within
+/// `order_max_price_deviation_pct` of market. Returns a summary on
+/// success.
+pub async fn check_order(config: &Config, order: &IntentOrder, signer: &str) -> Result<String, String> {
+    if let Some(r) = order.receivers.iter().find(|r| !r.eq_ignore_ascii_case(signer)) {
+        return Err(format!(
+            "PLIMSOLL INTENT ({}): proceeds go to {} instead of the signer {}",
+            order.protocol, r, signer
+        ));
+    }
+    if let Some(permit) = &order.permit {
+        check_permit(config, order, permit)?;
+    }
+
+    let sell_price = reference_price(config, &order.sell.token).await.ok_or_else(|| {
+        format!("PLIMSOLL INTENT ({}): no reference price for sell token {}", order.protocol, order.sell.token)
+    })?;
+    let sell_usd = usd_value(order.sell.amount, &sell_price);
+
+    let mut buy_usd = 0.0;
+    for leg in &order.buy {
+        let price = reference_price(config, &leg.token).await.ok_or_else(|| {
+            format!("PLIMSOLL INTENT ({}): no reference price for buy token {}", order.protocol, leg.token)
+        })?;
+        buy_usd += usd_value(leg.amount, &price);
+    }
+
+    let summary = format!(
+        "{}: sell {} of {} (${:.2}) for ${:.2}",
+        order.protocol, order.sell.amount, order.sell.token, sell_usd, buy_usd
+    );
+    if sell_usd <= 0.0 {
+        return Ok(summary);
+    }
+    let deviation_pct = (sell_usd - buy_usd) / sell_usd * 100.0;
+    if deviation_pct > config.order_max_price_deviation_pct {
+        return Err(format!(
+            "PLIMSOLL INTENT ({}): execution price is {:.2}% below market (max {}%) — {}",
+            order.protocol, deviation_pct, config.order_max_price_deviation_pct, summary
+        ));
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: &str = "0x1111111111111111111111111111111111111111";
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    fn config() -> Config {
+        let mut config = Config { order_max_price_deviation_pct: 3.0, ..Config::default() };
+        config.reference_prices.insert(USDC.into(), ReferencePrice { usd: 1.0, decimals: 6 });
+        config.reference_prices.insert(WETH.into(), ReferencePrice { usd: 3000.0, decimals: 18 });
+        config
+    }
+
+    fn cow(buy_amount: &str, receiver: &str) -> serde_json::Value {
+        serde_json::json!({
+            "primaryType": "Order",
+            "message": {
+                "sellToken": WETH, "buyToken": USDC, "receiver": receiver,
+                "sellAmount": "1000000000000000000", "buyAmount": buy_amount,
+                "feeAmount": "0", "validTo": 1_700_000_000u64, "kind": "sell"
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_fair_cowswap_order_allowed() {
+        let order = decode(&cow("2990000000", ZERO_ADDRESS), AGENT).unwrap().unwrap();
+        assert_eq!(order.receivers, vec![AGENT.to_string()]);
+        assert!(check_order(&config(), &order, AGENT).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_underpriced_order_blocked() {
+        let order = decode(&cow("1000000000", ZERO_ADDRESS), AGENT).unwrap().unwrap();
+        let err = check_order(&config(), &order, AGENT).await.unwrap_err();
+        assert!(err.contains("below market"));
+    }
+
+    #[tokio::test]
+    async fn test_foreign_receiver_blocked() {
+        let attacker = "0x6666666666666666666666666666666666666666";
+        let order = decode(&cow("3000000000", attacker), AGENT).unwrap().unwrap();
+        assert!(check_order(&config(), &order, AGENT).await.unwrap_err().contains("instead of the signer"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_price_fails_closed() {
+        let mut config = config();
+        config.reference_prices.clear();
+        let order = decode(&cow("3000000000", ZERO_ADDRESS), AGENT).unwrap().unwrap();
+        assert!(check_order(&config, &order, AGENT).await.is_err());
+    }
+
+    const REACTOR: &str = "0x00000011f84b9aa48e5f8aa8b9897600006289be";
+
+    fn uniswapx(spender: &str, permitted: &str) -> serde_json::Value {
+        serde_json::json!({
+            "primaryType": "PermitWitnessTransferFrom",
+            "message": {
+                "permitted": { "token": WETH, "amount": permitted },
+                "spender": spender,
+                "witness": {
+                    "info": { "reactor": REACTOR, "swapper": AGENT },
+                    "input": { "token": WETH, "startAmount": "1000000000000000000", "endAmount": "1000000000000000000" },
+                    "outputs": [{ "token": USDC, "startAmount": "3000000000", "endAmount": "2990000000", "recipient": AGENT }]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_decode_uniswapx_worst_case() {
+        let order = decode(&uniswapx(REACTOR, "1000000000000000000"), AGENT).unwrap().unwrap();
+        assert_eq!(order.protocol, "UniswapX");
+        assert_eq!(order.buy[0].amount, U256::from(2_990_000_000u64));
+        assert_eq!(order.permit.unwrap().spender, REACTOR);
+    }
+
+    #[tokio::test]
+    async fn test_uniswapx_permit_must_match_the_order() {
+        let config = config();
+        let fair = decode(&uniswapx(REACTOR, "1000000000000000000"), AGENT).unwrap().unwrap();
+        assert!(check_order(&config, &fair, AGENT).await.is_ok());
+
+        let attacker = "0x6666666666666666666666666666666666666666";
+        let stolen = decode(&uniswapx(attacker, "1000000000000000000"), AGENT).unwrap().unwrap();
+        assert!(check_order(&config, &stolen, AGENT).await.unwrap_err().contains("not a known reactor"));
+
+        let oversized = decode(&uniswapx(REACTOR, "5000000000000000000"), AGENT).unwrap().unwrap();
+        assert!(check_order(&config, &oversized, AGENT).await.unwrap_err().contains("sells at most"));
+    }
+}
@@ -0,0 +1,265 @@
+//! Local IOC store with STIX 2.1 export.
+//!
+//! With `ioc_store_path` set, every IOC this proxy extracts is kept in a
+//! SQLite database — whether or not it passes the uplink stake gates and
+//! in `telemetry_offline` mode too — so security teams can pull it into
+//! their SIEM / TIP without going through Plimsoll Cloud:
+//!
+//! | Route                              | Output                                        |
+//! |------------------------------------|-----------------------------------------------|
+//! | `GET /admin/iocs/stix`             | STIX 2.1 bundle                               |
+//! | `GET /admin/iocs/taxii/objects`    | TAXII 2.1 envelope (`more` / `next` paging)   |
+//!
+//! Both take `?added_after=<unix secs>&limit=<n>`; the TAXII route also
+//! takes `next=<cursor>`. Each IOC becomes an `indicator` created by the
+//! proxy's `identity`, with a pattern on the target address (custom
+//! `x-evm-address` object) and the engine, chain, selector and calldata
+//! hash as `x_plimsoll_*` properties. Object ids are name-based, so
+//! re-exports of the same IOC carry the same id.
+
+use crate::config::Config;
+use crate::identity;
+use crate::telemetry::IOCReport;
+use alloy_primitives::keccak256;
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use std::sync::{Mutex, OnceLock};
+use tracing::{info, warn};
+
+/// Most objects returned per export.
+pub const MAX_EXPORT: usize = 1000;
+
+/// TAXII 2.1 media type.
+pub const TAXII_MEDIA_TYPE: &str = "application/taxii+json;version=2.1";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS iocs (
+        seq         INTEGER PRIMARY KEY AUTOINCREMENT,
+        recorded_at INTEGER NOT NULL,
+        engine      TEXT NOT NULL,
+        target      TEXT NOT NULL,
+        chain_id    INTEGER NOT NULL,
+        ioc         TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS iocs_recorded_at ON iocs (recorded_at);
+";
+
+static STORE: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+fn init(conn: Connection) -> Result<Connection> {
+    conn.execute_batch(SCHEMA).context("Failed to initialize the IOC store")?;
+    Ok(conn)
+}
+
+/// Open the store at `ioc_store_path` (no-op when unset).
+pub fn open(config: &Config) -> Result<()> {
+    if config.ioc_store_path.is_empty() {
+        return Ok(());
+    }
+    let conn = Connection::open(&config.ioc_store_path)
+        .with_context(|| format!("Failed to open IOC store {}", config.ioc_store_path))?;
+    if STORE.set(Mutex::new(init(conn)?)).is_err() {
+        anyhow::bail!("IOC store already open");
+    }
+    info!("Local IOC store at {}", config.ioc_store_path);
+    Ok(())
+}
+
+fn insert(conn: &Connection, ioc: &IOCReport) -> Result<()> {
+    conn.execute(
+        "INSERT INTO iocs (recorded_at, engine, target, chain_id, ioc) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            ioc.timestamp as i64,
+            ioc.block_engine,
+            ioc.target_address.to_lowercase(),
+            ioc.chain_id as i64,
+            serde_json::to_string(ioc)?
+        ],
+    )?;
+    Ok(())
+}
+
+/// Keep `ioc`. A no-op when the store is off.
+pub fn record(ioc: &IOCReport) {
+    let Some(store) = STORE.get() else {
+        return;
+    };
+    let inserted = match store.lock() {
+        Ok(conn) => insert(&conn, ioc),
+        Err(_) => Err(anyhow::anyhow!("IOC store lock poisoned")),
+    };
+    if let Err(e) = inserted {
+        warn!(error = %e, "Failed to store IOC");
+    }
+}
+
+/// IOCs after `cursor` (a store sequence number) recorded at or after
+/// `added_after`, oldest first, with their sequence numbers.
+fn query(conn: &Connection, added_after: u64, cursor: i64, limit: usize) -> Result<Vec<(i64, IOCReport)>> {
+    let mut stmt = conn.prepare(
+        "SELECT seq, ioc FROM iocs WHERE recorded_at >= ?1 AND seq > ?2 ORDER BY seq LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(
+        rusqlite::params![added_after as i64, cursor, limit.min(MAX_EXPORT) as i64],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+    )?;
+    let mut out = Vec::new();
+    for row in rows {
+        let (seq, ioc) = row?;
+        out.push((seq, serde_json::from_str(&ioc).context("Invalid stored IOC")?));
+    }
+    Ok(out)
+}
+
+/// Stored IOCs, as in `query`.
+pub fn load(added_after: u64, cursor: i64, limit: usize) -> Result<Vec<(i64, IOCReport)>> {
+    let store = STORE.get().context("IOC store is not configured (ioc_store_path)")?;
+    let conn = store.lock().map_err(|_| anyhow::anyhow!("IOC store lock poisoned"))?;
+    query(&conn, added_after, cursor, limit)
+}
+
+/// Name-based (version 5 layout) UUID of `name`.
+fn uuid(name: &str) -> String {
+    let mut b = keccak256(name.as_bytes()).0;
+    b[6] = (b[6] & 0x0f) | 0x50;
+    b[8] = (b[8] & 0x3f) | 0x80;
+    let h = hex::encode(&b[..16]);
+    format!("{}-{}-{}-{}-{}", &h[..8], &h[8..12], &h[12..16], &h[16..20], &h[20..32])
+}
+
+fn timestamp(secs: u64) -> String {
+    chrono::DateTime::from_timestamp(secs as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// The STIX `identity` of this proxy.
+pub fn producer() -> Value {
+    let key = identity::public_key();
+    json!({
+        "type": "identity",
+        "spec_version": "2.1",
+        "id": format!("identity--{}", uuid(&format!("plimsoll-proxy:{key}"))),
+        "created": timestamp(0),
+        "modified": timestamp(0),
+        "name": format!("Plimsoll proxy {}", &key[..16]),
+        "identity_class": "system",
+        "x_plimsoll_public_key": key,
+    })
+}
+
+/// A STIX `indicator` for `ioc`.
+pub fn indicator(ioc: &IOCReport, created_by: &str) -> Value {
+    let target = ioc.target_address.to_lowercase();
+    let id = uuid(&format!("{}:{}:{}:{}:{}", ioc.agent_id, target, ioc.calldata_hash, ioc.block_engine, ioc.timestamp));
+    let at = timestamp(ioc.timestamp);
+    let mut indicator = json!({
+        "type": "indicator",
+        "spec_version": "2.1",
+        "id": format!("indicator--{id}"),
+        "created_by_ref": created_by,
+        "created": at,
+        "modified": at,
+        "valid_from": at,
+        "name": format!("Plimsoll {} block of {}", ioc.block_engine, target),
+        "description": ioc.block_reason,
+        "indicator_types": ["malicious-activity"],
+        "pattern": format!("[x-evm-address:value = '{}' AND x-evm-address:chain_id = {}]", target, ioc.chain_id),
+        "pattern_type": "stix",
+        "x_plimsoll_engine": ioc.block_engine,
+        "x_plimsoll_chain_id": ioc.chain_id,
+        "x_plimsoll_selector": ioc.calldata_selector,
+        "x_plimsoll_calldata_hash": ioc.calldata_hash,
+    });
+    if !ioc.calldata_function.is_empty() {
+        indicator["x_plimsoll_function"] = json!(ioc.calldata_function);
+    }
+    if let Some(source) = &ioc.threat_source {
+        indicator["x_plimsoll_threat_source"] = json!(source);
+    }
+    indicator
+}
+
+/// STIX objects for `iocs`: the producer identity, then one indicator each.
+pub fn stix_objects(iocs: &[IOCReport]) -> Vec<Value> {
+    let producer = producer();
+    let created_by = producer["id"].as_str().unwrap_or_default().to_string();
+    std::iter::once(producer)
+        .chain(iocs.iter().map(|ioc| indicator(ioc, &created_by)))
+        .collect()
+}
+
+/// A STIX 2.1 bundle of `iocs`.
+pub fn bundle(iocs: &[IOCReport]) -> Value {
+    let objects = stix_objects(iocs);
+    let name: String = objects.iter().filter_map(|o| o["id"].as_str()).collect();
+    json!({
+        "type": "bundle",
+        "id": format!("bundle--{}", uuid(&name)),
+        "objects": objects,
+    })
+}
+
+/// A TAXII 2.1 envelope of a page; `next` is the cursor of the next page.
+pub fn envelope(page: &[(i64, IOCReport)], limit: usize) -> Value {
+    let iocs: Vec<IOCReport> = page.iter().map(|(_, ioc)| ioc.clone()).collect();
+    let more = page.len() >= limit.min(MAX_EXPORT);
+    let mut envelope = json!({ "more": more, "objects": stix_objects(&iocs) });
+    if let (true, Some((seq, _))) = (more, page.last()) {
+        envelope["next"] = json!(seq.to_string());
+    }
+    envelope
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry;
+
+    fn ioc(target: &str, at: u64) -> IOCReport {
+        let mut ioc = telemetry::extract_ioc("0xagent", target, &[0xde, 0xad, 0xbe, 0xef], "metamorphic", "blocked", None, 8453);
+        ioc.timestamp = at;
+        ioc
+    }
+
+    #[test]
+    fn test_store_pages_in_order() {
+        let conn = init(Connection::open_in_memory().unwrap()).unwrap();
+        for i in 0..5 {
+            insert(&conn, &ioc(&format!("0x{i:040x}"), 1_700_000_000 + i)).unwrap();
+        }
+        let page = query(&conn, 0, 0, 2).unwrap();
+        assert_eq!(page.len(), 2);
+        let rest = query(&conn, 0, page[1].0, 10).unwrap();
+        assert_eq!(rest.len(), 3);
+        assert_eq!(query(&conn, 1_700_000_004, 0, 10).unwrap().len(), 1);
+
+        let envelope = envelope(&page, 2);
+        assert_eq!(envelope["more"], true);
+        assert_eq!(envelope["next"], page[1].0.to_string());
+        assert_eq!(envelope["objects"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_stix_indicator_shape() {
+        let reported = ioc("0x6666666666666666666666666666666666666666", 1_700_000_000);
+        let bundle = bundle(std::slice::from_ref(&reported));
+        assert_eq!(bundle["type"], "bundle");
+        let objects = bundle["objects"].as_array().unwrap();
+        assert_eq!(objects[0]["type"], "identity");
+        let found = &objects[1];
+        assert_eq!(found["created_by_ref"], objects[0]["id"]);
+        assert_eq!(found["created"], "2023-11-14T22:13:20.000Z");
+        assert_eq!(
+            found["pattern"],
+            "[x-evm-address:value = '0x6666666666666666666666666666666666666666' AND x-evm-address:chain_id = 8453]"
+        );
+        // Stable ids with an RFC 4122 layout.
+        let id = found["id"].as_str().unwrap();
+        assert_eq!(id, indicator(&reported, "identity--x")["id"].as_str().unwrap());
+        let uuid = id.strip_prefix("indicator--").unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "5");
+    }
+}
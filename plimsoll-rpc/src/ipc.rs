@@ -0,0 +1,148 @@
+//! Unix domain socket listener.
+//!
+//! An agent on the same host can reach the proxy without a network port,
+//! the way geth serves `--ipcpath`: set `ipc_path` (or pass `--ipcpath`)
+//! and the same router — JSON-RPC, admin, metrics — is served over HTTP on
+//! that socket as well as on TCP. `curl --unix-socket <path>` works.
+//!
+//! The socket is created owner-only (0600): it is bound inside a private
+//! (0700) directory next to `ipc_path`, restricted, then renamed into
+//! place, so it is never reachable with looser permissions. A stale socket
+//! left by a previous run is replaced, any other file at the path is an
+//! error. On shutdown the listener stops accepting and open connections
+//! finish their in-flight request and close; shutdown waits for them
+//! within the grace period.
+
+use crate::shutdown;
+use anyhow::{bail, Context, Result};
+use axum::Router;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::Path;
+use tokio::net::UnixListener;
+use tower::Service;
+use tracing::{debug, info, warn};
+
+/// Remove a stale socket at `path`; refuse to touch anything else.
+fn clear_stale_socket(path: &Path) -> Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => {
+            fs::remove_file(path).with_context(|| format!("removing stale socket {}", path.display()))
+        }
+        Ok(_) => bail!("ipc_path {} exists and is not a socket", path.display()),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Bind an owner-only socket at `path`: bound and restricted inside a
+/// private directory, then renamed into place.
+fn bind_private(path: &Path) -> Result<UnixListener> {
+    let name = path.file_name().with_context(|| format!("ipc_path {} has no file name", path.display()))?;
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let private = parent.join(format!(".{}.{}", name.to_string_lossy(), std::process::id()));
+    let _ = fs::remove_dir_all(&private);
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private)
+        .with_context(|| format!("creating {}", private.display()))?;
+    let staged = private.join(name);
+    let bound = UnixListener::bind(&staged)
+        .with_context(|| format!("binding {}", path.display()))
+        .and_then(|listener| {
+            fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("restricting {}", path.display()))?;
+            fs::rename(&staged, path).with_context(|| format!("moving socket to {}", path.display()))?;
+            Ok(listener)
+        });
+    let _ = fs::remove_dir_all(&private);
+    bound
+}
+
+/// Bind `path` and serve `app` on it in the background.
+pub fn spawn_listener(path: &str, app: Router) -> Result<()> {
+    let path = Path::new(path);
+    clear_stale_socket(path)?;
+    let listener = bind_private(path)?;
+    info!("Listening on unix:{}", path.display());
+
+    shutdown::track(async move {
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = shutdown::signaled() => break,
+            };
+            let socket = match accepted {
+                Ok((socket, _)) => socket,
+                Err(e) => {
+                    warn!(error = %e, "IPC accept failed");
+                    continue;
+                }
+            };
+            let app = app.clone();
+            shutdown::track(async move {
+                let service = hyper::service::service_fn(move |request: axum::http::Request<Incoming>| {
+                    app.clone().call(request)
+                });
+                let builder = auto::Builder::new(TokioExecutor::new());
+                let connection = builder.serve_connection_with_upgrades(TokioIo::new(socket), service);
+                tokio::pin!(connection);
+                let served = tokio::select! {
+                    served = connection.as_mut() => served,
+                    _ = shutdown::signaled() => {
+                        // Finish the request in flight, then close.
+                        connection.as_mut().graceful_shutdown();
+                        connection.await
+                    }
+                };
+                if let Err(e) = served {
+                    debug!(error = %e, "IPC connection closed with an error");
+                }
+            });
+        }
+        info!("IPC listener closed");
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_stale_socket() {
+        let dir = std::env::temp_dir().join(format!("plimsoll-ipc-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let missing = dir.join("missing.ipc");
+        assert!(clear_stale_socket(&missing).is_ok());
+
+        let file = dir.join("plain.ipc");
+        fs::write(&file, b"not a socket").unwrap();
+        assert!(clear_stale_socket(&file).is_err());
+        assert!(file.exists());
+
+        let socket = dir.join("stale.ipc");
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        assert!(clear_stale_socket(&socket).is_ok());
+        assert!(!socket.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_socket_bound_owner_only() {
+        let dir = std::env::temp_dir().join(format!("plimsoll-ipc-bind-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("plimsoll.ipc");
+        let _listener = bind_private(&path).unwrap();
+        let meta = fs::symlink_metadata(&path).unwrap();
+        assert!(meta.file_type().is_socket());
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        // Only the socket is left behind.
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,319 @@
+//! L2-aware transaction handling (OP Stack and Arbitrum).
+//!
+//! Rollups charge for posting a transaction's data to L1 on top of L2
+//! execution, and that fee never shows up in a simulated balance. On a
+//! congested L1 it can dwarf the L2 cost, so it is looked up on-chain and
+//! charged to the simulation before physics runs:
+//!
+//!   - OP Stack — `GasPriceOracle.getL1Fee(bytes)` on the signed
+//!     transaction, or `getL1FeeUpperBound(uint256)` for an unsigned one,
+//!   - Arbitrum — `NodeInterface.gasEstimateL1Component(...)`, L1 gas
+//!     priced at the L2 base fee.
+//!
+//! Arbitrum also bills the L1 component as L2 gas: a receipt's `gasUsed`
+//! includes `gasUsedForL1`, which the gas anomaly check must not read as
+//! execution the simulation missed.
+//!
+//! OP Stack deposit transactions (type `0x7E`) are decoded so their call is
+//! vetted like any other; they carry no signature or nonce and are refused
+//! outright on chains that are not OP Stack.
+//!
+//! The chain is `expected_chain_id` (falling back to `chain_id`).
+
+use crate::abi;
+use crate::config::Config;
+use crate::simulator;
+use crate::types;
+use anyhow::Result;
+use ethers::types::{Address, H256, U256};
+use ethers::utils::rlp::{DecoderError, Rlp};
+
+/// EIP-2718 transaction type of an OP Stack deposit transaction.
+pub const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+/// OP Stack `GasPriceOracle` predeploy.
+const GAS_PRICE_ORACLE: &str = "0x420000000000000000000000000000000000000f";
+
+/// Arbitrum `NodeInterface` precompile (virtual, `eth_call` only).
+const NODE_INTERFACE: &str = "0x00000000000000000000000000000000000000c8";
+
+/// `getL1Fee(bytes)`
+const GET_L1_FEE: [u8; 4] = [0x49, 0x94, 0x8e, 0x0e];
+
+/// `getL1FeeUpperBound(uint256)` (Fjord and later)
+const GET_L1_FEE_UPPER_BOUND: [u8; 4] = [0xf1, 0xc7, 0xa5, 0x8b];
+
+/// `gasEstimateL1Component(address,bool,bytes)`
+const GAS_ESTIMATE_L1_COMPONENT: [u8; 4] = [0x77, 0xd4, 0x88, 0xa2];
+
+/// Generous size of an unsigned transaction's non-calldata fields, in bytes.
+const UNSIGNED_TX_OVERHEAD: u64 = 128;
+
+/// Number of RLP fields in a deposit transaction.
+const DEPOSIT_TX_FIELDS: usize = 8;
+
+const OP_STACK_CHAINS: &[u64] = &[
+    10,       // OP Mainnet
+    130,      // Unichain
+    252,      // Fraxtal
+    480,      // World Chain
+    8453,     // Base
+    34443,    // Mode
+    7777777,  // Zora
+    84532,    // Base Sepolia
+    11155420, // OP Sepolia
+];
+
+const ARBITRUM_CHAINS: &[u64] = &[
+    42161,  // Arbitrum One
+    42170,  // Arbitrum Nova
+    421614, // Arbitrum Sepolia
+];
+
+/// Rollup family of the configured chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rollup {
+    OpStack,
+    Arbitrum,
+}
+
+fn chain_id(config: &Config) -> u64 {
+    if config.expected_chain_id != 0 {
+        config.expected_chain_id
+    } else {
+        config.chain_id
+    }
+}
+
+/// The rollup the proxy fronts, `None` on L1 and unknown chains.
+pub fn rollup(config: &Config) -> Option<Rollup> {
+    let id = chain_id(config);
+    if OP_STACK_CHAINS.contains(&id) {
+        Some(Rollup::OpStack)
+    } else if ARBITRUM_CHAINS.contains(&id) {
+        Some(Rollup::Arbitrum)
+    } else {
+        None
+    }
+}
+
+/// A decoded OP Stack deposit transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepositTx {
+    pub source_hash: H256,
+    pub from: String,
+    /// `None` for a contract creation.
+    pub to: Option<String>,
+    pub mint: U256,
+    pub value: U256,
+    pub gas_limit: u64,
+    pub is_system_tx: bool,
+    pub data: Vec<u8>,
+}
+
+fn decode_deposit_body(rlp: &Rlp) -> Result<DepositTx, DecoderError> {
+    if rlp.item_count()? != DEPOSIT_TX_FIELDS {
+        return Err(DecoderError::RlpIncorrectListLen);
+    }
+    let from: Address = rlp.val_at(1)?;
+    let to = if rlp.at(2)?.is_empty() {
+        None
+    } else {
+        Some(format!("{:#x}", rlp.val_at::<Address>(2)?))
+    };
+    Ok(DepositTx {
+        source_hash: rlp.val_at(0)?,
+        from: format!("{:#x}", from),
+        to,
+        mint: rlp.val_at(3)?,
+        value: rlp.val_at(4)?,
+        gas_limit: rlp.val_at(5)?,
+        is_system_tx: rlp.val_at(6)?,
+        data: rlp.val_at(7)?,
+    })
+}
+
+/// Decode a raw transaction. `Ok(None)` if it is not a deposit.
+pub fn decode_deposit_raw(raw: &[u8]) -> Result<Option<DepositTx>, String> {
+    let Some((&DEPOSIT_TX_TYPE, body)) = raw.split_first() else {
+        return Ok(None);
+    };
+    decode_deposit_body(&Rlp::new(body))
+        .map(Some)
+        .map_err(|e| format!("undecodable deposit transaction: {e}"))
+}
+
+/// Decode a hex-encoded raw transaction (`eth_sendRawTransaction` param).
+pub fn decode_deposit_raw_hex(raw_hex: &str) -> Result<Option<DepositTx>, String> {
+    types::decode_raw_tx_hex(raw_hex, decode_deposit_raw)
+}
+
+/// Refuse a deposit transaction the configured chain cannot carry.
+pub fn check_deposit(config: &Config, tx: &DepositTx) -> Result<(), String> {
+    if rollup(config) != Some(Rollup::OpStack) {
+        return Err(format!(
+            "PLIMSOLL L2: deposit transaction (type 0x7e) from {} on chain {}, which is not an OP Stack chain",
+            tx.from,
+            chain_id(config)
+        ));
+    }
+    if tx.is_system_tx {
+        return Err(format!(
+            "PLIMSOLL L2: system deposit transaction from {} — system deposits are derived from L1 only",
+            tx.from
+        ));
+    }
+    Ok(())
+}
+
+/// ABI-encode a single dynamic `bytes` argument.
+fn encode_bytes(data: &[u8]) -> Vec<u8> {
+    let mut out = abi::encode_uint(32).to_vec();
+    out.extend_from_slice(&abi::encode_uint(data.len() as u64));
+    out.extend_from_slice(data);
+    out.resize(out.len() + (32 - data.len() % 32) % 32, 0);
+    out
+}
+
+/// Word `index` of a return value as a `u128`, saturating.
+fn uint_word(ret: &[u8], index: usize) -> Result<u128> {
+    let word = abi::uint(ret, index).map_err(anyhow::Error::msg)?;
+    Ok(u128::try_from(word).unwrap_or(u128::MAX))
+}
+
+/// OP Stack L1 data fee, in wei. `raw` is the signed transaction when the
+/// agent sent one; an unsigned send is priced at its upper bound.
+async fn op_stack_l1_fee(rpc_url: &str, data: &[u8], raw: Option<&[u8]>) -> Result<u128> {
+    if raw.is_none() {
+        let size = data.len() as u64 + UNSIGNED_TX_OVERHEAD;
+        let mut call = GET_L1_FEE_UPPER_BOUND.to_vec();
+        call.extend_from_slice(&abi::encode_uint(size));
+        match simulator::eth_call(rpc_url, GAS_PRICE_ORACLE, &call, "latest").await {
+            Ok(ret) => return uint_word(&ret, 0),
+            // Pre-Fjord oracles only price concrete payloads.
+            Err(e) => tracing::debug!(error = %e, "getL1FeeUpperBound unavailable — pricing calldata"),
+        }
+    }
+    let mut call = GET_L1_FEE.to_vec();
+    call.extend_from_slice(&encode_bytes(raw.unwrap_or(data)));
+    let ret = simulator::eth_call(rpc_url, GAS_PRICE_ORACLE, &call, "latest").await?;
+    uint_word(&ret, 0)
+}
+
+/// Arbitrum L1 component, in wei: L1 gas units priced at the L2 base fee.
+async fn arbitrum_l1_fee(rpc_url: &str, to: &str, data: &[u8]) -> Result<u128> {
+    let creation = to.is_empty();
+    let target = if creation { "0x0000000000000000000000000000000000000000" } else { to };
+    let mut call = GAS_ESTIMATE_L1_COMPONENT.to_vec();
+    call.extend_from_slice(&abi::encode_address(target).map_err(anyhow::Error::msg)?);
+    call.extend_from_slice(&abi::encode_uint(creation as u64));
+    // Dynamic `bytes` is the third head word: offset 0x60.
+    call.extend_from_slice(&abi::encode_uint(96));
+    call.extend_from_slice(&encode_bytes(data)[32..]);
+    let ret = simulator::eth_call(rpc_url, NODE_INTERFACE, &call, "latest").await?;
+    Ok(uint_word(&ret, 0)?.saturating_mul(uint_word(&ret, 1)?))
+}
+
+/// The L1 cost of a transaction on `rollup`, in wei.
+pub async fn l1_fee(rpc_url: &str, rollup: Rollup, to: &str, data: &[u8], raw: Option<&[u8]>) -> Result<u128> {
+    match rollup {
+        Rollup::OpStack => op_stack_l1_fee(rpc_url, data, raw).await,
+        Rollup::Arbitrum => arbitrum_l1_fee(rpc_url, to, data).await,
+    }
+}
+
+/// Gas a receipt spent on execution: Arbitrum bills its L1 component as
+/// L2 gas (`gasUsedForL1`), which the simulation never sees.
+pub fn execution_gas(config: &Config, receipt: &serde_json::Value, gas_used: u64) -> u64 {
+    if rollup(config) != Some(Rollup::Arbitrum) {
+        return gas_used;
+    }
+    let l1_gas = receipt
+        .get("gasUsedForL1")
+        .and_then(|v| v.as_str())
+        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+        .unwrap_or(0);
+    gas_used.saturating_sub(l1_gas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::utils::rlp::RlpStream;
+    use std::str::FromStr;
+
+    const FROM: &str = "0xa9e0000000000000000000000000000000000001";
+    const TARGET: &str = "0x1111111111111111111111111111111111111111";
+
+    fn deposit(to: Option<&str>, is_system_tx: bool) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(DEPOSIT_TX_FIELDS);
+        stream.append(&H256::repeat_byte(0x22));
+        stream.append(&Address::from_str(FROM).unwrap());
+        match to {
+            Some(to) => stream.append(&Address::from_str(to).unwrap()),
+            None => stream.append_empty_data(),
+        };
+        stream.append(&U256::zero());
+        stream.append(&U256::from(7u64));
+        stream.append(&100_000u64);
+        stream.append(&is_system_tx);
+        stream.append(&vec![0xa9u8, 0x05, 0x9c, 0xbb]);
+        let mut raw = vec![DEPOSIT_TX_TYPE];
+        raw.extend_from_slice(&stream.out());
+        raw
+    }
+
+    #[test]
+    fn test_rollup_keyed_off_expected_chain_id() {
+        assert_eq!(rollup(&Config::default()), None);
+        let base = Config { expected_chain_id: 8453, ..Config::default() };
+        assert_eq!(rollup(&base), Some(Rollup::OpStack));
+        let arbitrum = Config { chain_id: 42161, ..Config::default() };
+        assert_eq!(rollup(&arbitrum), Some(Rollup::Arbitrum));
+        let pinned = Config { expected_chain_id: 1, chain_id: 10, ..Config::default() };
+        assert_eq!(rollup(&pinned), None);
+    }
+
+    #[test]
+    fn test_deposit_decoded() {
+        let tx = decode_deposit_raw(&deposit(Some(TARGET), false)).unwrap().unwrap();
+        assert_eq!(tx.from, FROM);
+        assert_eq!(tx.to.as_deref(), Some(TARGET));
+        assert_eq!(tx.value, U256::from(7u64));
+        assert_eq!(tx.gas_limit, 100_000);
+        assert_eq!(tx.data, vec![0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(decode_deposit_raw(&deposit(None, false)).unwrap().unwrap().to, None);
+        assert!(decode_deposit_raw(&[0x02, 0xc0]).unwrap().is_none());
+        assert!(decode_deposit_raw(&[DEPOSIT_TX_TYPE, 0xc0]).unwrap_err().contains("undecodable"));
+    }
+
+    #[test]
+    fn test_deposit_only_on_op_stack() {
+        let tx = decode_deposit_raw(&deposit(Some(TARGET), false)).unwrap().unwrap();
+        let err = check_deposit(&Config::default(), &tx).unwrap_err();
+        assert!(err.contains("chain 1, which is not an OP Stack chain"));
+        let optimism = Config { expected_chain_id: 10, ..Config::default() };
+        assert!(check_deposit(&optimism, &tx).is_ok());
+        let system = decode_deposit_raw(&deposit(Some(TARGET), true)).unwrap().unwrap();
+        assert!(check_deposit(&optimism, &system).unwrap_err().contains("system deposit"));
+    }
+
+    #[test]
+    fn test_bytes_encoding_padded() {
+        let enc = encode_bytes(&[0xab; 33]);
+        assert_eq!(enc.len(), 32 + 32 + 64);
+        assert_eq!(enc[31], 0x20);
+        assert_eq!(enc[63], 33);
+        assert_eq!(enc[64 + 32], 0xab);
+        assert_eq!(enc[64 + 33], 0);
+        assert_eq!(encode_bytes(&[]).len(), 64);
+    }
+
+    #[test]
+    fn test_arbitrum_receipt_excludes_l1_gas() {
+        let receipt = serde_json::json!({ "gasUsed": "0x30d40", "gasUsedForL1": "0x186a0" });
+        let arbitrum = Config { expected_chain_id: 42161, ..Config::default() };
+        assert_eq!(execution_gas(&arbitrum, &receipt, 200_000), 100_000);
+        assert_eq!(execution_gas(&Config::default(), &receipt, 200_000), 200_000);
+    }
+}
@@ -0,0 +1,6 @@
+//! Library target for the modules benchmarked in `benches/`: the read-path
+//! sanitizer and the homoglyph folding it depends on. The binary uses them
+//! from here, so each is compiled (and tested) once.
+
+pub mod homoglyphs;
+pub mod sanitizer;
@@ -35,59 +35,180 @@
 //! Ethereum Mainnet (via private block builders)
 //! ```
 
+mod abi;
+mod abi_registry;
+mod admin;
+mod approval_diff;
+mod approvals;
+mod audit;
+mod auth;
+mod block_codes;
+mod chains;
+mod cluster;
 mod config;
+mod counterparties;
+mod cuckoo;
+mod delegatecall;
+mod drawdown;
+mod eip4844;
+mod eip712;
+mod eip7702;
+mod ens;
+mod erc3009;
+mod false_positives;
 mod fee;
+mod fee_ledger;
 mod flashbots;
+mod forwarder;
+mod gas_fees;
+mod gossip;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod health;
+mod honeypot;
 mod http_proxy;
+mod identity;
+mod indexer_feed;
 mod inspector;
+mod intents;
+#[cfg(unix)]
+mod ipc;
+mod ioc_store;
+mod l2;
+mod mcp;
+mod metamorphic;
+mod method_policy;
+mod metrics;
+mod multicall;
+mod nonces;
+mod notify;
+mod oracle;
+mod otel;
+mod pending;
+mod permit2;
+mod phishing;
+mod pinning;
+mod poisoning;
+mod portfolio;
+mod protection;
+mod proxy;
+mod quarantine;
+mod rate_limit;
+mod reentrancy;
+mod reload;
+mod replacement;
+mod reputation;
+mod rescue;
+mod response_meta;
+mod revocations;
 mod router;
 mod rpc;
-mod sanitizer;
+mod rugpull;
+mod safe;
+mod sanctions;
+mod seaport;
+mod selectors;
+mod session_scopes;
+mod shadow;
+mod shutdown;
 mod simulator;
+mod siwe;
+mod state_store;
 mod svm_simulator;
+mod target_allowlist;
 mod telemetry;
 mod threat_feed;
+mod tls;
 mod types;
 mod utxo_guard;
+mod vault_sync;
+mod velocity;
+mod verification;
+
+use plimsoll_rpc::{homoglyphs, sanitizer};
 
 use anyhow::Result;
-use tracing_subscriber::{fmt, EnvFilter};
+use std::future::IntoFuture;
+use std::path::PathBuf;
+
+/// Value of the command-line flag `name`, as `name <value>` or
+/// `name=<value>`.
+fn flag(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Config file path from `--config <path>` / `--config=<path>`, falling
+/// back to `PLIMSOLL_CONFIG`. `None` = defaults + environment only.
+fn config_path() -> Option<PathBuf> {
+    flag("--config")
+        .or_else(|| std::env::var("PLIMSOLL_CONFIG").ok().filter(|p| !p.is_empty()))
+        .map(PathBuf::from)
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| EnvFilter::new("plimsoll_rpc=info,tower_http=debug")),
-        )
-        .init();
+    let config_path = config_path();
+    let mut cfg = config::Config::load(config_path.as_deref())?;
+    // geth-style `--ipcpath` overrides `ipc_path`.
+    if let Some(path) = flag("--ipcpath") {
+        cfg.ipc_path = path;
+        cfg.validate()?;
+    }
+
+    // Initialize tracing (stdout + optional OTLP export)
+    otel::init_tracing(&cfg)?;
 
-    let cfg = config::Config::from_env()?;
     tracing::info!(
         "Plimsoll RPC Proxy v{} starting on {}:{}",
         env!("CARGO_PKG_VERSION"),
         cfg.host,
         cfg.port
     );
-    tracing::info!("Upstream RPC: {}", cfg.upstream_rpc_url);
-    tracing::info!("Fee: {} bps", cfg.fee_bps);
-    tracing::info!(
-        "Max loss: {}%, MEV shield: {}",
-        cfg.max_loss_pct,
-        if cfg.flashbots_enabled {
-            "Flashbots"
-        } else {
-            "disabled"
-        }
-    );
+    if let Some(path) = &config_path {
+        tracing::info!("Config file: {}", path.display());
+    }
+    cfg.log_summary();
     tracing::info!("Engine 0: Swarm Bloom Filter enabled (pre-flight blacklist)");
 
-    let app = router::build_router(cfg).await?;
+    #[cfg(unix)]
+    let ipc_path = cfg.ipc_path.clone();
+    let tls = tls::Settings::from_config(&cfg);
+    let app = router::build_router(cfg, config_path).await?;
+    #[cfg(unix)]
+    if !ipc_path.is_empty() {
+        ipc::spawn_listener(&ipc_path, app.clone())?;
+    }
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8545").await?;
-    tracing::info!("Listening on 0.0.0.0:8545");
+    let served = async {
+        if tls.enabled() {
+            tls::serve(&tls, "0.0.0.0:8545".parse()?, app).await?;
+        } else {
+            let listener = tokio::net::TcpListener::bind("0.0.0.0:8545").await?;
+            tracing::info!("Listening on 0.0.0.0:8545");
 
-    axum::serve(listener, app).await?;
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown::signaled())
+                .into_future()
+                .await?;
+        }
+        // The gRPC server and IPC connections drain on their own tasks.
+        shutdown::drained().await;
+        anyhow::Ok(())
+    };
+    tokio::select! {
+        served = served => served?,
+        _ = shutdown::deadline() => tracing::warn!("Shutdown grace period over: dropping in-flight requests"),
+    }
+    shutdown::finish();
+    otel::shutdown();
     Ok(())
 }
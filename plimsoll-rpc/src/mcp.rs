@@ -0,0 +1,277 @@
+//! Model Context Protocol (MCP) server.
+//!
+//! Agent frameworks that speak MCP can consult the firewall as a tool
+//! instead of only meeting it as a transparent RPC proxy. `POST /mcp`
+//! serves the Streamable HTTP transport (JSON responses, no SSE stream,
+//! stateless) with these tools:
+//!
+//! | Tool                | Runs                      | Arguments                                   |
+//! |---------------------|---------------------------|---------------------------------------------|
+//! | `check_transaction` | `plimsoll_dryRun`         | `transaction`, or `method` + `params`       |
+//! | `explain_block`     | `plimsoll_explainBlock`   | `tx_hash` (the synthetic hash of a block)   |
+//! | `get_vault_limits`  | `plimsoll_getVaultLimits` | `address` (optional: adds the agent's spend)|
+//!
+//! Each call goes through the same path as the RPC endpoint — agent API
+//! key, `from` binding, rate limits, chain selection (`plimsoll-chain`),
+//! audit log — so a tool sees exactly what the proxy would decide. The
+//! key is presented the same way too (`plimsoll-api-key` or bearer), or
+//! the client certificate behind mutual TLS.
+//!
+//! Off unless `mcp_server` is set. Requests carrying an `Origin` header
+//! are refused: agents are not browsers, and this closes DNS rebinding.
+
+use crate::auth;
+use crate::chains;
+use crate::router::{self, AppState};
+use crate::types::{JsonRpcRequest, INVALID_REQUEST_CODE, PARSE_ERROR_CODE};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Protocol revisions served, newest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+const INVALID_PARAMS_CODE: i64 = -32602;
+
+/// Tool name → the proxy-native method it runs.
+const TOOLS: &[(&str, &str)] = &[
+    ("check_transaction", "plimsoll_dryRun"),
+    ("explain_block", "plimsoll_explainBlock"),
+    ("get_vault_limits", "plimsoll_getVaultLimits"),
+];
+
+fn success(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message.into() } })
+}
+
+/// The `tools/list` result.
+fn tool_list() -> Value {
+    json!({ "tools": [
+        {
+            "name": "check_transaction",
+            "description": "Run a transaction or signature request through every Plimsoll engine \
+                without sending it. Returns wouldBlock, the first verdict (code, category, engine, \
+                reason) and every engine's verdicts, a 0-100 risk score, the target's reputation and the simulation.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "transaction": {
+                        "type": "object",
+                        "description": "An eth_sendTransaction object (from, to, value, data, ...)."
+                    },
+                    "method": {
+                        "type": "string",
+                        "description": "Send or sign method, e.g. eth_signTypedData_v4 (default eth_sendTransaction)."
+                    },
+                    "params": {
+                        "type": "array",
+                        "description": "The method's params, when not using transaction."
+                    }
+                }
+            }
+        },
+        {
+            "name": "explain_block",
+            "description": "Explain why Plimsoll blocked a transaction: its code, category, engine \
+                and reason, and how to dispute it.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "tx_hash": {
+                        "type": "string",
+                        "description": "The transaction hash returned for the blocked send."
+                    }
+                },
+                "required": ["tx_hash"]
+            }
+        },
+        {
+            "name": "get_vault_limits",
+            "description": "The limits Plimsoll holds sends to: per-token velocity caps, maximum \
+                drawdown and loss, and the vaults' target allowlist.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "address": {
+                        "type": "string",
+                        "description": "Agent address; adds what it has spent in each velocity window."
+                    }
+                }
+            }
+        }
+    ]})
+}
+
+/// The proxy-native request a `tools/call` runs, or why there is none.
+fn tool_request(name: &str, args: &Value) -> Result<JsonRpcRequest, String> {
+    let Some((_, method)) = TOOLS.iter().find(|(tool, _)| *tool == name) else {
+        return Err(format!("Unknown tool: {name}"));
+    };
+    let text = |key: &str| args.get(key).and_then(Value::as_str).map(str::to_string);
+    let params = match name {
+        "check_transaction" => {
+            let call_method = text("method").unwrap_or_else(|| "eth_sendTransaction".to_string());
+            let call_params = match (args.get("transaction"), args.get("params")) {
+                (Some(tx), _) => json!([tx]),
+                (None, Some(params)) if params.is_array() => params.clone(),
+                _ => return Err("check_transaction needs a transaction or params".into()),
+            };
+            json!([{ "method": call_method, "params": call_params }])
+        }
+        "explain_block" => match text("tx_hash") {
+            Some(hash) => json!([hash]),
+            None => return Err("explain_block needs a tx_hash".into()),
+        },
+        _ => text("address").map_or_else(|| json!([]), |address| json!([address])),
+    };
+    Ok(JsonRpcRequest {
+        jsonrpc: "2.0".into(),
+        method: method.to_string(),
+        params,
+        id: json!(1),
+        notification: false,
+    })
+}
+
+/// A tool result from the proxy's JSON-RPC response.
+fn tool_result(response: &Value) -> Value {
+    if let Some(err) = response.get("error").filter(|e| !e.is_null()) {
+        let message = err.get("message").and_then(Value::as_str).unwrap_or("Request failed");
+        return json!({ "content": [{ "type": "text", "text": message }], "isError": true });
+    }
+    let result = response.get("result").cloned().unwrap_or(Value::Null);
+    json!({
+        "content": [{ "type": "text", "text": result.to_string() }],
+        "structuredContent": result,
+        "isError": false,
+    })
+}
+
+/// POST /mcp — one MCP JSON-RPC message.
+pub async fn handle(
+    State(state): State<Arc<AppState>>,
+    client_cert: Option<Extension<auth::ClientCert>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !state.config.current().mcp_server {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    if headers.contains_key(header::ORIGIN) {
+        warn!("MCP request with an Origin header refused");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let message: Value = match serde_json::from_slice(&body) {
+        Ok(message) => message,
+        Err(e) => return Json(error(Value::Null, PARSE_ERROR_CODE, format!("Parse error: {e}"))).into_response(),
+    };
+    let req = match JsonRpcRequest::parse(message) {
+        Ok(req) => req,
+        Err(response) => {
+            let message = response.error.map_or_else(|| "Invalid Request".to_string(), |e| e.message);
+            return Json(error(response.id, INVALID_REQUEST_CODE, message)).into_response();
+        }
+    };
+    // Notifications (`notifications/initialized`, ...) need no answer.
+    if req.notification {
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    let credentials = auth::credentials(&headers, client_cert.as_deref());
+    if auth::authenticate(&state.config.current(), &credentials).is_err() {
+        let body = error(req.id, -32001, "Unauthorized: missing or invalid API key");
+        return (StatusCode::UNAUTHORIZED, Json(body)).into_response();
+    }
+
+    let body = match req.method.as_str() {
+        "initialize" => {
+            let requested = req.params.get("protocolVersion").and_then(Value::as_str);
+            let version = PROTOCOL_VERSIONS
+                .iter()
+                .find(|v| Some(**v) == requested)
+                .unwrap_or(&PROTOCOL_VERSIONS[0]);
+            success(
+                req.id,
+                json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": { "listChanged": false } },
+                    "serverInfo": { "name": "plimsoll", "version": env!("CARGO_PKG_VERSION") },
+                    "instructions": "Check transactions and signatures with check_transaction before \
+                        sending them; explain_block says why one was blocked.",
+                }),
+            )
+        }
+        "ping" => success(req.id, json!({})),
+        "tools/list" => success(req.id, tool_list()),
+        "tools/call" => {
+            let name = req.params.get("name").and_then(Value::as_str).unwrap_or("");
+            let args = req.params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+            match tool_request(name, &args) {
+                Ok(call) => {
+                    let (_, _, Json(response)) =
+                        router::serve_rpc(&state, credentials, chains::requested(&headers), call).await;
+                    success(req.id, tool_result(&response))
+                }
+                Err(message) => error(req.id, INVALID_PARAMS_CODE, message),
+            }
+        }
+        other => error(req.id, METHOD_NOT_FOUND_CODE, format!("Method not found: {other}")),
+    };
+    Json(body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_requests() {
+        let tx = json!({ "from": "0xA9E0000000000000000000000000000000002114", "to": "0x1", "value": "0x0" });
+        let check = tool_request("check_transaction", &json!({ "transaction": tx })).unwrap();
+        assert_eq!(check.method, "plimsoll_dryRun");
+        assert_eq!(check.params, json!([{ "method": "eth_sendTransaction", "params": [tx] }]));
+
+        let sign = tool_request(
+            "check_transaction",
+            &json!({ "method": "personal_sign", "params": ["0xdead", "0xA9E0000000000000000000000000000000002114"] }),
+        )
+        .unwrap();
+        assert_eq!(sign.params[0]["method"], "personal_sign");
+
+        let explain = tool_request("explain_block", &json!({ "tx_hash": "0xplimsoll01" })).unwrap();
+        assert_eq!((explain.method.as_str(), explain.params), ("plimsoll_explainBlock", json!(["0xplimsoll01"])));
+        assert_eq!(tool_request("get_vault_limits", &json!({})).unwrap().params, json!([]));
+
+        assert!(tool_request("explain_block", &json!({})).is_err());
+        assert!(tool_request("check_transaction", &json!({})).is_err());
+        assert!(tool_request("drain_vault", &json!({})).is_err());
+    }
+
+    #[test]
+    fn test_tool_results() {
+        let listed = tool_list();
+        let names: Vec<&str> = listed["tools"].as_array().unwrap().iter().filter_map(|t| t["name"].as_str()).collect();
+        assert_eq!(names, TOOLS.iter().map(|(name, _)| *name).collect::<Vec<_>>());
+
+        let ok = tool_result(&json!({ "jsonrpc": "2.0", "id": 1, "result": { "wouldBlock": true }, "error": null }));
+        assert_eq!(ok["isError"], false);
+        assert_eq!(ok["structuredContent"]["wouldBlock"], true);
+
+        let failed = tool_result(&json!({ "jsonrpc": "2.0", "id": 1, "result": null, "error": { "code": -32602, "message": "bad" } }));
+        assert_eq!(failed["isError"], true);
+        assert_eq!(failed["content"][0]["text"], "bad");
+    }
+}
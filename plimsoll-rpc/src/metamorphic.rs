@@ -0,0 +1,200 @@
+//! SELFDESTRUCT and CREATE2-redeploy detection.
+//!
+//! The classic simulate-nice / execute-evil swap needs a contract that can
+//! be destroyed and redeployed with different code at the same address: a
+//! CREATE2 deployment (the address depends only on deployer, salt and init
+//! code, and a metamorphic init code fetches its runtime code elsewhere)
+//! plus a SELFDESTRUCT. Two signals are checked:
+//!
+//!   - any `SELFDESTRUCT` frame in the simulation's call trace, by the
+//!     target or anything it calls;
+//!   - the target's code differing from the code it had
+//!     `redeploy_lookback_blocks` ago. Only CREATE2 redeployment can
+//!     replace non-empty code at an address, so this needs an archive
+//!     node; without one the check is skipped. An EIP-7702 delegation
+//!     designator (`0xef0100 || delegate`) is exempt: its EOA re-points
+//!     or clears it with a signature, no redeployment involved.
+
+use crate::config::Config;
+use crate::eip7702::DELEGATION_DESIGNATOR;
+use crate::types::CallFrame;
+use alloy_primitives::keccak256;
+use anyhow::{Context, Result};
+
+/// A `SELFDESTRUCT` found in a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selfdestruct {
+    /// The contract that destroyed itself.
+    pub contract: String,
+    /// Where its balance went.
+    pub beneficiary: String,
+}
+
+/// Every `SELFDESTRUCT` in `trace`.
+pub fn selfdestructs(trace: &CallFrame) -> Vec<Selfdestruct> {
+    fn walk(frame: &CallFrame, out: &mut Vec<Selfdestruct>) {
+        if frame.call_type == "SELFDESTRUCT" {
+            out.push(Selfdestruct {
+                contract: frame.from.to_lowercase(),
+                beneficiary: frame.to.to_lowercase(),
+            });
+        }
+        for call in &frame.calls {
+            walk(call, out);
+        }
+    }
+    let mut out = Vec::new();
+    walk(trace, &mut out);
+    out
+}
+
+/// Block a simulated transaction that self-destructs any contract.
+pub fn check_trace(trace: &CallFrame) -> Result<(), String> {
+    match selfdestructs(trace).first() {
+        Some(s) => Err(format!(
+            "PLIMSOLL SELFDESTRUCT: {} self-destructs during simulation (balance → {}) — \
+             its address can be redeployed with different code",
+            s.contract, s.beneficiary
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Whether `code` is an EIP-7702 delegation designator.
+fn is_delegation(code: &[u8]) -> bool {
+    code.len() == DELEGATION_DESIGNATOR.len() + 20 && code.starts_with(&DELEGATION_DESIGNATOR)
+}
+
+fn codehash(code: &[u8]) -> String {
+    if code.is_empty() {
+        return String::new();
+    }
+    format!("0x{}", hex::encode(keccak256(code).as_slice()))
+}
+
+async fn code_at(rpc_url: &str, address: &str, block: u64) -> Result<Vec<u8>> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_getCode",
+        "params": [address, format!("0x{block:x}")],
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to call eth_getCode")?
+        .json()
+        .await
+        .context("Failed to parse eth_getCode response")?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("eth_getCode at block {block}: {err}");
+    }
+    hex::decode(body["result"].as_str().unwrap_or("0x").trim_start_matches("0x"))
+        .context("eth_getCode returned invalid hex")
+}
+
+/// Block a target whose non-empty code at `block - redeploy_lookback_blocks`
+/// differs from `current_codehash`, unless that code was a delegation
+/// designator.
+pub fn compare_history(target: &str, past_code: &[u8], current_codehash: &str, lookback: u64) -> Result<(), String> {
+    if is_delegation(past_code) {
+        return Ok(());
+    }
+    let past_codehash = codehash(past_code);
+    if !past_codehash.is_empty() && past_codehash != current_codehash {
+        return Err(format!(
+            "PLIMSOLL METAMORPHIC: code at {} was replaced within the last {} blocks ({} → {}) — \
+             CREATE2 redeployment",
+            target,
+            lookback,
+            past_codehash,
+            if current_codehash.is_empty() { "none" } else { current_codehash }
+        ));
+    }
+    Ok(())
+}
+
+/// Check whether `target`'s code was redeployed within the lookback window
+/// ending at `block`.
+pub async fn check_redeploy(config: &Config, target: &str, current_codehash: &str, block: u64) -> Result<(), String> {
+    if config.redeploy_lookback_blocks == 0 || block == 0 {
+        return Ok(());
+    }
+    let past_block = block.saturating_sub(config.redeploy_lookback_blocks);
+    match code_at(&config.upstream_rpc_url, target, past_block).await {
+        Ok(past) => compare_history(target, &past, current_codehash, config.redeploy_lookback_blocks),
+        Err(e) => {
+            tracing::debug!(error = %e, target, "Historical code unavailable — redeploy check skipped");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: &str = "0x1111111111111111111111111111111111111111";
+    const VICTIM: &str = "0x2222222222222222222222222222222222222222";
+    const ATTACKER: &str = "0x6666666666666666666666666666666666666666";
+
+    fn frame(call_type: &str, from: &str, to: &str, calls: Vec<CallFrame>) -> CallFrame {
+        CallFrame {
+            call_type: call_type.into(),
+            from: from.into(),
+            to: to.into(),
+            input: "0x".into(),
+            error: None,
+            calls,
+            ..CallFrame::default()
+        }
+    }
+
+    #[test]
+    fn test_nested_selfdestruct_blocked() {
+        let trace = frame(
+            "CALL",
+            ATTACKER,
+            TARGET,
+            vec![frame("CALL", TARGET, VICTIM, vec![frame("SELFDESTRUCT", VICTIM, ATTACKER, vec![])])],
+        );
+        assert_eq!(
+            selfdestructs(&trace),
+            vec![Selfdestruct { contract: VICTIM.into(), beneficiary: ATTACKER.into() }]
+        );
+        let err = check_trace(&trace).unwrap_err();
+        assert!(err.contains("0x2222222222222222222222222222222222222222 self-destructs"));
+        assert!(check_trace(&frame("CALL", ATTACKER, TARGET, vec![])).is_ok());
+    }
+
+    #[test]
+    fn test_replaced_code_blocked() {
+        let past = codehash(&[0xaa]);
+        let err = compare_history(TARGET, &[0xaa], "0xbb", 100).unwrap_err();
+        assert!(err.contains(&format!("replaced within the last 100 blocks ({past} → 0xbb)")));
+        let err = compare_history(TARGET, &[0xaa], "", 100).unwrap_err();
+        assert!(err.contains(&format!("{past} → none")));
+    }
+
+    #[test]
+    fn test_unchanged_or_fresh_code_allowed() {
+        assert!(compare_history(TARGET, &[0xaa], &codehash(&[0xaa]), 100).is_ok());
+        // Freshly deployed: contract age is the rug-pull engine's concern.
+        assert!(compare_history(TARGET, &[], "0xbb", 100).is_ok());
+    }
+
+    #[test]
+    fn test_delegation_designator_exempt() {
+        let mut designator = DELEGATION_DESIGNATOR.to_vec();
+        designator.extend_from_slice(&[0x22; 20]);
+        // Re-delegated or undelegated since: not a redeployment.
+        assert!(compare_history(VICTIM, &designator, "0xbb", 100).is_ok());
+        assert!(compare_history(VICTIM, &designator, "", 100).is_ok());
+        // Real code that merely starts with the prefix is not exempt.
+        let mut code = DELEGATION_DESIGNATOR.to_vec();
+        code.extend_from_slice(&[0x22; 40]);
+        assert!(compare_history(VICTIM, &code, "0xbb", 100).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+//! Upstream method allowlist / denylist.
+//!
+//! By default every method the proxy doesn't intercept is forwarded to the
+//! upstream provider, including ones an agent has no business calling:
+//! `eth_accounts` (enumerates node-held keys), `txpool_content` (leaks
+//! pending order flow), `debug_*` / `admin_*` (expensive or privileged).
+//!
+//! Operators declare comma-separated method patterns; a trailing `*`
+//! matches any suffix (`debug_*`). The denylist always wins. When an
+//! allowlist is set, anything not on it is rejected.
+
+use crate::config::Config;
+
+/// JSON-RPC error code for a method rejected by policy (EIP-1474
+/// "method not supported").
+pub const METHOD_NOT_ALLOWED_CODE: i64 = -32004;
+
+fn parse_patterns(list: &str) -> Vec<&str> {
+    list.split(',').map(str::trim).filter(|s| !s.is_empty()).collect()
+}
+
+fn matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => method.starts_with(prefix),
+        None => pattern == method,
+    }
+}
+
+/// Check `method` against the configured policy.
+/// Returns `Err(reason)` if it must not be forwarded.
+pub fn check(config: &Config, method: &str) -> Result<(), String> {
+    if let Some(pattern) = parse_patterns(&config.method_denylist)
+        .into_iter()
+        .find(|p| matches(p, method))
+    {
+        return Err(format!(
+            "PLIMSOLL METHOD POLICY: '{}' is denied by operator policy (rule '{}')",
+            method, pattern
+        ));
+    }
+
+    let allow = parse_patterns(&config.method_allowlist);
+    if !allow.is_empty() && !allow.iter().any(|p| matches(p, method)) {
+        return Err(format!(
+            "PLIMSOLL METHOD POLICY: '{}' is not on the operator allowlist",
+            method
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_allows_everything() {
+        let config = Config::default();
+        assert!(check(&config, "debug_traceTransaction").is_ok());
+    }
+
+    #[test]
+    fn test_denylist_with_wildcard() {
+        let config = Config { method_denylist: "eth_accounts, txpool_content, debug_*".into(), ..Config::default() };
+        assert!(check(&config, "eth_accounts").is_err());
+        assert!(check(&config, "debug_traceCall").is_err());
+        assert!(check(&config, "eth_call").is_ok());
+    }
+
+    #[test]
+    fn test_allowlist_and_deny_precedence() {
+        let config = Config {
+            method_allowlist: "eth_*,net_version".into(),
+            method_denylist: "eth_accounts".into(),
+            ..Config::default()
+        };
+        assert!(check(&config, "eth_getBalance").is_ok());
+        assert!(check(&config, "net_version").is_ok());
+        assert!(check(&config, "web3_clientVersion").is_err());
+        assert!(check(&config, "eth_accounts").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! Prometheus metrics for the Plimsoll RPC Proxy.
+//!
+//! Exposed on `GET /metrics` in the Prometheus text exposition format.
+//! Operators get request volume, block decisions per engine, simulation
+//! and upstream latency, and the protective-state gauges (revoked keys,
+//! paymaster severed) without grepping logs.
+
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge,
+    Encoder, Histogram, IntCounter, IntCounterVec, IntGauge, TextEncoder,
+};
+
+/// Latency buckets (seconds) tuned for the sub-100ms hot path.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Methods reported under their own label. Anything else is folded into
+/// `other` so an agent spamming random method names can't blow up the
+/// label cardinality of the time series database.
+const LABELLED_METHODS: &[&str] = &[
+    "eth_sendTransaction",
+    "eth_sendRawTransaction",
+    "eth_sign",
+    "personal_sign",
+    "eth_signTypedData",
+    "eth_signTypedData_v3",
+    "eth_signTypedData_v4",
+    "eth_call",
+    "eth_estimateGas",
+    "eth_getBalance",
+    "eth_getCode",
+    "eth_getLogs",
+    "eth_getTransactionReceipt",
+    "eth_getTransactionByHash",
+    "eth_getTransactionCount",
+    "eth_blockNumber",
+    "eth_chainId",
+    "eth_gasPrice",
+    "eth_feeHistory",
+    "sendTransaction",
+];
+
+lazy_static! {
+    static ref RPC_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "plimsoll_rpc_requests_total",
+        "JSON-RPC requests received, by method",
+        &["method"]
+    )
+    .unwrap();
+
+    static ref BLOCKS: IntCounterVec = register_int_counter_vec!(
+        "plimsoll_blocks_total",
+        "Requests blocked by the proxy, by engine",
+        &["engine"]
+    )
+    .unwrap();
+
+    static ref SHADOW_BLOCKS: IntCounterVec = register_int_counter_vec!(
+        "plimsoll_shadow_blocks_total",
+        "Requests that would have been blocked in shadow mode, by engine",
+        &["engine"]
+    )
+    .unwrap();
+
+    static ref SHADOW_MODE: IntGauge = register_int_gauge!(
+        "plimsoll_shadow_mode",
+        "1 if shadow (log-only) mode is enabled, 0 otherwise"
+    )
+    .unwrap();
+
+    static ref RATE_LIMITED: IntCounterVec = register_int_counter_vec!(
+        "plimsoll_rate_limited_total",
+        "Requests rejected by the per-agent rate limiter, by method class",
+        &["class"]
+    )
+    .unwrap();
+
+    static ref SIMULATION_LATENCY: Histogram = register_histogram!(
+        "plimsoll_simulation_duration_seconds",
+        "Wall-clock time of pre-flight simulations",
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap();
+
+    static ref UPSTREAM_LATENCY: Histogram = register_histogram!(
+        "plimsoll_upstream_duration_seconds",
+        "Round-trip time of requests forwarded to the upstream RPC",
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap();
+
+    static ref REVERT_STRIKES: IntCounter = register_int_counter!(
+        "plimsoll_revert_strikes_total",
+        "Post-simulation revert / gas anomaly strikes recorded"
+    )
+    .unwrap();
+
+    static ref REVOKED_SESSION_KEYS: IntGauge = register_int_gauge!(
+        "plimsoll_revoked_session_keys",
+        "Session keys currently in the pessimistic revocation cache"
+    )
+    .unwrap();
+
+    static ref PAYMASTER_SEVERED: IntGauge = register_int_gauge!(
+        "plimsoll_paymaster_severed",
+        "1 if the Paymaster connection is severed, 0 otherwise"
+    )
+    .unwrap();
+}
+
+/// Map a JSON-RPC method to a bounded-cardinality label.
+pub fn method_label(method: &str) -> &str {
+    if LABELLED_METHODS.contains(&method) {
+        method
+    } else {
+        "other"
+    }
+}
+
+pub fn record_request(method: &str) {
+    RPC_REQUESTS.with_label_values(&[method_label(method)]).inc();
+}
+
+pub fn record_block(engine: &str) {
+    BLOCKS.with_label_values(&[engine]).inc();
+}
+
+pub fn record_shadow_block(engine: &str) {
+    SHADOW_BLOCKS.with_label_values(&[engine]).inc();
+}
+
+pub fn record_rate_limited(class: &str) {
+    RATE_LIMITED.with_label_values(&[class]).inc();
+}
+
+pub fn observe_simulation(elapsed: std::time::Duration) {
+    SIMULATION_LATENCY.observe(elapsed.as_secs_f64());
+}
+
+pub fn observe_upstream(elapsed: std::time::Duration) {
+    UPSTREAM_LATENCY.observe(elapsed.as_secs_f64());
+}
+
+pub fn record_revert_strike() {
+    REVERT_STRIKES.inc();
+}
+
+/// Render all metrics in the Prometheus text format.
+/// Gauges mirroring `rpc.rs` state are refreshed at scrape time.
+pub fn render() -> String {
+    REVOKED_SESSION_KEYS.set(crate::rpc::revoked_session_key_count() as i64);
+    PAYMASTER_SEVERED.set(crate::rpc::is_paymaster_severed() as i64);
+    SHADOW_MODE.set(crate::rpc::is_shadow_mode() as i64);
+
+    let mut buf = Vec::new();
+    let encoder = TextEncoder::new();
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buf) {
+        tracing::warn!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buf).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_label_bounded() {
+        assert_eq!(method_label("eth_sendTransaction"), "eth_sendTransaction");
+        assert_eq!(method_label("eth_call"), "eth_call");
+        assert_eq!(method_label("attacker_randomMethod12345"), "other");
+    }
+
+    #[test]
+    fn test_render_contains_metrics() {
+        record_request("eth_call");
+        record_block("engine0");
+        observe_simulation(std::time::Duration::from_millis(3));
+        let text = render();
+        assert!(text.contains("plimsoll_rpc_requests_total"));
+        assert!(text.contains("plimsoll_blocks_total"));
+        assert!(text.contains("plimsoll_simulation_duration_seconds"));
+        assert!(text.contains("plimsoll_paymaster_severed"));
+    }
+}
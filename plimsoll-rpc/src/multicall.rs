@@ -0,0 +1,242 @@
+//! Recursive multicall unwrapping.
+//!
+//! Wrapping a drainer call inside a Multicall3 batch makes the top-level
+//! `to` the benign, universally-deployed Multicall3 address and the
+//! selector `aggregate3` — Engine 0 and every selector check see nothing.
+//! Batches are decoded here, recursively, into their leaf calls:
+//!
+//!   - Multicall3 `aggregate`, `tryAggregate`, `blockAndAggregate`,
+//!     `tryBlockAndAggregate`, `aggregate3`, `aggregate3Value`
+//!   - router-style self-multicalls `multicall(bytes[])` and
+//!     `multicall(uint256 deadline, bytes[])` (inner calls target `to`)
+//!
+//! Leaf calls execute as the multicall contract, so `from` is the
+//! contract that dispatched them.
+
+use crate::abi;
+use crate::config::Config;
+use crate::types::InnerCall;
+
+/// `aggregate((address,bytes)[])`
+const AGGREGATE: [u8; 4] = [0x25, 0x2d, 0xba, 0x42];
+/// `tryAggregate(bool,(address,bytes)[])`
+const TRY_AGGREGATE: [u8; 4] = [0xbc, 0xe3, 0x8b, 0xd7];
+/// `blockAndAggregate((address,bytes)[])`
+const BLOCK_AND_AGGREGATE: [u8; 4] = [0xc3, 0x07, 0x7f, 0xa9];
+/// `tryBlockAndAggregate(bool,(address,bytes)[])`
+const TRY_BLOCK_AND_AGGREGATE: [u8; 4] = [0x39, 0x95, 0x42, 0xe9];
+/// `aggregate3((address,bool,bytes)[])`
+const AGGREGATE3: [u8; 4] = [0x82, 0xad, 0x56, 0xcb];
+/// `aggregate3Value((address,bool,uint256,bytes)[])`
+const AGGREGATE3_VALUE: [u8; 4] = [0x17, 0x4d, 0xea, 0x71];
+/// `multicall(bytes[])`
+const MULTICALL_BYTES: [u8; 4] = [0xac, 0x96, 0x50, 0xd8];
+/// `multicall(uint256,bytes[])`
+const MULTICALL_DEADLINE: [u8; 4] = [0x5a, 0xe4, 0x01, 0xdc];
+
+/// Selectors that grant token allowances or operator rights.
+const APPROVAL_SELECTORS: &[([u8; 4], &str)] = &[
+    ([0x09, 0x5e, 0xa7, 0xb3], "approve(address,uint256)"),
+    ([0x39, 0x50, 0x93, 0x51], "increaseAllowance(address,uint256)"),
+    ([0xa2, 0x2c, 0xb4, 0x65], "setApprovalForAll(address,bool)"),
+    ([0x87, 0x51, 0x7c, 0x45], "Permit2.approve(address,address,uint160,uint48)"),
+];
+
+/// Maximum batch nesting depth.
+const MAX_DEPTH: usize = 4;
+
+/// Maximum number of leaf calls in one transaction.
+const MAX_CALLS: usize = 256;
+
+/// Decode `data` sent to `to`. `Ok(None)` if it is not a multicall;
+/// otherwise the flattened leaf calls.
+pub fn decode(to: &str, data: &[u8]) -> Result<Option<Vec<InnerCall>>, String> {
+    let Some(selector) = abi::selector(data) else {
+        return Ok(None);
+    };
+    if !is_batch(selector) {
+        return Ok(None);
+    }
+    let mut leaves = Vec::new();
+    flatten(to, data, 0, &mut leaves)?;
+    Ok(Some(leaves))
+}
+
+fn is_batch(selector: [u8; 4]) -> bool {
+    [
+        AGGREGATE,
+        TRY_AGGREGATE,
+        BLOCK_AND_AGGREGATE,
+        TRY_BLOCK_AND_AGGREGATE,
+        AGGREGATE3,
+        AGGREGATE3_VALUE,
+        MULTICALL_BYTES,
+        MULTICALL_DEADLINE,
+    ]
+    .contains(&selector)
+}
+
+/// Decode one level of a batch sent to `to`.
+fn decode_batch(to: &str, data: &[u8]) -> Result<Vec<InnerCall>, String> {
+    let selector = abi::selector(data).ok_or("calldata too short")?;
+    let args = &data[4..];
+    let call = |target: String, value: u128, data: Vec<u8>| InnerCall {
+        from: to.to_lowercase(),
+        to: target,
+        value,
+        data,
+    };
+    let calls = match selector {
+        AGGREGATE | BLOCK_AND_AGGREGATE => abi::dynamic_array(args, 0)?
+            .into_iter()
+            .map(|e| Ok(call(abi::address(e, 0)?, 0, abi::bytes(e, 1)?)))
+            .collect::<Result<Vec<_>, String>>()?,
+        TRY_AGGREGATE | TRY_BLOCK_AND_AGGREGATE => abi::dynamic_array(args, 1)?
+            .into_iter()
+            .map(|e| Ok(call(abi::address(e, 0)?, 0, abi::bytes(e, 1)?)))
+            .collect::<Result<Vec<_>, String>>()?,
+        AGGREGATE3 => abi::dynamic_array(args, 0)?
+            .into_iter()
+            .map(|e| Ok(call(abi::address(e, 0)?, 0, abi::bytes(e, 2)?)))
+            .collect::<Result<Vec<_>, String>>()?,
+        AGGREGATE3_VALUE => abi::dynamic_array(args, 0)?
+            .into_iter()
+            .map(|e| {
+                let value = u128::try_from(abi::uint(e, 2)?)
+                    .map_err(|_| "aggregate3Value value exceeds u128".to_string())?;
+                Ok(call(abi::address(e, 0)?, value, abi::bytes(e, 3)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        MULTICALL_BYTES | MULTICALL_DEADLINE => {
+            let head = if selector == MULTICALL_BYTES { 0 } else { 1 };
+            abi::dynamic_array(args, head)?
+                .into_iter()
+                .map(|e| Ok(call(to.to_lowercase(), 0, abi::bytes_body(e)?)))
+                .collect::<Result<Vec<_>, String>>()?
+        }
+        _ => vec![],
+    };
+    Ok(calls)
+}
+
+fn flatten(to: &str, data: &[u8], depth: usize, out: &mut Vec<InnerCall>) -> Result<(), String> {
+    if depth >= MAX_DEPTH {
+        return Err(format!("multicall nested deeper than {MAX_DEPTH}"));
+    }
+    for inner in decode_batch(to, data)? {
+        if abi::selector(&inner.data).is_some_and(is_batch) {
+            flatten(&inner.to, &inner.data, depth + 1, out)?;
+        } else {
+            out.push(inner);
+        }
+        if out.len() > MAX_CALLS {
+            return Err(format!("multicall expands to more than {MAX_CALLS} calls"));
+        }
+    }
+    Ok(())
+}
+
+/// The approval a call grants, if any.
+pub fn approval_selector(data: &[u8]) -> Option<&'static str> {
+    let selector = abi::selector(data)?;
+    APPROVAL_SELECTORS
+        .iter()
+        .find(|(s, _)| *s == selector)
+        .map(|(_, name)| *name)
+}
+
+/// Block approvals hidden inside a batch when approval changes are
+/// blocked.
+pub fn check_calls(config: &Config, calls: &[InnerCall]) -> Result<(), String> {
+    if !config.block_approval_changes {
+        return Ok(());
+    }
+    if let Some((call, name)) = calls
+        .iter()
+        .find_map(|c| approval_selector(&c.data).map(|name| (c, name)))
+    {
+        return Err(format!(
+            "PLIMSOLL MULTICALL: batch hides {} on {}",
+            name, call.to
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::encode_uint;
+
+    const MULTICALL3: &str = "0xca11bde05977b3631167028862be2a173976ca11";
+    const TOKEN: &str = "0x2222222222222222222222222222222222222222";
+
+    fn addr_word(a: &str) -> [u8; 32] {
+        let mut w = [0u8; 32];
+        w[12..].copy_from_slice(&hex::decode(&a[2..]).unwrap());
+        w
+    }
+
+    fn padded(data: &[u8]) -> Vec<u8> {
+        let mut out = encode_uint(data.len() as u64).to_vec();
+        out.extend_from_slice(data);
+        out.resize(32 + data.len().div_ceil(32) * 32, 0);
+        out
+    }
+
+    /// `aggregate3([(target, false, data)...])`
+    fn aggregate3(calls: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let elements: Vec<Vec<u8>> = calls
+            .iter()
+            .map(|(target, data)| {
+                let mut e = addr_word(target).to_vec();
+                e.extend_from_slice(&encode_uint(0));
+                e.extend_from_slice(&encode_uint(96));
+                e.extend_from_slice(&padded(data));
+                e
+            })
+            .collect();
+        let mut out = AGGREGATE3.to_vec();
+        out.extend_from_slice(&encode_uint(32));
+        out.extend_from_slice(&encode_uint(elements.len() as u64));
+        let mut offset = 32 * elements.len();
+        for e in &elements {
+            out.extend_from_slice(&encode_uint(offset as u64));
+            offset += e.len();
+        }
+        for e in elements {
+            out.extend_from_slice(&e);
+        }
+        out
+    }
+
+    #[test]
+    fn test_aggregate3_flattened() {
+        let data = aggregate3(&[(TOKEN, vec![0xa9, 0x05, 0x9c, 0xbb]), (TOKEN, vec![])]);
+        let calls = decode(MULTICALL3, &data).unwrap().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].to, TOKEN);
+        assert_eq!(calls[0].from, MULTICALL3);
+        assert_eq!(calls[0].data, vec![0xa9, 0x05, 0x9c, 0xbb]);
+    }
+
+    #[test]
+    fn test_nested_approval_found() {
+        let mut approve = vec![0x09, 0x5e, 0xa7, 0xb3];
+        approve.extend_from_slice(&addr_word("0x6666666666666666666666666666666666666666"));
+        approve.extend_from_slice(&[0xff; 32]);
+        let inner = aggregate3(&[(TOKEN, approve)]);
+        let outer = aggregate3(&[(MULTICALL3, inner)]);
+        let calls = decode(MULTICALL3, &outer).unwrap().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].to, TOKEN);
+        let err = check_calls(&Config::default(), &calls).unwrap_err();
+        assert!(err.contains("approve(address,uint256)"));
+    }
+
+    #[test]
+    fn test_non_batch_and_truncated() {
+        assert_eq!(decode(TOKEN, &[0xa9, 0x05, 0x9c, 0xbb]).unwrap(), None);
+        assert!(decode(MULTICALL3, &AGGREGATE3).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Nonce tracking and gap detection.
+//!
+//! A transaction whose nonce is above the sender's next one is not mined:
+//! it parks in the mempool until the missing nonces are filled, and whoever
+//! fills them decides when it executes. A prompt-injected "nonce 999999"
+//! send is a time bomb — approved against today's state, fired whenever
+//! the attacker chooses.
+//!
+//! A sender's expected nonce is the higher of the node's pending
+//! transaction count and the confirmed count plus the proxy's own
+//! contiguous run of forwarded transactions (`pending`). A send is then:
+//!
+//!   - `Next` — the expected nonce, or within `max_nonce_gap` above it,
+//!   - `Replacement` — a nonce still pending (speed-up / cancel),
+//!   - `Stale` — already mined; the node rejects it,
+//!   - `Gap` — further ahead than `max_nonce_gap`.
+//!
+//! `nonce_gap_policy` decides a gap: `block`, `rewrite` (an unsigned
+//! `eth_sendTransaction` is given the expected nonce; signed transactions
+//! can't be rewritten and are blocked) or `off`.
+
+use crate::config::Config;
+use crate::pending;
+use anyhow::Result;
+use std::time::Duration;
+
+/// Where a send's nonce falls relative to the sender's queue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NonceStatus {
+    Next,
+    Replacement,
+    Stale,
+    Gap { expected: u64 },
+}
+
+/// A sender's confirmed and expected next nonce.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NonceState {
+    pub confirmed: u64,
+    pub expected: u64,
+}
+
+/// Classify `nonce` against `state`.
+pub fn classify(nonce: u64, state: &NonceState, max_gap: u64) -> NonceStatus {
+    if nonce < state.confirmed {
+        NonceStatus::Stale
+    } else if nonce < state.expected {
+        NonceStatus::Replacement
+    } else if nonce - state.expected > max_gap {
+        NonceStatus::Gap { expected: state.expected }
+    } else {
+        NonceStatus::Next
+    }
+}
+
+/// The nonce queue of `from`, from the node and the proxy's pending record.
+pub async fn state(config: &Config, from: &str) -> Result<NonceState> {
+    let rpc_url = &config.upstream_rpc_url;
+    let confirmed = pending::transaction_count(rpc_url, from, "latest").await?;
+    let node_pending = pending::transaction_count(rpc_url, from, "pending").await?;
+    let ttl = Duration::from_secs(config.pending_tx_ttl_secs);
+    let forwarded = pending::chain(from, u64::MAX, confirmed, ttl).len() as u64;
+    Ok(NonceState { confirmed, expected: node_pending.max(confirmed + forwarded) })
+}
+
+/// Apply `nonce_gap_policy` to a send. `Ok(Some(n))` rewrites the nonce to
+/// `n`; `signed` transactions can only be forwarded or blocked.
+pub fn resolve(config: &Config, nonce: u64, state: &NonceState, signed: bool) -> Result<Option<u64>, String> {
+    match classify(nonce, state, config.max_nonce_gap) {
+        NonceStatus::Gap { expected } => {
+            if config.nonce_gap_policy == "rewrite" && !signed {
+                return Ok(Some(expected));
+            }
+            Err(format!(
+                "PLIMSOLL NONCE GAP: nonce {} is {} ahead of the sender's next nonce {} \
+                 (max gap {}) — the transaction would park in the mempool until the gap is filled",
+                nonce,
+                nonce - expected,
+                expected,
+                config.max_nonce_gap
+            ))
+        }
+        NonceStatus::Stale => {
+            tracing::warn!(nonce, confirmed = state.confirmed, "Nonce already mined — upstream will reject");
+            Ok(None)
+        }
+        NonceStatus::Next | NonceStatus::Replacement => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUEUE: NonceState = NonceState { confirmed: 10, expected: 12 };
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(9, &QUEUE, 0), NonceStatus::Stale);
+        assert_eq!(classify(10, &QUEUE, 0), NonceStatus::Replacement);
+        assert_eq!(classify(11, &QUEUE, 0), NonceStatus::Replacement);
+        assert_eq!(classify(12, &QUEUE, 0), NonceStatus::Next);
+        assert_eq!(classify(13, &QUEUE, 0), NonceStatus::Gap { expected: 12 });
+        assert_eq!(classify(13, &QUEUE, 1), NonceStatus::Next);
+    }
+
+    #[test]
+    fn test_gap_blocked_by_default() {
+        let err = resolve(&Config::default(), 999_999, &QUEUE, false).unwrap_err();
+        assert!(err.contains("PLIMSOLL NONCE GAP: nonce 999999 is 999987 ahead of the sender's next nonce 12"));
+        assert_eq!(resolve(&Config::default(), 12, &QUEUE, false), Ok(None));
+        assert_eq!(resolve(&Config::default(), 3, &QUEUE, true), Ok(None));
+    }
+
+    #[test]
+    fn test_gap_rewritten_for_unsigned_only() {
+        let config = Config { nonce_gap_policy: "rewrite".into(), ..Config::default() };
+        assert_eq!(resolve(&config, 999_999, &QUEUE, false), Ok(Some(12)));
+        assert!(resolve(&config, 999_999, &QUEUE, true).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Security event notifications.
+//!
+//! Operators should hear about an attack when it happens, not from log
+//! scraping. Each of these events is pushed to every `notify_targets`
+//! entry that subscribes to it (empty `events` = all):
+//!
+//! | Event               | When                                        | Placeholders                                   |
+//! |---------------------|---------------------------------------------|------------------------------------------------|
+//! | `blocked`           | A request is blocked (not in shadow)        | `tx_hash`, `engine`, `code`, `category`, `reason` |
+//! | `paymaster_severed` | Revert strikes severed the Paymaster        | `strikes`                                      |
+//! | `session_revoked`   | A session key is revoked                    | `session_key`, `status` (`pending` / `confirmed`) |
+//!
+//! `{event}` works in every template. A target's `kind` picks the body:
+//!
+//! - `json`: `{"event", "message", "at", ...placeholders}` for any webhook,
+//! - `slack`: an incoming-webhook `{"text"}`,
+//! - `telegram`: a Bot API `sendMessage` `{"chat_id", "text"}`.
+//!
+//! Delivery runs on a background task reading the live config, so
+//! targets can be changed with a reload. Nothing waits on it: when the
+//! queue is full, or a target fails, the event is dropped with a warning.
+//! Webhook URLs carry secrets and are never logged.
+//!
+//! The same queue streams blocks and Paymaster severs to the fleet
+//! indexer (see `indexer_feed`).
+
+use crate::block_codes;
+use crate::config::NotifyTarget;
+use crate::indexer_feed;
+use crate::reload::SharedConfigHandle;
+use serde_json::{json, Map, Value};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Target kinds.
+pub const KINDS: &[&str] = &["json", "slack", "telegram"];
+
+/// Event names.
+pub const EVENTS: &[&str] = &["blocked", "paymaster_severed", "session_revoked"];
+
+/// Events waiting for delivery.
+const QUEUE_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecurityEvent {
+    Blocked {
+        tx_hash: String,
+        engine: String,
+        reason: String,
+        /// `None` on the default chain.
+        chain: Option<u64>,
+        /// Sender and counterparty; empty when the request wasn't a send.
+        agent: String,
+        target: String,
+    },
+    PaymasterSevered { strikes: usize },
+    SessionRevoked { session_key: String, pending: bool },
+}
+
+impl SecurityEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SecurityEvent::Blocked { .. } => "blocked",
+            SecurityEvent::PaymasterSevered { .. } => "paymaster_severed",
+            SecurityEvent::SessionRevoked { .. } => "session_revoked",
+        }
+    }
+
+    /// Placeholder values, `event` first.
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![("event", self.name().to_string())];
+        match self {
+            SecurityEvent::Blocked { tx_hash, engine, reason, .. } => {
+                let code = block_codes::of(engine);
+                fields.extend([
+                    ("tx_hash", tx_hash.clone()),
+                    ("engine", engine.clone()),
+                    ("code", code.code.to_string()),
+                    ("category", code.category.to_string()),
+                    ("reason", reason.clone()),
+                ]);
+            }
+            SecurityEvent::PaymasterSevered { strikes } => fields.push(("strikes", strikes.to_string())),
+            SecurityEvent::SessionRevoked { session_key, pending } => {
+                let status = if *pending { "pending" } else { "confirmed" };
+                fields.extend([("session_key", session_key.clone()), ("status", status.to_string())]);
+            }
+        }
+        fields
+    }
+
+    fn default_template(&self) -> &'static str {
+        match self {
+            SecurityEvent::Blocked { .. } => {
+                "Plimsoll blocked {tx_hash} ({category} {code}, engine {engine}): {reason}"
+            }
+            SecurityEvent::PaymasterSevered { .. } => {
+                "Plimsoll severed the Paymaster after {strikes} reverts — every transaction \
+                 is blocked until an operator resets it"
+            }
+            SecurityEvent::SessionRevoked { .. } => "Plimsoll revoked session key {session_key} ({status})",
+        }
+    }
+}
+
+static OUTBOX: OnceLock<mpsc::Sender<SecurityEvent>> = OnceLock::new();
+
+/// `template` with the event's `{placeholders}` filled in.
+pub fn render(template: &str, event: &SecurityEvent) -> String {
+    event
+        .fields()
+        .into_iter()
+        .fold(template.to_string(), |text, (key, value)| text.replace(&format!("{{{key}}}"), &value))
+}
+
+/// The body posted to `target` for `event`.
+fn payload(target: &NotifyTarget, event: &SecurityEvent) -> Value {
+    let template = if target.template.is_empty() { event.default_template() } else { &target.template };
+    let message = render(template, event);
+    match target.kind.as_str() {
+        "slack" => json!({ "text": message }),
+        "telegram" => json!({ "chat_id": target.chat_id, "text": message }),
+        _ => {
+            let mut body: Map<String, Value> =
+                event.fields().into_iter().map(|(k, v)| (k.to_string(), json!(v))).collect();
+            body.insert("message".into(), json!(message));
+            body.insert(
+                "at".into(),
+                json!(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()),
+            );
+            Value::Object(body)
+        }
+    }
+}
+
+fn subscribed(target: &NotifyTarget, event: &SecurityEvent) -> bool {
+    target.events.is_empty() || target.events.iter().any(|e| e == event.name())
+}
+
+/// Queue `event` for delivery. A no-op before the notifier task starts.
+pub fn send(event: SecurityEvent) {
+    if let Some(outbox) = OUTBOX.get() {
+        if outbox.try_send(event).is_err() {
+            warn!("Notification queue full — security event dropped");
+        }
+    }
+}
+
+/// Spawn the background notifier task. Reads the live config per event.
+pub fn spawn_notifier_task(config: SharedConfigHandle) {
+    let (outbox, mut events) = mpsc::channel(QUEUE_CAPACITY);
+    if OUTBOX.set(outbox).is_err() {
+        return;
+    }
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        while let Some(event) = events.recv().await {
+            let cfg = config.current();
+            for target in cfg.notify_targets.iter().filter(|t| subscribed(t, &event)) {
+                let sent = client
+                    .post(&target.url)
+                    .json(&payload(target, &event))
+                    .timeout(Duration::from_secs(10))
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| e.without_url());
+                match sent {
+                    Ok(_) => info!(event = event.name(), kind = %target.kind, "Security event notified"),
+                    Err(e) => {
+                        warn!(event = event.name(), kind = %target.kind, error = %e, "Security event notification failed")
+                    }
+                }
+            }
+            indexer_feed::forward(&client, &cfg, &event).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(kind: &str) -> NotifyTarget {
+        NotifyTarget {
+            kind: kind.into(),
+            url: "https://hooks.example.com/plimsoll".into(),
+            chat_id: "-100123".into(),
+            events: Vec::new(),
+            template: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_payloads() {
+        let event = SecurityEvent::Blocked {
+            tx_hash: "0xabc".into(),
+            engine: "physics".into(),
+            reason: "PLIMSOLL PHYSICS: loss 40%".into(),
+            chain: None,
+            agent: String::new(),
+            target: String::new(),
+        };
+        let json_body = payload(&target("json"), &event);
+        assert_eq!(json_body["event"], "blocked");
+        assert_eq!(json_body["code"], "4902");
+        assert_eq!(
+            json_body["message"],
+            "Plimsoll blocked 0xabc (PHYSICS 4902, engine physics): PLIMSOLL PHYSICS: loss 40%"
+        );
+
+        let slack = payload(&target("slack"), &event);
+        assert_eq!(slack, json!({ "text": json_body["message"] }));
+
+        let telegram = NotifyTarget { template: "{event} on {engine}".into(), ..target("telegram") };
+        assert_eq!(payload(&telegram, &event), json!({ "chat_id": "-100123", "text": "blocked on physics" }));
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let severed = SecurityEvent::PaymasterSevered { strikes: 3 };
+        let revoked = SecurityEvent::SessionRevoked { session_key: "0xkey".into(), pending: true };
+        assert!(subscribed(&target("json"), &severed));
+        let only = NotifyTarget { events: vec!["session_revoked".into()], ..target("json") };
+        assert!(!subscribed(&only, &severed));
+        assert!(subscribed(&only, &revoked));
+        assert_eq!(render("{session_key} is {status}", &revoked), "0xkey is pending");
+    }
+}
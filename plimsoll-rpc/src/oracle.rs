@@ -0,0 +1,181 @@
+//! Oracle price deviation guard.
+//!
+//! Simulation measures what a swap *delivers*, not whether that is a fair
+//! price: against a pool an attacker has just skewed, the agent receives
+//! exactly what the simulation promised — a fraction of the tokens' worth —
+//! and physics rates it "no loss".
+//!
+//! For Uniswap-V2-router swaps worth at least `oracle_min_trade_usd`, both
+//! legs are priced in USD and the simulated execution price is compared to
+//! the oracle's. A shortfall above `oracle_max_deviation_pct` blocks.
+//!
+//! Prices come from Chainlink (`chainlink_feeds`, token → USD feed, with
+//! `oracle_max_staleness_secs`), then the intent-order reference prices
+//! (`reference_prices` / `price_oracle_url`). A trade with an unpriced leg
+//! is not judged.
+
+use crate::abi;
+use crate::config::{Config, ReferencePrice};
+use crate::honeypot::{self, Swap};
+use crate::intents;
+use crate::simulator;
+use alloy_primitives::U256;
+use anyhow::{Context, Result};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `latestRoundData()`
+const LATEST_ROUND_DATA: [u8; 4] = [0xfe, 0xaf, 0x96, 0x8c];
+/// `decimals()`
+const DECIMALS: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// A swap priced at both the oracle and the simulated execution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricedTrade {
+    pub token_in: String,
+    pub token_out: String,
+    pub amount_out: U256,
+    /// Oracle value of what the agent pays.
+    pub in_usd: f64,
+    /// Oracle value of what the simulation says the agent receives.
+    pub out_usd: f64,
+}
+
+impl PricedTrade {
+    /// How far the execution price falls below the oracle price, in %.
+    pub fn deviation_pct(&self) -> f64 {
+        if self.in_usd <= 0.0 {
+            return 0.0;
+        }
+        (self.in_usd - self.out_usd) / self.in_usd * 100.0
+    }
+}
+
+async fn decimals(rpc_url: &str, contract: &str) -> Result<u8> {
+    let ret = simulator::eth_call(rpc_url, contract, &DECIMALS, "latest").await?;
+    let decimals = abi::uint(&ret, 0).map_err(anyhow::Error::msg)?;
+    u8::try_from(decimals).context("implausible decimals()")
+}
+
+/// `token`'s USD price from its Chainlink feed.
+async fn chainlink_price(config: &Config, token: &str, feed: &str) -> Result<ReferencePrice> {
+    let rpc_url = &config.upstream_rpc_url;
+    let round = simulator::eth_call(rpc_url, feed, &LATEST_ROUND_DATA, "latest").await?;
+    let answer = abi::uint(&round, 1).map_err(anyhow::Error::msg)?;
+    let updated_at = abi::uint(&round, 3).map_err(anyhow::Error::msg)?;
+    if answer.is_zero() || answer.bit(255) {
+        anyhow::bail!("feed {feed} reports a non-positive price");
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let age = now.saturating_sub(u64::try_from(updated_at).unwrap_or(u64::MAX));
+    if config.oracle_max_staleness_secs > 0 && age > config.oracle_max_staleness_secs {
+        anyhow::bail!("feed {feed} last updated {age}s ago");
+    }
+    let feed_decimals = decimals(rpc_url, feed).await?;
+    let answer: f64 = answer.to_string().parse().unwrap_or(f64::INFINITY);
+    Ok(ReferencePrice {
+        usd: answer / 10f64.powi(feed_decimals as i32),
+        decimals: decimals(rpc_url, token).await?,
+    })
+}
+
+/// USD price of `token`: its Chainlink feed, else the reference prices.
+pub async fn price(config: &Config, token: &str) -> Option<ReferencePrice> {
+    let feed = config
+        .chainlink_feeds
+        .iter()
+        .find(|(t, _)| t.eq_ignore_ascii_case(token))
+        .map(|(_, feed)| feed.clone());
+    if let Some(feed) = feed {
+        match chainlink_price(config, token, &feed).await {
+            Ok(price) => return Some(price),
+            Err(e) => tracing::warn!(error = %e, token, "Chainlink price unavailable"),
+        }
+    }
+    intents::reference_price(config, token).await
+}
+
+/// Price `swap` at the oracle and at its simulated execution. `Ok(None)`
+/// when a leg is unpriced, the trade is below `oracle_min_trade_usd`, or
+/// the swap reverts.
+pub async fn assess(
+    config: &Config,
+    from: &str,
+    value: u128,
+    data: &[u8],
+    swap: &Swap,
+) -> Result<Option<PricedTrade>> {
+    let token_in = swap.path[0].clone();
+    let token_out = swap.token().to_string();
+    let (Some(in_price), Some(out_price)) =
+        (price(config, &token_in).await, price(config, &token_out).await)
+    else {
+        tracing::debug!(%token_in, %token_out, "No oracle price for swap leg — skipping deviation check");
+        return Ok(None);
+    };
+    let in_usd = intents::usd_value(swap.amount_in, &in_price);
+    if in_usd < config.oracle_min_trade_usd {
+        return Ok(None);
+    }
+    let Some(amount_out) = honeypot::simulate_received(config, from, U256::from(value), data, swap).await?
+    else {
+        return Ok(None);
+    };
+    Ok(Some(PricedTrade {
+        token_in,
+        token_out,
+        amount_out,
+        in_usd,
+        out_usd: intents::usd_value(amount_out, &out_price),
+    }))
+}
+
+/// Block a trade executing too far below the oracle price.
+pub fn check(config: &Config, trade: &PricedTrade) -> Result<(), String> {
+    let deviation = trade.deviation_pct();
+    if deviation > config.oracle_max_deviation_pct {
+        return Err(format!(
+            "PLIMSOLL ORACLE DEVIATION: swap {} → {} executes {:.2}% below oracle price \
+             (${:.2} for ${:.2}, max {}%) — pool price likely manipulated",
+            trade.token_in,
+            trade.token_out,
+            deviation,
+            trade.in_usd,
+            trade.out_usd,
+            config.oracle_max_deviation_pct
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(in_usd: f64, out_usd: f64) -> PricedTrade {
+        PricedTrade {
+            token_in: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".into(),
+            token_out: "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".into(),
+            amount_out: U256::from(1u64),
+            in_usd,
+            out_usd,
+        }
+    }
+
+    #[test]
+    fn test_manipulated_price_blocked() {
+        let config = Config { oracle_max_deviation_pct: 3.0, ..Config::default() };
+        let err = check(&config, &trade(30_000.0, 21_000.0)).unwrap_err();
+        assert!(err.contains("30.00% below oracle price"));
+    }
+
+    #[test]
+    fn test_fair_or_favourable_price_allowed() {
+        let config = Config { oracle_max_deviation_pct: 3.0, ..Config::default() };
+        assert!(check(&config, &trade(30_000.0, 29_400.0)).is_ok());
+        assert!(check(&config, &trade(30_000.0, 31_000.0)).is_ok());
+        assert_eq!(trade(0.0, 10.0).deviation_pct(), 0.0);
+    }
+}
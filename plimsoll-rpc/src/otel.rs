@@ -0,0 +1,125 @@
+//! OpenTelemetry tracing export.
+//!
+//! Every JSON-RPC request runs inside an `rpc_request` span with child spans
+//! for each stage of the pipeline:
+//!
+//! ```text
+//! rpc_request ─┬─ parse
+//!              ├─ engine0
+//!              ├─ simulation
+//!              ├─ physics
+//!              └─ upstream
+//! ```
+//!
+//! When `PLIMSOLL_OTLP_ENDPOINT` is set, spans are exported over OTLP/gRPC
+//! to the operator's collector (Jaeger, Tempo, Honeycomb, ...). The trace ID
+//! of each request is echoed back in the `plimsoll-trace-id` response header
+//! so agent operators can jump from a slow or blocked call straight to its
+//! trace.
+
+use crate::config::Config;
+use crate::response_meta::StageLayer;
+use anyhow::{Context, Result};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime, trace as sdktrace, Resource};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// Response header carrying the request's trace ID.
+pub const TRACE_ID_HEADER: &str = "plimsoll-trace-id";
+
+/// Fallback request counter used when no OTLP exporter is configured.
+static LOCAL_TRACE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Initialize the global tracing subscriber: env-filtered stdout logging,
+/// plus an OTLP span exporter when `config.otlp_endpoint` is set.
+pub fn init_tracing(config: &Config) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("plimsoll_rpc=info,tower_http=debug"));
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(StageLayer);
+
+    if config.otlp_endpoint.is_empty() {
+        registry.init();
+        return Ok(());
+    }
+
+    let provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(config.otlp_endpoint.clone()),
+        )
+        .with_trace_config(sdktrace::Config::default().with_resource(Resource::new(vec![
+            KeyValue::new("service.name", config.otel_service_name.clone()),
+            KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+        ])))
+        .install_batch(runtime::Tokio)
+        .context("Failed to install OTLP trace exporter")?;
+
+    let tracer = provider.tracer("plimsoll-rpc");
+    opentelemetry::global::set_tracer_provider(provider);
+
+    registry
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .init();
+
+    tracing::info!(endpoint = %config.otlp_endpoint, "OTLP trace export enabled");
+    Ok(())
+}
+
+/// Flush buffered spans before the process exits.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Trace ID for `span` as 32 lowercase hex chars.
+///
+/// Uses the OpenTelemetry trace ID when an exporter is installed; otherwise
+/// falls back to a process-local ID so the header is always present for
+/// log correlation.
+pub fn trace_id(span: &tracing::Span) -> String {
+    let span_context = span.context().span().span_context().clone();
+    if span_context.is_valid() {
+        return span_context.trace_id().to_string();
+    }
+    local_trace_id()
+}
+
+fn local_trace_id() -> String {
+    let seq = LOCAL_TRACE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    format!("{:016x}{:016x}", nanos, seq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_trace_id_format() {
+        let id = local_trace_id();
+        assert_eq!(id.len(), 32);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn test_local_trace_ids_unique() {
+        assert_ne!(local_trace_id(), local_trace_id());
+    }
+
+    #[test]
+    fn test_trace_id_without_exporter_falls_back() {
+        let span = tracing::info_span!("rpc_request");
+        assert_eq!(trace_id(&span).len(), 32);
+    }
+}
@@ -0,0 +1,156 @@
+//! Pending-nonce chains.
+//!
+//! An agent often fires several transactions with consecutive nonces
+//! before the first is mined: approve then swap, or a batch of transfers.
+//! Simulated one by one against latest state, each looks fine — the
+//! transfer of 80% of the balance and the next transfer of 80% both pass.
+//!
+//! Every forwarded transaction is remembered under its sender and nonce
+//! until the chain's confirmed nonce passes it (or `pending_tx_ttl_secs`
+//! elapses, for dropped transactions). A new transaction with nonce N is
+//! simulated after the contiguous run of pending transactions from the
+//! confirmed nonce up to N - 1.
+
+use crate::chains;
+use crate::config::Config;
+use crate::types::PendingTx;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Pending transactions remembered per sender.
+const MAX_PENDING_PER_SENDER: usize = 64;
+
+/// One sender's pending transactions: nonce → (transaction, forwarded at).
+type SenderTxs = BTreeMap<u64, (PendingTx, Instant)>;
+
+lazy_static! {
+    /// sender (lowercase, chain-scoped) → its pending transactions.
+    static ref PENDING: Mutex<HashMap<String, SenderTxs>> = Mutex::new(HashMap::new());
+}
+
+/// `from`'s key in the store, on the chain being served.
+fn sender_key(from: &str) -> String {
+    chains::scope_key(chains::current(), &from.to_lowercase())
+}
+
+/// Remember a forwarded transaction. A replacement (same nonce)
+/// overwrites the original.
+pub fn record(from: &str, tx: PendingTx) {
+    let Ok(mut store) = PENDING.lock() else {
+        return;
+    };
+    let txs = store.entry(sender_key(from)).or_default();
+    if txs.len() >= MAX_PENDING_PER_SENDER && !txs.contains_key(&tx.nonce) {
+        return;
+    }
+    txs.insert(tx.nonce, (tx, Instant::now()));
+}
+
+/// The contiguous pending transactions of `from` with nonces in
+/// `confirmed..nonce`, in order. Mined and expired entries are pruned.
+pub fn chain(from: &str, nonce: u64, confirmed: u64, ttl: Duration) -> Vec<PendingTx> {
+    let Ok(mut store) = PENDING.lock() else {
+        return Vec::new();
+    };
+    let Some(txs) = store.get_mut(&sender_key(from)) else {
+        return Vec::new();
+    };
+    txs.retain(|&n, (_, at)| n >= confirmed && at.elapsed() < ttl);
+
+    let mut out = Vec::new();
+    for expected in confirmed..nonce {
+        match txs.get(&expected) {
+            Some((tx, _)) => out.push(tx.clone()),
+            None => break,
+        }
+    }
+    out
+}
+
+/// The pending transaction of `from` with `nonce`, if it was forwarded
+/// within `ttl`.
+pub fn get(from: &str, nonce: u64, ttl: Duration) -> Option<PendingTx> {
+    let store = PENDING.lock().ok()?;
+    let (tx, at) = store.get(&sender_key(from))?.get(&nonce)?;
+    (at.elapsed() < ttl).then(|| tx.clone())
+}
+
+/// `eth_getTransactionCount` of `from` at `tag` (`latest` = confirmed,
+/// `pending` = including the node's mempool).
+pub async fn transaction_count(rpc_url: &str, from: &str, tag: &str) -> Result<u64> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_getTransactionCount",
+        "params": [from, tag],
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to call eth_getTransactionCount")?
+        .json()
+        .await
+        .context("Failed to parse eth_getTransactionCount response")?;
+    let count = body["result"].as_str().context("eth_getTransactionCount returned no result")?;
+    u64::from_str_radix(count.trim_start_matches("0x"), 16).context("invalid transaction count")
+}
+
+/// The pending transactions a send from `from` with `nonce` depends on.
+pub async fn predecessors(config: &Config, from: &str, nonce: u64) -> Vec<PendingTx> {
+    let confirmed = match transaction_count(&config.upstream_rpc_url, from, "latest").await {
+        Ok(n) => n,
+        Err(e) => {
+            tracing::warn!(error = %e, "Confirmed nonce unavailable — simulating against latest only");
+            return Vec::new();
+        }
+    };
+    chain(from, nonce, confirmed, Duration::from_secs(config.pending_tx_ttl_secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(600);
+
+    fn tx(nonce: u64) -> PendingTx {
+        PendingTx {
+            nonce,
+            to: "0x1111111111111111111111111111111111111111".into(),
+            value: 1,
+            ..PendingTx::default()
+        }
+    }
+
+    #[test]
+    fn test_chain_is_contiguous_from_confirmed_nonce() {
+        let agent = "0xa9e00000000000000000000000000000000000a1";
+        for n in [4, 5, 6, 8] {
+            record(agent, tx(n));
+        }
+        let nonces = |c: Vec<PendingTx>| c.iter().map(|t| t.nonce).collect::<Vec<_>>();
+        assert_eq!(nonces(chain(agent, 7, 4, TTL)), vec![4, 5, 6]);
+        // Nonce 7 never arrived: 9 can't run before it, the chain stops at the gap.
+        assert_eq!(nonces(chain(agent, 9, 4, TTL)), vec![4, 5, 6]);
+        // 4 and 5 mined — pruned.
+        assert_eq!(nonces(chain(agent, 7, 6, TTL)), vec![6]);
+        assert!(chain(agent, 5, 4, Duration::ZERO).is_empty());
+    }
+
+    #[test]
+    fn test_replacement_overwrites_and_senders_are_separate() {
+        let agent = "0xa9e00000000000000000000000000000000000a2";
+        record(agent, tx(0));
+        record(&agent.to_uppercase().replace("0X", "0x"), PendingTx { value: 9, ..tx(0) });
+        assert_eq!(chain(agent, 1, 0, TTL)[0].value, 9);
+        assert!(chain("0xa9e00000000000000000000000000000000000a3", 1, 0, TTL).is_empty());
+        assert_eq!(get(agent, 0, TTL).map(|t| t.value), Some(9));
+        assert_eq!(get(agent, 1, TTL), None);
+        assert_eq!(get(agent, 0, Duration::ZERO), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Permit2 `PermitDetails` decoding (PermitSingle / PermitBatch).
+//!
+//! A `PermitBatch` grants allowances on many tokens at once, each with its
+//! own `(token, amount, expiration, nonce)` tuple nested in `details[]`.
+//! Summarising it as "MULTIPLE_TOKENS" hides exactly what an operator needs
+//! to see, and the top-level deadline check never looks at the nested
+//! per-token `expiration`s.
+//!
+//! Every tuple is decoded and checked:
+//!   - amount = MAX_UINT160 / MAX_UINT256 (unlimited allowance) → block
+//!   - amount above the operator's per-token cap → block
+//!   - expiration beyond the configured horizon (or immortal) → block
+//!
+//! These checks run even for operator-trusted EIP-712 domains: trusting
+//! Permit2 itself must not mean trusting unlimited, immortal allowances.
+
+use crate::config::Config;
+use crate::eip712::parse_integer;
+use alloy_primitives::U256;
+use std::str::FromStr;
+
+/// Permit2, deployed at the same address on every chain.
+pub const PERMIT2: &str = "0x000000000022d473030f116ddee9f6b43ac78ba3";
+
+/// One decoded `PermitDetails` tuple.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermitDetail {
+    pub token: String,
+    pub amount: U256,
+    pub expiration: U256,
+    pub nonce: U256,
+}
+
+impl std::fmt::Display for PermitDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let amount = if is_unlimited(self.amount) {
+            "MAX_UINT".to_string()
+        } else {
+            self.amount.to_string()
+        };
+        write!(
+            f,
+            "(token {}, amount {}, expiration {}, nonce {})",
+            self.token, amount, self.expiration, self.nonce
+        )
+    }
+}
+
+fn max_uint160() -> U256 {
+    (U256::from(1) << 160) - U256::from(1)
+}
+
+/// Permit2 amounts are uint160; treat its max (and uint256 max) as unlimited.
+pub fn is_unlimited(amount: U256) -> bool {
+    amount >= max_uint160()
+}
+
+/// Decode `message.details` — an object (PermitSingle) or array (PermitBatch).
+/// Returns `Ok(None)` when the message has no `details`.
+pub fn decode_details(typed_data: &serde_json::Value) -> Result<Option<Vec<PermitDetail>>, String> {
+    let Some(details) = typed_data.get("message").and_then(|m| m.get("details")) else {
+        return Ok(None);
+    };
+    let items: Vec<&serde_json::Value> = match details {
+        serde_json::Value::Array(a) => a.iter().collect(),
+        obj @ serde_json::Value::Object(_) => vec![obj],
+        _ => return Err("Permit2 `details` is neither an object nor an array".into()),
+    };
+
+    let field = |item: &serde_json::Value, name: &str| -> Result<U256, String> {
+        item.get(name)
+            .ok_or_else(|| format!("Permit2 details entry missing `{name}`"))
+            .and_then(parse_integer)
+    };
+
+    items
+        .into_iter()
+        .map(|item| -> Result<PermitDetail, String> {
+            Ok(PermitDetail {
+                token: item
+                    .get("token")
+                    .and_then(|v| v.as_str())
+                    .ok_or("Permit2 details entry missing `token`")?
+                    .to_lowercase(),
+                amount: field(item, "amount")?,
+                expiration: field(item, "expiration")?,
+                nonce: field(item, "nonce")?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(Some)
+}
+
+/// Apply amount and expiration policy to every tuple.
+pub fn check_details(config: &Config, details: &[PermitDetail], now: u64) -> Result<(), String> {
+    for (i, d) in details.iter().enumerate() {
+        if is_unlimited(d.amount) {
+            return Err(format!(
+                "PLIMSOLL PERMIT2: details[{i}] grants an UNLIMITED allowance {d}"
+            ));
+        }
+
+        let cap = config
+            .permit2_token_caps
+            .iter()
+            .find(|(token, _)| token.eq_ignore_ascii_case(&d.token))
+            .map(|(_, cap)| cap);
+        if let Some(cap) = cap {
+            let cap = U256::from_str(cap)
+                .map_err(|e| format!("invalid permit2 cap for {}: {e}", d.token))?;
+            if d.amount > cap {
+                return Err(format!(
+                    "PLIMSOLL PERMIT2: details[{i}] amount {} exceeds cap {} for token {}",
+                    d.amount, cap, d.token
+                ));
+            }
+        }
+
+        if config.permit2_max_expiration_secs > 0 {
+            let horizon = U256::from(now.saturating_add(config.permit2_max_expiration_secs));
+            // Permit2 treats expiration 0 as "expires at this block" — allowed.
+            if d.expiration > horizon {
+                return Err(format!(
+                    "PLIMSOLL PERMIT2: details[{i}] expiration {} is beyond the {}s horizon {d}",
+                    d.expiration, config.permit2_max_expiration_secs
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Human-readable list of all tuples, for synthetic actions and logs.
+pub fn describe(details: &[PermitDetail]) -> String {
+    details
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    fn batch(amount_b: &str, expiration_b: u64) -> serde_json::Value {
+        serde_json::json!({
+            "primaryType": "PermitBatch",
+            "message": {
+                "details": [
+                    { "token": USDC, "amount": "1000000", "expiration": 1_700_000_600u64, "nonce": 0 },
+                    { "token": WETH, "amount": amount_b, "expiration": expiration_b, "nonce": "1" }
+                ],
+                "spender": "0x3fc91a3afd70395cd496c647d5a6cc9d4b2b7fad",
+                "sigDeadline": 1_700_000_600u64
+            }
+        })
+    }
+
+    #[test]
+    fn test_decode_batch() {
+        let details = decode_details(&batch("5", 1_700_000_600)).unwrap().unwrap();
+        assert_eq!(details.len(), 2);
+        assert_eq!(details[0].token, USDC);
+        assert_eq!(details[1].amount, U256::from(5));
+        assert!(describe(&details).contains(WETH));
+    }
+
+    #[test]
+    fn test_max_uint_blocked() {
+        let max160 = max_uint160().to_string();
+        let details = decode_details(&batch(&max160, 1_700_000_600)).unwrap().unwrap();
+        let err = check_details(&Config::default(), &details, 1_700_000_000).unwrap_err();
+        assert!(err.contains("UNLIMITED"));
+        assert!(err.contains("details[1]"));
+    }
+
+    #[test]
+    fn test_cap_and_horizon() {
+        let mut config = Config::default();
+        config.permit2_token_caps.insert(WETH.to_uppercase().replace("0X", "0x"), "10".into());
+        config.permit2_max_expiration_secs = 3600;
+        let now = 1_700_000_000;
+
+        let ok = decode_details(&batch("10", now + 600)).unwrap().unwrap();
+        assert!(check_details(&config, &ok, now).is_ok());
+
+        let over_cap = decode_details(&batch("11", now + 600)).unwrap().unwrap();
+        assert!(check_details(&config, &over_cap, now).unwrap_err().contains("cap"));
+
+        let too_long = decode_details(&batch("1", now + 86_400)).unwrap().unwrap();
+        assert!(check_details(&config, &too_long, now).unwrap_err().contains("horizon"));
+    }
+
+    #[test]
+    fn test_no_details() {
+        let data = serde_json::json!({ "message": { "spender": "0x0" } });
+        assert_eq!(decode_details(&data).unwrap(), None);
+    }
+}
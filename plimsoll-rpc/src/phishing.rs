@@ -0,0 +1,266 @@
+//! Phishing checks for EIP-712 domains (GOD-TIER 1).
+//!
+//! Wallet UIs show the typed data's `domain.name` as "who is asking", and
+//! drainer kits mint domains that read like the real protocol — "Uniswaр"
+//! with a Cyrillic `р`, "Un1swap", a zero-width joiner in "Permit2" — or
+//! embed links to their own site in the message. Typed data is blocked
+//! when:
+//!
+//!   - `domain.name` or a link's host mixes Latin with homoglyphs, contains
+//!     invisible characters, or reads like a protected name
+//!     (`phishing_protected_names`) without being it,
+//!   - `domain.name` or a link's host (or a parent domain) is on the
+//!     phishing domain feed (`phishing_feed_url`).
+//!
+//! Links are `http(s)://` URLs anywhere in the domain or message, and bare
+//! hosts under `uri` / `url` / `origin` / `website` keys. The feed may be
+//! MetaMask's `eth-phishing-detect` config (its `blacklist`), a JSON array
+//! or one domain per line; it is reloaded every
+//! `phishing_feed_refresh_secs` and a failed reload keeps the last one.
+
+use crate::config::Config;
+use crate::ens;
+use crate::homoglyphs;
+use crate::reload::SharedConfigHandle;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Message keys whose bare value is a host.
+const HOST_KEYS: &[&str] = &["uri", "url", "origin", "website"];
+
+lazy_static! {
+    /// Phishing domains from the feed, lowercase.
+    static ref FEED: RwLock<HashSet<String>> = RwLock::new(HashSet::new());
+}
+
+/// Domains in a feed document.
+pub fn parse_feed(text: &str) -> HashSet<String> {
+    let entries: Vec<String> = match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(serde_json::Value::Object(config)) => config
+            .get("blacklist")
+            .and_then(|b| b.as_array())
+            .map(|b| b.iter().filter_map(|d| d.as_str().map(String::from)).collect())
+            .unwrap_or_default(),
+        Ok(serde_json::Value::Array(list)) => {
+            list.iter().filter_map(|d| d.as_str().map(String::from)).collect()
+        }
+        _ => text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(String::from)
+            .collect(),
+    };
+    entries
+        .into_iter()
+        .map(|d| d.trim().trim_end_matches('.').to_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect()
+}
+
+async fn fetch(location: &str) -> Result<String> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let resp = reqwest::Client::new()
+            .get(location)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to fetch the phishing feed")?
+            .error_for_status()
+            .context("Phishing feed server returned an error")?;
+        return resp.text().await.context("Failed to read the phishing feed");
+    }
+    tokio::fs::read_to_string(location)
+        .await
+        .with_context(|| format!("Failed to read the phishing feed {location}"))
+}
+
+/// Spawn the feed reload task. Loads at once, then every
+/// `phishing_feed_refresh_secs`; reads the live config every round.
+pub fn spawn_refresh_task(config: SharedConfigHandle) {
+    tokio::spawn(async move {
+        loop {
+            let cfg = config.current();
+            if !cfg.phishing_feed_url.is_empty() {
+                match fetch(&cfg.phishing_feed_url).await {
+                    Ok(text) => {
+                        let domains = parse_feed(&text);
+                        info!(domains = domains.len(), "Phishing domain feed loaded");
+                        if let Ok(mut feed) = FEED.write() {
+                            *feed = domains;
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "Phishing feed refresh failed — keeping the current list"),
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(cfg.phishing_feed_refresh_secs.max(60))).await;
+        }
+    });
+}
+
+/// Lowercase letters and digits of `s` — what a reader compares.
+fn letters(s: &str) -> String {
+    s.to_lowercase().chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+/// Why `text` is a spoof of a protected name, if it is one.
+fn spoof(config: &Config, text: &str) -> Option<String> {
+    if let Some(c) = text.chars().find(|c| homoglyphs::INVISIBLE.contains(c)) {
+        return Some(format!("contains invisible character U+{:04X}", c as u32));
+    }
+    let has_latin = text.chars().any(|c| c.is_ascii_alphabetic());
+    if let Some((c, latin)) = text.chars().find_map(|c| homoglyphs::confusable(c).map(|l| (c, l))) {
+        if has_latin {
+            return Some(format!("mixes Latin with '{}' (U+{:04X}) imitating '{}'", c, c as u32, latin));
+        }
+    }
+    let candidate = letters(text);
+    let target = ens::skeleton(&candidate);
+    config
+        .phishing_protected_names
+        .split(',')
+        .map(letters)
+        .filter(|name| !name.is_empty())
+        .find(|name| *name != candidate && ens::skeleton(name) == target)
+        .map(|name| format!("is a lookalike of protected name {:?}", name))
+}
+
+/// Whether `host` or one of its parent domains is on the feed.
+fn on_feed(host: &str) -> bool {
+    let Ok(feed) = FEED.read() else {
+        return false;
+    };
+    let host = host.trim_end_matches('.').to_lowercase();
+    let mut rest = host.as_str();
+    loop {
+        if feed.contains(rest) {
+            return true;
+        }
+        match rest.split_once('.') {
+            Some((_, parent)) if parent.contains('.') => rest = parent,
+            _ => return false,
+        }
+    }
+}
+
+/// Host of an `http(s)://` URL.
+fn url_host(url: &str) -> Option<&str> {
+    let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+/// Hosts linked from the typed data's `domain` and `message`.
+pub fn hosts_in(typed_data: &serde_json::Value) -> Vec<String> {
+    fn walk(key: Option<&str>, value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(s) => {
+                for (i, _) in s.match_indices("http") {
+                    let url = s[i..].split(char::is_whitespace).next().unwrap_or("");
+                    out.extend(url_host(url).map(String::from));
+                }
+                let bare = s.trim();
+                if key.is_some_and(|k| HOST_KEYS.contains(&k.to_lowercase().as_str()))
+                    && bare.contains('.')
+                    && !bare.contains(|c: char| c.is_whitespace() || c == '/')
+                {
+                    out.push(bare.to_string());
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| walk(key, v, out)),
+            serde_json::Value::Object(map) => map.iter().for_each(|(k, v)| walk(Some(k), v, out)),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    for part in ["domain", "message"] {
+        if let Some(value) = typed_data.get(part) {
+            walk(None, value, &mut out);
+        }
+    }
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Check the typed data's domain name and linked hosts.
+pub fn check_typed_data(config: &Config, typed_data: &serde_json::Value) -> Result<(), String> {
+    let name = typed_data
+        .get("domain")
+        .and_then(|d| d.get("name"))
+        .and_then(|n| n.as_str())
+        .unwrap_or("");
+    if !name.is_empty() {
+        if let Some(why) = spoof(config, name) {
+            return Err(format!("GOD-TIER 1 (EIP-712 Phishing): domain name {:?} {}", name, why));
+        }
+        if name.contains('.') && on_feed(name) {
+            return Err(format!(
+                "GOD-TIER 1 (EIP-712 Phishing): domain name {:?} is a known phishing domain",
+                name
+            ));
+        }
+    }
+    for host in hosts_in(typed_data) {
+        if let Some(why) = host.split('.').find_map(|label| spoof(config, label)) {
+            return Err(format!("GOD-TIER 1 (EIP-712 Phishing): linked host {:?} {}", host, why));
+        }
+        if on_feed(&host) {
+            return Err(format!(
+                "GOD-TIER 1 (EIP-712 Phishing): linked host {:?} is a known phishing domain",
+                host
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn typed(name: &str, message: serde_json::Value) -> serde_json::Value {
+        json!({ "primaryType": "Login", "domain": { "name": name, "chainId": 1 }, "message": message })
+    }
+
+    #[test]
+    fn test_homoglyph_domain_names_blocked() {
+        let config = Config::default();
+        assert!(check_typed_data(&config, &typed("Uniswap", json!({}))).is_ok());
+        // Cyrillic р.
+        let err = check_typed_data(&config, &typed("Uniswa\u{0440}", json!({}))).unwrap_err();
+        assert!(err.contains("U+0440"), "{err}");
+        assert!(check_typed_data(&config, &typed("Un1swap", json!({}))).is_err());
+        assert!(check_typed_data(&config, &typed("Permit\u{200b}2", json!({}))).is_err());
+        // Unrelated names and all-Cyrillic names pass.
+        assert!(check_typed_data(&config, &typed("My Game", json!({}))).is_ok());
+        assert!(check_typed_data(&config, &typed("Биржа", json!({}))).is_ok());
+    }
+
+    #[test]
+    fn test_linked_hosts_checked() {
+        let config = Config::default();
+        let message = json!({ "note": "Claim at https://app.un1swap.org/claim?x=1", "nonce": 1 });
+        assert_eq!(hosts_in(&typed("Airdrop", message.clone())), vec!["app.un1swap.org"]);
+        assert!(check_typed_data(&config, &typed("Airdrop", message)).is_err());
+
+        FEED.write().unwrap().extend(parse_feed(r#"{"version":2,"blacklist":["drainer-claim.xyz"]}"#));
+        let message = json!({ "website": "airdrop.drainer-claim.xyz" });
+        let err = check_typed_data(&config, &typed("Airdrop", message)).unwrap_err();
+        assert!(err.contains("known phishing domain"), "{err}");
+        let message = json!({ "uri": "https://app.uniswap.org" });
+        assert!(check_typed_data(&config, &typed("Uniswap", message)).is_ok());
+    }
+
+    #[test]
+    fn test_feed_formats() {
+        assert_eq!(parse_feed("# list\nEvil.com\n\nbad.io.\n").len(), 2);
+        assert!(parse_feed(r#"["evil.com"]"#).contains("evil.com"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Send-time enforcement of the simulation's code pins.
+//!
+//! The simulator records the target's EXTCODEHASH, its EIP-1967 slot and
+//! the resolved implementation's codehash, but between simulation and
+//! forwarding a metamorphic contract can be destroyed and redeployed with
+//! different code at the same address (CREATE2 + SELFDESTRUCT), or a proxy
+//! upgraded. Immediately before forwarding, every pin is re-read from the
+//! upstream and the request is blocked if any of them moved. A pin that
+//! can't be re-read blocks too — the point is to know the code is the
+//! code that was simulated.
+
+use crate::config::Config;
+use crate::proxy;
+use crate::simulator;
+use crate::types::SimulationResult;
+use anyhow::Result;
+
+/// Code identity of a target at one point in time. Empty = nothing to pin.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pins {
+    pub target_codehash: String,
+    pub impl_slot_value: String,
+    pub implementation_codehash: String,
+}
+
+impl Pins {
+    /// The pins a simulation recorded.
+    pub fn simulated(sim: &SimulationResult) -> Self {
+        Pins {
+            target_codehash: sim.target_codehash.clone(),
+            impl_slot_value: sim.impl_slot_value.clone(),
+            implementation_codehash: sim.implementation_codehash.clone(),
+        }
+    }
+}
+
+/// Re-read the pins `simulated` recorded for `target`. The target's
+/// codehash is always re-read: an address that was empty at simulation
+/// can have code deployed to it since.
+pub async fn current(config: &Config, target: &str, simulated: &Pins) -> Result<Pins> {
+    let rpc_url = &config.upstream_rpc_url;
+    let mut pins = Pins {
+        target_codehash: simulator::fetch_extcodehash(rpc_url, target).await?,
+        ..Pins::default()
+    };
+    if !simulated.impl_slot_value.is_empty() {
+        pins.impl_slot_value = simulator::fetch_implementation_slot(rpc_url, target).await?;
+    }
+    if !simulated.implementation_codehash.is_empty() {
+        pins.implementation_codehash = proxy::resolve(rpc_url, target)
+            .await?
+            .map(|r| r.codehash)
+            .unwrap_or_default();
+    }
+    Ok(pins)
+}
+
+fn describe(hash: &str) -> &str {
+    if hash.is_empty() { "none" } else { hash }
+}
+
+/// Block if any pin changed between simulation and now.
+pub fn compare(target: &str, simulated: &Pins, current: &Pins) -> Result<(), String> {
+    if simulated.target_codehash != current.target_codehash {
+        return Err(format!(
+            "PLIMSOLL CODEHASH PIN: code at {} changed since simulation ({} → {}) — \
+             metamorphic contract swap",
+            target,
+            describe(&simulated.target_codehash),
+            describe(&current.target_codehash)
+        ));
+    }
+    if simulated.impl_slot_value != current.impl_slot_value {
+        return Err(format!(
+            "PLIMSOLL CODEHASH PIN: EIP-1967 implementation slot of {} changed since simulation \
+             ({} → {}) — proxy upgraded",
+            target,
+            describe(&simulated.impl_slot_value),
+            describe(&current.impl_slot_value)
+        ));
+    }
+    if simulated.implementation_codehash != current.implementation_codehash {
+        return Err(format!(
+            "PLIMSOLL CODEHASH PIN: implementation code behind {} changed since simulation \
+             ({} → {})",
+            target,
+            describe(&simulated.implementation_codehash),
+            describe(&current.implementation_codehash)
+        ));
+    }
+    Ok(())
+}
+
+/// Re-read and compare the simulation's pins for `target`.
+pub async fn enforce(config: &Config, target: &str, sim: &SimulationResult) -> Result<(), String> {
+    let simulated = Pins::simulated(sim);
+    let current = current(config, target, &simulated).await.map_err(|e| {
+        format!("PLIMSOLL CODEHASH PIN: could not re-read code pins of {target} before forwarding: {e}")
+    })?;
+    compare(target, &simulated, &current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET: &str = "0x1111111111111111111111111111111111111111";
+
+    fn pins(target: &str, slot: &str, implementation: &str) -> Pins {
+        Pins {
+            target_codehash: target.into(),
+            impl_slot_value: slot.into(),
+            implementation_codehash: implementation.into(),
+        }
+    }
+
+    #[test]
+    fn test_unchanged_pins_pass() {
+        let p = pins("0xaa", "0xbb", "0xcc");
+        assert!(compare(TARGET, &p, &p.clone()).is_ok());
+        assert!(compare(TARGET, &Pins::default(), &Pins::default()).is_ok());
+    }
+
+    #[test]
+    fn test_swapped_code_blocked() {
+        let err = compare(TARGET, &pins("0xaa", "", ""), &pins("0xdd", "", "")).unwrap_err();
+        assert!(err.contains("code at 0x1111111111111111111111111111111111111111 changed"));
+        assert!(err.contains("0xaa → 0xdd"));
+        // Code deployed to an address that was empty at simulation.
+        let err = compare(TARGET, &pins("", "", ""), &pins("0xdd", "", "")).unwrap_err();
+        assert!(err.contains("none → 0xdd"));
+    }
+
+    #[test]
+    fn test_proxy_upgrade_blocked() {
+        let err = compare(TARGET, &pins("0xaa", "0xbb", ""), &pins("0xaa", "0xee", "")).unwrap_err();
+        assert!(err.contains("proxy upgraded"));
+        let err = compare(TARGET, &pins("0xaa", "", "0xcc"), &pins("0xaa", "", "0xff")).unwrap_err();
+        assert!(err.contains("implementation code behind"));
+    }
+}
@@ -0,0 +1,78 @@
+//! Address-poisoning detection.
+//!
+//! Poisoners send dust (or zero-value `transferFrom`s) from vanity
+//! addresses that share the first and last hex characters of a victim's
+//! real counterparty — `0x1234…abcd` vs `0x1234…abcd`. An agent that picks
+//! recipients out of its own transaction history copies the wrong one.
+//!
+//! A new recipient whose prefix and suffix match one of the agent's known
+//! counterparties (see `counterparties`) but whose middle differs is a
+//! lookalike.
+
+use crate::config::Config;
+use crate::counterparties;
+
+/// Whether `a` and `b` differ but share `chars` leading and trailing hex
+/// characters.
+pub fn is_lookalike(a: &str, b: &str, chars: usize) -> bool {
+    let a = a.trim_start_matches("0x").to_lowercase();
+    let b = b.trim_start_matches("0x").to_lowercase();
+    if a == b || a.len() != 40 || b.len() != 40 || chars == 0 || chars * 2 >= 40 {
+        return false;
+    }
+    a[..chars] == b[..chars] && a[40 - chars..] == b[40 - chars..]
+}
+
+/// Flag a recipient that imitates one of `agent`'s known counterparties.
+pub fn check(config: &Config, agent: &str, to: &str, data: &[u8]) -> Result<(), String> {
+    let known = counterparties::known(agent);
+    for recipient in counterparties::recipients(to, data) {
+        if known.contains(&recipient) {
+            continue;
+        }
+        if let Some(real) = known
+            .iter()
+            .find(|k| is_lookalike(k, &recipient, config.poisoning_match_chars))
+        {
+            return Err(format!(
+                "PLIMSOLL ADDRESS POISONING: recipient {} imitates known counterparty {} \
+                 (same first/last {} hex chars) — likely copied from a poisoned history",
+                recipient, real, config.poisoning_match_chars
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi;
+
+    const REAL: &str = "0x1234a0b86991c6218b36c1d19d4a2e9eb0ceabcd";
+    const FAKE: &str = "0x1234ffffffffffffffffffffffffffffffffabcd";
+
+    #[test]
+    fn test_is_lookalike() {
+        assert!(is_lookalike(REAL, FAKE, 4));
+        assert!(!is_lookalike(REAL, REAL, 4));
+        assert!(!is_lookalike(REAL, FAKE, 5));
+        assert!(!is_lookalike(REAL, "0x1234", 4));
+    }
+
+    #[test]
+    fn test_poisoned_token_transfer_flagged() {
+        let agent = "0xa9e0000000000000000000000000000000000001";
+        let config = Config::default();
+        counterparties::record(agent, REAL, &[]);
+
+        // transfer(FAKE, 1)
+        let mut data = vec![0xa9, 0x05, 0x9c, 0xbb];
+        data.extend_from_slice(&abi::encode_address(FAKE).unwrap());
+        data.extend_from_slice(&abi::encode_uint(1));
+        let token = "0x2222222222222222222222222222222222222222";
+        assert!(check(&config, agent, token, &data).unwrap_err().contains(REAL));
+        assert!(check(&config, agent, REAL, &[]).is_ok());
+        assert!(check(&config, "0xa9e0000000000000000000000000000000000002", FAKE, &[]).is_ok());
+    }
+}
@@ -0,0 +1,385 @@
+//! Multi-asset portfolio accounting over simulation traces.
+//!
+//! The sandbox only sees native ETH. Draining an agent's USDC, or its
+//! Bored Ape, moves no ETH and passes a native-balance physics check. The
+//! upstream `callTracer` trace (with `withLog`) carries every token event
+//! the transaction emits; from it we derive, for the agent's portfolio
+//! (the sender plus `vault_addresses`):
+//!
+//!   - ERC-20 / ERC-721 / ERC-1155 balance deltas,
+//!   - ERC-20 `Approval` and `ApprovalForAll` grants and revocations.
+//!
+//! Events of reverted frames are ignored. Transfers between two portfolio
+//! accounts cancel out.
+//!
+//! With `portfolio_loss_accounting`, loss percentage is then computed in
+//! USD over the whole portfolio — native balance plus the balances of
+//! every priced ERC-20 the transaction touches — instead of ETH alone.
+//! NFTs have no price source and are reported, not valued.
+
+use crate::abi;
+use crate::config::Config;
+use crate::intents;
+use crate::oracle;
+use crate::types::{ApprovalDelta, AssetDelta, CallFrame, CallLog, TokenStandard};
+use alloy_primitives::U256;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+/// `Transfer(address,address,uint256)` — ERC-20 and ERC-721.
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+/// `TransferSingle(address,address,address,uint256,uint256)`
+const TRANSFER_SINGLE_TOPIC: &str = "0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+/// `TransferBatch(address,address,address,uint256[],uint256[])`
+const TRANSFER_BATCH_TOPIC: &str = "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+/// `Approval(address,address,uint256)`
+const APPROVAL_TOPIC: &str = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+/// `ApprovalForAll(address,address,bool)`
+const APPROVAL_FOR_ALL_TOPIC: &str = "0x17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31";
+/// `balanceOf(address)`
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// The agent's portfolio: the sender and its vaults.
+pub fn accounts(config: &Config, from: &str) -> Vec<String> {
+    let mut accounts: Vec<String> = config
+        .vault_addresses
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    accounts.push(from.to_lowercase());
+    accounts
+}
+
+fn topic_address(topic: &str) -> String {
+    let hex = topic.trim_start_matches("0x");
+    format!("0x{}", hex.get(24..).unwrap_or_default().to_lowercase())
+}
+
+fn topic_uint(topic: &str) -> U256 {
+    U256::from_str_radix(topic.trim_start_matches("0x"), 16).unwrap_or_default()
+}
+
+fn log_data(log: &CallLog) -> Vec<u8> {
+    hex::decode(log.data.trim_start_matches("0x")).unwrap_or_default()
+}
+
+/// Portfolio flows keyed by (standard, token, id) → (sent, received).
+type Flows = BTreeMap<(TokenStandard, String, Option<U256>), (U256, U256)>;
+
+fn flow(
+    flows: &mut Flows,
+    accounts: &[String],
+    key: (TokenStandard, String, Option<U256>),
+    from: &str,
+    to: &str,
+    amount: U256,
+) {
+    let sent = accounts.iter().any(|a| a == from);
+    let received = accounts.iter().any(|a| a == to);
+    if sent == received {
+        return;
+    }
+    let entry = flows.entry(key).or_default();
+    if sent {
+        entry.0 = entry.0.saturating_add(amount);
+    } else {
+        entry.1 = entry.1.saturating_add(amount);
+    }
+}
+
+fn scan_log(log: &CallLog, accounts: &[String], flows: &mut Flows, approvals: &mut Vec<ApprovalDelta>) {
+    let token = log.address.to_lowercase();
+    let topics = &log.topics;
+    let Some(topic0) = topics.first().map(|t| t.to_lowercase()) else {
+        return;
+    };
+    let data = log_data(log);
+    match (topic0.as_str(), topics.len()) {
+        (TRANSFER_TOPIC, 3) => {
+            let amount = abi::uint(&data, 0).unwrap_or_default();
+            let key = (TokenStandard::Erc20, token, None);
+            flow(flows, accounts, key, &topic_address(&topics[1]), &topic_address(&topics[2]), amount);
+        }
+        (TRANSFER_TOPIC, 4) => {
+            let key = (TokenStandard::Erc721, token, Some(topic_uint(&topics[3])));
+            flow(flows, accounts, key, &topic_address(&topics[1]), &topic_address(&topics[2]), U256::from(1));
+        }
+        (TRANSFER_SINGLE_TOPIC, 4) => {
+            let (Ok(id), Ok(amount)) = (abi::uint(&data, 0), abi::uint(&data, 1)) else {
+                return;
+            };
+            let key = (TokenStandard::Erc1155, token, Some(id));
+            flow(flows, accounts, key, &topic_address(&topics[2]), &topic_address(&topics[3]), amount);
+        }
+        (TRANSFER_BATCH_TOPIC, 4) => {
+            let (Ok(ids), Ok(amounts)) = (abi::uint_array(&data, 0), abi::uint_array(&data, 1)) else {
+                return;
+            };
+            let (from, to) = (topic_address(&topics[2]), topic_address(&topics[3]));
+            for (id, amount) in ids.into_iter().zip(amounts) {
+                flow(flows, accounts, (TokenStandard::Erc1155, token.clone(), Some(id)), &from, &to, amount);
+            }
+        }
+        (APPROVAL_TOPIC, 3) => {
+            let owner = topic_address(&topics[1]);
+            if accounts.contains(&owner) {
+                let amount = abi::uint(&data, 0).unwrap_or_default();
+                approvals.push(ApprovalDelta {
+                    owner,
+                    token,
+                    spender: topic_address(&topics[2]),
+                    amount: Some(amount),
+                    approved: !amount.is_zero(),
+                });
+            }
+        }
+        (APPROVAL_FOR_ALL_TOPIC, 3) => {
+            let owner = topic_address(&topics[1]);
+            if accounts.contains(&owner) {
+                approvals.push(ApprovalDelta {
+                    owner,
+                    token,
+                    spender: topic_address(&topics[2]),
+                    amount: None,
+                    approved: !abi::uint(&data, 0).unwrap_or_default().is_zero(),
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Token and approval deltas of the portfolio `accounts` (lowercase) in
+/// `trace`.
+pub fn deltas(trace: &CallFrame, accounts: &[String]) -> (Vec<AssetDelta>, Vec<ApprovalDelta>) {
+    fn walk(frame: &CallFrame, accounts: &[String], flows: &mut Flows, approvals: &mut Vec<ApprovalDelta>) {
+        if frame.error.is_some() {
+            return;
+        }
+        for log in &frame.logs {
+            scan_log(log, accounts, flows, approvals);
+        }
+        for call in &frame.calls {
+            walk(call, accounts, flows, approvals);
+        }
+    }
+    let mut flows = Flows::new();
+    let mut approvals = Vec::new();
+    walk(trace, accounts, &mut flows, &mut approvals);
+    let assets = flows
+        .into_iter()
+        .map(|((standard, token, token_id), (sent, received))| AssetDelta {
+            standard,
+            token,
+            token_id,
+            sent,
+            received,
+        })
+        .collect();
+    (assets, approvals)
+}
+
+/// ERC-20 balance of `account` in `token`.
+pub async fn balance_of(rpc_url: &str, token: &str, account: &str) -> Result<U256> {
+    let mut call = BALANCE_OF.to_vec();
+    call.extend(abi::encode_address(account).map_err(anyhow::Error::msg)?);
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_call",
+        "params": [{ "to": token, "data": format!("0x{}", hex::encode(call)) }, "latest"],
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to call balanceOf")?
+        .json()
+        .await
+        .context("Failed to parse balanceOf response")?;
+    let ret = hex::decode(body["result"].as_str().context("balanceOf reverted")?.trim_start_matches("0x"))
+        .context("balanceOf returned invalid hex")?;
+    abi::uint(&ret, 0).map_err(anyhow::Error::msg)
+}
+
+/// USD the portfolio loses: the native outflow plus the net outflow of
+/// every priced ERC-20 the simulation moved. `None` when the native token
+/// is unpriced.
+pub async fn loss_usd(
+    config: &Config,
+    balance_before: u128,
+    balance_after: u128,
+    assets: &[AssetDelta],
+) -> Option<f64> {
+    let native = oracle::price(config, &config.native_price_token).await?;
+    let mut loss = intents::usd_value(U256::from(balance_before), &native)
+        - intents::usd_value(U256::from(balance_after), &native);
+    for asset in assets.iter().filter(|a| a.standard == TokenStandard::Erc20) {
+        if let Some(price) = oracle::price(config, &asset.token).await {
+            loss += intents::usd_value(asset.sent, &price) - intents::usd_value(asset.received, &price);
+        }
+    }
+    Some(loss.max(0.0))
+}
+
+/// Loss as a percentage of the portfolio's USD value: native balance plus
+/// every priced ERC-20 the simulation moved. `None` when the native token
+/// is unpriced or the portfolio has no value — the caller keeps the
+/// native-only figure.
+pub async fn loss_pct(
+    config: &Config,
+    accounts: &[String],
+    balance_before: u128,
+    balance_after: u128,
+    assets: &[AssetDelta],
+) -> Option<f64> {
+    let native = oracle::price(config, &config.native_price_token).await?;
+    let mut value = intents::usd_value(U256::from(balance_before), &native);
+    let mut loss = value - intents::usd_value(U256::from(balance_after), &native);
+
+    for asset in assets.iter().filter(|a| a.standard == TokenStandard::Erc20) {
+        let Some(price) = oracle::price(config, &asset.token).await else {
+            tracing::debug!(token = %asset.token, "Unpriced token left out of portfolio loss");
+            continue;
+        };
+        let mut held = U256::ZERO;
+        for account in accounts {
+            match balance_of(&config.upstream_rpc_url, &asset.token, account).await {
+                Ok(balance) => held = held.saturating_add(balance),
+                Err(e) => tracing::debug!(error = %e, token = %asset.token, "balanceOf failed"),
+            }
+        }
+        value += intents::usd_value(held, &price);
+        loss += intents::usd_value(asset.sent, &price) - intents::usd_value(asset.received, &price);
+    }
+
+    if value <= 0.0 {
+        return None;
+    }
+    Some((loss.max(0.0) / value * 100.0).min(100.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: &str = "0xa9e0000000000000000000000000000000000001";
+    const VAULT: &str = "0x1111111111111111111111111111111111111111";
+    const TOKEN: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const NFT: &str = "0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d";
+    const ATTACKER: &str = "0x6666666666666666666666666666666666666666";
+
+    fn topic(address: &str) -> String {
+        format!("0x{:0>64}", address.trim_start_matches("0x"))
+    }
+
+    fn word(v: u64) -> String {
+        format!("{v:064x}")
+    }
+
+    fn log(address: &str, topics: Vec<String>, data: String) -> CallLog {
+        CallLog { address: address.into(), topics, data: format!("0x{data}") }
+    }
+
+    fn frame(logs: Vec<CallLog>, error: Option<&str>) -> CallFrame {
+        CallFrame {
+            call_type: "CALL".into(),
+            from: AGENT.into(),
+            to: TOKEN.into(),
+            error: error.map(String::from),
+            logs,
+            ..CallFrame::default()
+        }
+    }
+
+    fn accounts() -> Vec<String> {
+        vec![VAULT.to_string(), AGENT.to_string()]
+    }
+
+    #[test]
+    fn test_token_and_nft_outflows() {
+        let trace = frame(
+            vec![
+                log(TOKEN, vec![TRANSFER_TOPIC.into(), topic(VAULT), topic(ATTACKER)], word(500)),
+                log(TOKEN, vec![TRANSFER_TOPIC.into(), topic(ATTACKER), topic(AGENT)], word(20)),
+                // Vault → agent: internal, cancels out.
+                log(TOKEN, vec![TRANSFER_TOPIC.into(), topic(VAULT), topic(AGENT)], word(1000)),
+                log(NFT, vec![TRANSFER_TOPIC.into(), topic(AGENT), topic(ATTACKER), word(42)], String::new()),
+                log(
+                    NFT,
+                    vec![TRANSFER_SINGLE_TOPIC.into(), topic(AGENT), topic(AGENT), topic(ATTACKER)],
+                    format!("{}{}", word(7), word(3)),
+                ),
+            ],
+            None,
+        );
+        let (assets, approvals) = deltas(&trace, &accounts());
+        assert!(approvals.is_empty());
+        assert_eq!(
+            assets,
+            vec![
+                AssetDelta {
+                    standard: TokenStandard::Erc20,
+                    token: TOKEN.into(),
+                    token_id: None,
+                    sent: U256::from(500),
+                    received: U256::from(20),
+                },
+                AssetDelta {
+                    standard: TokenStandard::Erc721,
+                    token: NFT.into(),
+                    token_id: Some(U256::from(42)),
+                    sent: U256::from(1),
+                    received: U256::ZERO,
+                },
+                AssetDelta {
+                    standard: TokenStandard::Erc1155,
+                    token: NFT.into(),
+                    token_id: Some(U256::from(7)),
+                    sent: U256::from(3),
+                    received: U256::ZERO,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_erc1155_batch_decoded() {
+        // ids = [1, 2], values = [10, 20]
+        let data = [64, 160, 2, 1, 2, 2, 10, 20].iter().map(|&v| word(v)).collect::<String>();
+        let trace = frame(
+            vec![log(NFT, vec![TRANSFER_BATCH_TOPIC.into(), topic(AGENT), topic(AGENT), topic(ATTACKER)], data)],
+            None,
+        );
+        let (assets, _) = deltas(&trace, &accounts());
+        assert_eq!(assets.len(), 2);
+        assert_eq!(assets[1].token_id, Some(U256::from(2)));
+        assert_eq!(assets[1].sent, U256::from(20));
+    }
+
+    #[test]
+    fn test_approvals_and_reverted_frames() {
+        let mut trace = frame(
+            vec![
+                log(TOKEN, vec![APPROVAL_TOPIC.into(), topic(AGENT), topic(ATTACKER)], "f".repeat(64)),
+                log(NFT, vec![APPROVAL_FOR_ALL_TOPIC.into(), topic(VAULT), topic(ATTACKER)], word(1)),
+                // Someone else's approval is not the portfolio's concern.
+                log(TOKEN, vec![APPROVAL_TOPIC.into(), topic(ATTACKER), topic(AGENT)], word(5)),
+            ],
+            None,
+        );
+        trace.calls.push(frame(
+            vec![log(TOKEN, vec![TRANSFER_TOPIC.into(), topic(AGENT), topic(ATTACKER)], word(9))],
+            Some("execution reverted"),
+        ));
+        let (assets, approvals) = deltas(&trace, &accounts());
+        assert!(assets.is_empty());
+        assert_eq!(approvals.len(), 2);
+        assert_eq!(approvals[0].amount, Some(U256::MAX));
+        assert!(approvals[0].approved);
+        assert_eq!(approvals[1].owner, VAULT);
+        assert_eq!(approvals[1].amount, None);
+    }
+}
@@ -0,0 +1,236 @@
+//! Per-class protection modes.
+//!
+//! The interception classes below are configured independently instead
+//! of always blocking:
+//!
+//! | Class                         | Config             | Engine          |
+//! |-------------------------------|--------------------|-----------------|
+//! | Raw message signing           | `raw_sign_mode`    | `raw_sign`      |
+//! | Dangerous EIP-712 types       | `typed_data_mode`  | `eip712_permit` |
+//! | Physics violations            | `physics_mode`     | `physics`       |
+//! | Threat feed (Engine 0) hits   | `threat_feed_mode` | `engine0`       |
+//!
+//! - `block` (default): the request is blocked.
+//! - `warn`: the verdict is logged and the request forwarded.
+//! - `approve`: the request is blocked and queued for an operator
+//!   (`GET /admin/approvals`, `POST /admin/approvals/review`). Once
+//!   approved, the same request resubmitted within `approval_ttl_secs`
+//!   passes that engine — once. Approvals are per engine: a request held
+//!   by two engines needs both. An approval is used up only when the
+//!   request is forwarded; if another engine blocks it, it stays.
+//!
+//! Every other engine always blocks. Queued requests are kept in memory
+//! only: a restart drops them, and the agent resubmits.
+
+use crate::config::Config;
+use alloy_primitives::keccak256;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Accepted values of the `*_mode` settings.
+pub const MODES: &[&str] = &["block", "warn", "approve"];
+
+/// Requests kept; the oldest go first.
+const MAX_REQUESTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Block,
+    Warn,
+    Approve,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApprovalRequest {
+    pub id: String,
+    pub engine: String,
+    pub method: String,
+    pub reason: String,
+    /// Hash of the request's method and params; a resubmission matches it.
+    pub fingerprint: String,
+    pub queued_at: u64,
+    pub reviewed_at: Option<u64>,
+    pub status: ApprovalStatus,
+    /// Relied on by a request still being evaluated.
+    #[serde(skip)]
+    claimed: bool,
+}
+
+lazy_static! {
+    static ref REQUESTS: Mutex<VecDeque<ApprovalRequest>> = Mutex::new(VecDeque::new());
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// The protection mode of blocks by `engine`.
+pub fn mode(config: &Config, engine: &str) -> Mode {
+    let setting = match engine {
+        "raw_sign" => &config.raw_sign_mode,
+        "eip712_permit" => &config.typed_data_mode,
+        "physics" => &config.physics_mode,
+        "engine0" => &config.threat_feed_mode,
+        _ => return Mode::Block,
+    };
+    match setting.as_str() {
+        "warn" => Mode::Warn,
+        "approve" => Mode::Approve,
+        _ => Mode::Block,
+    }
+}
+
+/// Fingerprint of a request with `method` and `params`.
+pub fn fingerprint(method: &str, params: &Value) -> String {
+    format!("0x{}", hex::encode(keccak256(format!("{method}:{params}"))))
+}
+
+/// Whether `request` has outlived `approval_ttl_secs` at `now`.
+fn expired(config: &Config, request: &ApprovalRequest, now: u64) -> bool {
+    let since = request.reviewed_at.unwrap_or(request.queued_at);
+    now.saturating_sub(since) >= config.approval_ttl_secs
+}
+
+/// Queue the request with `fingerprint` for approval by `engine`; the
+/// request already pending for both, if any, is returned instead.
+pub fn queue(config: &Config, engine: &str, method: &str, reason: &str, fingerprint: String) -> ApprovalRequest {
+    let now = now();
+    let mut requests = REQUESTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    requests.retain(|r| !expired(config, r, now));
+    if let Some(pending) = requests
+        .iter()
+        .find(|r| r.fingerprint == fingerprint && r.engine == engine && r.status == ApprovalStatus::Pending)
+    {
+        return pending.clone();
+    }
+    if requests.len() >= MAX_REQUESTS {
+        requests.pop_front();
+    }
+    let request = ApprovalRequest {
+        id: format!("apr-{:x}-{}", now, NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+        engine: engine.to_string(),
+        method: method.to_string(),
+        reason: reason.to_string(),
+        fingerprint,
+        queued_at: now,
+        reviewed_at: None,
+        status: ApprovalStatus::Pending,
+        claimed: false,
+    };
+    requests.push_back(request.clone());
+    request
+}
+
+/// Claim `engine`'s approval of the request with `fingerprint`, within
+/// `approval_ttl_secs` of the review. A claimed approval can't be claimed
+/// again until it is `release`d; `consume` uses it up.
+pub fn claim(config: &Config, fingerprint: &str, engine: &str) -> bool {
+    let now = now();
+    let Ok(mut requests) = REQUESTS.lock() else {
+        return false;
+    };
+    let approved = requests.iter_mut().find(|r| {
+        r.fingerprint == fingerprint
+            && r.engine == engine
+            && r.status == ApprovalStatus::Approved
+            && !r.claimed
+            && !expired(config, r, now)
+    });
+    approved.map(|r| r.claimed = true).is_some()
+}
+
+/// Use up a claimed approval: the request was forwarded.
+pub fn consume(fingerprint: &str, engine: &str) {
+    if let Ok(mut requests) = REQUESTS.lock() {
+        requests.retain(|r| !(r.fingerprint == fingerprint && r.engine == engine && r.claimed));
+    }
+}
+
+/// Give a claimed approval back: the request was blocked after all.
+pub fn release(fingerprint: &str, engine: &str) {
+    if let Ok(mut requests) = REQUESTS.lock() {
+        requests
+            .iter_mut()
+            .filter(|r| r.fingerprint == fingerprint && r.engine == engine)
+            .for_each(|r| r.claimed = false);
+    }
+}
+
+/// Queued requests, newest first.
+pub fn list() -> Vec<ApprovalRequest> {
+    REQUESTS
+        .lock()
+        .map(|requests| requests.iter().rev().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Approve or reject the pending request `id`.
+pub fn review(id: &str, approve: bool) -> Option<ApprovalRequest> {
+    let mut requests = REQUESTS.lock().ok()?;
+    let request = requests
+        .iter_mut()
+        .find(|r| r.id == id && r.status == ApprovalStatus::Pending)?;
+    request.status = if approve { ApprovalStatus::Approved } else { ApprovalStatus::Rejected };
+    request.reviewed_at = Some(now());
+    Some(request.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_modes() {
+        let config = Config {
+            raw_sign_mode: "warn".into(),
+            physics_mode: "approve".into(),
+            ..Config::default()
+        };
+        assert_eq!(mode(&config, "raw_sign"), Mode::Warn);
+        assert_eq!(mode(&config, "physics"), Mode::Approve);
+        assert_eq!(mode(&config, "engine0"), Mode::Block);
+        assert_eq!(mode(&config, "velocity"), Mode::Block);
+    }
+
+    #[test]
+    fn test_approval_is_consumed_once() {
+        let config = Config::default();
+        let params = json!(["0xA9E0000000000000000000000000000000002110", "0xdeadbeef"]);
+        let fp = fingerprint("eth_sign", &params);
+        assert_ne!(fp, fingerprint("personal_sign", &params));
+
+        let queued = queue(&config, "raw_sign", "eth_sign", "GOD-TIER 1: test", fp.clone());
+        assert_eq!(queue(&config, "raw_sign", "eth_sign", "GOD-TIER 1: test", fp.clone()).id, queued.id);
+        assert!(!claim(&config, &fp, "raw_sign"));
+
+        let reviewed = review(&queued.id, true).unwrap();
+        assert_eq!(reviewed.status, ApprovalStatus::Approved);
+        assert!(review(&queued.id, false).is_none());
+        // Another engine's approval is separate.
+        assert!(!claim(&config, &fp, "eip712_permit"));
+        assert!(claim(&config, &fp, "raw_sign"));
+        assert!(!claim(&config, &fp, "raw_sign"));
+        // Blocked by a later engine: the approval comes back.
+        release(&fp, "raw_sign");
+        assert!(claim(&config, &fp, "raw_sign"));
+        consume(&fp, "raw_sign");
+        release(&fp, "raw_sign");
+        assert!(!claim(&config, &fp, "raw_sign"));
+    }
+}
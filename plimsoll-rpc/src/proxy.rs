@@ -0,0 +1,168 @@
+//! Upgradeable proxy implementation resolution.
+//!
+//! Calls into an upgradeable proxy execute the *implementation's* code, but
+//! every check before this only ever saw the thin proxy address — a proxy
+//! pointed at a blacklisted drainer passed Engine 0. The implementation is
+//! resolved from the standard storage slots:
+//!
+//!   - EIP-1967 implementation slot (transparent / UUPS proxies)
+//!   - EIP-1967 beacon slot, then `beacon.implementation()`
+//!   - EIP-1822 `PROXIABLE` slot (legacy UUPS)
+//!
+//! and its codehash pinned alongside the proxy's so the state-delta record
+//! covers both.
+
+use alloy_primitives::keccak256;
+use anyhow::{Context, Result};
+
+/// `bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)`
+const EIP1967_IMPLEMENTATION_SLOT: &str =
+    "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+/// `bytes32(uint256(keccak256("eip1967.proxy.beacon")) - 1)`
+const EIP1967_BEACON_SLOT: &str =
+    "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+/// `keccak256("PROXIABLE")`
+const EIP1822_PROXIABLE_SLOT: &str =
+    "0xc5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7";
+/// `implementation()`
+const BEACON_IMPLEMENTATION_CALL: &str = "0x5c60da1b";
+/// `masterCopy()` — answered by the Gnosis Safe proxy itself.
+const SAFE_MASTER_COPY_CALL: &str = "0xa619486e";
+
+/// How the implementation was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyKind {
+    Eip1967,
+    Beacon,
+    Eip1822,
+}
+
+impl ProxyKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Eip1967 => "EIP-1967",
+            Self::Beacon => "EIP-1967 beacon",
+            Self::Eip1822 => "EIP-1822",
+        }
+    }
+}
+
+/// A resolved proxy implementation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyResolution {
+    pub kind: ProxyKind,
+    pub implementation: String,
+    /// keccak256 of the implementation's runtime code; empty if it has none.
+    pub codehash: String,
+}
+
+async fn upstream_call(rpc_url: &str, method: &str, params: serde_json::Value) -> Result<String> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .with_context(|| format!("Failed to call {method}"))?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {method} response"))?;
+    Ok(body["result"].as_str().unwrap_or("0x").to_string())
+}
+
+/// Interpret a 32-byte word as an address; `None` if zero or not an
+/// address (dirty high bytes).
+fn word_to_address(word: &str) -> Option<String> {
+    let hex = word.trim_start_matches("0x");
+    if hex.len() != 64 || !hex[..24].chars().all(|c| c == '0') {
+        return None;
+    }
+    let addr = &hex[24..];
+    if addr.chars().all(|c| c == '0') {
+        return None;
+    }
+    Some(format!("0x{}", addr.to_lowercase()))
+}
+
+async fn read_slot(rpc_url: &str, address: &str, slot: &str) -> Result<Option<String>> {
+    let word = upstream_call(rpc_url, "eth_getStorageAt", serde_json::json!([address, slot, "latest"])).await?;
+    Ok(word_to_address(&word))
+}
+
+/// Hash of an address's runtime code, or empty if it has none.
+pub fn codehash(code: &[u8]) -> String {
+    if code.is_empty() {
+        String::new()
+    } else {
+        format!("0x{}", hex::encode(keccak256(code)))
+    }
+}
+
+/// Resolve the implementation behind `proxy`. `Ok(None)` if it is not a
+/// recognised proxy.
+pub async fn resolve(rpc_url: &str, proxy: &str) -> Result<Option<ProxyResolution>> {
+    let (kind, implementation) =
+        if let Some(imp) = read_slot(rpc_url, proxy, EIP1967_IMPLEMENTATION_SLOT).await? {
+            (ProxyKind::Eip1967, imp)
+        } else if let Some(beacon) = read_slot(rpc_url, proxy, EIP1967_BEACON_SLOT).await? {
+            let word = upstream_call(
+                rpc_url,
+                "eth_call",
+                serde_json::json!([{ "to": beacon, "data": BEACON_IMPLEMENTATION_CALL }, "latest"]),
+            )
+            .await?;
+            let imp = word_to_address(&word)
+                .with_context(|| format!("beacon {beacon} returned no implementation"))?;
+            (ProxyKind::Beacon, imp)
+        } else if let Some(imp) = read_slot(rpc_url, proxy, EIP1822_PROXIABLE_SLOT).await? {
+            (ProxyKind::Eip1822, imp)
+        } else {
+            return Ok(None);
+        };
+
+    let code = crate::simulator::fetch_code(rpc_url, &implementation).await?;
+    Ok(Some(ProxyResolution { kind, implementation, codehash: codehash(&code) }))
+}
+
+/// The singleton a Gnosis Safe proxy delegates to. `Ok(None)` if `safe`
+/// is not a Safe proxy.
+pub async fn safe_singleton(rpc_url: &str, safe: &str) -> Result<Option<String>> {
+    let word = upstream_call(
+        rpc_url,
+        "eth_call",
+        serde_json::json!([{ "to": safe, "data": SAFE_MASTER_COPY_CALL }, "latest"]),
+    )
+    .await?;
+    Ok(word_to_address(&word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_to_address() {
+        let word = "0x000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+        assert_eq!(
+            word_to_address(word).as_deref(),
+            Some("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48")
+        );
+        assert_eq!(word_to_address(&format!("0x{}", "0".repeat(64))), None);
+        assert_eq!(word_to_address(&format!("0x{}", "f".repeat(64))), None);
+        assert_eq!(word_to_address("0x"), None);
+    }
+
+    #[test]
+    fn test_codehash_empty_for_eoa() {
+        assert_eq!(codehash(&[]), "");
+        assert_eq!(
+            codehash(&[0x00]),
+            "0xbc36789e7a1e281436464229828f817d6612f7b477d66591ff96a9e064bcc98a"
+        );
+    }
+}
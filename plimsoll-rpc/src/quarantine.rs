@@ -0,0 +1,237 @@
+//! Sanitizer quarantine.
+//!
+//! The read-path sanitizer (`sanitizer`) scrubs control tokens before the
+//! agent sees them — and with them the evidence. Every tainted or withheld
+//! response is kept here: the original `result`, the patterns matched and
+//! the contracts it came from. An agent (or its operator's tooling) calls
+//!
+//! ```text
+//! plimsoll_getSanitizerIncidents(source?)
+//! ```
+//!
+//! for the incidents, newest first, optionally only those from one
+//! contract. The original comes back base64-encoded: the method answers
+//! the agent, and the tokens must not reach its context as text a second
+//! time. Each source is also reported as a `sanitizer` IOC.
+
+use crate::config::{is_hex_address, Config};
+use crate::telemetry;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Incidents kept; the oldest go first.
+const MAX_INCIDENTS: usize = 500;
+
+/// Longest original payload kept, in bytes of JSON.
+const MAX_ORIGINAL_BYTES: usize = 256 * 1024;
+
+/// Methods whose first param is a call object naming the contract.
+const CALL_METHODS: &[&str] = &["eth_call", "debug_traceCall", "trace_call"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SanitizerIncident {
+    pub id: String,
+    pub method: String,
+    /// Contracts the tainted data came from, lowercase.
+    pub sources: Vec<String>,
+    pub patterns: Vec<String>,
+    pub details: Vec<String>,
+    /// The unsanitized `result` as JSON, base64-encoded.
+    pub original_base64: String,
+    /// Whether `original_base64` was cut at `MAX_ORIGINAL_BYTES`.
+    pub truncated: bool,
+    /// Whether the response was withheld (a `block` pattern).
+    pub withheld: bool,
+    pub recorded_at: u64,
+}
+
+lazy_static! {
+    static ref INCIDENTS: Mutex<VecDeque<SanitizerIncident>> = Mutex::new(VecDeque::new());
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The pattern quoted in a sanitizer detail or block reason.
+fn pattern_of(detail: &str) -> Option<&str> {
+    let (_, rest) = detail.split_once('\'')?;
+    rest.rsplit_once("' found").or_else(|| rest.rsplit_once('\'')).map(|(p, _)| p)
+}
+
+/// Addresses (`address` of a log, `to` of a trace frame) of the objects
+/// in `original` the sanitizer changed.
+fn changed_sources(original: &Value, sanitized: &Value, out: &mut Vec<String>) {
+    if original == sanitized {
+        return;
+    }
+    match (original, sanitized) {
+        (Value::Object(before), Value::Object(after)) => {
+            for key in ["address", "to"] {
+                if let Some(address) = before.get(key).and_then(Value::as_str).filter(|a| is_hex_address(a)) {
+                    out.push(address.to_lowercase());
+                    break;
+                }
+            }
+            for (key, value) in before {
+                if let Some(other) = after.get(key) {
+                    changed_sources(value, other, out);
+                }
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for (value, other) in before.iter().zip(after) {
+                changed_sources(value, other, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Contracts a tainted response came from.
+fn sources(method: &str, params: &Value, original: &Value, sanitized: Option<&Value>) -> Vec<String> {
+    let mut out = Vec::new();
+    if CALL_METHODS.contains(&method) {
+        if let Some(to) = params.get(0).and_then(|c| c.get("to")).and_then(Value::as_str) {
+            out.push(to.to_lowercase());
+        }
+    }
+    if let Some(sanitized) = sanitized {
+        changed_sources(original, sanitized, &mut out);
+    }
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Quarantine a response the sanitizer changed (`sanitized` = its result)
+/// or withheld (`None`), and report its sources as IOCs.
+pub fn record(
+    config: &Config,
+    method: &str,
+    params: &Value,
+    original: &Value,
+    sanitized: Option<&Value>,
+    details: &[String],
+) -> SanitizerIncident {
+    let mut patterns: Vec<String> = details.iter().filter_map(|d| pattern_of(d)).map(str::to_string).collect();
+    patterns.sort();
+    patterns.dedup();
+    let mut json = serde_json::to_string(original).unwrap_or_default();
+    let truncated = json.len() > MAX_ORIGINAL_BYTES;
+    if truncated {
+        let mut cut = MAX_ORIGINAL_BYTES;
+        while !json.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        json.truncate(cut);
+    }
+    let recorded_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let incident = SanitizerIncident {
+        id: format!("san-{:x}-{}", recorded_at, NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+        method: method.to_string(),
+        sources: sources(method, params, original, sanitized),
+        patterns,
+        details: details.to_vec(),
+        original_base64: STANDARD.encode(json),
+        truncated,
+        withheld: sanitized.is_none(),
+        recorded_at,
+    };
+    warn!(
+        id = %incident.id,
+        method = %incident.method,
+        sources = ?incident.sources,
+        patterns = ?incident.patterns,
+        "PATCH 1 (TROJAN RECEIPT): Tainted read response quarantined"
+    );
+
+    let call = params.get(0);
+    let from = call.and_then(|c| c.get("from")).and_then(Value::as_str).unwrap_or("");
+    let data = call
+        .and_then(|c| c.get("data").or_else(|| c.get("input")))
+        .and_then(Value::as_str)
+        .and_then(|d| hex::decode(d.trim_start_matches("0x")).ok())
+        .unwrap_or_default();
+    let reason = format!(
+        "PLIMSOLL SANITIZER: control tokens {} in {} response",
+        incident.patterns.join(", "),
+        method
+    );
+    for source in &incident.sources {
+        let ioc = telemetry::extract_ioc(from, source, &data, "sanitizer", &reason, None, 1);
+        telemetry::uplink_ioc(&ioc, config);
+    }
+
+    if let Ok(mut incidents) = INCIDENTS.lock() {
+        if incidents.len() >= MAX_INCIDENTS {
+            incidents.pop_front();
+        }
+        incidents.push_back(incident.clone());
+    }
+    incident
+}
+
+/// Quarantined incidents, newest first; only `source`'s when given.
+pub fn list(source: Option<&str>) -> Vec<SanitizerIncident> {
+    let source = source.map(str::to_lowercase);
+    INCIDENTS
+        .lock()
+        .map(|incidents| {
+            incidents
+                .iter()
+                .rev()
+                .filter(|i| source.as_ref().is_none_or(|s| i.sources.contains(s)))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pattern_of_details() {
+        assert_eq!(pattern_of("TROJAN RECEIPT: Control token '[INST]' found at result[0].data"), Some("[INST]"));
+        assert_eq!(
+            pattern_of("PLIMSOLL SANITIZER: response withheld — it contains blocked pattern 'it's'"),
+            Some("it's")
+        );
+        assert_eq!(pattern_of("no quotes"), None);
+    }
+
+    #[test]
+    fn test_incident_sources_and_original() {
+        let original = json!([
+            {"address": "0x1111111111111111111111111111111111111111", "data": "clean"},
+            {"address": "0x2222222222222222222222222222222222222222", "data": "<|im_start|>"},
+        ]);
+        let sanitized = json!([
+            {"address": "0x1111111111111111111111111111111111111111", "data": "clean"},
+            {"address": "0x2222222222222222222222222222222222222222", "data": "[SANITIZED]"},
+        ]);
+        let details = vec!["TROJAN RECEIPT: Control token '<|im_start|>' found at result[1].data".to_string()];
+        let incident = record(&Config::default(), "eth_getLogs", &json!([{}]), &original, Some(&sanitized), &details);
+        assert_eq!(incident.sources, vec!["0x2222222222222222222222222222222222222222"]);
+        assert_eq!(incident.patterns, vec!["<|im_start|>"]);
+        assert!(!incident.withheld);
+        let decoded: Value = serde_json::from_slice(&STANDARD.decode(&incident.original_base64).unwrap()).unwrap();
+        assert_eq!(decoded, original);
+        assert!(list(Some("0x2222222222222222222222222222222222222222")).iter().any(|i| i.id == incident.id));
+        assert!(!list(Some("0x1111111111111111111111111111111111111111")).iter().any(|i| i.id == incident.id));
+
+        let call = json!([{"to": "0x3333333333333333333333333333333333333333", "data": "0x06fdde03"}]);
+        let incident = record(&Config::default(), "eth_call", &call, &json!("0x00"), None, &[]);
+        assert_eq!(incident.sources, vec!["0x3333333333333333333333333333333333333333"]);
+        assert!(incident.withheld);
+    }
+}
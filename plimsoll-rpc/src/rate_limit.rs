@@ -0,0 +1,170 @@
+//! Per-agent, per-method-class rate limiting.
+//!
+//! A prompt-injected agent stuck in a loop can spam `eth_sendTransaction`
+//! or `eth_signTypedData_v4` hundreds of times a second. Every send burns a
+//! full revm simulation and every read is forwarded to the paid upstream
+//! provider. A token bucket per `(agent, method class)` throttles the loop
+//! before it exhausts either.
+//!
+//! Limits are configured per class in requests per minute (0 = unlimited);
+//! `rate_limit_burst` caps the bucket size. Unauthenticated callers share
+//! one `anonymous` bucket per class.
+
+use crate::config::Config;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// JSON-RPC error code for a throttled request (EIP-1474 "limit exceeded").
+pub const LIMIT_EXCEEDED_CODE: i64 = -32005;
+
+/// Bucket key used when agent authentication is disabled.
+pub const ANONYMOUS_AGENT: &str = "anonymous";
+
+/// Method classes with independent budgets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MethodClass {
+    Send,
+    Sign,
+    Read,
+}
+
+impl MethodClass {
+    pub fn of(method: &str) -> Self {
+        match method {
+            "eth_sendTransaction" | "eth_sendRawTransaction" | "sendTransaction" => Self::Send,
+            "eth_sign" | "personal_sign" | "eth_signTypedData" | "eth_signTypedData_v3"
+            | "eth_signTypedData_v4" => Self::Sign,
+            _ => Self::Read,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Send => "send",
+            Self::Sign => "sign",
+            Self::Read => "read",
+        }
+    }
+
+    fn per_minute(self, config: &Config) -> u32 {
+        match self {
+            Self::Send => config.rate_limit_send_per_min,
+            Self::Sign => config.rate_limit_sign_per_min,
+            Self::Read => config.rate_limit_read_per_min,
+        }
+    }
+}
+
+/// Classic token bucket.
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, now: Instant) -> Self {
+        Self { tokens: capacity, last_refill: now }
+    }
+
+    /// Refill at `per_sec`, capped at `capacity`, then take one token.
+    fn try_take(&mut self, capacity: f64, per_sec: f64, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_sec).min(capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+lazy_static! {
+    static ref BUCKETS: Mutex<HashMap<(String, MethodClass), TokenBucket>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Check and consume one request from `agent`'s budget for `method`.
+/// Returns `Err(message)` when the request must be throttled.
+pub fn check(config: &Config, agent: &str, method: &str) -> Result<(), String> {
+    check_at(config, agent, method, Instant::now())
+}
+
+fn check_at(config: &Config, agent: &str, method: &str, now: Instant) -> Result<(), String> {
+    let class = MethodClass::of(method);
+    let per_min = class.per_minute(config);
+    if per_min == 0 {
+        return Ok(());
+    }
+    let capacity = if config.rate_limit_burst > 0 {
+        config.rate_limit_burst.min(per_min)
+    } else {
+        per_min
+    } as f64;
+    let per_sec = per_min as f64 / 60.0;
+
+    let Ok(mut buckets) = BUCKETS.lock() else {
+        // Lock poisoned — fail open: throttling is about capacity, not safety.
+        return Ok(());
+    };
+    let bucket = buckets
+        .entry((agent.to_string(), class))
+        .or_insert_with(|| TokenBucket::new(capacity, now));
+    if bucket.try_take(capacity, per_sec, now) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Rate limit exceeded: agent '{}' is limited to {} {} requests/min",
+            agent,
+            per_min,
+            class.label()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_method_classes() {
+        assert_eq!(MethodClass::of("eth_sendRawTransaction"), MethodClass::Send);
+        assert_eq!(MethodClass::of("eth_signTypedData_v4"), MethodClass::Sign);
+        assert_eq!(MethodClass::of("eth_call"), MethodClass::Read);
+    }
+
+    #[test]
+    fn test_unlimited_by_default() {
+        let config = Config::default();
+        for _ in 0..1_000 {
+            assert!(check(&config, "test-unlimited", "eth_sendTransaction").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_burst_then_refill() {
+        let config = Config {
+            rate_limit_send_per_min: 60, // 1 per second
+            rate_limit_burst: 3,
+            ..Config::default()
+        };
+        let t0 = Instant::now();
+
+        for _ in 0..3 {
+            assert!(check_at(&config, "test-burst", "eth_sendTransaction", t0).is_ok());
+        }
+        assert!(check_at(&config, "test-burst", "eth_sendTransaction", t0).is_err());
+        // Other classes and other agents have their own buckets.
+        assert!(check_at(&config, "test-burst", "eth_call", t0).is_ok());
+        assert!(check_at(&config, "test-burst-other", "eth_sendTransaction", t0).is_ok());
+
+        let t1 = t0 + Duration::from_secs(1);
+        assert!(check_at(&config, "test-burst", "eth_sendTransaction", t1).is_ok());
+        assert!(check_at(&config, "test-burst", "eth_sendTransaction", t1).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Re-entrancy detection over simulation call traces.
+//!
+//! A transaction that calls out of the agent's vault and is called *back*
+//! into it before the outer frame returns can act on half-updated state —
+//! the pattern behind most vault drains. Balance physics can't see it: the
+//! damage lands in storage the simulation never diffed.
+//!
+//! The simulator's `callTracer` trace is walked with the stack of executing
+//! accounts. A state-changing call into a protected account (the sender
+//! and `vault_addresses`) while that account is already on the stack below
+//! some other contract is a re-entry. Plain ETH transfers (empty calldata)
+//! and the callbacks in `reentrancy_allowed_callbacks` — NFT receiver hooks
+//! by default — are expected and allowed.
+
+use crate::config::{parse_list, Config};
+use crate::types::CallFrame;
+
+/// A re-entrant call found in a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reentry {
+    /// The protected account that was re-entered.
+    pub account: String,
+    /// The contract that called back into it.
+    pub via: String,
+    /// Selector of the re-entrant call (`0x…`).
+    pub selector: String,
+    /// Call depth of the re-entrant frame (root = 0).
+    pub depth: usize,
+}
+
+fn walk(
+    frame: &CallFrame,
+    protected: &[String],
+    allowed: &[String],
+    stack: &mut Vec<String>,
+) -> Option<Reentry> {
+    let context = frame.context().to_lowercase();
+    let selector = frame.input.get(..10).unwrap_or("").to_lowercase();
+
+    let reentrant = frame.call_type != "STATICCALL"
+        && protected.contains(&context)
+        && stack.contains(&context)
+        && stack.last() != Some(&context);
+    if reentrant && frame.input.len() > 2 && !allowed.contains(&selector) {
+        return Some(Reentry {
+            account: context,
+            via: frame.from.to_lowercase(),
+            selector,
+            depth: stack.len(),
+        });
+    }
+
+    stack.push(context);
+    let found = frame.calls.iter().find_map(|call| walk(call, protected, allowed, stack));
+    stack.pop();
+    found
+}
+
+/// The first re-entrant call into `protected` in `trace`.
+pub fn find(trace: &CallFrame, protected: &[String], allowed_callbacks: &[String]) -> Option<Reentry> {
+    walk(trace, protected, allowed_callbacks, &mut Vec::new())
+}
+
+/// Block a simulated transaction that re-enters the sender or a vault.
+pub fn check(config: &Config, from: &str, trace: &CallFrame) -> Result<(), String> {
+    let mut protected = parse_list(&config.vault_addresses);
+    protected.push(from.to_lowercase());
+    let allowed = parse_list(&config.reentrancy_allowed_callbacks);
+    match find(trace, &protected, &allowed) {
+        Some(r) => Err(format!(
+            "PLIMSOLL REENTRANCY: {} re-entered {} with {} at call depth {} during simulation",
+            r.via, r.account, r.selector, r.depth
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: &str = "0xa9e0000000000000000000000000000000000001";
+    const VAULT: &str = "0x1111111111111111111111111111111111111111";
+    const ATTACKER: &str = "0x6666666666666666666666666666666666666666";
+
+    fn frame(call_type: &str, from: &str, to: &str, input: &str, calls: Vec<CallFrame>) -> CallFrame {
+        CallFrame {
+            call_type: call_type.into(),
+            from: from.into(),
+            to: to.into(),
+            input: input.into(),
+            error: None,
+            calls,
+            ..CallFrame::default()
+        }
+    }
+
+    /// agent → vault.execute → attacker → `inner`
+    fn vault_calls_out(inner: CallFrame) -> CallFrame {
+        frame(
+            "CALL",
+            AGENT,
+            VAULT,
+            "0xb61d27f6",
+            vec![frame("CALL", VAULT, ATTACKER, "0xdeadbeef", vec![inner])],
+        )
+    }
+
+    fn config() -> Config {
+        Config { vault_addresses: VAULT.into(), ..Config::default() }
+    }
+
+    #[test]
+    fn test_callback_into_vault_blocked() {
+        let trace = vault_calls_out(frame("CALL", ATTACKER, VAULT, "0x2e1a7d4d0000", vec![]));
+        let err = check(&config(), AGENT, &trace).unwrap_err();
+        assert!(err.contains("re-entered 0x1111111111111111111111111111111111111111 with 0x2e1a7d4d"));
+        assert!(err.contains("call depth 2"));
+    }
+
+    #[test]
+    fn test_static_eth_and_receiver_callbacks_allowed() {
+        for inner in [
+            frame("STATICCALL", ATTACKER, VAULT, "0x70a08231", vec![]),
+            frame("CALL", ATTACKER, VAULT, "0x", vec![]),
+            frame("CALL", ATTACKER, VAULT, "0x150b7a02aaaa", vec![]),
+        ] {
+            assert!(check(&config(), AGENT, &vault_calls_out(inner)).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_delegatecall_and_unprotected_accounts_ignored() {
+        // The vault delegatecalling a library stays in the vault's context.
+        let trace = frame(
+            "CALL",
+            AGENT,
+            VAULT,
+            "0xb61d27f6",
+            vec![frame("DELEGATECALL", VAULT, ATTACKER, "0x12345678", vec![])],
+        );
+        assert!(check(&config(), AGENT, &trace).is_ok());
+        // Without the vault listed, only the sender is protected.
+        let trace = vault_calls_out(frame("CALL", ATTACKER, VAULT, "0x2e1a7d4d", vec![]));
+        assert!(check(&Config::default(), AGENT, &trace).is_ok());
+    }
+}
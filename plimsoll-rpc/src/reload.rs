@@ -0,0 +1,253 @@
+//! Hot configuration reload.
+//!
+//! Restarting the proxy to change a threshold drops in-flight requests and
+//! (without a state store) wipes the protective state. Instead, the live
+//! config is held behind a [`ConfigHandle`] and can be swapped atomically:
+//!
+//!   - `SIGHUP` (Unix), or
+//!   - `POST /admin/reload` with `Authorization: Bearer <PLIMSOLL_ADMIN_TOKEN>`
+//!
+//! Each request takes an `Arc<Config>` snapshot when it starts, so in-flight
+//! requests finish under the config they began with. Protective state
+//! (revoked keys, blocked txs, revert strikes, paymaster sever) lives in
+//! `rpc.rs` and is never touched by a reload.
+//!
+//! Settings bound at startup (listen address, state DB, OTLP exporter,
+//! audit log) are kept at their current values; a change to them is
+//! reported and ignored.
+//! Policies synced from the vaults (`vault_sync`) are overlaid on every
+//! config swapped in.
+
+use crate::audit;
+use crate::config::Config;
+use crate::vault_sync;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+/// Fields that only take effect at startup.
+const RESTART_ONLY_FIELDS: &[&str] = &[
+    "host",
+    "port",
+    "state_db_path",
+    "state_snapshot_interval_secs",
+    "otlp_endpoint",
+    "otel_service_name",
+    "http_proxy_enabled",
+    "http_proxy_port",
+    "audit_log_path",
+    "grpc_port",
+    "ipc_path",
+    "tls_cert_path",
+    "tls_key_path",
+    "tls_client_ca_path",
+    "tls_acme_domains",
+    "tls_acme_contact",
+    "tls_acme_cache_dir",
+    "tls_acme_staging",
+];
+
+/// Outcome of a reload.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReloadSummary {
+    /// Fields whose new values are now live.
+    pub applied: Vec<String>,
+    /// Restart-only fields that changed in the source but were not applied.
+    pub ignored: Vec<String>,
+}
+
+/// Swappable handle to the live configuration.
+pub struct ConfigHandle {
+    current: RwLock<Arc<Config>>,
+    /// The live config as loaded, before vault policies were overlaid.
+    source: RwLock<Config>,
+    /// Config file to re-read on reload (None = environment only).
+    path: Option<PathBuf>,
+}
+
+pub type SharedConfigHandle = Arc<ConfigHandle>;
+
+impl ConfigHandle {
+    pub fn new(config: Config, path: Option<PathBuf>) -> Self {
+        let mut current = config.clone();
+        vault_sync::overlay(&mut current);
+        audit::set_config_version(&current);
+        Self {
+            current: RwLock::new(Arc::new(current)),
+            source: RwLock::new(config),
+            path,
+        }
+    }
+
+    /// Snapshot of the live config. Cheap (one `Arc` clone).
+    pub fn current(&self) -> Arc<Config> {
+        match self.current.read() {
+            Ok(cfg) => Arc::clone(&cfg),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    /// Re-read the config file + environment and swap it in.
+    /// On any load or validation error the live config is left untouched.
+    pub fn reload(&self) -> Result<ReloadSummary> {
+        let next = Config::load(self.path.as_deref())?;
+        Ok(self.apply(next))
+    }
+
+    /// Swap in `next`, keeping restart-only fields from the live config.
+    pub fn apply(&self, mut next: Config) -> ReloadSummary {
+        match self.source.write() {
+            Ok(mut source) => *source = next.clone(),
+            Err(poisoned) => *poisoned.into_inner() = next.clone(),
+        }
+        vault_sync::overlay(&mut next);
+        let mut guard = match self.current.write() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let live = Arc::clone(&guard);
+
+        let mut summary = ReloadSummary::default();
+        for field in changed_fields(&live, &next) {
+            if RESTART_ONLY_FIELDS.contains(&field.as_str()) {
+                summary.ignored.push(field);
+            } else {
+                summary.applied.push(field);
+            }
+        }
+
+        next.host = live.host.clone();
+        next.port = live.port;
+        next.state_db_path = live.state_db_path.clone();
+        next.state_snapshot_interval_secs = live.state_snapshot_interval_secs;
+        next.otlp_endpoint = live.otlp_endpoint.clone();
+        next.otel_service_name = live.otel_service_name.clone();
+        next.http_proxy_enabled = live.http_proxy_enabled;
+        next.http_proxy_port = live.http_proxy_port;
+        next.audit_log_path = live.audit_log_path.clone();
+        next.grpc_port = live.grpc_port;
+        next.ipc_path = live.ipc_path.clone();
+        next.tls_cert_path = live.tls_cert_path.clone();
+        next.tls_key_path = live.tls_key_path.clone();
+        next.tls_client_ca_path = live.tls_client_ca_path.clone();
+        next.tls_acme_domains = live.tls_acme_domains.clone();
+        next.tls_acme_contact = live.tls_acme_contact.clone();
+        next.tls_acme_cache_dir = live.tls_acme_cache_dir.clone();
+        next.tls_acme_staging = live.tls_acme_staging;
+        audit::set_config_version(&next);
+
+        *guard = Arc::new(next);
+        summary
+    }
+
+    /// Re-apply the live config with the latest synced vault policies.
+    pub fn resync(&self) -> ReloadSummary {
+        let source = match self.source.read() {
+            Ok(source) => source.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        self.apply(source)
+    }
+}
+
+/// Names of top-level config fields that differ between `a` and `b`.
+fn changed_fields(a: &Config, b: &Config) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(a)), Ok(serde_json::Value::Object(b))) =
+        (serde_json::to_value(a), serde_json::to_value(b))
+    else {
+        return Vec::new();
+    };
+    a.iter()
+        .filter(|(k, v)| b.get(*k) != Some(*v))
+        .map(|(k, _)| k.clone())
+        .collect()
+}
+
+/// Reload and log the outcome. Used by both the SIGHUP listener and the
+/// admin endpoint.
+pub fn reload_and_log(handle: &ConfigHandle, trigger: &str) -> Result<ReloadSummary> {
+    match handle.reload() {
+        Ok(summary) => {
+            info!(
+                trigger,
+                applied = ?summary.applied,
+                "Configuration reloaded"
+            );
+            if !summary.ignored.is_empty() {
+                warn!(
+                    ignored = ?summary.ignored,
+                    "Restart-only settings changed; restart the proxy to apply them"
+                );
+            }
+            handle.current().log_summary();
+            Ok(summary)
+        }
+        Err(e) => {
+            warn!(trigger, "Configuration reload rejected, keeping live config: {:#}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Reload the config on every `SIGHUP`.
+#[cfg(unix)]
+pub fn spawn_sighup_listener(handle: SharedConfigHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut hup = match signal(SignalKind::hangup()) {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Failed to install SIGHUP handler: {}", e);
+                return;
+            }
+        };
+        while hup.recv().await.is_some() {
+            let _ = reload_and_log(&handle, "SIGHUP");
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_listener(_handle: SharedConfigHandle) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_swaps_thresholds() {
+        let handle = ConfigHandle::new(Config::default(), None);
+        let before = handle.current();
+
+        let next = Config { max_loss_pct: 5.0, ..Config::default() };
+        let summary = handle.apply(next);
+
+        assert_eq!(summary.applied, vec!["max_loss_pct".to_string()]);
+        assert!(summary.ignored.is_empty());
+        assert_eq!(handle.current().max_loss_pct, 5.0);
+        // Snapshot taken before the reload is unaffected (in-flight requests).
+        assert_eq!(before.max_loss_pct, 20.0);
+    }
+
+    #[test]
+    fn test_restart_only_fields_kept() {
+        let handle = ConfigHandle::new(Config::default(), None);
+        let next = Config { port: 9999, chain_id: 10, ..Config::default() };
+        let summary = handle.apply(next);
+
+        assert_eq!(summary.ignored, vec!["port".to_string()]);
+        assert_eq!(handle.current().port, 8545);
+        assert_eq!(handle.current().chain_id, 10);
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let handle = ConfigHandle::new(Config::default(), None);
+        let summary = handle.apply(Config::default());
+        assert!(summary.applied.is_empty());
+        assert!(summary.ignored.is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! Replacement (speed-up / cancel) transactions.
+//!
+//! A transaction with the nonce of one still pending replaces it once it
+//! pays a higher fee. Wallets use this to speed up or cancel; an attacker
+//! uses it to get a benign transaction approved and then swap in a drain
+//! under the same nonce before it is mined.
+//!
+//! A replacement of a transaction the proxy forwarded may only be:
+//!
+//!   - a speed-up — same `to`, `value` and `data`, new fees,
+//!   - a cancel — a zero-value, empty-calldata send to the sender itself.
+//!
+//! Anything else changes what was approved and is blocked. A speed-up is
+//! re-simulated after the same predecessors as the original and must not
+//! come out worse than the loss the original was approved with: the state
+//! may have moved under a transaction still waiting in the mempool.
+
+use crate::types::{PendingTx, SimulationResult};
+
+/// Simulated loss a speed-up may exceed its original's by, in %.
+const LOSS_TOLERANCE_PCT: f64 = 0.01;
+
+/// What a replacement does to the original.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Replacement {
+    SpeedUp,
+    Cancel,
+}
+
+/// Classify a send replacing `original`. Changed contents are an error.
+pub fn classify(
+    original: &PendingTx,
+    from: &str,
+    to: &str,
+    value: u128,
+    data: &[u8],
+) -> Result<Replacement, String> {
+    if to.eq_ignore_ascii_case(&original.to) && value == original.value && data == original.data.as_slice() {
+        return Ok(Replacement::SpeedUp);
+    }
+    if to.eq_ignore_ascii_case(from) && value == 0 && data.is_empty() {
+        return Ok(Replacement::Cancel);
+    }
+    let mut changed = Vec::new();
+    if !to.eq_ignore_ascii_case(&original.to) {
+        changed.push(format!("to {} → {}", original.to, to));
+    }
+    if value != original.value {
+        changed.push(format!("value {} → {}", original.value, value));
+    }
+    if data != original.data.as_slice() {
+        changed.push(format!("calldata ({} → {} bytes)", original.data.len(), data.len()));
+    }
+    Err(format!(
+        "PLIMSOLL REPLACEMENT: nonce {} replaces an approved pending transaction with different \
+         contents ({}) — only speed-ups and cancels may reuse a pending nonce",
+        original.nonce,
+        changed.join(", ")
+    ))
+}
+
+/// Whether the replacement's fees are above the original's. Unknown fees
+/// (signed raw transactions) are not compared.
+pub fn fee_bumped(original: &PendingTx, max_fee: Option<u128>, priority_fee: Option<u128>) -> bool {
+    let higher = |new: Option<u128>, old: Option<u128>| match (new, old) {
+        (Some(new), Some(old)) => new > old,
+        _ => true,
+    };
+    higher(max_fee, original.max_fee) && higher(priority_fee, original.priority_fee)
+}
+
+/// Block a speed-up whose re-simulation loses more than its original did.
+pub fn check_outcome(original: &PendingTx, sim_result: &SimulationResult) -> Result<(), String> {
+    if sim_result.loss_pct > original.loss_pct + LOSS_TOLERANCE_PCT {
+        return Err(format!(
+            "PLIMSOLL REPLACEMENT: speed-up of nonce {} now simulates a {:.2}% loss, \
+             the original was approved at {:.2}% — state changed while it was pending",
+            original.nonce, sim_result.loss_pct, original.loss_pct
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: &str = "0xa9e0000000000000000000000000000000000001";
+    const ROUTER: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+
+    fn original() -> PendingTx {
+        PendingTx {
+            nonce: 7,
+            to: ROUTER.into(),
+            value: 1_000,
+            data: vec![0x38, 0xed, 0x17, 0x39],
+            max_fee: Some(30),
+            priority_fee: Some(2),
+            loss_pct: 1.5,
+        }
+    }
+
+    #[test]
+    fn test_speed_up_and_cancel_allowed() {
+        let tx = original();
+        assert_eq!(classify(&tx, AGENT, &ROUTER.to_uppercase().replace("0X", "0x"), 1_000, &tx.data), Ok(Replacement::SpeedUp));
+        assert_eq!(classify(&tx, AGENT, AGENT, 0, &[]), Ok(Replacement::Cancel));
+        assert!(fee_bumped(&tx, Some(40), Some(3)));
+        assert!(!fee_bumped(&tx, Some(40), Some(2)));
+        assert!(fee_bumped(&tx, None, None));
+    }
+
+    #[test]
+    fn test_changed_contents_blocked() {
+        let tx = original();
+        let drain = "0x6666666666666666666666666666666666666666";
+        let err = classify(&tx, AGENT, drain, 5_000, &[0xa9, 0x05, 0x9c, 0xbb, 0x00]).unwrap_err();
+        assert!(err.contains("nonce 7 replaces"));
+        assert!(err.contains("to 0x7a250d5630b4cf539739df2c5dacb4c659f2488d → 0x6666"));
+        assert!(err.contains("value 1000 → 5000"));
+        assert!(err.contains("calldata (4 → 5 bytes)"));
+        // A "cancel" that still moves value is not a cancel.
+        assert!(classify(&tx, AGENT, AGENT, 1, &[]).is_err());
+    }
+
+    #[test]
+    fn test_worse_outcome_blocked() {
+        let sim = |loss_pct| SimulationResult {
+            success: true,
+            gas_used: 21_000,
+            balance_before: 0,
+            balance_after: 0,
+            approval_changes: vec![],
+            loss_pct,
+            error: None,
+            simulated_block: 1,
+            target_codehash: String::new(),
+            non_deterministic: false,
+            impl_slot_value: String::new(),
+            implementation_codehash: String::new(),
+            call_trace: None,
+            asset_deltas: vec![],
+            approval_deltas: vec![],
+            storage_changes: vec![],
+        };
+        assert!(check_outcome(&original(), &sim(1.5)).is_ok());
+        let err = check_outcome(&original(), &sim(40.0)).unwrap_err();
+        assert!(err.contains("now simulates a 40.00% loss, the original was approved at 1.50%"));
+    }
+}
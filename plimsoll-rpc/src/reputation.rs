@@ -0,0 +1,258 @@
+//! Counterparty reputation scoring.
+//!
+//! Each engine answers a yes/no question about one transaction. The
+//! reputation score folds what the proxy already knows about the *target*
+//! into a single 0–100 number, logged with every send decision and served
+//! to agents by `plimsoll_getReputation`:
+//!
+//!   - threat feed: a blacklisted address scores 0 outright,
+//!   - verification status and contract age (`verification`, when
+//!     `contract_verification_check` is on),
+//!   - counterparty history: other agents already deal with it,
+//!   - prior blocks: sends to it this proxy has blocked, by an engine
+//!     whose verdict is about the target (`TARGET_ENGINES`) — a send
+//!     stopped by the agent's own velocity or gas limits says nothing
+//!     about where it was going,
+//!   - fleet history: executions blocked by other vaults, from the
+//!     indexer (`indexer_url`).
+//!
+//! Scores start neutral at 50.
+
+use crate::config::Config;
+use crate::counterparties;
+use crate::threat_feed::SharedThreatFilter;
+use crate::verification::{self, Provenance};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Indexer lookup timeout — this runs on the send path.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Engines whose block reflects on the send's target.
+pub const TARGET_ENGINES: &[&str] = &[
+    "engine0",
+    "sanctions",
+    "rugpull",
+    "honeypot",
+    "verification",
+    "metamorphic",
+    "codehash_pin",
+    "proxy",
+    "reentrancy",
+    "delegatecall",
+    "address_poisoning",
+];
+
+const NEUTRAL: i64 = 50;
+const DAY: u64 = 24 * 3600;
+
+lazy_static! {
+    /// target (lowercase) → sends to it this proxy has blocked.
+    static ref PRIOR_BLOCKS: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+}
+
+/// Everything known about a target.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Signals {
+    pub blacklisted: bool,
+    /// `None` = not looked up, or not a contract.
+    pub provenance: Option<Provenance>,
+    /// Agents with the target in their counterparty history.
+    pub familiar_agents: usize,
+    pub prior_blocks: u64,
+    /// `(blocked, approved)` executions across the fleet, if the indexer
+    /// was reachable.
+    pub fleet: Option<(u64, u64)>,
+}
+
+/// A target's score and the signals that moved it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reputation {
+    pub address: String,
+    pub score: u8,
+    pub signals: Vec<String>,
+}
+
+/// Count a send to `target` blocked by `engine`, if the block is about
+/// the target.
+pub fn record_block(engine: &str, target: &str) {
+    if !TARGET_ENGINES.contains(&engine) {
+        return;
+    }
+    if let Ok(mut blocks) = PRIOR_BLOCKS.lock() {
+        *blocks.entry(target.to_lowercase()).or_default() += 1;
+    }
+}
+
+/// Sends to `target` this proxy has blocked.
+pub fn prior_blocks(target: &str) -> u64 {
+    PRIOR_BLOCKS
+        .lock()
+        .ok()
+        .and_then(|blocks| blocks.get(&target.to_lowercase()).copied())
+        .unwrap_or(0)
+}
+
+/// Fold `signals` into a score.
+pub fn score(address: &str, signals: &Signals) -> Reputation {
+    let address = address.to_lowercase();
+    if signals.blacklisted {
+        return Reputation { address, score: 0, signals: vec!["threat feed: blacklisted".into()] };
+    }
+
+    let mut score = NEUTRAL;
+    let mut notes = Vec::new();
+    let mut adjust = |delta: i64, note: String| {
+        score += delta;
+        notes.push(format!("{note} ({delta:+})"));
+    };
+
+    if let Some(p) = &signals.provenance {
+        match p.verified_on {
+            Some(v) => adjust(20, format!("verified on {}", v.label())),
+            None => adjust(-15, "unverified".into()),
+        }
+        match p.age_secs {
+            Some(age) if age >= 180 * DAY => adjust(15, format!("deployed {} days ago", age / DAY)),
+            Some(age) if age >= 30 * DAY => adjust(5, format!("deployed {} days ago", age / DAY)),
+            Some(age) if age < 2 * DAY => adjust(-20, format!("deployed {:.1}h ago", age as f64 / 3600.0)),
+            _ => {}
+        }
+    }
+    if signals.familiar_agents > 0 {
+        adjust(10, format!("counterparty of {} agent(s)", signals.familiar_agents));
+    }
+    if signals.prior_blocks > 0 {
+        let penalty = (signals.prior_blocks.min(4) * 10) as i64;
+        adjust(-penalty, format!("{} prior block(s)", signals.prior_blocks));
+    }
+    if let Some((blocked, approved)) = signals.fleet {
+        if blocked > 0 {
+            let penalty = (blocked.min(3) * 10) as i64;
+            adjust(-penalty, format!("blocked {blocked} time(s) across the fleet"));
+        } else if approved > 0 {
+            adjust(5, format!("{approved} clean execution(s) across the fleet"));
+        }
+    }
+
+    Reputation { address, score: score.clamp(0, 100) as u8, signals: notes }
+}
+
+/// `GET {indexer_url}/targets/{address}`.
+async fn fleet_history(config: &Config, address: &str) -> Option<(u64, u64)> {
+    if config.indexer_url.is_empty() {
+        return None;
+    }
+    let url = format!("{}/targets/{}", config.indexer_url.trim_end_matches('/'), address);
+    let body: serde_json::Value = reqwest::Client::new()
+        .get(url)
+        .timeout(LOOKUP_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    Some((
+        body["blocked_executions"].as_u64()?,
+        body["approved_executions"].as_u64().unwrap_or(0),
+    ))
+}
+
+/// Gather every enabled signal for `address` and score it.
+pub async fn assess(config: &Config, threat_filter: &SharedThreatFilter, address: &str) -> Reputation {
+    let address = address.to_lowercase();
+    let blacklisted = threat_filter
+        .read()
+        .map(|f| f.is_address_blacklisted(&address))
+        .unwrap_or(false);
+    let provenance = if config.contract_verification_check && !blacklisted {
+        verification::provenance(config, &address, None).await.unwrap_or_else(|e| {
+            tracing::debug!(error = %e, target = %address, "Reputation: provenance lookup failed");
+            None
+        })
+    } else {
+        None
+    };
+    let signals = Signals {
+        blacklisted,
+        provenance,
+        familiar_agents: counterparties::familiar_agents(&address),
+        prior_blocks: prior_blocks(&address),
+        fleet: fleet_history(config, &address).await,
+    };
+    score(&address, &signals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification::Verifier;
+
+    const TARGET: &str = "0x7777777777777777777777777777777777777777";
+
+    fn provenance(verified_on: Option<Verifier>, age_days: u64) -> Option<Provenance> {
+        Some(Provenance {
+            contract: TARGET.into(),
+            proxy: None,
+            verified_on,
+            age_secs: Some(age_days * DAY),
+        })
+    }
+
+    #[test]
+    fn test_unknown_target_is_neutral() {
+        let rep = score(TARGET, &Signals::default());
+        assert_eq!(rep.score, 50);
+        assert!(rep.signals.is_empty());
+    }
+
+    #[test]
+    fn test_blacklisted_scores_zero() {
+        let signals = Signals {
+            blacklisted: true,
+            provenance: provenance(Some(Verifier::Sourcify), 400),
+            ..Signals::default()
+        };
+        assert_eq!(score(TARGET, &signals).score, 0);
+    }
+
+    #[test]
+    fn test_established_verified_contract_scores_high() {
+        let signals = Signals {
+            provenance: provenance(Some(Verifier::Etherscan), 400),
+            familiar_agents: 3,
+            fleet: Some((0, 12)),
+            ..Signals::default()
+        };
+        let rep = score(TARGET, &signals);
+        assert_eq!(rep.score, 100);
+        assert!(rep.signals.iter().any(|s| s.starts_with("verified on Etherscan")));
+    }
+
+    #[test]
+    fn test_fresh_unverified_blocked_contract_scores_low() {
+        let signals = Signals {
+            provenance: provenance(None, 0),
+            prior_blocks: 2,
+            fleet: Some((5, 0)),
+            ..Signals::default()
+        };
+        // 50 - 15 - 20 - 20 - 30, clamped
+        assert_eq!(score(TARGET, &signals).score, 0);
+    }
+
+    #[test]
+    fn test_prior_blocks_recorded_for_target_engines_only() {
+        let target = "0x7777777777777777777777777777777777770001";
+        record_block("rugpull", &target.to_uppercase().replace("0X", "0x"));
+        record_block("rugpull", target);
+        record_block("velocity", target);
+        assert_eq!(prior_blocks(target), 2);
+    }
+}
@@ -0,0 +1,352 @@
+//! Stuck-transaction rescue queue.
+//!
+//! A forwarded transaction that sits unmined is a liability: fees rise past
+//! it, the agent's later nonces queue behind it, and the approval it was
+//! given ages — a target that was clean at simulation time may be flagged
+//! by the threat feed an hour later, while the transaction can still land.
+//!
+//! With `tx_rescue` on, every forwarded `eth_sendTransaction` is tracked
+//! until its nonce is confirmed. Every `rescue_poll_secs`:
+//!
+//!   - a transaction whose target has since entered the threat feed is
+//!     cancelled (`rescue_cancel_unsafe`): a zero-value self-send under
+//!     the same nonce, with bumped fees;
+//!   - one unmined after `rescue_after_blocks` blocks is resubmitted with
+//!     fees raised by `rescue_fee_bump_pct` (at most `rescue_max_bumps`
+//!     times, and never past the gas fee ceilings).
+//!
+//! Resubmissions go through `eth_sendTransaction`, so only node-managed
+//! keys can be rescued; signed raw transactions are not tracked.
+
+use crate::config::Config;
+use crate::gas_fees;
+use crate::pending;
+use crate::reload::SharedConfigHandle;
+use crate::threat_feed::{self, SharedThreatFilter};
+use crate::types::PendingTx;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Transactions tracked at once.
+const MAX_TRACKED: usize = 256;
+
+/// Gas for a plain self-send cancel.
+const CANCEL_GAS: u64 = 21_000;
+
+/// A forwarded transaction awaiting inclusion.
+#[derive(Debug, Clone)]
+pub struct Tracked {
+    pub from: String,
+    /// The `eth_sendTransaction` object last sent.
+    pub tx: serde_json::Value,
+    /// Hash of the last submission.
+    pub hash: String,
+    /// From the request, else filled from the node once it knows the
+    /// transaction.
+    pub nonce: Option<u64>,
+    /// Nonce, gas and fees filled in from the node's copy.
+    pub resolved: bool,
+    /// Block of the last submission, set on the first poll after it.
+    pub since_block: Option<u64>,
+    pub bumps: u32,
+    pub cancelled: bool,
+    pub tracked_at: Instant,
+}
+
+lazy_static! {
+    /// Submission hash of the original → tracked transaction.
+    static ref QUEUE: Mutex<BTreeMap<String, Tracked>> = Mutex::new(BTreeMap::new());
+}
+
+/// Start tracking a forwarded `eth_sendTransaction`.
+pub fn track(from: &str, nonce: Option<u64>, tx: serde_json::Value, hash: &str) {
+    let Ok(mut queue) = QUEUE.lock() else {
+        return;
+    };
+    if queue.len() >= MAX_TRACKED {
+        return;
+    }
+    queue.insert(
+        hash.to_lowercase(),
+        Tracked {
+            from: from.to_lowercase(),
+            tx,
+            hash: hash.to_lowercase(),
+            nonce,
+            resolved: false,
+            since_block: None,
+            bumps: 0,
+            cancelled: false,
+            tracked_at: Instant::now(),
+        },
+    );
+}
+
+fn forget(key: &str) {
+    if let Ok(mut queue) = QUEUE.lock() {
+        queue.remove(key);
+    }
+}
+
+fn quantity(value: &serde_json::Value) -> Option<u128> {
+    u128::from_str_radix(value.as_str()?.trim_start_matches("0x"), 16).ok()
+}
+
+/// Raise the fees of `tx` by `pct` %, within the ceilings
+/// `(priority_cap, max_fee_cap)`. `false` (and `tx` untouched) when the
+/// bumped fees would exceed them or `tx` carries no fees.
+pub fn bump_fees(tx: &mut serde_json::Value, pct: f64, caps: Option<(u128, u128)>) -> bool {
+    let bump = |fee: u128| (fee as f64 * (1.0 + pct / 100.0)).ceil() as u128;
+    let (priority_cap, max_fee_cap) = caps.unwrap_or((u128::MAX, u128::MAX));
+    let mut bumped = Vec::new();
+    for (field, cap) in [
+        ("maxPriorityFeePerGas", priority_cap),
+        ("maxFeePerGas", max_fee_cap),
+        ("gasPrice", max_fee_cap),
+    ] {
+        if let Some(fee) = tx.get(field).and_then(quantity) {
+            let new = bump(fee);
+            if new > cap {
+                return false;
+            }
+            bumped.push((field, new));
+        }
+    }
+    if bumped.is_empty() {
+        return false;
+    }
+    for (field, fee) in bumped {
+        tx[field] = serde_json::json!(format!("0x{fee:x}"));
+    }
+    true
+}
+
+/// A zero-value self-send under `original`'s nonce, at its fees.
+pub fn cancel_tx(original: &serde_json::Value, from: &str, nonce: u64) -> serde_json::Value {
+    let mut tx = serde_json::json!({
+        "from": from,
+        "to": from,
+        "value": "0x0",
+        "data": "0x",
+        "gas": format!("0x{CANCEL_GAS:x}"),
+        "nonce": format!("0x{nonce:x}"),
+    });
+    for field in ["maxPriorityFeePerGas", "maxFeePerGas", "gasPrice"] {
+        if let Some(fee) = original.get(field) {
+            tx[field] = fee.clone();
+        }
+    }
+    tx
+}
+
+async fn rpc_call(rpc_url: &str, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+    let payload = serde_json::json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .with_context(|| format!("Failed to call {method}"))?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {method} response"))?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("{method} failed: {err}");
+    }
+    Ok(body["result"].clone())
+}
+
+/// Fill the nonce and the fees the node chose from the node's copy of the
+/// transaction. `false` while the node doesn't know it.
+async fn resolve(rpc_url: &str, entry: &mut Tracked) -> Result<bool> {
+    let known = rpc_call(rpc_url, "eth_getTransactionByHash", serde_json::json!([entry.hash])).await?;
+    if known.is_null() {
+        return Ok(false);
+    }
+    entry.nonce = known.get("nonce").and_then(quantity).map(|n| n as u64);
+    entry.resolved = entry.nonce.is_some();
+    let fields: &[&str] = if known.get("maxFeePerGas").is_some() {
+        &["nonce", "gas", "maxFeePerGas", "maxPriorityFeePerGas"]
+    } else {
+        &["nonce", "gas", "gasPrice"]
+    };
+    for field in fields {
+        if let Some(value) = known.get(*field).filter(|_| entry.tx.get(*field).is_none()) {
+            entry.tx[*field] = value.clone();
+        }
+    }
+    Ok(entry.nonce.is_some())
+}
+
+/// Submit `tx` for `entry` and reset its wait.
+async fn resubmit(config: &Config, entry: &mut Tracked, tx: serde_json::Value, block: u64) -> Result<()> {
+    let hash = rpc_call(&config.upstream_rpc_url, "eth_sendTransaction", serde_json::json!([tx])).await?;
+    entry.hash = hash.as_str().context("eth_sendTransaction returned no hash")?.to_lowercase();
+    entry.since_block = Some(block);
+    let ttl = Duration::from_secs(config.pending_tx_ttl_secs);
+    if let Some(nonce) = entry.nonce {
+        let previous = pending::get(&entry.from, nonce, ttl).unwrap_or_default();
+        pending::record(
+            &entry.from,
+            PendingTx {
+                nonce,
+                to: tx["to"].as_str().unwrap_or_default().to_string(),
+                value: tx.get("value").and_then(quantity).unwrap_or(0),
+                data: hex::decode(tx["data"].as_str().unwrap_or("0x").trim_start_matches("0x")).unwrap_or_default(),
+                max_fee: tx.get("maxFeePerGas").or_else(|| tx.get("gasPrice")).and_then(quantity),
+                priority_fee: tx.get("maxPriorityFeePerGas").and_then(quantity),
+                loss_pct: previous.loss_pct,
+            },
+        );
+    }
+    entry.tx = tx;
+    Ok(())
+}
+
+/// Check one tracked transaction: forget it once mined or expired,
+/// cancel or bump it otherwise.
+async fn rescue(
+    config: &Config,
+    threat_filter: &SharedThreatFilter,
+    key: String,
+    mut entry: Tracked,
+    block: u64,
+    caps: &mut Option<(u128, u128)>,
+) -> Result<()> {
+    let rpc_url = &config.upstream_rpc_url;
+    let ttl = Duration::from_secs(config.pending_tx_ttl_secs);
+    if !entry.resolved && !resolve(rpc_url, &mut entry).await.unwrap_or(false) {
+        if entry.tracked_at.elapsed() > ttl {
+            forget(&key);
+        }
+        return Ok(());
+    }
+    let nonce = entry.nonce.unwrap_or_default();
+    let confirmed = pending::transaction_count(rpc_url, &entry.from, "latest").await?;
+    if nonce < confirmed || entry.tracked_at.elapsed() > ttl {
+        forget(&key);
+        return Ok(());
+    }
+    let since = *entry.since_block.get_or_insert(block);
+
+    let to = entry.tx["to"].as_str().unwrap_or_default().to_string();
+    let flagged = threat_filter.read().map(|f| f.is_address_blacklisted(&to)).unwrap_or(false)
+        && !threat_feed::is_allowlisted(config, &to);
+    if caps.is_none() {
+        *caps = gas_fees::reference(rpc_url, config.fee_history_blocks)
+            .await
+            .ok()
+            .map(|reference| gas_fees::ceilings(config, &reference));
+    }
+
+    if flagged && config.rescue_cancel_unsafe && !entry.cancelled {
+        let mut cancel = cancel_tx(&entry.tx, &entry.from, nonce);
+        bump_fees(&mut cancel, config.rescue_fee_bump_pct, None);
+        warn!(from = %entry.from, nonce, target = %to, "Pending tx target now in the threat feed — cancelling");
+        resubmit(config, &mut entry, cancel, block).await?;
+        entry.cancelled = true;
+    } else if block.saturating_sub(since) >= config.rescue_after_blocks
+        && entry.bumps < config.rescue_max_bumps
+    {
+        let mut tx = entry.tx.clone();
+        if bump_fees(&mut tx, config.rescue_fee_bump_pct, *caps) {
+            info!(from = %entry.from, nonce, bump = entry.bumps + 1, "Stuck tx — resubmitting with bumped fees");
+            resubmit(config, &mut entry, tx, block).await?;
+            entry.bumps += 1;
+        } else {
+            warn!(from = %entry.from, nonce, "Stuck tx at the gas fee ceiling — not bumped");
+            entry.bumps = config.rescue_max_bumps;
+        }
+    }
+    if let Ok(mut queue) = QUEUE.lock() {
+        queue.insert(key, entry);
+    }
+    Ok(())
+}
+
+/// One pass over the queue. A transaction that can't be checked is
+/// retried next pass; the rest of the queue still is.
+async fn poll(config: &Config, threat_filter: &SharedThreatFilter) -> Result<()> {
+    let block = rpc_call(&config.upstream_rpc_url, "eth_blockNumber", serde_json::json!([])).await?;
+    let block = quantity(&block).context("invalid block number")? as u64;
+    let entries: Vec<(String, Tracked)> = match QUEUE.lock() {
+        Ok(queue) => queue.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        Err(_) => return Ok(()),
+    };
+    let mut caps = None;
+
+    for (key, entry) in entries {
+        let (from, hash) = (entry.from.clone(), entry.hash.clone());
+        if let Err(e) = rescue(config, threat_filter, key, entry, block, &mut caps).await {
+            warn!(error = %e, from = %from, hash = %hash, "Rescue of tracked tx failed — retrying next poll");
+        }
+    }
+    Ok(())
+}
+
+/// Spawn the background task polling the rescue queue. Reads the live
+/// config every round, so `tx_rescue` can be toggled by reload.
+pub fn spawn_rescue_task(config: SharedConfigHandle, threat_filter: SharedThreatFilter) {
+    tokio::spawn(async move {
+        loop {
+            let cfg = config.current();
+            tokio::time::sleep(Duration::from_secs(cfg.rescue_poll_secs.max(1))).await;
+            if !cfg.tx_rescue {
+                continue;
+            }
+            if let Err(e) = poll(&cfg, &threat_filter).await {
+                warn!(error = %e, "Rescue queue poll failed");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: &str = "0xa9e0000000000000000000000000000000000001";
+
+    #[test]
+    fn test_fees_bumped_within_ceilings() {
+        let mut tx = serde_json::json!({ "maxFeePerGas": "0x64", "maxPriorityFeePerGas": "0xa" });
+        assert!(bump_fees(&mut tx, 15.0, None));
+        assert_eq!(tx["maxFeePerGas"], "0x73");
+        assert_eq!(tx["maxPriorityFeePerGas"], "0xc");
+        // Past the ceiling: left alone.
+        let mut tx = serde_json::json!({ "gasPrice": "0x64" });
+        assert!(!bump_fees(&mut tx, 15.0, Some((1_000, 110))));
+        assert_eq!(tx["gasPrice"], "0x64");
+        assert!(!bump_fees(&mut serde_json::json!({}), 15.0, None));
+    }
+
+    #[test]
+    fn test_cancel_is_zero_value_self_send() {
+        let original = serde_json::json!({
+            "to": "0x6666666666666666666666666666666666666666",
+            "value": "0xde0b6b3a7640000",
+            "data": "0xa9059cbb",
+            "maxFeePerGas": "0x64",
+        });
+        let cancel = cancel_tx(&original, AGENT, 7);
+        assert_eq!(cancel["to"], AGENT);
+        assert_eq!(cancel["value"], "0x0");
+        assert_eq!(cancel["data"], "0x");
+        assert_eq!(cancel["nonce"], "0x7");
+        assert_eq!(cancel["maxFeePerGas"], "0x64");
+    }
+
+    #[test]
+    fn test_track_is_bounded() {
+        track(AGENT, Some(1), serde_json::json!({}), "0xAB01");
+        let queue = QUEUE.lock().unwrap();
+        assert_eq!(queue.get("0xab01").map(|t| t.nonce), Some(Some(1)));
+        // A nonce from the request still leaves the fees to fill in.
+        assert_eq!(queue.get("0xab01").map(|t| t.resolved), Some(false));
+        assert!(queue.len() <= MAX_TRACKED);
+    }
+}
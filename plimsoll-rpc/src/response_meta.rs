@@ -0,0 +1,174 @@
+//! Decision metadata on responses.
+//!
+//! With `response_metadata`, every JSON-RPC response carries a `plimsoll`
+//! object recording how it was decided, so tooling can audit a decision
+//! without a second round trip:
+//!
+//! ```text
+//! "plimsoll": {
+//!   "riskScore": 20,
+//!   "blockedBy": null,
+//!   "engines": ["engine0", "reputation", "simulation", "physics", "upstream"],
+//!   "simulatedBlock": 19000000,
+//!   "latencyMs": { "total": 412.5, "simulation": 380.1, "upstream": 20.3 }
+//! }
+//! ```
+//!
+//! Engines and their latencies are the pipeline's tracing spans (`otel`),
+//! collected by `StageLayer`; a log filter above `info` hides them. The
+//! audit log (`audit`) records the same decision.
+//! `riskScore` is the `plimsoll_dryRun` score.
+
+use serde_json::{json, Map, Value};
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// What the request being served ran through.
+#[derive(Debug, Default)]
+pub struct Decision {
+    /// Pipeline stages in the order they finished, with their duration.
+    pub stages: Vec<(&'static str, Duration)>,
+    pub blocked_by: Option<String>,
+    /// Reason of the block.
+    pub reason: Option<String>,
+    pub simulated_block: Option<u64>,
+    pub loss_pct: Option<f64>,
+    /// Reputation score of the send's target.
+    pub reputation: Option<u8>,
+}
+
+tokio::task_local! {
+    static DECISION: RefCell<Decision>;
+}
+
+/// When a stage span was created.
+struct Started(Instant);
+
+/// Tracing layer timing every span opened while a request is collected
+/// (the request's own `rpc_request` span opens before).
+pub struct StageLayer;
+
+impl<S> Layer<S> for StageLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if DECISION.try_with(|_| ()).is_err() {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(elapsed) = span.extensions().get::<Started>().map(|s| s.0.elapsed()) else {
+            return;
+        };
+        let name = span.metadata().name();
+        let _ = DECISION.try_with(|d| d.borrow_mut().stages.push((name, elapsed)));
+    }
+}
+
+/// Run `fut`, collecting its decision.
+pub async fn collect<F: Future>(fut: F) -> (F::Output, Decision) {
+    DECISION
+        .scope(RefCell::new(Decision::default()), async {
+            let output = fut.await;
+            let decision = DECISION.with(|d| d.take());
+            (output, decision)
+        })
+        .await
+}
+
+fn update(f: impl FnOnce(&mut Decision)) {
+    let _ = DECISION.try_with(|d| f(&mut d.borrow_mut()));
+}
+
+/// Record the engine that blocked (or would block) the request.
+pub fn blocked(engine: &str, reason: &str) {
+    update(|d| {
+        d.blocked_by = Some(engine.to_string());
+        d.reason = Some(reason.to_string());
+    });
+}
+
+/// Record the simulation the decision rests on.
+pub fn simulated(block: u64, loss_pct: f64) {
+    update(|d| {
+        d.simulated_block = Some(block);
+        d.loss_pct = Some(loss_pct);
+    });
+}
+
+/// Record the reputation score of the send's target.
+pub fn reputation(score: u8) {
+    update(|d| d.reputation = Some(score));
+}
+
+/// The `plimsoll` object of a response taking `total` with risk `risk_score`.
+pub fn render(decision: &Decision, risk_score: u8, total: Duration) -> Value {
+    let mut latency = Map::new();
+    latency.insert("total".into(), json!(millis(total)));
+    for (name, elapsed) in &decision.stages {
+        let spent = latency.get(*name).and_then(Value::as_f64).unwrap_or(0.0);
+        latency.insert(name.to_string(), json!(spent + millis(*elapsed)));
+    }
+    json!({
+        "riskScore": risk_score,
+        "blockedBy": decision.blocked_by,
+        "engines": engines(decision),
+        "simulatedBlock": decision.simulated_block,
+        "latencyMs": latency,
+    })
+}
+
+/// Stages the request ran through, in order, each once.
+pub fn engines(decision: &Decision) -> Vec<&'static str> {
+    let mut engines: Vec<&'static str> = Vec::new();
+    for (name, _) in &decision.stages {
+        if !engines.contains(name) {
+            engines.push(name);
+        }
+    }
+    engines
+}
+
+fn millis(d: Duration) -> f64 {
+    (d.as_secs_f64() * 1e4).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_sums_repeated_stages() {
+        let decision = Decision {
+            stages: vec![
+                ("engine0", Duration::from_micros(1_500)),
+                ("simulation", Duration::from_millis(300)),
+                ("simulation", Duration::from_millis(100)),
+            ],
+            blocked_by: Some("physics".into()),
+            simulated_block: Some(19_000_000),
+            ..Decision::default()
+        };
+        let meta = render(&decision, 100, Duration::from_millis(450));
+        assert_eq!(meta["engines"], json!(["engine0", "simulation"]));
+        assert_eq!(meta["latencyMs"]["simulation"], 400.0);
+        assert_eq!(meta["latencyMs"]["engine0"], 1.5);
+        assert_eq!(meta["latencyMs"]["total"], 450.0);
+        assert_eq!(meta["blockedBy"], "physics");
+        assert_eq!(meta["simulatedBlock"], 19_000_000);
+    }
+}
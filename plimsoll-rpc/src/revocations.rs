@@ -0,0 +1,180 @@
+//! Reconciliation of pessimistic session key revocations.
+//!
+//! A `SessionKeyRevoked` seen in the mempool revokes the key at once (see
+//! `rpc::revoke_session_key_pessimistically`), but the revocation may be
+//! dropped or replaced and never land — leaving a valid key blocked for
+//! good. Every `revocation_reconcile_secs` each pending revocation is
+//! checked against the vaults in `vault_addresses`:
+//!
+//!   - no vault reports the key active at the `finalized` block → the
+//!     revocation is confirmed and kept,
+//!   - `pessimistic_revocation_ttl_secs` has passed and some vault still
+//!     reports it active at `latest` → the revocation never landed and the
+//!     key is unblocked,
+//!   - otherwise it stays pending.
+//!
+//! A failed lookup never expires a revocation: it stays pending until a
+//! vault answers. Keys scoped to a non-default chain, or seen without any
+//! vault to read, can't be checked and are only expired by the TTL. A TTL
+//! of 0 never expires anything.
+
+use crate::abi;
+use crate::config::Config;
+use crate::reload::SharedConfigHandle;
+use crate::rpc;
+use crate::simulator;
+use anyhow::Result;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// `isSessionActive(address)`
+const IS_SESSION_ACTIVE: [u8; 4] = [0xa7, 0xcc, 0xc7, 0x63];
+
+/// What to do with a pending revocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The revocation is final on-chain: keep it.
+    Confirmed,
+    /// The revocation never landed: lift it.
+    Expired,
+    /// Not decided yet.
+    Pending,
+}
+
+/// Decide a revocation first seen at `seen_at`. `readable` says whether
+/// the key can be checked at all; `finalized_active` / `latest_active` say
+/// whether any vault reports the key active at that block, `None` when the
+/// lookup failed.
+pub fn verdict(
+    ttl_secs: u64,
+    seen_at: u64,
+    now: u64,
+    readable: bool,
+    finalized_active: Option<bool>,
+    latest_active: Option<bool>,
+) -> Verdict {
+    if finalized_active == Some(false) {
+        return Verdict::Confirmed;
+    }
+    let timed_out = ttl_secs > 0 && now.saturating_sub(seen_at) >= ttl_secs;
+    // Expire only on a vault still reporting the key active; revoked at
+    // `latest` but not final yet, or not read, it waits.
+    if timed_out && (!readable || latest_active == Some(true)) {
+        return Verdict::Expired;
+    }
+    Verdict::Pending
+}
+
+/// Whether any of `vaults` reports `session_key` active at `block`.
+async fn active_on_any(rpc_url: &str, vaults: &[String], session_key: &str, block: &str) -> Result<bool> {
+    let mut data = IS_SESSION_ACTIVE.to_vec();
+    data.extend_from_slice(&abi::encode_address(session_key).map_err(anyhow::Error::msg)?);
+    for vault in vaults {
+        let ret = simulator::eth_call(rpc_url, vault, &data, block).await?;
+        if !abi::uint(&ret, 0).map_err(anyhow::Error::msg)?.is_zero() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Check every pending revocation once. Returns (confirmed, expired).
+pub async fn reconcile(config: &Config) -> (usize, usize) {
+    let vaults: Vec<String> = config
+        .vault_addresses
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (mut confirmed, mut expired) = (0, 0);
+
+    for (key, seen_at) in rpc::pending_revocations() {
+        let readable = !vaults.is_empty() && !key.contains(':');
+        let (finalized, latest) = if readable {
+            let finalized = active_on_any(&config.upstream_rpc_url, &vaults, &key, "finalized").await;
+            let latest = active_on_any(&config.upstream_rpc_url, &vaults, &key, "latest").await;
+            if let Err(e) = finalized.as_ref().and(latest.as_ref()) {
+                warn!(session_key = %key, error = %e, "Revocation check failed");
+            }
+            (finalized.ok(), latest.ok())
+        } else {
+            (None, None)
+        };
+        match verdict(config.pessimistic_revocation_ttl_secs, seen_at, now, readable, finalized, latest) {
+            Verdict::Confirmed => {
+                rpc::confirm_revocation(&key);
+                info!(session_key = %key, "Session key revocation confirmed on-chain");
+                confirmed += 1;
+            }
+            Verdict::Expired => {
+                if rpc::expire_revocation(&key) {
+                    warn!(session_key = %key, "Pessimistic revocation never landed — session key unblocked");
+                    expired += 1;
+                }
+            }
+            Verdict::Pending => {}
+        }
+    }
+    (confirmed, expired)
+}
+
+/// Spawn the background reconciliation task. Reads the live config every
+/// round.
+pub fn spawn_reconcile_task(config: SharedConfigHandle) {
+    tokio::spawn(async move {
+        loop {
+            let cfg = config.current();
+            tokio::time::sleep(Duration::from_secs(cfg.revocation_reconcile_secs.max(1))).await;
+            let (confirmed, expired) = reconcile(&cfg).await;
+            if confirmed + expired > 0 {
+                info!(confirmed, expired, "Pessimistic revocations reconciled");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmed_at_finality() {
+        assert_eq!(verdict(1800, 1_000, 1_010, true, Some(false), Some(false)), Verdict::Confirmed);
+        // Confirmed even past the TTL.
+        assert_eq!(verdict(1800, 1_000, 9_000, true, Some(false), Some(true)), Verdict::Confirmed);
+    }
+
+    #[test]
+    fn test_expired_when_revocation_never_landed() {
+        assert_eq!(verdict(1800, 1_000, 2_000, true, Some(true), Some(true)), Verdict::Pending);
+        assert_eq!(verdict(1800, 1_000, 2_800, true, Some(true), Some(true)), Verdict::Expired);
+        // Unreadable: TTL only.
+        assert_eq!(verdict(1800, 1_000, 2_800, false, None, None), Verdict::Expired);
+        // The lookup failed: never expired on that.
+        assert_eq!(verdict(1800, 1_000, 9_000, true, None, None), Verdict::Pending);
+        assert_eq!(verdict(1800, 1_000, 9_000, true, Some(true), None), Verdict::Pending);
+        // Landed but not final yet.
+        assert_eq!(verdict(1800, 1_000, 2_800, true, Some(true), Some(false)), Verdict::Pending);
+        // TTL 0 never expires.
+        assert_eq!(verdict(0, 1_000, u64::MAX, true, Some(true), Some(true)), Verdict::Pending);
+    }
+
+    #[test]
+    fn test_expire_lifts_only_pending_revocations() {
+        let pending = "0xa9e00000000000000000000000000000000000d1";
+        let permanent = "0xa9e00000000000000000000000000000000000d2";
+        rpc::revoke_session_key_pessimistically(pending);
+        rpc::revoke_session_key(permanent);
+        assert!(rpc::pending_revocations().iter().any(|(k, _)| k == pending));
+        assert!(!rpc::expire_revocation(permanent));
+        assert!(rpc::is_session_revoked(permanent));
+        assert!(rpc::expire_revocation(pending));
+        assert!(!rpc::is_session_revoked(pending));
+
+        rpc::revoke_session_key_pessimistically(pending);
+        rpc::confirm_revocation(pending);
+        assert!(!rpc::expire_revocation(pending));
+        assert!(rpc::is_session_revoked(pending));
+    }
+}
@@ -1,47 +1,451 @@
 //! Axum router setup for the Plimsoll RPC Proxy.
 
+use crate::abi_registry;
+use crate::admin;
+use crate::audit;
+use crate::auth;
+use crate::chains::{self, ThreatFilters};
+use crate::cluster;
 use crate::config::Config;
+use crate::drawdown;
+use crate::fee;
+use crate::fee_ledger;
+use crate::gossip;
+#[cfg(feature = "grpc")]
+use crate::grpc;
+use crate::health::{self, HealthReport};
+use crate::identity;
+use crate::ioc_store;
+use crate::mcp;
+use crate::notify;
+use crate::otel;
+use crate::phishing;
+use crate::rate_limit;
+use crate::reload::{self, ConfigHandle, SharedConfigHandle};
+use crate::rescue;
+use crate::revocations;
 use crate::rpc;
+use crate::sanctions;
+use crate::selectors;
+use crate::shutdown;
+use crate::state_store::{self, SharedStateStore};
+use crate::telemetry;
 use crate::threat_feed::{self, SharedThreatFilter};
-use crate::types::JsonRpcRequest;
+use crate::types::{JsonRpcRequest, JsonRpcResponse, INVALID_REQUEST_CODE, PARSE_ERROR_CODE};
+use crate::vault_sync;
 use anyhow::Result;
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use axum::{
+    extract::{Path, State},
+    body::Bytes,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Extension, Json, Router,
+};
+use serde_json::Value;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
+use tracing::Instrument;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub config: Config,
+    /// v2.1: Live config, swappable via SIGHUP or `POST /admin/reload`.
+    pub config: SharedConfigHandle,
     /// Engine 0: Global Bloom Filter — shared across all request handlers.
     pub threat_filter: SharedThreatFilter,
+    /// v2.1: Engine 0 filters of the additional `chains`.
+    pub chain_filters: ThreatFilters,
+    /// v2.1: Persistent store for protective state (None = in-memory only).
+    pub state_store: Option<SharedStateStore>,
 }
 
 /// Build the Axum router with all RPC routes.
-pub async fn build_router(config: Config) -> Result<Router> {
+pub async fn build_router(config: Config, config_path: Option<PathBuf>) -> Result<Router> {
     let threat_filter = threat_feed::new_shared_filter();
-    tracing::info!("Engine 0 threat filter initialized (empty, awaiting Cloud push)");
+    let chain_filters = ThreatFilters::default();
+    // v2.1: Warm start every served chain from its last persisted filter.
+    if !threat_feed::restore(&config, None, &threat_filter) {
+        tracing::info!("Engine 0 threat filter initialized (empty, awaiting Cloud push)");
+    }
+    for chain in &config.chains {
+        let filter = chains::threat_filter(&config, &threat_filter, &chain_filters, chain.chain_id);
+        threat_feed::restore(&config, Some(chain.chain_id), &filter);
+    }
 
-    let state = Arc::new(AppState { config, threat_filter });
+    // v2.1: Restore protective state persisted by a previous run.
+    let state_store = state_store::open_store(&config.state_db_path)?;
+    if let Some(ref store) = state_store {
+        let snapshot = store.load()?;
+        tracing::info!(
+            revoked_keys = snapshot.revoked_session_keys.len(),
+            blocked_txs = snapshot.blocked_txs.len(),
+            revert_strikes = snapshot.revert_strikes.len(),
+            paymaster_severed = snapshot.paymaster_severed,
+            "Restored persisted proxy state from {}",
+            config.state_db_path
+        );
+        rpc::restore_state(snapshot);
+        state_store::spawn_snapshot_task(Arc::clone(store), config.state_snapshot_interval_secs);
+    }
+
+    // v2.1: Share protective state with the other replicas.
+    cluster::start(&config).await?;
+
+    // v2.1: Function selector directory (4byte seed + local overrides).
+    let known_selectors = selectors::load(&config)?;
+    tracing::info!(signatures = known_selectors, "Function selector directory loaded");
+    let known_abis = abi_registry::load(&config)?;
+    tracing::info!(abis = known_abis, "ABI registry loaded");
+
+    // v2.1: Sanctions screening fails closed — no list, no start.
+    if !config.sanctions_list_url.is_empty() {
+        let entries = sanctions::load(&config).await?;
+        tracing::info!(entries, "Sanctions screening enabled");
+    }
+
+    // v2.1: Identity signing uplinked IOCs.
+    let identity = identity::init(&config)?;
+    tracing::info!(public_key = %identity, "Proxy identity loaded — register this key with Plimsoll Cloud");
+
+    // v2.1: Local IOC store for STIX / TAXII export.
+    ioc_store::open(&config)?;
+
+    // v2.1: Hash-chained audit log of every decision.
+    audit::open(&config)?;
+
+    // v2.1: Fee accounting ledger.
+    fee_ledger::open(&config)?;
+
+    // v2.1: Every additional chain's upstream must serve that chain.
+    chains::verify_upstreams(&config).await?;
+
+    // v2.1: Shadow (log-only) mode until an operator turns enforcement on.
+    if config.shadow_mode {
+        rpc::set_shadow_mode(true);
+        tracing::warn!("SHADOW MODE: enforcement off — would-block decisions are only recorded");
+    }
+
+    let config = Arc::new(ConfigHandle::new(config, config_path));
+    reload::spawn_sighup_listener(Arc::clone(&config));
+    rescue::spawn_rescue_task(Arc::clone(&config), Arc::clone(&threat_filter));
+    threat_feed::spawn_refresh_task(Arc::clone(&config), Arc::clone(&threat_filter), Arc::clone(&chain_filters));
+    drawdown::spawn_snapshot_task(Arc::clone(&config));
+    vault_sync::spawn_sync_task(Arc::clone(&config));
+    revocations::spawn_reconcile_task(Arc::clone(&config));
+    sanctions::spawn_refresh_task(Arc::clone(&config));
+    phishing::spawn_refresh_task(Arc::clone(&config));
+    telemetry::spawn_uplink_task(Arc::clone(&config));
+    notify::spawn_notifier_task(Arc::clone(&config));
+    audit::spawn_checkpoint_task(Arc::clone(&config));
+    fee::spawn_settlement_task(Arc::clone(&config), state_store.clone());
+    shutdown::spawn_signal_listener(Arc::clone(&config), state_store.clone());
+    gossip::start(Arc::clone(&config));
+
+    let state = Arc::new(AppState { config, threat_filter, chain_filters, state_store });
+    #[cfg(feature = "grpc")]
+    grpc::spawn_server(Arc::clone(&state));
 
     let app = Router::new()
         .route("/", post(handle_rpc))
+        .route("/rpc/:api_key", post(handle_rpc_with_key))
         .route("/health", axum::routing::get(health))
+        .route("/healthz", axum::routing::get(healthz))
+        .route("/readyz", axum::routing::get(readyz))
+        .route("/metrics", axum::routing::get(metrics))
+        .route("/mcp", post(mcp::handle))
+        .nest("/admin", admin::routes(Arc::clone(&state)))
+        .nest("/gossip", gossip::routes())
         .layer(CorsLayer::permissive())
         .with_state(state);
 
     Ok(app)
 }
 
+/// Most calls one batch may carry.
+const MAX_BATCH: usize = 1000;
+
 /// POST / — Main JSON-RPC endpoint.
 async fn handle_rpc(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<JsonRpcRequest>,
-) -> (StatusCode, Json<serde_json::Value>) {
-    let response = rpc::handle_rpc(&state.config, &state.threat_filter, req).await;
-    (StatusCode::OK, Json(serde_json::to_value(response).unwrap()))
+    client_cert: Option<Extension<auth::ClientCert>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let credentials = auth::credentials(&headers, client_cert.as_deref());
+    serve_body(&state, credentials, chains::requested(&headers), &body).await
+}
+
+/// POST /rpc/:api_key — JSON-RPC endpoint with the agent key in the path,
+/// for clients that can only be configured with a URL.
+async fn handle_rpc_with_key(
+    State(state): State<Arc<AppState>>,
+    Path(api_key): Path<String>,
+    client_cert: Option<Extension<auth::ClientCert>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let credentials = auth::Credentials {
+        api_key: Some(api_key),
+        client_cert: client_cert.map(|Extension(cert)| cert.0),
+        admin_token: auth::presented_admin_token(&headers),
+    };
+    serve_body(&state, credentials, chains::requested(&headers), &body).await
+}
+
+fn rpc_error(id: Value, code: i64, message: String) -> Response {
+    Json(serde_json::to_value(JsonRpcResponse::error(id, code, message)).unwrap()).into_response()
+}
+
+/// Serve a JSON-RPC body: one call, or a batch of them run in order.
+///
+/// Notifications (calls without an `id`) run but get no response; a body
+/// of nothing but notifications answers `204 No Content`.
+async fn serve_body(
+    state: &AppState,
+    credentials: auth::Credentials,
+    requested_chain: Option<String>,
+    body: &[u8],
+) -> Response {
+    let call: Value = match serde_json::from_slice(body) {
+        Ok(call) => call,
+        Err(e) => return rpc_error(Value::Null, PARSE_ERROR_CODE, format!("Parse error: {e}")),
+    };
+
+    let Value::Array(calls) = call else {
+        let req = match JsonRpcRequest::parse(call) {
+            Ok(req) => req,
+            Err(response) => return Json(serde_json::to_value(response).unwrap()).into_response(),
+        };
+        let notification = req.notification;
+        let (status, headers, body) = serve_rpc(state, credentials, requested_chain, req).await;
+        if notification {
+            return (StatusCode::NO_CONTENT, headers).into_response();
+        }
+        return (status, headers, body).into_response();
+    };
+
+    if calls.is_empty() {
+        return rpc_error(Value::Null, INVALID_REQUEST_CODE, "Invalid Request: empty batch".into());
+    }
+    if calls.len() > MAX_BATCH {
+        return rpc_error(
+            Value::Null,
+            INVALID_REQUEST_CODE,
+            format!("Invalid Request: batch of {} calls exceeds {}", calls.len(), MAX_BATCH),
+        );
+    }
+
+    let mut responses = Vec::with_capacity(calls.len());
+    let mut first_headers = None;
+    for call in calls {
+        let req = match JsonRpcRequest::parse(call) {
+            Ok(req) => req,
+            Err(response) => {
+                responses.push(serde_json::to_value(response).unwrap());
+                continue;
+            }
+        };
+        let notification = req.notification;
+        let (_, headers, Json(body)) = serve_rpc(state, credentials.clone(), requested_chain.clone(), req).await;
+        first_headers.get_or_insert(headers);
+        if !notification {
+            responses.push(body);
+        }
+    }
+
+    let headers = first_headers.unwrap_or_default();
+    if responses.is_empty() {
+        return (StatusCode::NO_CONTENT, headers).into_response();
+    }
+    (StatusCode::OK, headers, Json(Value::Array(responses))).into_response()
+}
+
+/// Authenticate the agent, enforce its `from` binding, and run the request
+/// on the chain it selects.
+///
+/// The whole request lifecycle runs inside an `rpc_request` span whose
+/// trace ID is echoed in the `plimsoll-trace-id` response header.
+pub async fn serve_rpc(
+    state: &AppState,
+    credentials: auth::Credentials,
+    requested_chain: Option<String>,
+    req: JsonRpcRequest,
+) -> (StatusCode, HeaderMap, Json<serde_json::Value>) {
+    let span = tracing::info_span!(
+        "rpc_request",
+        method = %req.method,
+        agent = tracing::field::Empty,
+        chain = tracing::field::Empty,
+        trace_id = tracing::field::Empty,
+    );
+    let trace_id = otel::trace_id(&span);
+    span.record("trace_id", trace_id.as_str());
+
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&trace_id) {
+        headers.insert(otel::TRACE_ID_HEADER, value);
+    }
+
+    // Snapshot the live config: a reload mid-request doesn't affect this call.
+    let config = state.config.current();
+
+    // v2.1: Session key rotation and the fee report are admin calls: admin
+    // token, no agent key.
+    if req.method == admin::ROTATE_SESSION_KEY_METHOD {
+        let response = admin::rotate_session_key_rpc(state, credentials.admin_token.as_deref(), req);
+        return (StatusCode::OK, headers, Json(serde_json::to_value(response).unwrap()));
+    }
+    if req.method == admin::FEE_REPORT_METHOD {
+        let response = admin::fee_report_rpc(state, credentials.admin_token.as_deref(), req);
+        return (StatusCode::OK, headers, Json(serde_json::to_value(response).unwrap()));
+    }
+
+    // v2.1: Per-agent API key authentication.
+    let agent = match auth::authenticate(&config, &credentials) {
+        Ok(agent) => agent,
+        Err(e) => {
+            let _guard = span.enter();
+            tracing::warn!(error = ?e, "Agent authentication failed");
+            let body = JsonRpcResponse::error(
+                req.id,
+                -32001,
+                "Unauthorized: missing or invalid API key".into(),
+            );
+            return (StatusCode::UNAUTHORIZED, headers, Json(serde_json::to_value(body).unwrap()));
+        }
+    };
+
+    // v2.1: The agent may only send/sign for its own addresses.
+    if let Some(agent) = agent {
+        span.record("agent", agent.name.as_str());
+        if let Err(reason) = auth::check_from_binding(agent, &req) {
+            let _guard = span.enter();
+            tracing::warn!("{}", reason);
+            crate::metrics::record_block("agent_binding");
+            let body = JsonRpcResponse::blocked(req.id, auth::UNAUTHORIZED_SENDER_CODE, "agent_binding", reason);
+            return (StatusCode::OK, headers, Json(serde_json::to_value(body).unwrap()));
+        }
+    }
+
+    // v2.1: Per-agent, per-method-class rate limiting.
+    let agent_name = agent.map_or(rate_limit::ANONYMOUS_AGENT, |a| a.name.as_str());
+    if let Err(message) = rate_limit::check(&config, agent_name, &req.method) {
+        let _guard = span.enter();
+        tracing::warn!("{}", message);
+        crate::metrics::record_rate_limited(rate_limit::MethodClass::of(&req.method).label());
+        let body = JsonRpcResponse::error(req.id, rate_limit::LIMIT_EXCEEDED_CODE, message);
+        return (StatusCode::TOO_MANY_REQUESTS, headers, Json(serde_json::to_value(body).unwrap()));
+    }
+
+    // v2.1: Route to the header's chain, else the agent's, else the default.
+    let chain = match chains::select(&config, requested_chain.as_deref(), agent) {
+        Ok(chain) => chain,
+        Err(reason) => {
+            let _guard = span.enter();
+            tracing::warn!("{}", reason);
+            let body = JsonRpcResponse::error(req.id, chains::UNSUPPORTED_CHAIN_CODE, reason);
+            return (StatusCode::OK, headers, Json(serde_json::to_value(body).unwrap()));
+        }
+    };
+    span.record("chain", chain);
+    let threat_filter = chains::threat_filter(&config, &state.threat_filter, &state.chain_filters, chain);
+    let chain_config = chains::config_for(&config, chain);
+
+    let response = chains::scope(&config, chain, rpc::handle_rpc(&chain_config, &threat_filter, req))
+        .instrument(span)
+        .await;
+
+    (StatusCode::OK, headers, Json(serde_json::to_value(response).unwrap()))
 }
 
 /// GET /health — Health check endpoint.
 async fn health() -> &'static str {
     "plimsoll-rpc OK"
 }
+
+/// GET /healthz — Liveness probe. Always 200 while the process serves HTTP;
+/// the body carries the full component report.
+async fn healthz(State(state): State<Arc<AppState>>) -> Json<HealthReport> {
+    Json(health::build_report(&state.config.current(), &state.threat_filter).await)
+}
+
+/// GET /readyz — Readiness probe. 503 when the proxy cannot protect traffic.
+async fn readyz(State(state): State<Arc<AppState>>) -> (StatusCode, Json<HealthReport>) {
+    let mut report = health::build_report(&state.config.current(), &state.threat_filter).await;
+    // Draining for shutdown: take no new traffic.
+    report.ready &= !shutdown::draining();
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+/// GET /metrics — Prometheus scrape endpoint.
+async fn metrics() -> ([(axum::http::header::HeaderName, &'static str); 1], String) {
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        crate::metrics::render(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn state() -> AppState {
+        AppState {
+            config: Arc::new(ConfigHandle::new(Config::default(), None)),
+            threat_filter: threat_feed::new_shared_filter(),
+            chain_filters: ThreatFilters::default(),
+            state_store: None,
+        }
+    }
+
+    /// A call the proxy answers itself, so no upstream is needed.
+    fn call(id: Option<u64>) -> Value {
+        let mut call = json!({"jsonrpc": "2.0", "method": "plimsoll_getVaultLimits", "params": []});
+        if let Some(id) = id {
+            call["id"] = json!(id);
+        }
+        call
+    }
+
+    async fn serve(body: Value) -> (StatusCode, Value) {
+        let response = serve_body(&state(), auth::Credentials::default(), None, body.to_string().as_bytes()).await;
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = if bytes.is_empty() { Value::Null } else { serde_json::from_slice(&bytes).unwrap() };
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_batch_over_limit_rejected() {
+        let (_, body) = serve(Value::Array((0..=MAX_BATCH as u64).map(|id| call(Some(id))).collect())).await;
+        assert_eq!(body["error"]["code"], INVALID_REQUEST_CODE);
+        assert_eq!(body["id"], Value::Null);
+
+        let (status, body) = serve(Value::Array((0..MAX_BATCH as u64).map(|id| call(Some(id))).collect())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), MAX_BATCH);
+    }
+
+    #[tokio::test]
+    async fn test_notification_only_batch_returns_no_content() {
+        let (status, body) = serve(json!([call(None), call(None)])).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(body, Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_mixed_batch_drops_notification_responses() {
+        let (status, body) = serve(json!([call(Some(1)), call(None), call(Some(2))])).await;
+        assert_eq!(status, StatusCode::OK);
+        let ids: Vec<&Value> = body.as_array().unwrap().iter().map(|r| &r["id"]).collect();
+        assert_eq!(ids, [&json!(1), &json!(2)]);
+        assert!(body[0]["result"].is_object());
+    }
+}
@@ -10,18 +10,84 @@
 //!   (via WebSocket `pending` subscription), NOT when the block confirms.
 //!   This closes the 12-second window where a revoked key is still usable.
 
-use crate::config::Config;
+use crate::abi_registry;
+use crate::approval_diff;
+use crate::approvals;
+use crate::audit;
+use crate::block_codes;
+use crate::chains;
+use crate::cluster::{self, ClusterEvent};
+use crate::config::{is_hex_address, Config};
+use crate::counterparties;
+use crate::delegatecall;
+use crate::drawdown;
+use crate::eip4844;
+use crate::eip712;
+use crate::eip7702;
+use crate::ens;
+use crate::false_positives;
 use crate::fee;
+use crate::fee_ledger;
+use crate::forwarder;
+use crate::gas_fees;
+use crate::honeypot;
+use crate::intents;
+use crate::ioc_store;
+use crate::l2;
+use crate::metamorphic;
+use crate::method_policy;
+use crate::metrics;
+use crate::multicall;
+use crate::nonces;
+use crate::notify::{self, SecurityEvent};
+use crate::oracle;
+use crate::pending;
+use crate::permit2;
+use crate::phishing;
+use crate::pinning;
+use crate::poisoning;
+use crate::portfolio;
+use crate::protection;
+use crate::proxy;
+use crate::quarantine;
+use crate::reentrancy;
+use crate::replacement;
+use crate::reputation;
+use crate::rescue;
+use crate::response_meta;
+use crate::rugpull;
+use crate::safe;
+use crate::sanctions;
 use crate::sanitizer;
+use crate::seaport;
+use crate::selectors;
+use crate::session_scopes;
+use crate::shadow;
 use crate::simulator;
+use crate::siwe;
+use crate::state_store::ProxyStateSnapshot;
+use crate::target_allowlist;
 use crate::telemetry;
 use crate::threat_feed::{self, SharedThreatFilter};
-use crate::types::{JsonRpcRequest, JsonRpcResponse};
+use crate::types::{
+    BlockedTx, InnerCall, JsonRpcRequest, JsonRpcResponse, PendingTx, SimulationResult, SimulationState,
+    StateOverride,
+};
+use crate::vault_sync;
+use crate::velocity;
+use crate::verification;
+use alloy_primitives::U256;
 use anyhow::Result;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::rlp::Rlp;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{info, warn};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, info_span, warn, Instrument};
 
 /// Methods that involve broadcasting transactions (need simulation).
 const SEND_METHODS: &[&str] = &[
@@ -45,6 +111,33 @@ const SIGN_METHODS: &[&str] = &[
     "eth_signTypedData_v4",
 ];
 
+/// v2.1: Proxy-native method returning a counterparty's reputation score.
+const REPUTATION_METHOD: &str = "plimsoll_getReputation";
+
+/// v2.1: Proxy-native dispute of a block.
+const REPORT_FALSE_POSITIVE_METHOD: &str = "plimsoll_reportFalsePositive";
+
+/// v2.1: Proxy-native listing of quarantined sanitizer incidents.
+const SANITIZER_INCIDENTS_METHOD: &str = "plimsoll_getSanitizerIncidents";
+
+/// v2.1: Proxy-native ENS resolution with spoof detection.
+const RESOLVE_NAME_METHOD: &str = "plimsoll_resolveName";
+
+/// v2.1: Proxy-native dry-run simulation with optional state overrides.
+const SIMULATE_METHOD: &str = "plimsoll_simulate";
+
+/// v2.1: Proxy-native verdict on a send or signature, never forwarded.
+const DRY_RUN_METHOD: &str = "plimsoll_dryRun";
+
+/// v2.1: Proxy-native EIP-1559 fee recommendation.
+const SUGGEST_FEES_METHOD: &str = "plimsoll_suggestFees";
+
+/// v2.1: Proxy-native explanation of a blocked transaction.
+const EXPLAIN_BLOCK_METHOD: &str = "plimsoll_explainBlock";
+
+/// v2.1: Proxy-native listing of the limits enforced on the agent.
+const VAULT_LIMITS_METHOD: &str = "plimsoll_getVaultLimits";
+
 /// GOD-TIER 1: Known dangerous EIP-712 type hashes.
 /// These are keccak256 of the EIP-712 type strings used by major protocols.
 /// When we detect these in a signTypedData request, we translate the
@@ -75,6 +168,8 @@ mod permit_decoder {
         "MetaTransaction",    // Biconomy
         "ForwardRequest",     // OpenZeppelin Defender
         "Delegation",         // EIP-7702
+        "TransferWithAuthorization", // ERC-3009 (USDC)
+        "ReceiveWithAuthorization",  // ERC-3009 (USDC)
     ];
 
     /// Analyze an EIP-712 typed data payload and classify the risk.
@@ -96,9 +191,32 @@ mod permit_decoder {
             .iter()
             .any(|dt| primary_type.eq_ignore_ascii_case(dt));
 
-        if !is_dangerous_type {
+        // v2.1: Resolve the full type graph — a renamed primary type or a
+        // Permit nested under an innocuous wrapper is just as dangerous.
+        // A malformed type graph fails closed.
+        let graph_findings = match crate::eip712::find_dangerous_structs(typed_data) {
+            Ok(findings) => findings,
+            Err(e) => vec![format!("malformed EIP-712 types: {e}")],
+        };
+
+        if !is_dangerous_type && graph_findings.is_empty() {
             return (false, String::new(), String::new());
         }
+        if !is_dangerous_type {
+            let synthetic_action = format!(
+                "HIDDEN AUTHORIZATION in '{}': {}",
+                primary_type,
+                graph_findings.join("; ")
+            );
+            let risk_description = format!(
+                "GOD-TIER 1 (EIP-712 Silent Dagger): Agent asked to sign '{}', whose \
+                 type graph contains a token-moving struct: {}. The primary type \
+                 name is a disguise.",
+                primary_type,
+                graph_findings.join("; ")
+            );
+            return (true, synthetic_action, risk_description);
+        }
 
         // Extract the message body for deeper analysis
         let message = typed_data.get("message").cloned()
@@ -124,6 +242,16 @@ mod permit_decoder {
             .unwrap_or("unknown");
 
         let synthetic_action = match primary_type {
+            "Permit" if message.get("tokenId").is_some() => {
+                // ERC-4494: the NFT counterpart of an ERC-2612 permit.
+                let token_id = message.get("tokenId")
+                    .map(|v| v.as_str().map(String::from).unwrap_or_else(|| v.to_string()))
+                    .unwrap_or_default();
+                format!(
+                    "ERC721.approve({}, tokenId {}) on token {} (ERC-4494 permit)",
+                    spender, token_id, token
+                )
+            }
             "Permit" | "PermitSingle" => {
                 format!(
                     "ERC20.approve({}, {}) on token {}",
@@ -131,10 +259,18 @@ mod permit_decoder {
                 )
             }
             "PermitBatch" => {
-                format!(
-                    "BATCH ERC20.approve({}, MULTIPLE_TOKENS)",
-                    spender
-                )
+                // v2.1: Enumerate every (token, amount, expiration, nonce) tuple.
+                match crate::permit2::decode_details(typed_data) {
+                    Ok(Some(details)) if !details.is_empty() => format!(
+                        "BATCH ERC20.approve({}, [{}])",
+                        spender,
+                        crate::permit2::describe(&details)
+                    ),
+                    _ => format!(
+                        "BATCH ERC20.approve({}, MULTIPLE_TOKENS)",
+                        spender
+                    ),
+                }
             }
             "PermitTransferFrom" | "PermitWitnessTransferFrom" => {
                 format!(
@@ -142,12 +278,30 @@ mod permit_decoder {
                     spender, value, token
                 )
             }
-            "Order" | "OrderComponents" => {
+            "OrderComponents" => match crate::seaport::decode_order(typed_data) {
+                Ok(order) => format!("Seaport order: {}", crate::seaport::describe(&order)),
+                Err(_) => format!(
+                    "DEX Order: {} gains trading rights via signed order",
+                    spender
+                ),
+            },
+            "Order" => {
                 format!(
                     "DEX Order: {} gains trading rights via signed order",
                     spender
                 )
             }
+            "TransferWithAuthorization" | "ReceiveWithAuthorization" => {
+                // v2.1: ERC-3009 moves the tokens outright — report the
+                // recipient and amount, not a spender.
+                match crate::erc3009::decode(typed_data) {
+                    Ok(Some(auth)) => auth.to_string(),
+                    _ => format!(
+                        "ERC20.transferFrom(agent, UNKNOWN, UNKNOWN) on token {} via {}",
+                        token, primary_type
+                    ),
+                }
+            }
             _ => {
                 format!(
                     "DANGEROUS SIGNATURE: {} authorizes {} on {}",
@@ -173,7 +327,13 @@ mod permit_decoder {
 // eth_getTransactionReceipt, we return a synthetic reverted receipt
 // instead of null. This keeps the agent's web3 client alive.
 lazy_static::lazy_static! {
-    static ref BLOCKED_TX_STORE: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    static ref BLOCKED_TX_STORE: Mutex<HashMap<String, BlockedTx>> = Mutex::new(HashMap::new());
+
+    /// Call fingerprint (`call_fingerprint`) of a blocked send → its
+    /// synthetic tx hash and when it was blocked. Clients replay a failed
+    /// tx as an `eth_call` to fetch its revert reason; for
+    /// `BLOCKED_CALL_TTL` the replay reverts with the block reason.
+    static ref BLOCKED_CALLS: Mutex<HashMap<String, (String, Instant)>> = Mutex::new(HashMap::new());
 
     /// Zero-Day 2: Ghost Session — Pessimistic revocation cache.
     /// Session keys that appear in a `SessionKeyRevoked` event in the
@@ -182,6 +342,11 @@ lazy_static::lazy_static! {
     /// This closes the 12-second block confirmation window.
     static ref REVOKED_SESSION_KEYS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
 
+    /// v2.1: Pessimistic revocations not yet confirmed on-chain → unix
+    /// time first seen. A mempool revocation can be dropped or replaced;
+    /// `revocations` confirms or expires these.
+    static ref PENDING_REVOCATIONS: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+
     /// v1.0.2 Patch 4: Paymaster Slashing — Revert strike timestamps.
     /// Tracks timestamps of post-simulation on-chain reverts within a
     /// rolling window. When the count exceeds the threshold, the agent's
@@ -198,18 +363,66 @@ lazy_static::lazy_static! {
     static ref SIMULATED_GAS_STORE: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
 }
 
+/// Zero-Day 2: Set once the mempool revocation watcher task is running.
+static MEMPOOL_WATCHER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// v2.1: Shadow mode — evaluate everything, log would-block decisions,
+/// but forward all traffic unchanged. Starts at `shadow_mode`; toggled
+/// via the admin API.
+static SHADOW_MODE: AtomicBool = AtomicBool::new(false);
+
+tokio::task_local! {
+    /// v2.1: Would-block decision `(engine, reason)` captured for the
+    /// request currently being evaluated in shadow mode.
+    static SHADOW_VERDICT: RefCell<Option<(String, String)>>;
+
+    /// v2.1: Target and reputation score of the send being evaluated, so
+    /// a block is attributed to the counterparty and logged with its score.
+    static DECISION_TARGET: RefCell<Option<(String, u8)>>;
+
+    /// v2.1: `(agent, target)` of the send being decided, for the
+    /// security event its block raises.
+    static DECISION_PARTIES: (String, String);
+
+    /// v2.1: Set while a `plimsoll_dryRun` evaluates its request: nothing
+    /// is forwarded, and the verdicts and the send's simulation are
+    /// captured here.
+    static DRY_RUN: RefCell<DryRun>;
+
+    /// v2.1: Operator approvals `(fingerprint, engine)` the request being
+    /// enforced has claimed: used up once it is forwarded, given back if
+    /// it is blocked.
+    static APPROVALS: RefCell<Vec<(String, String)>>;
+}
+
+/// What a dry run captures while its request is evaluated.
+#[derive(Debug, Default)]
+struct DryRun {
+    /// Keep evaluating past a would-block, collecting every engine's
+    /// verdict; otherwise the first one ends the evaluation.
+    all_verdicts: bool,
+    /// `(engine, reason)` would-blocks so far, in pipeline order.
+    verdicts: Vec<(String, String)>,
+    simulation: Option<serde_json::Value>,
+}
+
 /// Zero-Day 2: SessionKeyRevoked event topic (keccak256 of event signature).
 /// `keccak256("SessionKeyRevoked(address,bytes32)")` — matches the
 /// PlimsollSessionManager.sol contract event.
 const SESSION_KEY_REVOKED_TOPIC: &str =
     "0x9e87fac88ff661f02d44f95383c817fece4bce600a3dab7a54406878b965e752";
 
+/// `key` in the state of the chain being served (v2.1 multi-chain).
+fn chain_key(key: &str) -> String {
+    chains::scope_key(chains::current(), key)
+}
+
 /// Zero-Day 2: Check if a session key has been pessimistically revoked.
 /// Called before simulation — if the sender's session key is in the
 /// revoked set, we reject immediately.
 pub fn is_session_revoked(session_key: &str) -> bool {
     if let Ok(store) = REVOKED_SESSION_KEYS.lock() {
-        store.contains(&session_key.to_lowercase())
+        store.contains(&chain_key(&session_key.to_lowercase()))
     } else {
         // Lock poisoned — fail closed (assume revoked)
         warn!("Revoked session key lock poisoned — failing closed");
@@ -217,18 +430,96 @@ pub fn is_session_revoked(session_key: &str) -> bool {
     }
 }
 
+/// Revoke a chain-scoped key; `pending_since` marks a pessimistic
+/// revocation, which never downgrades a permanent one. Returns `false`
+/// if nothing changed.
+fn revoke_key(key: &str, pending_since: Option<u64>) -> bool {
+    let Ok(mut store) = REVOKED_SESSION_KEYS.lock() else {
+        return false;
+    };
+    let Ok(mut pending) = PENDING_REVOCATIONS.lock() else {
+        return false;
+    };
+    match pending_since {
+        None => pending.remove(key).is_some() | store.insert(key.to_string()),
+        Some(_) if store.contains(key) => false,
+        Some(seen_at) => {
+            pending.insert(key.to_string(), seen_at);
+            store.insert(key.to_string())
+        }
+    }
+}
+
+/// Lift the revocation of a chain-scoped key. Returns `false` if it
+/// wasn't revoked.
+fn unrevoke_key(key: &str) -> bool {
+    if let Ok(mut pending) = PENDING_REVOCATIONS.lock() {
+        pending.remove(key);
+    }
+    REVOKED_SESSION_KEYS
+        .lock()
+        .map(|mut store| store.remove(key))
+        .unwrap_or(false)
+}
+
+/// Zero-Day 2: Add a session key to the revocation cache for good
+/// (admin API, confirmed revocations).
+pub fn revoke_session_key(session_key: &str) {
+    let key = chain_key(&session_key.to_lowercase());
+    if revoke_key(&key, None) {
+        info!(session_key = %key, "ZERO-DAY 2: Session key revoked");
+        notify::send(SecurityEvent::SessionRevoked { session_key: key.clone(), pending: false });
+        cluster::publish(ClusterEvent::Revoked { session_key: key, pending_since: None });
+    }
+}
+
 /// Zero-Day 2: Add a session key to the pessimistic revocation cache.
 /// Called when a `SessionKeyRevoked` event is seen in the mempool
-/// (pending transaction, NOT yet mined).
-pub fn revoke_session_key(session_key: &str) {
-    if let Ok(mut store) = REVOKED_SESSION_KEYS.lock() {
-        let key = session_key.to_lowercase();
+/// (pending transaction, NOT yet mined). The entry stays pending until
+/// the revocation is confirmed on-chain or `revocation_ttl_secs` passes.
+pub fn revoke_session_key_pessimistically(session_key: &str) {
+    let key = chain_key(&session_key.to_lowercase());
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if revoke_key(&key, Some(now)) {
         info!(
             session_key = %key,
             "ZERO-DAY 2: Session key pessimistically revoked from mempool"
         );
-        store.insert(key);
+        notify::send(SecurityEvent::SessionRevoked { session_key: key.clone(), pending: true });
+        cluster::publish(ClusterEvent::Revoked { session_key: key, pending_since: Some(now) });
+    }
+}
+
+/// v2.1: Pessimistic revocations awaiting confirmation, with the unix
+/// time each was first seen. Keys are as stored (chain-scoped).
+pub fn pending_revocations() -> Vec<(String, u64)> {
+    PENDING_REVOCATIONS
+        .lock()
+        .map(|pending| pending.iter().map(|(k, t)| (k.clone(), *t)).collect())
+        .unwrap_or_default()
+}
+
+/// v2.1: A pessimistic revocation landed on-chain: keep it for good.
+pub fn confirm_revocation(key: &str) {
+    if revoke_key(key, None) {
+        cluster::publish(ClusterEvent::Revoked { session_key: key.to_string(), pending_since: None });
+    }
+}
+
+/// v2.1: A pessimistic revocation never landed: lift it. Only pending
+/// entries are lifted. Returns `false` if `key` wasn't pending.
+pub fn expire_revocation(key: &str) -> bool {
+    let was_pending = PENDING_REVOCATIONS
+        .lock()
+        .map(|mut pending| pending.remove(key).is_some())
+        .unwrap_or(false);
+    if was_pending {
+        if let Ok(mut store) = REVOKED_SESSION_KEYS.lock() {
+            store.remove(key);
+        }
+        cluster::publish(ClusterEvent::Unrevoked { session_key: key.to_string() });
     }
+    was_pending
 }
 
 /// v1.0.2 Patch 4: Record a post-simulation on-chain revert.
@@ -244,6 +535,7 @@ pub fn record_revert_strike(config: &Config) {
         .unwrap_or_default()
         .as_secs();
 
+    metrics::record_revert_strike();
     if let Ok(mut tracker) = REVERT_STRIKE_TRACKER.lock() {
         tracker.push_back(now);
 
@@ -254,15 +546,14 @@ pub fn record_revert_strike(config: &Config) {
         }
 
         // Check if revert count exceeds threshold
-        if tracker.len() >= config.revert_strike_max as usize {
-            if let Ok(mut severed) = PAYMASTER_SEVERED.lock() {
-                *severed = true;
-                warn!(
-                    revert_count = tracker.len(),
-                    threshold = config.revert_strike_max,
-                    "PATCH 4 (PAYMASTER SLASHING): Paymaster severed — too many reverts"
-                );
-            }
+        if tracker.len() >= config.revert_strike_max as usize && set_paymaster_severed(true) {
+            warn!(
+                revert_count = tracker.len(),
+                threshold = config.revert_strike_max,
+                "PATCH 4 (PAYMASTER SLASHING): Paymaster severed — too many reverts"
+            );
+            cluster::publish(ClusterEvent::PaymasterSevered { severed: true });
+            notify::send(SecurityEvent::PaymasterSevered { strikes: tracker.len() });
         }
     }
 }
@@ -278,6 +569,278 @@ pub fn is_paymaster_severed() -> bool {
     }
 }
 
+/// v2.1: Manually clear the Paymaster sever (admin API). The revert strike
+/// window is reset too, otherwise the next strike would sever again.
+pub fn reset_paymaster_sever() {
+    if let Ok(mut tracker) = REVERT_STRIKE_TRACKER.lock() {
+        tracker.clear();
+    }
+    if set_paymaster_severed(false) {
+        cluster::publish(ClusterEvent::PaymasterSevered { severed: false });
+    }
+}
+
+/// Set the Paymaster severed flag. Returns `false` if it was already set
+/// that way.
+fn set_paymaster_severed(value: bool) -> bool {
+    PAYMASTER_SEVERED
+        .lock()
+        .map(|mut severed| std::mem::replace(&mut *severed, value) != value)
+        .unwrap_or(false)
+}
+
+/// v2.1: Remove a session key from the revocation cache (admin API).
+/// Returns `false` if the key was not revoked.
+pub fn unrevoke_session_key(session_key: &str) -> bool {
+    let key = chain_key(&session_key.to_lowercase());
+    let unrevoked = unrevoke_key(&key);
+    if unrevoked {
+        cluster::publish(ClusterEvent::Unrevoked { session_key: key });
+    }
+    unrevoked
+}
+
+/// v2.1: Apply a change made by another replica (see `cluster`), without
+/// publishing it again. Keys arrive chain-scoped.
+pub fn apply_cluster_event(event: ClusterEvent) {
+    match event {
+        ClusterEvent::Revoked { session_key, pending_since } => {
+            if revoke_key(&session_key.to_lowercase(), pending_since) {
+                info!(session_key = %session_key, "Session key revoked by another replica");
+            }
+        }
+        ClusterEvent::Unrevoked { session_key } => {
+            if unrevoke_key(&session_key.to_lowercase()) {
+                info!(session_key = %session_key, "Session key unrevoked by another replica");
+            }
+        }
+        ClusterEvent::PaymasterSevered { severed } => {
+            if !severed {
+                if let Ok(mut tracker) = REVERT_STRIKE_TRACKER.lock() {
+                    tracker.clear();
+                }
+            }
+            if set_paymaster_severed(severed) {
+                warn!(severed, "Paymaster sever changed by another replica");
+            }
+        }
+        ClusterEvent::Blocked { tx_hash, reason, engine } => {
+            if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
+                store.insert(tx_hash, BlockedTx { engine, reason, ..BlockedTx::default() });
+            }
+        }
+    }
+}
+
+/// v2.1: Drop all remembered blocked txs (admin API). Returns the count.
+pub fn flush_blocked_txs() -> usize {
+    if let Ok(mut calls) = BLOCKED_CALLS.lock() {
+        calls.clear();
+    }
+    BLOCKED_TX_STORE
+        .lock()
+        .map(|mut store| store.drain().count())
+        .unwrap_or(0)
+}
+
+/// A call as `parse_tx_params` reads it: from, to, value and data.
+type CallParams = (String, String, u128, Vec<u8>);
+
+/// The call a send makes: its params for `eth_sendTransaction`, the
+/// decoded transaction for `eth_sendRawTransaction`. `None` for anything
+/// else or a raw transaction that doesn't decode.
+fn sent_call(req: &JsonRpcRequest) -> Option<CallParams> {
+    match req.method.as_str() {
+        "eth_sendTransaction" => parse_tx_params(req).ok(),
+        "eth_sendRawTransaction" => decode_raw_call(req.params.get(0)?.as_str()?),
+        _ => None,
+    }
+}
+
+/// The call a raw signed transaction makes, from its recovered sender.
+fn decode_raw_call(raw_hex: &str) -> Option<CallParams> {
+    let raw = hex::decode(raw_hex.trim_start_matches("0x")).ok()?;
+    let wei = |v: ethers::types::U256| u128::try_from(v).unwrap_or(u128::MAX);
+    if let Some(tx) = eip7702::decode_raw(&raw).ok()? {
+        return Some((tx.sender, tx.to, wei(tx.value), tx.data));
+    }
+    if let Some(tx) = eip4844::decode_raw(&raw).ok()? {
+        return Some((tx.sender, tx.to, wei(tx.value), tx.data));
+    }
+    if let Some(tx) = l2::decode_deposit_raw(&raw).ok()? {
+        return Some((tx.from, tx.to.unwrap_or_default(), wei(tx.value), tx.data));
+    }
+    // ethers predates EIP-4844 and EIP-7702: legacy, 2930 and 1559 only.
+    let (tx, signature) = TypedTransaction::decode_signed(&Rlp::new(&raw)).ok()?;
+    let from = signature.recover(tx.sighash()).ok()?;
+    let to = tx.to().and_then(|to| to.as_address()).map(|to| format!("{:#x}", to)).unwrap_or_default();
+    let data = tx.data().map(|d| d.to_vec()).unwrap_or_default();
+    Some((format!("{:#x}", from), to, wei(tx.value().copied().unwrap_or_default()), data))
+}
+
+/// How long an `eth_call` replaying a blocked send reverts with its block
+/// reason. Clients replay right after the failed receipt; later the same
+/// call is an ordinary read.
+const BLOCKED_CALL_TTL: Duration = Duration::from_secs(300);
+
+/// Chain-scoped fingerprint of a call.
+fn call_fingerprint((from, to, value, data): &CallParams) -> String {
+    chain_key(&format!(
+        "{}:{}:{:x}:{}",
+        from.to_lowercase(),
+        to.to_lowercase(),
+        value,
+        hex::encode(data)
+    ))
+}
+
+/// Intrinsic gas of a transaction carrying `data`.
+fn intrinsic_gas(data: &[u8]) -> u64 {
+    21_000 + data.iter().map(|&b| if b == 0 { 4 } else { 16 }).sum::<u64>()
+}
+
+/// Patch 4: Remember the call of a send answered with a synthetic hash,
+/// for its receipt and for `eth_call` replays.
+fn remember_blocked_send(send: Option<CallParams>, response: &JsonRpcResponse) {
+    let hash = response.result.as_ref().and_then(|r| r.as_str());
+    let (Some(send), Some(hash)) = (send, hash) else {
+        return;
+    };
+    let hash = chain_key(hash);
+    if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
+        let Some(blocked) = store.get_mut(&hash) else {
+            return;
+        };
+        blocked.from = send.0.clone();
+        blocked.to = send.1.clone();
+        blocked.gas_used = intrinsic_gas(&send.3);
+    }
+    if let Ok(mut calls) = BLOCKED_CALLS.lock() {
+        calls.retain(|_, (_, at)| at.elapsed() < BLOCKED_CALL_TTL);
+        calls.insert(call_fingerprint(&send), (hash, Instant::now()));
+    }
+}
+
+/// The block reason an `eth_call` replaying a recently blocked send
+/// reverts with. Only a replay at `latest` / `pending` (or no tag) is
+/// one; a call at any other block is a plain historical read.
+fn blocked_call_reason(req: &JsonRpcRequest) -> Option<String> {
+    let tag = req.params.get(1).and_then(|t| t.as_str()).unwrap_or("latest");
+    if !matches!(tag, "latest" | "pending") {
+        return None;
+    }
+    let call = parse_tx_params(req).ok()?;
+    let hash = BLOCKED_CALLS
+        .lock()
+        .ok()?
+        .get(&call_fingerprint(&call))
+        .filter(|(_, at)| at.elapsed() < BLOCKED_CALL_TTL)?
+        .0
+        .clone();
+    BLOCKED_TX_STORE.lock().ok()?.get(&hash).map(|b| b.reason.clone())
+}
+
+/// v2.1: Block reason of a synthetic tx hash.
+pub fn blocked_reason(tx_hash: &str) -> Option<String> {
+    BLOCKED_TX_STORE.lock().ok()?.get(&chain_key(tx_hash)).map(|b| b.reason.clone())
+}
+
+/// Up to `limit` blocked txs of the current chain (synthetic hash, block),
+/// newest first.
+pub fn blocked_txs(limit: usize) -> Vec<(String, BlockedTx)> {
+    let prefix = chain_key("");
+    let Ok(store) = BLOCKED_TX_STORE.lock() else {
+        return Vec::new();
+    };
+    let mut blocked: Vec<(String, BlockedTx)> = store
+        .iter()
+        .filter_map(|(key, b)| {
+            let hash = key.strip_prefix(&prefix).filter(|hash| !hash.contains(':'))?;
+            Some((hash.to_string(), b.clone()))
+        })
+        .collect();
+    blocked.sort_by_key(|(_, b)| std::cmp::Reverse(b.blocked_at));
+    blocked.truncate(limit);
+    blocked
+}
+
+/// Number of blocked txs awaiting synthetic receipts.
+pub fn blocked_tx_count() -> usize {
+    BLOCKED_TX_STORE.lock().map(|s| s.len()).unwrap_or(0)
+}
+
+/// v2.1: Enable or disable shadow (log-only) mode.
+pub fn set_shadow_mode(enabled: bool) {
+    SHADOW_MODE.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_shadow_mode() -> bool {
+    SHADOW_MODE.load(Ordering::SeqCst)
+}
+
+/// v2.1: Capture the protective in-memory state for persistence.
+pub fn snapshot_state() -> ProxyStateSnapshot {
+    let mut snapshot = ProxyStateSnapshot::default();
+    if let Ok(store) = REVOKED_SESSION_KEYS.lock() {
+        snapshot.revoked_session_keys = store.iter().cloned().collect();
+    }
+    if let Ok(store) = BLOCKED_TX_STORE.lock() {
+        snapshot.blocked_txs = store.iter().map(|(k, b)| (k.clone(), b.reason.clone())).collect();
+        snapshot.blocked_tx_engines = store
+            .iter()
+            .filter(|(_, b)| !b.engine.is_empty())
+            .map(|(k, b)| (k.clone(), b.engine.clone()))
+            .collect();
+    }
+    if let Ok(tracker) = REVERT_STRIKE_TRACKER.lock() {
+        snapshot.revert_strikes = tracker.iter().copied().collect();
+    }
+    snapshot.pending_revocations = pending_revocations();
+    snapshot.paymaster_severed = is_paymaster_severed();
+    snapshot.counterparties = counterparties::snapshot();
+    snapshot.session_scopes = session_scopes::snapshot();
+    snapshot.false_positive_reports = false_positives::snapshot();
+    snapshot.fees_owed = fee::snapshot();
+    snapshot.fee_settlements = fee::pending_snapshot();
+    snapshot
+}
+
+/// v2.1: Restore protective state from a persisted snapshot (startup).
+/// Entries are merged into the live state — nothing already revoked or
+/// blocked in this process is ever un-revoked by a restore.
+pub fn restore_state(snapshot: ProxyStateSnapshot) {
+    if let Ok(mut store) = REVOKED_SESSION_KEYS.lock() {
+        store.extend(snapshot.revoked_session_keys.into_iter().map(|k| k.to_lowercase()));
+    }
+    if let Ok(mut pending) = PENDING_REVOCATIONS.lock() {
+        for (key, seen_at) in snapshot.pending_revocations {
+            pending.entry(key.to_lowercase()).or_insert(seen_at);
+        }
+    }
+    if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
+        let mut engines: HashMap<String, String> = snapshot.blocked_tx_engines.into_iter().collect();
+        store.extend(snapshot.blocked_txs.into_iter().map(|(hash, reason)| {
+            let engine = engines.remove(&hash).unwrap_or_default();
+            (hash, BlockedTx { engine, reason, ..BlockedTx::default() })
+        }));
+    }
+    if let Ok(mut tracker) = REVERT_STRIKE_TRACKER.lock() {
+        let mut strikes: Vec<u64> = tracker.drain(..).chain(snapshot.revert_strikes).collect();
+        strikes.sort_unstable();
+        tracker.extend(strikes);
+    }
+    if snapshot.paymaster_severed {
+        if let Ok(mut severed) = PAYMASTER_SEVERED.lock() {
+            *severed = true;
+        }
+    }
+    counterparties::restore(snapshot.counterparties);
+    session_scopes::restore(snapshot.session_scopes);
+    false_positives::restore(snapshot.false_positive_reports);
+    fee::restore(snapshot.fees_owed);
+    fee::restore_pending(snapshot.fee_settlements);
+}
+
 /// v1.0.3 Bounty 4: Store simulated gas for later comparison with receipt.
 fn store_simulated_gas(tx_hash: &str, gas_used: u64) {
     if let Ok(mut store) = SIMULATED_GAS_STORE.lock() {
@@ -496,18 +1059,7 @@ fn validate_eip712_chain_id(
     }
 
     // Parse chainId from various formats (int, hex string, decimal string)
-    let chain_id_val = chain_id_val.unwrap();
-    let parsed_chain_id: Option<u64> = if let Some(n) = chain_id_val.as_u64() {
-        Some(n)
-    } else if let Some(s) = chain_id_val.as_str() {
-        if s.starts_with("0x") || s.starts_with("0X") {
-            u64::from_str_radix(s.trim_start_matches("0x").trim_start_matches("0X"), 16).ok()
-        } else {
-            s.parse().ok()
-        }
-    } else {
-        None
-    };
+    let parsed_chain_id = eip712::parse_chain_id(chain_id_val.unwrap());
 
     match parsed_chain_id {
         None => Some(
@@ -655,6 +1207,7 @@ pub async fn start_mempool_revocation_watcher(
             contract = %contract,
             "Zero-Day 2: Starting mempool revocation watcher"
         );
+        MEMPOOL_WATCHER_RUNNING.store(true, Ordering::SeqCst);
 
         // Subscribe to pending logs matching SessionKeyRevoked topic
         let subscribe_payload = serde_json::json!({
@@ -677,18 +1230,192 @@ pub async fn start_mempool_revocation_watcher(
         );
 
         // Polling fallback: check every 2 seconds for new revocation events
-        // in the pending transaction pool.
-        loop {
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        // in the pending transaction pool, until shutdown stops the watcher.
+        while MEMPOOL_WATCHER_RUNNING.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_secs(2)).await;
 
             // In production: parse WebSocket frames for log events
             // containing SessionKeyRevoked, extract the session key
-            // from topics[1], and call revoke_session_key().
+            // from topics[1], and call revoke_session_key_pessimistically().
             //
             // let session_key = extract_session_key_from_log(&log);
-            // revoke_session_key(&session_key);
+            // revoke_session_key_pessimistically(&session_key);
         }
+        info!("Zero-Day 2: Mempool revocation watcher stopped");
+    });
+}
+
+/// Zero-Day 2: Stop the mempool revocation watcher (graceful shutdown).
+pub fn stop_mempool_revocation_watcher() {
+    MEMPOOL_WATCHER_RUNNING.store(false, Ordering::SeqCst);
+}
+
+/// Block a request: count it against `engine`, hand the agent a synthetic
+/// tx hash, and remember the reason so the matching receipt poll returns a
+/// synthetic reverted receipt (Patch 4). The agent stays alive.
+///
+/// In shadow mode the decision is only recorded; `handle_rpc` then
+/// forwards the original request.
+fn block_request(id: serde_json::Value, engine: &str, reason: String) -> JsonRpcResponse {
+    response_meta::blocked(engine, &reason);
+    if record_shadow_verdict(engine, &reason) {
+        return JsonRpcResponse::plimsoll_synthetic_send(id, &reason).0;
+    }
+
+    metrics::record_block(engine);
+    if let Ok(Some((target, score))) = DECISION_TARGET.try_with(|t| t.borrow().clone()) {
+        reputation::record_block(engine, &target);
+        warn!(engine, target = %target, reputation = score, "Decision: blocked");
+    }
+    let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(id, &reason);
+    let tx_hash = chain_key(&tx_hash);
+    if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
+        let blocked = BlockedTx {
+            engine: engine.to_string(),
+            reason: reason.clone(),
+            blocked_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            ..BlockedTx::default()
+        };
+        store.insert(tx_hash.clone(), blocked);
+    }
+    let (agent, target) = DECISION_PARTIES.try_with(|p| p.clone()).unwrap_or_default();
+    notify::send(SecurityEvent::Blocked {
+        tx_hash: tx_hash.clone(),
+        engine: engine.to_string(),
+        reason: reason.clone(),
+        chain: chains::current(),
+        agent,
+        target,
     });
+    cluster::publish(ClusterEvent::Blocked { tx_hash, reason, engine: engine.to_string() });
+    resp
+}
+
+/// In shadow mode, record the would-block decision for the current request
+/// and return `true`; the caller's response is then discarded.
+fn record_shadow_verdict(engine: &str, reason: &str) -> bool {
+    let shadowed = SHADOW_VERDICT
+        .try_with(|verdict| {
+            *verdict.borrow_mut() = Some((engine.to_string(), reason.to_string()));
+        })
+        .is_ok();
+    if shadowed && !is_dry_run() {
+        shadow::record(engine, reason, shadow::Scope::Global);
+    }
+    shadowed
+}
+
+/// Block for `engine` — unless it is in `shadow_engines`: then only the
+/// would-block is recorded and `None` lets the pipeline carry on.
+fn block_unless_shadowed(
+    config: &Config,
+    id: &serde_json::Value,
+    engine: &str,
+    reason: String,
+) -> Option<JsonRpcResponse> {
+    if !shadow::engine_shadowed(config, engine) {
+        if collect_dry_run_verdict(engine, &reason) {
+            return None;
+        }
+        return Some(block_request(id.clone(), engine, reason));
+    }
+    if !is_dry_run() {
+        shadow::record(engine, &reason, shadow::Scope::Engine);
+    }
+    None
+}
+
+/// Block for `engine` according to its protection mode (`protection`):
+/// `warn` lets the pipeline carry on, `approve` holds the request for an
+/// operator and lets an approved resubmission through.
+fn intercept(
+    config: &Config,
+    req: &JsonRpcRequest,
+    engine: &str,
+    reason: String,
+) -> Option<JsonRpcResponse> {
+    match protection::mode(config, engine) {
+        protection::Mode::Block => block_unless_shadowed(config, &req.id, engine, reason),
+        protection::Mode::Warn => {
+            warn!(engine, reason = %reason, "Protection mode warn — forwarding");
+            None
+        }
+        protection::Mode::Approve => {
+            if SHADOW_VERDICT.try_with(|_| ()).is_ok() || shadow::engine_shadowed(config, engine) {
+                return block_unless_shadowed(config, &req.id, engine, reason);
+            }
+            let fingerprint = protection::fingerprint(&req.method, &req.params);
+            if protection::claim(config, &fingerprint, engine) {
+                info!(engine, fingerprint = %fingerprint, "Operator-approved request — passing");
+                let claim = (fingerprint, engine.to_string());
+                if let Err(e) = APPROVALS.try_with(|a| a.borrow_mut().push(claim.clone())) {
+                    info!(error = %e, "No approval scope — using the approval up now");
+                    protection::consume(&claim.0, &claim.1);
+                }
+                return None;
+            }
+            let held = protection::queue(config, engine, &req.method, &reason, fingerprint);
+            warn!(engine, approval = %held.id, "Request held for operator approval");
+            let reason = format!("{reason} [held for operator approval: {}]", held.id);
+            Some(block_request(req.id.clone(), engine, reason))
+        }
+    }
+}
+
+/// Whether a block by `engine` is only recorded or not final: shadow
+/// (global or per engine), a dry run, or a `warn` / `approve` protection
+/// mode. Such blocks leave no local blocklist entries and no shared IOCs.
+fn is_shadowed(config: &Config, engine: &str) -> bool {
+    SHADOW_VERDICT.try_with(|_| ()).is_ok()
+        || shadow::engine_shadowed(config, engine)
+        || protection::mode(config, engine) != protection::Mode::Block
+}
+
+/// Report the IOC of a block by `engine`. A shadowed block's IOC is only
+/// kept in the local IOC store; a dry run reports nothing.
+fn report_ioc(config: &Config, engine: &str, ioc: &telemetry::IOCReport) {
+    if is_dry_run() {
+        return;
+    }
+    if is_shadowed(config, engine) {
+        ioc_store::record(ioc);
+    } else {
+        telemetry::uplink_ioc(ioc, config);
+    }
+}
+
+/// Whether the current request is a `plimsoll_dryRun` evaluation.
+fn is_dry_run() -> bool {
+    DRY_RUN.try_with(|_| ()).is_ok()
+}
+
+/// In a dry run collecting every verdict, record `engine`'s would-block
+/// and return `true`: the pipeline carries on to the next engine.
+fn collect_dry_run_verdict(engine: &str, reason: &str) -> bool {
+    DRY_RUN
+        .try_with(|dry_run| {
+            let mut dry_run = dry_run.borrow_mut();
+            if dry_run.all_verdicts {
+                response_meta::blocked(engine, reason);
+                dry_run.verdicts.push((engine.to_string(), reason.to_string()));
+            }
+            dry_run.all_verdicts
+        })
+        .unwrap_or(false)
+}
+
+/// Number of session keys in the pessimistic revocation cache.
+pub fn revoked_session_key_count() -> usize {
+    REVOKED_SESSION_KEYS.lock().map(|s| s.len()).unwrap_or(0)
+}
+
+/// Zero-Day 2: Mempool revocation watcher status for health reporting.
+pub fn mempool_watcher_status() -> &'static str {
+    if MEMPOOL_WATCHER_RUNNING.load(Ordering::SeqCst) {
+        "running (HTTP polling fallback)"
+    } else {
+        "disabled"
+    }
 }
 
 /// Handle an incoming JSON-RPC request.
@@ -696,8 +1423,102 @@ pub async fn handle_rpc(
     config: &Config,
     threat_filter: &SharedThreatFilter,
     req: JsonRpcRequest,
+) -> JsonRpcResponse {
+    let audited = !config.audit_log_path.is_empty();
+    if !config.response_metadata && !audited {
+        return decide(config, threat_filter, req).await;
+    }
+
+    // ── v2.1: Decision metadata on the response / audit log ─────
+    let started = Instant::now();
+    let audit_request = audited.then(|| (protection::fingerprint(&req.method, &req.params), req.method.clone()));
+    let (mut response, decision) = response_meta::collect(decide(config, threat_filter, req)).await;
+    if let Some((request_hash, method)) = audit_request {
+        let verdict = match &decision.blocked_by {
+            _ if method == DRY_RUN_METHOD => "dry_run",
+            None => "forwarded",
+            Some(_) if is_shadow_mode() => "would_block",
+            Some(_) => "blocked",
+        };
+        let engines = response_meta::engines(&decision).into_iter().map(String::from).collect();
+        audit::record_decision(
+            request_hash,
+            &method,
+            engines,
+            verdict,
+            decision.blocked_by.clone(),
+            decision.reason.clone(),
+        );
+    }
+    if config.response_metadata {
+        let score = risk_score(decision.blocked_by.is_some(), decision.reputation, decision.loss_pct);
+        response.plimsoll = Some(response_meta::render(&decision, score, started.elapsed()));
+    }
+    response
+}
+
+/// Decide `req`: enforce it, or in shadow mode evaluate and forward it.
+async fn decide(
+    config: &Config,
+    threat_filter: &SharedThreatFilter,
+    req: JsonRpcRequest,
+) -> JsonRpcResponse {
+    let send = sent_call(&req);
+    let parties = send.as_ref().map(|s| (s.0.to_lowercase(), s.1.to_lowercase())).unwrap_or_default();
+    DECISION_TARGET
+        .scope(RefCell::new(None), DECISION_PARTIES.scope(parties, async {
+            if !is_shadow_mode() {
+                let (response, unused) = APPROVALS
+                    .scope(RefCell::new(Vec::new()), async {
+                        let response = enforce_rpc(config, threat_filter, req).await;
+                        (response, APPROVALS.with(|a| a.take()))
+                    })
+                    .await;
+                // Approvals a forward didn't use up: blocked after all.
+                for (fingerprint, engine) in unused {
+                    protection::release(&fingerprint, &engine);
+                }
+                remember_blocked_send(send, &response);
+                return response;
+            }
+
+            // v2.1: Shadow mode — run the full pipeline, but if any engine
+            // would block, forward the original request instead.
+            let original = req.clone();
+            let (response, verdict) = SHADOW_VERDICT
+                .scope(RefCell::new(None), async {
+                    let response = enforce_rpc(config, threat_filter, req).await;
+                    let verdict = SHADOW_VERDICT.with(|v| v.borrow_mut().take());
+                    (response, verdict)
+                })
+                .await;
+
+            match verdict {
+                Some(_) => proxy_to_upstream(config, &original).await,
+                None => response,
+            }
+        }))
+        .await
+}
+
+/// Run every engine against `req` and either block it or forward it.
+async fn enforce_rpc(
+    config: &Config,
+    threat_filter: &SharedThreatFilter,
+    req: JsonRpcRequest,
 ) -> JsonRpcResponse {
     info!(method = %req.method, "RPC request received");
+    metrics::record_request(&req.method);
+
+    // ── v2.1: Operator method allowlist / denylist ──────────────
+    // Methods the operator hasn't sanctioned never reach the upstream.
+    if let Err(reason) = method_policy::check(config, &req.method) {
+        warn!("{}", reason);
+        if !record_shadow_verdict("method_policy", &reason) {
+            metrics::record_block("method_policy");
+        }
+        return JsonRpcResponse::blocked(req.id, method_policy::METHOD_NOT_ALLOWED_CODE, "method_policy", reason);
+    }
 
     // ── Patch 4: Intercept receipt polling for synthetic txs ─────
     // If the agent calls eth_getTransactionReceipt on a blocked tx hash,
@@ -707,37 +1528,212 @@ pub async fn handle_rpc(
             .and_then(|a| a.first())
             .and_then(|v| v.as_str())
         {
-            if let Ok(store) = BLOCKED_TX_STORE.lock() {
-                if let Some(reason) = store.get(hash) {
-                    info!(tx_hash = hash, "Returning synthetic receipt for blocked tx");
-                    return JsonRpcResponse::plimsoll_synthetic_receipt(
-                        req.id, hash, reason,
-                    );
-                }
+            let blocked = BLOCKED_TX_STORE.lock().ok().and_then(|s| s.get(&chain_key(hash)).cloned());
+            if let Some(blocked) = blocked {
+                return synthetic_receipt(config, req.id, hash, blocked).await;
             }
         }
     }
 
-    // ── v1.0.2 Patch 4: Paymaster Sever Check ──────────────────
-    // If the Paymaster has been severed due to too many post-simulation
-    // reverts, block ALL outgoing transactions immediately.
-    if is_paymaster_severed() && SEND_METHODS.contains(&req.method.as_str()) {
-        let reason = "PLIMSOLL PATCH 4 (PAYMASTER SLASHING): Paymaster connection severed. \
-                       Too many post-simulation reverts detected — all transactions blocked \
-                       to prevent gas drain."
-            .to_string();
-        warn!("{}", reason);
-        let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &reason);
-        if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-            store.insert(tx_hash, reason);
+    // ── Patch 4: Revert reasons for blocked txs ──────────────────
+    // Clients fetch a failed tx's revert reason by tracing it or by
+    // replaying it as an eth_call; both get the Error(string)-encoded
+    // block reason.
+    if req.method == "debug_traceTransaction" {
+        let params = req.params.as_array();
+        let hash = params.and_then(|a| a.first()).and_then(|v| v.as_str()).unwrap_or("");
+        if let Some(reason) = blocked_reason(hash) {
+            let tracer = params
+                .and_then(|a| a.get(1))
+                .and_then(|o| o.get("tracer"))
+                .and_then(|t| t.as_str());
+            info!(tx_hash = hash, "Returning synthetic trace for blocked tx");
+            return JsonRpcResponse::plimsoll_synthetic_trace(req.id, &reason, tracer);
+        }
+    }
+    if req.method == "eth_call" {
+        if let Some(reason) = blocked_call_reason(&req) {
+            info!("Returning synthetic revert for replayed blocked tx");
+            return JsonRpcResponse::plimsoll_synthetic_revert(req.id, &reason);
         }
-        return resp;
     }
 
-    // ── GOD-TIER 1: EIP-712 Silent Dagger Interception ─────────
-    // Intercept ALL cryptographic signing endpoints. The agent should
-    // NEVER blindly sign off-chain messages — they can be weaponized
-    // as Permit2 approvals, gasless swap orders, or governance votes.
+    // ── v2.1: Counterparty reputation lookup ────────────────────
+    // Answered locally; never forwarded upstream.
+    if req.method == REPUTATION_METHOD {
+        let address = req.params.as_array()
+            .and_then(|a| a.first())
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        if !is_hex_address(address) {
+            return JsonRpcResponse::error(
+                req.id,
+                -32602,
+                format!("Invalid params: {REPUTATION_METHOD} expects an address"),
+            );
+        }
+        let rep = reputation::assess(config, threat_filter, address).await;
+        return JsonRpcResponse::success(req.id, serde_json::to_value(rep).unwrap_or_default());
+    }
+
+    // ── v2.1: False-positive reports ────────────────────────────
+    // The agent disputes a block; see `false_positives`.
+    if req.method == REPORT_FALSE_POSITIVE_METHOD {
+        let params = req.params.as_array();
+        let subject = params.and_then(|a| a.first()).and_then(|v| v.as_str()).unwrap_or("");
+        let note = params.and_then(|a| a.get(1)).and_then(|v| v.as_str()).unwrap_or("");
+        return match false_positives::report(config, subject, note) {
+            Ok(report) => JsonRpcResponse::success(req.id, serde_json::to_value(report).unwrap_or_default()),
+            Err(e) => JsonRpcResponse::error(
+                req.id,
+                -32602,
+                format!("Invalid params: {REPORT_FALSE_POSITIVE_METHOD}: {e}"),
+            ),
+        };
+    }
+
+    // ── v2.1: Quarantined sanitizer incidents ───────────────────
+    // The evidence the read-path sanitizer scrubbed; see `quarantine`.
+    if req.method == SANITIZER_INCIDENTS_METHOD {
+        let source = req.params.as_array().and_then(|a| a.first()).and_then(|v| v.as_str());
+        if source.is_some_and(|s| !is_hex_address(s)) {
+            return JsonRpcResponse::error(
+                req.id,
+                -32602,
+                format!("Invalid params: {SANITIZER_INCIDENTS_METHOD} expects an address or no params"),
+            );
+        }
+        return JsonRpcResponse::success(
+            req.id,
+            serde_json::to_value(quarantine::list(source)).unwrap_or_default(),
+        );
+    }
+
+    // ── v2.1: ENS resolution for agent-declared payees ──────────
+    // "Pay vitalik.eth" resolves here, through the spoof checks, rather
+    // than through whatever resolver the agent's tooling trusts.
+    if req.method == RESOLVE_NAME_METHOD {
+        let name = req.params.as_array()
+            .and_then(|a| a.first())
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        if !ens::is_ens_name(name) {
+            return JsonRpcResponse::error(
+                req.id,
+                -32602,
+                format!("Invalid params: {RESOLVE_NAME_METHOD} expects an ENS name"),
+            );
+        }
+        if !ens::deployed(config) {
+            return JsonRpcResponse::error(
+                req.id,
+                chains::UNSUPPORTED_CHAIN_CODE,
+                format!("ENS is not deployed on chain {}", config.default_chain_id()),
+            );
+        }
+        let resolution = if config.ens_spoof_detection {
+            ens::verify(config, name).await
+        } else {
+            match ens::resolve(config, name).await {
+                Ok(Some(address)) => Ok(ens::Resolution {
+                    name: name.to_lowercase(),
+                    address,
+                    primary_name: None,
+                    age_secs: None,
+                }),
+                Ok(None) => Err(format!("PLIMSOLL ENS: {name:?} does not resolve to an address")),
+                Err(e) => Err(format!("PLIMSOLL ENS: lookup of {name:?} failed: {e}")),
+            }
+        };
+        return match resolution {
+            Ok(r) => {
+                info!(name = %r.name, address = %r.address, "ENS name resolved");
+                JsonRpcResponse::success(
+                    req.id,
+                    serde_json::json!({
+                        "name": r.name,
+                        "address": r.address,
+                        "primaryName": r.primary_name,
+                        "ageSecs": r.age_secs,
+                    }),
+                )
+            }
+            Err(reason) => {
+                warn!("{}", reason);
+                metrics::record_block("ens");
+                JsonRpcResponse::blocked(req.id, -32000, "ens", reason)
+            }
+        };
+    }
+
+    // ── v2.1: Dry-run simulation against hypothetical state ─────
+    if req.method == SIMULATE_METHOD {
+        return simulate_rpc(config, req).await;
+    }
+
+    // ── v2.1: Dry-run verdict on a send or signature ────────────
+    if req.method == DRY_RUN_METHOD {
+        return dry_run_rpc(config, threat_filter, req).await;
+    }
+
+    // ── v2.1: Fee guidance from the proxy's own market read ─────
+    // Agents ask here instead of a fee API that could be poisoned into
+    // suggesting tips the gas fee ceilings would then block.
+    if req.method == SUGGEST_FEES_METHOD {
+        return match gas_fees::reference(&config.upstream_rpc_url, config.fee_history_blocks).await {
+            Ok(reference) => JsonRpcResponse::success(req.id, gas_fees::suggest(config, &reference).to_json()),
+            Err(e) => JsonRpcResponse::error(req.id, -32000, format!("Fee history unavailable: {e}")),
+        };
+    }
+
+    // ── v2.1: Why a synthetic tx hash was blocked ───────────────
+    if req.method == EXPLAIN_BLOCK_METHOD {
+        let hash = req.params.as_array()
+            .and_then(|a| a.first())
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let blocked = BLOCKED_TX_STORE.lock().ok().and_then(|s| s.get(&chain_key(hash)).cloned());
+        return match blocked {
+            Some(blocked) => JsonRpcResponse::success(req.id, explain_block(hash, &blocked)),
+            None => JsonRpcResponse::error(
+                req.id,
+                -32602,
+                format!("Invalid params: {EXPLAIN_BLOCK_METHOD} expects the hash of a blocked transaction"),
+            ),
+        };
+    }
+
+    // ── v2.1: Limits the agent's sends are held to ──────────────
+    // Velocity (with the agent's spend so far), drawdown, loss and
+    // target limits, as synced from its vaults.
+    if req.method == VAULT_LIMITS_METHOD {
+        let agent = req.params.as_array().and_then(|a| a.first()).and_then(|v| v.as_str());
+        if agent.is_some_and(|a| !is_hex_address(a)) {
+            return JsonRpcResponse::error(
+                req.id,
+                -32602,
+                format!("Invalid params: {VAULT_LIMITS_METHOD} expects an address or no params"),
+            );
+        }
+        return JsonRpcResponse::success(req.id, vault_limits(config, agent));
+    }
+
+    // ── v1.0.2 Patch 4: Paymaster Sever Check ──────────────────
+    // If the Paymaster has been severed due to too many post-simulation
+    // reverts, block ALL outgoing transactions immediately.
+    if is_paymaster_severed() && SEND_METHODS.contains(&req.method.as_str()) {
+        let reason = "PLIMSOLL PATCH 4 (PAYMASTER SLASHING): Paymaster connection severed. \
+                       Too many post-simulation reverts detected — all transactions blocked \
+                       to prevent gas drain."
+            .to_string();
+        warn!("{}", reason);
+        return block_request(req.id, "paymaster", reason);
+    }
+
+    // ── GOD-TIER 1: EIP-712 Silent Dagger Interception ─────────
+    // Intercept ALL cryptographic signing endpoints. The agent should
+    // NEVER blindly sign off-chain messages — they can be weaponized
+    // as Permit2 approvals, gasless swap orders, or governance votes.
     if SIGN_METHODS.contains(&req.method.as_str()) {
         warn!(
             method = %req.method,
@@ -759,6 +1755,25 @@ pub async fn handle_rpc(
                 typed_data
             };
 
+            // ── v2.1: Sanctions screening ───────────────────────────
+            // The verifying contract and every counterparty the message
+            // names (spender, operator, taker, recipient, ...).
+            let signer = req.params.get(0).and_then(|v| v.as_str()).unwrap_or_default();
+            if let Err(reason) = sanctions::check_typed_data(config, signer, &parsed_data) {
+                warn!("{}", reason);
+                return block_request(req.id, "sanctions", reason);
+            }
+
+            // ── v2.1: Target allowlist ──────────────────────────────
+            // A signature is held to the same list as a send: the
+            // verifying contract and whoever it empowers.
+            if let Err(reason) = target_allowlist::check_typed_data(config, &parsed_data) {
+                warn!("{}", reason);
+                if let Some(blocked) = block_unless_shadowed(config, &req.id, "target_allowlist", reason) {
+                    return blocked;
+                }
+            }
+
             // ── v1.0.2 Patch 3: Cross-Chain Replay Defense ──────
             // Validate chainId in the EIP-712 domain BEFORE checking
             // dangerous primary types. Missing/zero/mismatched chainId
@@ -767,13 +1782,9 @@ pub async fn handle_rpc(
                 &parsed_data, config.expected_chain_id
             ) {
                 warn!("{}", chain_err);
-                let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(
-                    req.id, &chain_err,
-                );
-                if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-                    store.insert(tx_hash, chain_err);
+                if let Some(blocked) = block_unless_shadowed(config, &req.id, "eip712_chain_id", chain_err) {
+                    return blocked;
                 }
-                return resp;
             }
 
             // ── v1.0.4 Kill-Shot 4: Permit2 Time-Bomb Defense ──────
@@ -783,13 +1794,211 @@ pub async fn handle_rpc(
                 &parsed_data, config.max_permit_duration_secs
             ) {
                 warn!("{}", deadline_err);
-                let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(
-                    req.id, &deadline_err,
-                );
-                if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-                    store.insert(tx_hash, deadline_err);
+                if let Some(blocked) = block_unless_shadowed(config, &req.id, "eip712_deadline", deadline_err) {
+                    return blocked;
+                }
+            }
+
+            // ── v2.1: Phishing domains ──────────────────────────────
+            // A domain name that only *reads* like the real protocol, or
+            // a link to a known drainer site, blocks the signature.
+            if config.phishing_detection {
+                if let Err(reason) = phishing::check_typed_data(config, &parsed_data) {
+                    warn!("{}", reason);
+                    // A drainer kit's payload: whoever it grants power to is
+                    // the drainer — reject it in Engine 0 from now on.
+                    let message = parsed_data.get("message");
+                    if let Some(spender) = ["spender", "operator", "taker"]
+                        .iter()
+                        .find_map(|k| message.and_then(|m| m.get(k)).and_then(|v| v.as_str()))
+                    {
+                        if !is_shadowed(config, "eip712_phishing") {
+                            threat_feed::block_locally(config, spender, "eip712_phishing");
+                        }
+                    }
+                    if let Some(blocked) = block_unless_shadowed(config, &req.id, "eip712_phishing", reason) {
+                        return blocked;
+                    }
+                }
+            }
+
+            // ── v2.1: ENS names inside the signed message ───────────
+            // A payee written as a name is only as good as what it
+            // resolves to; spoofed names block the signature. Off the
+            // ENS chains only the name itself can be checked.
+            if config.ens_spoof_detection {
+                for name in ens::names_in(&parsed_data) {
+                    let verdict = if ens::deployed(config) {
+                        ens::verify(config, &name).await.map(|r| (r.name, r.address))
+                    } else {
+                        ens::check_name(config, &name).map(|()| (name.to_lowercase(), "unresolved".to_string()))
+                    };
+                    match verdict {
+                        Ok((name, address)) => info!(name = %name, address = %address, "ENS name in typed data verified"),
+                        Err(reason) => {
+                            warn!("{}", reason);
+                            if let Some(blocked) = block_unless_shadowed(config, &req.id, "ens", reason) {
+                                return blocked;
+                            }
+                        }
+                    }
+                }
+            }
+
+            // ── v2.1: Permit2 per-token policy ──────────────────────
+            // Decode every PermitDetails tuple and enforce the unlimited-
+            // allowance, per-token cap and expiration-horizon rules. Runs
+            // before the trusted-domain bypass on purpose.
+            let primary_type = parsed_data
+                .get("primaryType")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            if primary_type == "PermitSingle" || primary_type == "PermitBatch" {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let verdict = permit2::decode_details(&parsed_data).and_then(|details| {
+                    let details = details.unwrap_or_default();
+                    info!(details = %permit2::describe(&details), "Permit2 details decoded");
+                    permit2::check_details(config, &details, now)
+                });
+                if let Err(reason) = verdict {
+                    warn!("{}", reason);
+                    if let Some(blocked) = block_unless_shadowed(config, &req.id, "permit2_details", reason) {
+                        return blocked;
+                    }
+                }
+            }
+
+            // ── v2.1: Seaport give-away detection ───────────────────
+            // NFTs or tokens offered for zero / dust consideration to the
+            // offerer is the "free mint" phishing signature.
+            if primary_type == "OrderComponents" {
+                let verdict = match seaport::decode_order(&parsed_data) {
+                    Ok(order) => seaport::check_order(config, &order, &seaport::value(config, &order).await),
+                    Err(e) => Err(e),
+                };
+                match verdict {
+                    Ok(summary) => info!(order = %summary, "Seaport order decoded"),
+                    Err(reason) => {
+                        warn!("{}", reason);
+                        if let Some(blocked) = block_unless_shadowed(config, &req.id, "seaport", reason) {
+                            return blocked;
+                        }
+                    }
+                }
+            }
+
+            // ── v2.1: Intent order price sanity (CowSwap / UniswapX) ─
+            // Block orders that don't pay the signer at a market-consistent
+            // price. A fair order still faces the checks below.
+            if config.order_max_price_deviation_pct > 0.0 {
+                let signer = req.params.as_array()
+                    .and_then(|a| a.first())
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                let verdict = match intents::decode(&parsed_data, signer) {
+                    Ok(Some(order)) => Some(intents::check_order(config, &order, signer).await),
+                    Ok(None) => None,
+                    Err(e) => Some(Err(format!("PLIMSOLL INTENT: undecodable order: {e}"))),
+                };
+                match verdict {
+                    Some(Ok(summary)) => info!(order = %summary, "GOD-TIER 1: Intent order priced within tolerance"),
+                    Some(Err(reason)) => {
+                        warn!("{}", reason);
+                        if let Some(blocked) = block_unless_shadowed(config, &req.id, "intent_price", reason) {
+                            return blocked;
+                        }
+                    }
+                    None => {}
                 }
-                return resp;
+            }
+
+            // ── v2.1: ERC-2771 / meta-transaction deep decode ───────
+            // A ForwardRequest is a transaction in disguise. Vet the
+            // wrapped call like a direct send and judge it on that.
+            match forwarder::decode(&parsed_data) {
+                Ok(Some(call)) => {
+                    let signer = req.params.as_array()
+                        .and_then(|a| a.first())
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("");
+                    if !call.from.eq_ignore_ascii_case(signer) {
+                        let reason = format!(
+                            "PLIMSOLL ERC-2771: {} executes as {} but is signed by {}",
+                            primary_type, call.from, signer
+                        );
+                        warn!("{}", reason);
+                        if let Some(blocked) = block_unless_shadowed(config, &req.id, "forward_request", reason) {
+                            return blocked;
+                        }
+                    }
+                    info!(
+                        to = %call.to,
+                        value = call.value,
+                        "GOD-TIER 1: Meta-transaction decoded — vetting inner call"
+                    );
+                    match vet_inner_call(config, threat_filter, &call).await {
+                        Ok(()) => {
+                            info!("GOD-TIER 1: Meta-transaction inner call passed — forwarding");
+                            return proxy_to_upstream(config, &req).await;
+                        }
+                        Err((engine, reason)) => {
+                            let reason = format!(
+                                "PLIMSOLL ERC-2771 ({}): inner call {} on {} blocked — {}",
+                                primary_type, selectors::describe(&call.data), call.to, reason
+                            );
+                            warn!("{}", reason);
+                            if let Some(blocked) = block_unless_shadowed(config, &req.id, &engine, reason) {
+                                return blocked;
+                            }
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    let reason = format!("PLIMSOLL ERC-2771: undecodable {primary_type}: {e}");
+                    warn!("{}", reason);
+                    if let Some(blocked) = block_unless_shadowed(config, &req.id, "forward_request", reason) {
+                        return blocked;
+                    }
+                }
+            }
+
+            // ── v2.1: EIP-7702 / delegation-framework grants ────────
+            // A signed `Delegation` hands the delegate power over the
+            // signer's account; only verified delegates are allowed.
+            if primary_type == "Delegation" {
+                let signer = req.params.as_array()
+                    .and_then(|a| a.first())
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                match eip7702::check_typed_delegation(config, threat_filter, &parsed_data, signer).await {
+                    Ok(summary) => {
+                        info!(delegation = %summary, "GOD-TIER 1: Delegation to verified delegate — forwarding");
+                        return proxy_to_upstream(config, &req).await;
+                    }
+                    Err(reason) => {
+                        warn!("{}", reason);
+                        if let Some(blocked) = block_unless_shadowed(config, &req.id, "eip7702", reason) {
+                            return blocked;
+                        }
+                    }
+                }
+            }
+
+            // ── v2.1: Operator-trusted EIP-712 domains ──────────────
+            // Vetted protocols (exact verifyingContract + name + chainId)
+            // are allowed to request dangerous primary types.
+            if let Some(trusted) = eip712::trusted_domain(config, &parsed_data) {
+                info!(
+                    verifying_contract = %trusted.verifying_contract,
+                    name = %trusted.name,
+                    chain_id = trusted.chain_id,
+                    "GOD-TIER 1: EIP-712 domain is operator-trusted — forwarding"
+                );
+                return proxy_to_upstream(config, &req).await;
             }
 
             let (is_dangerous, synthetic_action, risk_desc) =
@@ -811,15 +2020,11 @@ pub async fn handle_rpc(
                     from, "eip712_permit", &[], "permit_decoder",
                     &risk_desc, None, 1,
                 );
-                telemetry::uplink_ioc(&ioc, "https://cloud.plimsoll.network/v1/ioc").await;
+                report_ioc(config, "eip712_permit", &ioc);
 
-                let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(
-                    req.id, &risk_desc,
-                );
-                if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-                    store.insert(tx_hash, risk_desc);
+                if let Some(blocked) = intercept(config, &req, "eip712_permit", risk_desc) {
+                    return blocked;
                 }
-                return resp;
             }
         }
 
@@ -827,6 +2032,29 @@ pub async fn handle_rpc(
         // Raw message signing is ALWAYS dangerous for an AI agent.
         // A human can sign arbitrary messages; an AI agent cannot
         // distinguish a "login challenge" from a "drain everything" payload.
+        // ── v2.1: SIWE exception ─────────────────────────────────
+        // A `personal_sign` whose payload is a strictly-valid EIP-4361
+        // login for an allowlisted domain is the one raw message an agent
+        // may sign. Anything else falls through to the blanket block.
+        if req.method == "personal_sign" && !config.siwe_allowed_domains.is_empty() {
+            match siwe::check_personal_sign(config, &req.params, chrono::Utc::now()) {
+                Ok(domain) => {
+                    info!(domain = %domain, "GOD-TIER 1: SIWE login message allowed");
+                    return proxy_to_upstream(config, &req).await;
+                }
+                Err(e) => warn!(error = %e, "GOD-TIER 1: personal_sign is not a compliant SIWE login"),
+            }
+        }
+
+        if req.method == "eth_sign" {
+            if let Err(reason) = target_allowlist::check_raw_sign(config) {
+                warn!("{}", reason);
+                if let Some(blocked) = block_unless_shadowed(config, &req.id, "target_allowlist", reason) {
+                    return blocked;
+                }
+            }
+        }
+
         if req.method == "eth_sign" || req.method == "personal_sign" {
             let reason = format!(
                 "GOD-TIER 1: Raw message signing ({}) blocked. \
@@ -836,11 +2064,9 @@ pub async fn handle_rpc(
                 req.method
             );
             warn!("{}", reason);
-            let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &reason);
-            if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-                store.insert(tx_hash, reason);
+            if let Some(blocked) = intercept(config, &req, "raw_sign", reason) {
+                return blocked;
             }
-            return resp;
         }
     }
 
@@ -856,7 +2082,16 @@ pub async fn handle_rpc(
         {
             // Convert to serde_json::Value for sanitization
             if let Ok(mut resp_json) = serde_json::to_value(&response) {
-                let (tainted, details) = sanitizer::sanitize_rpc_response(&mut resp_json);
+                let detector = sanitizer::detector(&config.sanitizer_patterns);
+                let original = response.result.clone().unwrap_or_default();
+                let (tainted, details) = match sanitizer::sanitize_rpc_response(&mut resp_json, &detector) {
+                    Ok(outcome) => outcome,
+                    Err(reason) => {
+                        warn!(method = %req.method, "{}", reason);
+                        quarantine::record(config, &req.method, &req.params, &original, None, std::slice::from_ref(&reason));
+                        return JsonRpcResponse::blocked(req.id, -32000, "sanitizer", reason);
+                    }
+                };
                 if tainted {
                     warn!(
                         method = %req.method,
@@ -865,6 +2100,7 @@ pub async fn handle_rpc(
                     );
                     // Reconstruct the response from sanitized JSON
                     if let Some(result) = resp_json.get("result").cloned() {
+                        quarantine::record(config, &req.method, &req.params, &original, Some(&result), &details);
                         response.result = Some(result);
                     }
                 }
@@ -898,7 +2134,7 @@ pub async fn handle_rpc(
             {
                 if let Some(ref result) = response.result {
                     if let Some(simulated_gas) = get_simulated_gas(hash) {
-                        let receipt_gas = parse_gas_used_from_receipt(result);
+                        let receipt_gas = l2::execution_gas(config, result, parse_gas_used_from_receipt(result));
                         if simulated_gas > 0 && receipt_gas > 0 {
                             let ratio = receipt_gas as f64 / simulated_gas as f64;
                             if ratio > config.gas_anomaly_ratio {
@@ -921,6 +2157,16 @@ pub async fn handle_rpc(
         return response;
     }
 
+    enforce_send(config, threat_filter, req).await
+}
+
+/// Run every send-path engine against a transaction and either block it
+/// or forward it.
+async fn enforce_send(
+    config: &Config,
+    threat_filter: &SharedThreatFilter,
+    mut req: JsonRpcRequest,
+) -> JsonRpcResponse {
     // ── Transaction methods: simulate first ─────────────────────
     info!("Intercepted send tx — running pre-flight simulation");
 
@@ -936,16 +2182,98 @@ pub async fn handle_rpc(
                 dup_key
             );
             warn!("{}", reason);
-            let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &reason);
-            if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-                store.insert(tx_hash, reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "json_pollution", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: EIP-7702 SetCode transactions ─────────────────────
+    // Type-4 raw transactions are decoded so the authorization list can
+    // be vetted and the real call simulated.
+    let raw_tx = if req.method == "eth_sendRawTransaction" {
+        req.params.as_array()
+            .and_then(|a| a.first())
+            .and_then(|v| v.as_str())
+    } else {
+        None
+    };
+    let set_code_tx = match raw_tx.map(eip7702::decode_raw_hex) {
+        Some(Ok(tx)) => tx,
+        Some(Err(e)) => {
+            let reason = format!("PLIMSOLL EIP-7702: {e}");
+            warn!("{}", reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "eip7702", reason) {
+                return blocked;
             }
-            return resp;
+            None
+        }
+        None => None,
+    };
+
+    // ── v2.1: EIP-4844 blob transactions ────────────────────────
+    // Type-3 envelopes (with their sidecar) are decoded so the call they
+    // carry is simulated rather than read as an empty transaction.
+    let blob_tx = match raw_tx.map(eip4844::decode_raw_hex) {
+        Some(Ok(tx)) => tx,
+        Some(Err(e)) => {
+            let reason = format!("PLIMSOLL EIP-4844: {e}");
+            warn!("{}", reason);
+            return block_request(req.id, "eip4844", reason);
+        }
+        None => None,
+    };
+
+    // ── v2.1: OP Stack deposit transactions ────────────────────
+    // Type-0x7E envelopes are unsigned; their call is vetted like any
+    // other, and they are refused on chains that aren't OP Stack.
+    let deposit_tx = match raw_tx.map(l2::decode_deposit_raw_hex) {
+        Some(Ok(tx)) => tx,
+        Some(Err(e)) => {
+            let reason = format!("PLIMSOLL L2: {e}");
+            warn!("{}", reason);
+            return block_request(req.id, "l2", reason);
+        }
+        None => None,
+    };
+    if let Some(tx) = &deposit_tx {
+        if let Err(reason) = l2::check_deposit(config, tx) {
+            warn!("{}", reason);
+            return block_request(req.id, "l2", reason);
         }
     }
+    // The signed bytes the L1 data fee is priced on.
+    let raw_bytes = raw_tx.and_then(|raw| hex::decode(raw.trim_start_matches("0x")).ok());
 
     // Parse tx parameters from the request
-    let (from, to, value, data) = match parse_tx_params(&req) {
+    let parsed = match (&set_code_tx, &blob_tx, &deposit_tx) {
+        (Some(tx), _, _) => Ok((
+            tx.sender.clone(),
+            tx.to.clone(),
+            u128::try_from(tx.value).unwrap_or(u128::MAX),
+            tx.data.clone(),
+        )),
+        (None, None, Some(tx)) => {
+            info!(from = %tx.from, source_hash = ?tx.source_hash, "OP Stack deposit transaction decoded");
+            Ok((
+                tx.from.clone(),
+                tx.to.clone().unwrap_or_default(),
+                u128::try_from(tx.value).unwrap_or(u128::MAX),
+                tx.data.clone(),
+            ))
+        }
+        (None, Some(tx), _) => {
+            info!(blobs = tx.blob_versioned_hashes.len(), sender = %tx.sender, "EIP-4844: blob transaction decoded");
+            Ok((
+                tx.sender.clone(),
+                tx.to.clone(),
+                u128::try_from(tx.value).unwrap_or(u128::MAX),
+                tx.data.clone(),
+            ))
+        }
+        (None, None, None) => info_span!("parse").in_scope(|| parse_tx_params(&req)),
+    };
+    let (from, to, value, data) = match parsed {
         Ok(params) => params,
         Err(e) => {
             warn!("Failed to parse tx params: {}", e);
@@ -953,17 +2281,126 @@ pub async fn handle_rpc(
         }
     };
 
+    // ── v2.1: Chain id ──────────────────────────────────────────
+    // A transaction for another chain than the one it is routed to.
+    let tx_chain_id = match (&set_code_tx, &blob_tx) {
+        (Some(tx), _) => Some(tx.chain_id),
+        (None, Some(tx)) => Some(tx.chain_id),
+        (None, None) => req.params.get(0).and_then(|tx| tx.get("chainId")).and_then(eip712::parse_chain_id),
+    };
+    if let Err(reason) = chains::check_tx_chain_id(config, tx_chain_id) {
+        warn!("{}", reason);
+        return block_request(req.id, "chain", reason);
+    }
+
+    // Every authorization must delegate to a verified, non-blacklisted
+    // implementation. The delegate code `to` will run is simulated below.
+    let delegated_code = match &set_code_tx {
+        Some(tx) => {
+            info!(
+                authorizations = tx.authorizations.len(),
+                sender = %tx.sender,
+                "EIP-7702: SetCode transaction decoded"
+            );
+            match eip7702::check_set_code_tx(config, threat_filter, tx).await {
+                Ok(code) => code,
+                Err(reason) => {
+                    warn!("{}", reason);
+                    if let Some(blocked) = block_unless_shadowed(config, &req.id, "eip7702", reason) {
+                        return blocked;
+                    }
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    // ── v2.1: Function selector directory + function policy ─────
+    // Resolve the called function so logs, block reasons and IOCs name
+    // it, and enforce the operator's blocked-function list.
+    let function = selectors::resolve(config, &data).await;
+    info!(function = function.as_deref().unwrap_or("unknown"), to = %to, "Target function resolved");
+    if let Err(reason) = selectors::check_function_policy(config, &data) {
+        warn!("{}", reason);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "function_policy", reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Unbounded approvals (ERC-20 / operator / NFT permit) ──
+    if let Err(reason) = approvals::check(config, &to, &data) {
+        warn!("{}", reason);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "approval", reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Counterparty reputation ───────────────────────────
+    // Scored once per send; the score rides along with the decision.
+    if config.reputation_scoring {
+        let rep = reputation::assess(config, threat_filter, &to)
+            .instrument(info_span!("reputation"))
+            .await;
+        info!(to = %rep.address, reputation = rep.score, signals = ?rep.signals, "Counterparty reputation scored");
+        response_meta::reputation(rep.score);
+        let _ = DECISION_TARGET.try_with(|t| *t.borrow_mut() = Some((rep.address, rep.score)));
+    }
+
+    // ── v2.1: Address-poisoning lookalikes ─────────────────────
+    if config.address_poisoning_check {
+        if let Err(reason) = poisoning::check(config, &from, &to, &data) {
+            warn!("{}", reason);
+            if config.block_address_poisoning {
+                if let Some(blocked) = block_unless_shadowed(config, &req.id, "address_poisoning", reason) {
+                    return blocked;
+                }
+            }
+        }
+    }
+
+    // ── v2.1: First interaction with a counterparty ────────────
+    if let Err(reason) = counterparties::check_first_interaction(config, &from, &to, value, &data).await {
+        warn!("{}", reason);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "first_interaction", reason) {
+            return blocked;
+        }
+    }
+
     // ── v1.0.4 Kill-Shot 2: PVG Heist Defense ────────────────────
     // Check preVerificationGas BEFORE simulation, since PVG is invisible
     // to the EVM simulator. This must run before ANY simulation.
     if let Some(tx_obj) = req.params.as_array().and_then(|a| a.first()) {
         if let Err(pvg_reason) = enforce_pvg_ceiling(config, tx_obj) {
             warn!("{}", pvg_reason);
-            let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &pvg_reason);
-            if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-                store.insert(tx_hash, pvg_reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "pvg", pvg_reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: Gas price / priority fee ceilings ─────────────────
+    // Gas is paid outside the value flows physics measures.
+    let fees = match &blob_tx {
+        Some(tx) => gas_fees::TxFees {
+            max_fee: u128::try_from(tx.max_fee_per_gas).ok(),
+            priority_fee: u128::try_from(tx.max_priority_fee_per_gas).ok(),
+        },
+        None => req.params.as_array().and_then(|a| a.first()).map(gas_fees::parse).unwrap_or_default(),
+    };
+    if config.gas_fee_ceilings && fees != gas_fees::TxFees::default() {
+        let reference = match gas_fees::reference(&config.upstream_rpc_url, config.fee_history_blocks).await {
+            Ok(reference) => Some(reference),
+            Err(e) => {
+                warn!(error = %e, "eth_feeHistory unavailable — relative fee ceilings skipped");
+                None
+            }
+        };
+        if let Err(reason) = gas_fees::check(config, &fees, reference.as_ref()) {
+            warn!("{}", reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "gas_fee", reason) {
+                return blocked;
             }
-            return resp;
         }
     }
 
@@ -972,81 +2409,586 @@ pub async fn handle_rpc(
     // in Arbitrum/Optimism bridge calls don't match the sender, block.
     if let Err(bridge_reason) = validate_bridge_params(config, &from, &to, &data) {
         warn!("{}", bridge_reason);
-        let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &bridge_reason);
-        if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-            store.insert(tx_hash, bridge_reason);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "bridge", bridge_reason) {
+            return blocked;
+        }
+    }
+
+    // ── ZERO-DAY 2: Pessimistic Session Key Check ──────────────
+    // Before ANY engine runs, check if the sender's session key has
+    // been revoked in the mempool. This closes the 12-second window
+    // between mempool revocation and block confirmation.
+    if is_session_revoked(&from) {
+        let reason = format!(
+            "PLIMSOLL ZERO-DAY 2: Session key {} pessimistically revoked \
+             (seen in mempool before block confirmation)",
+            &from
+        );
+        warn!("{}", reason);
+        return block_request(req.id, "session_revoked", reason);
+    }
+
+    // ── v2.1: Session key scope ─────────────────────────────────
+    // A scoped key may only reach its own targets and selectors, up to
+    // its value cap, until it expires.
+    if let Err(reason) = session_scopes::check(config, &from, &to, value, &data) {
+        warn!("{}", reason);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "session_scope", reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Target allowlist ──────────────────────────────────
+    // Every contract the send ultimately reaches must be listed.
+    // Decoded up front: a rejected send costs no simulation.
+    if let Err(reason) = target_allowlist::check(config, &to, &data) {
+        warn!("{}", reason);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "target_allowlist", reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Gnosis Safe unwrapping ────────────────────────────
+    // execTransaction hides the real target/value/data behind a call to
+    // the agent's own Safe. Vet every unwrapped operation as the Safe.
+    match safe::decode(config, &to, &data) {
+        Ok(Some(safe::SafeCall::ApproveHash(hash))) => {
+            let reason = format!(
+                "PLIMSOLL SAFE: approveHash({}) pre-approves an opaque Safe transaction \
+                 that cannot be inspected",
+                hash
+            );
+            warn!("{}", reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "safe", reason) {
+                return blocked;
+            }
+        }
+        Ok(Some(safe::SafeCall::Exec(ops))) => {
+            info!(operations = ops.len(), safe = %to, "Safe execTransaction decoded");
+            if let Err(reason) = safe::check_operations(config, &ops) {
+                warn!("{}", reason);
+                if let Some(blocked) = block_unless_shadowed(config, &req.id, "safe", reason) {
+                    return blocked;
+                }
+            }
+            for op in &ops {
+                if let Err((engine, reason)) = vet_inner_call(config, threat_filter, &op.call).await {
+                    let reason = format!(
+                        "PLIMSOLL SAFE: operation {} on {} blocked — {}",
+                        selectors::describe(&op.call.data), op.call.to, reason
+                    );
+                    warn!("{}", reason);
+                    if let Some(blocked) = block_unless_shadowed(config, &req.id, &engine, reason) {
+                        return blocked;
+                    }
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            let reason = format!("PLIMSOLL SAFE: undecodable Safe call: {e}");
+            warn!("{}", reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "safe", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: Recursive multicall unwrapping ────────────────────
+    // A drainer call wrapped in aggregate3 shows Engine 0 only the
+    // benign Multicall3 address. Screen every leaf call.
+    if let Err((engine, reason)) = screen_batch(config, threat_filter, &to, &data) {
+        warn!("{}", reason);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, engine, reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Sanctions screening (OFAC SDN) ────────────────────
+    // Exact-match compliance check, audited; kept out of Engine 0 so
+    // feed allowlists and overrides can never clear a sanctions hit.
+    if let Err(reason) = sanctions::check(config, &from, &to, &data) {
+        warn!("{}", reason);
+        return block_request(req.id, "sanctions", reason);
+    }
+    if let Some(tx) = &set_code_tx {
+        let delegates: Vec<String> = tx.authorizations.iter().map(|a| a.address.clone()).collect();
+        if let Err(reason) = sanctions::check_delegates(config, &from, &to, &delegates) {
+            warn!("{}", reason);
+            return block_request(req.id, "sanctions", reason);
+        }
+    }
+
+    // ── ENGINE 0: Global Bloom Filter Pre-Flight ────────────────
+    // Runs BEFORE Engines 1-6. Sub-millisecond O(1) lookup against
+    // the Swarm-compiled global blacklist.
+    let engine0_hit = info_span!("engine0").in_scope(|| {
+        threat_feed::engine0_match(config, threat_filter, &to, &data)
+    });
+    if let Some(hit) = engine0_hit {
+        warn!(source = %hit.source, "{}", hit.reason);
+        // Extract IOC and uplink to Plimsoll Cloud
+        let mut ioc = telemetry::extract_ioc(
+            &from, &to, &data, "bloom", &hit.reason, None, 1,
+        );
+        ioc.threat_source = Some(hit.source);
+        report_ioc(config, "engine0", &ioc);
+        // Patch 4: Return synthetic tx hash — agent stays alive
+        if let Some(blocked) = intercept(config, &req, "engine0", hit.reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Proxy implementation resolution ───────────────────
+    // The proxy address is a thin shell; the code that runs is the
+    // implementation's. Resolve it, pin its codehash and screen it too.
+    let proxy_impl = if config.resolve_proxy_implementations {
+        match proxy::resolve(&config.upstream_rpc_url, &to)
+            .instrument(info_span!("proxy"))
+            .await
+        {
+            Ok(resolution) => resolution,
+            Err(e) => {
+                warn!(error = %e, "Proxy resolution failed — screening the proxy address only");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if let Some(resolution) = &proxy_impl {
+        info!(
+            proxy = %to,
+            implementation = %resolution.implementation,
+            kind = resolution.kind.label(),
+            codehash = %resolution.codehash,
+            "Proxy implementation resolved and pinned"
+        );
+        let (blocked, reason) =
+            threat_feed::engine0_check(config, threat_filter, &resolution.implementation, &data);
+        if blocked {
+            let reason = format!(
+                "PLIMSOLL PROXY: {} implementation {} behind {} — {}",
+                resolution.kind.label(), resolution.implementation, to, reason
+            );
+            warn!("{}", reason);
+            if let Some(blocked) = intercept(config, &req, "engine0", reason) {
+                return blocked;
+            }
+        }
+        if resolution.codehash.is_empty() {
+            let reason = format!(
+                "PLIMSOLL PROXY: {} implementation {} behind {} has no code \
+                 (uninitialized or self-destructed) — the call's effect is unverifiable",
+                resolution.kind.label(), resolution.implementation, to
+            );
+            warn!("{}", reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "proxy", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: ABI registry ─────────────────────────────────────
+    // Decode the calldata into named parameters for logs and block
+    // reasons. A verified ABI also names selectors the 4byte directory
+    // didn't know, so re-run the function policy for those.
+    let implementation = proxy_impl.as_ref().map(|r| r.implementation.as_str());
+    let decoded_call = abi_registry::decode_call(config, &to, implementation, &data).await;
+    let call_context = match &decoded_call {
+        Some(call) => {
+            info!(contract = %call.contract, call = %call, "Calldata decoded against contract ABI");
+            call.to_string()
+        }
+        None => selectors::describe(&data),
+    };
+    if function.is_none() && decoded_call.is_some() {
+        if let Err(reason) = selectors::check_function_policy(config, &data) {
+            warn!("{}", reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "function_policy", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: Honeypot detection ───────────────────────────────
+    // Before buying an unknown token, prove it can be sold back.
+    if config.honeypot_detection {
+        match honeypot::decode_swap(&to, value, &data) {
+            Ok(Some(swap)) if !config.is_known_token(swap.token()) => {
+                match honeypot::round_trip(config, &from, U256::from(value), &data, &swap)
+                    .instrument(info_span!("honeypot"))
+                    .await
+                {
+                    Ok(Some(trip)) => {
+                        info!(
+                            token = %trip.token,
+                            buy_tax_pct = trip.buy_tax_pct,
+                            sell_tax_pct = trip.sell_tax_pct,
+                            "Honeypot round trip simulated"
+                        );
+                        if let Err(reason) = honeypot::check(config, &trip) {
+                            warn!("{}", reason);
+                            // The token itself can't be sold back — no agent
+                            // should buy it.
+                            if !is_shadowed(config, "honeypot") {
+                                threat_feed::block_locally(config, &trip.token, "honeypot");
+                            }
+                            if let Some(blocked) = block_unless_shadowed(config, &req.id, "honeypot", reason) {
+                                return blocked;
+                            }
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!(error = %e, "Honeypot round trip failed — continuing without it"),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => warn!(error = %e, "Undecodable router swap — skipping honeypot check"),
+        }
+    }
+
+    // ── v2.1: Rug-pull heuristics ──────────────────────────────
+    // Score the target and, for swaps, the token being bought — through
+    // to the implementation of either when it is a proxy.
+    if config.rugpull_heuristics {
+        let mut contracts = vec![(to.to_lowercase(), proxy_impl.as_ref().map(|r| r.implementation.clone()))];
+        if let Ok(Some(swap)) = honeypot::decode_swap(&to, value, &data) {
+            let token = swap.token().to_string();
+            let implementation = if config.resolve_proxy_implementations {
+                proxy::resolve(&config.upstream_rpc_url, &token).await.ok().flatten().map(|r| r.implementation)
+            } else {
+                None
+            };
+            contracts.push((token, implementation));
+        }
+        for (contract, implementation) in contracts.iter().filter(|(c, _)| !config.is_known_token(c)) {
+            match rugpull::assess(config, contract, implementation.as_deref())
+                .instrument(info_span!("rugpull"))
+                .await
+            {
+                Ok(Some(report)) => {
+                    info!(
+                        contract = %report.contract,
+                        score = report.score,
+                        findings = ?report.findings,
+                        "Rug-pull risk assessed"
+                    );
+                    if let Err(reason) = rugpull::check(config, &report) {
+                        warn!("{}", reason);
+                        if let Some(blocked) = block_unless_shadowed(config, &req.id, "rugpull", reason) {
+                            return blocked;
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, contract = %contract, "Rug-pull assessment failed"),
+            }
+        }
+    }
+
+    // ── v2.1: Oracle price deviation ───────────────────────────
+    // A swap against a skewed pool simulates cleanly; compare what it
+    // delivers with what the oracle says it's worth.
+    if config.oracle_max_deviation_pct > 0.0 {
+        if let Ok(Some(swap)) = honeypot::decode_swap(&to, value, &data) {
+            match oracle::assess(config, &from, value, &data, &swap)
+                .instrument(info_span!("oracle"))
+                .await
+            {
+                Ok(Some(trade)) => {
+                    info!(
+                        token_in = %trade.token_in,
+                        token_out = %trade.token_out,
+                        in_usd = trade.in_usd,
+                        out_usd = trade.out_usd,
+                        deviation_pct = trade.deviation_pct(),
+                        "Swap priced against oracle"
+                    );
+                    if let Err(reason) = oracle::check(config, &trade) {
+                        warn!("{}", reason);
+                        if let Some(blocked) = block_unless_shadowed(config, &req.id, "oracle", reason) {
+                            return blocked;
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(error = %e, "Oracle deviation check failed — continuing without it"),
+            }
+        }
+    }
+
+    // ── v2.1: Contract verification + deployment age ───────────
+    if config.contract_verification_check && value > 0 {
+        match verification::provenance(config, &to, implementation)
+            .instrument(info_span!("verification"))
+            .await
+        {
+            Ok(Some(p)) => {
+                info!(
+                    contract = %p.contract,
+                    verified_on = p.verified_on.map(|v| v.label()).unwrap_or("unverified"),
+                    age_secs = ?p.age_secs,
+                    "Contract provenance checked"
+                );
+                if let Err(reason) = verification::check(config, value, &p) {
+                    warn!("{}", reason);
+                    if let Some(blocked) = block_unless_shadowed(config, &req.id, "verification", reason) {
+                        return blocked;
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, "Contract provenance lookup failed"),
+        }
+    }
+
+    // ── v2.1: Nonce gap detection ───────────────────────────────
+    // A nonce far ahead of the sender's queue parks in the mempool until
+    // someone fills the gap — a transaction that fires later, not now.
+    let mut nonce = match (&set_code_tx, &blob_tx) {
+        (Some(tx), _) => Some(tx.nonce),
+        (None, Some(tx)) => Some(tx.nonce),
+        (None, None) => parse_tx_nonce(&req),
+    };
+    if let Some(n) = nonce.filter(|_| config.nonce_gap_policy != "off") {
+        let signed = req.method == "eth_sendRawTransaction";
+        match nonces::state(config, &from).await {
+            Ok(state) => match nonces::resolve(config, n, &state, signed) {
+                Ok(Some(next)) => {
+                    info!(requested = n, rewritten = next, "Nonce gap — nonce rewritten");
+                    req.params[0]["nonce"] = serde_json::json!(format!("0x{next:x}"));
+                    nonce = Some(next);
+                }
+                Ok(None) => {}
+                Err(reason) => {
+                    warn!("{}", reason);
+                    if let Some(blocked) = block_unless_shadowed(config, &req.id, "nonce_gap", reason) {
+                        return blocked;
+                    }
+                }
+            },
+            Err(e) => warn!(error = %e, "Nonce state unavailable — gap check skipped"),
         }
-        return resp;
     }
 
-    // ── ZERO-DAY 2: Pessimistic Session Key Check ──────────────
-    // Before ANY engine runs, check if the sender's session key has
-    // been revoked in the mempool. This closes the 12-second window
-    // between mempool revocation and block confirmation.
-    if is_session_revoked(&from) {
-        let reason = format!(
-            "PLIMSOLL ZERO-DAY 2: Session key {} pessimistically revoked \
-             (seen in mempool before block confirmation)",
-            &from
-        );
-        warn!("{}", reason);
-        let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &reason);
-        if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-            store.insert(tx_hash, reason);
+    // ── v2.1: Replacement (speed-up / cancel) ───────────────────
+    // Reusing the nonce of a transaction the proxy forwarded must not
+    // change what was approved.
+    let mut replaced = None;
+    if let Some(n) = nonce.filter(|_| config.replacement_guard) {
+        if let Some(original) = pending::get(&from, n, Duration::from_secs(config.pending_tx_ttl_secs)) {
+            match replacement::classify(&original, &from, &to, value, &data) {
+                Ok(kind) => {
+                    if !replacement::fee_bumped(&original, fees.max_fee, fees.priority_fee) {
+                        warn!(nonce = n, "Replacement does not raise fees — upstream will likely reject it");
+                    }
+                    info!(nonce = n, kind = ?kind, "Replacement of a pending transaction");
+                    if kind == replacement::Replacement::SpeedUp {
+                        replaced = Some(original);
+                    }
+                }
+                Err(reason) => {
+                    warn!("{}", reason);
+                    if let Some(blocked) = block_unless_shadowed(config, &req.id, "replacement", reason) {
+                        return blocked;
+                    }
+                }
+            }
         }
-        return resp;
     }
 
-    // ── ENGINE 0: Global Bloom Filter Pre-Flight ────────────────
-    // Runs BEFORE Engines 1-6. Sub-millisecond O(1) lookup against
-    // the Swarm-compiled global blacklist.
-    let (engine0_blocked, engine0_reason) = threat_feed::engine0_check(
-        threat_filter, &to, &data,
-    );
-    if engine0_blocked {
-        warn!("{}", engine0_reason);
-        // Extract IOC and uplink to Plimsoll Cloud
-        let ioc = telemetry::extract_ioc(
-            &from, &to, &data, "bloom", &engine0_reason, None, 1,
-        );
-        telemetry::uplink_ioc(&ioc, "https://cloud.plimsoll.network/v1/ioc").await;
-        // Patch 4: Return synthetic tx hash — agent stays alive
-        let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &engine0_reason);
-        if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-            store.insert(tx_hash, engine0_reason);
+    // ── v2.1: Pending nonce chain ───────────────────────────────
+    // Earlier sends of this agent that are still in the mempool run first
+    // in the simulation.
+    let mut sim_state = SimulationState::default();
+    if let Some(nonce) = nonce.filter(|_| config.sequential_nonce_simulation) {
+        sim_state.pending = pending::predecessors(config, &from, nonce).await;
+        if !sim_state.pending.is_empty() {
+            info!(nonce, predecessors = sim_state.pending.len(), "Simulating after pending transactions");
         }
-        return resp;
     }
 
     // Run pre-flight simulation
-    let sim_result = match simulator::simulate_transaction(config, &from, &to, value, &data).await {
+    let sim_start = Instant::now();
+    let sim_outcome = simulator::simulate_transaction(config, &from, &to, value, &data, delegated_code, &sim_state)
+        .instrument(info_span!("simulation"))
+        .await;
+    metrics::observe_simulation(sim_start.elapsed());
+    let mut sim_result = match sim_outcome {
         Ok(r) => r,
         Err(e) => {
             warn!("Simulation failed: {}", e);
             // Patch 4: Return synthetic tx hash — agent stays alive
-            let reason = format!("Simulation error: {e}");
-            let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &reason);
-            if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-                store.insert(tx_hash, reason);
-            }
-            return resp;
+            return block_request(req.id, "simulation_error", format!("Simulation error: {e}"));
         }
     };
 
+    if let Some(resolution) = &proxy_impl {
+        sim_result.implementation_codehash = resolution.codehash.clone();
+    }
+
+    // Blob gas is burned outside execution: charge the worst case.
+    if let Some(tx) = &blob_tx {
+        sim_result.charge(tx.max_blob_fee());
+    }
+
+    // ── v2.1: L2 data fee ───────────────────────────────────────
+    // Rollups bill L1 data posting on top of execution; deposits pay none.
+    if let Some(rollup) = l2::rollup(config).filter(|_| config.l1_fee_accounting && deposit_tx.is_none()) {
+        match l2::l1_fee(&config.upstream_rpc_url, rollup, &to, &data, raw_bytes.as_deref()).await {
+            Ok(fee) => {
+                info!(rollup = ?rollup, l1_fee = fee, "Charging L1 data fee to simulation");
+                sim_result.charge(fee);
+            }
+            Err(e) => warn!(error = %e, "L1 data fee unavailable — not charged"),
+        }
+    }
+
+    let _ = DRY_RUN.try_with(|d| d.borrow_mut().simulation = Some(simulation_json(&sim_result)));
+    response_meta::simulated(sim_result.simulated_block, sim_result.loss_pct);
+
     // Check physics constraints
-    if let Err(reason) = simulator::check_physics(config, &sim_result) {
+    if let Err(reason) = info_span!("physics").in_scope(|| simulator::check_physics(config, &sim_result)) {
+        let reason = format!("{reason} [call: {call_context}]");
         warn!("Physics violation: {}", reason);
         // Extract IOC and uplink to Plimsoll Cloud
         let ioc = telemetry::extract_ioc(
             &from, &to, &data, "simulator", &reason, Some(&reason), 1,
         );
-        telemetry::uplink_ioc(&ioc, "https://cloud.plimsoll.network/v1/ioc").await;
+        report_ioc(config, "physics", &ioc);
         // Patch 4: Return synthetic tx hash — agent stays alive
-        let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &reason);
-        if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-            store.insert(tx_hash, reason);
+        if let Some(blocked) = intercept(config, &req, "physics", reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Speed-up outcome vs. the approved original ────────
+    if let Some(original) = &replaced {
+        if let Err(reason) = replacement::check_outcome(original, &sim_result) {
+            warn!("{}", reason);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "replacement", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: Allowance increases in the simulated execution ────
+    if let Err(reason) = approval_diff::check(config, &from, &sim_result) {
+        warn!("{}", reason);
+        let ioc = telemetry::extract_ioc(
+            &from, &to, &data, "approval_diff", &reason, None, 1,
+        );
+        report_ioc(config, "approval_diff", &ioc);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "approval_diff", reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Per-token velocity limits ─────────────────────────
+    // Mirrors VelocityLimitModule: rolling outflow caps per token.
+    // Checked and counted at once; the count is given back unless the
+    // send is forwarded.
+    let outflows = velocity::outflows(&sim_result);
+    let (velocity_reserved, velocity_verdict) = velocity::reserve(config, &from, &outflows);
+    if let Err(reason) = velocity_verdict {
+        warn!("{}", reason);
+        let ioc = telemetry::extract_ioc(
+            &from, &to, &data, "velocity", &reason, None, 1,
+        );
+        report_ioc(config, "velocity", &ioc);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "velocity", reason) {
+            return blocked;
+        }
+    }
+
+    // ── v2.1: Drawdown from the portfolio's high-water mark ─────
+    if config.max_drawdown_pct > 0.0 && chains::current().is_none() {
+        if drawdown::watch(&from) {
+            drawdown::snapshot(config, &from).await;
+        }
+        // The loss in USD, against the USD watermark — not a share of the
+        // native balance.
+        let loss_usd = portfolio::loss_usd(
+            config, sim_result.balance_before, sim_result.balance_after, &sim_result.asset_deltas,
+        )
+        .await
+        .unwrap_or_default();
+        if let Err(reason) = drawdown::check(config, &from, loss_usd) {
+            warn!("{}", reason);
+            let ioc = telemetry::extract_ioc(
+                &from, &to, &data, "drawdown", &reason, None, 1,
+            );
+            report_ioc(config, "drawdown", &ioc);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "drawdown", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: Re-entrancy into the agent's vault ────────────────
+    if let Some(trace) = sim_result.call_trace.as_ref().filter(|_| config.reentrancy_detection) {
+        if let Err(reason) = reentrancy::check(config, &from, trace) {
+            warn!("{}", reason);
+            let ioc = telemetry::extract_ioc(
+                &from, &to, &data, "reentrancy", &reason, None, 1,
+            );
+            report_ioc(config, "reentrancy", &ioc);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "reentrancy", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: Vault calls outside its target whitelist ──────────
+    if let Some(trace) = sim_result.call_trace.as_ref() {
+        if let Err(reason) = vault_sync::check_targets(config, trace) {
+            warn!("{}", reason);
+            let ioc = telemetry::extract_ioc(
+                &from, &to, &data, "whitelist", &reason, None, 1,
+            );
+            report_ioc(config, "whitelist", &ioc);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "whitelist", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: Delegatecall into unpinned code ───────────────────
+    if let Some(trace) = sim_result.call_trace.as_ref().filter(|_| config.delegatecall_detection) {
+        if let Err(reason) = delegatecall::check(config, &from, trace).await {
+            warn!("{}", reason);
+            if config.block_unknown_delegatecall {
+                let ioc = telemetry::extract_ioc(
+                    &from, &to, &data, "delegatecall", &reason, None, 1,
+                );
+                report_ioc(config, "delegatecall", &ioc);
+                if let Some(blocked) = block_unless_shadowed(config, &req.id, "delegatecall", reason) {
+                    return blocked;
+                }
+            }
+        }
+    }
+
+    // ── v2.1: SELFDESTRUCT / CREATE2 redeploy ───────────────────
+    if config.metamorphic_detection {
+        let mut verdict = sim_result.call_trace.as_ref().map_or(Ok(()), metamorphic::check_trace);
+        if verdict.is_ok() && !sim_result.target_codehash.is_empty() {
+            verdict = metamorphic::check_redeploy(
+                config, &to, &sim_result.target_codehash, sim_result.simulated_block,
+            )
+            .await;
+        }
+        if let Err(reason) = verdict {
+            warn!("{}", reason);
+            let ioc = telemetry::extract_ioc(
+                &from, &to, &data, "metamorphic", &reason, None, 1,
+            );
+            report_ioc(config, "metamorphic", &ioc);
+            if let Some(blocked) = block_unless_shadowed(config, &req.id, "metamorphic", reason) {
+                return blocked;
+            }
         }
-        return resp;
     }
 
     // ── v1.0.2 Patch 2: Non-determinism check ──────────────────
@@ -1058,11 +3000,9 @@ pub async fn handle_rpc(
                        into conditional branches. Simulation outcome is unreliable."
             .to_string();
         warn!("{}", reason);
-        let (resp, tx_hash) = JsonRpcResponse::plimsoll_synthetic_send(req.id, &reason);
-        if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
-            store.insert(tx_hash, reason);
+        if let Some(blocked) = block_unless_shadowed(config, &req.id, "non_determinism", reason) {
+            return blocked;
         }
-        return resp;
     }
 
     // ── Patch 2 + GOD-TIER 3 + ZERO-DAY 2: State-Delta + Block Pinning + Codehash
@@ -1077,44 +3017,475 @@ pub async fn handle_rpc(
         sim_block = sim_result.simulated_block,
         target_codehash = %sim_result.target_codehash,
         impl_slot = %sim_result.impl_slot_value,
+        impl_codehash = %sim_result.implementation_codehash,
         "State-delta invariant captured (pinned to block + codehash + impl slot)"
     );
 
-    // Calculate and log fee
-    let fee_amount = fee::calculate_fee(value, config.fee_bps);
-    if fee_amount > 0 {
-        info!(fee_bps = config.fee_bps, fee_wei = fee_amount, "Fee calculated");
+    // Calculate fee (accrued once the tx is forwarded)
+    let value_usd = fee::value_usd(config, value, &outflows).await;
+    let protocol_fees = fee::charges(config, &from, value, &outflows, value_usd);
+    for (asset, amount) in &protocol_fees {
+        info!(fee_bps = config.fee_bps, asset = %asset, fee = %amount, "Fee calculated");
     }
 
     // ── Route through MEV-shielded path ─────────────────────────
     if config.flashbots_enabled {
         info!("Routing through Flashbots Protect");
-        // TODO: Build Flashbots bundle with fee tx + state-delta assert
+        // TODO: Build Flashbots bundle with state-delta assert
         // For now, fall through to upstream
     }
 
     // ── v1.0.3 Bounty 1: Canonical re-serialization ──────────────
     // Re-serialize from typed fields to eliminate parser divergence.
     // The upstream node sees exactly what was simulated.
-    let canonical_req = if config.reject_duplicate_json_keys {
+    // Signed raw transactions are forwarded byte-for-byte.
+    let canonical_req = if config.reject_duplicate_json_keys && req.method != "eth_sendRawTransaction" {
         canonicalize_send_request(&req, &from, &to, value, &data)
     } else {
         req
     };
 
+    // ── v2.1: Send-time codehash pin ────────────────────────────
+    // Last thing before the request leaves: the code at `to` must still
+    // be the code that was simulated.
+    if config.enforce_codehash_pin {
+        if let Err(reason) = pinning::enforce(config, &to, &sim_result)
+            .instrument(info_span!("codehash_pin"))
+            .await
+        {
+            warn!("{}", reason);
+            let ioc = telemetry::extract_ioc(
+                &from, &to, &data, "codehash_pin", &reason, None, 1,
+            );
+            report_ioc(config, "codehash_pin", &ioc);
+            if let Some(blocked) = block_unless_shadowed(config, &canonical_req.id, "codehash_pin", reason) {
+                return blocked;
+            }
+        }
+    }
+
+    // ── v2.1: Dry run — every engine passed; nothing is forwarded ──
+    if is_dry_run() {
+        return JsonRpcResponse::success(canonical_req.id, serde_json::Value::Null);
+    }
+
+    if let Ok(Some((target, score))) = DECISION_TARGET.try_with(|t| t.borrow().clone()) {
+        info!(target = %target, reputation = score, "Decision: forwarded");
+    }
+    counterparties::record(&from, &to, &data);
+
     // Forward to upstream RPC
-    proxy_to_upstream(config, &canonical_req).await
+    // Blob transactions go to an endpoint that accepts their sidecars.
+    let upstream = match &blob_tx {
+        Some(_) if !config.blob_upstream_rpc_url.is_empty() => &config.blob_upstream_rpc_url,
+        _ => &config.upstream_rpc_url,
+    };
+    let response = proxy_to(upstream, &canonical_req).await;
+    if config.tx_rescue && canonical_req.method == "eth_sendTransaction" && chains::current().is_none() {
+        if let (Some(hash), Some(tx)) = (
+            response.result.as_ref().and_then(|r| r.as_str()),
+            canonical_req.params.get(0),
+        ) {
+            rescue::track(&from, nonce, tx.clone(), hash);
+        }
+    }
+    // A speed-up moves what its original already counted.
+    if response.error.is_none() && replaced.is_none() {
+        velocity_reserved.commit();
+        let tx_hash = response.result.as_ref().and_then(|r| r.as_str()).unwrap_or_default();
+        for (asset, amount) in &protocol_fees {
+            fee::accrue(chains::current(), &from, asset, *amount);
+            let chain_id = chains::current().unwrap_or_else(|| config.default_chain_id());
+            fee_ledger::record(config, fee_ledger::CHARGED, chain_id, &from, asset, *amount, tx_hash).await;
+        }
+    }
+    if let (Some(nonce), None) = (nonce, &response.error) {
+        pending::record(&from, PendingTx {
+            nonce,
+            to,
+            value,
+            data,
+            max_fee: fees.max_fee,
+            priority_fee: fees.priority_fee,
+            loss_pct: sim_result.loss_pct,
+        });
+    }
+    response
+}
+
+/// v2.1: If `data` is a (possibly nested) multicall batch, run Engine 0
+/// and the hidden-approval check against every leaf call.
+fn screen_batch(
+    config: &Config,
+    threat_filter: &SharedThreatFilter,
+    to: &str,
+    data: &[u8],
+) -> Result<(), (&'static str, String)> {
+    let calls = match multicall::decode(to, data) {
+        Ok(Some(calls)) => calls,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            return Err(("multicall", format!("PLIMSOLL MULTICALL: undecodable batch: {e}")));
+        }
+    };
+    info!(calls = calls.len(), target = to, "Multicall batch unwrapped");
+    multicall::check_calls(config, &calls).map_err(|reason| ("multicall", reason))?;
+    for call in &calls {
+        selectors::check_function_policy(config, &call.data)
+            .map_err(|reason| ("function_policy", format!("PLIMSOLL MULTICALL: {reason}")))?;
+        approvals::check(config, &call.to, &call.data)
+            .map_err(|reason| ("approval", format!("PLIMSOLL MULTICALL: {reason}")))?;
+        let (blocked, reason) = threat_feed::engine0_check(config, threat_filter, &call.to, &call.data);
+        if blocked {
+            return Err((
+                "engine0",
+                format!(
+                    "PLIMSOLL MULTICALL: inner call {} on {} — {}",
+                    selectors::describe(&call.data), call.to, reason
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `Err((engine, reason))` of the engine that blocks an inner call.
+type InnerVerdict = Result<(), (String, String)>;
+
+/// v2.1: Vet a call unwrapped from a wrapper (meta-transaction, Safe
+/// operation) exactly like a direct send: the inner call runs through the
+/// whole send pipeline as an `eth_sendTransaction` from `call.from`,
+/// evaluated like a dry run — never forwarded, nothing recorded.
+///
+/// Boxed: the send pipeline vets Safe operations through this again.
+fn vet_inner_call<'a>(
+    config: &'a Config,
+    threat_filter: &'a SharedThreatFilter,
+    call: &'a InnerCall,
+) -> Pin<Box<dyn Future<Output = InnerVerdict> + Send + 'a>> {
+    Box::pin(async move {
+        let req = JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            method: "eth_sendTransaction".into(),
+            params: serde_json::json!([{
+                "from": call.from,
+                "to": call.to,
+                "value": format!("0x{:x}", call.value),
+                "data": format!("0x{}", hex::encode(&call.data)),
+            }]),
+            id: serde_json::Value::Null,
+            notification: false,
+        };
+        let (response, verdict) = DRY_RUN
+            .scope(RefCell::new(DryRun::default()), async {
+                SHADOW_VERDICT
+                    .scope(RefCell::new(None), async {
+                        let response = enforce_send(config, threat_filter, req).await;
+                        (response, SHADOW_VERDICT.with(|v| v.borrow_mut().take()))
+                    })
+                    .await
+            })
+            .await;
+        match (verdict, response.error) {
+            (Some(verdict), _) => Err(verdict),
+            (None, Some(error)) => Err(("simulation_error".into(), error.message)),
+            (None, None) => Ok(()),
+        }
+    })
+}
+
+/// Physics plus the approval-diff and call-trace engines (re-entrancy,
+/// delegatecall, SELFDESTRUCT) over a finished simulation.
+async fn check_simulation(
+    config: &Config,
+    from: &str,
+    sim_result: &SimulationResult,
+) -> Result<(), (&'static str, String)> {
+    simulator::check_physics(config, sim_result).map_err(|reason| ("physics", reason))?;
+    approval_diff::check(config, from, sim_result).map_err(|reason| ("approval_diff", reason))?;
+    if let Some(trace) = sim_result.call_trace.as_ref().filter(|_| config.reentrancy_detection) {
+        reentrancy::check(config, from, trace).map_err(|reason| ("reentrancy", reason))?;
+    }
+    if let Some(trace) = sim_result.call_trace.as_ref() {
+        vault_sync::check_targets(config, trace).map_err(|reason| ("whitelist", reason))?;
+    }
+    if let Some(trace) = sim_result.call_trace.as_ref().filter(|_| config.delegatecall_detection) {
+        if let Err(reason) = delegatecall::check(config, from, trace).await {
+            warn!("{}", reason);
+            if config.block_unknown_delegatecall {
+                return Err(("delegatecall", reason));
+            }
+        }
+    }
+    if let Some(trace) = sim_result.call_trace.as_ref().filter(|_| config.metamorphic_detection) {
+        metamorphic::check_trace(trace).map_err(|reason| ("metamorphic", reason))?;
+    }
+    Ok(())
+}
+
+/// Patch 4: The receipt of a blocked tx: pending (null) for
+/// `synthetic_receipt_delay_secs`, then reverted — with intrinsic gas, at
+/// the upstream block first seen, when `synthetic_receipt_realistic`.
+async fn synthetic_receipt(
+    config: &Config,
+    id: serde_json::Value,
+    hash: &str,
+    mut blocked: BlockedTx,
+) -> JsonRpcResponse {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if now < blocked.blocked_at.saturating_add(config.synthetic_receipt_delay_secs) {
+        return JsonRpcResponse::success(id, serde_json::Value::Null);
+    }
+    if !config.synthetic_receipt_realistic {
+        blocked.gas_used = 0;
+        blocked.block_number = None;
+    } else if blocked.block_number.is_none() {
+        match simulator::fetch_block_number(&config.upstream_rpc_url).await {
+            Ok(block) => {
+                blocked.block_number = Some(block);
+                if let Ok(mut store) = BLOCKED_TX_STORE.lock() {
+                    if let Some(stored) = store.get_mut(&chain_key(hash)) {
+                        blocked.block_number = Some(*stored.block_number.get_or_insert(block));
+                    }
+                }
+            }
+            Err(e) => warn!(error = %e, "Synthetic receipt: block number unavailable"),
+        }
+    }
+    info!(tx_hash = hash, "Returning synthetic receipt for blocked tx");
+    JsonRpcResponse::plimsoll_synthetic_receipt(id, hash, &blocked)
+}
+
+/// v2.1: `plimsoll_simulate` — simulate a transaction, optionally against
+/// a `stateOverride`, and report what the policy engines would decide.
+/// Params: `[tx]`, `[tx, stateOverride]` or `[tx, blockTag, stateOverride]`
+/// (the block tag is ignored: simulations run against latest).
+async fn simulate_rpc(config: &Config, req: JsonRpcRequest) -> JsonRpcResponse {
+    let (from, to, value, data) = match parse_tx_params(&req) {
+        Ok(params) => params,
+        Err(e) => return JsonRpcResponse::error(req.id, -32602, format!("Invalid params: {e}")),
+    };
+    let override_param = req.params.as_array().and_then(|params| {
+        params.iter().skip(1).find(|p| p.is_object()).cloned()
+    });
+    let overrides: Option<StateOverride> = match override_param.map(serde_json::from_value) {
+        Some(Ok(overrides)) => Some(overrides),
+        Some(Err(e)) => {
+            return JsonRpcResponse::error(req.id, -32602, format!("Invalid params: stateOverride: {e}"));
+        }
+        None => None,
+    };
+
+    let sim_start = Instant::now();
+    let sim_outcome = simulator::simulate_transaction(
+        config, &from, &to, value, &data, None,
+        &SimulationState { overrides, ..SimulationState::default() },
+    )
+    .instrument(info_span!("simulation"))
+    .await;
+    metrics::observe_simulation(sim_start.elapsed());
+    let sim_result = match sim_outcome {
+        Ok(r) => r,
+        Err(e) => return JsonRpcResponse::error(req.id, -32000, format!("Simulation error: {e}")),
+    };
+    let verdict = match check_simulation(config, &from, &sim_result).await {
+        Ok(()) => serde_json::json!({ "allowed": true }),
+        Err((engine, reason)) => serde_json::json!({
+            "allowed": false,
+            "code": block_codes::of(engine).code,
+            "engine": engine,
+            "reason": reason,
+        }),
+    };
+    let mut result = simulation_json(&sim_result);
+    result["verdict"] = verdict;
+    JsonRpcResponse::success(req.id, result)
+}
+
+/// A simulation as `plimsoll_simulate` and `plimsoll_dryRun` report it.
+fn simulation_json(sim_result: &SimulationResult) -> serde_json::Value {
+    serde_json::json!({
+        "success": sim_result.success,
+        "gasUsed": sim_result.gas_used,
+        "balanceBefore": sim_result.balance_before.to_string(),
+        "balanceAfter": sim_result.balance_after.to_string(),
+        "lossPct": sim_result.loss_pct,
+        "approvalChanges": sim_result.approval_changes,
+        "error": sim_result.error,
+        "assetDeltas": sim_result.asset_deltas.iter().map(|d| serde_json::json!({
+            "standard": d.standard.label(),
+            "token": d.token,
+            "tokenId": d.token_id.map(|id| id.to_string()),
+            "sent": d.sent.to_string(),
+            "received": d.received.to_string(),
+        })).collect::<Vec<_>>(),
+        "approvalDeltas": sim_result.approval_deltas.iter().map(|a| serde_json::json!({
+            "owner": a.owner,
+            "token": a.token,
+            "spender": a.spender,
+            "amount": a.amount.map(|v| v.to_string()),
+            "approved": a.approved,
+        })).collect::<Vec<_>>(),
+        "nonDeterministic": sim_result.non_deterministic,
+        "simulatedBlock": sim_result.simulated_block,
+    })
+}
+
+/// v2.1: `plimsoll_dryRun` — run a send or signature request through every
+/// engine without forwarding it, and report every engine's verdict (the
+/// first as `verdict`). An engine whose block ends the pipeline — a
+/// global check like the Paymaster sever — is the last one. Params:
+/// `[{ "method": "eth_sendTransaction", "params": [tx] }]` (any send or
+/// sign method).
+///
+/// Boxed: `enforce_rpc` dispatches the dry run, which evaluates the inner
+/// request through `enforce_rpc` again.
+fn dry_run_rpc<'a>(
+    config: &'a Config,
+    threat_filter: &'a SharedThreatFilter,
+    req: JsonRpcRequest,
+) -> Pin<Box<dyn Future<Output = JsonRpcResponse> + Send + 'a>> {
+    Box::pin(async move {
+        let call = req.params.get(0);
+        let method = call.and_then(|c| c.get("method")).and_then(|m| m.as_str()).unwrap_or("");
+        if !SEND_METHODS.contains(&method) && !SIGN_METHODS.contains(&method) {
+            return JsonRpcResponse::error(
+                req.id,
+                -32602,
+                format!("Invalid params: {DRY_RUN_METHOD} expects a {{method, params}} send or sign request"),
+            );
+        }
+        let inner = JsonRpcRequest {
+            jsonrpc: req.jsonrpc.clone(),
+            method: method.to_string(),
+            params: call.and_then(|c| c.get("params")).cloned().unwrap_or_else(|| serde_json::json!([])),
+            id: req.id.clone(),
+            notification: false,
+        };
+
+        let dry_run = DryRun { all_verdicts: true, ..DryRun::default() };
+        let (response, verdicts, simulation) = DRY_RUN
+            .scope(RefCell::new(dry_run), async {
+                SHADOW_VERDICT
+                    .scope(RefCell::new(None), async {
+                        let response = enforce_rpc(config, threat_filter, inner).await;
+                        let last = SHADOW_VERDICT.with(|v| v.borrow_mut().take());
+                        let dry_run = DRY_RUN.with(|d| d.take());
+                        let mut verdicts = dry_run.verdicts;
+                        verdicts.extend(last);
+                        (response, verdicts, dry_run.simulation)
+                    })
+                    .await
+            })
+            .await;
+        if verdicts.is_empty() && response.error.is_some() {
+            return JsonRpcResponse { id: req.id, ..response };
+        }
+
+        let would_block = !verdicts.is_empty();
+        let reputation = DECISION_TARGET.try_with(|t| t.borrow().clone()).ok().flatten();
+        let loss_pct = simulation.as_ref().and_then(|s| s["lossPct"].as_f64());
+        let score = risk_score(would_block, reputation.as_ref().map(|(_, score)| *score), loss_pct);
+        info!(method, would_block, verdicts = verdicts.len(), risk_score = score, "Dry run evaluated");
+        let verdicts: Vec<serde_json::Value> =
+            verdicts.iter().map(|(engine, reason)| block_codes::data(engine, reason)).collect();
+        JsonRpcResponse::success(
+            req.id,
+            serde_json::json!({
+                "wouldBlock": would_block,
+                "verdict": verdicts.first().cloned().unwrap_or(serde_json::Value::Null),
+                "verdicts": verdicts,
+                "riskScore": score,
+                "reputation": reputation.map(|(target, score)| serde_json::json!({ "target": target, "score": score })),
+                "simulation": simulation,
+            }),
+        )
+    })
+}
+
+/// `plimsoll_explainBlock` result: the block's code, category, engine and
+/// reason, what was sent, and how to dispute it.
+fn explain_block(tx_hash: &str, blocked: &BlockedTx) -> serde_json::Value {
+    let mut explanation = block_codes::data(&blocked.engine, &blocked.reason);
+    explanation["txHash"] = serde_json::json!(tx_hash);
+    explanation["from"] = serde_json::json!(blocked.from);
+    explanation["to"] = serde_json::json!(blocked.to);
+    explanation["blockedAt"] = serde_json::json!(blocked.blocked_at);
+    explanation["dispute"] = serde_json::json!(REPORT_FALSE_POSITIVE_METHOD);
+    explanation
+}
+
+/// `plimsoll_getVaultLimits` result. With an `agent`, each velocity limit
+/// also reports what the agent has spent in its current window.
+fn vault_limits(config: &Config, agent: Option<&str>) -> serde_json::Value {
+    let list = |csv: &str| -> Vec<String> {
+        csv.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_lowercase).collect()
+    };
+    let velocity: serde_json::Map<String, serde_json::Value> = config
+        .token_velocity_limits
+        .iter()
+        .map(|(token, limit)| {
+            let mut entry = serde_json::json!({
+                "maxPerWindow": limit.max_per_window,
+                "maxSingleTx": limit.max_single_tx,
+                "windowSecs": limit.window_secs,
+            });
+            if let Some(agent) = agent {
+                let spent = velocity::spent(agent, &token.to_lowercase(), limit.window_secs);
+                entry["spent"] = serde_json::json!(spent.to_string());
+            }
+            (token.clone(), entry)
+        })
+        .collect();
+    serde_json::json!({
+        "velocity": velocity,
+        "maxDrawdownPct": config.max_drawdown_pct,
+        "maxLossPct": config.max_loss_pct,
+        "targetAllowlist": list(&config.vault_target_allowlist),
+        "vaults": list(&config.vault_addresses),
+        "synced": config.vault_policy_sync,
+    })
+}
+
+/// Risk of an action, 0–100: 100 when an engine would block it, else the
+/// worse of the target's distrust (100 − reputation) and the simulated
+/// loss percentage.
+fn risk_score(would_block: bool, reputation: Option<u8>, loss_pct: Option<f64>) -> u8 {
+    if would_block {
+        return 100;
+    }
+    let distrust = reputation.map_or(0, |score| 100 - score.min(100));
+    let loss = loss_pct.map_or(0.0, |pct| pct.clamp(0.0, 100.0)).round() as u8;
+    distrust.max(loss)
 }
 
 /// Forward a request to the upstream Ethereum RPC.
 async fn proxy_to_upstream(config: &Config, req: &JsonRpcRequest) -> JsonRpcResponse {
+    proxy_to(&config.upstream_rpc_url, req).await
+}
+
+/// Forward a request to the RPC endpoint at `url`.
+async fn proxy_to(url: &str, req: &JsonRpcRequest) -> JsonRpcResponse {
+    if is_dry_run() {
+        return JsonRpcResponse::success(req.id.clone(), serde_json::Value::Null);
+    }
+    // Forwarding the approved request (possibly with its nonce filled or
+    // canonicalized) uses its approvals up.
+    let _ = APPROVALS.try_with(|approvals| {
+        for (fingerprint, engine) in approvals.take() {
+            protection::consume(&fingerprint, &engine);
+        }
+    });
     let client = reqwest::Client::new();
-    match client
-        .post(&config.upstream_rpc_url)
+    let upstream_start = Instant::now();
+    let upstream_result = client
+        .post(url)
         .json(req)
         .send()
-        .await
-    {
+        .instrument(info_span!("upstream", method = %req.method))
+        .await;
+    metrics::observe_upstream(upstream_start.elapsed());
+    match upstream_result {
         Ok(resp) => {
             match resp.json::<serde_json::Value>().await {
                 Ok(body) => JsonRpcResponse {
@@ -1122,6 +3493,7 @@ async fn proxy_to_upstream(config: &Config, req: &JsonRpcRequest) -> JsonRpcResp
                     result: body.get("result").cloned(),
                     error: None,
                     id: req.id.clone(),
+                    plimsoll: None,
                 },
                 Err(e) => JsonRpcResponse::error(
                     req.id.clone(),
@@ -1223,11 +3595,12 @@ fn canonicalize_send_request(
 
     // v1.0.4 Kill-Shot 2: Preserve gas fields for PVG/TVAR accounting.
     // Without this, canonicalization would drop preVerificationGas, maxFeePerGas,
-    // etc., causing the upstream node to use default gas params.
+    // etc., causing the upstream node to use default gas params. The nonce
+    // is kept so the node sends exactly the nonce that was vetted.
     if let Some(tx_obj) = req.params.as_array().and_then(|a| a.first()) {
         for gas_field in &[
-            "gas", "gasLimit", "maxFeePerGas", "maxPriorityFeePerGas",
-            "preVerificationGas",
+            "gas", "gasLimit", "gasPrice", "maxFeePerGas", "maxPriorityFeePerGas",
+            "preVerificationGas", "nonce",
         ] {
             if let Some(val) = tx_obj.get(gas_field) {
                 canonical_tx[gas_field] = val.clone();
@@ -1240,9 +3613,16 @@ fn canonicalize_send_request(
         method: req.method.clone(),
         params: serde_json::json!([canonical_tx]),
         id: req.id.clone(),
+        notification: req.notification,
     }
 }
 
+/// The explicit `nonce` of an `eth_sendTransaction` request, if any.
+fn parse_tx_nonce(req: &JsonRpcRequest) -> Option<u64> {
+    let nonce = req.params.get(0)?.get("nonce")?.as_str()?;
+    u64::from_str_radix(nonce.trim_start_matches("0x"), 16).ok()
+}
+
 /// Parse transaction parameters from a JSON-RPC request.
 fn parse_tx_params(req: &JsonRpcRequest) -> Result<(String, String, u128, Vec<u8>)> {
     let params = req.params.as_array()
@@ -1304,6 +3684,20 @@ mod tests {
         assert_eq!(result.unwrap(), "to");
     }
 
+    #[test]
+    fn test_parse_tx_nonce() {
+        let req = |params: serde_json::Value| JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            method: "eth_sendTransaction".into(),
+            params,
+            id: serde_json::json!(1),
+            notification: false,
+        };
+        assert_eq!(parse_tx_nonce(&req(serde_json::json!([{ "to": "0xdef", "nonce": "0x1f" }]))), Some(31));
+        assert_eq!(parse_tx_nonce(&req(serde_json::json!([{ "to": "0xdef" }]))), None);
+        assert_eq!(parse_tx_nonce(&req(serde_json::json!(["0x02f8"]))), None);
+    }
+
     #[test]
     fn test_canonicalize_send_request() {
         let req = JsonRpcRequest {
@@ -1316,6 +3710,7 @@ mod tests {
                 "value": "0x100"
             }]),
             id: serde_json::json!(1),
+            notification: false,
         };
         let canonical = canonicalize_send_request(
             &req, "0xabc", "0xhacker", 256, &[],
@@ -1529,9 +3924,11 @@ mod tests {
                 "to": "0xdef",
                 "value": "0x100",
                 "maxFeePerGas": "0x4A817C800",
-                "preVerificationGas": "0x7A120"
+                "preVerificationGas": "0x7A120",
+                "nonce": "0x2a"
             }]),
             id: serde_json::json!(1),
+            notification: false,
         };
         let canonical = canonicalize_send_request(
             &req, "0xabc", "0xdef", 256, &[],
@@ -1539,5 +3936,247 @@ mod tests {
         let tx = canonical.params.as_array().unwrap()[0].clone();
         assert_eq!(tx["maxFeePerGas"].as_str().unwrap(), "0x4A817C800");
         assert_eq!(tx["preVerificationGas"].as_str().unwrap(), "0x7A120");
+        assert_eq!(tx["nonce"].as_str().unwrap(), "0x2a");
+    }
+
+    #[test]
+    fn test_analyze_typed_data_sees_through_wrapper() {
+        let typed_data = serde_json::json!({
+            "types": {
+                "EIP712Domain": [{ "name": "name", "type": "string" }],
+                "SignIn": [
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "permitted", "type": "TokenPermissions" }
+                ],
+                "TokenPermissions": [
+                    { "name": "token", "type": "address" },
+                    { "name": "amount", "type": "uint256" }
+                ]
+            },
+            "primaryType": "SignIn",
+            "domain": { "name": "Login" },
+            "message": {}
+        });
+        let (dangerous, action, _) = permit_decoder::analyze_typed_data(&typed_data);
+        assert!(dangerous);
+        assert!(action.contains("TokenPermissions"));
+    }
+
+    #[test]
+    fn test_analyze_typed_data_erc3009_reports_recipient() {
+        let typed_data = serde_json::json!({
+            "types": {
+                "EIP712Domain": [{ "name": "verifyingContract", "type": "address" }],
+                "TransferWithAuthorization": [
+                    { "name": "from", "type": "address" },
+                    { "name": "to", "type": "address" },
+                    { "name": "value", "type": "uint256" },
+                    { "name": "validAfter", "type": "uint256" },
+                    { "name": "validBefore", "type": "uint256" },
+                    { "name": "nonce", "type": "bytes32" }
+                ]
+            },
+            "primaryType": "TransferWithAuthorization",
+            "domain": { "verifyingContract": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48" },
+            "message": {
+                "from": "0x1111111111111111111111111111111111111111",
+                "to": "0x6666666666666666666666666666666666666666",
+                "value": "1000000",
+                "validAfter": "0",
+                "validBefore": "1700000000",
+                "nonce": "0x00"
+            }
+        });
+        let (dangerous, action, _) = permit_decoder::analyze_typed_data(&typed_data);
+        assert!(dangerous);
+        assert!(action.contains("0x6666666666666666666666666666666666666666"));
+        assert!(action.contains("1000000"));
+    }
+
+    #[test]
+    fn test_raw_send_call_decoded() {
+        use ethers::signers::{LocalWallet, Signer};
+        use ethers::types::Eip1559TransactionRequest;
+
+        let wallet: LocalWallet = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap();
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to("0x1111111111111111111111111111111111111111".parse::<ethers::types::Address>().unwrap())
+            .value(16)
+            .data(vec![0xa9, 0x05, 0x9c, 0xbb])
+            .nonce(0)
+            .gas(50_000)
+            .chain_id(1)
+            .into();
+        let signature = wallet.sign_transaction_sync(&tx).unwrap();
+        let raw = format!("0x{}", hex::encode(tx.rlp_signed(&signature)));
+        let req = JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            method: "eth_sendRawTransaction".into(),
+            params: serde_json::json!([raw]),
+            id: serde_json::json!(1),
+            notification: false,
+        };
+        let (from, to, value, data) = sent_call(&req).unwrap();
+        assert_eq!(from, format!("{:#x}", wallet.address()));
+        assert_eq!(to, "0x1111111111111111111111111111111111111111");
+        assert_eq!(value, 16);
+        assert_eq!(data, vec![0xa9, 0x05, 0x9c, 0xbb]);
+
+        let garbage = JsonRpcRequest { params: serde_json::json!(["0x1234"]), ..req.clone() };
+        assert!(sent_call(&garbage).is_none());
+        let read = JsonRpcRequest { method: "eth_call".into(), ..req };
+        assert!(sent_call(&read).is_none());
+    }
+
+    #[test]
+    fn test_blocked_send_call_remembered() {
+        let send = JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            method: "eth_sendTransaction".into(),
+            params: serde_json::json!([{
+                "from": "0xA9E0000000000000000000000000000000000105",
+                "to": "0x1111111111111111111111111111111111111111",
+                "value": "0x10",
+                "data": "0xa9059cbb",
+            }]),
+            id: serde_json::json!(1),
+            notification: false,
+        };
+        let call = parse_tx_params(&send).unwrap();
+        let response = block_request(send.id.clone(), "physics", "PLIMSOLL PHYSICS: test 2105".into());
+        remember_blocked_send(Some(call.clone()), &response);
+
+        let replay = JsonRpcRequest {
+            method: "eth_call".into(),
+            params: serde_json::json!([{
+                "from": "0xa9e0000000000000000000000000000000000105",
+                "to": "0x1111111111111111111111111111111111111111",
+                "value": "0x10",
+                "data": "0xA9059CBB",
+                "gas": "0x5208",
+            }, "latest"]),
+            ..send
+        };
+        assert_eq!(call_fingerprint(&parse_tx_params(&replay).unwrap()), call_fingerprint(&call));
+        assert_eq!(blocked_call_reason(&replay).as_deref(), Some("PLIMSOLL PHYSICS: test 2105"));
+        // A read at a past block is not a replay.
+        let mut historical = replay.clone();
+        historical.params[1] = serde_json::json!("0x10");
+        assert_eq!(blocked_call_reason(&historical), None);
+        let hash = BLOCKED_CALLS.lock().unwrap().get(&call_fingerprint(&call)).cloned().unwrap().0;
+        assert_eq!(blocked_reason(&hash).as_deref(), Some("PLIMSOLL PHYSICS: test 2105"));
+        let blocked = BLOCKED_TX_STORE.lock().unwrap().get(&hash).cloned().unwrap();
+        assert_eq!(blocked.from, "0xA9E0000000000000000000000000000000000105");
+        assert_eq!(blocked.to, "0x1111111111111111111111111111111111111111");
+        assert_eq!(blocked.gas_used, 21_000 + 4 * 16);
+    }
+
+    #[test]
+    fn test_dry_run_risk_score() {
+        assert_eq!(risk_score(true, Some(100), None), 100);
+        assert_eq!(risk_score(false, None, None), 0);
+        assert_eq!(risk_score(false, Some(80), Some(3.4)), 20);
+        assert_eq!(risk_score(false, Some(95), Some(42.6)), 43);
+        assert_eq!(risk_score(false, None, Some(250.0)), 100);
+    }
+
+    #[test]
+    fn test_dry_run_collects_every_verdict() {
+        let config = Config::default();
+        let id = serde_json::json!(7);
+        let evaluate = |all_verdicts: bool| {
+            let dry_run = RefCell::new(DryRun { all_verdicts, ..DryRun::default() });
+            DRY_RUN.sync_scope(dry_run, || {
+                SHADOW_VERDICT.sync_scope(RefCell::new(None), || {
+                    let first = block_unless_shadowed(&config, &id, "velocity", "PLIMSOLL VELOCITY: test 2107".into());
+                    let second = first.is_none().then(|| {
+                        block_unless_shadowed(&config, &id, "physics", "PLIMSOLL PHYSICS: test 2107".into())
+                    });
+                    let last = SHADOW_VERDICT.with(|v| v.borrow_mut().take());
+                    (first.is_some(), second.flatten().is_some(), DRY_RUN.with(|d| d.take().verdicts), last)
+                })
+            })
+        };
+
+        // plimsoll_dryRun: every engine's verdict, the pipeline carries on.
+        let (stopped, _, verdicts, last) = evaluate(true);
+        assert!(!stopped);
+        let engines: Vec<&str> = verdicts.iter().map(|(engine, _)| engine.as_str()).collect();
+        assert_eq!(engines, ["velocity", "physics"]);
+        assert_eq!(last, None);
+
+        // Inner calls: the first verdict ends the evaluation.
+        let (stopped, _, verdicts, last) = evaluate(false);
+        assert!(stopped);
+        assert!(verdicts.is_empty());
+        assert_eq!(last.map(|(engine, _)| engine).as_deref(), Some("velocity"));
+    }
+
+    #[test]
+    fn test_shadowed_engine_does_not_block() {
+        let config = Config { shadow_engines: "velocity".into(), ..Config::default() };
+        let id = serde_json::json!(7);
+        let reason = "PLIMSOLL VELOCITY: test 2109".to_string();
+        assert!(block_unless_shadowed(&config, &id, "velocity", reason.clone()).is_none());
+        let recorded = shadow::list(Some("velocity"));
+        assert!(recorded.iter().any(|v| v.reason == reason && v.scope == shadow::Scope::Engine));
+        assert!(is_shadowed(&config, "velocity"));
+
+        let blocked = block_unless_shadowed(&config, &id, "physics", "PLIMSOLL PHYSICS: test 2109".into());
+        assert!(blocked.is_some_and(|r| r.id == id));
+        assert!(!is_shadowed(&config, "physics"));
+    }
+
+    #[tokio::test]
+    async fn test_inner_call_runs_the_send_pipeline() {
+        // Session key revocation is a send-path check the old inner-call
+        // subset never ran.
+        let signer = "0x1111111111111111111111111111111111112034";
+        let call = InnerCall {
+            from: signer.into(),
+            to: "0x2222222222222222222222222222222222222222".into(),
+            value: 0,
+            data: vec![],
+        };
+        revoke_session_key(signer);
+        let filter = threat_feed::new_shared_filter();
+        let (engine, reason) = vet_inner_call(&Config::default(), &filter, &call).await.unwrap_err();
+        unrevoke_session_key(signer);
+        assert_eq!(engine, "session_revoked");
+        assert!(reason.contains(signer), "{reason}");
+    }
+
+    #[test]
+    fn test_explain_block_and_vault_limits() {
+        let blocked = BlockedTx {
+            engine: "velocity".into(),
+            reason: "PLIMSOLL VELOCITY: test 2114".into(),
+            from: "0xA9E0000000000000000000000000000000002114".into(),
+            ..BlockedTx::default()
+        };
+        let explanation = explain_block("0xplimsoll01", &blocked);
+        assert_eq!(explanation["code"], 4902);
+        assert_eq!(explanation["txHash"], "0xplimsoll01");
+        assert_eq!(explanation["dispute"], REPORT_FALSE_POSITIVE_METHOD);
+
+        let mut config = Config {
+            max_drawdown_pct: 15.0,
+            vault_target_allowlist: "0xAAAA000000000000000000000000000000000001, ".into(),
+            ..Config::default()
+        };
+        config.token_velocity_limits.insert(
+            "native".into(),
+            crate::config::VelocityLimit {
+                max_per_window: "5000".into(),
+                max_single_tx: String::new(),
+                window_secs: 3600,
+            },
+        );
+        let limits = vault_limits(&config, Some("0xA9E0000000000000000000000000000000002114"));
+        assert_eq!(limits["maxDrawdownPct"], 15.0);
+        assert_eq!(limits["targetAllowlist"], serde_json::json!(["0xaaaa000000000000000000000000000000000001"]));
+        assert_eq!(limits["velocity"]["native"]["maxPerWindow"], "5000");
+        assert_eq!(limits["velocity"]["native"]["spent"], "0");
+        assert!(vault_limits(&config, None)["velocity"]["native"].get("spent").is_none());
     }
 }
@@ -0,0 +1,324 @@
+//! Rug-pull heuristics for unknown contracts.
+//!
+//! The cloud threat feed only knows a scam once someone has been scammed.
+//! A fresh token can be scored before the first victim from what its code
+//! is *able* to do:
+//!
+//! | Heuristic            | Signal                                            | Weight |
+//! |----------------------|---------------------------------------------------|--------|
+//! | Mint                 | `mint(...)` — supply can be inflated and dumped   | 30     |
+//! | Blacklist            | `blacklist` / `setBots` — holders can be frozen   | 25     |
+//! | Owner fees           | `setFee` / `setTaxFee` — tax raised to 100%       | 20     |
+//! | Unlocked liquidity   | < 50% of the Uniswap V2 LP burned                 | 20     |
+//! | Pausable             | `pause()` — transfers can be stopped              | 15     |
+//! | Trading controls     | `setMaxTxAmount` / `enableTrading` — sells capped | 10     |
+//!
+//! Selectors come from the runtime bytecode's dispatcher (`PUSH4`
+//! immediates) plus the verified ABI when the ABI registry has one —
+//! of the implementation too when the contract is an upgradeable proxy,
+//! whose own code only forwards. Off by default: the liquidity check's
+//! factory and quote token default to mainnet's and must be set per
+//! chain.
+//! Owner-gated powers count half once `owner()` is the zero address.
+//! The 0–100 score is logged for every assessed contract and blocks at
+//! `rugpull_block_score`.
+
+use crate::abi;
+use crate::abi_registry;
+use crate::config::Config;
+use crate::simulator;
+use alloy_primitives::U256;
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// `owner()`
+const OWNER: [u8; 4] = [0x8d, 0xa5, 0xcb, 0x5b];
+/// `transfer(address,uint256)` — marks the contract as a token.
+const TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// `getPair(address,address)`
+const GET_PAIR: [u8; 4] = [0xe6, 0xa4, 0x39, 0x05];
+/// `totalSupply()`
+const TOTAL_SUPPLY: [u8; 4] = [0x18, 0x16, 0x0d, 0xdd];
+/// `balanceOf(address)`
+const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// Where burned LP tokens go.
+const BURN_ADDRESSES: &[&str] = &[
+    "0x0000000000000000000000000000000000000000",
+    "0x000000000000000000000000000000000000dead",
+];
+
+/// Minimum share of LP that must be burned to count as locked.
+const MIN_BURNED_LP_PCT: f64 = 50.0;
+
+/// A rug-pull capability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Heuristic {
+    Mint,
+    Blacklist,
+    OwnerFees,
+    UnlockedLiquidity,
+    Pausable,
+    TradingControls,
+}
+
+impl Heuristic {
+    pub fn weight(self) -> u32 {
+        match self {
+            Self::Mint => 30,
+            Self::Blacklist => 25,
+            Self::OwnerFees | Self::UnlockedLiquidity => 20,
+            Self::Pausable => 15,
+            Self::TradingControls => 10,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Mint => "mintable supply",
+            Self::Blacklist => "holder blacklist",
+            Self::OwnerFees => "owner-settable fees",
+            Self::UnlockedLiquidity => "unlocked liquidity",
+            Self::Pausable => "pausable transfers",
+            Self::TradingControls => "owner trading controls",
+        }
+    }
+
+    /// Powers that go away when ownership is renounced.
+    fn owner_gated(self) -> bool {
+        !matches!(self, Self::UnlockedLiquidity)
+    }
+}
+
+/// Selectors that grant each capability.
+const RISKY_SELECTORS: &[([u8; 4], Heuristic, &str)] = &[
+    ([0x40, 0xc1, 0x0f, 0x19], Heuristic::Mint, "mint(address,uint256)"),
+    ([0xa0, 0x71, 0x2d, 0x68], Heuristic::Mint, "mint(uint256)"),
+    ([0xf9, 0xf9, 0x2b, 0xe4], Heuristic::Blacklist, "blacklist(address)"),
+    ([0x44, 0x33, 0x7e, 0xa1], Heuristic::Blacklist, "addToBlacklist(address)"),
+    ([0x15, 0x3b, 0x0d, 0x1e], Heuristic::Blacklist, "setBlacklist(address,bool)"),
+    ([0x45, 0x5a, 0x43, 0x96], Heuristic::Blacklist, "blacklistAddress(address,bool)"),
+    ([0xd3, 0x46, 0x28, 0xcc], Heuristic::Blacklist, "addBots(address[])"),
+    ([0xb5, 0x15, 0x56, 0x6a], Heuristic::Blacklist, "setBots(address[])"),
+    ([0x69, 0xfe, 0x0e, 0x2d], Heuristic::OwnerFees, "setFee(uint256)"),
+    ([0xc4, 0x08, 0x1a, 0x4c], Heuristic::OwnerFees, "setTaxFee(uint256)"),
+    ([0x0b, 0x78, 0xf9, 0xc0], Heuristic::OwnerFees, "setFees(uint256,uint256)"),
+    ([0x0c, 0xc8, 0x35, 0xa3], Heuristic::OwnerFees, "setBuyFee(uint256)"),
+    ([0x8b, 0x4c, 0xee, 0x08], Heuristic::OwnerFees, "setSellFee(uint256)"),
+    ([0x6d, 0xb7, 0x94, 0x37], Heuristic::OwnerFees, "updateFees(uint256,uint256)"),
+    ([0x84, 0x56, 0xcb, 0x59], Heuristic::Pausable, "pause()"),
+    ([0xec, 0x28, 0x43, 0x8a], Heuristic::TradingControls, "setMaxTxAmount(uint256)"),
+    ([0xea, 0x16, 0x44, 0xd5], Heuristic::TradingControls, "setMaxWalletSize(uint256)"),
+    ([0xc2, 0xe5, 0xec, 0x04], Heuristic::TradingControls, "setTradingEnabled(bool)"),
+    ([0x8a, 0x8c, 0x52, 0x3c], Heuristic::TradingControls, "enableTrading()"),
+];
+
+/// A contract's rug-pull risk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskReport {
+    pub contract: String,
+    /// 0–100.
+    pub score: u32,
+    pub findings: Vec<String>,
+}
+
+/// Selectors pushed by the dispatcher: every `PUSH4` immediate in `code`.
+pub fn scan_selectors(code: &[u8]) -> HashSet<[u8; 4]> {
+    const PUSH1: u8 = 0x60;
+    const PUSH4: u8 = 0x63;
+    const PUSH32: u8 = 0x7f;
+
+    let mut selectors = HashSet::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = code[pc];
+        if op == PUSH4 {
+            if let Some(imm) = code.get(pc + 1..pc + 5) {
+                selectors.insert([imm[0], imm[1], imm[2], imm[3]]);
+            }
+        }
+        pc += 1;
+        if (PUSH1..=PUSH32).contains(&op) {
+            pc += (op - PUSH1 + 1) as usize;
+        }
+    }
+    selectors
+}
+
+/// Score a contract exposing `selectors`.
+pub fn score(
+    contract: &str,
+    selectors: &HashSet<[u8; 4]>,
+    owner_renounced: bool,
+    lp_burned_pct: Option<f64>,
+) -> RiskReport {
+    let mut hits: Vec<(Heuristic, String)> = Vec::new();
+    for (selector, heuristic, signature) in RISKY_SELECTORS {
+        if !selectors.contains(selector) {
+            continue;
+        }
+        match hits.iter_mut().find(|(h, _)| h == heuristic) {
+            Some((_, detail)) => detail.push_str(&format!(", {signature}")),
+            None => hits.push((*heuristic, signature.to_string())),
+        }
+    }
+    if let Some(pct) = lp_burned_pct.filter(|pct| *pct < MIN_BURNED_LP_PCT) {
+        hits.push((Heuristic::UnlockedLiquidity, format!("{pct:.1}% of LP burned")));
+    }
+
+    let mut total = 0;
+    let findings = hits
+        .into_iter()
+        .map(|(heuristic, detail)| {
+            let dampened = owner_renounced && heuristic.owner_gated();
+            total += if dampened { heuristic.weight() / 2 } else { heuristic.weight() };
+            format!(
+                "{} ({}){}",
+                heuristic.label(),
+                detail,
+                if dampened { " [ownership renounced]" } else { "" }
+            )
+        })
+        .collect();
+    RiskReport { contract: contract.to_lowercase(), score: total.min(100), findings }
+}
+
+fn call_data(selector: [u8; 4], addresses: &[&str]) -> Result<Vec<u8>> {
+    let mut out = selector.to_vec();
+    for a in addresses {
+        out.extend_from_slice(&abi::encode_address(a).map_err(anyhow::Error::msg)?);
+    }
+    Ok(out)
+}
+
+/// `owner()` is the zero address. `false` when there is no `owner()`.
+async fn ownership_renounced(rpc_url: &str, contract: &str) -> bool {
+    match simulator::eth_call(rpc_url, contract, &OWNER, "latest").await {
+        Ok(ret) => abi::uint(&ret, 0).map(|owner| owner.is_zero()).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Share of the token's Uniswap V2 LP sent to burn addresses.
+/// `None` if there is no pool.
+async fn lp_burned_pct(config: &Config, token: &str) -> Result<Option<f64>> {
+    let rpc = &config.upstream_rpc_url;
+    let ret = simulator::eth_call(
+        rpc,
+        &config.uniswap_v2_factory,
+        &call_data(GET_PAIR, &[token, &config.lp_quote_token])?,
+        "latest",
+    )
+    .await?;
+    let pair = abi::address(&ret, 0).map_err(anyhow::Error::msg)?;
+    if pair == BURN_ADDRESSES[0] {
+        return Ok(None);
+    }
+    let total = abi::uint(&simulator::eth_call(rpc, &pair, &TOTAL_SUPPLY, "latest").await?, 0).map_err(anyhow::Error::msg)?;
+    if total.is_zero() {
+        return Ok(None);
+    }
+    let mut burned = U256::ZERO;
+    for holder in BURN_ADDRESSES {
+        let ret = simulator::eth_call(rpc, &pair, &call_data(BALANCE_OF, &[holder])?, "latest").await?;
+        burned = burned.saturating_add(abi::uint(&ret, 0).map_err(anyhow::Error::msg)?);
+    }
+    let lossy = |v: U256| v.to_string().parse::<f64>().unwrap_or(f64::MAX);
+    Ok(Some(lossy(burned) / lossy(total) * 100.0))
+}
+
+/// Assess `contract`, with the code and ABI of `implementation` when
+/// it is a proxy resolved to one. `Ok(None)` if it has no code.
+pub async fn assess(config: &Config, contract: &str, implementation: Option<&str>) -> Result<Option<RiskReport>> {
+    let code = simulator::fetch_code(&config.upstream_rpc_url, contract).await?;
+    if code.is_empty() {
+        return Ok(None);
+    }
+    let mut selectors = scan_selectors(&code);
+    for address in std::iter::once(contract).chain(implementation) {
+        if address != contract {
+            selectors.extend(scan_selectors(&simulator::fetch_code(&config.upstream_rpc_url, address).await?));
+        }
+        if let Some(verified) = abi_registry::get(config, address).await {
+            selectors.extend(verified.functions().map(|f| f.short_signature()));
+        }
+    }
+
+    let renounced = ownership_renounced(&config.upstream_rpc_url, contract).await;
+    let burned = if selectors.contains(&TRANSFER) && !config.uniswap_v2_factory.is_empty() {
+        lp_burned_pct(config, contract).await.unwrap_or_else(|e| {
+            tracing::debug!(error = %e, token = contract, "LP lock check failed");
+            None
+        })
+    } else {
+        None
+    };
+    Ok(Some(score(contract, &selectors, renounced, burned)))
+}
+
+/// Block when the score reaches `rugpull_block_score`.
+pub fn check(config: &Config, report: &RiskReport) -> Result<(), String> {
+    if config.rugpull_block_score == 0 || report.score < config.rugpull_block_score {
+        return Ok(());
+    }
+    Err(format!(
+        "PLIMSOLL RUGPULL: contract {} scores {}/100 (threshold {}) — {}",
+        report.contract,
+        report.score,
+        config.rugpull_block_score,
+        report.findings.join("; ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0x1111111111111111111111111111111111111111";
+
+    /// A dispatcher fragment: `PUSH4 sel; EQ; PUSH2 dest; JUMPI` per selector.
+    fn dispatcher(selectors: &[[u8; 4]]) -> Vec<u8> {
+        let mut code = vec![0x60, 0x80, 0x60, 0x40, 0x52];
+        for s in selectors {
+            code.push(0x63);
+            code.extend_from_slice(s);
+            code.extend_from_slice(&[0x14, 0x61, 0x01, 0x00, 0x57]);
+        }
+        code
+    }
+
+    #[test]
+    fn test_scan_skips_push_immediates() {
+        // PUSH5 whose immediate contains a PUSH4 opcode must not be read
+        // as a selector.
+        let code = [0x64, 0x63, 0x40, 0xc1, 0x0f, 0x19, 0x00];
+        assert!(scan_selectors(&code).is_empty());
+        let code = dispatcher(&[[0x40, 0xc1, 0x0f, 0x19]]);
+        assert!(scan_selectors(&code).contains(&[0x40, 0xc1, 0x0f, 0x19]));
+    }
+
+    #[test]
+    fn test_score_scam_token() {
+        let selectors = scan_selectors(&dispatcher(&[
+            TRANSFER,
+            [0x40, 0xc1, 0x0f, 0x19],
+            [0xb5, 0x15, 0x56, 0x6a],
+            [0xc4, 0x08, 0x1a, 0x4c],
+        ]));
+        let report = score(TOKEN, &selectors, false, Some(0.0));
+        assert_eq!(report.score, 95);
+        assert_eq!(report.findings.len(), 4);
+        assert!(check(&Config::default(), &report).unwrap_err().contains("95/100"));
+
+        let renounced = score(TOKEN, &selectors, true, Some(100.0));
+        assert_eq!(renounced.score, 37);
+        assert!(check(&Config::default(), &renounced).is_ok());
+    }
+
+    #[test]
+    fn test_plain_token_scores_zero() {
+        let report = score(TOKEN, &scan_selectors(&dispatcher(&[TRANSFER])), false, None);
+        assert_eq!(report.score, 0);
+        assert!(report.findings.is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! Gnosis Safe call unwrapping.
+//!
+//! An agent operating a Safe sends `execTransaction(to, value, data,
+//! operation, ...)` to the Safe itself, so every check upstream only sees
+//! "a call to my own multisig". The real target, value and calldata are
+//! decoded here — including `MultiSend` batches, recursively — so each
+//! operation can be run through Engine 0 and simulation as the Safe.
+//!
+//! `operation = 1` is a DELEGATECALL: the target's code runs with the
+//! Safe's storage and balance, i.e. it can rewrite owners or drain
+//! everything. Delegatecalls are only allowed into the operator's
+//! `safe_delegatecall_allowlist` (the canonical MultiSend contracts by
+//! default).
+//!
+//! `approveHash(bytes32)` pre-approves an opaque Safe transaction hash;
+//! nothing about the approved transaction is visible, so it is blocked.
+
+use crate::abi;
+use crate::config::Config;
+use crate::types::InnerCall;
+use alloy_primitives::U256;
+
+/// `execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)`
+pub const EXEC_TRANSACTION: [u8; 4] = [0x6a, 0x76, 0x12, 0x02];
+/// `approveHash(bytes32)`
+pub const APPROVE_HASH: [u8; 4] = [0xd4, 0xd9, 0xbd, 0xcd];
+/// `multiSend(bytes)`
+pub const MULTI_SEND: [u8; 4] = [0x8d, 0x80, 0xff, 0x0a];
+
+/// Maximum MultiSend nesting depth.
+const MAX_DEPTH: usize = 4;
+
+/// Safe `Enum.Operation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Call,
+    DelegateCall,
+}
+
+impl Operation {
+    fn from_word(v: usize) -> Result<Self, String> {
+        match v {
+            0 => Ok(Self::Call),
+            1 => Ok(Self::DelegateCall),
+            other => Err(format!("invalid Safe operation {other}")),
+        }
+    }
+}
+
+/// One unwrapped Safe operation, executed as the Safe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SafeOperation {
+    pub call: InnerCall,
+    pub operation: Operation,
+}
+
+/// A decoded call to a Safe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SafeCall {
+    /// `execTransaction`, flattened into leaf operations.
+    Exec(Vec<SafeOperation>),
+    /// `approveHash(hash)`.
+    ApproveHash(String),
+}
+
+fn is_allowed_delegate_target(config: &Config, target: &str) -> bool {
+    config
+        .safe_delegatecall_allowlist
+        .split(',')
+        .map(str::trim)
+        .any(|a| !a.is_empty() && a.eq_ignore_ascii_case(target))
+}
+
+fn u128_value(v: U256) -> Result<u128, String> {
+    u128::try_from(v).map_err(|_| "Safe operation value exceeds u128".to_string())
+}
+
+/// Decode a call to `safe`. `Ok(None)` if `data` is not a Safe call.
+pub fn decode(config: &Config, safe: &str, data: &[u8]) -> Result<Option<SafeCall>, String> {
+    let Some(selector) = abi::selector(data) else {
+        return Ok(None);
+    };
+    let args = &data[4..];
+    match selector {
+        EXEC_TRANSACTION => {
+            let op = SafeOperation {
+                call: InnerCall {
+                    from: safe.to_lowercase(),
+                    to: abi::address(args, 0)?,
+                    value: u128_value(abi::uint(args, 1)?)?,
+                    data: abi::bytes(args, 2)?,
+                },
+                operation: Operation::from_word(abi::usize_word(args, 3)?)?,
+            };
+            let mut ops = Vec::new();
+            flatten(config, op, 0, &mut ops)?;
+            Ok(Some(SafeCall::Exec(ops)))
+        }
+        APPROVE_HASH => Ok(Some(SafeCall::ApproveHash(format!(
+            "0x{}",
+            hex::encode(abi::word(args, 0)?)
+        )))),
+        _ => Ok(None),
+    }
+}
+
+/// Expand allowlisted MultiSend delegatecalls into their operations.
+fn flatten(
+    config: &Config,
+    op: SafeOperation,
+    depth: usize,
+    out: &mut Vec<SafeOperation>,
+) -> Result<(), String> {
+    let is_multisend = op.operation == Operation::DelegateCall
+        && abi::selector(&op.call.data) == Some(MULTI_SEND)
+        && is_allowed_delegate_target(config, &op.call.to);
+    if !is_multisend {
+        out.push(op);
+        return Ok(());
+    }
+    if depth >= MAX_DEPTH {
+        return Err(format!("MultiSend nested deeper than {MAX_DEPTH}"));
+    }
+    let packed = abi::bytes(&op.call.data[4..], 0)?;
+    for inner in decode_multisend(&op.call.from, &packed)? {
+        flatten(config, inner, depth + 1, out)?;
+    }
+    Ok(())
+}
+
+/// Decode MultiSend's packed encoding:
+/// `operation (1) ‖ to (20) ‖ value (32) ‖ dataLength (32) ‖ data`.
+fn decode_multisend(safe: &str, packed: &[u8]) -> Result<Vec<SafeOperation>, String> {
+    let mut ops = Vec::new();
+    let mut i = 0;
+    while i < packed.len() {
+        let header = packed
+            .get(i..i + 85)
+            .ok_or("MultiSend entry truncated")?;
+        let operation = Operation::from_word(header[0] as usize)?;
+        let to = format!("0x{}", hex::encode(&header[1..21]));
+        let value = u128_value(U256::from_be_slice(&header[21..53]))?;
+        let len = usize::try_from(U256::from_be_slice(&header[53..85]))
+            .map_err(|_| "MultiSend data length overflow")?;
+        let start = i + 85;
+        let data = start
+            .checked_add(len)
+            .and_then(|end| packed.get(start..end))
+            .ok_or("MultiSend data out of bounds")?
+            .to_vec();
+        ops.push(SafeOperation {
+            call: InnerCall { from: safe.to_string(), to, value, data },
+            operation,
+        });
+        i = start + len;
+    }
+    Ok(ops)
+}
+
+/// Flag delegatecalls outside the allowlist.
+pub fn check_operations(config: &Config, ops: &[SafeOperation]) -> Result<(), String> {
+    if let Some(op) = ops
+        .iter()
+        .find(|op| op.operation == Operation::DelegateCall && !is_allowed_delegate_target(config, &op.call.to))
+    {
+        return Err(format!(
+            "PLIMSOLL SAFE: DELEGATECALL into {} — the target's code would run with the \
+             Safe's storage and funds (owner takeover / full drain)",
+            op.call.to
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abi::encode_uint;
+
+    const SAFE: &str = "0x5afe5afe5afe5afe5afe5afe5afe5afe5afe5afe";
+    const TARGET: &str = "0x2222222222222222222222222222222222222222";
+
+    fn addr_word(a: &str) -> [u8; 32] {
+        let mut w = [0u8; 32];
+        w[12..].copy_from_slice(&hex::decode(&a[2..]).unwrap());
+        w
+    }
+
+    fn exec(to: &str, value: u64, data: &[u8], operation: u64) -> Vec<u8> {
+        let mut out = EXEC_TRANSACTION.to_vec();
+        out.extend_from_slice(&addr_word(to));
+        out.extend_from_slice(&encode_uint(value));
+        out.extend_from_slice(&encode_uint(10 * 32)); // data offset
+        out.extend_from_slice(&encode_uint(operation));
+        for _ in 0..5 {
+            out.extend_from_slice(&[0u8; 32]); // safeTxGas..refundReceiver
+        }
+        out.extend_from_slice(&encode_uint(0)); // signatures offset (unused)
+        out.extend_from_slice(&encode_uint(data.len() as u64));
+        out.extend_from_slice(data);
+        out.resize(out.len() + (32 - data.len() % 32) % 32, 0);
+        out
+    }
+
+    fn multisend(entries: &[(u8, &str, u64, Vec<u8>)]) -> Vec<u8> {
+        let mut packed = Vec::new();
+        for (op, to, value, data) in entries {
+            packed.push(*op);
+            packed.extend_from_slice(&hex::decode(&to[2..]).unwrap());
+            packed.extend_from_slice(&encode_uint(*value));
+            packed.extend_from_slice(&encode_uint(data.len() as u64));
+            packed.extend_from_slice(data);
+        }
+        let mut out = MULTI_SEND.to_vec();
+        out.extend_from_slice(&encode_uint(32));
+        out.extend_from_slice(&encode_uint(packed.len() as u64));
+        out.extend_from_slice(&packed);
+        out
+    }
+
+    #[test]
+    fn test_exec_transaction_unwrapped() {
+        let data = exec(TARGET, 5, &[0xa9, 0x05, 0x9c, 0xbb], 0);
+        let Some(SafeCall::Exec(ops)) = decode(&Config::default(), SAFE, &data).unwrap() else {
+            panic!("expected execTransaction");
+        };
+        assert_eq!(ops.len(), 1);
+        assert_eq!(ops[0].call.from, SAFE);
+        assert_eq!(ops[0].call.to, TARGET);
+        assert_eq!(ops[0].call.value, 5);
+        assert_eq!(ops[0].call.data, vec![0xa9, 0x05, 0x9c, 0xbb]);
+    }
+
+    #[test]
+    fn test_multisend_flattened_and_nested_delegatecall_flagged() {
+        let config = Config::default();
+        let multisend_addr = config.safe_delegatecall_allowlist.split(',').next().unwrap().trim().to_lowercase();
+        let batch = multisend(&[(0, TARGET, 1, vec![]), (1, TARGET, 0, vec![0xde, 0xad, 0xbe, 0xef])]);
+        let data = exec(&multisend_addr, 0, &batch, 1);
+        let Some(SafeCall::Exec(ops)) = decode(&config, SAFE, &data).unwrap() else {
+            panic!("expected execTransaction");
+        };
+        assert_eq!(ops.len(), 2);
+        assert!(check_operations(&config, &ops).unwrap_err().contains("DELEGATECALL"));
+    }
+
+    #[test]
+    fn test_direct_delegatecall_flagged() {
+        let data = exec(TARGET, 0, &[], 1);
+        let Some(SafeCall::Exec(ops)) = decode(&Config::default(), SAFE, &data).unwrap() else {
+            panic!("expected execTransaction");
+        };
+        assert!(check_operations(&Config::default(), &ops).is_err());
+    }
+
+    #[test]
+    fn test_approve_hash_and_unrelated_calls() {
+        let mut data = APPROVE_HASH.to_vec();
+        data.extend_from_slice(&[0x11; 32]);
+        assert!(matches!(decode(&Config::default(), SAFE, &data).unwrap(), Some(SafeCall::ApproveHash(_))));
+        assert_eq!(decode(&Config::default(), SAFE, &[0xa9, 0x05, 0x9c, 0xbb]).unwrap(), None);
+        assert!(decode(&Config::default(), SAFE, &EXEC_TRANSACTION).is_err());
+    }
+}
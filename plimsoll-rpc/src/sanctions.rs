@@ -0,0 +1,411 @@
+//! Sanctions screening (OFAC SDN).
+//!
+//! Kept apart from Engine 0 on purpose: the threat feed is probabilistic,
+//! crowd-sourced and tunable (allowlists, per-source overrides), while a
+//! sanctions hit has to be exact, explainable and recorded. With
+//! `sanctions_list_url` set, the list is loaded at startup — the proxy
+//! refuses to start without it — and reloaded every
+//! `sanctions_refresh_secs`; a failed reload keeps the previous list.
+//! Until a list is loaded, every transaction is blocked.
+//!
+//! The list may be the SDN `sdn.csv` / `sdn_advanced.xml` export or a
+//! plain one-address-per-line file: every EVM address in it is taken. The
+//! transaction's `to`, any ERC-20 `transfer` / `transferFrom` recipient,
+//! any approval / permit spender and the same for every leaf of a
+//! multicall batch are screened, as are EIP-7702 delegates and — before a
+//! signature — the `verifyingContract` and every address in an EIP-712
+//! message. Meta-transaction and Safe inner calls run the whole send
+//! path, this screen included. Each block appends one JSON line to
+//! `sanctions_audit_path` naming the matched address and the exact list
+//! (location, digest, load time) it was matched against.
+
+use crate::abi;
+use crate::config::{is_hex_address, Config};
+use crate::counterparties;
+use crate::multicall;
+use crate::reload::SharedConfigHandle;
+use alloy_primitives::keccak256;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// A loaded sanctions list.
+#[derive(Debug, Clone, Default)]
+pub struct SanctionsList {
+    /// Sanctioned addresses, lowercase.
+    addresses: HashSet<String>,
+    /// Where the list was read from.
+    pub location: String,
+    /// keccak256 of the raw list, `0x`-prefixed.
+    pub digest: String,
+    /// RFC 3339 time the list was loaded.
+    pub loaded_at: String,
+}
+
+impl SanctionsList {
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+}
+
+/// One sanctions block, as written to the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: String,
+    pub decision: String,
+    pub agent: String,
+    pub chain_id: u64,
+    pub tx_to: String,
+    /// keccak256 of the calldata (of the typed-data JSON for a
+    /// signature), `0x`-prefixed.
+    pub calldata_hash: String,
+    pub matched_address: String,
+    /// `to`, `token_recipient`, `spender`, their `batched_` forms,
+    /// `delegate`, `verifying_contract` or `typed_data_counterparty`.
+    pub matched_as: String,
+    pub list_location: String,
+    pub list_digest: String,
+    pub list_loaded_at: String,
+    pub list_entries: usize,
+}
+
+lazy_static! {
+    static ref LIST: RwLock<Option<SanctionsList>> = RwLock::new(None);
+}
+
+/// Every EVM address in `text`, lowercase.
+pub fn extract_addresses(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|token| is_hex_address(token))
+        .collect()
+}
+
+async fn fetch(location: &str) -> Result<String> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let resp = reqwest::Client::new()
+            .get(location)
+            .timeout(Duration::from_secs(60))
+            .send()
+            .await
+            .context("Failed to fetch the sanctions list")?
+            .error_for_status()
+            .context("Sanctions list server returned an error")?;
+        return resp.text().await.context("Failed to read the sanctions list");
+    }
+    tokio::fs::read_to_string(location)
+        .await
+        .with_context(|| format!("Failed to read the sanctions list {location}"))
+}
+
+/// Build a list from its raw text. An empty list is an error: it would
+/// silently screen nothing.
+pub fn build(location: &str, text: &str) -> Result<SanctionsList> {
+    let list = SanctionsList {
+        addresses: extract_addresses(text),
+        location: location.to_string(),
+        digest: format!("0x{}", hex::encode(keccak256(text.as_bytes()))),
+        loaded_at: chrono::Utc::now().to_rfc3339(),
+    };
+    if list.is_empty() {
+        anyhow::bail!("Sanctions list {location} contains no addresses");
+    }
+    Ok(list)
+}
+
+fn install(list: SanctionsList) {
+    if let Ok(mut current) = LIST.write() {
+        *current = Some(list);
+    }
+}
+
+/// (Re)load the list from `sanctions_list_url`. Returns the entry count;
+/// a no-op when screening is off.
+pub async fn load(config: &Config) -> Result<usize> {
+    if config.sanctions_list_url.is_empty() {
+        return Ok(0);
+    }
+    let text = fetch(&config.sanctions_list_url).await?;
+    let list = build(&config.sanctions_list_url, &text)?;
+    let entries = list.len();
+    info!(entries, digest = %list.digest, "Sanctions list loaded from {}", list.location);
+    install(list);
+    Ok(entries)
+}
+
+/// Spawn the background reload task. Checks the live config every minute
+/// and reloads when the list is due or its location changed.
+pub fn spawn_refresh_task(config: SharedConfigHandle) {
+    tokio::spawn(async move {
+        let mut last_load = tokio::time::Instant::now();
+        loop {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            let cfg = config.current();
+            if cfg.sanctions_list_url.is_empty() {
+                continue;
+            }
+            let moved = LIST
+                .read()
+                .map(|l| l.as_ref().map(|l| l.location.as_str()) != Some(cfg.sanctions_list_url.as_str()))
+                .unwrap_or(true);
+            if !moved && last_load.elapsed() < Duration::from_secs(cfg.sanctions_refresh_secs) {
+                continue;
+            }
+            last_load = tokio::time::Instant::now();
+            if let Err(e) = load(&cfg).await {
+                warn!(error = %e, "Sanctions list reload failed — keeping the current list");
+            }
+        }
+    });
+}
+
+/// `approve` / `increaseAllowance` / `setApprovalForAll` / ERC-4494 `permit`:
+/// the spender is the first argument.
+const SPENDER_FIRST: [[u8; 4]; 4] = [
+    [0x09, 0x5e, 0xa7, 0xb3],
+    [0x39, 0x50, 0x93, 0x51],
+    [0xa2, 0x2c, 0xb4, 0x65],
+    [0x74, 0x5a, 0x41, 0xbc],
+];
+/// ERC-2612 `permit(owner, spender, ...)` / Permit2
+/// `approve(token, spender, ...)`: the spender is the second argument.
+const SPENDER_SECOND: [[u8; 4]; 2] = [[0xd5, 0x05, 0xac, 0xcf], [0x87, 0x51, 0x7c, 0x45]];
+
+/// The address an approval-type call grants spending rights to.
+fn spender(data: &[u8]) -> Option<String> {
+    let selector = abi::selector(data)?;
+    let args = &data[4..];
+    if SPENDER_FIRST.contains(&selector) {
+        abi::address(args, 0).ok()
+    } else if SPENDER_SECOND.contains(&selector) {
+        abi::address(args, 1).ok()
+    } else {
+        None
+    }
+}
+
+/// `to`, the ERC-20 recipient and the approval spender of one call.
+fn call_parties(to: &str, data: &[u8], batched: bool) -> Vec<(String, &'static str)> {
+    let mut out: Vec<(String, &'static str)> = counterparties::recipients(to, data)
+        .into_iter()
+        .enumerate()
+        .map(|(i, address)| {
+            let role = match (i == 0, batched) {
+                (true, false) => "to",
+                (false, false) => "token_recipient",
+                (true, true) => "batched_to",
+                (false, true) => "batched_token_recipient",
+            };
+            (address, role)
+        })
+        .collect();
+    out.extend(spender(data).map(|s| (s, if batched { "batched_spender" } else { "spender" })));
+    out
+}
+
+/// Every counterparty of a transaction with its role, including the
+/// leaf calls of a multicall batch. (An undecodable batch is blocked by
+/// the multicall check.)
+fn parties(to: &str, data: &[u8]) -> Vec<(String, &'static str)> {
+    let mut out = call_parties(to, data, false);
+    if let Ok(Some(calls)) = multicall::decode(to, data) {
+        for call in &calls {
+            out.extend(call_parties(&call.to, &call.data, true));
+        }
+    }
+    out
+}
+
+/// The `verifyingContract` and every address in the message of an
+/// EIP-712 payload.
+fn typed_data_parties(typed_data: &serde_json::Value) -> Vec<(String, &'static str)> {
+    fn walk(value: &serde_json::Value, out: &mut Vec<(String, &'static str)>) {
+        match value {
+            serde_json::Value::String(s) if is_hex_address(s) => {
+                out.push((s.to_lowercase(), "typed_data_counterparty"));
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, out)),
+            serde_json::Value::Object(fields) => fields.values().for_each(|v| walk(v, out)),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    if let Some(contract) = typed_data
+        .get("domain")
+        .and_then(|d| d.get("verifyingContract"))
+        .and_then(|v| v.as_str())
+        .filter(|s| is_hex_address(s))
+    {
+        out.push((contract.to_lowercase(), "verifying_contract"));
+    }
+    if let Some(message) = typed_data.get("message") {
+        walk(message, &mut out);
+    }
+    out
+}
+
+/// The first sanctioned party.
+fn screen(list: &SanctionsList, parties: Vec<(String, &'static str)>) -> Option<(String, &'static str)> {
+    parties.into_iter().find(|(address, _)| list.addresses.contains(address))
+}
+
+fn append_audit(path: &str, record: &AuditRecord) -> Result<()> {
+    let line = serde_json::to_string(record)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open the sanctions audit log {path}"))?;
+    writeln!(file, "{line}")?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Screen a transaction: `to`, token recipients, approval spenders and
+/// every leaf of a multicall batch. On a hit, write the audit record and
+/// return the block reason.
+pub fn check(config: &Config, from: &str, to: &str, data: &[u8]) -> Result<(), String> {
+    enforce(config, from, to, data, |list| screen(list, parties(to, data)))
+}
+
+/// Screen the addresses an EIP-7702 SetCode transaction delegates to.
+pub fn check_delegates(config: &Config, from: &str, to: &str, delegates: &[String]) -> Result<(), String> {
+    enforce(config, from, to, &[], |list| {
+        screen(list, delegates.iter().map(|d| (d.to_lowercase(), "delegate")).collect())
+    })
+}
+
+/// Screen an EIP-712 payload before `signer` signs it: the
+/// `verifyingContract` and every address in the message (spender,
+/// operator, taker, recipient, ...).
+pub fn check_typed_data(config: &Config, signer: &str, typed_data: &serde_json::Value) -> Result<(), String> {
+    let to = typed_data
+        .get("domain")
+        .and_then(|d| d.get("verifyingContract"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let payload = serde_json::to_vec(typed_data).unwrap_or_default();
+    enforce(config, signer, to, &payload, |list| screen(list, typed_data_parties(typed_data)))
+}
+
+fn enforce(
+    config: &Config,
+    agent: &str,
+    to: &str,
+    data: &[u8],
+    find: impl FnOnce(&SanctionsList) -> Option<(String, &'static str)>,
+) -> Result<(), String> {
+    if config.sanctions_list_url.is_empty() {
+        return Ok(());
+    }
+    let Ok(guard) = LIST.read() else {
+        return Err("PLIMSOLL SANCTIONS: sanctions list unavailable — failing closed".into());
+    };
+    let Some(list) = guard.as_ref() else {
+        return Err("PLIMSOLL SANCTIONS: sanctions list not loaded — failing closed".into());
+    };
+    let Some((matched, role)) = find(list) else {
+        return Ok(());
+    };
+    let record = AuditRecord {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        decision: "blocked".into(),
+        agent: agent.to_lowercase(),
+        chain_id: config.chain_id,
+        tx_to: to.to_lowercase(),
+        calldata_hash: format!("0x{}", hex::encode(keccak256(data))),
+        matched_address: matched.clone(),
+        matched_as: role.into(),
+        list_location: list.location.clone(),
+        list_digest: list.digest.clone(),
+        list_loaded_at: list.loaded_at.clone(),
+        list_entries: list.len(),
+    };
+    info!(target: "plimsoll::sanctions_audit", record = %serde_json::to_string(&record).unwrap_or_default());
+    if !config.sanctions_audit_path.is_empty() {
+        if let Err(e) = append_audit(&config.sanctions_audit_path, &record) {
+            error!(error = %e, "Failed to write the sanctions audit record");
+        }
+    }
+    Err(format!(
+        "PLIMSOLL SANCTIONS: {} {} is on the sanctions list (list {})",
+        role, matched, list.digest
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SANCTIONED: &str = "0x098b716b8aaf21512996dc57eb0615e2383e2f96";
+
+    #[test]
+    fn test_parse_sdn_exports() {
+        let csv = "36596,\"LAZARUS GROUP\",\"-0- \",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\
+                   \"Digital Currency Address - ETH 0x098B716B8Aaf21512996dC57EB0615e2383E2f96; \
+                   Digital Currency Address - XBT 3LwWyq7Vm8E9zjr3ZMbfPg7HJzKZfexqQG;\"";
+        assert_eq!(extract_addresses(csv), HashSet::from([SANCTIONED.to_string()]));
+        let xml = "<VersionDetail DetailTypeID=\"1432\">0x098B716B8Aaf21512996dC57EB0615e2383E2f96</VersionDetail>";
+        assert_eq!(extract_addresses(xml).len(), 1);
+        assert!(build("empty.csv", "no addresses here").is_err());
+    }
+
+    #[test]
+    fn test_screens_recipients_and_audits() {
+        let list = build("sdn.csv", SANCTIONED).unwrap();
+        assert_eq!(list.len(), 1);
+        assert!(list.digest.starts_with("0x"));
+        assert_eq!(screen(&list, parties(SANCTIONED, &[])), Some((SANCTIONED.to_string(), "to")));
+
+        // transfer(SANCTIONED, 1) on a clean token.
+        let mut data = vec![0xa9, 0x05, 0x9c, 0xbb];
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(&hex::decode(&SANCTIONED[2..]).unwrap());
+        data.extend_from_slice(&[0u8; 31]);
+        data.push(1);
+        let token = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+        assert_eq!(screen(&list, parties(token, &data)), Some((SANCTIONED.to_string(), "token_recipient")));
+        assert_eq!(screen(&list, parties(token, &[])), None);
+
+        // approve(SANCTIONED, 1): the spender.
+        let mut approve = data.clone();
+        approve[..4].copy_from_slice(&[0x09, 0x5e, 0xa7, 0xb3]);
+        assert_eq!(screen(&list, parties(token, &approve)), Some((SANCTIONED.to_string(), "spender")));
+
+        let typed = serde_json::json!({
+            "domain": { "name": "Permit2", "verifyingContract": "0x000000000022d473030f116ddee9f6b43ac78ba3" },
+            "message": { "details": { "token": token }, "spender": SANCTIONED.to_uppercase().replace("0X", "0x") },
+        });
+        assert_eq!(
+            screen(&list, typed_data_parties(&typed)),
+            Some((SANCTIONED.to_string(), "typed_data_counterparty"))
+        );
+
+        install(list);
+        let audit = std::env::temp_dir().join(format!("plimsoll-sanctions-{}.jsonl", std::process::id()));
+        let config = Config {
+            sanctions_list_url: "sdn.csv".into(),
+            sanctions_audit_path: audit.to_string_lossy().into_owned(),
+            ..Config::default()
+        };
+        let reason = check(&config, "0xagent", token, &data).unwrap_err();
+        assert!(reason.starts_with("PLIMSOLL SANCTIONS: token_recipient"));
+        let record: AuditRecord =
+            serde_json::from_str(std::fs::read_to_string(&audit).unwrap().lines().last().unwrap()).unwrap();
+        assert_eq!(record.matched_address, SANCTIONED);
+        assert_eq!(record.list_location, "sdn.csv");
+        let _ = std::fs::remove_file(&audit);
+        assert!(check(&config, "0xagent", token, &[]).is_ok());
+        assert!(check(&Config::default(), "0xagent", SANCTIONED, &[]).is_ok());
+        assert!(check_delegates(&config, "0xagent", "0xagent", &[SANCTIONED.to_string()]).is_err());
+    }
+}
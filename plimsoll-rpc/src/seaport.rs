@@ -0,0 +1,425 @@
+//! Seaport `OrderComponents` deep analysis.
+//!
+//! The classic NFT phishing signature is a Seaport listing that offers the
+//! agent's NFTs (or tokens) while the consideration pays the offerer
+//! nothing — or pays a fraction of a wei — and routes the rest to the
+//! attacker. The order is "just a signature" until the attacker fulfils it.
+//!
+//! The decoder enumerates offer and consideration items, computes the
+//! implied valuation returned to the offerer (native ETH plus configured
+//! ETH-equivalent ERC20s such as WETH), values the fungible items of both
+//! sides in USD with the price oracle, and rejects:
+//!   - offers of anything for zero consideration to the offerer,
+//!   - orders paying the offerer less than `seaport_min_offerer_share_pct`
+//!     of their ETH-valued consideration (the rest being "fees"),
+//!   - native / ERC-20 offers returning less than
+//!     `seaport_min_consideration_ratio` of their USD value — or that
+//!     can't be priced,
+//!   - NFT offers valued below `seaport_min_nft_consideration_wei` or
+//!     `seaport_min_nft_consideration_usd`,
+//!   - criteria-based offers (`identifierOrCriteria = 0`) that hand over
+//!     *any* token of a collection.
+
+use crate::config::Config;
+use crate::eip712::parse_integer;
+use crate::intents;
+use crate::oracle;
+use alloy_primitives::U256;
+use std::str::FromStr;
+
+/// Seaport `ItemType` enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemType {
+    Native,
+    Erc20,
+    Erc721,
+    Erc1155,
+    Erc721WithCriteria,
+    Erc1155WithCriteria,
+}
+
+impl ItemType {
+    fn from_u256(v: U256) -> Option<Self> {
+        Some(match u64::try_from(v).ok()? {
+            0 => Self::Native,
+            1 => Self::Erc20,
+            2 => Self::Erc721,
+            3 => Self::Erc1155,
+            4 => Self::Erc721WithCriteria,
+            5 => Self::Erc1155WithCriteria,
+            _ => return None,
+        })
+    }
+
+    pub fn is_nft(self) -> bool {
+        !matches!(self, Self::Native | Self::Erc20)
+    }
+
+    fn is_criteria(self) -> bool {
+        matches!(self, Self::Erc721WithCriteria | Self::Erc1155WithCriteria)
+    }
+}
+
+/// One offer or consideration item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub item_type: ItemType,
+    pub token: String,
+    pub identifier: U256,
+    pub start_amount: U256,
+    pub end_amount: U256,
+    /// Consideration items only.
+    pub recipient: Option<String>,
+}
+
+impl std::fmt::Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} {} #{} x{}",
+            self.item_type,
+            self.token,
+            self.identifier,
+            self.start_amount.max(self.end_amount)
+        )?;
+        if let Some(r) = &self.recipient {
+            write!(f, " → {r}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decoded `OrderComponents` message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub offerer: String,
+    pub offer: Vec<Item>,
+    pub consideration: Vec<Item>,
+}
+
+fn decode_item(v: &serde_json::Value, with_recipient: bool) -> Result<Item, String> {
+    let int = |name: &str| -> Result<U256, String> {
+        v.get(name)
+            .ok_or_else(|| format!("Seaport item missing `{name}`"))
+            .and_then(parse_integer)
+    };
+    let item_type = ItemType::from_u256(int("itemType")?).ok_or("unknown Seaport itemType")?;
+    let recipient = if with_recipient {
+        Some(
+            v.get("recipient")
+                .and_then(|r| r.as_str())
+                .ok_or("consideration item missing `recipient`")?
+                .to_lowercase(),
+        )
+    } else {
+        None
+    };
+    Ok(Item {
+        item_type,
+        token: v.get("token").and_then(|t| t.as_str()).unwrap_or("").to_lowercase(),
+        identifier: int("identifierOrCriteria")?,
+        start_amount: int("startAmount")?,
+        end_amount: int("endAmount")?,
+        recipient,
+    })
+}
+
+/// Decode the `message` of an `OrderComponents` typed-data payload.
+pub fn decode_order(typed_data: &serde_json::Value) -> Result<Order, String> {
+    let msg = typed_data.get("message").ok_or("missing `message`")?;
+    let offerer = msg
+        .get("offerer")
+        .and_then(|v| v.as_str())
+        .ok_or("OrderComponents missing `offerer`")?
+        .to_lowercase();
+    let list = |name: &str, with_recipient: bool| -> Result<Vec<Item>, String> {
+        msg.get(name)
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| format!("OrderComponents missing `{name}` array"))?
+            .iter()
+            .map(|i| decode_item(i, with_recipient))
+            .collect()
+    };
+    Ok(Order {
+        offerer,
+        offer: list("offer", false)?,
+        consideration: list("consideration", true)?,
+    })
+}
+
+/// USD values of an order's fungible (native and ERC-20) items.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Valuation {
+    /// Fungible items offered, at the larger of start/end amount. `None`
+    /// when any of them is unpriced.
+    pub offer_usd: Option<f64>,
+    /// Fungible items paid to the offerer, at the smaller of start/end
+    /// amount. `None` when any of them is unpriced.
+    pub proceeds_usd: Option<f64>,
+}
+
+fn to_offerer(order: &Order) -> impl Iterator<Item = &Item> {
+    order
+        .consideration
+        .iter()
+        .filter(|c| c.recipient.as_deref() == Some(order.offerer.as_str()))
+}
+
+/// Worst-case wei value of `items`: native ETH plus configured
+/// ETH-equivalent ERC20s, using the lower of start/end amount (Dutch
+/// auctions).
+fn eth_value_wei<'a>(config: &Config, items: impl Iterator<Item = &'a Item>) -> U256 {
+    let eth_equivalents: Vec<String> = config
+        .seaport_eth_equivalent_tokens
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    items
+        .filter(|c| match c.item_type {
+            ItemType::Native => true,
+            ItemType::Erc20 => eth_equivalents.contains(&c.token),
+            _ => false,
+        })
+        .fold(U256::ZERO, |acc, c| acc.saturating_add(c.start_amount.min(c.end_amount)))
+}
+
+/// Worst-case wei value returned to the offerer.
+pub fn offerer_proceeds_wei(config: &Config, order: &Order) -> U256 {
+    eth_value_wei(config, to_offerer(order))
+}
+
+async fn fungible_usd<'a>(config: &Config, items: impl Iterator<Item = (&'a Item, U256)>) -> Option<f64> {
+    let mut usd = 0.0;
+    for (item, amount) in items {
+        let token = match item.item_type {
+            ItemType::Native => config.native_price_token.as_str(),
+            ItemType::Erc20 => item.token.as_str(),
+            _ => continue,
+        };
+        usd += intents::usd_value(amount, &oracle::price(config, token).await?);
+    }
+    Some(usd)
+}
+
+/// Price both sides of `order`.
+pub async fn value(config: &Config, order: &Order) -> Valuation {
+    let offer = order.offer.iter().map(|i| (i, i.start_amount.max(i.end_amount)));
+    let proceeds = to_offerer(order).map(|c| (c, c.start_amount.min(c.end_amount)));
+    Valuation {
+        offer_usd: fungible_usd(config, offer).await,
+        proceeds_usd: fungible_usd(config, proceeds).await,
+    }
+}
+
+/// Human-readable summary of the order.
+pub fn describe(order: &Order) -> String {
+    let list = |items: &[Item]| items.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ");
+    format!(
+        "offerer {} gives [{}] for [{}]",
+        order.offerer,
+        list(&order.offer),
+        list(&order.consideration)
+    )
+}
+
+/// Apply the give-away rules to `order`, priced as `valuation`.
+/// `Ok(summary)` if the order is acceptable.
+pub fn check_order(config: &Config, order: &Order, valuation: &Valuation) -> Result<String, String> {
+    let summary = describe(order);
+    if order.offer.is_empty() {
+        return Ok(summary);
+    }
+
+    if let Some(item) = order.offer.iter().find(|i| i.item_type.is_criteria()) {
+        return Err(format!(
+            "PLIMSOLL SEAPORT: criteria-based offer hands over ANY token of {} — {}",
+            item.token, summary
+        ));
+    }
+
+    if to_offerer(order).next().is_none() {
+        return Err(format!(
+            "PLIMSOLL SEAPORT: order gives away assets for ZERO consideration to the offerer — {}",
+            summary
+        ));
+    }
+
+    let lossy = |v: U256| v.to_string().parse::<f64>().unwrap_or(f64::MAX);
+    let total_wei = eth_value_wei(config, order.consideration.iter());
+    let share_pct = lossy(offerer_proceeds_wei(config, order)) / lossy(total_wei) * 100.0;
+    if !total_wei.is_zero() && share_pct < config.seaport_min_offerer_share_pct {
+        return Err(format!(
+            "PLIMSOLL SEAPORT: offerer receives only {:.1}% of the consideration (minimum {}%) — {}",
+            share_pct, config.seaport_min_offerer_share_pct, summary
+        ));
+    }
+
+    let ratio = config.seaport_min_consideration_ratio;
+    if ratio > 0.0 && order.offer.iter().any(|i| !i.item_type.is_nft()) {
+        match (valuation.offer_usd, valuation.proceeds_usd) {
+            (Some(offered), Some(proceeds)) if proceeds < offered * ratio => {
+                return Err(format!(
+                    "PLIMSOLL SEAPORT: order returns ${:.2} for ${:.2} of tokens offered (minimum ratio {}) — {}",
+                    proceeds, offered, ratio, summary
+                ));
+            }
+            (Some(_), Some(_)) => {}
+            _ => {
+                return Err(format!(
+                    "PLIMSOLL SEAPORT: tokens offered for unpriced consideration — can't value the order — {}",
+                    summary
+                ));
+            }
+        }
+    }
+
+    let offers_nft = order.offer.iter().any(|i| i.item_type.is_nft());
+    let proceeds = offerer_proceeds_wei(config, order);
+    let min = U256::from_str(&config.seaport_min_nft_consideration_wei).unwrap_or(U256::ZERO);
+    if offers_nft && min > U256::ZERO && proceeds < min {
+        return Err(format!(
+            "PLIMSOLL SEAPORT: NFT offered for {} wei (< minimum {} wei) — {}",
+            proceeds, min, summary
+        ));
+    }
+    let paid_fungible = to_offerer(order).any(|c| !c.item_type.is_nft());
+    let min_usd = config.seaport_min_nft_consideration_usd;
+    if let Some(usd) = valuation.proceeds_usd.filter(|usd| offers_nft && paid_fungible && *usd < min_usd) {
+        return Err(format!(
+            "PLIMSOLL SEAPORT: NFT offered for ${:.2} (< minimum ${:.2}) — {}",
+            usd, min_usd, summary
+        ));
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: &str = "0x1111111111111111111111111111111111111111";
+    const ATTACKER: &str = "0x6666666666666666666666666666666666666666";
+    const BAYC: &str = "0xbc4ca0eda7647a8ab7c2061c2e118a18a936f13d";
+
+    fn order(consideration: serde_json::Value, offer_type: u8, id: u64) -> serde_json::Value {
+        serde_json::json!({
+            "primaryType": "OrderComponents",
+            "message": {
+                "offerer": AGENT,
+                "offer": [{
+                    "itemType": offer_type, "token": BAYC,
+                    "identifierOrCriteria": id.to_string(), "startAmount": "1", "endAmount": "1"
+                }],
+                "consideration": consideration
+            }
+        })
+    }
+
+    fn eth_to(recipient: &str, wei: &str) -> serde_json::Value {
+        serde_json::json!({
+            "itemType": 0, "token": "0x0000000000000000000000000000000000000000",
+            "identifierOrCriteria": "0", "startAmount": wei, "endAmount": wei,
+            "recipient": recipient
+        })
+    }
+
+    fn config() -> Config {
+        Config {
+            seaport_min_nft_consideration_wei: "10000000000000000".into(), // 0.01 ETH
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_fair_listing_allowed() {
+        let data = order(serde_json::json!([eth_to(AGENT, "1000000000000000000")]), 2, 42);
+        let decoded = decode_order(&data).unwrap();
+        assert!(check_order(&config(), &decoded, &Valuation::default()).is_ok());
+    }
+
+    #[test]
+    fn test_free_giveaway_blocked() {
+        let data = order(serde_json::json!([eth_to(ATTACKER, "1")]), 2, 42);
+        let err = check_order(&config(), &decode_order(&data).unwrap(), &Valuation::default()).unwrap_err();
+        assert!(err.contains("ZERO consideration"));
+    }
+
+    #[test]
+    fn test_dust_price_blocked() {
+        let data = order(
+            serde_json::json!([eth_to(AGENT, "1"), eth_to(ATTACKER, "1000000000000000000")]),
+            2,
+            42,
+        );
+        let err = check_order(&config(), &decode_order(&data).unwrap(), &Valuation::default()).unwrap_err();
+        assert!(err.contains("minimum"));
+    }
+
+    #[test]
+    fn test_criteria_offer_blocked() {
+        let data = order(serde_json::json!([eth_to(AGENT, "1000000000000000000")]), 4, 0);
+        let err = check_order(&config(), &decode_order(&data).unwrap(), &Valuation::default()).unwrap_err();
+        assert!(err.contains("ANY token"));
+    }
+
+    #[test]
+    fn test_consideration_routed_to_others_blocked() {
+        // The offerer's "price" is a sliver; the rest goes to the attacker.
+        let data = order(
+            serde_json::json!([eth_to(AGENT, "100000000000000000"), eth_to(ATTACKER, "900000000000000000")]),
+            2,
+            42,
+        );
+        let err = check_order(&config(), &decode_order(&data).unwrap(), &Valuation::default()).unwrap_err();
+        assert!(err.contains("only 10.0% of the consideration"), "{err}");
+        // Marketplace fees and royalties are a small share.
+        let data = order(
+            serde_json::json!([eth_to(AGENT, "900000000000000000"), eth_to(ATTACKER, "100000000000000000")]),
+            2,
+            42,
+        );
+        assert!(check_order(&config(), &decode_order(&data).unwrap(), &Valuation::default()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_token_offer_valued_against_consideration() {
+        const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+        let mut config = config();
+        config.reference_prices.insert(USDC.into(), crate::config::ReferencePrice { usd: 1.0, decimals: 6 });
+        config.reference_prices.insert(
+            config.native_price_token.clone(),
+            crate::config::ReferencePrice { usd: 2000.0, decimals: 18 },
+        );
+        let usdc_offer = |wei: &str| {
+            let mut data = order(serde_json::json!([eth_to(AGENT, wei)]), 1, 0);
+            data["message"]["offer"][0]["token"] = USDC.into();
+            data["message"]["offer"][0]["startAmount"] = "1000000000".into(); // 1000 USDC
+            data["message"]["offer"][0]["endAmount"] = "1000000000".into();
+            decode_order(&data).unwrap()
+        };
+
+        // 1000 USDC for 0.0001 ETH ($0.20).
+        let dust = usdc_offer("100000000000000");
+        let valuation = value(&config, &dust).await;
+        assert_eq!(valuation.offer_usd, Some(1000.0));
+        let err = check_order(&config, &dust, &valuation).unwrap_err();
+        assert!(err.contains("minimum ratio"), "{err}");
+
+        // 1000 USDC for 0.5 ETH ($1000).
+        let fair = usdc_offer("500000000000000000");
+        assert!(check_order(&config, &fair, &value(&config, &fair).await).is_ok());
+
+        // Unpriced: can't be shown to be fair.
+        assert!(check_order(&config, &fair, &Valuation::default()).unwrap_err().contains("unpriced"));
+    }
+
+    #[test]
+    fn test_nft_below_usd_floor_blocked() {
+        let data = order(serde_json::json!([eth_to(AGENT, "1")]), 2, 42);
+        let config = Config { seaport_min_nft_consideration_usd: 1.0, ..Config::default() };
+        let valuation = Valuation { offer_usd: Some(0.0), proceeds_usd: Some(0.000_000_1) };
+        let err = check_order(&config, &decode_order(&data).unwrap(), &valuation).unwrap_err();
+        assert!(err.contains("< minimum $1.00"), "{err}");
+    }
+}
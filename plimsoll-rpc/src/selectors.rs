@@ -0,0 +1,252 @@
+//! Function selector directory.
+//!
+//! Block reasons and IOCs used to say `selector 0xf2fde38b`, which means
+//! nothing to an operator reading logs at 3am. This module maps selectors
+//! to text signatures (`transferOwnership(address)`) from three sources,
+//! in order:
+//!
+//!   1. a built-in seed of high-signal signatures from 4byte.directory,
+//!   2. an optional local JSON file (`selector_db_path`,
+//!      `{"0xf2fde38b": "transferOwnership(address)"}`), and
+//!   3. an optional 4byte-compatible lookup API (`selector_lookup_url`),
+//!      queried on a miss and cached.
+//!
+//! The table is rebuilt at startup and by `POST /admin/selectors/refresh`.
+//!
+//! `blocked_functions` lets the operator block calls by function name
+//! (`transferOwnership`) or full signature (`upgradeTo(address)`).
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Remote lookup timeout — this runs on the send path.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Seed signatures (4byte.directory canonical entries).
+const SEED: &[(&str, &str)] = &[
+    ("0xa9059cbb", "transfer(address,uint256)"),
+    ("0x23b872dd", "transferFrom(address,address,uint256)"),
+    ("0x095ea7b3", "approve(address,uint256)"),
+    ("0x39509351", "increaseAllowance(address,uint256)"),
+    ("0xd505accf", "permit(address,address,uint256,uint256,uint8,bytes32,bytes32)"),
+    ("0xa22cb465", "setApprovalForAll(address,bool)"),
+    ("0x42842e0e", "safeTransferFrom(address,address,uint256)"),
+    ("0xb88d4fde", "safeTransferFrom(address,address,uint256,bytes)"),
+    ("0xf242432a", "safeTransferFrom(address,address,uint256,uint256,bytes)"),
+    ("0x2eb2c2d6", "safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)"),
+    ("0xf2fde38b", "transferOwnership(address)"),
+    ("0x715018a6", "renounceOwnership()"),
+    ("0x3659cfe6", "upgradeTo(address)"),
+    ("0x4f1ef286", "upgradeToAndCall(address,bytes)"),
+    ("0x2f2ff15d", "grantRole(bytes32,address)"),
+    ("0xd0e30db0", "deposit()"),
+    ("0x2e1a7d4d", "withdraw(uint256)"),
+    ("0x6a761202", "execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)"),
+    ("0xd4d9bdcd", "approveHash(bytes32)"),
+    ("0x8d80ff0a", "multiSend(bytes)"),
+    ("0x252dba42", "aggregate((address,bytes)[])"),
+    ("0x82ad56cb", "aggregate3((address,bool,bytes)[])"),
+    ("0x174dea71", "aggregate3Value((address,bool,uint256,bytes)[])"),
+    ("0xac9650d8", "multicall(bytes[])"),
+    ("0x5ae401dc", "multicall(uint256,bytes[])"),
+    ("0x87517c45", "approve(address,address,uint160,uint48)"),
+    ("0x3593564c", "execute(bytes,bytes[],uint256)"),
+    ("0x38ed1739", "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)"),
+    ("0x7ff36ab5", "swapExactETHForTokens(uint256,address[],address,uint256)"),
+    ("0x414bf389", "exactInputSingle((address,address,uint24,address,uint256,uint256,uint256,uint160))"),
+    ("0x679b6ded", "createRetryableTicket(address,uint256,uint256,address,address,uint256,uint256,bytes)"),
+    ("0xe9e05c42", "depositTransaction(address,uint256,uint64,bool,bytes)"),
+];
+
+lazy_static! {
+    static ref DIRECTORY: RwLock<HashMap<String, String>> = RwLock::new(seed());
+    /// Selectors the remote lookup didn't know, so we don't ask again.
+    static ref MISSES: RwLock<HashSet<String>> = RwLock::new(HashSet::new());
+}
+
+fn seed() -> HashMap<String, String> {
+    SEED.iter().map(|(s, sig)| (s.to_string(), sig.to_string())).collect()
+}
+
+fn normalize(selector: &str) -> Option<String> {
+    let selector = selector.to_lowercase();
+    let hex = selector.strip_prefix("0x").unwrap_or(&selector);
+    (hex.len() == 8 && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| format!("0x{hex}"))
+}
+
+/// The `0x`-prefixed selector of `data`, if it has one.
+pub fn selector_hex(data: &[u8]) -> Option<String> {
+    data.get(..4).map(|s| format!("0x{}", hex::encode(s)))
+}
+
+/// Rebuild the directory from the seed and `selector_db_path`.
+/// Returns the number of known signatures.
+pub fn load(config: &Config) -> Result<usize> {
+    let mut table = seed();
+    if !config.selector_db_path.is_empty() {
+        let raw = std::fs::read_to_string(&config.selector_db_path)
+            .with_context(|| format!("reading selector DB {}", config.selector_db_path))?;
+        let entries: HashMap<String, String> = serde_json::from_str(&raw)
+            .with_context(|| format!("parsing selector DB {}", config.selector_db_path))?;
+        for (selector, signature) in entries {
+            let key = normalize(&selector)
+                .with_context(|| format!("selector DB: invalid selector '{selector}'"))?;
+            table.insert(key, signature);
+        }
+    }
+    let count = table.len();
+    if let Ok(mut dir) = DIRECTORY.write() {
+        *dir = table;
+    }
+    if let Ok(mut misses) = MISSES.write() {
+        misses.clear();
+    }
+    Ok(count)
+}
+
+/// Text signature for a `0x`-prefixed selector.
+pub fn lookup_hex(selector: &str) -> Option<String> {
+    let key = normalize(selector)?;
+    DIRECTORY.read().ok()?.get(&key).cloned()
+}
+
+/// Text signature of the function `data` calls.
+pub fn lookup(data: &[u8]) -> Option<String> {
+    lookup_hex(&selector_hex(data)?)
+}
+
+/// Human-readable description of the function `data` calls:
+/// `transfer(address,uint256)`, or the raw selector when unknown.
+pub fn describe(data: &[u8]) -> String {
+    match (lookup(data), selector_hex(data)) {
+        (Some(signature), _) => signature,
+        (None, Some(selector)) => selector,
+        (None, None) => "(no calldata)".into(),
+    }
+}
+
+/// Like [`lookup`], but on a miss queries `selector_lookup_url`
+/// (`GET {url}?hex_signature=0x..`, 4byte.directory response format) and
+/// caches the answer.
+pub async fn resolve(config: &Config, data: &[u8]) -> Option<String> {
+    if let Some(signature) = lookup(data) {
+        return Some(signature);
+    }
+    let selector = selector_hex(data)?;
+    if config.selector_lookup_url.is_empty()
+        || MISSES.read().map(|m| m.contains(&selector)).unwrap_or(false)
+    {
+        return None;
+    }
+
+    let signature = fetch_remote(&config.selector_lookup_url, &selector).await;
+    match &signature {
+        Some(sig) => {
+            if let Ok(mut dir) = DIRECTORY.write() {
+                dir.insert(selector, sig.clone());
+            }
+        }
+        None => {
+            if let Ok(mut misses) = MISSES.write() {
+                misses.insert(selector);
+            }
+        }
+    }
+    signature
+}
+
+async fn fetch_remote(url: &str, selector: &str) -> Option<String> {
+    let body: serde_json::Value = reqwest::Client::new()
+        .get(url)
+        .query(&[("hex_signature", selector)])
+        .timeout(LOOKUP_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    // Several signatures can collide on a selector; the earliest
+    // registered one is the canonical function.
+    body.get("results")?
+        .as_array()?
+        .iter()
+        .filter_map(|r| Some((r.get("id")?.as_u64()?, r.get("text_signature")?.as_str()?)))
+        .min_by_key(|(id, _)| *id)
+        .map(|(_, sig)| sig.to_string())
+}
+
+/// Function name of a text signature (`transfer(address,uint256)` → `transfer`).
+pub fn function_name(signature: &str) -> &str {
+    signature.split('(').next().unwrap_or(signature)
+}
+
+/// Block calls to functions listed in `blocked_functions` (by name or
+/// full signature). Unknown selectors can't match.
+pub fn check_function_policy(config: &Config, data: &[u8]) -> Result<(), String> {
+    if config.blocked_functions.trim().is_empty() {
+        return Ok(());
+    }
+    let Some(signature) = lookup(data) else {
+        return Ok(());
+    };
+    let name = function_name(&signature);
+    let matched = config
+        .blocked_functions
+        .split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .any(|entry| {
+            if entry.contains('(') {
+                entry == signature
+            } else {
+                entry == name
+            }
+        });
+    if matched {
+        return Err(format!(
+            "PLIMSOLL FUNCTION POLICY: call to {} is blocked by operator policy",
+            signature
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_known_and_unknown() {
+        assert_eq!(describe(&[0xf2, 0xfd, 0xe3, 0x8b, 0x00]), "transferOwnership(address)");
+        assert_eq!(describe(&[0xde, 0xad, 0xbe, 0xef]), "0xdeadbeef");
+        assert_eq!(describe(&[]), "(no calldata)");
+        assert_eq!(lookup_hex("0XA9059CBB").as_deref(), Some("transfer(address,uint256)"));
+    }
+
+    #[test]
+    fn test_function_policy_by_name_and_signature() {
+        let mut config = Config::default();
+        let data = [0xf2, 0xfd, 0xe3, 0x8b];
+        assert!(check_function_policy(&config, &data).is_ok());
+
+        config.blocked_functions = "transferOwnership".into();
+        let err = check_function_policy(&config, &data).unwrap_err();
+        assert!(err.contains("transferOwnership(address)"));
+
+        config.blocked_functions = "upgradeTo(address)".into();
+        assert!(check_function_policy(&config, &data).is_ok());
+        assert!(check_function_policy(&config, &[0x36, 0x59, 0xcf, 0xe6]).is_err());
+    }
+
+    #[test]
+    fn test_function_name() {
+        assert_eq!(function_name("approve(address,uint256)"), "approve");
+    }
+}
@@ -0,0 +1,362 @@
+//! Scoped session keys.
+//!
+//! Revocation is all-or-nothing. A session key handed to an agent for one
+//! job — "swap on this router, at most 1 ETH, until Friday" — can be
+//! registered here with that scope, and every send it makes is held to it
+//! before simulation, whatever the vault would let through on-chain:
+//!
+//!   - `targets`: contracts the key may call (empty = any),
+//!   - `selectors`: functions it may call (empty = any; `0x` = plain
+//!     transfers),
+//!   - `max_value`: wei it may send per transaction (empty = any),
+//!   - `expires_at`: unix time after which it may send nothing (0 = never).
+//!
+//! Targets and selectors are checked on every leaf call after vault,
+//! Safe and multicall unwrapping; the value is the larger of the
+//! transaction's and the leaf calls' total. Scopes are per chain (see
+//! `chains`), set through the admin API and persisted with the protective
+//! state. Keys without a scope are unrestricted.
+//!
+//! Rotation replaces a key in one step: the old key is pessimistically
+//! revoked, the new one takes over its scope (or a new one), and the
+//! owner gets the unsigned `PlimsollVault` transactions that do the same
+//! on-chain.
+
+use crate::abi;
+use crate::chains;
+use crate::config::Config;
+use crate::target_allowlist;
+use alloy_primitives::U256;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Scopes kept at most.
+const MAX_SCOPES: usize = 4096;
+
+/// `revokeSessionKey(address)`
+const REVOKE_SESSION_KEY: [u8; 4] = [0x84, 0xf4, 0xfc, 0x6a];
+/// `issueSessionKey(address,uint256,uint256,uint256)`
+const ISSUE_SESSION_KEY: [u8; 4] = [0xf4, 0x99, 0x32, 0x18];
+
+/// What a session key may do.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionScope {
+    pub targets: Vec<String>,
+    pub selectors: Vec<String>,
+    /// Wei, decimal.
+    pub max_value: String,
+    pub expires_at: u64,
+}
+
+impl SessionScope {
+    /// Reject malformed addresses, selectors and amounts.
+    pub fn validate(&self) -> Result<(), String> {
+        for target in &self.targets {
+            let hex = target.strip_prefix("0x").unwrap_or_default();
+            if hex.len() != 40 || hex::decode(hex).is_err() {
+                return Err(format!("invalid target '{target}'"));
+            }
+        }
+        for selector in &self.selectors {
+            let hex = selector.strip_prefix("0x").unwrap_or("?");
+            if !(hex.is_empty() || (hex.len() == 8 && hex::decode(hex).is_ok())) {
+                return Err(format!("invalid selector '{selector}'"));
+            }
+        }
+        if !self.max_value.is_empty() && self.max_value.parse::<U256>().is_err() {
+            return Err(format!("invalid max_value '{}'", self.max_value));
+        }
+        Ok(())
+    }
+
+    fn normalized(mut self) -> Self {
+        self.targets.iter_mut().for_each(|t| *t = t.to_lowercase());
+        self.selectors.iter_mut().for_each(|s| *s = s.to_lowercase());
+        self
+    }
+}
+
+/// The replacement key's parameters on a `PlimsollVault`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OnChainRotation {
+    pub vault: String,
+    pub duration_secs: u64,
+    /// Wei, decimal.
+    pub max_single_amount: String,
+    /// Wei, decimal.
+    pub daily_budget: String,
+}
+
+lazy_static! {
+    /// Session key (lowercase, chain-scoped) → scope.
+    static ref SCOPES: Mutex<HashMap<String, SessionScope>> = Mutex::new(HashMap::new());
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Set the scope of `session_key` (chain-scoped by the caller), replacing
+/// any previous one. `false` when the table is full.
+pub fn register(session_key: &str, scope: SessionScope) -> bool {
+    let Ok(mut scopes) = SCOPES.lock() else {
+        return false;
+    };
+    let key = session_key.to_lowercase();
+    if !scopes.contains_key(&key) && scopes.len() >= MAX_SCOPES {
+        return false;
+    }
+    scopes.insert(key, scope.normalized());
+    true
+}
+
+/// The scope of `session_key` (chain-scoped by the caller).
+pub fn get(session_key: &str) -> Option<SessionScope> {
+    SCOPES.lock().ok()?.get(&session_key.to_lowercase()).cloned()
+}
+
+/// Hand `old_key`'s scope — or `scope` — to `new_key` (both chain-scoped
+/// by the caller). The old key keeps its scope, should it ever be
+/// un-revoked. Returns the new key's scope; `Err` when the table is full.
+pub fn rotate(old_key: &str, new_key: &str, scope: Option<SessionScope>) -> Result<Option<SessionScope>, String> {
+    let Some(scope) = scope.or_else(|| get(old_key)) else {
+        remove(new_key);
+        return Ok(None);
+    };
+    if !register(new_key, scope.clone()) {
+        return Err("session scope table is full".into());
+    }
+    Ok(Some(scope.normalized()))
+}
+
+/// The unsigned owner transactions rotating `old_key` to `new_key` on the
+/// vault: `revokeSessionKey(old)`, then `issueSessionKey(new, …)`.
+pub fn rotation_transactions(
+    rotation: &OnChainRotation,
+    old_key: &str,
+    new_key: &str,
+) -> Result<Vec<serde_json::Value>, String> {
+    let amount = |name: &str, v: &str| v.parse::<U256>().map_err(|_| format!("invalid {name} '{v}'"));
+    let mut revoke = REVOKE_SESSION_KEY.to_vec();
+    revoke.extend(abi::encode_address(old_key)?);
+    let mut issue = ISSUE_SESSION_KEY.to_vec();
+    issue.extend(abi::encode_address(new_key)?);
+    issue.extend(abi::encode_uint(rotation.duration_secs));
+    issue.extend(abi::encode_u256(amount("max_single_amount", &rotation.max_single_amount)?));
+    issue.extend(abi::encode_u256(amount("daily_budget", &rotation.daily_budget)?));
+    abi::encode_address(&rotation.vault)?;
+    Ok([revoke, issue]
+        .iter()
+        .map(|data| {
+            serde_json::json!({
+                "to": rotation.vault.to_lowercase(),
+                "value": "0x0",
+                "data": format!("0x{}", hex::encode(data)),
+            })
+        })
+        .collect())
+}
+
+/// Drop the scope of `session_key`. `false` if it had none.
+pub fn remove(session_key: &str) -> bool {
+    SCOPES
+        .lock()
+        .map(|mut scopes| scopes.remove(&session_key.to_lowercase()).is_some())
+        .unwrap_or(false)
+}
+
+fn scope_of(session_key: &str) -> Result<Option<SessionScope>, ()> {
+    let scopes = SCOPES.lock().map_err(|_| ())?;
+    let key = chains::scope_key(chains::current(), &session_key.to_lowercase());
+    Ok(scopes.get(&key).cloned())
+}
+
+fn check_at(
+    config: &Config,
+    scope: &SessionScope,
+    session_key: &str,
+    to: &str,
+    value: u128,
+    data: &[u8],
+    now: u64,
+) -> Result<(), String> {
+    if scope.expires_at != 0 && now > scope.expires_at {
+        return Err(format!(
+            "PLIMSOLL SESSION SCOPE: session key {} expired at {}",
+            session_key, scope.expires_at
+        ));
+    }
+    let calls = target_allowlist::ultimate_calls(config, session_key, to, value, data);
+    for call in &calls {
+        if !scope.targets.is_empty() && !scope.targets.contains(&call.to) {
+            return Err(format!(
+                "PLIMSOLL SESSION SCOPE: session key {} may not call {}",
+                session_key, call.to
+            ));
+        }
+        let selector = format!("0x{}", hex::encode(call.data.get(..4).unwrap_or_default()));
+        if !scope.selectors.is_empty() && !scope.selectors.contains(&selector) {
+            return Err(format!(
+                "PLIMSOLL SESSION SCOPE: session key {} may not call {} on {}",
+                session_key, selector, call.to
+            ));
+        }
+    }
+    if !scope.max_value.is_empty() {
+        let leaves = calls.iter().fold(U256::ZERO, |sum, c| sum.saturating_add(U256::from(c.value)));
+        let spent = leaves.max(U256::from(value));
+        let cap: U256 = scope.max_value.parse().unwrap_or(U256::ZERO);
+        if spent > cap {
+            return Err(format!(
+                "PLIMSOLL SESSION SCOPE: session key {} sends {} wei, over its {} wei cap",
+                session_key, spent, scope.max_value
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Block a send by `session_key` that falls outside its scope.
+pub fn check(config: &Config, session_key: &str, to: &str, value: u128, data: &[u8]) -> Result<(), String> {
+    match scope_of(session_key) {
+        Ok(Some(scope)) => check_at(config, &scope, &session_key.to_lowercase(), to, value, data, now()),
+        Ok(None) => Ok(()),
+        Err(()) => {
+            warn!("Session scope lock poisoned — failing closed");
+            Err("PLIMSOLL SESSION SCOPE: scope table unavailable".into())
+        }
+    }
+}
+
+/// Number of scoped session keys.
+pub fn count() -> usize {
+    SCOPES.lock().map(|s| s.len()).unwrap_or(0)
+}
+
+/// Scoped keys for persistence.
+pub fn snapshot() -> Vec<(String, SessionScope)> {
+    SCOPES
+        .lock()
+        .map(|scopes| scopes.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default()
+}
+
+/// Restore persisted scopes. A scope already set in this process wins.
+pub fn restore(scopes: Vec<(String, SessionScope)>) {
+    if let Ok(mut live) = SCOPES.lock() {
+        for (key, scope) in scopes {
+            live.entry(key.to_lowercase()).or_insert_with(|| scope.normalized());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "0x5e55000000000000000000000000000000000001";
+    const ROUTER: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+    const ATTACKER: &str = "0x6666666666666666666666666666666666666666";
+    /// `swapExactTokensForTokens(uint256,uint256,address[],address,uint256)`
+    const SWAP: [u8; 4] = [0x38, 0xed, 0x17, 0x39];
+
+    fn scope() -> SessionScope {
+        SessionScope {
+            targets: vec![ROUTER.to_uppercase().replace("0X", "0x")],
+            selectors: vec!["0x38ED1739".into()],
+            max_value: "1000".into(),
+            expires_at: 2_000,
+        }
+        .normalized()
+    }
+
+    #[test]
+    fn test_in_scope_send_allowed() {
+        let config = Config::default();
+        assert!(check_at(&config, &scope(), KEY, ROUTER, 1_000, &SWAP, 1_000).is_ok());
+    }
+
+    #[test]
+    fn test_out_of_scope_sends_blocked() {
+        let config = Config::default();
+        let scope = scope();
+        let err = check_at(&config, &scope, KEY, ATTACKER, 0, &SWAP, 1_000).unwrap_err();
+        assert!(err.contains(&format!("may not call {ATTACKER}")));
+        let err = check_at(&config, &scope, KEY, ROUTER, 0, &[0x09, 0x5e, 0xa7, 0xb3], 1_000).unwrap_err();
+        assert!(err.contains("may not call 0x095ea7b3 on"));
+        let err = check_at(&config, &scope, KEY, ROUTER, 1_001, &SWAP, 1_000).unwrap_err();
+        assert!(err.contains("sends 1001 wei, over its 1000 wei cap"));
+        let err = check_at(&config, &scope, KEY, ROUTER, 0, &SWAP, 2_001).unwrap_err();
+        assert!(err.contains("expired at 2000"));
+    }
+
+    #[test]
+    fn test_plain_transfers_need_empty_selector() {
+        let config = Config::default();
+        let scope = SessionScope { selectors: vec!["0x".into()], ..SessionScope::default() };
+        assert!(check_at(&config, &scope, KEY, ATTACKER, 5, &[], 0).is_ok());
+        assert!(check_at(&config, &scope, KEY, ATTACKER, 0, &SWAP, 0).is_err());
+    }
+
+    #[test]
+    fn test_register_scopes_by_chain() {
+        let key = "0x5e55000000000000000000000000000000000002";
+        let config = Config::default();
+        assert!(register(&chains::scope_key(Some(8453), key), scope()));
+        assert!(check(&config, key, ATTACKER, 0, &[]).is_ok());
+        assert!(remove(&chains::scope_key(Some(8453), key)));
+        assert!(!remove(&chains::scope_key(Some(8453), key)));
+        assert!(register(key, scope()));
+        assert!(check(&config, &key.to_uppercase().replace("0X", "0x"), ATTACKER, 0, &[]).is_err());
+    }
+
+    #[test]
+    fn test_rotation_carries_scope_over() {
+        let old = "0x5e55000000000000000000000000000000000003";
+        let new = "0x5e55000000000000000000000000000000000004";
+        assert_eq!(rotate(old, new, None), Ok(None));
+        assert!(register(old, scope()));
+        assert_eq!(rotate(old, new, None), Ok(Some(scope())));
+        assert_eq!(get(new), Some(scope()));
+        assert_eq!(get(old), Some(scope()));
+        let narrower = SessionScope { max_value: "1".into(), ..scope() };
+        assert_eq!(rotate(old, new, Some(narrower.clone())), Ok(Some(narrower.clone())));
+        assert_eq!(get(new), Some(narrower));
+    }
+
+    #[test]
+    fn test_rotation_transactions() {
+        let rotation = OnChainRotation {
+            vault: "0x4444444444444444444444444444444444444444".into(),
+            duration_secs: 86_400,
+            max_single_amount: "1000".into(),
+            daily_budget: "5000".into(),
+        };
+        let txs = rotation_transactions(&rotation, KEY, "0x5e55000000000000000000000000000000000005").unwrap();
+        assert_eq!(txs.len(), 2);
+        assert_eq!(txs[0]["to"], "0x4444444444444444444444444444444444444444");
+        assert_eq!(
+            txs[0]["data"],
+            "0x84f4fc6a0000000000000000000000005e55000000000000000000000000000000000001"
+        );
+        let issue = txs[1]["data"].as_str().unwrap();
+        assert!(issue.starts_with("0xf49932180000000000000000000000005e55000000000000000000000000000000000005"));
+        assert_eq!(issue.len(), 2 + 8 + 4 * 64);
+        assert!(issue.ends_with(&format!("{:064x}", 5000)));
+        let bad = OnChainRotation { daily_budget: "lots".into(), ..rotation };
+        assert!(rotation_transactions(&bad, KEY, KEY).unwrap_err().contains("invalid daily_budget"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(scope().validate().is_ok());
+        assert!(SessionScope { targets: vec!["0x1234".into()], ..SessionScope::default() }.validate().is_err());
+        assert!(SessionScope { selectors: vec!["approve".into()], ..SessionScope::default() }.validate().is_err());
+        assert!(SessionScope { max_value: "1 ether".into(), ..SessionScope::default() }.validate().is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//! Shadow (log-only) mode.
+//!
+//! Before enforcement goes on for a production fleet, operators run the
+//! proxy in shadow to measure false positives: the engines still evaluate
+//! every request, but a would-block decision is recorded instead of
+//! enforced and the traffic goes upstream unchanged.
+//!
+//! - Global (`shadow_mode`, toggled at runtime by `POST /admin/shadow-mode`):
+//!   every engine. The pipeline stops at the first would-block and the
+//!   original request is forwarded.
+//! - Per engine (`shadow_engines`): only the listed engines. The pipeline
+//!   carries on past them, so the engines still enforced decide.
+//!
+//! Would-block decisions are counted in `plimsoll_shadow_blocks_total`,
+//! logged, and the latest are kept for `GET /admin/shadow-verdicts`. Their
+//! IOCs go to the local IOC store only: an unenforced verdict is not shared
+//! with peers or the Swarm, and adds nothing to the local blocklist.
+//!
+//! A few blocks are only shadowed globally: `eip7702`, `eip4844`, `l2` and
+//! `simulation_error` gate how the rest of the request is decoded and
+//! simulated; `chain`, `session_revoked` and `paymaster` decide whether
+//! the agent may send at all, not what it sends; and `sanctions` is a
+//! compliance control.
+
+use crate::block_codes;
+use crate::config::Config;
+use crate::metrics;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Verdicts kept; the oldest go first.
+const MAX_VERDICTS: usize = 1000;
+
+/// Engines `shadow_engines` may name.
+pub const ENGINES: &[&str] = &[
+    "eip712_chain_id",
+    "eip712_deadline",
+    "eip712_phishing",
+    "eip712_permit",
+    "ens",
+    "permit2_details",
+    "seaport",
+    "json_pollution",
+    "function_policy",
+    "approval",
+    "address_poisoning",
+    "first_interaction",
+    "pvg",
+    "gas_fee",
+    "bridge",
+    "session_scope",
+    "target_allowlist",
+    "engine0",
+    "proxy",
+    "honeypot",
+    "rugpull",
+    "oracle",
+    "verification",
+    "physics",
+    "approval_diff",
+    "velocity",
+    "drawdown",
+    "reentrancy",
+    "whitelist",
+    "delegatecall",
+    "metamorphic",
+    "non_determinism",
+    "codehash_pin",
+    "intent_price",
+    "forward_request",
+    "safe",
+    "multicall",
+    "nonce_gap",
+    "replacement",
+];
+
+/// How a would-block decision was shadowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Global,
+    Engine,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShadowVerdict {
+    pub engine: String,
+    pub code: u16,
+    pub reason: String,
+    pub scope: Scope,
+    pub recorded_at: u64,
+}
+
+lazy_static! {
+    static ref VERDICTS: Mutex<VecDeque<ShadowVerdict>> = Mutex::new(VecDeque::new());
+}
+
+/// Engines listed in `shadow_engines`.
+pub fn engines(config: &Config) -> impl Iterator<Item = &str> {
+    config.shadow_engines.split(',').map(str::trim).filter(|e| !e.is_empty())
+}
+
+/// Whether `engine` is shadowed per engine.
+pub fn engine_shadowed(config: &Config, engine: &str) -> bool {
+    engines(config).any(|e| e == engine)
+}
+
+/// Record that `engine` would have blocked for `reason`.
+pub fn record(engine: &str, reason: &str, scope: Scope) {
+    metrics::record_shadow_block(engine);
+    warn!(engine, scope = ?scope, reason = %reason, "SHADOW MODE: would block — forwarding unchanged");
+    let verdict = ShadowVerdict {
+        engine: engine.to_string(),
+        code: block_codes::of(engine).code,
+        reason: reason.to_string(),
+        scope,
+        recorded_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+    };
+    if let Ok(mut verdicts) = VERDICTS.lock() {
+        if verdicts.len() >= MAX_VERDICTS {
+            verdicts.pop_front();
+        }
+        verdicts.push_back(verdict);
+    }
+}
+
+/// Recorded would-block decisions, newest first; only `engine`'s when given.
+pub fn list(engine: Option<&str>) -> Vec<ShadowVerdict> {
+    VERDICTS
+        .lock()
+        .map(|verdicts| {
+            verdicts
+                .iter()
+                .rev()
+                .filter(|v| engine.is_none_or(|e| v.engine == e))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_shadowed() {
+        let config = Config {
+            shadow_engines: " physics, velocity ,".into(),
+            ..Config::default()
+        };
+        assert!(engine_shadowed(&config, "physics"));
+        assert!(engine_shadowed(&config, "velocity"));
+        assert!(!engine_shadowed(&config, "engine0"));
+        assert!(!engine_shadowed(&Config::default(), "physics"));
+    }
+
+    #[test]
+    fn test_engines_have_codes_and_exceptions_stay_global() {
+        for engine in ENGINES {
+            assert_ne!(block_codes::of(engine).code, block_codes::UNKNOWN.code, "{engine}");
+        }
+        for engine in ["eip7702", "eip4844", "l2", "simulation_error", "chain", "session_revoked", "paymaster", "sanctions"] {
+            assert!(!ENGINES.contains(&engine), "{engine}");
+        }
+    }
+
+    #[test]
+    fn test_record_and_list() {
+        record("shadow_test_a", "PLIMSOLL TEST: a", Scope::Engine);
+        record("shadow_test_b", "PLIMSOLL TEST: b", Scope::Global);
+        let all = list(None);
+        let a = all.iter().position(|v| v.engine == "shadow_test_a").unwrap();
+        let b = all.iter().position(|v| v.engine == "shadow_test_b").unwrap();
+        assert!(b < a, "newest first");
+
+        let only = list(Some("shadow_test_b"));
+        assert_eq!(only.len(), 1);
+        assert_eq!(only[0].scope, Scope::Global);
+        assert_eq!(only[0].code, block_codes::UNKNOWN.code);
+    }
+}
@@ -0,0 +1,200 @@
+//! Graceful shutdown.
+//!
+//! A kill mid-attack used to lose protective state written since the last
+//! snapshot (revocations, blocked txs, revert strikes). On `SIGTERM` (or
+//! Ctrl-C) the proxy now:
+//!
+//!   1. reports not ready (`/readyz` → 503) so load balancers drain it,
+//!   2. stops accepting connections on every listener (TCP/TLS, IPC, gRPC),
+//!   3. lets in-flight requests — simulations included — finish, and
+//!      waits for the gRPC server and IPC connections to wind down, for at
+//!      most `shutdown_grace_secs` in all,
+//!   4. stops the mempool revocation watcher, signs a last audit
+//!      checkpoint and flushes the state store,
+//!
+//! and exits. A second signal while draining exits at once, after the flush.
+
+use crate::audit;
+use crate::reload::SharedConfigHandle;
+use crate::rpc;
+use crate::state_store::{self, SharedStateStore};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+static DRAINING: AtomicBool = AtomicBool::new(false);
+/// `shutdown_grace_secs` as it was when the drain began.
+static GRACE_SECS: AtomicU64 = AtomicU64::new(30);
+static STATE_STORE: OnceLock<Option<SharedStateStore>> = OnceLock::new();
+
+/// Set to `true` once the drain begins; `false` → `true` only.
+fn phase() -> &'static watch::Sender<bool> {
+    static PHASE: OnceLock<watch::Sender<bool>> = OnceLock::new();
+    PHASE.get_or_init(|| watch::channel(false).0)
+}
+
+/// Number of tracked tasks still running.
+fn tracked() -> &'static watch::Sender<usize> {
+    static TRACKED: OnceLock<watch::Sender<usize>> = OnceLock::new();
+    TRACKED.get_or_init(|| watch::channel(0).0)
+}
+
+/// Counts a tracked task as finished when dropped, panics included.
+struct Untrack;
+
+impl Drop for Untrack {
+    fn drop(&mut self) {
+        tracked().send_modify(|n| *n -= 1);
+    }
+}
+
+/// Spawn `task` — a listener or one of its connections — so that
+/// [`drained`] waits for it.
+pub fn track<F>(task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tracked().send_modify(|n| *n += 1);
+    let untrack = Untrack;
+    tokio::spawn(async move {
+        let _untrack = untrack;
+        task.await;
+    });
+}
+
+/// Resolves once every tracked task has finished.
+pub async fn drained() {
+    let mut tracked = tracked().subscribe();
+    let _ = tracked.wait_for(|n| *n == 0).await;
+}
+
+/// Whether the proxy is draining for shutdown.
+pub fn draining() -> bool {
+    DRAINING.load(Ordering::SeqCst)
+}
+
+/// Start draining: listeners stop accepting and `/readyz` reports 503.
+/// Only the first call starts the drain and sets its grace period; later
+/// calls are no-ops.
+pub fn begin(grace_secs: u64) {
+    if !DRAINING.swap(true, Ordering::SeqCst) {
+        GRACE_SECS.store(grace_secs, Ordering::SeqCst);
+        info!(grace_secs, "Shutting down: draining in-flight requests");
+        phase().send_replace(true);
+    }
+}
+
+/// Resolves once the drain begins. Listeners pass this as their graceful
+/// shutdown trigger.
+pub async fn signaled() {
+    let mut phase = phase().subscribe();
+    let _ = phase.wait_for(|draining| *draining).await;
+}
+
+/// How long in-flight requests may take once the drain begins.
+pub fn grace() -> Duration {
+    Duration::from_secs(GRACE_SECS.load(Ordering::SeqCst))
+}
+
+/// Resolves when the drain's grace period has run out.
+pub async fn deadline() {
+    signaled().await;
+    tokio::time::sleep(grace()).await;
+}
+
+/// Resolves on `SIGTERM` or Ctrl-C.
+#[cfg(unix)]
+async fn termination() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut term) => {
+            tokio::select! {
+                _ = term.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+        Err(e) => {
+            warn!("Failed to install SIGTERM handler: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn termination() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Begin the drain on the first termination signal; exit (after flushing)
+/// on the second. `state_store` is flushed by [`finish`].
+pub fn spawn_signal_listener(config: SharedConfigHandle, state_store: Option<SharedStateStore>) {
+    let _ = STATE_STORE.set(state_store);
+    tokio::spawn(async move {
+        termination().await;
+        begin(config.current().shutdown_grace_secs);
+        termination().await;
+        warn!("Second termination signal: exiting without waiting for in-flight requests");
+        finish();
+        std::process::exit(1);
+    });
+}
+
+/// Persist everything that must survive the restart. Runs once the
+/// listeners have drained (or the grace period ran out).
+pub fn finish() {
+    rpc::stop_mempool_revocation_watcher();
+    if let Some(seq) = audit::checkpoint() {
+        info!(seq, "Audit log checkpoint signed at shutdown");
+    }
+    if let Some(Some(store)) = STATE_STORE.get() {
+        state_store::flush(store);
+        info!("Proxy state flushed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Puts the process-wide drain state back on drop, so the rest of the
+    /// suite (e.g. `/readyz`) doesn't see a draining proxy.
+    struct Reset;
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            DRAINING.store(false, Ordering::SeqCst);
+            GRACE_SECS.store(30, Ordering::SeqCst);
+            phase().send_replace(false);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_begin_releases_waiters() {
+        let _reset = Reset;
+        let waiter = tokio::spawn(signaled());
+        assert!(!draining());
+        begin(5);
+        begin(60);
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        assert!(draining());
+        // A late waiter resolves at once.
+        tokio::time::timeout(Duration::from_secs(1), signaled()).await.unwrap();
+        // Only the first call sets the grace period.
+        assert_eq!(grace(), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_drained_waits_for_tracked_tasks() {
+        let (done, wait) = tokio::sync::oneshot::channel::<()>();
+        track(async move {
+            let _ = wait.await;
+        });
+        assert!(tokio::time::timeout(Duration::from_millis(50), drained()).await.is_err());
+        done.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), drained()).await.unwrap();
+    }
+}
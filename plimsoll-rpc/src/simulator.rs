@@ -5,12 +5,15 @@
 //! against Plimsoll physics constraints.
 
 use crate::config::Config;
-use crate::types::SimulationResult;
+use crate::inspector::NonDeterminismInspector;
+use crate::portfolio;
+use crate::types::{CallFrame, SimulationResult, SimulationState, StateOverride, StorageChange};
 use alloy_primitives::{Address, U256};
 use anyhow::{Context, Result};
 use revm::{
     db::{CacheDB, EmptyDB},
-    primitives::{AccountInfo, ExecutionResult, TransactTo},
+    inspector_handle_register,
+    primitives::{AccountInfo, Bytecode, ExecutionResult, TransactTo},
     Evm,
 };
 use std::str::FromStr;
@@ -37,12 +40,25 @@ const SIMULATION_TIMEOUT_MS: u64 = 50;
 /// 3. Execute in revm sandbox
 /// 4. Compare pre/post state to compute deltas
 /// 5. Return SimulationResult for physics checking
+///
+/// EIP-7702: `code_override` is executed as the recipient's runtime code.
+/// A SetCode transaction turns the authority EOA into a proxy for the
+/// delegate, so the call must run the *delegate's* code in the authority's
+/// account context.
+///
+/// `state` describes a hypothetical chain: its overrides (geth
+/// `stateOverride` format) are applied on top of the fetched state, and
+/// the sender's pending transactions execute before this one, so it is
+/// judged against the state they leave behind. The upstream call trace
+/// sees the overrides but not the pending transactions.
 pub async fn simulate_transaction(
     config: &Config,
     from: &str,
     to: &str,
     value: u128,
     data: &[u8],
+    code_override: Option<Vec<u8>>,
+    state: &SimulationState,
 ) -> Result<SimulationResult> {
     info!(
         from = from,
@@ -94,6 +110,39 @@ pub async fn simulate_transaction(
         );
     }
 
+    // ── v2.1: Full call trace from the upstream node ────────────
+    // The in-memory sandbox only sees the sender and recipient; the
+    // node's callTracer shows every nested call the transaction makes.
+    let call_trace = if config.reentrancy_detection
+        || config.delegatecall_detection
+        || config.metamorphic_detection
+        || config.portfolio_loss_accounting
+        || !config.vault_target_allowlist.trim().is_empty()
+    {
+        match trace_call(&config.upstream_rpc_url, from, to, value, data, state.overrides.as_ref()).await {
+            Ok(trace) => Some(trace),
+            Err(e) => {
+                warn!(error = %e, "debug_traceCall unavailable — no call trace");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // ── v2.1: Storage writes for the approval-diff engine ──────
+    let storage_changes = if config.block_approval_changes {
+        match trace_storage_diff(&config.upstream_rpc_url, from, to, value, data, state.overrides.as_ref()).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                warn!(error = %e, "prestateTracer unavailable — no storage diff");
+                vec![]
+            }
+        }
+    } else {
+        vec![]
+    };
+
     // ── Step 1: Fetch account state from upstream RPC ──────────
     let sender_balance = fetch_balance(&config.upstream_rpc_url, from).await
         .unwrap_or(U256::from(0));
@@ -118,63 +167,118 @@ pub async fn simulate_transaction(
     cache_db.insert_account_info(sender_addr, sender_info);
 
     // Insert recipient account
+    let (code_hash, code) = match code_override {
+        Some(code) if !code.is_empty() => (
+            alloy_primitives::keccak256(&code),
+            Some(Bytecode::new_raw(code.into())),
+        ),
+        _ => (revm::primitives::KECCAK_EMPTY, None),
+    };
     let recipient_info = AccountInfo {
         balance: recipient_balance,
         nonce: 0,
-        code_hash: revm::primitives::KECCAK_EMPTY,
-        code: None,
+        code_hash,
+        code,
     };
     cache_db.insert_account_info(recipient_addr, recipient_info);
 
-    let balance_before_u128 = sender_balance.try_into().unwrap_or(u128::MAX);
+    if let Some(overrides) = &state.overrides {
+        apply_state_override(&mut cache_db, overrides)?;
+    }
 
     // ── Step 3: Configure revm transaction environment ─────────
     // Zero-Day 1: Clamp gas_limit to SIMULATION_GAS_CEILING.
     // A malicious contract that requests block.gaslimit (30M) gas
     // would peg the CPU for seconds — we cap it at 5M.
     let clamped_gas = std::cmp::min(500_000, SIMULATION_GAS_CEILING);
-    let mut evm = Evm::builder()
-        .with_db(cache_db)
-        .modify_tx_env(|tx| {
-            tx.caller = sender_addr;
-            tx.transact_to = TransactTo::Call(recipient_addr);
-            tx.value = U256::from(value);
-            tx.data = data.to_vec().into();
-            tx.gas_limit = clamped_gas;
-            tx.gas_price = U256::from(20_000_000_000u64); // 20 gwei
-            // ── v1.0.4 Kill-Shot 1: Bundler Illusion Defense ──────────
-            // In ERC-4337, tx.origin is the Bundler (Alchemy/Flashbots),
-            // NOT the agent. A malicious contract checking
-            // `if (tx.origin == BundlerAddr) { drain() }` passes simulation
-            // (where origin == caller) but drains on-chain.
-            //
-            // In revm v17, `tx.caller` maps to both `tx.origin` AND the
-            // top-level `msg.sender`. For ERC-4337 handleOps flow this is
-            // correct: the Bundler IS the EOA that calls EntryPoint.handleOps().
-            // Setting caller to the bundler address makes simulation match
-            // the on-chain reality where tx.origin = bundler.
-            //
-            // Note: This overrides the agent address. The ORIGINAL sender is
-            // preserved in `from` for all other checks (session key, etc.).
-            if !config.bundler_address.is_empty() {
-                if let Ok(bundler_addr) = Address::from_str(&config.bundler_address) {
-                    tx.caller = bundler_addr;
+    // The sandbox isn't Send: it lives in this block, which hands back
+    // plain values before the first await below.
+    let (balance_before_u128, result, sim_elapsed_ms, non_deterministic) = {
+        // Patch 2: the taint inspector watches every opcode for environmental
+        // values reaching a JUMPI condition.
+        let mut evm = Evm::builder()
+            .with_db(cache_db)
+            .with_external_context(NonDeterminismInspector::new())
+            .modify_tx_env(|tx| {
+                tx.caller = sender_addr;
+                tx.transact_to = TransactTo::Call(recipient_addr);
+                tx.value = U256::from(value);
+                tx.data = data.to_vec().into();
+                tx.gas_limit = clamped_gas;
+                tx.gas_price = U256::from(20_000_000_000u64); // 20 gwei
+                // ── v1.0.4 Kill-Shot 1: Bundler Illusion Defense ──────────
+                // In ERC-4337, tx.origin is the Bundler (Alchemy/Flashbots),
+                // NOT the agent. A malicious contract checking
+                // `if (tx.origin == BundlerAddr) { drain() }` passes simulation
+                // (where origin == caller) but drains on-chain.
+                //
+                // In revm v17, `tx.caller` maps to both `tx.origin` AND the
+                // top-level `msg.sender`. For ERC-4337 handleOps flow this is
+                // correct: the Bundler IS the EOA that calls EntryPoint.handleOps().
+                // Setting caller to the bundler address makes simulation match
+                // the on-chain reality where tx.origin = bundler.
+                //
+                // Note: This overrides the agent address. The ORIGINAL sender is
+                // preserved in `from` for all other checks (session key, etc.).
+                if !config.bundler_address.is_empty() {
+                    if let Ok(bundler_addr) = Address::from_str(&config.bundler_address) {
+                        tx.caller = bundler_addr;
+                    }
+                }
+            })
+            .modify_cfg_env(|cfg| {
+                cfg.chain_id = config.chain_id; // v1.0.3 Bounty 3: use configured chain ID
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+
+        // ── v2.1: Pending nonce chain ──────────────────────────────
+        // The sender's earlier, still-unmined transactions run first, so this
+        // one's physics check sees the state they leave behind.
+        if !state.pending.is_empty() {
+            let tx_env = evm.tx().clone();
+            for pending in &state.pending {
+                let Ok(pending_to) = Address::from_str(&pending.to) else {
+                    continue;
+                };
+                let tx = evm.tx_mut();
+                tx.transact_to = TransactTo::Call(pending_to);
+                tx.value = U256::from(pending.value);
+                tx.data = pending.data.clone().into();
+                match evm.transact_commit() {
+                    Ok(r) => info!(nonce = pending.nonce, success = r.is_success(), "Pending transaction replayed"),
+                    Err(e) => warn!(nonce = pending.nonce, error = %e, "Pending transaction failed to replay"),
                 }
             }
-        })
-        .modify_cfg_env(|cfg| {
-            cfg.chain_id = config.chain_id; // v1.0.3 Bounty 3: use configured chain ID
-        })
-        .build();
-
-    // ── Step 4: Execute in sandbox with wall-clock timeout ────
-    // Zero-Day 1: Even with gas capped, certain EVM opcodes
-    // (MODEXP, SHA256 precompile with huge inputs) can be cheap
-    // in gas but expensive in real time. We enforce a hard 50ms
-    // wall-clock deadline.
-    let sim_start = Instant::now();
-    let result = evm.transact_commit();
-    let sim_elapsed_ms = sim_start.elapsed().as_millis() as u64;
+            *evm.tx_mut() = tx_env;
+            evm.context.external.reset();
+        }
+        let sender_balance = evm
+            .db_mut()
+            .accounts
+            .get(&sender_addr)
+            .map_or(sender_balance, |account| account.info.balance);
+        let balance_before_u128 = sender_balance.try_into().unwrap_or(u128::MAX);
+
+        // ── Step 4: Execute in sandbox with wall-clock timeout ────
+        // Zero-Day 1: Even with gas capped, certain EVM opcodes
+        // (MODEXP, SHA256 precompile with huge inputs) can be cheap
+        // in gas but expensive in real time. We enforce a hard 50ms
+        // wall-clock deadline.
+        let sim_start = Instant::now();
+        let result = evm.transact_commit();
+        let sim_elapsed_ms = sim_start.elapsed().as_millis() as u64;
+        let taint = &evm.context.external;
+        let non_deterministic = taint.is_non_deterministic();
+        if non_deterministic {
+            warn!(
+                jumpi_pc = ?taint.jumpi_pc,
+                details = ?taint.detection_details,
+                "Patch 2: environmental value reaches a branch condition"
+            );
+        }
+        (balance_before_u128, result, sim_elapsed_ms, non_deterministic)
+    };
 
     if sim_elapsed_ms > SIMULATION_TIMEOUT_MS {
         warn!(
@@ -198,6 +302,11 @@ pub async fn simulate_transaction(
             target_codehash: target_codehash.clone(),
             non_deterministic: false,
             impl_slot_value: impl_slot_value.clone(),
+            implementation_codehash: String::new(),
+            call_trace: None,
+            asset_deltas: vec![],
+            approval_deltas: vec![],
+            storage_changes: vec![],
         });
     }
 
@@ -228,7 +337,7 @@ pub async fn simulate_transaction(
             };
 
             // Calculate loss percentage
-            let loss_pct = if balance_before_u128 > 0 && success {
+            let mut loss_pct = if balance_before_u128 > 0 && success {
                 let loss = balance_before_u128.saturating_sub(balance_after);
                 (loss as f64 / balance_before_u128 as f64) * 100.0
             } else {
@@ -238,6 +347,23 @@ pub async fn simulate_transaction(
             // Detect approval changes (ERC-20 Approval event signature)
             let approval_changes = detect_approval_changes(&execution_result);
 
+            // ── v2.1: Multi-asset deltas from the trace's events ─────
+            let accounts = portfolio::accounts(config, from);
+            let (asset_deltas, approval_deltas) = call_trace
+                .as_ref()
+                .map(|trace| portfolio::deltas(trace, &accounts))
+                .unwrap_or_default();
+            if config.portfolio_loss_accounting && success {
+                if let Some(pct) = portfolio::loss_pct(
+                    config, &accounts, balance_before_u128, balance_after, &asset_deltas,
+                )
+                .await
+                {
+                    info!(native_loss_pct = loss_pct, portfolio_loss_pct = pct, "Loss computed over portfolio value");
+                    loss_pct = pct;
+                }
+            }
+
             let sim_result = SimulationResult {
                 success,
                 gas_used,
@@ -248,8 +374,13 @@ pub async fn simulate_transaction(
                 error,
                 simulated_block,
                 target_codehash: target_codehash.clone(),
-                non_deterministic: false,
+                non_deterministic,
                 impl_slot_value: impl_slot_value.clone(),
+                implementation_codehash: String::new(),
+                call_trace,
+                asset_deltas,
+                approval_deltas,
+                storage_changes,
             };
 
             info!(
@@ -275,13 +406,70 @@ pub async fn simulate_transaction(
                 target_codehash: target_codehash.clone(),
                 non_deterministic: false,
                 impl_slot_value: impl_slot_value.clone(),
+                implementation_codehash: String::new(),
+                call_trace: None,
+                asset_deltas: vec![],
+                approval_deltas: vec![],
+                storage_changes: vec![],
             })
         }
     }
 }
 
+fn parse_quantity(field: &str, value: &str) -> Result<U256> {
+    U256::from_str_radix(value.trim_start_matches("0x"), 16)
+        .with_context(|| format!("stateOverride: invalid {field} {value:?}"))
+}
+
+/// Write a `stateOverride` into the sandbox, on top of whatever was
+/// fetched for the account. `state` replaces the account's storage — any
+/// slot it doesn't list reads zero — while `stateDiff` patches it; as in
+/// geth, an account may not set both.
+fn apply_state_override(db: &mut CacheDB<EmptyDB>, overrides: &StateOverride) -> Result<()> {
+    for (address, o) in overrides {
+        let addr = Address::from_str(address)
+            .with_context(|| format!("stateOverride: invalid address {address:?}"))?;
+        let mut info = db.accounts.get(&addr).map(|a| a.info.clone()).unwrap_or_default();
+        if let Some(balance) = &o.balance {
+            info.balance = parse_quantity("balance", balance)?;
+        }
+        if let Some(nonce) = &o.nonce {
+            info.nonce = u64::try_from(parse_quantity("nonce", nonce)?)
+                .context("stateOverride: nonce exceeds u64")?;
+        }
+        if let Some(code) = &o.code {
+            let code = hex::decode(code.trim_start_matches("0x"))
+                .context("stateOverride: invalid code hex")?;
+            info.code_hash = if code.is_empty() {
+                revm::primitives::KECCAK_EMPTY
+            } else {
+                alloy_primitives::keccak256(&code)
+            };
+            info.code = Some(Bytecode::new_raw(code.into()));
+        }
+        db.insert_account_info(addr, info);
+        match (&o.state, &o.state_diff) {
+            (Some(_), Some(_)) => anyhow::bail!("stateOverride: {address} sets both state and stateDiff"),
+            (Some(state), None) => {
+                let storage = state
+                    .iter()
+                    .map(|(slot, value)| Ok((parse_quantity("slot", slot)?, parse_quantity("slot value", value)?)))
+                    .collect::<Result<revm::primitives::HashMap<_, _>>>()?;
+                db.replace_account_storage(addr, storage)?;
+            }
+            (None, Some(diff)) => {
+                for (slot, value) in diff {
+                    db.insert_account_storage(addr, parse_quantity("slot", slot)?, parse_quantity("slot value", value)?)?;
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
 /// Fetch the ETH balance of an address via JSON-RPC.
-async fn fetch_balance(rpc_url: &str, address: &str) -> Result<U256> {
+pub async fn fetch_balance(rpc_url: &str, address: &str) -> Result<U256> {
     let client = reqwest::Client::new();
     let payload = serde_json::json!({
         "jsonrpc": "2.0",
@@ -311,7 +499,7 @@ async fn fetch_balance(rpc_url: &str, address: &str) -> Result<U256> {
 
 /// GOD-TIER 3: Fetch the current block number from the upstream RPC.
 /// Used to pin simulations to a specific block for temporal physics enforcement.
-async fn fetch_block_number(rpc_url: &str) -> Result<u64> {
+pub async fn fetch_block_number(rpc_url: &str) -> Result<u64> {
     let client = reqwest::Client::new();
     let payload = serde_json::json!({
         "jsonrpc": "2.0",
@@ -343,9 +531,154 @@ async fn fetch_block_number(rpc_url: &str) -> Result<u64> {
 /// Returns the keccak256 hash of the contract's deployed bytecode, or empty string
 /// if the address is an EOA (no code). This hash is pinned in the simulation result
 /// and enforced on-chain to prevent metamorphic contract attacks.
-async fn fetch_extcodehash(rpc_url: &str, address: &str) -> Result<String> {
+pub async fn fetch_extcodehash(rpc_url: &str, address: &str) -> Result<String> {
+    let code_bytes = fetch_code(rpc_url, address).await?;
+
+    // EOA (no code) → empty codehash (skip pinning)
+    if code_bytes.is_empty() {
+        return Ok(String::new());
+    }
+
+    use alloy_primitives::keccak256;
+    let hash = keccak256(&code_bytes);
+    Ok(format!("0x{}", hex::encode(hash.as_slice())))
+}
+
+/// v2.1: Trace the transaction on the upstream node with geth's
+/// `callTracer`, returning the root call frame.
+pub async fn trace_call(
+    rpc_url: &str,
+    from: &str,
+    to: &str,
+    value: u128,
+    data: &[u8],
+    state_override: Option<&StateOverride>,
+) -> Result<CallFrame> {
+    let client = reqwest::Client::new();
+    let mut tracer = serde_json::json!({
+        "tracer": "callTracer",
+        "tracerConfig": { "withLog": true }
+    });
+    if let Some(overrides) = state_override {
+        tracer["stateOverrides"] = serde_json::to_value(overrides)?;
+    }
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "debug_traceCall",
+        "params": [
+            {
+                "from": from,
+                "to": to,
+                "value": format!("0x{value:x}"),
+                "data": format!("0x{}", hex::encode(data)),
+            },
+            "latest",
+            tracer
+        ],
+        "id": 1
+    });
+
+    let body: serde_json::Value = client
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to call debug_traceCall")?
+        .json()
+        .await
+        .context("Failed to parse debug_traceCall response")?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("debug_traceCall failed: {err}");
+    }
+    serde_json::from_value(body["result"].clone()).context("Invalid callTracer result")
+}
+
+/// v2.1: Storage slots the transaction changes, from geth's
+/// `prestateTracer` in diff mode.
+pub async fn trace_storage_diff(
+    rpc_url: &str,
+    from: &str,
+    to: &str,
+    value: u128,
+    data: &[u8],
+    state_override: Option<&StateOverride>,
+) -> Result<Vec<StorageChange>> {
+    let client = reqwest::Client::new();
+    let mut tracer = serde_json::json!({
+        "tracer": "prestateTracer",
+        "tracerConfig": { "diffMode": true }
+    });
+    if let Some(overrides) = state_override {
+        tracer["stateOverrides"] = serde_json::to_value(overrides)?;
+    }
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "debug_traceCall",
+        "params": [
+            {
+                "from": from,
+                "to": to,
+                "value": format!("0x{value:x}"),
+                "data": format!("0x{}", hex::encode(data)),
+            },
+            "latest",
+            tracer
+        ],
+        "id": 1
+    });
+
+    let body: serde_json::Value = client
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to call debug_traceCall")?
+        .json()
+        .await
+        .context("Failed to parse debug_traceCall response")?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("debug_traceCall failed: {err}");
+    }
+    Ok(parse_storage_diff(&body["result"]))
+}
+
+/// Slots written in a `prestateTracer` diff. Only `post` values are
+/// reported (a slot cleared to zero is absent from `post`); a slot missing
+/// from `pre` was zero.
+fn parse_storage_diff(diff: &serde_json::Value) -> Vec<StorageChange> {
+    let word = |s: &str| U256::from_str_radix(s.trim_start_matches("0x"), 16).ok();
+    let mut changes = Vec::new();
+    let Some(post) = diff["post"].as_object() else {
+        return changes;
+    };
+    for (account, state) in post {
+        let Some(storage) = state["storage"].as_object() else {
+            continue;
+        };
+        for (slot, after) in storage {
+            let (Some(slot_key), Some(after)) = (word(slot), after.as_str().and_then(word)) else {
+                continue;
+            };
+            let before = diff["pre"][account]["storage"][slot]
+                .as_str()
+                .and_then(word)
+                .unwrap_or_default();
+            if before != after {
+                changes.push(StorageChange {
+                    account: account.to_lowercase(),
+                    slot: slot_key,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// Fetch the deployed bytecode at `address` (empty for an EOA).
+pub async fn fetch_code(rpc_url: &str, address: &str) -> Result<Vec<u8>> {
     let client = reqwest::Client::new();
-    // First check if there's code at the address
     let payload = serde_json::json!({
         "jsonrpc": "2.0",
         "method": "eth_getCode",
@@ -367,21 +700,7 @@ async fn fetch_extcodehash(rpc_url: &str, address: &str) -> Result<String> {
         .as_str()
         .unwrap_or("0x");
 
-    // EOA (no code) → empty codehash (skip pinning)
-    if code_hex == "0x" || code_hex.is_empty() {
-        return Ok(String::new());
-    }
-
-    // Compute keccak256 of the bytecode
-    let code_bytes = hex::decode(code_hex.trim_start_matches("0x"))
-        .unwrap_or_default();
-    if code_bytes.is_empty() {
-        return Ok(String::new());
-    }
-
-    use alloy_primitives::keccak256;
-    let hash = keccak256(&code_bytes);
-    Ok(format!("0x{}", hex::encode(hash.as_slice())))
+    Ok(hex::decode(code_hex.trim_start_matches("0x")).unwrap_or_default())
 }
 
 /// v1.0.3 Bounty 2 (Proxy Illusion): EIP-1967 implementation storage slot.
@@ -393,7 +712,7 @@ const EIP1967_IMPL_SLOT: &str =
 /// For transparent/UUPS proxies, this slot holds the implementation address.
 /// Returns the bytes32 value (as hex string), or empty if the slot is zero
 /// (indicating the target is not an EIP-1967 proxy).
-async fn fetch_implementation_slot(rpc_url: &str, address: &str) -> Result<String> {
+pub async fn fetch_implementation_slot(rpc_url: &str, address: &str) -> Result<String> {
     let client = reqwest::Client::new();
     let payload = serde_json::json!({
         "jsonrpc": "2.0",
@@ -425,6 +744,31 @@ async fn fetch_implementation_slot(rpc_url: &str, address: &str) -> Result<Strin
     Ok(slot_value.to_string())
 }
 
+/// `eth_call` `data` on `to` at `block` (`"latest"`, `"finalized"`, ...),
+/// returning the raw return data. Shared by every engine reading contracts.
+pub async fn eth_call(rpc_url: &str, to: &str, data: &[u8], block: &str) -> Result<Vec<u8>> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "eth_call",
+        "params": [{ "to": to, "data": format!("0x{}", hex::encode(data)) }, block],
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .send()
+        .await
+        .context("Failed to call eth_call")?
+        .json()
+        .await
+        .context("Failed to parse eth_call response")?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("eth_call to {to} failed: {err}");
+    }
+    let result = body["result"].as_str().context("eth_call returned no data")?;
+    hex::decode(result.trim_start_matches("0x")).context("eth_call returned invalid hex")
+}
+
 /// Detect ERC-20 Approval events in execution logs.
 ///
 /// The ERC-20 Approval event signature is:
@@ -494,3 +838,90 @@ pub fn check_physics(config: &Config, result: &SimulationResult) -> Result<(), S
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AccountOverride;
+
+    const AGENT: &str = "0xa9e0000000000000000000000000000000000001";
+
+    #[test]
+    fn test_state_override_applied_to_sandbox() {
+        let overrides: StateOverride = serde_json::from_value(serde_json::json!({
+            "0xa9e0000000000000000000000000000000000001": {
+                "balance": "0xde0b6b3a7640000",
+                "nonce": "0x7",
+                "code": "0x6001",
+                "stateDiff": { "0x1": "0x2a" }
+            }
+        }))
+        .unwrap();
+        let mut db = CacheDB::new(EmptyDB::default());
+        apply_state_override(&mut db, &overrides).unwrap();
+
+        let account = &db.accounts[&Address::from_str(AGENT).unwrap()];
+        assert_eq!(account.info.balance, U256::from(1_000_000_000_000_000_000u64));
+        assert_eq!(account.info.nonce, 7);
+        assert_eq!(account.info.code_hash, alloy_primitives::keccak256([0x60, 0x01]));
+        assert_eq!(account.storage[&U256::from(1)], U256::from(42));
+    }
+
+    #[test]
+    fn test_state_override_replaces_storage() {
+        use revm::Database;
+        let addr = Address::from_str(AGENT).unwrap();
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_storage(addr, U256::from(1), U256::from(7)).unwrap();
+        db.insert_account_storage(addr, U256::from(2), U256::from(8)).unwrap();
+
+        let diff: StateOverride =
+            serde_json::from_value(serde_json::json!({ AGENT: { "stateDiff": { "0x1": "0x2a" } } })).unwrap();
+        apply_state_override(&mut db, &diff).unwrap();
+        assert_eq!(db.storage(addr, U256::from(1)).unwrap(), U256::from(42));
+        assert_eq!(db.storage(addr, U256::from(2)).unwrap(), U256::from(8));
+
+        let state: StateOverride =
+            serde_json::from_value(serde_json::json!({ AGENT: { "state": { "0x1": "0x2b" } } })).unwrap();
+        apply_state_override(&mut db, &state).unwrap();
+        assert_eq!(db.storage(addr, U256::from(1)).unwrap(), U256::from(43));
+        assert_eq!(db.storage(addr, U256::from(2)).unwrap(), U256::ZERO);
+
+        let both: StateOverride = serde_json::from_value(serde_json::json!({
+            AGENT: { "state": { "0x1": "0x1" }, "stateDiff": { "0x2": "0x1" } }
+        }))
+        .unwrap();
+        assert!(apply_state_override(&mut db, &both).is_err());
+    }
+
+    #[test]
+    fn test_invalid_state_override_rejected() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        let bad_balance = StateOverride::from([(
+            AGENT.to_string(),
+            AccountOverride { balance: Some("0xzz".into()), ..AccountOverride::default() },
+        )]);
+        assert!(apply_state_override(&mut db, &bad_balance).is_err());
+        let bad_address = StateOverride::from([("0x1234".to_string(), AccountOverride::default())]);
+        assert!(apply_state_override(&mut db, &bad_address).is_err());
+    }
+
+    #[test]
+    fn test_prestate_diff_parsed() {
+        let diff = serde_json::json!({
+            "pre": {
+                "0xToken": { "storage": { "0x01": "0x05", "0x02": "0x07" } }
+            },
+            "post": {
+                "0xToken": { "storage": { "0x01": "0x09", "0x03": "0x01" } },
+                "0xa9e0000000000000000000000000000000000001": { "nonce": 8 }
+            }
+        });
+        let changes = parse_storage_diff(&diff);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].account, "0xtoken");
+        assert_eq!((changes[0].slot, changes[0].before, changes[0].after), (U256::from(1), U256::from(5), U256::from(9)));
+        // A slot absent from `pre` was zero.
+        assert_eq!((changes[1].slot, changes[1].before), (U256::from(3), U256::ZERO));
+    }
+}
@@ -0,0 +1,326 @@
+//! GOD-TIER 1 refinement: Sign-In With Ethereum (EIP-4361) for `personal_sign`.
+//!
+//! `personal_sign` is blanket-blocked because an agent cannot tell a login
+//! challenge from a drain authorization. SIWE login messages, however, have
+//! a rigid machine-checkable structure. A message is let through only if it
+//! parses as EIP-4361 *exactly* (no extra lines, no unknown fields) and:
+//!
+//!   - the domain is on the operator allowlist,
+//!   - the URI authority matches the domain,
+//!   - the address is the signing address,
+//!   - the chain ID matches the proxy's chain,
+//!   - it carries an `Expiration Time` in the near future, and
+//!   - `Not Before` / `Issued At` are not in the future.
+//!
+//! Anything else — freeform text, hex blobs, near-SIWE lookalikes — still
+//! falls through to the raw-sign block.
+
+use crate::config::Config;
+use chrono::{DateTime, Duration, Utc};
+
+const HEADER_SUFFIX: &str = " wants you to sign in with your Ethereum account:";
+
+/// Allowed clock skew for `Issued At` / `Not Before`.
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+
+/// A parsed EIP-4361 message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiweMessage {
+    pub domain: String,
+    pub address: String,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+fn parse_time(field: &str, value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| format!("invalid {field} timestamp '{value}'"))
+}
+
+/// Strictly parse an EIP-4361 message.
+pub fn parse(message: &str) -> Result<SiweMessage, String> {
+    let lines: Vec<&str> = message.split('\n').map(|l| l.trim_end_matches('\r')).collect();
+    let mut i = 0;
+    let next = |i: &mut usize| {
+        let line = lines.get(*i).copied();
+        *i += 1;
+        line
+    };
+
+    let header = next(&mut i).ok_or("empty message")?;
+    let domain_part = header.strip_suffix(HEADER_SUFFIX).ok_or("missing SIWE header")?;
+    // Optional scheme prefix (EIP-4361 allows `https://example.com wants ...`).
+    let domain = domain_part
+        .split_once("://")
+        .map_or(domain_part, |(_, d)| d)
+        .to_string();
+    if domain.is_empty() || domain.contains(char::is_whitespace) {
+        return Err("invalid domain".into());
+    }
+
+    let address = next(&mut i).ok_or("missing address")?.to_string();
+    if address.len() != 42
+        || !address.starts_with("0x")
+        || !address[2..].chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return Err(format!("invalid address '{address}'"));
+    }
+    if next(&mut i) != Some("") {
+        return Err("expected blank line after address".into());
+    }
+
+    // `[ statement LF ] LF`
+    let statement = match next(&mut i) {
+        Some("") => None,
+        Some(s) => {
+            if next(&mut i) != Some("") {
+                return Err("statement must be a single line".into());
+            }
+            Some(s.to_string())
+        }
+        None => return Err("truncated message".into()),
+    };
+
+    let field = |name: &str, required: bool, i: &mut usize| -> Result<Option<String>, String> {
+        let prefix = format!("{name}: ");
+        match lines.get(*i).and_then(|l| l.strip_prefix(prefix.as_str())) {
+            Some(v) => {
+                *i += 1;
+                Ok(Some(v.to_string()))
+            }
+            None if required => Err(format!("missing '{name}'")),
+            None => Ok(None),
+        }
+    };
+
+    let uri = field("URI", true, &mut i)?.unwrap_or_default();
+    let version = field("Version", true, &mut i)?.unwrap_or_default();
+    let chain_id = field("Chain ID", true, &mut i)?
+        .unwrap_or_default()
+        .parse::<u64>()
+        .map_err(|_| "invalid Chain ID")?;
+    let nonce = field("Nonce", true, &mut i)?.unwrap_or_default();
+    if nonce.len() < 8 || !nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err("nonce must be at least 8 alphanumeric characters".into());
+    }
+    let issued_at = parse_time("Issued At", &field("Issued At", true, &mut i)?.unwrap_or_default())?;
+    let expiration_time = field("Expiration Time", false, &mut i)?
+        .map(|v| parse_time("Expiration Time", &v))
+        .transpose()?;
+    let not_before = field("Not Before", false, &mut i)?
+        .map(|v| parse_time("Not Before", &v))
+        .transpose()?;
+    let request_id = field("Request ID", false, &mut i)?;
+
+    let mut resources = Vec::new();
+    if lines.get(i) == Some(&"Resources:") {
+        i += 1;
+        while let Some(r) = lines.get(i).and_then(|l| l.strip_prefix("- ")) {
+            resources.push(r.to_string());
+            i += 1;
+        }
+    }
+
+    // Nothing may follow (a single trailing newline is tolerated).
+    let rest = &lines[i.min(lines.len())..];
+    if !(rest.is_empty() || rest == [""]) {
+        return Err("unexpected trailing content".into());
+    }
+
+    Ok(SiweMessage {
+        domain,
+        address,
+        statement,
+        uri,
+        version,
+        chain_id,
+        nonce,
+        issued_at,
+        expiration_time,
+        not_before,
+        request_id,
+        resources,
+    })
+}
+
+/// Decode a `personal_sign` payload: `0x`-hex of UTF-8, or a plain string.
+pub fn decode_personal_message(data: &str) -> Option<String> {
+    match data.strip_prefix("0x") {
+        Some(h) => hex::decode(h).ok().and_then(|b| String::from_utf8(b).ok()),
+        None => Some(data.to_string()),
+    }
+}
+
+/// Validate a parsed SIWE message against operator policy.
+pub fn validate(
+    config: &Config,
+    msg: &SiweMessage,
+    signer: &str,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let allowed: Vec<String> = config
+        .siwe_allowed_domains
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let domain = msg.domain.to_lowercase();
+    if !allowed.contains(&domain) {
+        return Err(format!("domain '{}' is not on the SIWE allowlist", msg.domain));
+    }
+
+    let authority = msg
+        .uri
+        .split_once("://")
+        .map_or("", |(_, rest)| rest.split(['/', '?', '#']).next().unwrap_or(""));
+    if !authority.eq_ignore_ascii_case(&domain) {
+        return Err(format!("URI '{}' does not match domain '{}'", msg.uri, msg.domain));
+    }
+
+    if !msg.address.eq_ignore_ascii_case(signer) {
+        return Err(format!("message address {} is not the signer {}", msg.address, signer));
+    }
+    if msg.version != "1" {
+        return Err(format!("unsupported SIWE version '{}'", msg.version));
+    }
+
+    let expected_chain = if config.expected_chain_id != 0 {
+        config.expected_chain_id
+    } else {
+        config.chain_id
+    };
+    if msg.chain_id != expected_chain {
+        return Err(format!("chain ID {} != expected {}", msg.chain_id, expected_chain));
+    }
+
+    let skew = Duration::seconds(MAX_CLOCK_SKEW_SECS);
+    if msg.issued_at > now + skew {
+        return Err("Issued At is in the future".into());
+    }
+    if let Some(nb) = msg.not_before {
+        if nb > now + skew {
+            return Err("message is not valid yet (Not Before)".into());
+        }
+    }
+    let expiry = msg.expiration_time.ok_or("Expiration Time is required")?;
+    if expiry <= now {
+        return Err("message has expired".into());
+    }
+    if config.siwe_max_validity_secs > 0
+        && expiry > now + Duration::seconds(config.siwe_max_validity_secs as i64)
+    {
+        return Err(format!(
+            "expiration is more than {}s in the future",
+            config.siwe_max_validity_secs
+        ));
+    }
+    Ok(())
+}
+
+/// Check a `personal_sign` request (`[data, address]`). `Ok(domain)` if it
+/// is a compliant SIWE login, `Err(reason)` otherwise.
+pub fn check_personal_sign(
+    config: &Config,
+    params: &serde_json::Value,
+    now: DateTime<Utc>,
+) -> Result<String, String> {
+    if config.siwe_allowed_domains.trim().is_empty() {
+        return Err("SIWE allowlist is empty".into());
+    }
+    let data = params.get(0).and_then(|v| v.as_str()).ok_or("missing message")?;
+    let signer = params.get(1).and_then(|v| v.as_str()).ok_or("missing signer")?;
+    let text = decode_personal_message(data).ok_or("message is not UTF-8")?;
+    let msg = parse(&text)?;
+    validate(config, &msg, signer, now)?;
+    Ok(msg.domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNER: &str = "0x1111111111111111111111111111111111111111";
+
+    fn message(expiration: &str) -> String {
+        format!(
+            "app.example.com wants you to sign in with your Ethereum account:\n\
+             {SIGNER}\n\
+             \n\
+             Sign in to Example.\n\
+             \n\
+             URI: https://app.example.com/login\n\
+             Version: 1\n\
+             Chain ID: 1\n\
+             Nonce: abcdef1234\n\
+             Issued At: 2026-01-01T00:00:00Z\n\
+             Expiration Time: {expiration}"
+        )
+    }
+
+    fn config() -> Config {
+        Config {
+            siwe_allowed_domains: "app.example.com".into(),
+            siwe_max_validity_secs: 3600,
+            ..Config::default()
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        parse_time("now", "2026-01-01T00:05:00Z").unwrap()
+    }
+
+    #[test]
+    fn test_parse_valid() {
+        let msg = parse(&message("2026-01-01T00:30:00Z")).unwrap();
+        assert_eq!(msg.domain, "app.example.com");
+        assert_eq!(msg.statement.as_deref(), Some("Sign in to Example."));
+        assert_eq!(msg.chain_id, 1);
+    }
+
+    #[test]
+    fn test_compliant_login_allowed() {
+        let msg = parse(&message("2026-01-01T00:30:00Z")).unwrap();
+        assert!(validate(&config(), &msg, SIGNER, now()).is_ok());
+    }
+
+    #[test]
+    fn test_hex_encoded_personal_sign() {
+        let data = format!("0x{}", hex::encode(message("2026-01-01T00:30:00Z")));
+        let params = serde_json::json!([data, SIGNER]);
+        assert_eq!(check_personal_sign(&config(), &params, now()).unwrap(), "app.example.com");
+    }
+
+    #[test]
+    fn test_rejections() {
+        let cfg = config();
+        // Expired
+        let msg = parse(&message("2026-01-01T00:01:00Z")).unwrap();
+        assert!(validate(&cfg, &msg, SIGNER, now()).is_err());
+        // Too far in the future
+        let msg = parse(&message("2026-02-01T00:00:00Z")).unwrap();
+        assert!(validate(&cfg, &msg, SIGNER, now()).is_err());
+        // Wrong signer
+        let msg = parse(&message("2026-01-01T00:30:00Z")).unwrap();
+        assert!(validate(&cfg, &msg, "0x2222222222222222222222222222222222222222", now()).is_err());
+        // Domain not allowlisted
+        let mut other = cfg.clone();
+        other.siwe_allowed_domains = "other.example.com".into();
+        assert!(validate(&other, &msg, SIGNER, now()).is_err());
+    }
+
+    #[test]
+    fn test_freeform_and_trailing_payload_rejected() {
+        assert!(parse("Please sign to approve 0xdead").is_err());
+        let smuggled = format!("{}\nPermit: MAX_UINT", message("2026-01-01T00:30:00Z"));
+        assert!(parse(&smuggled).is_err());
+    }
+}
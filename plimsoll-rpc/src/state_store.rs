@@ -0,0 +1,446 @@
+//! Persistent Proxy State — protective state that survives restarts.
+//!
+//! The security patches in `rpc.rs` keep their state in process memory:
+//!   - Zero-Day 2: pessimistically revoked session keys
+//!   - Patch 4: blocked tx hashes (for synthetic receipts)
+//!   - v1.0.2 Patch 4: revert strike timestamps + paymaster-severed flag
+//!   - v2.1: per-agent counterparty history (poisoning + first-interaction checks)
+//!   - v2.1: session key scopes
+//!
+//! A restart (deploy, OOM, crash) wipes all of it, reopening the exact
+//! windows the patches close: a revoked key becomes usable again, a severed
+//! Paymaster is reconnected, and the agent's receipt polls for blocked txs
+//! return `null`.
+//!
+//! This module snapshots that state into a [`StateStore`] on a fixed
+//! interval and restores it on startup.
+//!
+//! ## Architecture
+//!
+//! ```text
+//! rpc.rs statics ──snapshot_state()──▶ ProxyStateSnapshot ──save()──▶ StateStore
+//!       ▲                                                               │
+//!       └──────────restore_state()◀──────── load() (startup) ◀──────────┘
+//! ```
+
+use crate::false_positives::FalsePositiveReport;
+use crate::fee::PendingSettlement;
+use crate::session_scopes::SessionScope;
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// Point-in-time copy of all protective proxy state.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProxyStateSnapshot {
+    /// Zero-Day 2: Session keys revoked from the mempool (lowercase).
+    pub revoked_session_keys: Vec<String>,
+    /// v2.1: Revocations above still awaiting on-chain confirmation, with
+    /// the unix time each was first seen.
+    #[serde(default)]
+    pub pending_revocations: Vec<(String, u64)>,
+    /// Patch 4: Synthetic tx hash → block reason.
+    pub blocked_txs: Vec<(String, String)>,
+    /// v1.0.2 Patch 4: Unix timestamps of recorded revert strikes.
+    pub revert_strikes: Vec<u64>,
+    /// v1.0.2 Patch 4: Whether the Paymaster connection is severed.
+    pub paymaster_severed: bool,
+    /// v2.1: `(agent, counterparty)` pairs seen in forwarded transactions.
+    #[serde(default)]
+    pub counterparties: Vec<(String, String)>,
+    /// v2.1: Session key → scope.
+    #[serde(default)]
+    pub session_scopes: Vec<(String, SessionScope)>,
+    /// v2.1: False-positive reports, oldest first.
+    #[serde(default)]
+    pub false_positive_reports: Vec<FalsePositiveReport>,
+    /// v2.1: `(agent, asset, amount)` protocol fees owed; agent is scoped
+    /// to its chain like other keys, asset is `native` or an ERC-20, amount
+    /// decimal.
+    #[serde(default)]
+    pub fees_owed: Vec<(String, String, String)>,
+    /// v2.1: Synthetic tx hash → engine that blocked it (its block code).
+    #[serde(default)]
+    pub blocked_tx_engines: Vec<(String, String)>,
+    /// v2.1: Signed fee settlements whose broadcast outcome is unknown.
+    #[serde(default)]
+    pub fee_settlements: Vec<PendingSettlement>,
+}
+
+/// Backend that can persist and restore a [`ProxyStateSnapshot`].
+///
+/// Implementations must be safe to call from any request handler thread.
+pub trait StateStore: Send + Sync {
+    /// Load the most recently saved snapshot (empty if none was saved).
+    fn load(&self) -> Result<ProxyStateSnapshot>;
+
+    /// Replace the stored state with `snapshot`.
+    fn save(&self, snapshot: &ProxyStateSnapshot) -> Result<()>;
+}
+
+/// Thread-safe handle to the configured state store.
+pub type SharedStateStore = Arc<dyn StateStore>;
+
+/// SQLite-backed state store (single file, no external service).
+pub struct SqliteStateStore {
+    conn: Mutex<Connection>,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS revoked_session_keys (
+        session_key TEXT PRIMARY KEY
+    );
+    CREATE TABLE IF NOT EXISTS pending_revocations (
+        session_key TEXT PRIMARY KEY,
+        seen_at     INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS blocked_txs (
+        tx_hash TEXT PRIMARY KEY,
+        reason  TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS revert_strikes (
+        ts INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS proxy_flags (
+        name  TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS counterparties (
+        agent        TEXT NOT NULL,
+        counterparty TEXT NOT NULL,
+        PRIMARY KEY (agent, counterparty)
+    );
+    CREATE TABLE IF NOT EXISTS session_scopes (
+        session_key TEXT PRIMARY KEY,
+        scope       TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS false_positive_reports (
+        seq    INTEGER PRIMARY KEY,
+        report TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS fees_owed (
+        agent  TEXT NOT NULL,
+        asset  TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        PRIMARY KEY (agent, asset)
+    );
+    CREATE TABLE IF NOT EXISTS blocked_tx_engines (
+        tx_hash TEXT PRIMARY KEY,
+        engine  TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS fee_settlements (
+        agent      TEXT NOT NULL,
+        asset      TEXT NOT NULL,
+        settlement TEXT NOT NULL,
+        PRIMARY KEY (agent, asset)
+    );
+";
+
+impl SqliteStateStore {
+    /// Open (or create) the state database at `path`.
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open state database {path}"))?;
+        Self::init(conn)
+    }
+
+    /// Open a throwaway in-memory database (used in tests).
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory().context("Failed to open in-memory state db")?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("Failed to initialize state schema")?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+}
+
+impl StateStore for SqliteStateStore {
+    fn load(&self) -> Result<ProxyStateSnapshot> {
+        let conn = self.conn.lock()
+            .map_err(|_| anyhow::anyhow!("State store lock poisoned"))?;
+        let mut snapshot = ProxyStateSnapshot::default();
+
+        let mut stmt = conn.prepare("SELECT session_key FROM revoked_session_keys")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        for row in rows {
+            snapshot.revoked_session_keys.push(row?);
+        }
+
+        let mut stmt = conn.prepare("SELECT session_key, seen_at FROM pending_revocations")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?.max(0) as u64))
+        })?;
+        for row in rows {
+            snapshot.pending_revocations.push(row?);
+        }
+
+        let mut stmt = conn.prepare("SELECT tx_hash, reason FROM blocked_txs")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            snapshot.blocked_txs.push(row?);
+        }
+
+        let mut stmt = conn.prepare("SELECT ts FROM revert_strikes ORDER BY ts ASC")?;
+        let rows = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+        for row in rows {
+            snapshot.revert_strikes.push(row?.max(0) as u64);
+        }
+
+        let mut stmt = conn.prepare("SELECT value FROM proxy_flags WHERE name = 'paymaster_severed'")?;
+        let mut rows = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+        if let Some(value) = rows.next() {
+            snapshot.paymaster_severed = value? != 0;
+        }
+
+        let mut stmt = conn.prepare("SELECT agent, counterparty FROM counterparties")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            snapshot.counterparties.push(row?);
+        }
+
+        let mut stmt = conn.prepare("SELECT session_key, scope FROM session_scopes")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (key, scope) = row?;
+            let scope = serde_json::from_str(&scope)
+                .with_context(|| format!("Invalid stored scope of session key {key}"))?;
+            snapshot.session_scopes.push((key, scope));
+        }
+
+        let mut stmt = conn.prepare("SELECT report FROM false_positive_reports ORDER BY seq")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        for row in rows {
+            let report = serde_json::from_str(&row?).context("Invalid stored false-positive report")?;
+            snapshot.false_positive_reports.push(report);
+        }
+
+        let mut stmt = conn.prepare("SELECT agent, asset, amount FROM fees_owed")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?;
+        for row in rows {
+            snapshot.fees_owed.push(row?);
+        }
+
+        let mut stmt = conn.prepare("SELECT tx_hash, engine FROM blocked_tx_engines")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            snapshot.blocked_tx_engines.push(row?);
+        }
+
+        let mut stmt = conn.prepare("SELECT settlement FROM fee_settlements")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        for row in rows {
+            let settlement = serde_json::from_str(&row?).context("Invalid stored fee settlement")?;
+            snapshot.fee_settlements.push(settlement);
+        }
+
+        Ok(snapshot)
+    }
+
+    fn save(&self, snapshot: &ProxyStateSnapshot) -> Result<()> {
+        let mut conn = self.conn.lock()
+            .map_err(|_| anyhow::anyhow!("State store lock poisoned"))?;
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM revoked_session_keys", [])?;
+        for key in &snapshot.revoked_session_keys {
+            tx.execute(
+                "INSERT OR REPLACE INTO revoked_session_keys (session_key) VALUES (?1)",
+                rusqlite::params![key],
+            )?;
+        }
+
+        tx.execute("DELETE FROM pending_revocations", [])?;
+        for (key, seen_at) in &snapshot.pending_revocations {
+            tx.execute(
+                "INSERT OR REPLACE INTO pending_revocations (session_key, seen_at) VALUES (?1, ?2)",
+                rusqlite::params![key, *seen_at as i64],
+            )?;
+        }
+
+        tx.execute("DELETE FROM blocked_txs", [])?;
+        for (hash, reason) in &snapshot.blocked_txs {
+            tx.execute(
+                "INSERT OR REPLACE INTO blocked_txs (tx_hash, reason) VALUES (?1, ?2)",
+                rusqlite::params![hash, reason],
+            )?;
+        }
+
+        tx.execute("DELETE FROM revert_strikes", [])?;
+        for ts in &snapshot.revert_strikes {
+            tx.execute(
+                "INSERT INTO revert_strikes (ts) VALUES (?1)",
+                rusqlite::params![*ts as i64],
+            )?;
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO proxy_flags (name, value) VALUES ('paymaster_severed', ?1)",
+            rusqlite::params![snapshot.paymaster_severed as i64],
+        )?;
+
+        tx.execute("DELETE FROM counterparties", [])?;
+        for (agent, counterparty) in &snapshot.counterparties {
+            tx.execute(
+                "INSERT OR REPLACE INTO counterparties (agent, counterparty) VALUES (?1, ?2)",
+                rusqlite::params![agent, counterparty],
+            )?;
+        }
+
+        tx.execute("DELETE FROM session_scopes", [])?;
+        for (key, scope) in &snapshot.session_scopes {
+            tx.execute(
+                "INSERT OR REPLACE INTO session_scopes (session_key, scope) VALUES (?1, ?2)",
+                rusqlite::params![key, serde_json::to_string(scope)?],
+            )?;
+        }
+
+        tx.execute("DELETE FROM false_positive_reports", [])?;
+        for (seq, report) in snapshot.false_positive_reports.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO false_positive_reports (seq, report) VALUES (?1, ?2)",
+                rusqlite::params![seq as i64, serde_json::to_string(report)?],
+            )?;
+        }
+
+        tx.execute("DELETE FROM fees_owed", [])?;
+        for (agent, asset, amount) in &snapshot.fees_owed {
+            tx.execute(
+                "INSERT OR REPLACE INTO fees_owed (agent, asset, amount) VALUES (?1, ?2, ?3)",
+                rusqlite::params![agent, asset, amount],
+            )?;
+        }
+
+        tx.execute("DELETE FROM blocked_tx_engines", [])?;
+        for (hash, engine) in &snapshot.blocked_tx_engines {
+            tx.execute(
+                "INSERT OR REPLACE INTO blocked_tx_engines (tx_hash, engine) VALUES (?1, ?2)",
+                rusqlite::params![hash, engine],
+            )?;
+        }
+
+        tx.execute("DELETE FROM fee_settlements", [])?;
+        for settlement in &snapshot.fee_settlements {
+            tx.execute(
+                "INSERT OR REPLACE INTO fee_settlements (agent, asset, settlement) VALUES (?1, ?2, ?3)",
+                rusqlite::params![settlement.agent, settlement.asset, serde_json::to_string(settlement)?],
+            )?;
+        }
+
+        tx.commit().context("Failed to commit state snapshot")?;
+        Ok(())
+    }
+}
+
+/// Open the configured state store. Empty path = persistence disabled.
+pub fn open_store(path: &str) -> Result<Option<SharedStateStore>> {
+    if path.is_empty() {
+        return Ok(None);
+    }
+    let store = SqliteStateStore::open(path)?;
+    Ok(Some(Arc::new(store)))
+}
+
+/// Snapshot the live `rpc.rs` state into `store` once.
+pub fn flush(store: &SharedStateStore) {
+    let snapshot = crate::rpc::snapshot_state();
+    if let Err(e) = store.save(&snapshot) {
+        warn!("Failed to persist proxy state: {:#}", e);
+    }
+}
+
+/// Spawn the background task that persists the protective state
+/// every `interval_secs` seconds.
+pub fn spawn_snapshot_task(store: SharedStateStore, interval_secs: u64) {
+    let interval = std::time::Duration::from_secs(interval_secs.max(1));
+    tokio::spawn(async move {
+        info!(interval_secs = interval.as_secs(), "Proxy state snapshot task started");
+        loop {
+            tokio::time::sleep(interval).await;
+            flush(&store);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_snapshot() -> ProxyStateSnapshot {
+        ProxyStateSnapshot {
+            revoked_session_keys: vec!["0xdeadkey".into(), "0xpendingkey".into()],
+            pending_revocations: vec![("0xpendingkey".into(), 1_700_000_000)],
+            blocked_txs: vec![("0xplimsoll01".into(), "ENGINE 0: blacklisted".into())],
+            revert_strikes: vec![1_700_000_000, 1_700_000_010],
+            paymaster_severed: true,
+            counterparties: vec![("0xagent".into(), "0xcounterparty".into())],
+            session_scopes: vec![(
+                "8453:0xsessionkey".into(),
+                SessionScope { selectors: vec!["0x38ed1739".into()], expires_at: 1_700_003_600, ..SessionScope::default() },
+            )],
+            false_positive_reports: vec![FalsePositiveReport {
+                id: "fp-1".into(),
+                subject: "0xcounterparty".into(),
+                note: "our treasury".into(),
+                block_reason: None,
+                entries: vec!["0xcounterparty".into()],
+                reported_at: 1_700_000_000,
+                whitelisted_until: Some(1_700_003_600),
+                status: crate::false_positives::ReportStatus::Pending,
+            }],
+            fees_owed: vec![("0xagent".into(), "native".into(), "200000000000000".into())],
+            blocked_tx_engines: vec![("0xplimsoll01".into(), "engine0".into())],
+            fee_settlements: vec![PendingSettlement {
+                agent: "0xagent".into(),
+                asset: "native".into(),
+                amount: "200000000000000".into(),
+                nonce: 12,
+                tx_hash: "0xfee1".into(),
+                raw_tx: "0x02f86f".into(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_empty_store_loads_default() {
+        let store = SqliteStateStore::in_memory().unwrap();
+        assert_eq!(store.load().unwrap(), ProxyStateSnapshot::default());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let store = SqliteStateStore::in_memory().unwrap();
+        let snapshot = sample_snapshot();
+        store.save(&snapshot).unwrap();
+        assert_eq!(store.load().unwrap(), snapshot);
+    }
+
+    #[test]
+    fn test_save_replaces_previous_snapshot() {
+        let store = SqliteStateStore::in_memory().unwrap();
+        store.save(&sample_snapshot()).unwrap();
+        store.save(&ProxyStateSnapshot::default()).unwrap();
+        let loaded = store.load().unwrap();
+        assert!(loaded.revoked_session_keys.is_empty());
+        assert!(loaded.blocked_txs.is_empty());
+        assert!(!loaded.paymaster_severed);
+    }
+
+    #[test]
+    fn test_open_store_disabled_when_empty() {
+        assert!(open_store("").unwrap().is_none());
+    }
+}
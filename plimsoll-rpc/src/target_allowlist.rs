@@ -0,0 +1,255 @@
+//! Proxy-level target allowlist.
+//!
+//! With `target_allowlist` set, a send (or a meta-transaction's wrapped
+//! call) is only forwarded when every contract it ultimately calls is
+//! listed. The ultimate targets are decoded before simulation, so a
+//! rejected send costs no simulator time:
+//!
+//!   - `execute` / `executeWithCosign` on a vault → the vault's target,
+//!   - Safe `execTransaction` → every (MultiSend-flattened) operation,
+//!   - multicall batches → every leaf call,
+//!
+//! recursively. The agent's own vaults are always allowed. With
+//! `target_allowlist_from_vaults` the list mirrors the vaults'
+//! `TargetWhitelistModule`s (see `vault_sync`).
+//!
+//! Signatures are held to the same list: an EIP-712 message's
+//! `verifyingContract` and the `spender` / `operator` it authorizes must
+//! be listed, and `eth_sign` — a bare hash with no target to check — is
+//! refused outright.
+
+use crate::abi;
+use crate::config::{parse_list, Config};
+use crate::multicall;
+use crate::safe::{self, SafeCall};
+use crate::types::InnerCall;
+
+/// `execute(address,uint256,bytes)`
+const EXECUTE: [u8; 4] = [0xb6, 0x1d, 0x27, 0xf6];
+/// `executeWithCosign(address,uint256,bytes,bytes32,uint256,uint256,bytes32,bytes32,uint8,bytes32,bytes32)`
+const EXECUTE_WITH_COSIGN: [u8; 4] = [0xbf, 0xb6, 0x3a, 0xf8];
+
+/// Maximum wrapper nesting depth.
+const MAX_DEPTH: usize = 8;
+
+/// The call a vault `execute` wraps, if `data` is one sent to a vault.
+fn vault_call(vaults: &[String], to: &str, data: &[u8]) -> Option<InnerCall> {
+    let selector = abi::selector(data)?;
+    if !vaults.contains(&to.to_lowercase()) || (selector != EXECUTE && selector != EXECUTE_WITH_COSIGN) {
+        return None;
+    }
+    let args = &data[4..];
+    Some(InnerCall {
+        from: to.to_lowercase(),
+        to: abi::address(args, 0).ok()?,
+        value: u128::try_from(abi::uint(args, 1).ok()?).unwrap_or(u128::MAX),
+        data: abi::bytes(args, 2).ok()?,
+    })
+}
+
+fn collect(config: &Config, vaults: &[String], call: InnerCall, depth: usize, out: &mut Vec<InnerCall>) {
+    if depth < MAX_DEPTH {
+        if let Some(inner) = vault_call(vaults, &call.to, &call.data) {
+            return collect(config, vaults, inner, depth + 1, out);
+        }
+        if let Ok(Some(SafeCall::Exec(ops))) = safe::decode(config, &call.to, &call.data) {
+            for op in ops {
+                collect(config, vaults, op.call, depth + 1, out);
+            }
+            return;
+        }
+        if let Ok(Some(calls)) = multicall::decode(&call.to, &call.data) {
+            for inner in calls {
+                collect(config, vaults, inner, depth + 1, out);
+            }
+            return;
+        }
+    }
+    out.push(InnerCall { to: call.to.to_lowercase(), ..call });
+}
+
+/// The leaf calls a call from `from` to `to` ultimately makes.
+pub fn ultimate_calls(config: &Config, from: &str, to: &str, value: u128, data: &[u8]) -> Vec<InnerCall> {
+    let call = InnerCall { from: from.to_lowercase(), to: to.to_string(), value, data: data.to_vec() };
+    let mut out = Vec::new();
+    collect(config, &parse_list(&config.vault_addresses), call, 0, &mut out);
+    out
+}
+
+/// Every contract a call to `to` with `data` ultimately reaches.
+pub fn ultimate_targets(config: &Config, to: &str, data: &[u8]) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for call in ultimate_calls(config, "", to, 0, data) {
+        if !targets.contains(&call.to) {
+            targets.push(call.to);
+        }
+    }
+    targets
+}
+
+/// Fields of an EIP-712 message naming who the signature empowers.
+const SIGNED_GRANTEES: &[&str] = &["spender", "operator"];
+
+fn allowed(config: &Config) -> Vec<String> {
+    let mut allowed = parse_list(&config.target_allowlist);
+    if !allowed.is_empty() {
+        allowed.extend(parse_list(&config.vault_addresses));
+    }
+    allowed
+}
+
+/// Block typed data whose verifying contract or grantee is not on
+/// `target_allowlist`.
+pub fn check_typed_data(config: &Config, typed_data: &serde_json::Value) -> Result<(), String> {
+    let allowed = allowed(config);
+    if allowed.is_empty() {
+        return Ok(());
+    }
+    let domain = typed_data.get("domain").and_then(|d| d.get("verifyingContract"));
+    let message = typed_data.get("message");
+    let parties = domain.map(|v| ("verifying contract", v)).into_iter().chain(
+        SIGNED_GRANTEES
+            .iter()
+            .filter_map(|k| message.and_then(|m| m.get(*k)).map(|v| (*k, v))),
+    );
+    for (role, value) in parties {
+        let address = value.as_str().unwrap_or_default().to_lowercase();
+        if !allowed.contains(&address) {
+            return Err(format!(
+                "PLIMSOLL TARGET: typed data {role} {address} is not on the target allowlist"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Block `eth_sign` while `target_allowlist` is set: the hash it signs
+/// names no target to check.
+pub fn check_raw_sign(config: &Config) -> Result<(), String> {
+    if allowed(config).is_empty() {
+        return Ok(());
+    }
+    Err("PLIMSOLL TARGET: eth_sign signs a bare hash whose target can't be checked \
+         against the target allowlist"
+        .into())
+}
+
+/// Block a call whose ultimate targets are not all on `target_allowlist`.
+pub fn check(config: &Config, to: &str, data: &[u8]) -> Result<(), String> {
+    let allowed = allowed(config);
+    if allowed.is_empty() {
+        return Ok(());
+    }
+    match ultimate_targets(config, to, data).into_iter().find(|t| !allowed.contains(t)) {
+        Some(target) if target.eq_ignore_ascii_case(to) => {
+            Err(format!("PLIMSOLL TARGET: {target} is not on the target allowlist"))
+        }
+        Some(target) => Err(format!(
+            "PLIMSOLL TARGET: call to {} reaches {}, which is not on the target allowlist",
+            to.to_lowercase(),
+            target
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = "0x4444444444444444444444444444444444444444";
+    const ROUTER: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+    const ATTACKER: &str = "0x6666666666666666666666666666666666666666";
+    /// Multicall3
+    const MULTICALL: &str = "0xca11bde05977b3631167028862be2a173976ca11";
+
+    fn config() -> Config {
+        Config {
+            target_allowlist: ROUTER.to_uppercase().replace("0X", "0x"),
+            vault_addresses: VAULT.into(),
+            ..Config::default()
+        }
+    }
+
+    fn execute(target: &str, inner: &[u8]) -> Vec<u8> {
+        let mut data = EXECUTE.to_vec();
+        data.extend(abi::encode_address(target).unwrap());
+        data.extend(abi::encode_uint(0));
+        data.extend(abi::encode_uint(96));
+        data.extend(abi::encode_uint(inner.len() as u64));
+        let mut padded = inner.to_vec();
+        padded.resize(inner.len().div_ceil(32) * 32, 0);
+        data.extend(padded);
+        data
+    }
+
+    /// `aggregate((address,bytes)[])` with one call per target.
+    fn aggregate(targets: &[&str]) -> Vec<u8> {
+        let mut data = vec![0x25, 0x2d, 0xba, 0x42];
+        data.extend(abi::encode_uint(32));
+        data.extend(abi::encode_uint(targets.len() as u64));
+        let mut heads = Vec::new();
+        let mut tails = Vec::new();
+        for target in targets {
+            heads.extend(abi::encode_uint((targets.len() * 32 + tails.len()) as u64));
+            tails.extend(abi::encode_address(target).unwrap());
+            tails.extend(abi::encode_uint(64));
+            tails.extend(abi::encode_uint(0));
+        }
+        data.extend(heads);
+        data.extend(tails);
+        data
+    }
+
+    #[test]
+    fn test_direct_target() {
+        let config = config();
+        assert!(check(&config, ROUTER, &[]).is_ok());
+        assert!(check(&config, VAULT, &[]).is_ok());
+        assert!(check(&Config::default(), ATTACKER, &[]).is_ok());
+        let err = check(&config, ATTACKER, &[0x12, 0x34, 0x56, 0x78]).unwrap_err();
+        assert_eq!(err, format!("PLIMSOLL TARGET: {ATTACKER} is not on the target allowlist"));
+    }
+
+    #[test]
+    fn test_vault_execute_unwrapped() {
+        let config = config();
+        assert!(check(&config, VAULT, &execute(ROUTER, &[0x38, 0xed, 0x17, 0x39])).is_ok());
+        let err = check(&config, VAULT, &execute(ATTACKER, &[])).unwrap_err();
+        assert!(err.contains(&format!("call to {VAULT} reaches {ATTACKER}")));
+        // Only the agent's own vaults are unwrapped.
+        assert_eq!(ultimate_targets(&config, ROUTER, &execute(ATTACKER, &[])), vec![ROUTER.to_string()]);
+    }
+
+    #[test]
+    fn test_multicall_leaves_checked() {
+        let config = config();
+        assert_eq!(
+            ultimate_targets(&config, MULTICALL, &aggregate(&[ROUTER, ATTACKER, ROUTER])),
+            vec![ROUTER.to_string(), ATTACKER.to_string()]
+        );
+        assert!(check(&config, MULTICALL, &aggregate(&[ROUTER])).is_ok());
+        assert!(check(&config, MULTICALL, &aggregate(&[ROUTER, ATTACKER])).is_err());
+        // Wrapped in a vault execute.
+        assert!(check(&config, VAULT, &execute(MULTICALL, &aggregate(&[ATTACKER]))).is_err());
+    }
+
+    #[test]
+    fn test_typed_data_contract_and_spender_checked() {
+        let config = config();
+        let typed = |contract: &str, spender: &str| {
+            serde_json::json!({
+                "domain": { "name": "Permit2", "verifyingContract": contract },
+                "message": { "spender": spender, "amount": "1" },
+            })
+        };
+        assert!(check_typed_data(&config, &typed(ROUTER, VAULT)).is_ok());
+        assert!(check_typed_data(&Config::default(), &typed(ATTACKER, ATTACKER)).is_ok());
+        let err = check_typed_data(&config, &typed(ATTACKER, ROUTER)).unwrap_err();
+        assert!(err.contains(&format!("verifying contract {ATTACKER}")), "{err}");
+        let err = check_typed_data(&config, &typed(ROUTER, ATTACKER)).unwrap_err();
+        assert!(err.contains(&format!("spender {ATTACKER}")), "{err}");
+        assert!(check_raw_sign(&config).is_err());
+        assert!(check_raw_sign(&Config::default()).is_ok());
+    }
+}
@@ -17,11 +17,20 @@
 //! - Token positions / balances: NEVER sent
 //! - API keys / private keys: NEVER sent (entropy guard catches these first)
 
-use serde::Serialize;
+use crate::config::Config;
+use crate::identity;
+use crate::reload::SharedConfigHandle;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
 use tracing::{info, warn};
 
 /// An anonymized Indicator of Compromise extracted from a blocked transaction.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IOCReport {
     /// Anonymized agent identifier (SHA-256 of agent pubkey, truncated)
     pub agent_id: String,
@@ -29,6 +38,9 @@ pub struct IOCReport {
     pub target_address: String,
     /// First 4 bytes of calldata (function selector), hex-encoded
     pub calldata_selector: String,
+    /// Text signature of the selector (e.g. `transferOwnership(address)`),
+    /// empty if unknown
+    pub calldata_function: String,
     /// Full calldata hash (SHA-256) for deduplication
     pub calldata_hash: String,
     /// Which engine blocked: "velocity", "entropy", "trajectory", "simulator", "bloom"
@@ -63,6 +75,11 @@ pub struct IOCReport {
     /// Newly created vaults (< 20,000 blocks old) cannot submit IOCs
     /// even if their current balance meets the threshold.
     pub vault_age_blocks: u64,
+
+    /// Engine 0: intelligence source that listed the target (`cloud` or a
+    /// `threat_sources` label); `None` for other engines.
+    #[serde(default)]
+    pub threat_source: Option<String>,
 }
 
 /// Zero-Day 4: Minimum TVL required to submit IOCs to the Swarm.
@@ -145,6 +162,7 @@ pub fn extract_ioc(
     IOCReport {
         agent_id,
         target_address: to.to_string(),
+        calldata_function: crate::selectors::lookup(data).unwrap_or_default(),
         calldata_selector,
         calldata_hash,
         block_engine: block_engine.to_string(),
@@ -159,6 +177,7 @@ pub fn extract_ioc(
         stake_weight,
         twab_usd: 0.0,
         vault_age_blocks: 0,
+        threat_source: None,
     }
 }
 
@@ -198,15 +217,22 @@ fn sanitize_reason(reason: &str) -> String {
     result
 }
 
-/// Async uplink IOC to the Plimsoll Cloud.
+/// Queue an IOC for uplink to the Plimsoll Cloud at `ioc_uplink_url`.
 ///
-/// In production, this sends to `https://api.plimsoll.network/v1/ioc`.
-/// For now, it logs locally. The uplink is fire-and-forget (async, non-blocking).
+/// Never waits on the network: the IOC is sent by the uplink task (see
+/// `spawn_uplink_task`). With `telemetry_offline` (or no URL) the IOC is
+/// only recorded in the local log.
 ///
 /// Zero-Day 4: IOCs from agents with TVL below $5,000 are logged locally
 /// but NOT uplinked. This prevents Sybil telemetry poisoning where
 /// 1000 fake agents with $0 TVL flood the consensus.
-pub async fn uplink_ioc(ioc: &IOCReport, cloud_url: &str) {
+pub fn uplink_ioc(ioc: &IOCReport, config: &Config) {
+    crate::ioc_store::record(ioc);
+
+    // Peers get every IOC right away; the stake gates below are for the
+    // Swarm consensus only.
+    crate::gossip::share(ioc);
+
     // GOD-TIER 2: TWAB gate supersedes point-in-time TVL check.
     // A flash loan can fake point-in-time TVL for 1 block.
     // TWAB requires maintaining balance for 72 hours (20,000 blocks).
@@ -248,12 +274,16 @@ pub async fn uplink_ioc(ioc: &IOCReport, cloud_url: &str) {
         }
     }
 
-    if cloud_url.is_empty() || cloud_url == "disabled" {
+    if config.telemetry_offline || config.ioc_uplink_url.is_empty() {
         info!(
             target = %ioc.target_address,
             selector = %ioc.calldata_selector,
             engine = %ioc.block_engine,
+            chain_id = ioc.chain_id,
+            calldata_hash = %ioc.calldata_hash,
+            reason = %ioc.block_reason,
             stake_weight = ioc.stake_weight,
+            offline = config.telemetry_offline,
             "IOC extracted (uplink disabled, logged locally)"
         );
         return;
@@ -264,28 +294,178 @@ pub async fn uplink_ioc(ioc: &IOCReport, cloud_url: &str) {
         stake_weight = ioc.stake_weight,
         "Zero-Day 4: IOC passes stake-weight gate"
     );
+    enqueue(QueuedIoc { url: config.ioc_uplink_url.clone(), ioc: ioc.clone() });
+}
 
-    let client = reqwest::Client::new();
-    match client
-        .post(format!("{}/v1/ioc", cloud_url))
-        .json(ioc)
-        .timeout(std::time::Duration::from_secs(5))
-        .send()
-        .await
-    {
-        Ok(resp) => {
-            info!(
-                status = resp.status().as_u16(),
-                target = %ioc.target_address,
-                stake_weight = ioc.stake_weight,
-                "IOC uplinked to Plimsoll Cloud (stake-weighted)"
-            );
-        }
-        Err(e) => {
-            // Fire-and-forget: never block the critical path on telemetry failure
-            warn!("IOC uplink failed (non-blocking): {}", e);
+// ── Uplink queue ─────────────────────────────────────────────────
+// Blocks never wait on the Cloud: `uplink_ioc` only queues the IOC.
+// `spawn_uplink_task` sends the queue in batches of `UPLINK_BATCH_SIZE`
+// (`{"iocs": [...]}` of `identity::SignedPayload`s to the IOC URL),
+// backing off exponentially while the
+// Cloud is unreachable. With `ioc_spool_path` set, a failed round moves
+// the queue to that JSON-lines file, and it is read back once the Cloud
+// answers again — IOCs survive outages and restarts. Nothing is sent
+// while `telemetry_offline` is set, queued IOCs included.
+
+/// IOCs held in memory; the oldest are dropped past this.
+const UPLINK_QUEUE_CAPACITY: usize = 10_000;
+
+/// IOCs per uplink request.
+const UPLINK_BATCH_SIZE: usize = 100;
+
+/// First retry delay; doubled per failure up to `MAX_UPLINK_BACKOFF`.
+const MIN_UPLINK_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_UPLINK_BACKOFF: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedIoc {
+    url: String,
+    ioc: IOCReport,
+}
+
+lazy_static! {
+    static ref UPLINK_QUEUE: Mutex<VecDeque<QueuedIoc>> = Mutex::new(VecDeque::new());
+    static ref UPLINK_WAKE: Notify = Notify::new();
+}
+
+fn enqueue(entry: QueuedIoc) {
+    if let Ok(mut queue) = UPLINK_QUEUE.lock() {
+        if queue.len() >= UPLINK_QUEUE_CAPACITY {
+            queue.pop_front();
+            warn!("IOC uplink queue full — dropped the oldest IOC");
         }
+        queue.push_back(entry);
     }
+    UPLINK_WAKE.notify_one();
+}
+
+/// Length of the next batch: IOCs at the front bound for the same URL.
+fn batch_len(queue: &VecDeque<QueuedIoc>) -> usize {
+    let Some(first) = queue.front() else {
+        return 0;
+    };
+    queue.iter().take(UPLINK_BATCH_SIZE).take_while(|e| e.url == first.url).count()
+}
+
+/// The next batch, without removing it.
+fn peek_batch() -> Option<(String, Vec<IOCReport>)> {
+    let queue = UPLINK_QUEUE.lock().ok()?;
+    let n = batch_len(&queue);
+    let url = queue.front()?.url.clone();
+    Some((url, queue.iter().take(n).map(|e| e.ioc.clone()).collect()))
+}
+
+/// Send a batch, each IOC signed with the proxy identity.
+async fn send_batch(url: &str, iocs: &[IOCReport]) -> anyhow::Result<()> {
+    let signed = iocs
+        .iter()
+        .map(|ioc| Ok(identity::sign_ioc(serde_json::to_string(ioc)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    reqwest::Client::new()
+        .post(url)
+        .json(&serde_json::json!({ "iocs": signed }))
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Append `entries` to the spool file.
+fn spill(path: &str, entries: &[QueuedIoc]) -> anyhow::Result<()> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    }
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Take up to `room` IOCs from the spool file, oldest first.
+fn unspill(path: &str, room: usize) -> anyhow::Result<Vec<QueuedIoc>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let taken = lines
+        .iter()
+        .take(room)
+        .filter_map(|l| serde_json::from_str(l).map_err(|e| warn!(error = %e, "Skipping a corrupt spooled IOC")).ok())
+        .collect();
+    if lines.len() > room {
+        let tmp = format!("{path}.tmp");
+        std::fs::write(&tmp, lines[room..].join("\n") + "\n")?;
+        std::fs::rename(&tmp, path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(taken)
+}
+
+/// Spawn the background uplink task. Reads the live config every round.
+pub fn spawn_uplink_task(config: SharedConfigHandle) {
+    tokio::spawn(async move {
+        let mut backoff = MIN_UPLINK_BACKOFF;
+        loop {
+            let cfg = config.current();
+            if cfg.telemetry_offline {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+            let Some((url, iocs)) = peek_batch() else {
+                if !cfg.ioc_spool_path.is_empty() {
+                    match unspill(&cfg.ioc_spool_path, UPLINK_QUEUE_CAPACITY) {
+                        Ok(spooled) if !spooled.is_empty() => {
+                            info!(count = spooled.len(), "Spooled IOCs queued for uplink");
+                            if let Ok(mut queue) = UPLINK_QUEUE.lock() {
+                                for entry in spooled.into_iter().rev() {
+                                    queue.push_front(entry);
+                                }
+                            }
+                            continue;
+                        }
+                        Ok(_) => {}
+                        Err(e) => warn!(error = %e, "Failed to read the IOC spool"),
+                    }
+                }
+                tokio::select! {
+                    _ = UPLINK_WAKE.notified() => {}
+                    _ = tokio::time::sleep(Duration::from_secs(5)) => {}
+                }
+                continue;
+            };
+
+            match send_batch(&url, &iocs).await {
+                Ok(()) => {
+                    if let Ok(mut queue) = UPLINK_QUEUE.lock() {
+                        let sent = iocs.len().min(queue.len());
+                        queue.drain(..sent);
+                    }
+                    info!(count = iocs.len(), "IOCs uplinked to Plimsoll Cloud (stake-weighted)");
+                    backoff = MIN_UPLINK_BACKOFF;
+                }
+                Err(e) => {
+                    warn!(error = %e, retry_in_secs = backoff.as_secs(), "IOC uplink failed — will retry");
+                    if !cfg.ioc_spool_path.is_empty() {
+                        let pending: Vec<QueuedIoc> =
+                            UPLINK_QUEUE.lock().map(|mut q| q.drain(..).collect()).unwrap_or_default();
+                        if let Err(e) = spill(&cfg.ioc_spool_path, &pending) {
+                            warn!(error = %e, "Failed to spool IOCs — keeping them in memory");
+                            if let Ok(mut queue) = UPLINK_QUEUE.lock() {
+                                for entry in pending.into_iter().rev() {
+                                    queue.push_front(entry);
+                                }
+                            }
+                        }
+                    }
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_UPLINK_BACKOFF);
+                }
+            }
+        }
+    });
 }
 
 #[cfg(test)]
@@ -373,6 +553,46 @@ mod tests {
         assert!((ioc.stake_weight - 0.5).abs() < 0.001);
     }
 
+    fn queued(url: &str, target: &str) -> QueuedIoc {
+        QueuedIoc { url: url.into(), ioc: extract_ioc("0xA", target, &[], "bloom", "blocked", None, 1) }
+    }
+
+    #[test]
+    fn test_batches_share_a_url() {
+        let mut queue: VecDeque<QueuedIoc> = (0..150).map(|_| queued("https://a", "0xB")).collect();
+        assert_eq!(batch_len(&queue), UPLINK_BATCH_SIZE);
+        queue.drain(..120);
+        queue.push_back(queued("https://b", "0xB"));
+        assert_eq!(batch_len(&queue), 30);
+        assert_eq!(batch_len(&VecDeque::new()), 0);
+    }
+
+    #[test]
+    fn test_offline_mode_never_queues() {
+        let queued = |target: &str| {
+            UPLINK_QUEUE.lock().unwrap().iter().any(|e| e.ioc.target_address == target)
+        };
+        let ioc = |target: &str| extract_ioc_with_tvl("0xA", target, &[], "bloom", "blocked", None, 1, 50_000.0);
+        let offline = Config { telemetry_offline: true, ..Config::default() };
+        uplink_ioc(&ioc("0xoffline"), &offline);
+        assert!(!queued("0xoffline"));
+        uplink_ioc(&ioc("0xonline"), &Config::default());
+        assert!(queued("0xonline"));
+    }
+
+    #[test]
+    fn test_spool_round_trip() {
+        let path = std::env::temp_dir().join(format!("plimsoll-ioc-spool-{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let entries: Vec<QueuedIoc> = (0..5).map(|i| queued("https://a", &format!("0x{i}"))).collect();
+        spill(path, &entries).unwrap();
+        let first = unspill(path, 3).unwrap();
+        assert_eq!(first.iter().map(|e| e.ioc.target_address.as_str()).collect::<Vec<_>>(), ["0x0", "0x1", "0x2"]);
+        let rest = unspill(path, 10).unwrap();
+        assert_eq!(rest.len(), 2);
+        assert!(unspill(path, 10).unwrap().is_empty());
+    }
+
     #[test]
     fn test_min_tvl_constant() {
         assert_eq!(MIN_TVL_FOR_IOC_SUBMISSION, 5_000.0);
@@ -29,42 +29,164 @@
 //! - Verified contracts with >$1M TVL and >6 months age are IMMUNE
 //! - Only newly deployed, unverified, or low-reputation addresses can be blacklisted
 //! - Minimum consensus threshold: 5+ independent agents must flag within 10 minutes
+//!
+//! ## Feed Refresh
+//!
+//! With `threat_feed_url`, a background task polls the feed every
+//! `threat_feed_refresh_secs` with `?since=<version>`. The feed answers
+//! with a full snapshot or a delta (entries added / removed since
+//! `base_version`), or 204/304 when nothing changed. The next filter is
+//! built off to the side and swapped in under a momentary write lock, so
+//! request handling never waits on the network. A delta that doesn't
+//! apply to the current version triggers a full resync. Each additional
+//! chain in `chains` has its own filter, polled from the same feed with
+//! `&chain_id=<id>`.
+//!
+//! Each of `threat_sources` (Chainalysis / TRM exports, local lists) is a
+//! CSV file or URL re-read on the same interval and kept as a separately
+//! labelled set, so a block reason and its IOC name the source that
+//! matched. A source that fails to load keeps its last entries. Sources
+//! are not chain-specific: they are loaded into every chain's filter.
+//!
+//! With `threat_filter_cache_path`, every refreshed filter is written to
+//! disk and loaded at startup, so a cold-started proxy enforces the last
+//! known filter right away and the first poll only fetches a delta. An
+//! additional chain's filter is cached at `<path>.<chain_id>`. A cache that
+//! doesn't parse or doesn't match this build's filter layout is ignored,
+//! and the first poll fetches a full snapshot instead.
+//!
+//! ## Local Blocklist
+//!
+//! An address a verdict pins on the address itself — a honeypot token, the
+//! spender of a drainer-site signature — is listed locally (source
+//! `local`) for `local_blocklist_ttl_secs`, so the attacker's next attempt
+//! drops in Engine 0 instead of paying for another simulation. Reverts
+//! and loss limits say more about the agent's parameters than the target
+//! and never list it. Immune protocols and allowlisted addresses are
+//! never listed, and the feed allowlist overrides local hits as any other.
+//!
+//! Targets blacklisted by peer gossip (see `gossip`) are kept the same way
+//! (source `gossip`), per chain and for `gossip_ttl_secs`, rather than in
+//! the feed filter: they expire, a repeated IOC refreshes its entry
+//! instead of taking another slot, and a feed snapshot doesn't drop them.
 
-use std::collections::HashSet;
+use crate::chains::{self, ThreatFilters};
+use crate::config::{is_hex_address, Config, ThreatSource};
+use crate::cuckoo::CuckooFilter;
+use crate::reload::SharedConfigHandle;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, RwLock};
-use tracing::warn;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
 
-/// Compressed Bloom Filter for O(1) threat lookups.
+/// Compressed filter for O(1) threat lookups.
 ///
-/// In production, this would be a proper Bloom filter with configurable
-/// false-positive rate (e.g., 0.01%). For the initial implementation,
-/// we use a HashSet (zero false positives, slightly more memory) that
-/// gets replaced by a proper Bloom filter when the dataset grows.
-#[derive(Debug, Clone)]
+/// Each set is a cuckoo filter (see `cuckoo`): compact like a Bloom
+/// filter, with a ~0.01% false-positive rate, but entries can be removed
+/// again by a feed delta.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThreatFilter {
     /// Blacklisted addresses (lowercase, with 0x prefix)
-    addresses: HashSet<String>,
+    addresses: CuckooFilter,
     /// Blacklisted function selectors (4-byte hex, e.g., "0xa9059cbb")
-    selectors: HashSet<String>,
+    selectors: CuckooFilter,
     /// Blacklisted calldata hashes
-    calldata_hashes: HashSet<String>,
+    calldata_hashes: CuckooFilter,
     /// Filter version (incremented on each Cloud push)
     pub version: u64,
     /// Number of contributing agents for this version
     pub consensus_count: u64,
     /// Timestamp of last update
     pub last_updated: u64,
+    /// `threat_sources` entries by label.
+    #[serde(default)]
+    sources: BTreeMap<String, SourceFilter>,
+}
+
+/// Label of the Cloud feed in hits and IOCs.
+pub const CLOUD_SOURCE: &str = "cloud";
+
+/// Label of the local blocklist in hits and IOCs.
+pub const LOCAL_SOURCE: &str = "local";
+
+/// Label of peer gossip in hits and IOCs.
+pub const GOSSIP_SOURCE: &str = "gossip";
+
+/// Most addresses on the local blocklist; the one expiring first goes.
+const MAX_LOCAL_BLOCKS: usize = 10_000;
+
+/// An address blocked by one of this proxy's own engines.
+#[derive(Debug, Clone)]
+struct LocalBlock {
+    engine: String,
+    expires: Instant,
+}
+
+lazy_static! {
+    /// Local blocklist by lowercase address.
+    static ref LOCAL_BLOCKS: RwLock<HashMap<String, LocalBlock>> = RwLock::new(HashMap::new());
+    /// Targets blacklisted by peer gossip, by chain and lowercase address.
+    static ref GOSSIP_BLOCKS: RwLock<HashMap<(u64, String), LocalBlock>> = RwLock::new(HashMap::new());
+}
+
+/// Entries of one labelled intelligence source.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SourceFilter {
+    addresses: CuckooFilter,
+    selectors: CuckooFilter,
+    calldata_hashes: CuckooFilter,
+}
+
+impl SourceFilter {
+    fn from_entries(entries: &FeedEntries) -> Self {
+        let mut filter = Self {
+            addresses: CuckooFilter::with_capacity(entries.addresses.len()),
+            selectors: CuckooFilter::with_capacity(entries.selectors.len()),
+            calldata_hashes: CuckooFilter::with_capacity(entries.calldata_hashes.len()),
+        };
+        for address in &entries.addresses {
+            let (eligible, reason) = AntiGriefing::validate_blacklist_entry(address);
+            if eligible {
+                filter.addresses.insert(&address.to_lowercase());
+            } else {
+                warn!("{}", reason);
+            }
+        }
+        entries.selectors.iter().for_each(|s| filter.selectors.insert(&s.to_lowercase()));
+        entries.calldata_hashes.iter().for_each(|h| filter.calldata_hashes.insert(h));
+        filter
+    }
+
+    fn len(&self) -> usize {
+        self.addresses.len() + self.selectors.len() + self.calldata_hashes.len()
+    }
+}
+
+/// An Engine 0 match and the source that listed it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreatHit {
+    pub source: String,
+    pub reason: String,
 }
 
 impl ThreatFilter {
     pub fn new() -> Self {
+        Self::with_capacity(0, 0, 0)
+    }
+
+    /// An empty filter sized for the given number of entries.
+    pub fn with_capacity(addresses: usize, selectors: usize, calldata_hashes: usize) -> Self {
         Self {
-            addresses: HashSet::new(),
-            selectors: HashSet::new(),
-            calldata_hashes: HashSet::new(),
+            addresses: CuckooFilter::with_capacity(addresses),
+            selectors: CuckooFilter::with_capacity(selectors),
+            calldata_hashes: CuckooFilter::with_capacity(calldata_hashes),
             version: 0,
             consensus_count: 0,
             last_updated: 0,
+            sources: BTreeMap::new(),
         }
     }
 
@@ -85,41 +207,96 @@ impl ThreatFilter {
         self.calldata_hashes.contains(hash)
     }
 
-    /// Full pre-flight check: address OR selector OR calldata.
-    /// Returns (is_blocked, reason) tuple.
-    pub fn check(&self, address: &str, selector: &str, calldata_hash: &str) -> (bool, String) {
-        if self.is_address_blacklisted(address) {
-            return (true, format!(
-                "ENGINE 0: Address {} is globally blacklisted (Swarm consensus: {} agents, v{})",
-                address, self.consensus_count, self.version,
-            ));
+    /// The first source (the Cloud feed first) listing `entry` in the set
+    /// `set` picks.
+    fn listed_by(&self, entry: &str, cloud: bool, set: fn(&SourceFilter) -> &CuckooFilter) -> Option<String> {
+        if cloud {
+            return Some(CLOUD_SOURCE.to_string());
         }
-        if !selector.is_empty() && self.is_selector_blacklisted(selector) {
-            return (true, format!(
-                "ENGINE 0: Selector {} is globally blacklisted (known drainer signature)",
-                selector,
-            ));
+        self.sources
+            .iter()
+            .find(|(_, source)| set(source).contains(entry))
+            .map(|(label, _)| label.clone())
+    }
+
+    /// Full pre-flight check: address OR selector OR calldata, against the
+    /// Cloud feed and every source. A hit on an `allowlist` entry (exact,
+    /// case-insensitive) is overridden.
+    pub fn matches(&self, address: &str, selector: &str, calldata_hash: &str, allowlist: &[String]) -> Option<ThreatHit> {
+        let overridden = |indicator: &str| {
+            let allowed = allowlist.iter().any(|a| a.eq_ignore_ascii_case(indicator));
+            if allowed {
+                info!(indicator, "ENGINE 0: hit overridden by the local allowlist");
+            }
+            allowed
+        };
+        let address_lc = address.to_lowercase();
+        let listed = self.listed_by(&address_lc, self.is_address_blacklisted(address), |s| &s.addresses);
+        if let Some(source) = listed.filter(|_| !overridden(address)) {
+            let reason = if source == CLOUD_SOURCE {
+                format!(
+                    "ENGINE 0: Address {} is globally blacklisted (Swarm consensus: {} agents, v{})",
+                    address, self.consensus_count, self.version,
+                )
+            } else {
+                format!("ENGINE 0: Address {} is listed by {}", address, source)
+            };
+            return Some(ThreatHit { source, reason });
         }
-        if !calldata_hash.is_empty() && self.is_calldata_blacklisted(calldata_hash) {
-            return (true, format!(
-                "ENGINE 0: Calldata hash {} matches known exploit payload",
-                calldata_hash,
-            ));
+        if !selector.is_empty() {
+            let selector_lc = selector.to_lowercase();
+            let listed = self.listed_by(&selector_lc, self.is_selector_blacklisted(selector), |s| &s.selectors);
+            if let Some(source) = listed.filter(|_| !overridden(selector)) {
+                let function = crate::selectors::lookup_hex(selector)
+                    .map(|sig| format!(" [{sig}]"))
+                    .unwrap_or_default();
+                let reason = if source == CLOUD_SOURCE {
+                    format!(
+                        "ENGINE 0: Selector {}{} is globally blacklisted (known drainer signature)",
+                        selector, function,
+                    )
+                } else {
+                    format!("ENGINE 0: Selector {}{} is listed by {}", selector, function, source)
+                };
+                return Some(ThreatHit { source, reason });
+            }
+        }
+        if !calldata_hash.is_empty() {
+            let listed =
+                self.listed_by(calldata_hash, self.is_calldata_blacklisted(calldata_hash), |s| &s.calldata_hashes);
+            if let Some(source) = listed.filter(|_| !overridden(calldata_hash)) {
+                let reason = if source == CLOUD_SOURCE {
+                    format!("ENGINE 0: Calldata hash {} matches known exploit payload", calldata_hash)
+                } else {
+                    format!("ENGINE 0: Calldata hash {} matches a payload listed by {}", calldata_hash, source)
+                };
+                return Some(ThreatHit { source, reason });
+            }
         }
-        (false, String::new())
+        None
+    }
+
+    /// Replace the entries of the source labelled `label`.
+    pub fn set_source(&mut self, label: &str, entries: &FeedEntries) {
+        self.sources.insert(label.to_string(), SourceFilter::from_entries(entries));
+    }
+
+    /// Drop every source not in `labels`.
+    pub fn retain_sources(&mut self, labels: &[&str]) {
+        self.sources.retain(|label, _| labels.contains(&label.as_str()));
     }
 
     /// Add a threat to the local filter (called on Cloud push).
     pub fn add_address(&mut self, address: &str) {
-        self.addresses.insert(address.to_lowercase());
+        self.addresses.insert(&address.to_lowercase());
     }
 
     pub fn add_selector(&mut self, selector: &str) {
-        self.selectors.insert(selector.to_lowercase());
+        self.selectors.insert(&selector.to_lowercase());
     }
 
     pub fn add_calldata_hash(&mut self, hash: &str) {
-        self.calldata_hashes.insert(hash.to_string());
+        self.calldata_hashes.insert(hash);
     }
 
     /// Replace the entire filter with a Cloud-pushed update.
@@ -131,9 +308,13 @@ impl ThreatFilter {
         version: u64,
         consensus_count: u64,
     ) {
-        self.addresses = addresses.into_iter().map(|a| a.to_lowercase()).collect();
-        self.selectors = selectors.into_iter().map(|s| s.to_lowercase()).collect();
-        self.calldata_hashes = calldata_hashes.into_iter().collect();
+        let mut next = Self::with_capacity(addresses.len(), selectors.len(), calldata_hashes.len());
+        addresses.iter().for_each(|a| next.add_address(a));
+        selectors.iter().for_each(|s| next.add_selector(s));
+        calldata_hashes.iter().for_each(|h| next.add_calldata_hash(h));
+        self.addresses = next.addresses;
+        self.selectors = next.selectors;
+        self.calldata_hashes = next.calldata_hashes;
         self.version = version;
         self.consensus_count = consensus_count;
         self.last_updated = std::time::SystemTime::now()
@@ -142,14 +323,92 @@ impl ThreatFilter {
             .as_secs();
     }
 
+    /// Remove a threat from the local filter (feed delta).
+    pub fn remove_address(&mut self, address: &str) {
+        self.addresses.remove(&address.to_lowercase());
+    }
+
+    pub fn remove_selector(&mut self, selector: &str) {
+        self.selectors.remove(&selector.to_lowercase());
+    }
+
+    pub fn remove_calldata_hash(&mut self, hash: &str) {
+        self.calldata_hashes.remove(hash);
+    }
+
+    /// The filter after applying a feed update (see `apply`).
+    pub fn updated(&self, update: FeedUpdate) -> Result<ThreatFilter, String> {
+        let mut next = self.clone();
+        next.apply(update)?;
+        Ok(next)
+    }
+
+    /// Apply a feed update in place: a snapshot replaces the feed's
+    /// entries, a delta edits them. Sources are untouched. Immune
+    /// addresses are never added (see `AntiGriefing`). Errors, leaving the
+    /// filter as it was, when a delta was computed against a version other
+    /// than this filter's.
+    pub fn apply(&mut self, update: FeedUpdate) -> Result<(), String> {
+        match update.snapshot {
+            Some(ref snapshot) => {
+                self.addresses = CuckooFilter::with_capacity(snapshot.addresses.len());
+                self.selectors = CuckooFilter::with_capacity(snapshot.selectors.len());
+                self.calldata_hashes = CuckooFilter::with_capacity(snapshot.calldata_hashes.len());
+            }
+            None if update.base_version == Some(self.version) => {}
+            None => {
+                return Err(format!(
+                    "delta against v{} does not apply to v{}",
+                    update.base_version.map_or_else(|| "?".to_string(), |v| v.to_string()),
+                    self.version
+                ))
+            }
+        }
+        let removed = update.removed;
+        removed.addresses.iter().for_each(|a| self.remove_address(a));
+        removed.selectors.iter().for_each(|s| self.remove_selector(s));
+        removed.calldata_hashes.iter().for_each(|h| self.remove_calldata_hash(h));
+        for added in update.snapshot.into_iter().chain([update.added]) {
+            for address in &added.addresses {
+                let (eligible, reason) = AntiGriefing::validate_blacklist_entry(address);
+                if eligible {
+                    self.add_address(address);
+                } else {
+                    warn!("{}", reason);
+                }
+            }
+            added.selectors.iter().for_each(|s| self.add_selector(s));
+            added.calldata_hashes.iter().for_each(|h| self.add_calldata_hash(h));
+        }
+        self.version = update.version;
+        self.consensus_count = update.consensus_count;
+        self.last_updated = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Ok(())
+    }
+
     /// Number of entries in the filter.
     pub fn len(&self) -> usize {
-        self.addresses.len() + self.selectors.len() + self.calldata_hashes.len()
+        self.addresses.len()
+            + self.selectors.len()
+            + self.calldata_hashes.len()
+            + self.sources.values().map(SourceFilter::len).sum::<usize>()
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Check the layout of every set, as read back from a cache.
+    fn validate(&self) -> Result<()> {
+        let sources = self.sources.values().flat_map(|s| [&s.addresses, &s.selectors, &s.calldata_hashes]);
+        for set in [&self.addresses, &self.selectors, &self.calldata_hashes].into_iter().chain(sources) {
+            set.validate().map_err(anyhow::Error::msg)?;
+        }
+        Ok(())
+    }
 }
 
 /// Thread-safe global threat filter, shared across all request handlers.
@@ -160,15 +419,140 @@ pub fn new_shared_filter() -> SharedThreatFilter {
     Arc::new(RwLock::new(ThreatFilter::new()))
 }
 
+/// Entries of `threat_allowlist`: addresses, selectors or calldata hashes
+/// that a feed hit never blocks.
+pub fn allowlist(config: &Config) -> Vec<String> {
+    config
+        .threat_allowlist
+        .split(',')
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .chain(crate::false_positives::whitelisted())
+        .collect()
+}
+
+/// Whether `address` is on the local allowlist.
+pub fn is_allowlisted(config: &Config, address: &str) -> bool {
+    allowlist(config).iter().any(|a| a.eq_ignore_ascii_case(address))
+}
+
+/// List `address`, just blocked by `engine`, on the local blocklist. A
+/// no-op for non-addresses, immune protocols, allowlisted addresses and
+/// with `local_blocklist_ttl_secs` = 0.
+pub fn block_locally(config: &Config, address: &str, engine: &str) {
+    let address = address.to_lowercase();
+    if config.local_blocklist_ttl_secs == 0 || !is_hex_address(&address) || is_allowlisted(config, &address) {
+        return;
+    }
+    let (eligible, reason) = AntiGriefing::validate_blacklist_entry(&address);
+    if !eligible {
+        info!("{}", reason);
+        return;
+    }
+    info!(address = %address, engine, ttl_secs = config.local_blocklist_ttl_secs, "ENGINE 0: address added to the local blocklist");
+    list(&LOCAL_BLOCKS, address, engine, config.local_blocklist_ttl_secs);
+}
+
+/// List `address` on `chain` for `gossip_ttl_secs`, blacklisted by a
+/// peer's `engine`. Listing it again only refreshes the expiry. A no-op
+/// for non-addresses, immune protocols, allowlisted addresses and with
+/// `gossip_ttl_secs` = 0.
+pub fn block_from_gossip(config: &Config, chain: u64, address: &str, engine: &str) {
+    let address = address.to_lowercase();
+    if config.gossip_ttl_secs == 0
+        || !is_hex_address(&address)
+        || is_allowlisted(config, &address)
+        || !AntiGriefing::validate_blacklist_entry(&address).0
+    {
+        return;
+    }
+    list(&GOSSIP_BLOCKS, (chain, address), engine, config.gossip_ttl_secs);
+}
+
+/// Insert or refresh `key` in `blocks`, dropping expired entries and, when
+/// full, the one expiring first.
+fn list<K: Clone + Eq + std::hash::Hash>(blocks: &RwLock<HashMap<K, LocalBlock>>, key: K, engine: &str, ttl_secs: u64) {
+    let Ok(mut blocks) = blocks.write() else {
+        return;
+    };
+    let now = Instant::now();
+    blocks.retain(|_, b| b.expires > now);
+    if blocks.len() >= MAX_LOCAL_BLOCKS && !blocks.contains_key(&key) {
+        let first = blocks.iter().min_by_key(|(_, b)| b.expires).map(|(k, _)| k.clone());
+        if let Some(first) = first {
+            blocks.remove(&first);
+        }
+    }
+    blocks.insert(
+        key,
+        LocalBlock {
+            engine: engine.to_string(),
+            expires: now + Duration::from_secs(ttl_secs),
+        },
+    );
+}
+
+/// A local blocklist hit on `address`.
+fn local_match(config: &Config, address: &str) -> Option<ThreatHit> {
+    let address = address.to_lowercase();
+    let engine = {
+        let blocks = LOCAL_BLOCKS.read().ok()?;
+        let block = blocks.get(&address).filter(|b| b.expires > Instant::now())?;
+        block.engine.clone()
+    };
+    if is_allowlisted(config, &address) {
+        info!(indicator = %address, "ENGINE 0: hit overridden by the local allowlist");
+        return None;
+    }
+    Some(ThreatHit {
+        source: LOCAL_SOURCE.to_string(),
+        reason: format!("ENGINE 0: Address {} was recently blocked by {} on this proxy", address, engine),
+    })
+}
+
+/// A gossip blocklist hit on `address` for the chain being served.
+fn gossip_match(config: &Config, address: &str) -> Option<ThreatHit> {
+    let address = address.to_lowercase();
+    let chain = chains::current().unwrap_or_else(|| config.default_chain_id());
+    let engine = {
+        let blocks = GOSSIP_BLOCKS.read().ok()?;
+        let block = blocks.get(&(chain, address.clone())).filter(|b| b.expires > Instant::now())?;
+        block.engine.clone()
+    };
+    if is_allowlisted(config, &address) {
+        info!(indicator = %address, "ENGINE 0: hit overridden by the local allowlist");
+        return None;
+    }
+    Some(ThreatHit {
+        source: GOSSIP_SOURCE.to_string(),
+        reason: format!("ENGINE 0: Address {} was recently blocked by {} on a peer proxy", address, engine),
+    })
+}
+
 /// Engine 0 pre-flight check using the shared filter.
 ///
 /// This runs BEFORE Engines 1-6. If the target is in the global blacklist,
-/// the transaction drops in sub-millisecond time.
+/// the transaction drops in sub-millisecond time — unless the hit is on
+/// the operator's `threat_allowlist`, which corrects feed false positives
+/// right away.
 pub fn engine0_check(
+    config: &Config,
     filter: &SharedThreatFilter,
     target: &str,
     data: &[u8],
 ) -> (bool, String) {
+    match engine0_match(config, filter, target, data) {
+        Some(hit) => (true, hit.reason),
+        None => (false, String::new()),
+    }
+}
+
+/// Engine 0 check naming the source that matched.
+pub fn engine0_match(config: &Config, filter: &SharedThreatFilter, target: &str, data: &[u8]) -> Option<ThreatHit> {
+    if let Some(hit) = local_match(config, target).or_else(|| gossip_match(config, target)) {
+        return Some(hit);
+    }
+
     let selector = if data.len() >= 4 {
         format!("0x{}", hex::encode(&data[..4]))
     } else {
@@ -188,13 +572,13 @@ pub fn engine0_check(
     match filter.read() {
         Ok(f) => {
             if f.is_empty() {
-                return (false, String::new()); // No filter loaded yet
+                return None; // No filter loaded yet
             }
-            f.check(target, &selector, &calldata_hash)
+            f.matches(target, &selector, &calldata_hash, &allowlist(config))
         }
         Err(_) => {
             warn!("Threat filter lock poisoned — failing open");
-            (false, String::new())
+            None
         }
     }
 }
@@ -232,6 +616,279 @@ pub fn validate_ioc_submission(vault_tvl_usd: f64, stake_weight: f64) -> (bool,
     ))
 }
 
+/// Threat entries of a feed snapshot or delta.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FeedEntries {
+    pub addresses: Vec<String>,
+    pub selectors: Vec<String>,
+    pub calldata_hashes: Vec<String>,
+}
+
+/// A threat feed response: a full `snapshot`, or the entries `added` and
+/// `removed` since `base_version`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedUpdate {
+    pub version: u64,
+    #[serde(default)]
+    pub consensus_count: u64,
+    #[serde(default)]
+    pub base_version: Option<u64>,
+    #[serde(default)]
+    pub snapshot: Option<FeedEntries>,
+    #[serde(default)]
+    pub added: FeedEntries,
+    #[serde(default)]
+    pub removed: FeedEntries,
+}
+
+/// Fetch the changes since `since` (0 = a full snapshot) to the feed of
+/// `chain` (`None` = default chain). `None` when nothing changed.
+async fn fetch_update(url: &str, chain: Option<u64>, since: u64) -> Result<Option<FeedUpdate>> {
+    let mut request = reqwest::Client::new().get(url).query(&[("since", since)]);
+    if let Some(chain) = chain {
+        request = request.query(&[("chain_id", chain)]);
+    }
+    let resp = request
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .context("Failed to fetch threat feed")?;
+    let status = resp.status();
+    if status == reqwest::StatusCode::NO_CONTENT || status == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        anyhow::bail!("Threat feed returned HTTP {}", status.as_u16());
+    }
+    let update: FeedUpdate = resp.json().await.context("Failed to parse threat feed")?;
+    Ok((update.snapshot.is_some() || update.version > since).then_some(update))
+}
+
+/// Bring `filter` up to date with the feed of `chain` (`None` = default
+/// chain). Returns the new version if it changed.
+///
+/// The feed is fetched without holding the lock and applied to the live
+/// filter under it, so entries added meanwhile are kept.
+pub async fn refresh(url: &str, chain: Option<u64>, filter: &SharedThreatFilter) -> Result<Option<u64>> {
+    let since = filter.read().map_err(|_| anyhow::anyhow!("Threat filter lock poisoned"))?.version;
+    let Some(update) = fetch_update(url, chain, since).await? else {
+        return Ok(None);
+    };
+    if let Err(e) = apply_update(filter, update)? {
+        warn!(error = %e, "Threat feed delta out of sequence — resyncing");
+        match fetch_update(url, chain, 0).await? {
+            Some(update) => apply_update(filter, update)?.map_err(anyhow::Error::msg)?,
+            None => return Ok(None),
+        }
+    }
+    let version = filter.read().map_err(|_| anyhow::anyhow!("Threat filter lock poisoned"))?.version;
+    Ok(Some(version))
+}
+
+fn apply_update(filter: &SharedThreatFilter, update: FeedUpdate) -> Result<Result<(), String>> {
+    let mut live = filter.write().map_err(|_| anyhow::anyhow!("Threat filter lock poisoned"))?;
+    Ok(live.apply(update))
+}
+
+/// Write `filter` to `path`, replacing the previous file atomically.
+pub fn save_cache(filter: &ThreatFilter, path: &str) -> Result<()> {
+    let tmp = format!("{path}.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(filter)?)
+        .with_context(|| format!("Failed to write threat filter cache {tmp}"))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace threat filter cache {path}"))
+}
+
+/// Load a filter written by `save_cache`. `None` when there is none yet;
+/// an error when it is unreadable or its layout doesn't check out.
+pub fn load_cache(path: &str) -> Result<Option<ThreatFilter>> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read threat filter cache {path}")),
+    };
+    let filter: ThreatFilter =
+        serde_json::from_slice(&bytes).with_context(|| format!("Corrupt threat filter cache {path}"))?;
+    filter.validate().with_context(|| format!("Corrupt threat filter cache {path}"))?;
+    Ok(Some(filter))
+}
+
+/// Cache file of `chain`'s filter (`None` = default chain).
+pub fn cache_path(config: &Config, chain: Option<u64>) -> String {
+    match chain {
+        Some(id) => format!("{}.{id}", config.threat_filter_cache_path),
+        None => config.threat_filter_cache_path.clone(),
+    }
+}
+
+/// Warm-start `filter` from `chain`'s cache. `false` when there was none.
+pub fn restore(config: &Config, chain: Option<u64>, filter: &SharedThreatFilter) -> bool {
+    if config.threat_filter_cache_path.is_empty() {
+        return false;
+    }
+    let path = cache_path(config, chain);
+    match load_cache(&path) {
+        Ok(Some(cached)) => {
+            info!(chain, version = cached.version, entries = cached.len(), "Engine 0 threat filter restored from {path}");
+            if let Ok(mut filter) = filter.write() {
+                *filter = cached;
+            }
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            warn!(chain, error = %e, "Ignoring the threat filter cache");
+            false
+        }
+    }
+}
+
+/// Parse a source export: a CSV with `address` / `selector` /
+/// `calldata_hash` columns (Chainalysis, TRM), or a headerless list whose
+/// cells are taken by shape (addresses and selectors). `#` lines are
+/// comments.
+pub fn parse_source_csv(text: &str) -> FeedEntries {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Column {
+        Address,
+        Selector,
+        CalldataHash,
+        Other,
+    }
+    fn cells(line: &str) -> Vec<String> {
+        line.split(',').map(|c| c.trim().trim_matches('"').trim().to_lowercase()).collect()
+    }
+    fn is_selector(cell: &str) -> bool {
+        cell.len() == 10 && cell.starts_with("0x") && cell[2..].chars().all(|c| c.is_ascii_hexdigit())
+    }
+    fn is_calldata_hash(cell: &str) -> bool {
+        cell.len() == 16 && cell.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
+    let mut entries = FeedEntries::default();
+    let mut rows = text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).peekable();
+    let header: Option<Vec<Column>> = rows.peek().map(|l| cells(l)).and_then(|first| {
+        let is_header = !first.iter().any(|c| is_hex_address(c) || is_selector(c));
+        is_header.then(|| {
+            first
+                .iter()
+                .map(|name| match name.as_str() {
+                    n if n.contains("address") => Column::Address,
+                    "selector" => Column::Selector,
+                    n if n.contains("hash") => Column::CalldataHash,
+                    _ => Column::Other,
+                })
+                .collect()
+        })
+    });
+    if header.is_some() {
+        rows.next();
+    }
+    for row in rows {
+        for (i, cell) in cells(row).into_iter().enumerate() {
+            let column = header.as_ref().map(|h| h.get(i).copied().unwrap_or(Column::Other));
+            match column {
+                Some(Column::Address) | None if is_hex_address(&cell) => entries.addresses.push(cell),
+                Some(Column::Selector) | None if is_selector(&cell) => entries.selectors.push(cell),
+                Some(Column::CalldataHash) if is_calldata_hash(&cell) => entries.calldata_hashes.push(cell),
+                _ => {}
+            }
+        }
+    }
+    entries
+}
+
+/// Read a source's export from a file or an http(s) URL.
+async fn fetch_source(location: &str) -> Result<String> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let resp = reqwest::Client::new()
+            .get(location)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to fetch threat source")?
+            .error_for_status()
+            .context("Threat source returned an error")?;
+        return resp.text().await.context("Failed to read threat source");
+    }
+    tokio::fs::read_to_string(location)
+        .await
+        .with_context(|| format!("Failed to read threat source {location}"))
+}
+
+/// Reload every configured source into `filter` and drop the ones no
+/// longer configured. A source that fails keeps its last entries.
+///
+/// Like `refresh`, the sources are read without holding the lock and
+/// written into the live filter under it: only the sources change.
+pub async fn refresh_sources(sources: &[ThreatSource], filter: &SharedThreatFilter) -> Result<()> {
+    let mut loaded = Vec::new();
+    for source in sources {
+        match fetch_source(&source.location).await {
+            Ok(text) => loaded.push((source.label.as_str(), SourceFilter::from_entries(&parse_source_csv(&text)))),
+            Err(e) => warn!(source = %source.label, error = %e, "Threat source refresh failed — keeping its last entries"),
+        }
+    }
+    let labels: Vec<&str> = sources.iter().map(|s| s.label.as_str()).collect();
+    let mut live = filter.write().map_err(|_| anyhow::anyhow!("Threat filter lock poisoned"))?;
+    live.retain_sources(&labels);
+    for (label, source) in loaded {
+        info!(source = %label, addresses = source.addresses.len(), "Threat source loaded");
+        live.sources.insert(label.to_string(), source);
+    }
+    Ok(())
+}
+
+fn persist(config: &Config, chain: Option<u64>, filter: &SharedThreatFilter) {
+    if config.threat_filter_cache_path.is_empty() {
+        return;
+    }
+    let saved = match filter.read() {
+        Ok(f) => save_cache(&f, &cache_path(config, chain)),
+        Err(_) => Err(anyhow::anyhow!("Threat filter lock poisoned")),
+    };
+    if let Err(e) = saved {
+        warn!(chain, error = %e, "Failed to persist the threat filter");
+    }
+}
+
+/// One refresh round of `chain`'s filter: feed, then sources.
+async fn refresh_chain(cfg: &Config, chain: Option<u64>, filter: &SharedThreatFilter) {
+    if !cfg.threat_feed_url.is_empty() {
+        match refresh(&cfg.threat_feed_url, chain, filter).await {
+            Ok(Some(version)) => {
+                let entries = filter.read().map(|f| f.len()).unwrap_or(0);
+                info!(chain, version, entries, "Engine 0 threat filter refreshed");
+                persist(cfg, chain, filter);
+            }
+            Ok(None) => {}
+            Err(e) => warn!(chain, error = %e, "Threat feed refresh failed — keeping the current filter"),
+        }
+    }
+    match refresh_sources(&cfg.threat_sources, filter).await {
+        Ok(()) if !cfg.threat_sources.is_empty() => persist(cfg, chain, filter),
+        Ok(()) => {}
+        Err(e) => warn!(chain, error = %e, "Threat source refresh failed"),
+    }
+}
+
+/// Spawn the background feed refresh task, covering the default filter
+/// and those of every served chain. Reads the live config every round, so
+/// the feed can be enabled or retargeted by reload.
+pub fn spawn_refresh_task(config: SharedConfigHandle, filter: SharedThreatFilter, chain_filters: ThreatFilters) {
+    tokio::spawn(async move {
+        loop {
+            let cfg = config.current();
+            refresh_chain(&cfg, None, &filter).await;
+            for chain in &cfg.chains {
+                let chain_filter = chains::threat_filter(&cfg, &filter, &chain_filters, chain.chain_id);
+                refresh_chain(&cfg, Some(chain.chain_id), &chain_filter).await;
+            }
+            tokio::time::sleep(Duration::from_secs(cfg.threat_feed_refresh_secs.max(1))).await;
+        }
+    });
+}
+
 /// Anti-Griefing Heuristic: determines if an address is immune to blacklisting.
 ///
 /// In production, this queries on-chain data (TVL, contract age, verification status)
@@ -281,10 +938,66 @@ impl AntiGriefing {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_locally_blocked_address_hits_engine0() {
+        let filter = new_shared_filter();
+        let attacker = "0x10ca1b10cced00000000000000000000000000aa";
+        let config = Config::default();
+        assert!(engine0_match(&config, &filter, attacker, &[]).is_none());
+
+        block_locally(&config, attacker, "honeypot");
+        let hit = engine0_match(&config, &filter, &attacker.to_uppercase().replace("0X", "0x"), &[]).unwrap();
+        assert_eq!(hit.source, LOCAL_SOURCE);
+        assert!(hit.reason.starts_with("ENGINE 0: Address"), "{}", hit.reason);
+
+        // The allowlist overrides; immune protocols are never listed.
+        let allowing = Config { threat_allowlist: attacker.into(), ..Config::default() };
+        assert!(engine0_match(&allowing, &filter, attacker, &[]).is_none());
+        let router = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+        block_locally(&config, router, "honeypot");
+        assert!(engine0_match(&config, &filter, router, &[]).is_none());
+        let off = Config { local_blocklist_ttl_secs: 0, ..Config::default() };
+        let other = "0x10ca1b10cced00000000000000000000000000bb";
+        block_locally(&off, other, "eip712_phishing");
+        assert!(engine0_match(&config, &filter, other, &[]).is_none());
+    }
+
+    #[test]
+    fn test_gossiped_block_is_per_chain_and_expires() {
+        let filter = new_shared_filter();
+        let drainer = "0x60551900000000000000000000000000000000aa";
+        let config = Config::default();
+        block_from_gossip(&config, config.default_chain_id(), drainer, "reentrancy");
+        block_from_gossip(&config, config.default_chain_id(), &drainer.to_uppercase().replace("0X", "0x"), "reentrancy");
+        let hit = engine0_match(&config, &filter, drainer, &[]).unwrap();
+        assert_eq!(hit.source, GOSSIP_SOURCE);
+        // Repeats refresh the one entry.
+        let listed = GOSSIP_BLOCKS.read().unwrap().keys().filter(|(_, a)| a == drainer).count();
+        assert_eq!(listed, 1);
+
+        // Another chain's gossip doesn't apply here.
+        let other = "0x60551900000000000000000000000000000000bb";
+        block_from_gossip(&config, 8453, other, "reentrancy");
+        assert!(engine0_match(&config, &filter, other, &[]).is_none());
+
+        // Entries expire.
+        let expired = "0x60551900000000000000000000000000000000cc";
+        block_from_gossip(&config, config.default_chain_id(), expired, "reentrancy");
+        if let Some(block) = GOSSIP_BLOCKS.write().unwrap().get_mut(&(config.default_chain_id(), expired.to_string())) {
+            block.expires = Instant::now();
+        }
+        assert!(engine0_match(&config, &filter, expired, &[]).is_none());
+
+        let off = Config { gossip_ttl_secs: 0, ..Config::default() };
+        let relayed = "0x60551900000000000000000000000000000000dd";
+        block_from_gossip(&off, off.default_chain_id(), relayed, "reentrancy");
+        assert!(engine0_match(&config, &filter, relayed, &[]).is_none());
+    }
+
     #[test]
     fn test_empty_filter_allows_all() {
         let filter = new_shared_filter();
-        let (blocked, _) = engine0_check(&filter, "0xAnything", &[0xa9, 0x05, 0x9c, 0xbb]);
+        let (blocked, _) = engine0_check(&Config::default(), &filter, "0xAnything", &[0xa9, 0x05, 0x9c, 0xbb]);
         assert!(!blocked);
     }
 
@@ -297,7 +1010,7 @@ mod tests {
             f.version = 1;
             f.consensus_count = 12;
         }
-        let (blocked, reason) = engine0_check(&filter, "0xhacker123", &[]);
+        let (blocked, reason) = engine0_check(&Config::default(), &filter, "0xhacker123", &[]);
         assert!(blocked);
         assert!(reason.contains("globally blacklisted"));
         assert!(reason.contains("12 agents"));
@@ -311,7 +1024,7 @@ mod tests {
             f.add_selector("0xdeadbeef");
             f.version = 2;
         }
-        let (blocked, reason) = engine0_check(&filter, "0xSafe", &[0xde, 0xad, 0xbe, 0xef, 0x00]);
+        let (blocked, reason) = engine0_check(&Config::default(), &filter, "0xSafe", &[0xde, 0xad, 0xbe, 0xef, 0x00]);
         assert!(blocked);
         assert!(reason.contains("known drainer signature"));
     }
@@ -324,7 +1037,7 @@ mod tests {
             f.add_address("0xBadGuy");
             f.version = 1;
         }
-        let (blocked, _) = engine0_check(&filter, "0xGoodGuy", &[0x01, 0x02, 0x03, 0x04]);
+        let (blocked, _) = engine0_check(&Config::default(), &filter, "0xGoodGuy", &[0x01, 0x02, 0x03, 0x04]);
         assert!(!blocked);
     }
 
@@ -346,16 +1059,190 @@ mod tests {
             );
         }
         // Old threat is gone
-        let (b1, _) = engine0_check(&filter, "0xoldthreat", &[]);
+        let (b1, _) = engine0_check(&Config::default(), &filter, "0xoldthreat", &[]);
         assert!(!b1);
         // New threats are active
-        let (b2, _) = engine0_check(&filter, "0xnewthreat1", &[]);
+        let (b2, _) = engine0_check(&Config::default(), &filter, "0xnewthreat1", &[]);
         assert!(b2);
         let f = filter.read().unwrap();
         assert_eq!(f.version, 42);
         assert_eq!(f.consensus_count, 100);
     }
 
+    fn entries(addresses: &[&str], selectors: &[&str]) -> FeedEntries {
+        FeedEntries {
+            addresses: addresses.iter().map(|a| a.to_string()).collect(),
+            selectors: selectors.iter().map(|s| s.to_string()).collect(),
+            calldata_hashes: vec![],
+        }
+    }
+
+    #[test]
+    fn test_feed_snapshot_then_delta() {
+        let snapshot = FeedUpdate {
+            version: 5,
+            consensus_count: 9,
+            base_version: None,
+            snapshot: Some(entries(&["0xDrainerA", "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D"], &["0xdeadbeef"])),
+            added: FeedEntries::default(),
+            removed: FeedEntries::default(),
+        };
+        let f = ThreatFilter::new().updated(snapshot).unwrap();
+        assert_eq!(f.version, 5);
+        assert!(f.is_address_blacklisted("0xdraineRa"));
+        // Immune protocols never make it in.
+        assert!(!f.is_address_blacklisted("0x7a250d5630b4cf539739df2c5dacb4c659f2488d"));
+
+        let delta = FeedUpdate {
+            version: 6,
+            consensus_count: 11,
+            base_version: Some(5),
+            snapshot: None,
+            added: entries(&["0xDrainerB"], &[]),
+            removed: entries(&["0xDrainerA"], &["0xdeadbeef"]),
+        };
+        let next = f.updated(delta.clone()).unwrap();
+        assert_eq!((next.version, next.consensus_count), (6, 11));
+        assert!(!next.is_address_blacklisted("0xdrainera"));
+        assert!(next.is_address_blacklisted("0xdrainerb"));
+        assert!(!next.is_selector_blacklisted("0xdeadbeef"));
+        // The original is untouched until swapped.
+        assert!(f.is_address_blacklisted("0xdrainera"));
+
+        // A delta against another version doesn't apply.
+        assert!(next.updated(delta.clone()).unwrap_err().contains("delta against v5 does not apply to v6"));
+
+        // Applied to the live filter: an entry added while the delta was
+        // in flight stays.
+        let mut live = f;
+        live.add_address("0xGossiped");
+        live.apply(delta).unwrap();
+        assert!(live.is_address_blacklisted("0xgossiped"));
+        assert!(live.is_address_blacklisted("0xdrainerb"));
+    }
+
+    #[test]
+    fn test_source_csv_parsing() {
+        let chainalysis = "Address,Category,First Seen\n\
+                           \"0x098B716B8Aaf21512996dC57EB0615e2383E2f96\",sanctions,2022-04-14\n\
+                           0x6666666666666666666666666666666666666666,scam,2023-01-01\n";
+        let entries = parse_source_csv(chainalysis);
+        assert_eq!(
+            entries.addresses,
+            vec!["0x098b716b8aaf21512996dc57eb0615e2383e2f96", "0x6666666666666666666666666666666666666666"]
+        );
+        let list = "# local list\n0x6666666666666666666666666666666666666666\n0xDEADBEEF\nnot-an-entry\n";
+        let entries = parse_source_csv(list);
+        assert_eq!(entries.addresses.len(), 1);
+        assert_eq!(entries.selectors, vec!["0xdeadbeef"]);
+        let hashes = parse_source_csv("calldata_hash,note\n0123456789abcdef,replayed exploit\n");
+        assert_eq!(hashes.calldata_hashes, vec!["0123456789abcdef"]);
+    }
+
+    #[test]
+    fn test_hits_name_their_source() {
+        let filter = new_shared_filter();
+        {
+            let mut f = filter.write().unwrap();
+            f.add_address("0x1111111111111111111111111111111111111111");
+            f.set_source("trm", &entries(&["0x2222222222222222222222222222222222222222"], &["0xdeadbeef"]));
+        }
+        let config = Config::default();
+        let hit = engine0_match(&config, &filter, "0x1111111111111111111111111111111111111111", &[]).unwrap();
+        assert_eq!(hit.source, CLOUD_SOURCE);
+        let hit = engine0_match(&config, &filter, "0x2222222222222222222222222222222222222222", &[]).unwrap();
+        assert_eq!(hit.source, "trm");
+        assert!(hit.reason.contains("is listed by trm"));
+        let hit = engine0_match(&config, &filter, "0x3333333333333333333333333333333333333333", &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(hit.unwrap().source, "trm");
+
+        // Sources survive a Cloud snapshot and go away when unconfigured.
+        let snapshot = FeedUpdate {
+            version: 2,
+            consensus_count: 0,
+            base_version: None,
+            snapshot: Some(FeedEntries::default()),
+            added: FeedEntries::default(),
+            removed: FeedEntries::default(),
+        };
+        let mut next = filter.read().unwrap().updated(snapshot).unwrap();
+        assert_eq!(next.len(), 2);
+        next.retain_sources(&[]);
+        assert!(next.is_empty());
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let path = std::env::temp_dir().join(format!("plimsoll-threat-cache-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        assert!(load_cache(path).unwrap().is_none());
+        let mut f = ThreatFilter::new();
+        f.add_address("0xDrainer");
+        f.add_selector("0xdeadbeef");
+        f.version = 12;
+        save_cache(&f, path).unwrap();
+        let loaded = load_cache(path).unwrap().unwrap();
+        assert_eq!(loaded.version, 12);
+        assert!(loaded.is_address_blacklisted("0xdrainer"));
+        assert!(loaded.is_selector_blacklisted("0xdeadbeef"));
+        std::fs::write(path, b"{not json").unwrap();
+        assert!(load_cache(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_cache_is_ignored() {
+        let path = std::env::temp_dir().join(format!("plimsoll-threat-cache-corrupt-{}.json", std::process::id()));
+        let config = Config { threat_filter_cache_path: path.to_str().unwrap().into(), ..Config::default() };
+        let mut f = ThreatFilter::new();
+        f.add_address("0xDrainer");
+        f.version = 12;
+        // Valid JSON, but a table `mask()` can't index.
+        let mut cached = serde_json::to_value(&f).unwrap();
+        cached["addresses"]["buckets"] = serde_json::json!([]);
+        std::fs::write(&path, serde_json::to_vec(&cached).unwrap()).unwrap();
+        assert!(load_cache(&config.threat_filter_cache_path).is_err());
+
+        // Startup skips it: the filter stays empty at version 0, so the
+        // first poll fetches a full snapshot.
+        let filter = new_shared_filter();
+        assert!(!restore(&config, None, &filter));
+        assert_eq!(filter.read().unwrap().version, 0);
+        assert!(!filter.read().unwrap().is_address_blacklisted("0xdrainer"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_feed_update_parses() {
+        let update: FeedUpdate = serde_json::from_str(
+            r#"{"version": 8, "base_version": 7, "added": {"addresses": ["0xbad"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(update.base_version, Some(7));
+        assert!(update.snapshot.is_none());
+        assert_eq!(update.added.addresses, vec!["0xbad".to_string()]);
+        assert!(update.removed.selectors.is_empty());
+    }
+
+    #[test]
+    fn test_allowlist_overrides_hits() {
+        let filter = new_shared_filter();
+        {
+            let mut f = filter.write().unwrap();
+            f.add_address("0xFalsePositive");
+            f.add_selector("0xdeadbeef");
+        }
+        let config = Config { threat_allowlist: " 0xfalsepositive ".into(), ..Config::default() };
+        let (blocked, _) = engine0_check(&config, &filter, "0xFALSEPOSITIVE", &[0x01, 0x02, 0x03, 0x04]);
+        assert!(!blocked);
+        // Only the allowlisted indicator is overridden.
+        let (blocked, reason) = engine0_check(&config, &filter, "0xfalsepositive", &[0xde, 0xad, 0xbe, 0xef]);
+        assert!(blocked);
+        assert!(reason.contains("Selector 0xdeadbeef"));
+        assert!(is_allowlisted(&config, "0xFalsePositive"));
+        assert!(engine0_check(&Config::default(), &filter, "0xfalsepositive", &[]).0);
+    }
+
     #[test]
     fn test_anti_griefing_uniswap_immune() {
         assert!(AntiGriefing::is_immune(
@@ -420,4 +1307,26 @@ mod tests {
         assert!(!accepted);
         assert!(reason.contains("zero or negative"));
     }
+
+    #[tokio::test]
+    async fn test_source_refresh_only_touches_sources() {
+        let path = std::env::temp_dir().join(format!("plimsoll-source-{}.csv", std::process::id()));
+        std::fs::write(&path, "0x2222222222222222222222222222222222222222\n").unwrap();
+        let filter = new_shared_filter();
+        {
+            let mut f = filter.write().unwrap();
+            f.version = 7;
+            f.add_address("0x1111111111111111111111111111111111111111");
+            f.set_source("gone", &entries(&["0x3333333333333333333333333333333333333333"], &[]));
+        }
+        let sources = [ThreatSource { label: "trm".into(), location: path.to_string_lossy().into_owned() }];
+        refresh_sources(&sources, &filter).await.unwrap();
+        let _ = std::fs::remove_file(&path);
+        let f = filter.read().unwrap();
+        assert_eq!(f.version, 7);
+        assert!(f.is_address_blacklisted("0x1111111111111111111111111111111111111111"));
+        let hit = f.matches("0x2222222222222222222222222222222222222222", "", "", &[]).unwrap();
+        assert_eq!(hit.source, "trm");
+        assert!(f.matches("0x3333333333333333333333333333333333333333", "", "", &[]).is_none());
+    }
 }
@@ -0,0 +1,241 @@
+//! Native TLS for the proxy listener.
+//!
+//! Deployments where the agent reaches the proxy across hosts no longer
+//! need a reverse proxy in front of it just to terminate TLS. The main
+//! listener serves HTTPS (rustls) when either is set:
+//!
+//! - `tls_cert_path` + `tls_key_path`: PEM certificate chain and private
+//!   key, read at startup (restart to pick up a renewed certificate);
+//! - `tls_acme_domains` (build with `--features acme`): certificates are
+//!   obtained and renewed from Let's Encrypt over TLS-ALPN-01, cached in
+//!   `tls_acme_cache_dir`. The listener must be reachable on port 443 of
+//!   the domains.
+//!
+//! TLS covers every route on the listener. Settings are restart-only.
+//!
+//! With `tls_client_ca_path` set (cert/key mode), connections must present a
+//! client certificate issued by that CA (mutual TLS). The certificate's
+//! SHA-256 fingerprint is attached to every request on the connection and
+//! authenticates it as the agent whose `client_cert` pins it — see `auth`.
+
+use crate::auth::ClientCert;
+use crate::config::Config;
+use crate::shutdown;
+use anyhow::{Context, Result};
+use axum::{Extension, Router};
+use axum_server::accept::{Accept, DefaultAcceptor};
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use axum_server::Handle;
+use futures::future::BoxFuture;
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+use tracing::info;
+#[cfg(feature = "acme")]
+use tracing::warn;
+
+/// TLS settings of the listener, taken from the config at startup.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    pub cert_path: String,
+    pub key_path: String,
+    pub client_ca_path: String,
+    pub acme_domains: Vec<String>,
+    pub acme_contact: String,
+    pub acme_cache_dir: String,
+    pub acme_staging: bool,
+}
+
+impl Settings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            cert_path: config.tls_cert_path.clone(),
+            key_path: config.tls_key_path.clone(),
+            client_ca_path: config.tls_client_ca_path.clone(),
+            acme_domains: config
+                .tls_acme_domains
+                .split(',')
+                .map(str::trim)
+                .filter(|d| !d.is_empty())
+                .map(str::to_string)
+                .collect(),
+            acme_contact: config.tls_acme_contact.clone(),
+            acme_cache_dir: config.tls_acme_cache_dir.clone(),
+            acme_staging: config.tls_acme_staging,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.cert_path.is_empty() || !self.acme_domains.is_empty()
+    }
+}
+
+/// Serve `app` over HTTPS on `addr` until the server stops, draining
+/// connections for `shutdown_grace_secs` on shutdown.
+pub async fn serve(settings: &Settings, addr: SocketAddr, app: Router) -> Result<()> {
+    // rustls picks its crypto from the process default; pin ring so the
+    // build doesn't depend on which providers other crates enable.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let handle = Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown::signaled().await;
+            handle.graceful_shutdown(Some(shutdown::grace()));
+        }
+    });
+
+    if !settings.acme_domains.is_empty() {
+        return serve_acme(settings, addr, app, handle).await;
+    }
+    if !settings.client_ca_path.is_empty() {
+        let rustls = RustlsConfig::from_config(Arc::new(mutual_tls_config(settings)?));
+        info!(client_ca = %settings.client_ca_path, "Listening on https://{addr} (mutual TLS)");
+        let acceptor = ClientCertAcceptor { inner: RustlsAcceptor::new(rustls) };
+        axum_server::bind(addr).acceptor(acceptor).handle(handle).serve(app.into_make_service()).await?;
+        return Ok(());
+    }
+    let rustls = RustlsConfig::from_pem_file(&settings.cert_path, &settings.key_path)
+        .await
+        .with_context(|| format!("loading TLS certificate {} / key {}", settings.cert_path, settings.key_path))?;
+    info!("Listening on https://{addr}");
+    axum_server::bind_rustls(addr, rustls).handle(handle).serve(app.into_make_service()).await?;
+    Ok(())
+}
+
+/// Lowercase hex SHA-256 of a DER certificate, as agents' `client_cert`
+/// pins it (`openssl x509 -noout -fingerprint -sha256`, colons optional).
+pub fn fingerprint(der: &[u8]) -> String {
+    hex::encode(Sha256::digest(der))
+}
+
+fn pem_reader(path: &str) -> Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(path).with_context(|| format!("opening {path}"))?))
+}
+
+/// Server config requiring client certificates issued by `client_ca_path`.
+fn mutual_tls_config(settings: &Settings) -> Result<ServerConfig> {
+    let mut roots = RootCertStore::empty();
+    for ca in rustls_pemfile::certs(&mut pem_reader(&settings.client_ca_path)?) {
+        roots.add(ca.with_context(|| format!("reading {}", settings.client_ca_path))?)?;
+    }
+    if roots.is_empty() {
+        anyhow::bail!("tls_client_ca_path {} holds no certificates", settings.client_ca_path);
+    }
+    let verifier = WebPkiClientVerifier::builder(Arc::new(roots)).build()?;
+
+    let chain = rustls_pemfile::certs(&mut pem_reader(&settings.cert_path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("reading {}", settings.cert_path))?;
+    let key = rustls_pemfile::private_key(&mut pem_reader(&settings.key_path)?)
+        .with_context(|| format!("reading {}", settings.key_path))?
+        .with_context(|| format!("{} holds no private key", settings.key_path))?;
+
+    let mut config = ServerConfig::builder()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(chain, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// TLS acceptor that tags each connection's requests with the fingerprint
+/// of its (verified) client certificate.
+#[derive(Clone)]
+struct ClientCertAcceptor {
+    inner: RustlsAcceptor<DefaultAcceptor>,
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = axum::middleware::AddExtension<S, ClientCert>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let handshake = self.inner.accept(stream, service);
+        Box::pin(async move {
+            let (stream, service) = handshake.await?;
+            let cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .map(|cert| ClientCert(fingerprint(cert)))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::PermissionDenied, "no client certificate"))?;
+            Ok((stream, Extension(cert).layer(service)))
+        })
+    }
+}
+
+#[cfg(feature = "acme")]
+async fn serve_acme(settings: &Settings, addr: SocketAddr, app: Router, handle: Handle) -> Result<()> {
+    use futures::StreamExt;
+    use rustls_acme::{caches::DirCache, AcmeConfig};
+
+    let mut acme = AcmeConfig::new(settings.acme_domains.clone())
+        .contact(
+            settings
+                .acme_contact
+                .split(',')
+                .map(str::trim)
+                .filter(|c| !c.is_empty())
+                .map(|c| format!("mailto:{c}")),
+        )
+        .cache(DirCache::new(settings.acme_cache_dir.clone()))
+        .directory_lets_encrypt(!settings.acme_staging)
+        .state();
+    let acceptor = acme.axum_acceptor(acme.default_rustls_config());
+    tokio::spawn(async move {
+        while let Some(event) = acme.next().await {
+            match event {
+                Ok(event) => info!(?event, "ACME"),
+                Err(e) => warn!(error = %e, "ACME certificate order failed"),
+            }
+        }
+    });
+    info!(domains = ?settings.acme_domains, "Listening on https://{addr} (ACME)");
+    axum_server::bind(addr).acceptor(acceptor).handle(handle).serve(app.into_make_service()).await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "acme"))]
+async fn serve_acme(_: &Settings, _: SocketAddr, _: Router, _: Handle) -> Result<()> {
+    anyhow::bail!("tls_acme_domains requires a build with --features acme")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings() {
+        assert!(!Settings::from_config(&Config::default()).enabled());
+
+        let config = Config {
+            tls_acme_domains: " rpc.example.com, ,rpc2.example.com".into(),
+            ..Config::default()
+        };
+        let settings = Settings::from_config(&config);
+        assert!(settings.enabled());
+        assert_eq!(settings.acme_domains, ["rpc.example.com", "rpc2.example.com"]);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(
+            fingerprint(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -1,14 +1,87 @@
 //! Shared types for JSON-RPC request/response handling.
 
+use crate::abi;
+use crate::block_codes;
+use crate::response_meta;
+use alloy_primitives::U256;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// JSON-RPC 2.0 error code of a request that is not a valid request object.
+pub const INVALID_REQUEST_CODE: i64 = -32600;
+
+/// JSON-RPC 2.0 error code of a body that is not JSON.
+pub const PARSE_ERROR_CODE: i64 = -32700;
+
+/// JSON-RPC error code geth returns for a reverted `eth_call`.
+pub const EXECUTION_REVERTED_CODE: i64 = 3;
 
 /// Standard JSON-RPC 2.0 request.
+///
+/// `id` is a number, a string or null, echoed back unchanged. A request
+/// without one is a notification: it runs, but gets no response.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "RawRequest")]
 pub struct JsonRpcRequest {
     pub jsonrpc: String,
     pub method: String,
     pub params: serde_json::Value,
     pub id: serde_json::Value,
+    #[serde(skip)]
+    pub notification: bool,
+}
+
+/// A request as sent: `params` may be omitted, and an absent `id` is told
+/// apart from `"id": null`.
+#[derive(Deserialize)]
+struct RawRequest {
+    jsonrpc: String,
+    method: String,
+    #[serde(default = "no_params")]
+    params: serde_json::Value,
+    #[serde(default, deserialize_with = "present")]
+    id: Option<serde_json::Value>,
+}
+
+fn no_params() -> serde_json::Value {
+    serde_json::Value::Array(Vec::new())
+}
+
+/// `Some` for any value present, `null` included.
+fn present<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<serde_json::Value>, D::Error> {
+    serde_json::Value::deserialize(d).map(Some)
+}
+
+/// Whether `id` is a legal JSON-RPC id.
+fn valid_id(id: &serde_json::Value) -> bool {
+    id.is_number() || id.is_string() || id.is_null()
+}
+
+impl TryFrom<RawRequest> for JsonRpcRequest {
+    type Error = String;
+
+    fn try_from(raw: RawRequest) -> Result<Self, String> {
+        if raw.id.as_ref().is_some_and(|id| !valid_id(id)) {
+            return Err("id must be a number, a string or null".into());
+        }
+        Ok(Self {
+            jsonrpc: raw.jsonrpc,
+            method: raw.method,
+            params: raw.params,
+            notification: raw.id.is_none(),
+            id: raw.id.unwrap_or_default(),
+        })
+    }
+}
+
+impl JsonRpcRequest {
+    /// Parse one call of a body. The error is the response to send: an
+    /// invalid request, under its id when that much is readable.
+    pub fn parse(call: serde_json::Value) -> Result<Self, Box<JsonRpcResponse>> {
+        let id = call.get("id").filter(|id| valid_id(id)).cloned().unwrap_or_default();
+        serde_json::from_value(call)
+            .map_err(|e| Box::new(JsonRpcResponse::error(id, INVALID_REQUEST_CODE, format!("Invalid Request: {e}"))))
+    }
 }
 
 /// Standard JSON-RPC 2.0 response.
@@ -18,6 +91,10 @@ pub struct JsonRpcResponse {
     pub result: Option<serde_json::Value>,
     pub error: Option<JsonRpcError>,
     pub id: serde_json::Value,
+    /// v2.1: How the response was decided (`response_meta`), when
+    /// `response_metadata` is on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plimsoll: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -58,6 +135,179 @@ pub struct SimulationResult {
     /// value at simulation time. For transparent proxies, EXTCODEHASH stays
     /// constant across upgrades — only this slot changes. Empty = not a proxy.
     pub impl_slot_value: String,
+    /// keccak256 of the resolved proxy implementation's code at simulation
+    /// time (EIP-1967 / beacon / EIP-1822). Empty = not a proxy.
+    pub implementation_codehash: String,
+    /// v2.1: Upstream `callTracer` trace of the transaction. `None` when
+    /// tracing is off or the upstream doesn't support `debug_traceCall`.
+    pub call_trace: Option<CallFrame>,
+    /// v2.1: Token movements of the sender and vaults, from the trace's
+    /// events.
+    pub asset_deltas: Vec<AssetDelta>,
+    /// v2.1: Approvals the sender and vaults granted or revoked.
+    pub approval_deltas: Vec<ApprovalDelta>,
+    /// v2.1: Storage slots the transaction wrote, from the upstream's
+    /// `prestateTracer` diff. Empty unless `block_approval_changes` is on.
+    pub storage_changes: Vec<StorageChange>,
+}
+
+/// Geth-style `stateOverride` for one account: hypothetical state the
+/// simulation runs against. Quantities and slots are hex strings.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AccountOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Replaces the account's storage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<BTreeMap<String, String>>,
+    /// Patches individual storage slots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<BTreeMap<String, String>>,
+}
+
+/// Address → override, as accepted by `eth_call` / `debug_traceCall`.
+pub type StateOverride = BTreeMap<String, AccountOverride>;
+
+/// A transaction the proxy forwarded that is not yet mined.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PendingTx {
+    pub nonce: u64,
+    pub to: String,
+    pub value: u128,
+    pub data: Vec<u8>,
+    /// `maxFeePerGas` / `gasPrice`, when the request carried one.
+    pub max_fee: Option<u128>,
+    /// `maxPriorityFeePerGas`, when the request carried one.
+    pub priority_fee: Option<u128>,
+    /// Simulated loss when it was approved — the baseline a replacement
+    /// is held to.
+    pub loss_pct: f64,
+}
+
+/// Hypothetical state a simulation runs against instead of plain latest.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationState {
+    /// Applied on top of the fetched state.
+    pub overrides: Option<StateOverride>,
+    /// The sender's unmined transactions, executed first in nonce order.
+    pub pending: Vec<PendingTx>,
+}
+
+/// One frame of a geth `callTracer` trace.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CallFrame {
+    /// `CALL`, `STATICCALL`, `DELEGATECALL`, `CALLCODE`, `CREATE`, ...
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub from: String,
+    pub to: String,
+    /// Hex calldata.
+    pub input: String,
+    pub error: Option<String>,
+    pub calls: Vec<CallFrame>,
+    /// Events emitted by the frame itself (`withLog`).
+    pub logs: Vec<CallLog>,
+}
+
+/// An event in a `callTracer` frame.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CallLog {
+    pub address: String,
+    pub topics: Vec<String>,
+    /// Hex event data.
+    pub data: String,
+}
+
+/// Token standard of an asset movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TokenStandard {
+    Erc20,
+    Erc721,
+    Erc1155,
+}
+
+impl TokenStandard {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TokenStandard::Erc20 => "erc20",
+            TokenStandard::Erc721 => "erc721",
+            TokenStandard::Erc1155 => "erc1155",
+        }
+    }
+}
+
+/// Net movement of one token (or one NFT id) in or out of the agent's
+/// portfolio during simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetDelta {
+    pub standard: TokenStandard,
+    pub token: String,
+    /// NFT id; `None` for ERC-20.
+    pub token_id: Option<U256>,
+    pub sent: U256,
+    pub received: U256,
+}
+
+/// An approval the agent's portfolio granted or revoked during simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApprovalDelta {
+    pub owner: String,
+    pub token: String,
+    pub spender: String,
+    /// ERC-20 allowance; `None` for an operator (`setApprovalForAll`) approval.
+    pub amount: Option<U256>,
+    /// False when the approval was revoked (zero allowance / `false`).
+    pub approved: bool,
+}
+
+/// A storage slot the simulated transaction changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageChange {
+    pub account: String,
+    pub slot: U256,
+    pub before: U256,
+    pub after: U256,
+}
+
+impl SimulationResult {
+    /// Charge `fee` wei paid outside execution (blob gas, L1 data) to the
+    /// simulation as value leaving the sender.
+    pub fn charge(&mut self, fee: u128) {
+        if fee == 0 || self.balance_before == 0 {
+            return;
+        }
+        self.balance_after = self.balance_after.saturating_sub(fee);
+        self.loss_pct += fee as f64 / self.balance_before as f64 * 100.0;
+    }
+}
+
+impl CallFrame {
+    /// The account whose storage and balance the frame executes against:
+    /// the caller's for `DELEGATECALL` / `CALLCODE`, the callee's otherwise.
+    pub fn context(&self) -> &str {
+        match self.call_type.as_str() {
+            "DELEGATECALL" | "CALLCODE" => &self.from,
+            _ => &self.to,
+        }
+    }
+}
+
+/// A call unwrapped from a wrapper (meta-transaction, Safe, multicall)
+/// that must be vetted as if the agent had sent it directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InnerCall {
+    /// The address the call executes as (`msg.sender` of the inner call).
+    pub from: String,
+    pub to: String,
+    pub value: u128,
+    pub data: Vec<u8>,
 }
 
 impl JsonRpcResponse {
@@ -67,6 +317,7 @@ impl JsonRpcResponse {
             result: Some(result),
             error: None,
             id,
+            plimsoll: None,
         }
     }
 
@@ -80,9 +331,21 @@ impl JsonRpcResponse {
                 data: None,
             }),
             id,
+            plimsoll: None,
         }
     }
 
+    /// An error for a request `engine` blocked, with the block code and
+    /// engine as `data`.
+    pub fn blocked(id: serde_json::Value, code: i64, engine: &str, reason: String) -> Self {
+        response_meta::blocked(engine, &reason);
+        let mut resp = Self::error(id, code, reason);
+        if let Some(error) = resp.error.as_mut() {
+            error.data = Some(block_codes::data(engine, &error.message));
+        }
+        resp
+    }
+
     pub fn plimsoll_block(id: serde_json::Value, reason: String) -> Self {
         Self::error(id, -32000, format!("Execution Reverted by Plimsoll Simulation Physics: {reason}"))
     }
@@ -109,6 +372,7 @@ impl JsonRpcResponse {
             result: Some(serde_json::json!(tx_hash)),
             error: None,
             id,
+            plimsoll: None,
         };
         (resp, tx_hash)
     }
@@ -116,41 +380,212 @@ impl JsonRpcResponse {
     /// Return a synthetic transaction receipt (status: 0x0 = reverted).
     /// When the agent polls `eth_getTransactionReceipt`, we return this
     /// instead of null. The agent reads the revert reason and stays alive.
-    pub fn plimsoll_synthetic_receipt(id: serde_json::Value, tx_hash: &str, reason: &str) -> Self {
-        let revert_data = format!("0x{}", hex::encode(
-            format!("PLIMSOLL_BLOCKED: {}", reason).as_bytes()
-        ));
+    pub fn plimsoll_synthetic_receipt(id: serde_json::Value, tx_hash: &str, blocked: &BlockedTx) -> Self {
+        let BlockedTx { engine, reason, .. } = blocked;
+        let from = if blocked.from.is_empty() { ZERO_ADDRESS } else { blocked.from.as_str() };
+        let to = if blocked.to.is_empty() { ZERO_ADDRESS } else { blocked.to.as_str() };
+        let revert_data = revert_data(reason);
         let feedback = format!(
             "[SYSTEM OVERRIDE: ERROR 403. TRANSACTION BLOCKED BY PLIMSOLL FIREWALL. \
              REASON: {}. DO NOT RETRY THIS ACTION. PIVOT STRATEGY.]",
             reason
         );
+        let code = block_codes::of(engine);
         Self {
             jsonrpc: "2.0".into(),
             result: Some(serde_json::json!({
                 "transactionHash": tx_hash,
                 "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
-                "blockNumber": "0x0",
+                "blockNumber": format!("0x{:x}", blocked.block_number.unwrap_or(0)),
                 "contractAddress": serde_json::Value::Null,
-                "cumulativeGasUsed": "0x0",
+                "cumulativeGasUsed": format!("0x{:x}", blocked.gas_used),
                 "effectiveGasPrice": "0x0",
-                "from": "0x0000000000000000000000000000000000000000",
-                "gasUsed": "0x0",
+                "from": from,
+                "gasUsed": format!("0x{:x}", blocked.gas_used),
                 "logs": [],
                 "logsBloom": format!("0x{}", "00".repeat(256)),
                 "status": "0x0",
-                "to": "0x0000000000000000000000000000000000000000",
+                "to": to,
                 "transactionIndex": "0x0",
                 "type": "0x0",
                 "revertReason": revert_data,
                 "plimsoll": {
                     "blocked": true,
+                    "code": code.code,
+                    "category": code.category,
+                    "engine": engine,
                     "reason": reason,
                     "feedback": feedback,
                 }
             })),
             error: None,
             id,
+            plimsoll: None,
         }
     }
+
+    /// The revert of an `eth_call` replaying a blocked send — ethers and
+    /// web3 replay the call to fetch a failed tx's revert reason, and
+    /// decode `data` as `Error(string)`.
+    pub fn plimsoll_synthetic_revert(id: serde_json::Value, reason: &str) -> Self {
+        let message = format!("execution reverted: {}", revert_message(reason));
+        let mut resp = Self::error(id, EXECUTION_REVERTED_CODE, message);
+        if let Some(error) = resp.error.as_mut() {
+            error.data = Some(serde_json::json!(revert_data(reason)));
+        }
+        resp
+    }
+
+    /// `debug_traceTransaction` of a blocked tx: a reverted top-level call
+    /// with the `Error(string)` output, as a `callTracer` frame or, for
+    /// any other tracer, the default struct-log result.
+    pub fn plimsoll_synthetic_trace(id: serde_json::Value, reason: &str, tracer: Option<&str>) -> Self {
+        let output = revert_data(reason);
+        let result = if tracer == Some("callTracer") {
+            serde_json::json!({
+                "type": "CALL",
+                "from": "0x0000000000000000000000000000000000000000",
+                "to": "0x0000000000000000000000000000000000000000",
+                "value": "0x0",
+                "gas": "0x0",
+                "gasUsed": "0x0",
+                "input": "0x",
+                "output": output,
+                "error": "execution reverted",
+                "revertReason": revert_message(reason),
+            })
+        } else {
+            serde_json::json!({
+                "gas": 0,
+                "failed": true,
+                "returnValue": output.trim_start_matches("0x"),
+                "structLogs": [],
+            })
+        };
+        Self::success(id, result)
+    }
+}
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// Patch 4: A blocked send, answered with a synthetic tx hash.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockedTx {
+    /// Engine that blocked it (its block code).
+    pub engine: String,
+    pub reason: String,
+    /// The send's `from` and `to`, when known (not kept across restarts).
+    pub from: String,
+    pub to: String,
+    /// Intrinsic gas of the send.
+    pub gas_used: u64,
+    /// Unix time of the block; 0 when restored or learned from a replica.
+    pub blocked_at: u64,
+    /// Chain block the synthetic receipt claims, pinned when first polled.
+    pub block_number: Option<u64>,
+}
+
+/// Revert string of a blocked tx.
+fn revert_message(reason: &str) -> String {
+    format!("PLIMSOLL_BLOCKED: {reason}")
+}
+
+/// `Error(string)`-encoded revert of a blocked tx, hex.
+fn revert_data(reason: &str) -> String {
+    format!("0x{}", hex::encode(abi::encode_revert(&revert_message(reason))))
+}
+
+/// Decode a hex-encoded raw transaction (`eth_sendRawTransaction` param)
+/// with the typed-transaction decoder `decode`.
+pub fn decode_raw_tx_hex<T>(
+    raw_hex: &str,
+    decode: impl FnOnce(&[u8]) -> Result<Option<T>, String>,
+) -> Result<Option<T>, String> {
+    let raw = hex::decode(raw_hex.trim_start_matches("0x")).map_err(|e| format!("invalid raw transaction hex: {e}"))?;
+    decode(&raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_ids() {
+        for id in [json!(7), json!("abc-1"), serde_json::Value::Null] {
+            let req = JsonRpcRequest::parse(json!({ "jsonrpc": "2.0", "method": "eth_chainId", "id": id })).unwrap();
+            assert_eq!(req.id, id);
+            assert!(!req.notification);
+            assert_eq!(req.params, json!([]));
+        }
+        let req = JsonRpcRequest::parse(json!({ "jsonrpc": "2.0", "method": "eth_chainId", "params": [] })).unwrap();
+        assert!(req.notification);
+    }
+
+    #[test]
+    fn test_decode_raw_tx_hex() {
+        let first_byte = |raw: &[u8]| Ok(raw.first().copied());
+        assert_eq!(decode_raw_tx_hex("0x04f8", first_byte), Ok(Some(0x04)));
+        assert_eq!(decode_raw_tx_hex("02", first_byte), Ok(Some(0x02)));
+        assert!(decode_raw_tx_hex("0xzz", first_byte).unwrap_err().contains("invalid raw transaction hex"));
+    }
+
+    #[test]
+    fn test_invalid_request_keeps_readable_id() {
+        let err = JsonRpcRequest::parse(json!({ "jsonrpc": "2.0", "id": "q1" })).unwrap_err();
+        assert_eq!(err.id, json!("q1"));
+        assert_eq!(err.error.unwrap().code, INVALID_REQUEST_CODE);
+
+        let err = JsonRpcRequest::parse(json!({ "jsonrpc": "2.0", "method": "eth_chainId", "id": {"a": 1} })).unwrap_err();
+        assert_eq!(err.id, serde_json::Value::Null);
+        assert!(err.error.unwrap().message.contains("id must be"));
+    }
+
+    #[test]
+    fn test_blocks_carry_codes() {
+        let resp = JsonRpcResponse::blocked(json!(1), -32000, "ens", "PLIMSOLL ENS: young name".into());
+        let data = resp.error.unwrap().data.unwrap();
+        assert_eq!(data["code"], 4908);
+        assert_eq!(data["engine"], "ens");
+
+        let blocked = BlockedTx { engine: "physics".into(), reason: "loss".into(), ..BlockedTx::default() };
+        let receipt = JsonRpcResponse::plimsoll_synthetic_receipt(json!(2), "0xplimsoll01", &blocked);
+        let plimsoll = &receipt.result.unwrap()["plimsoll"];
+        assert_eq!(plimsoll["code"], 4902);
+        assert_eq!(plimsoll["category"], "PHYSICS");
+        assert_eq!(plimsoll["engine"], "physics");
+    }
+
+    #[test]
+    fn test_synthetic_revert_reasons() {
+        let expected = format!("0x{}", hex::encode(abi::encode_revert("PLIMSOLL_BLOCKED: loss")));
+
+        let revert = JsonRpcResponse::plimsoll_synthetic_revert(json!(1), "loss");
+        let error = revert.error.unwrap();
+        assert_eq!(error.code, EXECUTION_REVERTED_CODE);
+        assert_eq!(error.message, "execution reverted: PLIMSOLL_BLOCKED: loss");
+        assert_eq!(error.data, Some(json!(expected)));
+
+        let blocked = BlockedTx {
+            engine: "physics".into(),
+            reason: "loss".into(),
+            from: "0xa9e0000000000000000000000000000000000105".into(),
+            gas_used: 21_064,
+            block_number: Some(19_000_000),
+            ..BlockedTx::default()
+        };
+        let receipt = JsonRpcResponse::plimsoll_synthetic_receipt(json!(1), "0xplimsoll01", &blocked).result.unwrap();
+        assert_eq!(receipt["revertReason"], json!(expected));
+        assert_eq!(receipt["from"], "0xa9e0000000000000000000000000000000000105");
+        assert_eq!(receipt["to"], ZERO_ADDRESS);
+        assert_eq!(receipt["gasUsed"], "0x5248");
+        assert_eq!(receipt["blockNumber"], "0x121eac0");
+
+        let frame = JsonRpcResponse::plimsoll_synthetic_trace(json!(1), "loss", Some("callTracer")).result.unwrap();
+        assert_eq!(frame["output"], json!(expected));
+        assert_eq!(frame["error"], "execution reverted");
+        let logs = JsonRpcResponse::plimsoll_synthetic_trace(json!(1), "loss", None).result.unwrap();
+        assert_eq!(logs["failed"], true);
+        assert_eq!(logs["returnValue"], json!(expected.trim_start_matches("0x")));
+    }
 }
@@ -0,0 +1,411 @@
+//! Proxy policies synced from the agent's on-chain vault modules.
+//!
+//! `PlimsollVault` enforces its velocity, target whitelist and drawdown
+//! modules at execution time; keeping the proxy's own limits in step used
+//! to mean copying every parameter into the config by hand. With
+//! `vault_policy_sync`, each vault in `vault_addresses` is read through the
+//! upstream at startup and every `vault_policy_sync_secs`:
+//!
+//!   - `VelocityLimitModule` → `token_velocity_limits["native"]`,
+//!   - `DrawdownGuardModule` → `max_drawdown_pct`,
+//!   - `TargetWhitelistModule` → `vault_target_allowlist` (and
+//!     `target_allowlist` with `target_allowlist_from_vaults`),
+//!
+//! and the module addresses are added to `vault_modules`. Where the local
+//! config sets a limit too, the stricter of the two applies; a local
+//! allowlist takes precedence over the on-chain lists.
+//! Several vaults merge the same way (the whitelists are joined). A failed
+//! sync keeps the last synced policy.
+//!
+//! The synced policy is overlaid on every config the handle swaps in, so a
+//! reload keeps it.
+
+use crate::abi;
+use crate::config::{parse_list, Config, VelocityLimit};
+use crate::reload::SharedConfigHandle;
+use crate::simulator;
+use crate::types::CallFrame;
+use crate::velocity;
+use alloy_primitives::U256;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// `velocityModule()`
+const VELOCITY_MODULE: [u8; 4] = [0x95, 0x1b, 0xe1, 0x35];
+/// `whitelistModule()`
+const WHITELIST_MODULE: [u8; 4] = [0x8f, 0xea, 0x31, 0xb0];
+/// `drawdownModule()`
+const DRAWDOWN_MODULE: [u8; 4] = [0xdd, 0x4c, 0x17, 0xae];
+/// `maxPerHour()`
+const MAX_PER_HOUR: [u8; 4] = [0x33, 0x5c, 0x9d, 0x8c];
+/// `maxSingleTx()`
+const MAX_SINGLE_TX: [u8; 4] = [0x0c, 0xf9, 0x60, 0x09];
+/// `windowSeconds()`
+const WINDOW_SECONDS: [u8; 4] = [0x4c, 0xb5, 0x92, 0x9a];
+/// `maxDrawdownBps()`
+const MAX_DRAWDOWN_BPS: [u8; 4] = [0x56, 0x61, 0xd4, 0x61];
+/// `getWhitelistCount()`
+const WHITELIST_COUNT: [u8; 4] = [0x3e, 0xdf, 0xf2, 0x0f];
+/// `whitelistedList(uint256)`
+const WHITELISTED_LIST: [u8; 4] = [0x05, 0xc8, 0xd3, 0xeb];
+/// `whitelisted(address)`
+const WHITELISTED: [u8; 4] = [0xd9, 0x36, 0x54, 0x7e];
+
+/// Whitelist entries read per vault.
+const MAX_TARGETS: u64 = 256;
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// Limits read from the vaults' modules. `None` / 0 where no vault has
+/// the module.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VaultPolicy {
+    pub velocity: Option<VelocityLimit>,
+    pub max_drawdown_pct: f64,
+    pub targets: Option<Vec<String>>,
+    pub modules: Vec<String>,
+}
+
+lazy_static! {
+    /// The last policy synced.
+    static ref SYNCED: Mutex<Option<VaultPolicy>> = Mutex::new(None);
+}
+
+fn amount(limit: &str) -> U256 {
+    limit.parse().unwrap_or(U256::MAX)
+}
+
+/// The tighter of two velocity limits: the lower caps, the longer window.
+fn stricter(a: &VelocityLimit, b: &VelocityLimit) -> VelocityLimit {
+    let lower = |x: &str, y: &str| if amount(x) <= amount(y) { x.to_string() } else { y.to_string() };
+    let max_single_tx = match (a.max_single_tx.is_empty(), b.max_single_tx.is_empty()) {
+        (true, _) => b.max_single_tx.clone(),
+        (_, true) => a.max_single_tx.clone(),
+        _ => lower(&a.max_single_tx, &b.max_single_tx),
+    };
+    VelocityLimit {
+        max_per_window: lower(&a.max_per_window, &b.max_per_window),
+        max_single_tx,
+        window_secs: a.window_secs.max(b.window_secs),
+    }
+}
+
+/// The tighter of two drawdown limits (0 = none).
+fn stricter_pct(a: f64, b: f64) -> f64 {
+    match (a > 0.0, b > 0.0) {
+        (true, true) => a.min(b),
+        (true, false) => a,
+        _ => b,
+    }
+}
+
+impl VaultPolicy {
+    fn merge(&mut self, other: VaultPolicy) {
+        self.velocity = match (self.velocity.take(), other.velocity) {
+            (Some(a), Some(b)) => Some(stricter(&a, &b)),
+            (a, b) => a.or(b),
+        };
+        self.max_drawdown_pct = stricter_pct(self.max_drawdown_pct, other.max_drawdown_pct);
+        self.targets = match (self.targets.take(), other.targets) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b.into_iter().filter(|t| !a.contains(t)).collect::<Vec<_>>());
+                Some(a)
+            }
+            (a, b) => a.or(b),
+        };
+        self.modules.extend(other.modules.into_iter().filter(|m| !self.modules.contains(m)).collect::<Vec<_>>());
+    }
+
+    /// Overlay the policy on `config`.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(synced) = &self.velocity {
+            let native = config
+                .token_velocity_limits
+                .keys()
+                .find(|t| t.eq_ignore_ascii_case(velocity::NATIVE))
+                .cloned()
+                .unwrap_or_else(|| velocity::NATIVE.to_string());
+            let limit = match config.token_velocity_limits.get(&native) {
+                Some(local) => stricter(local, synced),
+                None => synced.clone(),
+            };
+            config.token_velocity_limits.insert(native, limit);
+        }
+        config.max_drawdown_pct = stricter_pct(config.max_drawdown_pct, self.max_drawdown_pct);
+        if let Some(targets) = self.targets.as_ref().filter(|_| config.vault_target_allowlist.trim().is_empty()) {
+            config.vault_target_allowlist = targets.join(",");
+        }
+        if let Some(targets) = self
+            .targets
+            .as_ref()
+            .filter(|_| config.target_allowlist_from_vaults && config.target_allowlist.trim().is_empty())
+        {
+            config.target_allowlist = targets.join(",");
+        }
+        let known = config.vault_modules.to_lowercase();
+        for module in self.modules.iter().filter(|m| !known.contains(m.as_str())) {
+            if !config.vault_modules.trim().is_empty() {
+                config.vault_modules.push(',');
+            }
+            config.vault_modules.push_str(module);
+        }
+    }
+}
+
+/// Overlay the last synced policy on `config`.
+pub fn overlay(config: &mut Config) {
+    if !config.vault_policy_sync {
+        return;
+    }
+    if let Some(policy) = SYNCED.lock().ok().and_then(|p| p.clone()) {
+        policy.apply(config);
+    }
+}
+
+async fn call_uint(rpc_url: &str, to: &str, data: &[u8]) -> Result<U256> {
+    abi::uint(&simulator::eth_call(rpc_url, to, data, "latest").await?, 0).map_err(anyhow::Error::msg)
+}
+
+async fn call_address(rpc_url: &str, to: &str, data: &[u8]) -> Result<String> {
+    abi::address(&simulator::eth_call(rpc_url, to, data, "latest").await?, 0).map_err(anyhow::Error::msg)
+}
+
+/// The module `vault` has installed behind `getter`, if any.
+async fn module(rpc_url: &str, vault: &str, getter: [u8; 4]) -> Result<Option<String>> {
+    let module = call_address(rpc_url, vault, &getter).await?;
+    Ok((module != ZERO_ADDRESS).then_some(module))
+}
+
+async fn whitelist(rpc_url: &str, module: &str) -> Result<Vec<String>> {
+    let count = call_uint(rpc_url, module, &WHITELIST_COUNT).await?;
+    let count = u64::try_from(count).unwrap_or(u64::MAX);
+    if count > MAX_TARGETS {
+        warn!(module, count, "Vault whitelist truncated to {} entries", MAX_TARGETS);
+    }
+    let mut targets = Vec::new();
+    for i in 0..count.min(MAX_TARGETS) {
+        let mut data = WHITELISTED_LIST.to_vec();
+        data.extend(abi::encode_uint(i));
+        let target = call_address(rpc_url, module, &data).await?;
+        // Removed targets stay in the list with the flag cleared.
+        let mut data = WHITELISTED.to_vec();
+        data.extend(abi::encode_address(&target).map_err(anyhow::Error::msg)?);
+        if call_uint(rpc_url, module, &data).await? != U256::ZERO && !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    Ok(targets)
+}
+
+/// Read the module parameters of one vault.
+pub async fn fetch(rpc_url: &str, vault: &str) -> Result<VaultPolicy> {
+    let mut policy = VaultPolicy::default();
+    if let Some(m) = module(rpc_url, vault, VELOCITY_MODULE).await? {
+        policy.velocity = Some(VelocityLimit {
+            max_per_window: call_uint(rpc_url, &m, &MAX_PER_HOUR).await?.to_string(),
+            max_single_tx: call_uint(rpc_url, &m, &MAX_SINGLE_TX).await?.to_string(),
+            window_secs: u64::try_from(call_uint(rpc_url, &m, &WINDOW_SECONDS).await?).unwrap_or(u64::MAX),
+        });
+        policy.modules.push(m);
+    }
+    if let Some(m) = module(rpc_url, vault, WHITELIST_MODULE).await? {
+        policy.targets = Some(whitelist(rpc_url, &m).await?);
+        policy.modules.push(m);
+    }
+    if let Some(m) = module(rpc_url, vault, DRAWDOWN_MODULE).await? {
+        let bps = call_uint(rpc_url, &m, &MAX_DRAWDOWN_BPS).await?;
+        // 10 000 bps and up allow a total loss: no limit. 0 bps allows
+        // none: the smallest limit.
+        policy.max_drawdown_pct = match u64::try_from(bps) {
+            Ok(bps) if bps < 10_000 => (bps as f64 / 100.0).max(0.01),
+            _ => 0.0,
+        };
+        policy.modules.push(m);
+    }
+    Ok(policy)
+}
+
+/// Read and merge the policies of every vault in `vault_addresses`.
+pub async fn sync(config: &Config) -> Result<VaultPolicy> {
+    let mut merged = VaultPolicy::default();
+    for vault in parse_list(&config.vault_addresses) {
+        let policy = fetch(&config.upstream_rpc_url, &vault)
+            .await
+            .with_context(|| format!("vault {vault}"))?;
+        merged.merge(policy);
+    }
+    Ok(merged)
+}
+
+fn first_unlisted<'a>(frame: &'a CallFrame, vaults: &[String], allowed: &[String]) -> Option<&'a CallFrame> {
+    let from = frame.from.to_lowercase();
+    let to = frame.to.to_lowercase();
+    if frame.call_type == "CALL" && vaults.contains(&from) && from != to && !allowed.contains(&to) {
+        return Some(frame);
+    }
+    frame.calls.iter().find_map(|call| first_unlisted(call, vaults, allowed))
+}
+
+/// Block a simulated transaction in which a vault calls a contract outside
+/// `vault_target_allowlist` (or its own modules) — the call the vault's
+/// whitelist module would revert.
+pub fn check_targets(config: &Config, trace: &CallFrame) -> Result<(), String> {
+    let mut allowed = parse_list(&config.vault_target_allowlist);
+    if allowed.is_empty() {
+        return Ok(());
+    }
+    allowed.extend(parse_list(&config.vault_modules));
+    let vaults = parse_list(&config.vault_addresses);
+    match first_unlisted(trace, &vaults, &allowed) {
+        Some(call) => Err(format!(
+            "PLIMSOLL WHITELIST: vault {} calls {}, which is not on its target whitelist",
+            call.from.to_lowercase(),
+            call.to.to_lowercase()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Spawn the task syncing vault policies at startup and every
+/// `vault_policy_sync_secs`. A changed policy is swapped in through the
+/// config handle.
+pub fn spawn_sync_task(config: SharedConfigHandle) {
+    tokio::spawn(async move {
+        loop {
+            let cfg = config.current();
+            if cfg.vault_policy_sync {
+                match sync(&cfg).await {
+                    Ok(policy) => {
+                        let changed = SYNCED.lock().map(|mut synced| {
+                            let changed = synced.as_ref() != Some(&policy);
+                            *synced = Some(policy);
+                            changed
+                        });
+                        if changed.unwrap_or(false) {
+                            let summary = config.resync();
+                            info!(applied = ?summary.applied, "Vault policies synced");
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "Vault policy sync failed — keeping the last synced policy"),
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(cfg.vault_policy_sync_secs.max(1))).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const TARGET: &str = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+
+    fn limit(max_per_window: &str, max_single_tx: &str, window_secs: u64) -> VelocityLimit {
+        VelocityLimit { max_per_window: max_per_window.into(), max_single_tx: max_single_tx.into(), window_secs }
+    }
+
+    fn synced() -> VaultPolicy {
+        VaultPolicy {
+            velocity: Some(limit("5000", "1000", 3600)),
+            max_drawdown_pct: 5.0,
+            targets: Some(vec![TARGET.into()]),
+            modules: vec!["0x1111111111111111111111111111111111111111".into()],
+        }
+    }
+
+    #[test]
+    fn test_policy_fills_unset_limits() {
+        let mut config = Config {
+            vault_policy_sync: true,
+            vault_addresses: "0x4444444444444444444444444444444444444444".into(),
+            ..Config::default()
+        };
+        synced().apply(&mut config);
+        assert_eq!(config.token_velocity_limits.get("native"), Some(&limit("5000", "1000", 3600)));
+        assert_eq!(config.max_drawdown_pct, 5.0);
+        assert_eq!(config.vault_target_allowlist, TARGET);
+        assert_eq!(config.target_allowlist, "");
+        assert_eq!(config.vault_modules, "0x1111111111111111111111111111111111111111");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_stricter_of_local_and_synced() {
+        let mut config = Config {
+            token_velocity_limits: BTreeMap::from([("NATIVE".to_string(), limit("3000", "", 600))]),
+            max_drawdown_pct: 10.0,
+            vault_target_allowlist: "0x2222222222222222222222222222222222222222".into(),
+            vault_modules: "0x1111111111111111111111111111111111111111".into(),
+            ..Config::default()
+        };
+        synced().apply(&mut config);
+        assert_eq!(config.token_velocity_limits.len(), 1);
+        assert_eq!(config.token_velocity_limits.get("NATIVE"), Some(&limit("3000", "1000", 3600)));
+        assert_eq!(config.max_drawdown_pct, 5.0);
+        assert_eq!(config.vault_target_allowlist, "0x2222222222222222222222222222222222222222");
+        assert_eq!(config.vault_modules, "0x1111111111111111111111111111111111111111");
+    }
+
+    #[test]
+    fn test_vault_calls_checked_against_whitelist() {
+        const VAULT: &str = "0x4444444444444444444444444444444444444444";
+        let frame = |call_type: &str, from: &str, to: &str, calls: Vec<CallFrame>| CallFrame {
+            call_type: call_type.into(),
+            from: from.into(),
+            to: to.into(),
+            input: "0x".into(),
+            calls,
+            ..CallFrame::default()
+        };
+        let execute = |target: &str| {
+            frame(
+                "CALL",
+                "0xa9e0000000000000000000000000000000000001",
+                VAULT,
+                vec![
+                    frame("CALL", VAULT, "0x1111111111111111111111111111111111111111", vec![]),
+                    frame("CALL", VAULT, target, vec![frame("CALL", target, "0x6666666666666666666666666666666666666666", vec![])]),
+                ],
+            )
+        };
+        let mut config = Config { vault_addresses: VAULT.into(), ..Config::default() };
+        assert!(check_targets(&config, &execute("0x6666666666666666666666666666666666666666")).is_ok());
+        synced().apply(&mut config);
+        assert!(check_targets(&config, &execute(&TARGET.to_uppercase().replace("0X", "0x"))).is_ok());
+        let err = check_targets(&config, &execute("0x6666666666666666666666666666666666666666")).unwrap_err();
+        assert!(err.contains("vault 0x4444444444444444444444444444444444444444 calls 0x6666666666666666666666666666666666666666"));
+    }
+
+    #[test]
+    fn test_target_allowlist_from_vaults() {
+        let mut config = Config { target_allowlist_from_vaults: true, ..Config::default() };
+        synced().apply(&mut config);
+        assert_eq!(config.target_allowlist, TARGET);
+        let mut config = Config {
+            target_allowlist_from_vaults: true,
+            target_allowlist: "0x2222222222222222222222222222222222222222".into(),
+            ..Config::default()
+        };
+        synced().apply(&mut config);
+        assert_eq!(config.target_allowlist, "0x2222222222222222222222222222222222222222");
+    }
+
+    #[test]
+    fn test_vaults_merge() {
+        let mut merged = VaultPolicy::default();
+        merged.merge(synced());
+        merged.merge(VaultPolicy {
+            velocity: Some(limit("8000", "500", 7200)),
+            max_drawdown_pct: 0.0,
+            targets: Some(vec![TARGET.into(), "0x3333333333333333333333333333333333333333".into()]),
+            modules: vec![],
+        });
+        assert_eq!(merged.velocity, Some(limit("5000", "500", 7200)));
+        assert_eq!(merged.max_drawdown_pct, 5.0);
+        assert_eq!(merged.targets.as_ref().map(Vec::len), Some(2));
+    }
+}
@@ -0,0 +1,299 @@
+//! Per-token velocity limits.
+//!
+//! The proxy-side mirror of `VelocityLimitModule.sol`: per agent and per
+//! token, outflows are summed over a rolling window and a send is blocked
+//! when its simulated outflow would exceed the window's cap (or the single
+//! transaction cap). The vault module is the last line; this one holds
+//! even when the module is misconfigured, absent, or only caps ETH.
+//!
+//! A send's outflow is read from its simulation: the native balance drop
+//! (value plus any fees charged to it) under `native`, and each ERC-20's
+//! net amount sent out of the portfolio under the token address. The
+//! check and the count happen under one lock, so concurrent sends can't
+//! both slip under a cap; the count is given back unless the send is
+//! forwarded, and a speed-up is not counted again. Only outflows inside
+//! the window are kept; with `MAX_RECORDS` of them a token fails closed.
+//! Counters are per chain (see `chains`).
+
+use crate::chains;
+use crate::config::{Config, VelocityLimit};
+use crate::types::{SimulationResult, TokenStandard};
+use alloy_primitives::U256;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `token_velocity_limits` key of the chain's native asset.
+pub const NATIVE: &str = "native";
+
+/// Outflows remembered per agent and token within the window.
+const MAX_RECORDS: usize = 1024;
+
+/// (unix secs, amount, reservation id) of one agent's outflows of one
+/// token, in the order checked.
+type Outflows = VecDeque<(u64, U256, u64)>;
+
+/// Id of the next outflow counted.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+lazy_static! {
+    /// (agent, token) → its outflows.
+    static ref OUTFLOWS: Mutex<HashMap<(String, String), Outflows>> = Mutex::new(HashMap::new());
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+fn agent_key(agent: &str) -> String {
+    chains::scope_key(chains::current(), &agent.to_lowercase())
+}
+
+fn amount(limit: &str) -> U256 {
+    limit.parse().unwrap_or(U256::MAX)
+}
+
+/// What a simulated send moves out of the agent's portfolio, per token
+/// (`NATIVE` or a lowercase token address).
+pub fn outflows(sim_result: &SimulationResult) -> Vec<(String, U256)> {
+    let mut out = Vec::new();
+    let native = sim_result.balance_before.saturating_sub(sim_result.balance_after);
+    if native > 0 {
+        out.push((NATIVE.to_string(), U256::from(native)));
+    }
+    for delta in &sim_result.asset_deltas {
+        if delta.standard == TokenStandard::Erc20 && delta.sent > delta.received {
+            out.push((delta.token.to_lowercase(), delta.sent - delta.received));
+        }
+    }
+    out
+}
+
+fn limit_for<'a>(config: &'a Config, token: &str) -> Option<&'a VelocityLimit> {
+    config
+        .token_velocity_limits
+        .iter()
+        .find(|(t, _)| t.eq_ignore_ascii_case(token))
+        .map(|(_, limit)| limit)
+}
+
+/// The agent's outflow of `token` within the last `window_secs`, as of `now`.
+fn window_total(agent: &str, token: &str, window_secs: u64, now: u64) -> U256 {
+    let cutoff = now.saturating_sub(window_secs);
+    let Ok(mut store) = OUTFLOWS.lock() else {
+        return U256::ZERO;
+    };
+    let Some(records) = store.get_mut(&(agent_key(agent), token.to_string())) else {
+        return U256::ZERO;
+    };
+    records.retain(|&(at, _, _)| at >= cutoff);
+    records.iter().fold(U256::ZERO, |sum, &(_, amount, _)| sum.saturating_add(amount))
+}
+
+/// A send's outflows, counted from the moment it was checked so a
+/// concurrent send sees them. Given back when dropped, unless the send was
+/// forwarded and the reservation `commit`ted.
+#[must_use]
+#[derive(Debug, Default)]
+pub struct Reservation {
+    entries: Vec<((String, String), u64)>,
+}
+
+impl Reservation {
+    /// Keep the outflows counted: the send was forwarded.
+    pub fn commit(mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if self.entries.is_empty() {
+            return;
+        }
+        if let Ok(mut store) = OUTFLOWS.lock() {
+            for (key, id) in &self.entries {
+                if let Some(records) = store.get_mut(key) {
+                    records.retain(|&(_, _, record)| record != *id);
+                }
+            }
+        }
+    }
+}
+
+fn reserve_at(config: &Config, agent: &str, outflows: &[(String, U256)], now: u64) -> (Reservation, Result<(), String>) {
+    let mut reservation = Reservation::default();
+    let Ok(mut store) = OUTFLOWS.lock() else {
+        return (reservation, Err("PLIMSOLL VELOCITY: outflow counters unavailable — failing closed".into()));
+    };
+    let mut verdict = Ok(());
+    for (token, out) in outflows {
+        let Some(limit) = limit_for(config, token) else {
+            continue;
+        };
+        let key = (agent_key(agent), token.clone());
+        let records = store.entry(key.clone()).or_default();
+        let cutoff = now.saturating_sub(limit.window_secs);
+        records.retain(|&(at, _, _)| at >= cutoff);
+        let spent = records.iter().fold(U256::ZERO, |sum, &(_, amount, _)| sum.saturating_add(amount));
+        if verdict.is_ok() {
+            if !limit.max_single_tx.is_empty() && *out > amount(&limit.max_single_tx) {
+                verdict = Err(format!(
+                    "PLIMSOLL VELOCITY: {} outflow {} exceeds the single transaction cap {}",
+                    token, out, limit.max_single_tx
+                ));
+            } else if spent.saturating_add(*out) > amount(&limit.max_per_window) {
+                verdict = Err(format!(
+                    "PLIMSOLL VELOCITY: {} outflow {} on top of {} in the last {}s exceeds the cap {}",
+                    token, out, spent, limit.window_secs, limit.max_per_window
+                ));
+            }
+        }
+        // Only outflows still in the window are kept: never drop one that
+        // counts — with no room left, fail closed.
+        if records.len() >= MAX_RECORDS {
+            if verdict.is_ok() {
+                verdict = Err(format!(
+                    "PLIMSOLL VELOCITY: {} outflows of {} in the last {}s — failing closed",
+                    records.len(), token, limit.window_secs
+                ));
+            }
+            continue;
+        }
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        records.push_back((now, *out, id));
+        reservation.entries.push((key, id));
+    }
+    (reservation, verdict)
+}
+
+/// Check a send's outflows against the agent's limits and count them, in
+/// one step. The verdict is `Err` when a limit would be exceeded; the
+/// reservation counts the outflows until dropped or committed either way,
+/// so a send forwarded despite the verdict (shadow, warn) still counts.
+pub fn reserve(config: &Config, agent: &str, outflows: &[(String, U256)]) -> (Reservation, Result<(), String>) {
+    reserve_at(config, agent, outflows, now())
+}
+
+/// The agent's outflow of `token` within the last `window_secs`.
+pub fn spent(agent: &str, token: &str, window_secs: u64) -> U256 {
+    window_total(agent, token, window_secs, now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AssetDelta;
+    use std::collections::BTreeMap;
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+    fn config() -> Config {
+        let limit = |max_per_window: &str, max_single_tx: &str| VelocityLimit {
+            max_per_window: max_per_window.into(),
+            max_single_tx: max_single_tx.into(),
+            window_secs: 3600,
+        };
+        Config {
+            token_velocity_limits: BTreeMap::from([
+                (NATIVE.to_string(), limit("1000", "")),
+                (USDC.to_uppercase().replace("0X", "0x"), limit("5000", "3000")),
+            ]),
+            ..Config::default()
+        }
+    }
+
+    fn usdc(amount: u64) -> Vec<(String, U256)> {
+        vec![(USDC.to_string(), U256::from(amount))]
+    }
+
+    #[test]
+    fn test_outflows_from_simulation() {
+        let sim = SimulationResult {
+            success: true,
+            gas_used: 21_000,
+            balance_before: 1_000,
+            balance_after: 400,
+            approval_changes: vec![],
+            loss_pct: 60.0,
+            error: None,
+            simulated_block: 1,
+            target_codehash: String::new(),
+            non_deterministic: false,
+            impl_slot_value: String::new(),
+            implementation_codehash: String::new(),
+            call_trace: None,
+            asset_deltas: vec![
+                AssetDelta {
+                    standard: TokenStandard::Erc20,
+                    token: USDC.to_uppercase().replace("0X", "0x"),
+                    token_id: None,
+                    sent: U256::from(700u64),
+                    received: U256::from(200u64),
+                },
+                AssetDelta {
+                    standard: TokenStandard::Erc721,
+                    token: "0x2222222222222222222222222222222222222222".into(),
+                    token_id: Some(U256::from(1u64)),
+                    sent: U256::from(1u64),
+                    received: U256::ZERO,
+                },
+            ],
+            approval_deltas: vec![],
+            storage_changes: vec![],
+        };
+        assert_eq!(
+            outflows(&sim),
+            vec![(NATIVE.to_string(), U256::from(600u64)), (USDC.to_string(), U256::from(500u64))]
+        );
+    }
+
+    #[test]
+    fn test_window_cap_enforced_and_expires() {
+        let config = config();
+        let agent = "0xa9e00000000000000000000000000000000000b1";
+        let (first, verdict) = reserve_at(&config, agent, &usdc(3000), 10_000);
+        assert!(verdict.is_ok());
+        first.commit();
+        let (_, verdict) = reserve_at(&config, agent, &usdc(2500), 10_600);
+        assert!(verdict.unwrap_err().contains("outflow 2500 on top of 3000 in the last 3600s exceeds the cap 5000"));
+        let (second, verdict) = reserve_at(&config, agent, &usdc(2000), 10_600);
+        assert!(verdict.is_ok());
+        // Counted while in flight: a concurrent send sees it.
+        let (_, verdict) = reserve_at(&config, agent, &usdc(1), 10_600);
+        assert!(verdict.unwrap_err().contains("on top of 5000"));
+        // Not forwarded: given back.
+        drop(second);
+        assert_eq!(window_total(agent, USDC, 3600, 10_600), U256::from(3000u64));
+        // The first outflow leaves the window.
+        let (_, verdict) = reserve_at(&config, agent, &usdc(2500), 13_601);
+        assert!(verdict.is_ok());
+    }
+
+    #[test]
+    fn test_full_window_fails_closed() {
+        let config = config();
+        let agent = "0xa9e00000000000000000000000000000000000b3";
+        for i in 0..MAX_RECORDS as u64 {
+            reserve_at(&config, agent, &usdc(1), 20_000 + i % 100).0.commit();
+        }
+        let (_, verdict) = reserve_at(&config, agent, &usdc(1), 20_100);
+        assert!(verdict.unwrap_err().contains("failing closed"));
+        // Expired outflows make room again.
+        let (_, verdict) = reserve_at(&config, agent, &usdc(1), 30_000);
+        assert!(verdict.is_ok());
+    }
+
+    #[test]
+    fn test_single_tx_cap_and_unlisted_tokens() {
+        let config = config();
+        let agent = "0xa9e00000000000000000000000000000000000b2";
+        assert!(reserve(&config, agent, &usdc(3001)).1.unwrap_err().contains("single transaction cap 3000"));
+        let native = vec![(NATIVE.to_string(), U256::from(1001u64))];
+        assert!(reserve(&config, agent, &native).1.unwrap_err().contains("native outflow 1001"));
+        let dai = vec![("0x6b175474e89094c44da98b954eedeac495271d0f".to_string(), U256::MAX)];
+        assert!(reserve(&config, agent, &dai).1.is_ok());
+    }
+}
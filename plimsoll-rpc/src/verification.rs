@@ -0,0 +1,312 @@
+//! Contract verification and deployment-age checks.
+//!
+//! Drainers are deployed minutes before use and never verified; real
+//! protocols are verified and have been on-chain for months. Neither fact
+//! proves anything alone, but together they make a cheap, strong policy:
+//! *no value transfers to unverified contracts younger than N hours*.
+//!
+//! Verification is looked up on Sourcify (`abi_sourcify_url`, full or
+//! partial match) and then Etherscan (`etherscan_api_key`). The deployment
+//! time comes from Etherscan's contract-creation record when available,
+//! otherwise from a binary search for the first block with code (needs an
+//! archive upstream). Results that can't change — deployment times and
+//! positive verifications — are cached.
+//!
+//! Behind a resolved upgradeable proxy (see `proxy`) the implementation —
+//! the code that actually runs — is the one checked. Each remote call is
+//! bounded by `LOOKUP_TIMEOUT` and the whole lookup by
+//! `PROVENANCE_TIMEOUT`; a lookup that runs out fails like any other.
+
+use crate::config::Config;
+use crate::simulator;
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Remote lookup timeout — this runs on the send path.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Budget of one whole provenance lookup (the deployment block search
+/// alone is a few dozen calls).
+const PROVENANCE_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    /// address → deployment timestamp (unix seconds).
+    static ref DEPLOYED_AT: RwLock<HashMap<String, u64>> = RwLock::new(HashMap::new());
+    /// Addresses known to be verified.
+    static ref VERIFIED: RwLock<HashMap<String, Verifier>> = RwLock::new(HashMap::new());
+}
+
+/// Where a contract's source is verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verifier {
+    Sourcify,
+    Etherscan,
+}
+
+impl Verifier {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Sourcify => "Sourcify",
+            Self::Etherscan => "Etherscan",
+        }
+    }
+}
+
+/// What we know about a contract's provenance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    /// The contract whose code runs: the implementation behind a proxy.
+    pub contract: String,
+    /// The proxy `contract` was resolved behind, if any.
+    pub proxy: Option<String>,
+    /// `None` = unverified on every configured source.
+    pub verified_on: Option<Verifier>,
+    /// Seconds since deployment; `None` if it couldn't be determined.
+    pub age_secs: Option<u64>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+async fn rpc(rpc_url: &str, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    });
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&payload)
+        .timeout(LOOKUP_TIMEOUT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to call {method}"))?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {method} response"))?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("{method} failed: {err}");
+    }
+    Ok(body["result"].clone())
+}
+
+fn parse_quantity(v: &serde_json::Value) -> Option<u64> {
+    u64::from_str_radix(v.as_str()?.trim_start_matches("0x"), 16).ok()
+}
+
+async fn block_timestamp(rpc_url: &str, block: u64) -> Result<u64> {
+    let header = rpc(rpc_url, "eth_getBlockByNumber", serde_json::json!([format!("0x{block:x}"), false])).await?;
+    parse_quantity(&header["timestamp"]).context("block has no timestamp")
+}
+
+async fn has_code_at(rpc_url: &str, address: &str, block: u64) -> Result<bool> {
+    let code = rpc(rpc_url, "eth_getCode", serde_json::json!([address, format!("0x{block:x}")])).await?;
+    Ok(code.as_str().is_some_and(|c| c.len() > 2))
+}
+
+/// First block at which `address` has code (binary search over history).
+async fn deployment_block(rpc_url: &str, address: &str) -> Result<Option<u64>> {
+    let latest = parse_quantity(&rpc(rpc_url, "eth_blockNumber", serde_json::json!([])).await?)
+        .context("invalid eth_blockNumber")?;
+    if !has_code_at(rpc_url, address, latest).await? {
+        return Ok(None);
+    }
+    let (mut lo, mut hi) = (0, latest);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if has_code_at(rpc_url, address, mid).await? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Ok(Some(lo))
+}
+
+fn etherscan(config: &Config, params: &[(&str, &str)]) -> reqwest::RequestBuilder {
+    let chain_id = config.chain_id.to_string();
+    reqwest::Client::new()
+        .get(&config.etherscan_api_url)
+        .query(&[("chainid", chain_id.as_str()), ("apikey", config.etherscan_api_key.as_str())])
+        .query(params)
+        .timeout(LOOKUP_TIMEOUT)
+}
+
+/// Deployment block from Etherscan's contract-creation record.
+async fn etherscan_creation_block(config: &Config, address: &str) -> Result<Option<u64>> {
+    let body: serde_json::Value = etherscan(
+        config,
+        &[("module", "contract"), ("action", "getcontractcreation"), ("contractaddresses", address)],
+    )
+    .send()
+    .await?
+    .json()
+    .await?;
+    let Some(tx_hash) = body["result"][0]["txHash"].as_str() else {
+        return Ok(None);
+    };
+    let tx = rpc(&config.upstream_rpc_url, "eth_getTransactionByHash", serde_json::json!([tx_hash])).await?;
+    Ok(parse_quantity(&tx["blockNumber"]))
+}
+
+/// Unix timestamp at which `address` was deployed.
+async fn deployed_at(config: &Config, address: &str) -> Result<Option<u64>> {
+    if let Some(ts) = DEPLOYED_AT.read().ok().and_then(|m| m.get(address).copied()) {
+        return Ok(Some(ts));
+    }
+    let mut block = None;
+    if !config.etherscan_api_key.is_empty() {
+        block = etherscan_creation_block(config, address).await.unwrap_or_else(|e| {
+            tracing::debug!(error = %e, contract = address, "Etherscan creation lookup failed");
+            None
+        });
+    }
+    if block.is_none() {
+        block = deployment_block(&config.upstream_rpc_url, address).await?;
+    }
+    let Some(block) = block else {
+        return Ok(None);
+    };
+    let ts = block_timestamp(&config.upstream_rpc_url, block).await?;
+    if let Ok(mut cache) = DEPLOYED_AT.write() {
+        cache.insert(address.to_string(), ts);
+    }
+    Ok(Some(ts))
+}
+
+async fn sourcify_verified(config: &Config, address: &str) -> Result<bool> {
+    let url = format!(
+        "{}/v2/contract/{}/{}",
+        config.abi_sourcify_url.trim_end_matches('/'),
+        config.chain_id,
+        address
+    );
+    let resp = reqwest::Client::new().get(url).timeout(LOOKUP_TIMEOUT).send().await?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(false);
+    }
+    let body: serde_json::Value = resp.error_for_status()?.json().await?;
+    Ok(body["match"].as_str().is_some())
+}
+
+async fn etherscan_verified(config: &Config, address: &str) -> Result<bool> {
+    let body: serde_json::Value = etherscan(
+        config,
+        &[("module", "contract"), ("action", "getsourcecode"), ("address", address)],
+    )
+    .send()
+    .await?
+    .json()
+    .await?;
+    Ok(body["result"][0]["SourceCode"].as_str().is_some_and(|s| !s.is_empty()))
+}
+
+async fn verified_on(config: &Config, address: &str) -> Option<Verifier> {
+    if let Some(v) = VERIFIED.read().ok().and_then(|m| m.get(address).copied()) {
+        return Some(v);
+    }
+    let mut found = None;
+    if !config.abi_sourcify_url.is_empty() && sourcify_verified(config, address).await.unwrap_or(false) {
+        found = Some(Verifier::Sourcify);
+    } else if !config.etherscan_api_key.is_empty()
+        && etherscan_verified(config, address).await.unwrap_or(false)
+    {
+        found = Some(Verifier::Etherscan);
+    }
+    if let Some(v) = found {
+        if let Ok(mut cache) = VERIFIED.write() {
+            cache.insert(address.to_string(), v);
+        }
+    }
+    found
+}
+
+/// Look up the verification status and age of `contract` — or, when it
+/// is a proxy resolved to `implementation`, of the implementation.
+/// `Ok(None)` if it has no code (an EOA).
+pub async fn provenance(config: &Config, contract: &str, implementation: Option<&str>) -> Result<Option<Provenance>> {
+    let (contract, proxy) = match implementation {
+        Some(implementation) => (implementation.to_lowercase(), Some(contract.to_lowercase())),
+        None => (contract.to_lowercase(), None),
+    };
+    let lookup = async {
+        if simulator::fetch_code(&config.upstream_rpc_url, &contract).await?.is_empty() {
+            return Ok(None);
+        }
+        let verified_on = verified_on(config, &contract).await;
+        let age_secs = deployed_at(config, &contract)
+            .await?
+            .map(|ts| now_secs().saturating_sub(ts));
+        Ok(Some(Provenance { contract: contract.clone(), proxy, verified_on, age_secs }))
+    };
+    tokio::time::timeout(PROVENANCE_TIMEOUT, lookup)
+        .await
+        .with_context(|| format!("provenance lookup of {contract} timed out after {PROVENANCE_TIMEOUT:?}"))?
+}
+
+/// Block value transfers to unverified contracts younger than
+/// `unverified_min_age_hours`. An unknown age counts as young.
+pub fn check(config: &Config, value: u128, p: &Provenance) -> Result<(), String> {
+    if value == 0 || p.verified_on.is_some() || config.unverified_min_age_hours == 0 {
+        return Ok(());
+    }
+    let min_age = config.unverified_min_age_hours.saturating_mul(3600);
+    if p.age_secs.is_some_and(|age| age >= min_age) {
+        return Ok(());
+    }
+    let age = match p.age_secs {
+        Some(secs) => format!("deployed {:.1}h ago", secs as f64 / 3600.0),
+        None => "deployment time unknown".to_string(),
+    };
+    let behind = p.proxy.as_ref().map(|proxy| format!(" behind proxy {proxy}")).unwrap_or_default();
+    Err(format!(
+        "PLIMSOLL VERIFICATION: value transfer to unverified contract {}{} ({}, minimum {}h)",
+        p.contract, behind, age, config.unverified_min_age_hours
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(verified_on: Option<Verifier>, age_secs: Option<u64>) -> Provenance {
+        Provenance {
+            contract: "0x1111111111111111111111111111111111111111".into(),
+            proxy: None,
+            verified_on,
+            age_secs,
+        }
+    }
+
+    #[test]
+    fn test_young_unverified_contract_blocked() {
+        let config = Config::default();
+        let err = check(&config, 1, &record(None, Some(3600))).unwrap_err();
+        assert!(err.contains("deployed 1.0h ago"));
+        assert!(check(&config, 1, &record(None, None)).is_err());
+        let behind = Provenance { proxy: Some("0x2222222222222222222222222222222222222222".into()), ..record(None, None) };
+        assert!(check(&config, 1, &behind).unwrap_err().contains("behind proxy 0x2222"));
+    }
+
+    #[test]
+    fn test_verified_old_or_valueless_allowed() {
+        let config = Config::default();
+        assert!(check(&config, 1, &record(Some(Verifier::Sourcify), Some(60))).is_ok());
+        assert!(check(&config, 1, &record(None, Some(49 * 3600))).is_ok());
+        assert!(check(&config, 0, &record(None, Some(60))).is_ok());
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_quantity(&serde_json::json!("0x10")), Some(16));
+        assert_eq!(parse_quantity(&serde_json::json!(null)), None);
+    }
+}